@@ -3,13 +3,777 @@
 //! 定义应用程序的配置结构体和加载逻辑。
 //!
 //! ## 配置项说明
-//! - 服务监听地址和端口（8848）
+//! - 服务监听地址和端口（默认 8848，可按服务拆分到多个地址）
 //! - 文件路径（题库、密钥、转储目录）
+//!
+//! ## 配置文件与热重载
+//! 设置 `LIVE_SERVER_CONFIG_FILE` 后，从该文件读取 `KEY=VALUE` 形式的配置（键名与环境变量相同，
+//! `#` 开头为注释），文件中的值优先于环境变量。
+//! 收到 SIGHUP 或调用 `POST /admin/reload` 时重新读取配置文件，
+//! 只应用可热更新的配置项，其余变更需重启服务才能生效。
+//! 修改配置文件前可通过 `POST /admin/config/preview` 提交候选内容，预览校验结果和将发生的变更。
 
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
-use std::net::IpAddr;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 
+use chrono::NaiveTime;
+use parking_lot::RwLock;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use url::Url;
+
+use crate::logging;
+use crate::state::stream_policy;
+
+/// 配置文件中的配置项（优先于环境变量）
+static FILE_OVERLAY: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+
+/// 观众人数对非主播的可见性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudienceVisibility {
+    /// 显示精确人数（默认）
+    Exact,
+    /// 显示分档人数（如 "10+"、"50+"）
+    Bucketed,
+    /// 不显示人数
+    Hidden,
+}
+
+impl AudienceVisibility {
+    /// 从字符串解析可见性
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "exact" => Some(Self::Exact),
+            "bucketed" => Some(Self::Bucketed),
+            "hidden" => Some(Self::Hidden),
+            _ => None,
+        }
+    }
+}
+
+/// 监听地址上提供的服务
+///
+/// 将 SRS 回调限制在回环地址上，可避免公网上任何人伪造 `on_publish` 等回调
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Services {
+    /// SRS 回调（`/`）
+    pub callback: bool,
+    /// 观众接口（`/api`、`/chat`、`/streaming_info`、`/events`）
+    pub api: bool,
+    /// 管理接口（`/admin`）
+    pub admin: bool,
+}
+
+impl Services {
+    /// 全部服务
+    pub const ALL: Self = Self {
+        callback: true,
+        api: true,
+        admin: true,
+    };
+
+    /// 从 `+` 分隔的服务名解析，如 `api+admin`
+    ///
+    /// ### 返回值
+    /// 含有未知服务名或未指定任何服务时返回 `None`
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut services = Self {
+            callback: false,
+            api: false,
+            admin: false,
+        };
+        for name in s.split('+').map(str::trim) {
+            match name {
+                "callback" => services.callback = true,
+                "api" => services.api = true,
+                "admin" => services.admin = true,
+                "all" => services = Self::ALL,
+                _ => return None,
+            }
+        }
+        (services != Self { callback: false, api: false, admin: false }).then_some(services)
+    }
+
+    /// 服务名列表（用于日志）
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.callback, "callback"),
+            (self.api, "api"),
+            (self.admin, "admin"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect()
+    }
+}
+
+/// 一个监听地址及其提供的服务
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Listen {
+    /// 监听地址
+    pub addr: SocketAddr,
+    /// 提供的服务
+    pub services: Services,
+}
+
+impl Listen {
+    /// 解析 `LIVE_SERVER_LISTEN`
+    ///
+    /// 格式为逗号分隔的 `地址[=服务+服务]`，省略服务时提供全部服务，
+    /// 如 `127.0.0.1:8848=callback,0.0.0.0:8080=api+admin`
+    ///
+    /// ### 返回值
+    /// 无法解析的条目被忽略并输出警告
+    fn parse_list(s: &str) -> Vec<Self> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let (addr, services) = match entry.split_once('=') {
+                    Some((addr, services)) => (addr.trim(), Services::parse(services)),
+                    None => (entry, Some(Services::ALL)),
+                };
+                match (addr.parse(), services) {
+                    (Ok(addr), Some(services)) => Some(Self { addr, services }),
+                    _ => {
+                        tracing::warn!("LIVE_SERVER_LISTEN 中的条目 {} 无法解析，已忽略", entry);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// IP 地址段（单个地址或 CIDR）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    /// 网络地址
+    network: IpAddr,
+    /// 前缀长度
+    prefix: u8,
+}
+
+impl IpRange {
+    /// 解析 `10.0.0.0/8`、`::1` 形式的地址段，单个地址视为全长前缀
+    pub fn parse(s: &str) -> Option<Self> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr.trim().parse::<IpAddr>().ok()?, Some(prefix.trim().parse::<u8>().ok()?)),
+            None => (s.trim().parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { network: addr, prefix })
+    }
+
+    /// 地址是否在该地址段内（IPv4 映射的 IPv6 地址按 IPv4 比较）
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 观看时段（服务器本地时间，结束时间早于开始时间表示跨越午夜）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewingWindow {
+    /// 开始时间
+    pub start: NaiveTime,
+    /// 结束时间（不含）
+    pub end: NaiveTime,
+}
+
+impl ViewingWindow {
+    /// 解析 `19:00-02:00` 形式的时段
+    pub fn parse(s: &str) -> Option<Self> {
+        let (start, end) = s.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        Some(Self { start, end })
+    }
+
+    /// 时刻是否在时段内（开始与结束相同表示全天）
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start == self.end || (self.start <= time && time < self.end)
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// 网段题目分组策略
+///
+/// 同一网段的观众在同一轮换周期内只会抽到同一卡池子集中的题目，
+/// 降低同网段用户互相传答案的收益
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CohortPolicy {
+    /// IPv4 网段前缀长度
+    pub prefix_v4: u8,
+    /// IPv6 网段前缀长度
+    pub prefix_v6: u8,
+    /// 子集轮换周期（秒）
+    pub rotation_secs: i64,
+    /// 每个子集包含的卡池数量
+    pub subset_size: usize,
+}
+
+/// 后台任务的轮询间隔
+///
+/// 超出合理范围的取值会被截断到边界并输出警告
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Intervals {
+    /// 过期客户端/主播记录的清理间隔（秒）
+    pub cleanup_secs: u64,
+    /// 从 SRS API 获取观众人数的间隔（秒）
+    pub srs_poll_secs: u64,
+    /// SRS API 连续失败时的最大退避间隔（秒）
+    pub srs_poll_max_backoff_secs: u64,
+    /// 远程题库刷新间隔（秒）
+    pub banner_db_refresh_secs: u64,
+    /// SRS 回调看门狗的检查间隔（秒，0 表示不启用）
+    pub srs_watchdog_secs: u64,
+}
+
+impl Intervals {
+    /// 从环境变量读取轮询间隔并校验范围
+    fn from_env() -> Self {
+        let srs_poll_secs = env_bounded("LIVE_SERVER_SRS_POLL_INTERVAL", 5, 1, 600);
+        Self {
+            cleanup_secs: env_bounded("LIVE_SERVER_CLEANUP_INTERVAL", 10, 1, 300),
+            srs_poll_secs,
+            srs_poll_max_backoff_secs: env_bounded(
+                "LIVE_SERVER_SRS_POLL_MAX_BACKOFF",
+                300.max(srs_poll_secs),
+                srs_poll_secs,
+                3600,
+            ),
+            banner_db_refresh_secs: env_bounded("LIVE_SERVER_BANNER_DB_REFRESH", 3600, 60, 86400),
+            srs_watchdog_secs: env_bounded("LIVE_SERVER_SRS_WATCHDOG_INTERVAL", 30, 0, 3600),
+        }
+    }
+}
+
+/// 客户端轮询节奏建议
+///
+/// 聊天和状态查询的响应中附带 `poll_interval_ms`，负载升高或直播暂停时建议更长的间隔
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    /// 正常情况下建议的轮询间隔（毫秒）
+    pub base_ms: u64,
+    /// 建议轮询间隔的上限（毫秒）
+    pub max_ms: u64,
+    /// 每秒轮询请求数超过此值时开始放大间隔（0 表示不按负载调整）
+    pub load_threshold: u64,
+}
+
+impl PollPolicy {
+    /// 从环境变量读取轮询节奏配置
+    fn from_env() -> Self {
+        let base_ms = env_bounded("LIVE_SERVER_POLL_INTERVAL_MS", 1000, 100, 60_000);
+        Self {
+            base_ms,
+            max_ms: env_bounded("LIVE_SERVER_POLL_MAX_INTERVAL_MS", 10_000, base_ms, 300_000),
+            load_threshold: env_parse("LIVE_SERVER_POLL_LOAD_THRESHOLD").unwrap_or(200),
+        }
+    }
+}
+
+/// MQTT 状态发布目标
+///
+/// 向家庭自动化系统发布直播状态、观众人数和聊天速率，用于驱动"直播中"指示灯等
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttTarget {
+    /// Broker 主机
+    pub host: String,
+    /// Broker 端口
+    pub port: u16,
+    /// 主题前缀（发布到 `<前缀>/state` 等子主题）
+    pub topic: String,
+    /// MQTT 客户端 ID
+    pub client_id: String,
+    /// 用户名（`None` 表示匿名连接）
+    pub username: Option<String>,
+    /// 观众人数和聊天速率的发布间隔（秒）
+    pub interval_secs: u64,
+}
+
+impl MqttTarget {
+    /// 从环境变量读取 MQTT 配置
+    ///
+    /// ### 返回值
+    /// 未设置 `LIVE_SERVER_MQTT_BROKER` 或地址无法解析时返回 `None`
+    fn from_env() -> Option<Self> {
+        let broker = var("LIVE_SERVER_MQTT_BROKER").ok()?;
+        let addr = broker.trim().trim_start_matches("mqtt://").trim_end_matches('/');
+        let (host, port) = match addr.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => {
+                    tracing::warn!("LIVE_SERVER_MQTT_BROKER 的端口 {} 无法解析，已禁用 MQTT 发布", port);
+                    return None;
+                }
+            },
+            None => (addr, 1883),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            host: host.to_string(),
+            port,
+            topic: var("LIVE_SERVER_MQTT_TOPIC")
+                .map(|t| t.trim().trim_end_matches('/').to_string())
+                .unwrap_or_else(|_| "live-server".to_string()),
+            client_id: var("LIVE_SERVER_MQTT_CLIENT_ID").unwrap_or_else(|_| "rusty-live-server".to_string()),
+            username: var("LIVE_SERVER_MQTT_USERNAME").ok(),
+            interval_secs: env_bounded("LIVE_SERVER_MQTT_INTERVAL", 10, 1, 3600),
+        })
+    }
+}
+
+/// SMTP 连接加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// 明文连接后升级为 TLS（默认，端口 587）
+    StartTls,
+    /// 直接使用 TLS 连接（端口 465）
+    Tls,
+    /// 不加密（仅用于本机或内网中继）
+    None,
+}
+
+impl SmtpTls {
+    /// 从字符串解析加密方式
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "starttls" => Some(Self::StartTls),
+            "tls" => Some(Self::Tls),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    /// 该加密方式的默认端口
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Tls => 465,
+            Self::None => 25,
+        }
+    }
+}
+
+/// 邮件通知目标
+///
+/// 直播开始和服务出现严重问题时向收件人列表发送邮件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpTarget {
+    /// SMTP 服务器主机
+    pub host: String,
+    /// SMTP 服务器端口
+    pub port: u16,
+    /// 加密方式
+    pub tls: SmtpTls,
+    /// 登录用户名（`None` 表示不登录）
+    pub username: Option<String>,
+    /// 发件人地址
+    pub from: String,
+    /// 收件人地址列表
+    pub recipients: Vec<String>,
+}
+
+impl SmtpTarget {
+    /// 从环境变量读取邮件通知配置
+    ///
+    /// ### 返回值
+    /// 未设置 `LIVE_SERVER_SMTP_HOST` 或收件人为空时返回 `None`
+    fn from_env() -> Option<Self> {
+        let server = var("LIVE_SERVER_SMTP_HOST").ok()?;
+        let tls = var("LIVE_SERVER_SMTP_TLS")
+            .ok()
+            .and_then(|v| SmtpTls::parse(&v))
+            .unwrap_or(SmtpTls::StartTls);
+        let (host, port) = match server.trim().rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host, port),
+                Err(_) => {
+                    tracing::warn!("LIVE_SERVER_SMTP_HOST 的端口 {} 无法解析，已禁用邮件通知", port);
+                    return None;
+                }
+            },
+            None => (server.trim(), tls.default_port()),
+        };
+        let recipients: Vec<String> = var("LIVE_SERVER_NOTIFY_EMAILS")
+            .map(|v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|a| !a.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if host.is_empty() || recipients.is_empty() {
+            tracing::warn!("已设置 LIVE_SERVER_SMTP_HOST，但未配置收件人 LIVE_SERVER_NOTIFY_EMAILS，已禁用邮件通知");
+            return None;
+        }
+        let username = var("LIVE_SERVER_SMTP_USERNAME").ok();
+        let from = var("LIVE_SERVER_SMTP_FROM")
+            .ok()
+            .or_else(|| username.clone())
+            .unwrap_or_else(|| format!("live-server@{}", host));
+        Some(Self {
+            host: host.to_string(),
+            port,
+            tls,
+            username,
+            from,
+            recipients,
+        })
+    }
+}
+
+/// 主播身份登录策略
+///
+/// 控制知道推流密钥的人能否在网页端获得主播权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublisherLoginPolicy {
+    /// 不做额外限制（默认）
+    Open,
+    /// 仅允许来自当前推流 IP 的登录
+    PushIp,
+    /// 需要额外输入服务器日志中打印的一次性验证码（输错即作废，并计入密钥失败次数）
+    OneTimeCode,
+    /// 同一时间只允许一个主播会话，接管需显式确认
+    SingleSession,
+}
+
+impl CohortPolicy {
+    /// 计算客户端所属分组在当前轮换周期内的种子
+    ///
+    /// 无法解析的 IP 按原字符串单独分组
+    pub fn seed(&self, ip: &str, now_secs: i64) -> u64 {
+        let network = match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(v4)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_v4 as u32).unwrap_or(0);
+                IpAddr::V4((u32::from(v4) & mask).into()).to_string()
+            }
+            Ok(IpAddr::V6(v6)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_v6 as u32).unwrap_or(0);
+                IpAddr::V6((u128::from(v6) & mask).into()).to_string()
+            }
+            Err(_) => ip.to_string(),
+        };
+        let epoch = now_secs.div_euclid(self.rotation_secs);
+
+        let mut hasher = DefaultHasher::new();
+        (network, epoch).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// 聊天消息中链接的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatUrlPolicy {
+    /// 原样保留所有链接
+    Allow,
+    /// 移除所有链接
+    Strip,
+    /// 只保留白名单域名的链接，其余移除
+    Whitelist,
+    /// 白名单域名的链接原样保留，其余改写为经过警告页的跳转链接
+    Redirect,
+}
+
+impl ChatUrlPolicy {
+    /// 从字符串解析策略
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "allow" => Some(Self::Allow),
+            "strip" => Some(Self::Strip),
+            "whitelist" => Some(Self::Whitelist),
+            "redirect" => Some(Self::Redirect),
+            _ => None,
+        }
+    }
+}
+
+/// 播放延迟模式
+///
+/// 决定向观众推荐的播放协议，前端据此自动选择播放器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyMode {
+    /// 低延迟：优先 WebRTC，其次 HTTP-FLV（默认）
+    Low,
+    /// 稳定：优先 HLS，其次 HTTP-FLV，适合网络较差的观众
+    Stable,
+}
+
+impl LatencyMode {
+    /// 从字符串解析模式
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(Self::Low),
+            "stable" => Some(Self::Stable),
+            _ => None,
+        }
+    }
+
+    /// 将模式转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Stable => "stable",
+        }
+    }
+}
+
+/// 人机验证服务提供方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    /// Cloudflare Turnstile
+    Turnstile,
+    /// hCaptcha
+    HCaptcha,
+}
+
+impl CaptchaProvider {
+    /// 从字符串解析提供方
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "turnstile" => Some(Self::Turnstile),
+            "hcaptcha" => Some(Self::HCaptcha),
+            _ => None,
+        }
+    }
+
+    /// 服务端校验接口地址
+    pub fn verify_url(&self) -> &'static str {
+        match self {
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            Self::HCaptcha => "https://api.hcaptcha.com/siteverify",
+        }
+    }
+}
+
+impl PublisherLoginPolicy {
+    /// 从字符串解析策略
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(Self::Open),
+            "push_ip" => Some(Self::PushIp),
+            "one_time_code" => Some(Self::OneTimeCode),
+            "single_session" => Some(Self::SingleSession),
+            _ => None,
+        }
+    }
+}
+
+/// 无人推流时观众连接（`action=connect`）的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineConnect {
+    /// 照常发放题目（默认），同时告知直播已结束
+    Issue,
+    /// 只登记客户端，不发放题目也不直接放行，直播开始后重新连接再答题
+    Defer,
+    /// 照常发放题目，答题通过后进入排队，开播时自动放行
+    Queue,
+}
+
+impl OfflineConnect {
+    /// 从字符串解析处理方式
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "issue" => Some(Self::Issue),
+            "defer" => Some(Self::Defer),
+            "queue" => Some(Self::Queue),
+            _ => None,
+        }
+    }
+}
+
+/// 题库可出题条目不足时代替答题的入场方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuizFallback {
+    /// 两位数加减法口算题（默认）
+    Arithmetic,
+    /// 口算题，题目文本附带答案（等同公开直播）
+    Public,
+    /// 点击确认入场（同推流参数 `entry=confirm`）
+    Confirm,
+}
+
+impl QuizFallback {
+    /// 从字符串解析入场方式
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "arithmetic" => Some(Self::Arithmetic),
+            "public" => Some(Self::Public),
+            "confirm" => Some(Self::Confirm),
+            _ => None,
+        }
+    }
+}
+
+/// 答错题或推流密钥错误的观众被引导去观看的诱饵流（如循环播放"请认真答题"的视频）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecoyStream {
+    /// 应用名称
+    pub app: String,
+    /// 流名称
+    pub stream: String,
+}
+
+impl DecoyStream {
+    /// 从 `app/stream` 格式解析
+    ///
+    /// ### 返回值
+    /// 格式错误或名称含有 `-_.` 以外的符号时返回 `None`
+    pub fn parse(s: &str) -> Option<Self> {
+        let (app, stream) = s.trim().split_once('/')?;
+        let (app, stream) = (app.trim(), stream.trim());
+        (stream_policy::is_safe_name(app) && stream_policy::is_safe_name(stream)).then(|| Self {
+            app: app.to_string(),
+            stream: stream.to_string(),
+        })
+    }
+
+    /// 是否为该诱饵流
+    pub fn matches(&self, app: &str, stream: &str) -> bool {
+        self.app == app && self.stream == stream
+    }
+}
+
+/// 托管在其他域名上的前端页面及其公开 API 令牌
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontendToken {
+    /// 前端页面的来源（`scheme://host[:port]`，与浏览器发送的 `Origin` 头格式一致）
+    pub origin: String,
+    /// 绑定的 API 令牌（随前端页面公开发布，不是密钥）
+    pub token: String,
+}
+
+impl FrontendToken {
+    /// 从 `来源=令牌` 格式解析
+    ///
+    /// ### 返回值
+    /// 来源不是 http(s) 地址、带有路径，或令牌含有字母、数字和 `-_` 以外的字符时返回 `None`
+    pub fn parse(s: &str) -> Option<Self> {
+        let (origin, token) = s.trim().split_once('=')?;
+        let url = Url::parse(origin.trim()).ok()?;
+        if !matches!(url.scheme(), "http" | "https") || url.path() != "/" || url.query().is_some() {
+            return None;
+        }
+        let token = token.trim();
+        if token.is_empty() || !token.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_')) {
+            return None;
+        }
+        Some(Self {
+            origin: url.origin().ascii_serialization(),
+            token: token.to_string(),
+        })
+    }
+}
+
+/// 热备复制中本实例的角色
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationRole {
+    /// 主实例：在指定地址等待备用实例连接，向其推送状态变化
+    Primary(SocketAddr),
+    /// 备用实例：连接指定的主实例（`主机:端口`），接收并应用状态变化
+    Standby(String),
+}
+
+/// 人数或带宽受限时优先放行的观众类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityClass {
+    /// 连接时出示了有效回访观众令牌的老观众
+    Alumni,
+    /// 在聊天室中设置了昵称的观众
+    Nickname,
+}
+
+impl PriorityClass {
+    /// 从字符串解析类别
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "alumni" => Some(Self::Alumni),
+            "nickname" => Some(Self::Nickname),
+            _ => None,
+        }
+    }
+}
+
+/// 实验性功能开关
+///
+/// 新的高风险子系统默认关闭，可按部署单独开启而无需重新编译
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Features(BTreeMap<String, bool>);
+
+impl Features {
+    /// WebSocket 聊天
+    pub const WEBSOCKET_CHAT: &'static str = "websocket_chat";
+    /// 离线聊天大厅（等同于 `LIVE_SERVER_CHAT_LOBBY`，但可热更新）
+    pub const OFFLINE_LOBBY: &'static str = "offline_lobby";
+    /// 公开落地页
+    pub const PUBLIC_LANDING: &'static str = "public_landing";
+
+    /// 已知的功能名称
+    pub const KNOWN: &'static [&'static str] =
+        &[Self::WEBSOCKET_CHAT, Self::OFFLINE_LOBBY, Self::PUBLIC_LANDING];
+
+    /// 从逗号分隔的列表解析
+    ///
+    /// 每项为 `名称` 或 `名称=true/false`，如 `websocket_chat,offline_lobby=false`
+    pub fn parse(s: &str) -> Self {
+        let map = s
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| match item.split_once('=') {
+                Some((name, value)) => (
+                    name.trim().to_lowercase(),
+                    matches!(value.trim().to_lowercase().as_str(), "true" | "1" | "yes" | "on"),
+                ),
+                None => (item.to_lowercase(), true),
+            })
+            .collect();
+        Self(map)
+    }
+
+    /// 功能是否开启（未配置的功能视为关闭）
+    pub fn enabled(&self, name: &str) -> bool {
+        self.0.get(name).copied().unwrap_or(false)
+    }
+
+    /// 已开启的功能名称
+    pub fn enabled_names(&self) -> Vec<&str> {
+        self.0.iter().filter(|(_, on)| **on).map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// 配置中出现但不认识的功能名称（可能是拼写错误）
+    pub fn unknown_names(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !Self::KNOWN.contains(name))
+            .collect()
+    }
+}
+
 /// 应用配置结构体
 ///
 /// 包含所有运行时配置参数
@@ -19,32 +783,392 @@ pub struct Config {
     pub host: IpAddr,
     /// 服务监听端口
     pub port: u16,
+    /// 监听地址列表（为空时在 `host:port` 上提供全部服务，见 `listeners`）
+    pub listen: Vec<Listen>,
     /// 基础路径（所有其他路径的根目录）
     pub base_path: PathBuf,
-    /// 题库数据库目录路径
+    /// 题库数据库目录路径（配置远程题库时作为本地缓存）
     pub banner_db_path: PathBuf,
+    /// 远程题库地址（`None` 表示仅使用本地文件）
+    pub banner_db_url: Option<String>,
+    /// 是否宽松解析题库（跳过格式错误的条目）
+    pub banner_lenient: bool,
+    /// 宽松解析时至少需要的可出题条目数
+    pub banner_min_questions: usize,
+    /// 题目模板文件路径（`None` 表示使用卡池题库出题）
+    pub question_templates_path: Option<PathBuf>,
+    /// 准入策略脚本路径（`None` 表示不启用脚本钩子）
+    pub script_path: Option<PathBuf>,
+    /// 受信任的身份请求头名称（`None` 表示不信任任何身份请求头）
+    pub trusted_user_header: Option<String>,
+    /// 受信任的反向代理地址段（只有来自这些地址的连接才采用 `X-Forwarded-For` 和身份请求头）
+    pub trusted_proxies: Vec<IpRange>,
+    /// OIDC userinfo 接口地址（`None` 表示不启用 OIDC 令牌校验）
+    pub oidc_userinfo_url: Option<String>,
+    /// 聊天消息中链接的处理策略
+    pub chat_url_policy: ChatUrlPolicy,
+    /// 聊天链接白名单域名（包含其子域名）
+    pub chat_url_whitelist: Vec<String>,
+    /// 允许在聊天中嵌入图片的图床域名（包含其子域名，为空表示不启用）
+    pub chat_embed_hosts: Vec<String>,
+    /// 单个用户每分钟最多嵌入的图片数（0 表示不限制）
+    pub chat_embed_user_per_min: usize,
+    /// 整个房间每分钟最多嵌入的图片数（0 表示不限制）
+    pub chat_embed_global_per_min: usize,
+    /// 观众消息每分钟超过多少条时叠加层抽样消息流开始抽样（0 表示不抽样）
+    pub chat_sample_threshold: usize,
+    /// 抽样时每多少条观众消息保留一条
+    pub chat_sample_every: usize,
+    /// 被不同观众举报多少次后自动限制发言（0 表示不自动限制）
+    pub report_threshold: usize,
+    /// 日志中是否输出完整 IP、答案等敏感信息
+    pub log_sensitive: bool,
+    /// 日志级别（`EnvFilter` 语法）
+    pub log_level: String,
+    /// 答题前的人机验证（提供方, 服务端密钥），`None` 表示不启用
+    pub captcha: Option<(CaptchaProvider, SecretString)>,
     /// 聊天记录转储目录
     pub dump_path: PathBuf,
+    /// 转储目录所在磁盘的最低剩余空间（字节，0 表示不检查）
+    pub dump_min_free_bytes: u64,
     /// 密钥文件路径
     pub secret_path: PathBuf,
     /// SRS API 主机地址
     pub srs_api_host: String,
     /// SRS API 端口
     pub srs_api_port: u16,
+    /// 启动时是否检查 SRS 配置
+    pub srs_self_check: bool,
+    /// SRS 回调看门狗确认不一致后是否补做缺失的状态转换
+    pub srs_watchdog_heal: bool,
+    /// 期望的 SRS vhost
+    pub srs_vhost: String,
+    /// SRS 回调令牌：回调地址须携带 `?token=`（`None` 表示不校验）
+    pub srs_callback_token: Option<SecretString>,
+    /// 允许发送 SRS 回调的来源地址段（为空表示不限制）
+    pub srs_callback_allow: Vec<IpRange>,
+    /// SRS 回调回显文件路径（`None` 表示不记录）
+    pub srs_callback_echo_path: Option<PathBuf>,
+    /// 实验室模式：所有通过来源校验的 SRS 回调一律放行，只记录本应作出的决定
+    pub srs_callback_lab: bool,
+    /// 期望的 SRS 推流应用名
+    pub srs_app: String,
+    /// 允许推流的 app 模式（支持 `*` 通配，为空表示只允许 `srs_app`）
+    pub publish_apps: Vec<String>,
+    /// 允许推流的 stream 模式（支持 `*` 通配，为空表示不限制）
+    pub publish_streams: Vec<String>,
+    /// 推流服务器的公网主机名（未设置时使用请求的 Host 头）
+    pub push_host: Option<String>,
+    /// SRS 的 RTMP 端口
+    pub rtmp_port: u16,
+    /// SRS 的 SRT 端口
+    pub srt_port: u16,
+    /// SRS 转码输出的流名称后缀（如 `_hd`、`_sd`），带后缀的流视为同一场直播的清晰度版本
+    pub stream_variants: Vec<String>,
+    /// 答错题的观众被引导去观看的诱饵流（`None` 表示返回无法播放的假地址）
+    pub decoy_stream: Option<DecoyStream>,
+    /// 观众接口的公网地址（未设置时由请求的 Host 头推断）
+    pub public_url: Option<String>,
+    /// 允许跨域调用观众接口的外部前端及其 API 令牌
+    pub frontend_tokens: Vec<FrontendToken>,
+    /// 外部前端每个令牌、每个客户端 IP 每分钟的请求数上限，0 表示不限制
+    pub frontend_rate_per_min: u32,
+    /// 默认的播放延迟模式（主播可在直播中临时切换）
+    pub latency_mode: LatencyMode,
+    /// 主播身份登录策略
+    pub publisher_login_policy: PublisherLoginPolicy,
+    /// 无人推流时观众连接的处理方式
+    pub offline_connect: OfflineConnect,
+    /// 无人推流时展示的直播时间表（自由文本）
+    pub offline_schedule: Option<String>,
+    /// 无人推流时引导观众前往的落地页地址
+    pub offline_landing_url: Option<String>,
+    /// 是否启用离线聊天大厅
+    pub chat_lobby_enabled: bool,
+    /// 聊天室 UID 是否从彩蛋值 114514 开始分配
+    pub chat_uid_easter_egg: bool,
+    /// 是否启用进出直播间提示（还需观众本人开启）
+    pub chat_presence_notices: bool,
+    /// 是否开放仅限设置了昵称的观众参与的聊天频道（`verified`）
+    pub chat_verified_channel: bool,
+    /// 聊天身份回收时长（秒）：观众记录已过期、从未发言且超过此时长无聊天活动的身份被回收，0 表示不回收
+    pub chat_identity_retention_secs: u64,
+    /// 新一场直播开始时是否清空聊天室（关闭后沿用上一场的聊天记录）
+    pub chat_auto_reset: bool,
+    /// 观众人数对非主播的可见性
+    pub audience_visibility: AudienceVisibility,
+    /// 同时观看（Playing）的观众人数上限，超出的观众进入等候室，0 表示不限制
+    pub max_viewers: usize,
+    /// 上行带宽上限（kbps）：SRS 发送码率达到此值后不再接纳新观众，0 表示不限制
+    pub bandwidth_ceiling_kbps: u64,
+    /// 推流码率持续为零超过此秒数时判定主播可能已离开，0 表示不检测
+    pub away_after_secs: u64,
+    /// 判定主播离开和恢复时是否在聊天室发送系统消息
+    pub away_notice: bool,
+    /// 人数或带宽受限时优先放行的观众类别（按优先级从高到低，为空表示严格按先后顺序）
+    pub admission_priority: Vec<PriorityClass>,
+    /// 超过一分钟仍未通过答题的观众数超过此值时提醒主播，0 表示不检查
+    pub stuck_pending_alert: usize,
+    /// 近期答错率（百分比）达到此值时提醒主播，0 表示不检查
+    pub quiz_failure_alert: u32,
+    /// 单道题目的作答时限（秒，5-55）
+    pub question_time_limit_secs: i64,
+    /// 同一 IP 近期已发放题目的记忆时长（秒），期间不重复发放
+    pub question_memory_secs: i64,
+    /// 每个会话主动换题（`action=newquestion`）的次数上限，0 表示不允许换题
+    pub question_refresh_limit: u32,
+    /// 领取题目后多久才能换题（秒）
+    pub question_refresh_cooldown_secs: u64,
+    /// 点击确认入场模式下，领取确认令牌后至少等待多久才能提交（秒）
+    pub confirm_delay_secs: u64,
+    /// 题库可出题条目少于该数量时不再出题库题目，改用 `quiz_fallback`
+    pub quiz_min_pool: usize,
+    /// 题库可出题条目不足时代替答题的入场方式
+    pub quiz_fallback: QuizFallback,
+    /// 网段题目分组策略（`None` 表示不启用）
+    pub cohort_policy: Option<CohortPolicy>,
+    /// 允许新观众进入的时段（为空表示不限制）
+    pub viewing_windows: Vec<ViewingWindow>,
+    /// 由反向代理提供观众所在国家/地区代码的请求头（`None` 表示不识别地区）
+    pub country_header: Option<String>,
+    /// 允许进入的国家/地区代码（大写，为空表示不限制）
+    pub country_allow: Vec<String>,
+    /// 禁止进入的国家/地区代码（大写）
+    pub country_deny: Vec<String>,
+    /// 回访观众令牌签名密钥（`None` 表示不签发令牌）
+    pub alumni_key: Option<SecretString>,
+    /// 回访观众令牌有效期（天）
+    pub alumni_validity_days: i64,
+    /// 主播访问令牌（JWT）签名密钥（`None` 表示不签发令牌，快捷操作接口不可用）
+    pub publisher_jwt_key: Option<SecretString>,
+    /// 主播访问令牌有效期（小时，1-168）
+    pub publisher_jwt_hours: i64,
+    /// 转推目标地址的加密密钥（`None` 表示不可转推）
+    pub relay_key: Option<SecretString>,
+    /// 转推目标：名称 → 加密后的推流地址（由 `/admin/relay/seal` 生成）
+    pub relay_targets: BTreeMap<String, String>,
+    /// FFmpeg 可执行文件路径（转推使用）
+    pub ffmpeg_path: String,
+    /// 零停机升级时启动的新版本可执行文件（`None` 表示使用当前程序的路径）
+    pub upgrade_binary: Option<PathBuf>,
+    /// 热备复制角色（`None` 表示不复制）
+    pub replication: Option<ReplicationRole>,
+    /// 热备复制链路的共享密钥
+    pub replication_key: Option<SecretString>,
+    /// MQTT 状态发布目标（`None` 表示不发布）
+    pub mqtt: Option<MqttTarget>,
+    /// MQTT 密码（`None` 表示不使用密码）
+    pub mqtt_password: Option<SecretString>,
+    /// 邮件通知目标（`None` 表示不发送邮件）
+    pub smtp: Option<SmtpTarget>,
+    /// SMTP 登录密码
+    pub smtp_password: Option<SecretString>,
+    /// 管理接口令牌（`None` 表示禁用管理接口）
+    pub admin_token: Option<SecretString>,
+    /// 打赏回调共享密钥（`None` 表示禁用打赏回调）
+    pub tip_hook_secret: Option<SecretString>,
+    /// 内容问题"第 N 个字"中 N 的上限
+    pub content_char_limit: usize,
+    /// 后台任务的轮询间隔
+    pub intervals: Intervals,
+    /// 实验性功能开关
+    pub features: Features,
+    /// 观众首次连接时记录的请求头（小写），用于排查观众问题
+    pub client_headers: Vec<String>,
+    /// 客户端轮询节奏建议
+    pub poll: PollPolicy,
 }
 
 impl Config {
     /// 从环境变量或默认值创建配置
     ///
     /// ### 环境变量
+    /// - `LIVE_SERVER_CONFIG_FILE` - 配置文件路径（可选，见模块文档）
     /// - `LIVE_SERVER_BASE_PATH` - 基础路径（默认：当前工作目录）
+    /// - `LIVE_SERVER_LISTEN` - 监听地址列表，逗号分隔的 `地址[=服务+服务]`，
+    ///   服务为 `callback`（SRS 回调）/ `api`（观众接口与聊天）/ `admin`（管理接口），省略时提供全部服务。
+    ///   如 `127.0.0.1:8848=callback,0.0.0.0:8080=api+admin`（默认：`0.0.0.0:8848` 上的全部服务）
+    /// - `LIVE_SERVER_DUMP_MIN_FREE_MB` - 转储目录所在磁盘的最低剩余空间（MB，默认：100，0 表示不检查），
+    ///   低于该值时直播结束的聊天记录改为精简转储，并拒绝主播手动保存快照
+    /// - `LIVE_SERVER_BANNER_DB_URL` - 远程题库地址（HTTP/HTTPS，未设置则仅使用本地文件）
+    /// - `LIVE_SERVER_BANNER_LENIENT` - 宽松解析题库（默认：`false`）：跳过格式错误的条目并记录警告
+    ///   （见 `/admin/bannerdb/report` 的 `warnings`），否则任一条目格式错误都会导致题库加载失败
+    /// - `LIVE_SERVER_BANNER_MIN_QUESTIONS` - 宽松解析时至少需要的可出题条目数，不足时加载失败（默认：1）
+    /// - `LIVE_SERVER_QUESTION_TEMPLATES` - 题目模板文件路径（设置后使用模板题库代替卡池题库，
+    ///   相对路径以基础路径为基准）
+    /// - `LIVE_SERVER_SCRIPT` - 准入策略 Rhai 脚本路径（需启用 `scripting` 特性编译，
+    ///   相对路径以基础路径为基准）
+    /// - `LIVE_SERVER_TRUSTED_USER_HEADER` - 受信任的身份请求头（如 `X-Auth-User`），
+    ///   携带该请求头的观众无需答题。仅在服务只能经由认证代理访问时设置
+    /// - `LIVE_SERVER_TRUSTED_PROXIES` - 受信任的反向代理地址，逗号分隔的 IP 或 CIDR。
+    ///   来自其他地址的连接携带的 `X-Forwarded-For` 和身份请求头一律忽略，
+    ///   客户端 IP 取连接的对端地址（默认：`127.0.0.1,::1`）
+    /// - `LIVE_SERVER_OIDC_USERINFO_URL` - OIDC userinfo 接口地址，
+    ///   携带有效 `Authorization: Bearer` 令牌的观众无需答题
+    /// - `LIVE_SERVER_CHAT_URL_POLICY` - 聊天链接策略：`allow` / `strip` / `whitelist` / `redirect`（默认：`allow`）
+    /// - `LIVE_SERVER_CHAT_URL_WHITELIST` - 聊天链接白名单域名，逗号分隔（如 `bilibili.com,github.com`）
+    /// - `LIVE_SERVER_CHAT_EMBED_HOSTS` - 允许嵌入图片的图床域名，逗号分隔（如 `i.imgur.com`）
+    /// - `LIVE_SERVER_CHAT_EMBED_USER_LIMIT` - 单用户每分钟图片嵌入上限（默认：2）
+    /// - `LIVE_SERVER_CHAT_EMBED_GLOBAL_LIMIT` - 全房间每分钟图片嵌入上限（默认：20）
+    /// - `LIVE_SERVER_CHAT_SAMPLE_THRESHOLD` - 观众消息每分钟超过多少条时，`getchat` 的 `sampled=true`
+    ///   消息流只保留部分观众消息，主播消息和系统消息始终保留（默认：0，不抽样）
+    /// - `LIVE_SERVER_CHAT_SAMPLE_EVERY` - 抽样时每多少条观众消息保留一条（默认：5）
+    /// - `LIVE_SERVER_REPORT_THRESHOLD` - 被不同观众举报多少次后自动限制发言，
+    ///   被限制者的消息仅自己可见，等待主播审核（默认：3，0 表示不自动限制）
+    /// - `LIVE_SERVER_LOG_SENSITIVE` - 日志中输出完整 IP、答案等敏感信息（默认：`false`，遮蔽）
+    /// - `LIVE_SERVER_LOG_LEVEL` - 日志级别，如 `info`、`debug`、`info,audit=warn`（默认：`debug`）
+    /// - `LIVE_SERVER_CAPTCHA_PROVIDER` - 答题前的人机验证：`turnstile` / `hcaptcha`（默认不启用）
+    /// - `LIVE_SERVER_CAPTCHA_SECRET` - 人机验证服务端密钥（启用人机验证时必填）
+    /// - `LIVE_SERVER_SRS_SELF_CHECK` - 启动时检查 SRS 配置并对不一致之处输出警告（默认：`false`）
+    /// - `LIVE_SERVER_SRS_WATCHDOG_HEAL` - SRS 回调看门狗确认漏收回调后是否自动补做暂停或恢复（默认：`false`）
+    /// - `LIVE_SERVER_SRS_CALLBACK_TOKEN` - SRS 回调令牌，SRS 的回调地址须写成 `http://<本服务>/?token=<令牌>`
+    ///   （未设置则不校验）
+    /// - `LIVE_SERVER_SRS_CALLBACK_ALLOW` - 允许发送 SRS 回调的来源地址，逗号分隔的 IP 或 CIDR，
+    ///   如 `127.0.0.1,10.0.0.0/8`（默认不限制）
+    /// - `LIVE_SERVER_SRS_CALLBACK_ECHO` - SRS 回调回显文件路径，每个回调的完整内容和处理结果逐行写入
+    ///   （JSON Lines，含推流密钥，仅用于调试；相对路径以基础路径为基准，默认不记录）
+    /// - `LIVE_SERVER_SRS_CALLBACK_LAB` - 实验室模式：通过来源校验的 SRS 回调一律放行，
+    ///   只在日志和回显文件中记录本应作出的决定（默认：`false`，切勿在生产环境开启）
+    /// - `LIVE_SERVER_SRS_VHOST` - 期望的 SRS vhost（默认：`__defaultVhost__`）
+    /// - `LIVE_SERVER_SRS_APP` - 期望的 SRS 推流应用名（默认：`live`）
+    /// - `LIVE_SERVER_PUBLISH_APPS` - 允许推流的 app，逗号分隔，支持 `*` 通配（默认只允许 `LIVE_SERVER_SRS_APP`）
+    /// - `LIVE_SERVER_PUBLISH_STREAMS` - 允许推流的 stream，逗号分隔，支持 `*` 通配，如 `main,event-*`（默认不限制）；
+    ///   无论是否配置，app / stream 都只能包含字母、数字和 `-_.`
+    /// - `LIVE_SERVER_PUSH_HOST` - 推流地址中使用的公网主机名（默认使用请求的 Host 头）
+    /// - `LIVE_SERVER_RTMP_PORT` - 推流地址中使用的 RTMP 端口（默认：1935）
+    /// - `LIVE_SERVER_SRT_PORT` - 推流地址中使用的 SRT 端口（默认：10080）
+    /// - `LIVE_SERVER_STREAM_VARIANTS` - SRS 转码输出的流名称后缀，逗号分隔，如 `_hd,_sd`（默认不识别）
+    /// - `LIVE_SERVER_DECOY_STREAM` - 诱饵流 `app/stream`（如 `live/decoy`），答错题或推流密钥错误的观众得到该流的
+    ///   播放地址并可正常拉流（默认返回无法播放的假地址）。诱饵流由运维自行推送（如 FFmpeg 循环推送一段视频），
+    ///   推流端须在本机或携带任一有效的推流密钥，不会被当作主播登记
+    /// - `LIVE_SERVER_PUBLIC_URL` - 观众接口的公网地址，如 `https://live.example.com`（可带路径前缀），
+    ///   由 `GET /api/bootstrap` 返回给前端（默认由请求的 `X-Forwarded-Proto` 和 Host 头推断）
+    /// - `LIVE_SERVER_FRONTEND_TOKENS` - 托管在其他域名上的前端（如 GitHub Pages、CDN）及其公开 API 令牌，
+    ///   格式 `来源=令牌,来源=令牌`，如 `https://me.github.io=pk_abc123`（默认不允许跨域调用）。
+    ///   登记的来源可跨域调用观众接口（CORS），但必须携带绑定的令牌（请求头 `X-Api-Token` 或查询参数 `api_token`）
+    /// - `LIVE_SERVER_FRONTEND_RATE_LIMIT` - 外部前端每个令牌、每个客户端 IP 每分钟的请求数上限（默认：300，0 表示不限制）
+    /// - `LIVE_SERVER_LATENCY_MODE` - 播放延迟模式：`low`（WebRTC/FLV）/ `stable`（HLS/FLV）（默认：`low`）
+    /// - `LIVE_SERVER_PUBLISHER_LOGIN_POLICY` - 主播登录策略：
+    ///   `open` / `push_ip` / `one_time_code` / `single_session`（默认：`open`）
+    /// - `LIVE_SERVER_OFFLINE_CONNECT` - 无人推流时观众连接的处理方式：`issue`（照常发放题目）/
+    ///   `defer`（直播开始前不发放题目、不直接放行）/ `queue`（答题通过后排队，开播时自动放行并推送
+    ///   `stream_live` 事件）（默认：`issue`）
+    /// - `LIVE_SERVER_OFFLINE_SCHEDULE` - 无人推流时随连接响应返回的直播时间表，如 `每周六 20:00`（默认不返回）
+    /// - `LIVE_SERVER_OFFLINE_LANDING_URL` - 无人推流时随连接响应返回的落地页地址（默认不返回）
+    /// - `LIVE_SERVER_CHAT_LOBBY` - 是否启用离线聊天大厅（`true`/`false`，默认：`false`）
+    /// - `LIVE_SERVER_CHAT_UID_EASTER_EGG` - 聊天室 UID 是否从 114514 开始分配（`true`/`false`，默认：`false`，从 1 开始）
+    /// - `LIVE_SERVER_CHAT_PRESENCE` - 是否启用进出直播间提示（`true`/`false`，默认：`false`），
+    ///   仅对设置了昵称并主动开启的观众生效
+    /// - `LIVE_SERVER_CHAT_VERIFIED_CHANNEL` - 是否开放第二个聊天频道 `verified`（`true`/`false`，默认：`false`），
+    ///   只有设置了昵称的已授权观众和主播可以收发，通过 `sendchat` / `getchat` 的 `channel` 字段选择
+    /// - `LIVE_SERVER_CHAT_IDENTITY_RETENTION` - 聊天身份回收时长（秒，默认：1800，0 表示不回收）。
+    ///   观众记录过期后，从未发言且超过此时长无聊天活动的身份及其昵称被回收
+    /// - `LIVE_SERVER_CHAT_AUTO_RESET` - 新一场直播开始时是否清空聊天室（`true`/`false`，默认：`true`）。
+    ///   暂停超时后 10 分钟内以相同密钥重新推流视为同一场直播，无论此项如何都沿用原聊天室
+    /// - `LIVE_SERVER_AUDIENCE_VISIBILITY` - 观众人数可见性：`exact` / `bucketed` / `hidden`（默认：`exact`）
+    /// - `LIVE_SERVER_MAX_VIEWERS` - 同时观看的观众人数上限（默认：0，不限制）。
+    ///   超出的观众按先后进入等候室，有空位时依次放行
+    /// - `LIVE_SERVER_BANDWIDTH_CEILING_KBPS` - 上行带宽上限（kbps，默认：0，不限制）。
+    ///   SRS 全部流的发送码率达到此值后拒绝新观众拉流（已在观看或暂离的观众不受影响），
+    ///   按 SRS 统计的轮询间隔更新
+    /// - `LIVE_SERVER_AWAY_AFTER` - 推流端仍在推流但码率持续为零（编码器只发送空白画面）超过此秒数时，
+    ///   在主播的观众人数信息中提示可能已离开并推送 `streamer_away` 事件（默认：60，0 表示不检测）。
+    ///   SRS 报告的是近 30 秒的平均码率，实际判定会比设置的时长晚最多 30 秒
+    /// - `LIVE_SERVER_AWAY_NOTICE` - 判定主播离开和恢复时是否在聊天室发送系统消息（`true`/`false`，默认：`false`）
+    /// - `LIVE_SERVER_ADMISSION_PRIORITY` - 人数或带宽受限时优先放行的观众类别，按优先级从高到低逗号分隔：
+    ///   `alumni`（持有回访观众令牌）/ `nickname`（设置了聊天昵称），如 `alumni,nickname`（默认为空，严格按先后顺序）。
+    ///   列出的类别在等候室中排在匿名观众之前，且不受带宽上限限制
+    /// - `LIVE_SERVER_STUCK_PENDING_ALERT` - 连接超过一分钟仍未通过答题的观众数超过此值时，
+    ///   记录警告并推送 `quiz_trouble` 事件（默认：10，0 表示不检查）
+    /// - `LIVE_SERVER_QUIZ_FAILURE_ALERT` - 近 5 分钟答错率（百分比）达到此值时提醒（默认：80，0 表示不检查），
+    ///   作答不足 10 次时不计算
+    /// - `LIVE_SERVER_QUESTION_TIME_LIMIT` - 作答时限（秒，默认：45，范围 5-55）。
+    ///   待答题记录 60 秒无活动即被清理，时限须小于该值
+    /// - `LIVE_SERVER_QUESTION_MEMORY` - 同一 IP 不重复发放题目的记忆时长（秒，默认：600）
+    /// - `LIVE_SERVER_QUESTION_REFRESH_LIMIT` - 待答题观众主动换题（`action=newquestion`）的次数上限（默认：2，0 表示不允许），
+    ///   用于题库数据有误导致题目无法作答的情况
+    /// - `LIVE_SERVER_QUESTION_REFRESH_COOLDOWN` - 领取题目后多久才能换题（秒，默认：30）
+    /// - `LIVE_SERVER_CONFIRM_DELAY` - 点击确认入场模式（推流参数 `entry=confirm`）下，
+    ///   领取确认令牌后至少等待多久才能提交（秒，默认：3）
+    /// - `LIVE_SERVER_QUIZ_MIN_POOL` - 题库（或模板题库数据集）可出题条目少于该数量时不再出题库题目（默认：1），
+    ///   避免题库为空或全部被排除时只能发出无法作答的题目，改用 `LIVE_SERVER_QUIZ_FALLBACK` 指定的方式入场：
+    ///   `arithmetic`（口算题，默认）/ `public`（口算题并在题目中附带答案）/ `confirm`（点击确认入场）
+    /// - `LIVE_SERVER_COHORT_QUESTIONS` - 是否启用网段题目分组（默认：`false`），启用后：
+    ///   - `LIVE_SERVER_COHORT_PREFIX_V4` - IPv4 前缀长度（默认：24）
+    ///   - `LIVE_SERVER_COHORT_PREFIX_V6` - IPv6 前缀长度（默认：48）
+    ///   - `LIVE_SERVER_COHORT_ROTATION` - 子集轮换周期（秒，默认：600）
+    ///   - `LIVE_SERVER_COHORT_SUBSET` - 子集卡池数量（默认：8）
+    /// - `LIVE_SERVER_VIEWING_WINDOWS` - 允许新观众进入的时段（服务器本地时间），逗号分隔，
+    ///   如 `19:00-02:00`（默认不限制）。时段外连接不发放题目，返回 `stream_status=outside_window`
+    ///   和下一次开放时间 `next_open`，已进入的观众不受影响
+    /// - `LIVE_SERVER_COUNTRY_HEADER` - 反向代理 / CDN 提供的观众国家/地区代码请求头，如 `CF-IPCountry`
+    ///   （默认不识别地区；本服务不内置 IP 地理位置数据库）。设置后：
+    ///   - `LIVE_SERVER_COUNTRY_ALLOW` - 允许进入的国家/地区代码，逗号分隔，如 `CN,HK`（默认不限制），
+    ///     设置后地区未知的新观众同样被拒绝
+    ///   - `LIVE_SERVER_COUNTRY_DENY` - 禁止进入的国家/地区代码，逗号分隔
+    ///
+    ///   被拒绝的新观众连接返回 `stream_status=region_blocked`
+    /// - `LIVE_SERVER_ALUMNI_KEY` - 回访观众令牌签名密钥（未设置则不签发令牌）
+    /// - `LIVE_SERVER_ALUMNI_DAYS` - 回访观众令牌有效期（天，默认：30）
+    /// - `LIVE_SERVER_PUBLISHER_JWT_KEY` - 主播访问令牌签名密钥
+    ///   （未设置则不签发令牌，禁用 `/api/publisher/quick`）
+    /// - `LIVE_SERVER_PUBLISHER_JWT_HOURS` - 主播访问令牌有效期（小时，默认：12，范围 1-168）
+    /// - `LIVE_SERVER_RELAY_KEY` - 转推目标地址的加密密钥（未设置则禁用转推）
+    /// - `LIVE_SERVER_RELAY_TARGETS` - 转推目标，格式 `名称=密文,名称=密文`，
+    ///   密文由 `POST /admin/relay/seal` 生成（平台推流码不以明文出现在配置中）
+    /// - `LIVE_SERVER_FFMPEG` - 转推使用的 FFmpeg 路径（默认：`ffmpeg`）
+    /// - `LIVE_SERVER_UPGRADE_BINARY` - 收到 SIGUSR2 时接管监听地址的新版本可执行文件路径
+    ///   （默认：当前程序的路径，需启用 `hot-upgrade` 特性编译，仅 Unix 系统）
+    /// - `LIVE_SERVER_REPLICATION_LISTEN` - 作为热备复制的主实例，在该地址（如 `0.0.0.0:8850`）等待备用实例连接
+    /// - `LIVE_SERVER_REPLICATION_PRIMARY` - 作为热备复制的备用实例，连接该主实例地址（`主机:端口`），
+    ///   与 `LIVE_SERVER_REPLICATION_LISTEN` 同时设置时以此为准
+    /// - `LIVE_SERVER_REPLICATION_KEY` - 热备复制链路的共享密钥（未设置则不复制），主备两端须一致
+    /// - `LIVE_SERVER_MQTT_BROKER` - MQTT broker 地址 `主机[:端口]`（默认端口 1883，未设置则不发布，
+    ///   需启用 `mqtt` 特性编译），启用后：
+    ///   - `LIVE_SERVER_MQTT_TOPIC` - 主题前缀（默认：`live-server`），发布 `<前缀>/state`（`live` / `paused` / `offline`，保留消息）、
+    ///     `<前缀>/audience`（当前观众数，保留消息）和 `<前缀>/chat_rate`（近一分钟聊天条数）
+    ///   - `LIVE_SERVER_MQTT_CLIENT_ID` - 客户端 ID（默认：`rusty-live-server`）
+    ///   - `LIVE_SERVER_MQTT_USERNAME` / `LIVE_SERVER_MQTT_PASSWORD` - 认证信息（默认匿名）
+    ///   - `LIVE_SERVER_MQTT_INTERVAL` - 观众数和聊天速率的发布间隔（秒，默认：10，范围 1~3600），
+    ///     直播状态变化时立即发布
+    /// - `LIVE_SERVER_SMTP_HOST` - SMTP 服务器 `主机[:端口]`（未设置则不发送邮件，需启用 `email` 特性编译），
+    ///   直播开始，以及远程题库刷新失败、SRS API 超过 5 分钟不可用、转储目录剩余空间不足时发送邮件。启用后：
+    ///   - `LIVE_SERVER_NOTIFY_EMAILS` - 收件人地址，逗号分隔（必填）
+    ///   - `LIVE_SERVER_SMTP_TLS` - 加密方式：`starttls`（默认，端口 587）/ `tls`（端口 465）/ `none`（端口 25）
+    ///   - `LIVE_SERVER_SMTP_USERNAME` / `LIVE_SERVER_SMTP_PASSWORD` - 登录信息（默认不登录）
+    ///   - `LIVE_SERVER_SMTP_FROM` - 发件人地址（默认为用户名）
+    /// - `LIVE_SERVER_ADMIN_TOKEN` - 管理接口令牌（未设置则禁用 `/admin` 接口）
+    /// - `LIVE_SERVER_TIP_HOOK_SECRET` - 打赏回调共享密钥（未设置则禁用 `/api/hooks/tip`）
+    /// - `LIVE_SERVER_CONTENT_CHAR_LIMIT` - 内容问题"第 N 个字"中 N 的上限（默认：20）
+    /// - `LIVE_SERVER_FEATURES` - 开启的实验性功能，逗号分隔，
+    ///   如 `websocket_chat,offline_lobby=false`（默认全部关闭，见 `Features`）
+    /// - `LIVE_SERVER_CLIENT_HEADERS` - 观众首次连接时记录的请求头，逗号分隔，
+    ///   可在 `/admin/clients` 中查看（默认：`user-agent`，设为空则不记录）
+    ///
+    /// ### 客户端轮询节奏（毫秒）
+    /// - `LIVE_SERVER_POLL_INTERVAL_MS` - 建议的轮询间隔（默认：1000，范围 100~60000）
+    /// - `LIVE_SERVER_POLL_MAX_INTERVAL_MS` - 负载升高或直播暂停时建议间隔的上限
+    ///   （默认：10000，范围为轮询间隔 ~300000）
+    /// - `LIVE_SERVER_POLL_LOAD_THRESHOLD` - 每秒轮询请求数超过此值时按比例放大间隔
+    ///   （默认：200，0 表示不按负载调整）
+    ///
+    /// ### 后台任务间隔（秒）
+    /// - `LIVE_SERVER_CLEANUP_INTERVAL` - 过期记录清理间隔（默认：10，范围 1~300）
+    /// - `LIVE_SERVER_SRS_POLL_INTERVAL` - SRS 观众人数轮询间隔（默认：5，范围 1~600）
+    /// - `LIVE_SERVER_SRS_POLL_MAX_BACKOFF` - SRS API 不可用时的最大退避间隔
+    ///   （默认：300，范围为轮询间隔 ~3600）
+    /// - `LIVE_SERVER_BANNER_DB_REFRESH` - 远程题库刷新间隔（默认：3600，范围 60~86400）
+    /// - `LIVE_SERVER_SRS_WATCHDOG_INTERVAL` - SRS 回调看门狗检查间隔，对比 SRS 中的推流与主播记录，
+    ///   不一致时输出警告并发送邮件通知（默认：30，范围 0~3600，0 表示不启用）
     ///
     /// ### 默认值
     /// - 服务地址: 0.0.0.0:8848
     /// - 基础路径: 当前工作目录
+    ///
+    /// 已通过 `load_file` 读取配置文件时，文件中的值优先
     pub fn from_env() -> Self {
         // 基础路径：优先使用环境变量，否则使用当前工作目录
-        let base_path = if let Ok(path) = env::var("LIVE_SERVER_BASE_PATH") {
+        let base_path = if let Ok(path) = var("LIVE_SERVER_BASE_PATH") {
             PathBuf::from(path)
         } else {
             env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
@@ -53,12 +1177,261 @@ impl Config {
         Self {
             host: "0.0.0.0".parse().unwrap(),
             port: 8848,
+            listen: var("LIVE_SERVER_LISTEN")
+                .map(|v| Listen::parse_list(&v))
+                .unwrap_or_default(),
             base_path: base_path.clone(),
             banner_db_path: base_path.join("config/bannerdb"),
+            banner_db_url: var("LIVE_SERVER_BANNER_DB_URL")
+                .ok()
+                .filter(|u| u.starts_with("http://") || u.starts_with("https://")),
+            banner_lenient: env_flag("LIVE_SERVER_BANNER_LENIENT"),
+            banner_min_questions: env_parse("LIVE_SERVER_BANNER_MIN_QUESTIONS").unwrap_or(1),
+            question_templates_path: var("LIVE_SERVER_QUESTION_TEMPLATES")
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(|p| base_path.join(p)),
+            script_path: var("LIVE_SERVER_SCRIPT")
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(|p| base_path.join(p)),
+            trusted_user_header: var("LIVE_SERVER_TRUSTED_USER_HEADER")
+                .ok()
+                .filter(|h| !h.is_empty()),
+            trusted_proxies: env_ip_ranges("LIVE_SERVER_TRUSTED_PROXIES").unwrap_or_else(|| {
+                ["127.0.0.1", "::1"].into_iter().filter_map(IpRange::parse).collect()
+            }),
+            oidc_userinfo_url: var("LIVE_SERVER_OIDC_USERINFO_URL")
+                .ok()
+                .filter(|u| !u.is_empty()),
+            chat_url_policy: var("LIVE_SERVER_CHAT_URL_POLICY")
+                .ok()
+                .and_then(|v| ChatUrlPolicy::parse(&v))
+                .unwrap_or(ChatUrlPolicy::Allow),
+            chat_url_whitelist: env_domains("LIVE_SERVER_CHAT_URL_WHITELIST"),
+            chat_embed_hosts: env_domains("LIVE_SERVER_CHAT_EMBED_HOSTS"),
+            chat_embed_user_per_min: env_parse("LIVE_SERVER_CHAT_EMBED_USER_LIMIT").unwrap_or(2),
+            chat_embed_global_per_min: env_parse("LIVE_SERVER_CHAT_EMBED_GLOBAL_LIMIT").unwrap_or(20),
+            chat_sample_threshold: env_parse("LIVE_SERVER_CHAT_SAMPLE_THRESHOLD").unwrap_or(0),
+            chat_sample_every: env_parse("LIVE_SERVER_CHAT_SAMPLE_EVERY").unwrap_or(5).max(1),
+            report_threshold: env_parse("LIVE_SERVER_REPORT_THRESHOLD").unwrap_or(3),
+            log_sensitive: env_flag("LIVE_SERVER_LOG_SENSITIVE"),
+            log_level: var("LIVE_SERVER_LOG_LEVEL")
+                .ok()
+                .filter(|l| !l.trim().is_empty())
+                .unwrap_or_else(|| logging::DEFAULT_LEVEL.to_string()),
+            captcha: var("LIVE_SERVER_CAPTCHA_PROVIDER")
+                .ok()
+                .and_then(|v| CaptchaProvider::parse(&v))
+                .zip(env_secret("LIVE_SERVER_CAPTCHA_SECRET")),
             dump_path: base_path.join("dumps"),
+            dump_min_free_bytes: env_parse::<u64>("LIVE_SERVER_DUMP_MIN_FREE_MB").unwrap_or(100) * 1024 * 1024,
             secret_path: base_path.join("secrets/secret.txt"),
             srs_api_host: "127.0.0.1".to_string(),
             srs_api_port: 1985,
+            srs_self_check: env_flag("LIVE_SERVER_SRS_SELF_CHECK"),
+            srs_watchdog_heal: env_flag("LIVE_SERVER_SRS_WATCHDOG_HEAL"),
+            srs_vhost: var("LIVE_SERVER_SRS_VHOST").unwrap_or_else(|_| "__defaultVhost__".to_string()),
+            srs_callback_token: env_secret("LIVE_SERVER_SRS_CALLBACK_TOKEN"),
+            srs_callback_allow: env_ip_ranges("LIVE_SERVER_SRS_CALLBACK_ALLOW").unwrap_or_default(),
+            srs_callback_echo_path: var("LIVE_SERVER_SRS_CALLBACK_ECHO")
+                .ok()
+                .filter(|p| !p.is_empty())
+                .map(|p| base_path.join(p)),
+            srs_callback_lab: env_flag("LIVE_SERVER_SRS_CALLBACK_LAB"),
+            srs_app: var("LIVE_SERVER_SRS_APP").unwrap_or_else(|_| "live".to_string()),
+            publish_apps: var("LIVE_SERVER_PUBLISH_APPS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            publish_streams: var("LIVE_SERVER_PUBLISH_STREAMS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            push_host: var("LIVE_SERVER_PUSH_HOST").ok().filter(|h| !h.is_empty()),
+            rtmp_port: env_parse("LIVE_SERVER_RTMP_PORT").unwrap_or(1935),
+            srt_port: env_parse("LIVE_SERVER_SRT_PORT").unwrap_or(10080),
+            decoy_stream: var("LIVE_SERVER_DECOY_STREAM").ok().filter(|v| !v.trim().is_empty()).and_then(|v| {
+                let decoy = DecoyStream::parse(&v);
+                if decoy.is_none() {
+                    tracing::warn!("LIVE_SERVER_DECOY_STREAM 格式应为 app/stream，已忽略: {}", v);
+                }
+                decoy
+            }),
+            public_url: var("LIVE_SERVER_PUBLIC_URL")
+                .ok()
+                .map(|v| v.trim().trim_end_matches('/').to_string())
+                .filter(|v| !v.is_empty())
+                .and_then(|v| {
+                    let valid = Url::parse(&v).is_ok_and(|u| matches!(u.scheme(), "http" | "https"));
+                    if !valid {
+                        tracing::warn!("LIVE_SERVER_PUBLIC_URL 不是 http(s) 地址，已忽略: {}", v);
+                    }
+                    valid.then_some(v)
+                }),
+            frontend_tokens: var("LIVE_SERVER_FRONTEND_TOKENS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|pair| !pair.is_empty())
+                        .filter_map(|pair| {
+                            let token = FrontendToken::parse(pair);
+                            if token.is_none() {
+                                tracing::warn!("LIVE_SERVER_FRONTEND_TOKENS 中的 {} 格式应为 来源=令牌，已忽略", pair);
+                            }
+                            token
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            frontend_rate_per_min: env_parse("LIVE_SERVER_FRONTEND_RATE_LIMIT").unwrap_or(300),
+            stream_variants: var("LIVE_SERVER_STREAM_VARIANTS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            latency_mode: var("LIVE_SERVER_LATENCY_MODE")
+                .ok()
+                .and_then(|v| LatencyMode::parse(&v))
+                .unwrap_or(LatencyMode::Low),
+            publisher_login_policy: var("LIVE_SERVER_PUBLISHER_LOGIN_POLICY")
+                .ok()
+                .and_then(|v| PublisherLoginPolicy::parse(&v))
+                .unwrap_or(PublisherLoginPolicy::Open),
+            offline_connect: var("LIVE_SERVER_OFFLINE_CONNECT")
+                .ok()
+                .and_then(|v| OfflineConnect::parse(&v))
+                .unwrap_or(OfflineConnect::Issue),
+            offline_schedule: var("LIVE_SERVER_OFFLINE_SCHEDULE").ok().filter(|s| !s.is_empty()),
+            offline_landing_url: var("LIVE_SERVER_OFFLINE_LANDING_URL")
+                .ok()
+                .filter(|u| u.starts_with("http://") || u.starts_with("https://") || u.starts_with('/')),
+            chat_lobby_enabled: env_flag("LIVE_SERVER_CHAT_LOBBY"),
+            chat_uid_easter_egg: env_flag("LIVE_SERVER_CHAT_UID_EASTER_EGG"),
+            chat_presence_notices: env_flag("LIVE_SERVER_CHAT_PRESENCE"),
+            chat_verified_channel: env_flag("LIVE_SERVER_CHAT_VERIFIED_CHANNEL"),
+            chat_identity_retention_secs: env_parse("LIVE_SERVER_CHAT_IDENTITY_RETENTION").unwrap_or(1800),
+            chat_auto_reset: var("LIVE_SERVER_CHAT_AUTO_RESET")
+                .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "no"))
+                .unwrap_or(true),
+            audience_visibility: var("LIVE_SERVER_AUDIENCE_VISIBILITY")
+                .ok()
+                .and_then(|v| AudienceVisibility::parse(&v))
+                .unwrap_or(AudienceVisibility::Exact),
+            max_viewers: env_parse("LIVE_SERVER_MAX_VIEWERS").unwrap_or(0),
+            bandwidth_ceiling_kbps: env_parse("LIVE_SERVER_BANDWIDTH_CEILING_KBPS").unwrap_or(0),
+            away_after_secs: env_parse("LIVE_SERVER_AWAY_AFTER").unwrap_or(60),
+            away_notice: env_flag("LIVE_SERVER_AWAY_NOTICE"),
+            admission_priority: var("LIVE_SERVER_ADMISSION_PRIORITY")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .filter_map(|c| {
+                            let class = PriorityClass::parse(c);
+                            if class.is_none() {
+                                tracing::warn!("LIVE_SERVER_ADMISSION_PRIORITY 中的类别 {} 无法识别，已忽略", c);
+                            }
+                            class
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            stuck_pending_alert: env_parse("LIVE_SERVER_STUCK_PENDING_ALERT").unwrap_or(10),
+            quiz_failure_alert: env_parse::<u32>("LIVE_SERVER_QUIZ_FAILURE_ALERT").unwrap_or(80).min(100),
+            question_time_limit_secs: env_bounded("LIVE_SERVER_QUESTION_TIME_LIMIT", 45, 5, 55) as i64,
+            question_memory_secs: env_parse("LIVE_SERVER_QUESTION_MEMORY").unwrap_or(600),
+            question_refresh_limit: env_parse("LIVE_SERVER_QUESTION_REFRESH_LIMIT").unwrap_or(2),
+            question_refresh_cooldown_secs: env_parse("LIVE_SERVER_QUESTION_REFRESH_COOLDOWN").unwrap_or(30),
+            confirm_delay_secs: env_parse("LIVE_SERVER_CONFIRM_DELAY").unwrap_or(3),
+            quiz_min_pool: env_parse("LIVE_SERVER_QUIZ_MIN_POOL").unwrap_or(1),
+            quiz_fallback: var("LIVE_SERVER_QUIZ_FALLBACK")
+                .ok()
+                .and_then(|v| QuizFallback::parse(&v.to_lowercase()))
+                .unwrap_or(QuizFallback::Arithmetic),
+            cohort_policy: env_flag("LIVE_SERVER_COHORT_QUESTIONS").then(|| CohortPolicy {
+                prefix_v4: env_parse("LIVE_SERVER_COHORT_PREFIX_V4").unwrap_or(24).min(32),
+                prefix_v6: env_parse("LIVE_SERVER_COHORT_PREFIX_V6").unwrap_or(48).min(128),
+                rotation_secs: env_parse("LIVE_SERVER_COHORT_ROTATION").unwrap_or(600).max(1),
+                subset_size: env_parse("LIVE_SERVER_COHORT_SUBSET").unwrap_or(8),
+            }),
+            viewing_windows: var("LIVE_SERVER_VIEWING_WINDOWS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|w| !w.is_empty())
+                        .filter_map(|w| {
+                            let window = ViewingWindow::parse(w);
+                            if window.is_none() {
+                                tracing::warn!("LIVE_SERVER_VIEWING_WINDOWS 中的时段 {} 无法识别，已忽略", w);
+                            }
+                            window
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            country_header: var("LIVE_SERVER_COUNTRY_HEADER")
+                .ok()
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty()),
+            country_allow: env_country_list("LIVE_SERVER_COUNTRY_ALLOW"),
+            country_deny: env_country_list("LIVE_SERVER_COUNTRY_DENY"),
+            alumni_key: env_secret("LIVE_SERVER_ALUMNI_KEY"),
+            alumni_validity_days: env_parse("LIVE_SERVER_ALUMNI_DAYS").unwrap_or(30),
+            publisher_jwt_key: env_secret("LIVE_SERVER_PUBLISHER_JWT_KEY"),
+            publisher_jwt_hours: env_bounded("LIVE_SERVER_PUBLISHER_JWT_HOURS", 12, 1, 168) as i64,
+            relay_key: env_secret("LIVE_SERVER_RELAY_KEY"),
+            relay_targets: var("LIVE_SERVER_RELAY_TARGETS")
+                .map(|v| {
+                    v.split(',')
+                        .filter_map(|pair| pair.split_once('='))
+                        .map(|(name, sealed)| (name.trim().to_string(), sealed.trim().to_string()))
+                        .filter(|(name, sealed)| !name.is_empty() && !sealed.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ffmpeg_path: var("LIVE_SERVER_FFMPEG").unwrap_or_else(|_| "ffmpeg".to_string()),
+            upgrade_binary: var("LIVE_SERVER_UPGRADE_BINARY")
+                .ok()
+                .filter(|p| !p.trim().is_empty())
+                .map(PathBuf::from),
+            replication: match (var("LIVE_SERVER_REPLICATION_PRIMARY"), var("LIVE_SERVER_REPLICATION_LISTEN")) {
+                (Ok(primary), _) if !primary.trim().is_empty() => Some(ReplicationRole::Standby(primary.trim().to_string())),
+                (_, Ok(listen)) => listen.trim().parse().ok().map(ReplicationRole::Primary),
+                _ => None,
+            },
+            replication_key: env_secret("LIVE_SERVER_REPLICATION_KEY"),
+            mqtt: MqttTarget::from_env(),
+            mqtt_password: env_secret("LIVE_SERVER_MQTT_PASSWORD"),
+            smtp: SmtpTarget::from_env(),
+            smtp_password: env_secret("LIVE_SERVER_SMTP_PASSWORD"),
+            admin_token: env_secret("LIVE_SERVER_ADMIN_TOKEN"),
+            tip_hook_secret: env_secret("LIVE_SERVER_TIP_HOOK_SECRET"),
+            content_char_limit: env_parse("LIVE_SERVER_CONTENT_CHAR_LIMIT").unwrap_or(20),
+            intervals: Intervals::from_env(),
+            client_headers: var("LIVE_SERVER_CLIENT_HEADERS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|h| h.trim().to_lowercase())
+                        .filter(|h| !h.is_empty())
+                        .collect()
+                })
+                .unwrap_or_else(|_| vec!["user-agent".to_string()]),
+            poll: PollPolicy::from_env(),
+            features: var("LIVE_SERVER_FEATURES")
+                .map(|v| Features::parse(&v))
+                .unwrap_or_default(),
         }
     }
 
@@ -67,7 +1440,453 @@ impl Config {
         format!("{}:{}", self.host, self.port)
     }
 
+    /// 获取全部监听地址
+    ///
+    /// ### 返回值
+    /// 未配置 `LIVE_SERVER_LISTEN` 时为 `host:port` 上的全部服务
+    pub fn listeners(&self) -> Vec<Listen> {
+        if self.listen.is_empty() {
+            vec![Listen {
+                addr: SocketAddr::new(self.host, self.port),
+                services: Services::ALL,
+            }]
+        } else {
+            self.listen.clone()
+        }
+    }
+
     pub fn srs_api_addr(&self) -> String {
         format!("{}:{}", self.srs_api_host, self.srs_api_port)
     }
+
+    /// 读取配置文件（`LIVE_SERVER_CONFIG_FILE`），之后的 `from_env` 优先使用文件中的值
+    ///
+    /// ### 返回值
+    /// - `Ok(true)`: 已读取配置文件
+    /// - `Ok(false)`: 未设置配置文件
+    /// - `Err(msg)`: 读取配置文件失败（保留上一次读取的内容）
+    pub fn load_file() -> Result<bool, String> {
+        let Some(path) = env::var("LIVE_SERVER_CONFIG_FILE").ok().filter(|p| !p.is_empty()) else {
+            return Ok(false);
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("读取配置文件 {} 失败: {}", path, e))?;
+        let (entries, _) = parse_config_file(&content);
+        *FILE_OVERLAY.write() = Some(entries);
+        Ok(true)
+    }
+
+    /// 预览候选配置文件
+    ///
+    /// 以候选内容代替当前配置文件（环境变量照常生效）构建配置，与当前配置比较，不修改任何状态
+    ///
+    /// ### 参数
+    /// - `content`: 候选配置文件内容，格式同 `LIVE_SERVER_CONFIG_FILE`
+    ///
+    /// ### 返回值
+    /// 校验结果和重新加载后将发生的变更
+    pub fn preview(&self, content: &str) -> ConfigPreview {
+        let (entries, malformed_lines) = parse_config_file(content);
+        PREVIEW.set(Some(PreviewOverlay { entries, ..PreviewOverlay::default() }));
+        let candidate = Config::from_env();
+        let overlay = PREVIEW.take().unwrap_or_default();
+
+        let mut unknown_keys: Vec<String> = overlay
+            .entries
+            .into_keys()
+            .filter(|k| !overlay.read.contains(k))
+            .collect();
+        unknown_keys.sort();
+        let mut invalid_values = overlay.invalid;
+        invalid_values.sort();
+        invalid_values.dedup();
+
+        let (_, report) = self.merge_reload(candidate);
+        ConfigPreview {
+            valid: malformed_lines.is_empty() && unknown_keys.is_empty() && invalid_values.is_empty(),
+            malformed_lines,
+            unknown_keys,
+            invalid_values,
+            applied: report.applied,
+            restart_required: report.restart_required,
+            changes: report.changes,
+        }
+    }
+
+    /// 将重新加载的配置合并到当前配置
+    ///
+    /// 可热更新的配置项取新值，其余配置项保留当前值
+    ///
+    /// ### 返回值
+    /// (合并后的配置, 变更报告)
+    pub fn merge_reload(&self, new: Config) -> (Config, ReloadReport) {
+        let mut merged = self.clone();
+        let mut report = ReloadReport::default();
+
+        macro_rules! hot {
+            ($($field:ident),* $(,)?) => {$(
+                if merged.$field != new.$field {
+                    report.record_change(stringify!($field), &merged.$field, &new.$field, false);
+                    merged.$field = new.$field.clone();
+                    report.applied.push(stringify!($field));
+                }
+            )*};
+        }
+        macro_rules! cold {
+            ($($field:ident),* $(,)?) => {$(
+                if self.$field != new.$field {
+                    report.record_change(stringify!($field), &self.$field, &new.$field, true);
+                    report.restart_required.push(stringify!($field));
+                }
+            )*};
+        }
+
+        hot!(
+            log_sensitive,
+            log_level,
+            dump_min_free_bytes,
+            chat_url_policy,
+            chat_url_whitelist,
+            chat_embed_hosts,
+            chat_embed_user_per_min,
+            chat_embed_global_per_min,
+            chat_sample_threshold,
+            chat_sample_every,
+            report_threshold,
+            chat_presence_notices,
+            chat_verified_channel,
+            chat_identity_retention_secs,
+            chat_auto_reset,
+            audience_visibility,
+            max_viewers,
+            bandwidth_ceiling_kbps,
+            away_after_secs,
+            away_notice,
+            srs_watchdog_heal,
+            admission_priority,
+            stuck_pending_alert,
+            quiz_failure_alert,
+            question_time_limit_secs,
+            question_memory_secs,
+            question_refresh_limit,
+            question_refresh_cooldown_secs,
+            confirm_delay_secs,
+            quiz_min_pool,
+            quiz_fallback,
+            cohort_policy,
+            viewing_windows,
+            country_header,
+            country_allow,
+            country_deny,
+            publisher_login_policy,
+            offline_connect,
+            offline_schedule,
+            offline_landing_url,
+            features,
+            client_headers,
+            poll,
+            push_host,
+            rtmp_port,
+            srt_port,
+            stream_variants,
+            decoy_stream,
+            public_url,
+            frontend_tokens,
+            frontend_rate_per_min,
+            latency_mode,
+            srs_callback_allow,
+            srs_callback_lab,
+            publish_apps,
+            publish_streams,
+            relay_targets,
+            ffmpeg_path,
+            upgrade_binary,
+        );
+        if !secret_opt_eq(&merged.admin_token, &new.admin_token) {
+            merged.admin_token = new.admin_token.clone();
+            report.applied.push("admin_token");
+        }
+        if !secret_opt_eq(&merged.tip_hook_secret, &new.tip_hook_secret) {
+            merged.tip_hook_secret = new.tip_hook_secret.clone();
+            report.applied.push("tip_hook_secret");
+        }
+        if !secret_opt_eq(&merged.srs_callback_token, &new.srs_callback_token) {
+            merged.srs_callback_token = new.srs_callback_token.clone();
+            report.applied.push("srs_callback_token");
+        }
+
+        cold!(
+            host,
+            port,
+            listen,
+            base_path,
+            banner_db_path,
+            banner_db_url,
+            banner_lenient,
+            banner_min_questions,
+            question_templates_path,
+            script_path,
+            trusted_user_header,
+            trusted_proxies,
+            oidc_userinfo_url,
+            dump_path,
+            secret_path,
+            srs_api_host,
+            srs_api_port,
+            srs_self_check,
+            srs_vhost,
+            srs_callback_echo_path,
+            srs_app,
+            chat_lobby_enabled,
+            chat_uid_easter_egg,
+            alumni_validity_days,
+            publisher_jwt_hours,
+            content_char_limit,
+            intervals,
+            replication,
+            mqtt,
+            smtp,
+        );
+        let captcha_eq = match (&self.captcha, &new.captcha) {
+            (Some((pa, sa)), Some((pb, sb))) => pa == pb && sa.expose_secret() == sb.expose_secret(),
+            (None, None) => true,
+            _ => false,
+        };
+        if !captcha_eq {
+            report.restart_required.push("captcha");
+        }
+        if !secret_opt_eq(&self.alumni_key, &new.alumni_key) {
+            report.restart_required.push("alumni_key");
+        }
+        if !secret_opt_eq(&self.publisher_jwt_key, &new.publisher_jwt_key) {
+            report.restart_required.push("publisher_jwt_key");
+        }
+        if !secret_opt_eq(&self.relay_key, &new.relay_key) {
+            report.restart_required.push("relay_key");
+        }
+        if !secret_opt_eq(&self.replication_key, &new.replication_key) {
+            report.restart_required.push("replication_key");
+        }
+        if !secret_opt_eq(&self.mqtt_password, &new.mqtt_password) {
+            report.restart_required.push("mqtt_password");
+        }
+        if !secret_opt_eq(&self.smtp_password, &new.smtp_password) {
+            report.restart_required.push("smtp_password");
+        }
+
+        (merged, report)
+    }
+}
+
+/// 配置热重载报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReloadReport {
+    /// 已生效的配置项
+    pub applied: Vec<&'static str>,
+    /// 已变更但需重启服务才能生效的配置项
+    pub restart_required: Vec<&'static str>,
+    /// 变更前后的值（不含密钥类配置项，仅供预览）
+    #[serde(skip)]
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ReloadReport {
+    /// 记录一个配置项变更前后的值
+    fn record_change<T: std::fmt::Debug>(&mut self, field: &'static str, current: &T, candidate: &T, restart_required: bool) {
+        self.changes.push(ConfigChange {
+            field,
+            current: format!("{:?}", current),
+            candidate: format!("{:?}", candidate),
+            restart_required,
+        });
+    }
+}
+
+/// 单个配置项的变更
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    /// 配置项名称
+    pub field: &'static str,
+    /// 当前值
+    pub current: String,
+    /// 候选配置中的值
+    pub candidate: String,
+    /// 是否需要重启服务才能生效
+    pub restart_required: bool,
+}
+
+/// 候选配置的预览结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigPreview {
+    /// 候选配置能否直接使用（没有格式错误的行、未知配置项和无法解析的值）
+    pub valid: bool,
+    /// 不是 `KEY=VALUE` 形式的行（行号从 1 开始）
+    pub malformed_lines: Vec<usize>,
+    /// 服务不读取的配置项（通常是拼写错误）
+    pub unknown_keys: Vec<String>,
+    /// 值无法解析、将使用默认值的配置项
+    pub invalid_values: Vec<String>,
+    /// 重新加载后立即生效的配置项
+    pub applied: Vec<&'static str>,
+    /// 需要重启服务才能生效的配置项
+    pub restart_required: Vec<&'static str>,
+    /// 变更前后的值（密钥类配置项只在上面两个列表中列出名称）
+    pub changes: Vec<ConfigChange>,
+}
+
+/// 预览候选配置期间代替配置文件的配置项
+#[derive(Debug, Default)]
+struct PreviewOverlay {
+    /// 候选配置中的配置项
+    entries: HashMap<String, String>,
+    /// `from_env` 读取过的配置项
+    read: HashSet<String>,
+    /// 值无法解析的配置项
+    invalid: Vec<String>,
+}
+
+thread_local! {
+    /// 当前线程正在预览的候选配置（只在 `Config::preview` 执行期间设置）
+    static PREVIEW: RefCell<Option<PreviewOverlay>> = const { RefCell::new(None) };
+}
+
+/// 解析 `KEY=VALUE` 形式的配置文件内容
+///
+/// ### 返回值
+/// (配置项, 格式错误的行号)
+fn parse_config_file(content: &str) -> (HashMap<String, String>, Vec<usize>) {
+    let mut entries = HashMap::new();
+    let mut malformed = Vec::new();
+    for (no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((k, v)) => {
+                entries.insert(k.trim().to_string(), v.trim().trim_matches('"').to_string());
+            }
+            None => malformed.push(no + 1),
+        }
+    }
+    (entries, malformed)
+}
+
+/// 比较两个可选密钥是否相同
+fn secret_opt_eq(a: &Option<SecretString>, b: &Option<SecretString>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.expose_secret() == b.expose_secret(),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// 读取配置项：配置文件中的值优先，其次为环境变量
+///
+/// 预览候选配置期间以候选配置代替配置文件，并记录读取过的配置项
+fn var(key: &str) -> Result<String, env::VarError> {
+    let preview = PREVIEW.with_borrow_mut(|preview| {
+        preview.as_mut().map(|p| {
+            p.read.insert(key.to_string());
+            p.entries.get(key).cloned()
+        })
+    });
+    match preview {
+        Some(Some(value)) => return Ok(value),
+        Some(None) => return env::var(key),
+        None => {}
+    }
+    if let Some(value) = FILE_OVERLAY.read().as_ref().and_then(|m| m.get(key)) {
+        return Ok(value.clone());
+    }
+    env::var(key)
+}
+
+/// 读取布尔型环境变量（`true`/`1`/`yes` 视为开启，大小写不敏感）
+fn env_flag(key: &str) -> bool {
+    var(key)
+        .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(false)
+}
+
+/// 读取并解析环境变量，缺失或格式错误时返回 `None`
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    let value = var(key).ok()?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        PREVIEW.with_borrow_mut(|preview| {
+            if let Some(p) = preview.as_mut() {
+                p.invalid.push(key.to_string());
+            }
+        });
+    }
+    parsed
+}
+
+/// 读取有范围限制的整数环境变量
+///
+/// 缺失或格式错误时使用默认值，超出范围时截断到边界并输出警告
+fn env_bounded(key: &str, default: u64, min: u64, max: u64) -> u64 {
+    let value = env_parse(key).unwrap_or(default);
+    let clamped = value.clamp(min, max);
+    if clamped != value {
+        tracing::warn!("{}={} 超出范围 [{}, {}]，已调整为 {}", key, value, min, max, clamped);
+    }
+    clamped
+}
+
+/// 读取密钥类环境变量，空值视为未设置
+///
+/// 返回的 `SecretString` 在 `Debug` 输出中显示为 `[REDACTED]`
+fn env_secret(key: &str) -> Option<SecretString> {
+    var(key)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map(SecretString::from)
+}
+
+/// 读取逗号分隔的域名列表（转为小写，去掉开头的 `.`）
+fn env_domains(key: &str) -> Vec<String> {
+    var(key)
+        .map(|v| {
+            v.split(',')
+                .map(|d| d.trim().trim_start_matches('.').to_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 读取逗号分隔的 IP 地址段列表，无法解析的条目输出警告后忽略
+///
+/// ### 返回值
+/// 环境变量未设置时返回 `None`
+fn env_ip_ranges(key: &str) -> Option<Vec<IpRange>> {
+    let value = var(key).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .filter_map(|r| {
+                let range = IpRange::parse(r);
+                if range.is_none() {
+                    tracing::warn!("{} 中的地址段 {} 无法解析，已忽略", key, r);
+                }
+                range
+            })
+            .collect(),
+    )
+}
+
+/// 读取逗号分隔的国家/地区代码列表（转为大写）
+fn env_country_list(key: &str) -> Vec<String> {
+    var(key)
+        .map(|v| {
+            v.split(',')
+                .map(|c| c.trim().to_ascii_uppercase())
+                .filter(|c| !c.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
 }
@@ -0,0 +1,213 @@
+//! # 领域类型模块
+//!
+//! 为服务内到处传递的几类键提供独立的类型，避免互相混用：
+//! - `ClientIp` - 客户端 IP（规范化后的文本形式，不含端口）
+//! - `SessionId` - 客户端自行生成的会话 ID
+//! - `Uid` - 聊天室内的用户 ID
+//!
+//! 外部输入在进入状态模块之前完成解析和校验，状态模块只接受这些类型。
+
+use crate::config::IpRange;
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// 会话 ID 最大长度（字节）
+pub const MAX_SESSION_ID_LEN: usize = 128;
+
+/// 客户端 IP
+///
+/// 只能通过解析合法的 IP 地址得到，IPv4 映射的 IPv6 地址（`::ffff:a.b.c.d`）规范化为 IPv4 形式
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct ClientIp(String);
+
+impl ClientIp {
+    /// 解析 IP 地址文本
+    ///
+    /// ### 返回值
+    /// 不是合法的 IPv4 / IPv6 地址时返回 `None`
+    pub fn parse(s: &str) -> Option<Self> {
+        s.trim().parse::<IpAddr>().ok().map(Self::from)
+    }
+
+    /// 从请求中提取客户端 IP
+    ///
+    /// 只有连接的对端属于受信任代理时才采用 `X-Forwarded-For`，否则直接使用对端地址（不含端口），
+    /// 防止客户端伪造该头绕过按 IP 的限制
+    ///
+    /// ### 参数
+    /// - `headers`: 请求头
+    /// - `peer`: 连接的对端地址
+    /// - `trusted_proxies`: 受信任的反向代理地址段
+    pub fn from_request(headers: &HeaderMap, peer: SocketAddr, trusted_proxies: &[IpRange]) -> Self {
+        let trusted = |ip: IpAddr| trusted_proxies.iter().any(|range| range.contains(ip));
+        if !trusted(peer.ip()) {
+            return Self::from(peer.ip());
+        }
+        // X-Forwarded-For 形如 `客户端, 代理1, 代理2`，靠左的条目可由客户端伪造，
+        // 因此从右向左跳过受信任代理，取第一个不受信任的地址
+        let mut chain = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|s| s.split(','))
+            .map(|s| s.trim().parse::<IpAddr>().ok())
+            .collect::<Vec<_>>();
+        let mut client = None;
+        while let Some(hop) = chain.pop() {
+            match hop {
+                Some(ip) if trusted(ip) => client = Some(ip),
+                Some(ip) => return Self::from(ip),
+                // 无法解析的条目之后的地址都不可信
+                None => break,
+            }
+        }
+        Self::from(client.unwrap_or(peer.ip()))
+    }
+
+    /// 以文本形式获取
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 获取 IP 地址
+    pub fn addr(&self) -> IpAddr {
+        self.0.parse().expect("ClientIp 只能由合法地址构造")
+    }
+}
+
+impl From<IpAddr> for ClientIp {
+    fn from(ip: IpAddr) -> Self {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(IpAddr::V6(v6)),
+            v4 => v4,
+        };
+        Self(ip.to_string())
+    }
+}
+
+impl FromStr for ClientIp {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or("invalid ip address")
+    }
+}
+
+impl<'de> Deserialize<'de> for ClientIp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 会话 ID
+///
+/// 由客户端生成并附加在拉流地址的查询参数中，因此限制为不含查询参数分隔符的可见 ASCII 字符
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct SessionId(String);
+
+impl SessionId {
+    /// 解析会话 ID
+    ///
+    /// ### 返回值
+    /// - `Ok(session_id)`: 合法的会话 ID
+    /// - `Err(reason)`: 为空、过长或含有不允许的字符
+    pub fn parse(s: &str) -> Result<Self, &'static str> {
+        if s.is_empty() {
+            return Err("empty session_id");
+        }
+        if s.len() > MAX_SESSION_ID_LEN {
+            return Err("session_id too long");
+        }
+        if !s.chars().all(|c| c.is_ascii_graphic() && !matches!(c, '&' | '=' | '?' | '#')) {
+            return Err("invalid character in session_id");
+        }
+        Ok(Self(s.to_string()))
+    }
+
+    /// 以文本形式获取
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for SessionId {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for SessionId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 聊天室用户 ID
+///
+/// 序列化为数字，与原先的 `u32` 表示兼容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Uid(pub u32);
+
+impl FromStr for Uid {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse().map(Self)
+    }
+}
+
+/// 为文本类型实现显示、借用和比较
+macro_rules! text_key {
+    ($ty:ty) => {
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl AsRef<str> for $ty {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        // 哈希与 `str` 一致，可以直接用 `&str` 查询以该类型为键的映射
+        impl Borrow<str> for $ty {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $ty {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $ty {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+text_key!(ClientIp);
+text_key!(SessionId);
+
+impl fmt::Display for Uid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -0,0 +1,161 @@
+//! # 聊天转储合并模块
+//!
+//! `rusty-live-server dump-merge <转储.json> <日志.jsonl> [输出.json]` 子命令：
+//! 将崩溃恢复日志（每行一条序列化的 `ChatEntry`）与不完整的 `dump_full` 转储合并为一份完整的归档。
+//!
+//! ## 合并规则
+//! - 旧版转储先升级到当前格式版本（见 `dump_schema`）
+//! - 转储中的其他字段（用户映射、打赏、统计等）原样保留
+//! - 消息按 ID 去重，旧版转储中没有 ID 的记录按 (uid, 类型, 内容, 时间) 去重
+//! - 同一条消息同时出现在转储和日志中时以转储为准（转储包含删除标记）
+//! - 日志末尾因崩溃写了一半的行被跳过并计数
+//! - 合并后的记录按时间排序，同一秒内按消息 ID（ULID）排序
+//!
+//! 输出文件默认写到转储文件旁（`<转储>.merged.json`），已存在时拒绝覆盖。
+
+use crate::domain::{ClientIp, Uid};
+use crate::dump_schema;
+use crate::state::chat::ChatEntry;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 合并结果统计
+#[derive(Debug, Default)]
+pub struct MergeStats {
+    /// 转储中的记录数
+    pub dumped: usize,
+    /// 日志中成功解析的消息数
+    pub journaled: usize,
+    /// 被去重丢弃的记录数
+    pub duplicates: usize,
+    /// 日志中无法解析的行数
+    pub malformed: usize,
+    /// 合并后的记录数
+    pub total: usize,
+}
+
+/// 执行 `dump-merge` 子命令
+///
+/// ### 参数
+/// - `args`: 子命令之后的命令行参数（转储路径、日志路径、可选的输出路径）
+///
+/// ### 返回值
+/// - `Ok(message)`: 合并完成的摘要
+/// - `Err(msg)`: 参数错误、读取、解析或写入失败的原因
+pub fn run(args: &[String]) -> Result<String, String> {
+    let (dump_path, journal_path) = match args {
+        [dump, journal] | [dump, journal, _] => (Path::new(dump), Path::new(journal)),
+        _ => return Err("用法: rusty-live-server dump-merge <转储.json> <日志.jsonl> [输出.json]".to_string()),
+    };
+    let output = match args.get(2) {
+        Some(path) => PathBuf::from(path),
+        None => dump_path.with_extension("merged.json"),
+    };
+
+    let dump = fs::read_to_string(dump_path).map_err(|e| format!("读取转储 {} 失败: {}", dump_path.display(), e))?;
+    let dump: Value =
+        serde_json::from_str(&dump).map_err(|e| format!("解析转储 {} 失败: {}", dump_path.display(), e))?;
+    let journal =
+        fs::read_to_string(journal_path).map_err(|e| format!("读取日志 {} 失败: {}", journal_path.display(), e))?;
+
+    let (merged, stats) = merge(dump, &journal)?;
+
+    let content = serde_json::to_string_pretty(&merged).map_err(|e| format!("序列化归档失败: {}", e))?;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&output)
+        .map_err(|e| format!("创建归档 {} 失败: {}", output.display(), e))?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("写入归档 {} 失败: {}", output.display(), e))?;
+
+    Ok(format!(
+        "合并完成: 转储 {} 条，日志 {} 条（重复 {} 条，无法解析 {} 行），共 {} 条 → {}",
+        stats.dumped,
+        stats.journaled,
+        stats.duplicates,
+        stats.malformed,
+        stats.total,
+        output.display()
+    ))
+}
+
+/// 合并转储与崩溃恢复日志
+///
+/// ### 参数
+/// - `dump`: `dump_full` 输出的转储数据
+/// - `journal`: 日志文件内容（JSONL）
+///
+/// ### 返回值
+/// - `Ok((archive, stats))`: 合并后的归档和统计
+/// - `Err(msg)`: 转储不是 `dump_full` 的格式或格式版本过新
+pub fn merge(dump: Value, journal: &str) -> Result<(Value, MergeStats), String> {
+    let (mut dump, _) = dump_schema::migrate(dump)?;
+    let records = match dump.get_mut("records").map(Value::take) {
+        Some(Value::Array(records)) => records,
+        _ => return Err("转储中缺少 records 数组，不是完整转储".to_string()),
+    };
+    let mut stats = MergeStats { dumped: records.len(), ..MergeStats::default() };
+
+    // 日志中的消息只记录了 UID，昵称取自转储的用户映射，IP 取自转储中同一用户的其他记录
+    let names: HashMap<Uid, String> = dump
+        .get("umap")
+        .cloned()
+        .and_then(|umap| serde_json::from_value(umap).ok())
+        .unwrap_or_default();
+    let ips: HashMap<Uid, ClientIp> = records
+        .iter()
+        .filter_map(|r| {
+            let uid = serde_json::from_value(r["uid"].clone()).ok()?;
+            let ip = serde_json::from_value(r["ip"].clone()).ok()?;
+            Some((uid, ip))
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut merged = Vec::with_capacity(records.len());
+    for record in records {
+        if seen.insert(record_key(&record)) {
+            merged.push(record);
+        } else {
+            stats.duplicates += 1;
+        }
+    }
+    for line in journal.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(entry) = serde_json::from_str::<ChatEntry>(line) else {
+            stats.malformed += 1;
+            continue;
+        };
+        stats.journaled += 1;
+        let record = entry.dump_record(names.get(&entry.uid), ips.get(&entry.uid), false);
+        if seen.insert(record_key(&record)) {
+            merged.push(record);
+        } else {
+            stats.duplicates += 1;
+        }
+    }
+
+    // 时间为秒级精度的 ISO 8601 文本，可直接按字符串排序；稳定排序保留同一秒内旧记录的原有顺序
+    merged.sort_by(|a, b| sort_key(a).cmp(&sort_key(b)));
+    stats.total = merged.len();
+    dump["records"] = Value::Array(merged);
+    Ok((dump, stats))
+}
+
+/// 记录的去重键
+///
+/// 有消息 ID 时使用 ID，否则（旧版转储）使用 (uid, 类型, 内容, 时间)
+fn record_key(record: &Value) -> String {
+    match record["id"].as_str().filter(|id| !id.is_empty()) {
+        Some(id) => format!("id:{}", id),
+        None => format!("{}|{}|{}|{}", record["uid"], record["kind"], record["content"], record["date"]),
+    }
+}
+
+/// 记录的排序键：(时间, 消息 ID)
+fn sort_key(record: &Value) -> (&str, &str) {
+    (record["date"].as_str().unwrap_or_default(), record["id"].as_str().unwrap_or_default())
+}
@@ -0,0 +1,249 @@
+//! # 聊天转储格式模块
+//!
+//! 聊天转储（`live-<场次>-<时间>-<序号>.json`，含 `dump-merge` 输出的 `.merged.json`）的格式说明与读取接口，
+//! 供下游工具直接反序列化，不必从 JSON 样本中猜测字段。
+//!
+//! ## 版本
+//! 每份转储的顶层带有 `schema_version`，格式发生不兼容的变化时递增：
+//! - 0：没有 `schema_version` 字段的旧版转储（早期版本可能缺少消息 ID、场次、删除标记、打赏、统计和排行榜）
+//! - 1：当前版本，在版本 0 的基础上补齐全部顶层字段
+//!
+//! `read` 读取任意已知版本的转储并升级到当前版本；
+//! `rusty-live-server dump-migrate <转储.json> [输出.json]` 子命令将旧版转储升级后另存。
+//!
+//! 新增的可选字段不递增版本号，读取结构体对缺失的字段使用默认值，并忽略未知字段。
+
+use crate::domain::{ClientIp, SessionId, Uid};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 当前的转储格式版本
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 各版本升级到下一版本的步骤（下标为起始版本）
+const MIGRATIONS: [fn(&mut serde_json::Map<String, Value>); SCHEMA_VERSION as usize] = [migrate_v0];
+
+/// 一份聊天转储
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatDump {
+    /// 转储格式版本
+    pub schema_version: u32,
+    /// 聊天室 ID
+    pub stream_id: String,
+    /// 直播场次 ID（离线大厅为 `None`）
+    #[serde(default)]
+    pub stream_session_id: Option<String>,
+    /// UID -> 昵称
+    #[serde(default)]
+    pub umap: HashMap<Uid, String>,
+    /// 客户端 IP -> 会话 ID -> 身份
+    #[serde(default)]
+    pub cmap: HashMap<ClientIp, HashMap<SessionId, DumpIdentity>>,
+    /// 消息记录（按时间先后）
+    #[serde(default)]
+    pub records: Vec<DumpRecord>,
+    /// 打赏记录
+    #[serde(default)]
+    pub tips: Vec<DumpTip>,
+    /// UID -> 参与统计
+    #[serde(default)]
+    pub stats: HashMap<Uid, DumpChatterStats>,
+    /// 排行榜（只含愿意公开排名的用户）
+    #[serde(default)]
+    pub leaderboard: Vec<DumpLeaderboardEntry>,
+}
+
+/// 转储中的用户身份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpIdentity {
+    /// 用户 ID
+    pub uid: Uid,
+    /// 昵称
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 是否愿意公开进出直播间的提示
+    #[serde(default)]
+    pub announce: bool,
+    /// 是否愿意出现在排行榜中
+    #[serde(default)]
+    pub ranked: bool,
+    /// 该用户屏蔽的 UID
+    #[serde(default)]
+    pub blocked: Vec<Uid>,
+    /// 观众偏好设置
+    #[serde(default)]
+    pub prefs: BTreeMap<String, String>,
+}
+
+/// 转储中的单条消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpRecord {
+    /// 消息 ID（ULID，旧版转储中可能缺失）
+    #[serde(default)]
+    pub id: Option<String>,
+    /// 发送者 UID（系统消息为 0）
+    pub uid: Uid,
+    /// 直播场次 ID
+    #[serde(default)]
+    pub session: Option<String>,
+    /// 消息类型：`chat` / `system` 等
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// 发送者昵称
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 发送者 IP
+    #[serde(default)]
+    pub ip: Option<ClientIp>,
+    /// 消息内容（已删除且未导出原始内容时为 `None`）
+    #[serde(default)]
+    pub content: Option<String>,
+    /// 发送时间（ISO 8601，秒级精度）
+    pub date: String,
+    /// 删除标记
+    #[serde(default)]
+    pub deleted: Option<DumpTombstone>,
+}
+
+/// 转储中的删除标记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpTombstone {
+    /// 执行删除的管理者 UID
+    pub by: Uid,
+    /// 删除时间（ISO 8601，秒级精度）
+    pub date: String,
+}
+
+/// 转储中的打赏记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpTip {
+    /// 打赏者名称
+    pub name: String,
+    /// 打赏金额
+    pub amount: f64,
+    /// 货币单位
+    pub currency: String,
+    /// 打赏留言
+    #[serde(default)]
+    pub message: Option<String>,
+    /// 打赏时间戳（Unix 时间戳，秒）
+    pub stamp: f64,
+}
+
+/// 转储中的用户参与统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpChatterStats {
+    /// 发送的消息数
+    pub messages: u32,
+    /// 首次出现的时间戳（Unix 时间戳，秒）
+    pub first_seen: f64,
+    /// 答题用时（秒）
+    #[serde(default)]
+    pub answer_secs: Option<f64>,
+}
+
+/// 转储中的排行榜条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpLeaderboardEntry {
+    /// 用户昵称
+    pub name: String,
+    /// 发送的消息数
+    pub messages: u32,
+    /// 首次出现的时间戳（Unix 时间戳，秒）
+    pub first_seen: f64,
+    /// 答题用时（秒）
+    #[serde(default)]
+    pub answer_secs: Option<f64>,
+}
+
+/// 读取转储内容并升级到当前版本
+///
+/// ### 返回值
+/// - `Ok(dump)`: 解析后的转储
+/// - `Err(msg)`: 不是 JSON、版本过新或缺少必需字段
+pub fn read(content: &str) -> Result<ChatDump, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("解析转储失败: {}", e))?;
+    let (value, _) = migrate(value)?;
+    serde_json::from_value(value).map_err(|e| format!("转储格式不符: {}", e))
+}
+
+/// 转储数据的格式版本（没有 `schema_version` 字段时为 0）
+pub fn version_of(dump: &Value) -> u64 {
+    dump.get("schema_version").and_then(Value::as_u64).unwrap_or(0)
+}
+
+/// 将转储数据升级到当前版本
+///
+/// 只改动版本之间有差异的字段，其余字段（包括读取结构体未定义的字段）原样保留
+///
+/// ### 返回值
+/// - `Ok((dump, from))`: 升级后的数据和原来的版本
+/// - `Err(msg)`: 不是转储对象或版本比本程序支持的更新
+pub fn migrate(mut dump: Value) -> Result<(Value, u64), String> {
+    let from = version_of(&dump);
+    if from > SCHEMA_VERSION as u64 {
+        return Err(format!("转储格式版本 {} 比本程序支持的版本 {} 更新，请升级程序", from, SCHEMA_VERSION));
+    }
+    let obj = dump.as_object_mut().ok_or_else(|| "转储不是 JSON 对象".to_string())?;
+    for step in &MIGRATIONS[from as usize..] {
+        step(obj);
+    }
+    obj.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+    Ok((dump, from))
+}
+
+/// 版本 0 → 1：补齐早期转储缺少的顶层字段
+fn migrate_v0(dump: &mut serde_json::Map<String, Value>) {
+    for key in ["umap", "cmap", "stats"] {
+        dump.entry(key).or_insert_with(|| Value::Object(Default::default()));
+    }
+    for key in ["records", "tips", "leaderboard"] {
+        dump.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+    }
+    dump.entry("stream_session_id").or_insert(Value::Null);
+}
+
+/// 执行 `dump-migrate` 子命令
+///
+/// ### 参数
+/// - `args`: 子命令之后的命令行参数（转储路径、可选的输出路径）
+///
+/// ### 返回值
+/// - `Ok(message)`: 升级完成或无需升级的摘要
+/// - `Err(msg)`: 参数错误、读取、解析或写入失败的原因
+pub fn run(args: &[String]) -> Result<String, String> {
+    let dump_path = match args {
+        [dump] | [dump, _] => Path::new(dump),
+        _ => return Err("用法: rusty-live-server dump-migrate <转储.json> [输出.json]".to_string()),
+    };
+    let output = match args.get(1) {
+        Some(path) => PathBuf::from(path),
+        None => dump_path.with_extension("migrated.json"),
+    };
+
+    let content = fs::read_to_string(dump_path).map_err(|e| format!("读取转储 {} 失败: {}", dump_path.display(), e))?;
+    let dump: Value =
+        serde_json::from_str(&content).map_err(|e| format!("解析转储 {} 失败: {}", dump_path.display(), e))?;
+    let (migrated, from) = migrate(dump)?;
+    // 升级结果须能按当前版本读取
+    serde_json::from_value::<ChatDump>(migrated.clone())
+        .map_err(|e| format!("转储 {} 不符合格式版本 {}: {}", dump_path.display(), SCHEMA_VERSION, e))?;
+    if from == SCHEMA_VERSION as u64 {
+        return Ok(format!("{} 已是当前格式版本 {}，无需升级", dump_path.display(), SCHEMA_VERSION));
+    }
+
+    let content = serde_json::to_string_pretty(&migrated).map_err(|e| format!("序列化转储失败: {}", e))?;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&output)
+        .map_err(|e| format!("创建转储 {} 失败: {}", output.display(), e))?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("写入转储 {} 失败: {}", output.display(), e))?;
+
+    Ok(format!("升级完成: 格式版本 {} → {} → {}", from, SCHEMA_VERSION, output.display()))
+}
@@ -4,12 +4,53 @@
 //! 包括 API 错误响应、SRS 回调响应和聊天室禁止响应。
 
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Json, Response},
 };
+use serde::Serialize;
 use serde_json::json;
 use std::fmt;
 
+/// API 错误码
+///
+/// 序列化为 `SCREAMING_SNAKE_CASE`，前端应根据错误码而非错误文本判断错误类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApiErrorCode {
+    /// 无权限执行该操作
+    Forbidden,
+    /// 请求的资源不存在
+    NotFound,
+    /// 请求格式错误或参数无效
+    BadRequest,
+    /// 服务器内部错误
+    Internal,
+    /// 客户端不处于待答题状态
+    NotPending,
+    /// 客户端已通过验证，无需再次答题
+    AlreadyAnswered,
+    /// 直播未在进行
+    StreamOffline,
+    /// 触发频率限制
+    RateLimited,
+}
+
+impl ApiErrorCode {
+    /// 将错误码转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Forbidden => "FORBIDDEN",
+            Self::NotFound => "NOT_FOUND",
+            Self::BadRequest => "BAD_REQUEST",
+            Self::Internal => "INTERNAL",
+            Self::NotPending => "NOT_PENDING",
+            Self::AlreadyAnswered => "ALREADY_ANSWERED",
+            Self::StreamOffline => "STREAM_OFFLINE",
+            Self::RateLimited => "RATE_LIMITED",
+        }
+    }
+}
+
 /// API 错误枚举
 ///
 /// 定义了应用程序中可能出现的各种错误类型
@@ -23,17 +64,77 @@ pub enum ApiError {
     BadRequest(String),
     /// 500 内部错误 - 服务器端发生未预期的错误
     Internal(String),
+    /// 409 不处于待答题状态
+    NotPending,
+    /// 409 已通过验证，无需再次答题
+    AlreadyAnswered,
+    /// 409 直播未在进行
+    StreamOffline,
+    /// 429 请求过多 - 客户端触发了频率限制
+    RateLimited {
+        /// 建议的重试等待时间（秒）
+        retry_after: u64,
+    },
+}
+
+impl ApiError {
+    /// 获取错误码
+    pub fn code(&self) -> ApiErrorCode {
+        match self {
+            ApiError::Forbidden(_) => ApiErrorCode::Forbidden,
+            ApiError::NotFound(_) => ApiErrorCode::NotFound,
+            ApiError::BadRequest(_) => ApiErrorCode::BadRequest,
+            ApiError::Internal(_) => ApiErrorCode::Internal,
+            ApiError::NotPending => ApiErrorCode::NotPending,
+            ApiError::AlreadyAnswered => ApiErrorCode::AlreadyAnswered,
+            ApiError::StreamOffline => ApiErrorCode::StreamOffline,
+            ApiError::RateLimited { .. } => ApiErrorCode::RateLimited,
+        }
+    }
+
+    /// 获取 HTTP 状态码
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::NotPending | ApiError::AlreadyAnswered | ApiError::StreamOffline => {
+                StatusCode::CONFLICT
+            }
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// 获取面向用户的错误文本
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::Forbidden(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::BadRequest(msg)
+            | ApiError::Internal(msg) => msg.clone(),
+            ApiError::NotPending => "Not in pending state".to_string(),
+            ApiError::AlreadyAnswered => "Already answered".to_string(),
+            ApiError::StreamOffline => "Stream is offline".to_string(),
+            ApiError::RateLimited { retry_after } => {
+                format!("Too many attempts, retry in {}s", retry_after)
+            }
+        }
+    }
+
+    /// 获取建议的重试等待时间（秒）
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ApiError::RateLimited { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
 }
 
 /// 实现 Display trait，支持错误信息格式化输出
 impl fmt::Display for ApiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ApiError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
-            ApiError::NotFound(msg) => write!(f, "Not Found: {}", msg),
-            ApiError::BadRequest(msg) => write!(f, "Bad Request: {}", msg),
-            ApiError::Internal(msg) => write!(f, "Internal Error: {}", msg),
-        }
+        write!(f, "{}: {}", self.code().as_str(), self.message())
     }
 }
 
@@ -41,19 +142,25 @@ impl fmt::Display for ApiError {
 impl std::error::Error for ApiError {}
 
 /// 实现 IntoResponse trait，将 ApiError 转换为 HTTP 响应
+///
+/// ### 响应格式
+/// ```json
+/// {"error": "Too many attempts, retry in 5s", "code": "RATE_LIMITED", "retry_after": 5}
+/// ```
+/// `error` 字段保持与旧版本兼容，`retry_after` 仅在频率限制时出现，并同时设置 `Retry-After` 响应头
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        // 根据错误类型确定 HTTP 状态码和消息
-        let (status, message) = match &self {
-            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
-            ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
-        };
-
-        // 将错误信息包装成 JSON 响应
-        let body = json!({ "error": message });
-        (status, Json(body)).into_response()
+        let mut body = json!({
+            "error": self.message(),
+            "code": self.code(),
+        });
+        match self.retry_after() {
+            Some(secs) => {
+                body["retry_after"] = json!(secs);
+                (self.status(), [(header::RETRY_AFTER, secs.to_string())], Json(body)).into_response()
+            }
+            None => (self.status(), Json(body)).into_response(),
+        }
     }
 }
 
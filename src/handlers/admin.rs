@@ -0,0 +1,968 @@
+//! # 管理接口处理器模块
+//!
+//! 供运维人员和题库维护者使用的管理接口，均需携带管理令牌：
+//! - 请求头 `Authorization: Bearer <令牌>`，或
+//! - 查询参数 `admin_token=<令牌>`
+//!
+//! 未配置 `LIVE_SERVER_ADMIN_TOKEN` 时所有管理接口均返回 403。
+
+use super::super::{
+    config::IpRange,
+    domain::SessionId,
+    error::ApiError,
+    logging, redact,
+    state::{
+        banner::{BannerReport, QuestionKind},
+        health::{self, TaskStatus, MIN_DISK_BYTES},
+        push_url::{self, PushTarget, QrFormat},
+        resources::ResourceUsage,
+        secret_guard::secret_eq,
+        srs::{ClientStatus, EntryMode},
+        streaming_info::StreamEgress,
+        AppState,
+    },
+};
+use secrecy::ExposeSecret;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+
+/// 单次样例题目数量上限
+const MAX_SAMPLE_COUNT: usize = 200;
+
+/// 健康检查中 SRS API 探测的超时时间
+const SRS_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// ============================================================================
+// 鉴权
+// ============================================================================
+
+/// 校验管理令牌
+///
+/// ### 返回值
+/// - `Ok(())`: 令牌有效
+/// - `Err(ApiError::Forbidden)`: 未配置管理令牌或令牌无效
+pub fn check_admin(
+    state: &AppState,
+    headers: &HeaderMap,
+    query_token: Option<&str>,
+) -> Result<(), ApiError> {
+    let config = state.config();
+    let expected = config
+        .admin_token
+        .as_ref()
+        .ok_or_else(|| ApiError::Forbidden("admin api disabled".to_string()))?;
+
+    let provided = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or(query_token);
+
+    if provided.is_some_and(|p| secret_eq(p, expected.expose_secret())) {
+        Ok(())
+    } else {
+        tracing::warn!(target: logging::AUDIT_TARGET, "管理令牌验证失败");
+        Err(ApiError::Forbidden("invalid admin token".to_string()))
+    }
+}
+
+// ============================================================================
+// 题库样例
+// ============================================================================
+
+/// 题库样例请求参数
+#[derive(Debug, Deserialize)]
+pub struct SampleParams {
+    /// 题目类型：date / life / publisher / character / content
+    #[serde(rename = "type")]
+    kind: String,
+    /// 样例数量（默认 20，上限 200）
+    count: Option<usize>,
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 题库样例处理器
+///
+/// ### 路由
+/// `GET /admin/bannerdb/sample?type=date&count=20`
+///
+/// ### 响应格式
+/// ```json
+/// {
+///   "type": "date",
+///   "samples": [{"question": "...", "answer": "..."}]
+/// }
+/// ```
+pub async fn bannerdb_sample_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<SampleParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let Some(kind) = QuestionKind::parse(&params.kind) else {
+        return ApiError::BadRequest(format!("unknown question type: {}", params.kind)).into_response();
+    };
+    let count = params.count.unwrap_or(20).min(MAX_SAMPLE_COUNT);
+
+    let samples = state.banner_db.current().sample_questions(kind, count);
+    Json(json!({
+        "type": kind.as_str(),
+        "samples": samples,
+    }))
+    .into_response()
+}
+
+// ============================================================================
+// 题库校验报告
+// ============================================================================
+
+/// 管理令牌查询参数
+#[derive(Debug, Deserialize)]
+pub struct AdminParams {
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 题库校验报告处理器
+///
+/// ### 路由
+/// `GET /admin/bannerdb/report`
+///
+/// ### 响应格式
+/// ```json
+/// {"total": 400, "excluded": 3, "empty": 1, "eligible": 396, "skipped": 1,
+///  "warnings": ["第 12 个条目（index=11）格式错误，已跳过: missing field `announces`"]}
+/// ```
+///
+/// `warnings` 仅在宽松解析（`LIVE_SERVER_BANNER_LENIENT`）跳过了条目时返回
+pub async fn bannerdb_report_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    Json(state.banner_db.current().report()).into_response()
+}
+
+// ============================================================================
+// 运行指标
+// ============================================================================
+
+/// 运行指标导出处理器
+///
+/// ### 路由
+/// `GET /admin/metrics`
+///
+/// ### 返回值
+/// Prometheus 文本格式的指标，包括 SRS 回调耗时直方图、按原因统计的拒绝次数，
+/// 以及进程资源占用和主要内存表的条目数
+pub async fn metrics_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        format!("{}{}", state.metrics.render(), ResourceUsage::collect(&state).render()),
+    )
+        .into_response()
+}
+
+// ============================================================================
+// 健康状态汇总
+// ============================================================================
+
+/// 监听端口状态
+#[derive(Debug, Serialize)]
+pub struct ListenerStatus {
+    /// 配置的监听地址
+    pub addr: String,
+    /// 提供的服务
+    pub services: Vec<&'static str>,
+    /// 是否已成功绑定
+    pub bound: bool,
+}
+
+/// SRS API 可达性
+#[derive(Debug, Serialize)]
+pub struct SrsStatus {
+    /// SRS HTTP API 地址
+    pub api: String,
+    /// 是否可达
+    pub reachable: bool,
+    /// 不可达的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 推流密钥文件状态
+#[derive(Debug, Serialize)]
+pub struct SecretStatus {
+    /// 密钥文件路径
+    pub path: String,
+    /// 是否可读
+    pub readable: bool,
+    /// 密钥数量
+    pub keys: usize,
+}
+
+/// 转储目录剩余空间
+#[derive(Debug, Serialize)]
+pub struct DiskStatus {
+    /// 转储目录
+    pub path: String,
+    /// 剩余可用字节数（无法查询时为 `None`）
+    pub available_bytes: Option<u64>,
+}
+
+/// 下行流量估算（按 SRS 报告的码率累加，仅供参考）
+#[derive(Debug, Serialize)]
+pub struct EgressReport {
+    /// 开始估算的时间（服务启动时间）
+    pub since: DateTime<Utc>,
+    /// 各路流（`app/stream`）的估算
+    pub streams: BTreeMap<String, StreamEgress>,
+    /// 全部流的累计字节数
+    pub total_bytes: u64,
+}
+
+/// 健康状态汇总
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    /// 所有检查项是否均正常
+    pub ok: bool,
+    /// 监听端口
+    pub listeners: Vec<ListenerStatus>,
+    /// SRS API
+    pub srs: SrsStatus,
+    /// 题库
+    pub banner_db: BannerReport,
+    /// 推流密钥文件
+    pub secrets: SecretStatus,
+    /// 后台任务
+    pub tasks: Vec<TaskStatus>,
+    /// 转储目录剩余空间
+    pub disk: DiskStatus,
+    /// 进程资源占用（仅供参考，不影响整体状态）
+    pub resources: ResourceUsage,
+    /// 下行流量估算（仅供参考，不影响整体状态）
+    pub egress: EgressReport,
+}
+
+impl StatusReport {
+    /// 收集各项健康状态
+    pub async fn collect(state: &AppState) -> Self {
+        let bound = state.health.listeners();
+        let listeners: Vec<ListenerStatus> = state
+            .config()
+            .listeners()
+            .into_iter()
+            .map(|listen| ListenerStatus {
+                addr: listen.addr.to_string(),
+                services: listen.services.names(),
+                bound: bound.contains(&listen.addr),
+            })
+            .collect();
+
+        let probe = tokio::time::timeout(SRS_PROBE_TIMEOUT, state.srs_api.get_json("/api/v1/versions")).await;
+        let error = match probe {
+            Ok(Ok(_)) => None,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some(format!("请求超时（{} 秒）", SRS_PROBE_TIMEOUT.as_secs())),
+        };
+        let srs = SrsStatus {
+            api: state.config().srs_api_addr(),
+            reachable: error.is_none(),
+            error,
+        };
+
+        let key_count = state.srs_db.access.read().verifier.key_count();
+        let secrets = SecretStatus {
+            path: state.config().secret_path.display().to_string(),
+            readable: key_count.is_ok(),
+            keys: key_count.unwrap_or(0),
+        };
+
+        let disk = DiskStatus {
+            path: state.config().dump_path.display().to_string(),
+            available_bytes: health::disk_available(&state.config().dump_path),
+        };
+
+        let banner_db = state.banner_db.current().report();
+        let tasks = state.health.tasks();
+        let egress = {
+            let info = state.streaming_info.inner.read();
+            EgressReport {
+                since: info.egress_since,
+                total_bytes: info.egress.values().map(|e| e.bytes).sum(),
+                streams: info.egress.clone(),
+            }
+        };
+
+        let ok = listeners.iter().all(|l| l.bound)
+            && srs.reachable
+            && secrets.keys > 0
+            && banner_db.eligible > 0
+            && tasks.iter().all(|t| t.alive)
+            && disk.available_bytes.is_none_or(|b| b >= MIN_DISK_BYTES);
+
+        Self {
+            ok,
+            listeners,
+            srs,
+            banner_db,
+            secrets,
+            tasks,
+            disk,
+            resources: ResourceUsage::collect(state),
+            egress,
+        }
+    }
+
+    /// 渲染为纯文本表格
+    pub fn to_table(&self) -> String {
+        let mark = |ok: bool| if ok { "OK" } else { "FAIL" };
+        let mut out = String::new();
+        let mut row = |name: &str, ok: bool, detail: String| {
+            let _ = writeln!(out, "{:<16} {:<5} {}", name, mark(ok), detail);
+        };
+
+        row("overall", self.ok, String::new());
+        for listener in &self.listeners {
+            row(
+                "listener",
+                listener.bound,
+                format!("{} ({})", listener.addr, listener.services.join("+")),
+            );
+        }
+        row(
+            "srs",
+            self.srs.reachable,
+            match &self.srs.error {
+                Some(e) => format!("{} ({})", self.srs.api, e),
+                None => self.srs.api.clone(),
+            },
+        );
+        row(
+            "banner_db",
+            self.banner_db.eligible > 0,
+            format!("{} entries, {} eligible", self.banner_db.total, self.banner_db.eligible),
+        );
+        row(
+            "secrets",
+            self.secrets.keys > 0,
+            if self.secrets.readable {
+                format!("{} keys in {}", self.secrets.keys, self.secrets.path)
+            } else {
+                format!("{} unreadable", self.secrets.path)
+            },
+        );
+        for task in &self.tasks {
+            row(
+                &format!("task:{}", task.name),
+                task.alive,
+                match task.last_beat_secs {
+                    Some(secs) => format!("last beat {}s ago (every {}s)", secs, task.period_secs),
+                    None => format!("no beat yet (every {}s)", task.period_secs),
+                },
+            );
+        }
+        row(
+            "disk",
+            self.disk.available_bytes.is_none_or(|b| b >= MIN_DISK_BYTES),
+            match self.disk.available_bytes {
+                Some(b) => format!("{} MiB free in {}", b / 1024 / 1024, self.disk.path),
+                None => format!("unknown free space in {}", self.disk.path),
+            },
+        );
+        row("resources", true, self.resources.summary());
+        for (stream, egress) in &self.egress.streams {
+            row(
+                "egress",
+                true,
+                format!("{}: {} MiB sent, {} kbps now", stream, egress.bytes / 1024 / 1024, egress.kbps),
+            );
+        }
+        out
+    }
+}
+
+/// 健康状态汇总处理器
+///
+/// ### 路由
+/// `GET /admin/status`
+///
+/// ### 返回值
+/// - 默认返回 JSON，请求头 `Accept: text/plain` 时返回纯文本表格
+/// - 所有检查项正常时状态码为 200，否则为 503，可直接用作容器健康检查
+pub async fn status_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let report = StatusReport::collect(&state).await;
+    let code = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let wants_text = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/plain"));
+    if wants_text {
+        (
+            code,
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            report.to_table(),
+        )
+            .into_response()
+    } else {
+        (code, Json(report)).into_response()
+    }
+}
+
+// ============================================================================
+// 客户端列表
+// ============================================================================
+
+/// 客户端列表中的一项
+#[derive(Debug, Serialize)]
+pub struct ClientSummary {
+    /// 客户端 IP（按日志脱敏设置遮蔽）
+    pub ip: String,
+    /// 会话 ID
+    pub session_id: SessionId,
+    /// 当前状态
+    pub status: &'static str,
+    /// 显示昵称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// 是否为主播
+    pub is_publisher: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 最后活动时间
+    pub last_activity: DateTime<Utc>,
+    /// 首次连接时记录的请求头
+    pub headers: BTreeMap<String, String>,
+    /// 前端版本号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_version: Option<String>,
+    /// 前端声明的能力
+    pub capabilities: Vec<String>,
+    /// 正在拉流的 SRS client_id
+    pub srs_clients: Vec<String>,
+    /// 开始拉流次数（大于 1 说明发生过重连）
+    pub plays: u32,
+    /// 停止拉流次数
+    pub stops: u32,
+    /// 当前拉流码率（kbps，来自 SRS 统计）
+    pub send_kbps: u64,
+    /// 估算的累计下行流量（字节）
+    pub bytes_sent: u64,
+}
+
+/// 客户端列表处理器
+///
+/// 用于将观众反馈的问题（如"电视上的应用进不去"）与实际会话对应起来
+///
+/// ### 路由
+/// `GET /admin/clients`
+///
+/// ### 响应格式
+/// ```json
+/// [{"ip": "1.2.*.*", "session_id": "...", "status": "pending", "is_publisher": false,
+///   "created_at": "...", "last_activity": "...", "headers": {"user-agent": "..."},
+///   "plays": 2, "stops": 1, "send_kbps": 2500, "bytes_sent": 187500000}]
+/// ```
+pub async fn clients_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let registry = state.srs_db.clients.read();
+    let mut clients: Vec<ClientSummary> = registry
+        .clients
+        .values()
+        .flat_map(|m| m.values())
+        .map(|c| ClientSummary {
+            ip: redact::ip(&c.ip),
+            session_id: c.session_id.clone(),
+            status: c.status.as_str(),
+            display_name: c.display_name.clone(),
+            is_publisher: c.is_publisher,
+            created_at: c.created_at,
+            last_activity: c.last_activity,
+            headers: c.first_seen_headers.clone(),
+            client_version: c.capabilities.version.clone(),
+            capabilities: c.capabilities.flags.iter().cloned().collect(),
+            srs_clients: c.srs_clients.keys().cloned().collect(),
+            plays: c.plays,
+            stops: c.stops,
+            send_kbps: c.send_kbps,
+            bytes_sent: c.bytes_sent,
+        })
+        .collect();
+    clients.sort_by_key(|c| c.created_at);
+    Json(clients).into_response()
+}
+
+/// 客户端批量操作
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BulkClientAction {
+    /// 移除所有待答题的客户端
+    ExpirePending,
+    /// 将所有观看中的观众转为暂离
+    RestPlaying,
+    /// 移除所有答错被封禁的客户端（解除封禁）
+    PurgeNil,
+    /// 移除指定地址段内的所有客户端并踢出其拉流连接
+    ResetPrefix {
+        /// 地址段（`1.2.3.0/24`、`2001:db8::/32` 或单个地址）
+        prefix: String,
+    },
+}
+
+impl BulkClientAction {
+    /// 操作名称
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExpirePending => "expire_pending",
+            Self::RestPlaying => "rest_playing",
+            Self::PurgeNil => "purge_nil",
+            Self::ResetPrefix { .. } => "reset_prefix",
+        }
+    }
+}
+
+/// 客户端批量操作处理器
+///
+/// 用于直播中遭受攻击流量或配置失误后快速恢复，主播和联合主持的会话不受影响
+/// 被移除的会话在续连令牌有效期内不能凭令牌恢复，须重新答题
+///
+/// ### 路由
+/// `POST /admin/clients/bulk`
+///
+/// ### 请求格式
+/// ```json
+/// {"action": "expire_pending|rest_playing|purge_nil|reset_prefix", "prefix": "1.2.3.0/24"}
+/// ```
+///
+/// ### 响应格式
+/// ```json
+/// {"action": "reset_prefix", "affected": 12, "kicked": 3}
+/// ```
+pub async fn clients_bulk_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+    Json(action): Json<BulkClientAction>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let mut kick = Vec::new();
+    let affected = {
+        let mut clients = state.srs_db.clients.write();
+        let (affected, removed) = match &action {
+            BulkClientAction::ExpirePending => {
+                let removed = clients.remove_clients_where(|c| c.status == ClientStatus::Pending);
+                (removed.len(), removed)
+            }
+            BulkClientAction::RestPlaying => (clients.rest_all_playing(), Vec::new()),
+            BulkClientAction::PurgeNil => {
+                let removed = clients.remove_clients_where(|c| c.status == ClientStatus::Nil);
+                (removed.len(), removed)
+            }
+            BulkClientAction::ResetPrefix { prefix } => {
+                let Some(range) = IpRange::parse(prefix) else {
+                    return ApiError::BadRequest("invalid prefix".to_string()).into_response();
+                };
+                let removed = clients.remove_clients_where(|c| range.contains(c.ip.addr()));
+                kick.extend(removed.iter().flat_map(|c| c.srs_clients.keys().cloned()));
+                (removed.len(), removed)
+            }
+        };
+        // 被移除的会话不得凭仍在有效期内的续连令牌恢复，否则播放器重连即可撤销本次操作
+        for client in &removed {
+            clients.revoke_resume(&client.session_id);
+        }
+        affected
+    };
+    tracing::warn!(
+        target: logging::AUDIT_TARGET,
+        "管理员执行了客户端批量操作 {}: 影响 {} 个客户端",
+        action.as_str(),
+        affected
+    );
+
+    // 通知 SRS 踢出被重置客户端的拉流连接
+    let kicked = kick.len();
+    if !kick.is_empty() {
+        let srs_api = state.srs_api.clone();
+        tokio::spawn(async move {
+            for client_id in kick {
+                if let Err(e) = srs_api.kick_client(&client_id).await {
+                    tracing::warn!("踢出拉流连接失败 client_id={}: {}", client_id, e);
+                }
+            }
+        });
+    }
+    Json(json!({"action": action.as_str(), "affected": affected, "kicked": kicked})).into_response()
+}
+
+// ============================================================================
+// 推流地址二维码
+// ============================================================================
+
+/// 推流地址二维码请求参数
+#[derive(Debug, Deserialize)]
+pub struct PushQrParams {
+    /// 推流密钥（必须是密钥文件中的有效密钥）
+    secret: String,
+    /// 流名称
+    stream: String,
+    /// 是否公开模式（默认 `false`）
+    public: Option<bool>,
+    /// 观众入场方式：`quiz` / `confirm`（省略时不携带该参数）
+    entry: Option<EntryMode>,
+    /// 直播间名称（省略时不携带该参数）
+    name: Option<String>,
+    /// 会话 ID（可选）
+    session_id: Option<String>,
+    /// 图片格式：`png`（默认）/ `svg`
+    format: Option<String>,
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 推流地址二维码处理器
+///
+/// 将 RTMP 推流地址（含密钥和推荐参数）渲染为二维码，供手机推流应用扫码配置
+///
+/// ### 路由
+/// `GET /admin/push_qr?secret=secret_xxx&stream=test&format=svg`
+///
+/// ### 返回值
+/// PNG 或 SVG 图片，响应头 `X-Push-Url` 中附带二维码内容
+pub async fn push_qr_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PushQrParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let Some(format) = QrFormat::parse(params.format.as_deref().unwrap_or("png")) else {
+        return ApiError::BadRequest("format must be png or svg".to_string()).into_response();
+    };
+    if !state.srs_db.access.read().verify_streamer(&params.secret) {
+        return ApiError::BadRequest("unknown stream secret".to_string()).into_response();
+    }
+
+    let config = state.config();
+    let host_header = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+    let Some(host) = push_url::resolve_host(config.push_host.as_deref(), host_header) else {
+        return ApiError::BadRequest("push host unknown, set LIVE_SERVER_PUSH_HOST".to_string())
+            .into_response();
+    };
+    let target = PushTarget {
+        host,
+        app: config.srs_app.clone(),
+        stream: params.stream,
+        secret: params.secret,
+        public: Some(params.public.unwrap_or(false)),
+        entry: params.entry,
+        name: params.name.filter(|n| !n.trim().is_empty()),
+        session_id: params.session_id,
+    };
+    let url = target.rtmp_url(config.rtmp_port);
+
+    match push_url::render_qr(&url, format) {
+        Ok(image) => (
+            [
+                (header::CONTENT_TYPE, format.content_type().to_string()),
+                (header::CACHE_CONTROL, "no-store".to_string()),
+                (header::HeaderName::from_static("x-push-url"), url),
+            ],
+            image,
+        )
+            .into_response(),
+        Err(e) => ApiError::Internal(e).into_response(),
+    }
+}
+
+// ============================================================================
+// 配置热重载
+// ============================================================================
+
+/// 配置热重载处理器
+///
+/// ### 路由
+/// `POST /admin/reload`
+///
+/// ### 响应格式
+/// ```json
+/// {"applied": ["log_level"], "restart_required": ["port"]}
+/// ```
+pub async fn reload_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    match state.reload_config() {
+        Ok(report) => {
+            tracing::info!(
+                "配置已重新加载: 生效 {:?}，需重启 {:?}",
+                report.applied,
+                report.restart_required
+            );
+            Json(report).into_response()
+        }
+        Err(e) => {
+            tracing::warn!("重新加载配置失败: {}", e);
+            ApiError::Internal(e).into_response()
+        }
+    }
+}
+
+/// 候选配置预览处理器
+///
+/// 请求体为候选配置文件内容（`KEY=VALUE` 或 TOML 风格的 `KEY = "value"`，键名与环境变量相同），
+/// 校验后返回与当前配置相比将发生的变更，不修改任何状态。确认无误后写入配置文件再调用
+/// `POST /admin/reload` 生效
+///
+/// ### 路由
+/// `POST /admin/config/preview`
+///
+/// ### 响应格式
+/// ```json
+/// {"valid": false, "malformed_lines": [3], "unknown_keys": ["LIVE_SERVER_LOG_LEVLE"],
+///  "invalid_values": ["LIVE_SERVER_MAX_VIEWERS"], "applied": ["poll"], "restart_required": ["port"],
+///  "changes": [{"field": "poll", "current": "...", "candidate": "...", "restart_required": false}]}
+/// ```
+pub async fn config_preview_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+    body: String,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    Json(state.config().preview(&body)).into_response()
+}
+
+// ============================================================================
+// 转推目标加密
+// ============================================================================
+
+/// 转推目标加密请求体
+#[derive(Debug, Deserialize)]
+pub struct RelaySealBody {
+    /// 平台推流地址（含推流码）
+    url: String,
+}
+
+/// 转推目标加密处理器
+///
+/// 将平台推流地址加密为可写入 `LIVE_SERVER_RELAY_TARGETS` 的密文
+///
+/// ### 路由
+/// `POST /admin/relay/seal`
+///
+/// ### 请求格式
+/// ```json
+/// {"url": "rtmp://a.rtmp.youtube.com/live2/xxxx-xxxx"}
+/// ```
+///
+/// ### 响应格式
+/// ```json
+/// {"sealed": "Hq2k..."}
+/// ```
+pub async fn relay_seal_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+    Json(body): Json<RelaySealBody>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    let Some(cipher) = state.relays.cipher() else {
+        return ApiError::Forbidden("relay disabled, set LIVE_SERVER_RELAY_KEY".to_string()).into_response();
+    };
+    if !body.url.starts_with("rtmp://") && !body.url.starts_with("rtmps://") {
+        return ApiError::BadRequest("url must be rtmp:// or rtmps://".to_string()).into_response();
+    }
+    Json(json!({"sealed": cipher.seal(&body.url)})).into_response()
+}
+
+// ============================================================================
+// 录制文件
+// ============================================================================
+
+/// 录制文件列表请求参数
+#[derive(Debug, Deserialize)]
+pub struct RecordingsParams {
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+    /// 只列出指定直播场次的录制文件
+    stream_session_id: Option<String>,
+}
+
+/// 录制文件列表处理器
+///
+/// ### 路由
+/// `GET /admin/recordings?stream_session_id=<场次 ID>`
+///
+/// ### 响应格式
+/// ```json
+/// [{"file": "./objs/nginx/html/live/test.1700000000000.flv", "app": "live", "stream": "test",
+///   "stream_session_id": "01H...", "created_at": "..."}]
+/// ```
+pub async fn recordings_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<RecordingsParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    Json(state.recordings.list(params.stream_session_id.as_deref())).into_response()
+}
+
+// ============================================================================
+// 聊天记录导出
+// ============================================================================
+
+/// 聊天记录导出请求参数
+#[derive(Debug, Deserialize)]
+pub struct ChatExportParams {
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+    /// 是否包含已删除消息的原始内容
+    #[serde(default)]
+    include_redacted: bool,
+}
+
+/// 聊天记录导出处理器
+///
+/// 将当前聊天室完整转储到转储目录，默认遮蔽已删除消息的内容
+///
+/// ### 路由
+/// `GET /admin/chat/export?include_redacted=true`
+///
+/// ### 响应格式
+/// ```json
+/// {"file": "live-1700000000-1700003600-0.json", "include_redacted": true}
+/// ```
+pub async fn chat_export_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ChatExportParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    let result = state.chat_db.inner.read().active().dump_full_with(params.include_redacted);
+    match result {
+        Ok(path) => {
+            if params.include_redacted {
+                tracing::info!("管理员导出了包含已删除内容的聊天记录: {}", path.display());
+            }
+            let file = path.file_name().map(|n| n.to_string_lossy().into_owned());
+            Json(json!({"file": file, "include_redacted": params.include_redacted})).into_response()
+        }
+        Err(e) => ApiError::Internal(e).into_response(),
+    }
+}
+
+// ============================================================================
+// 实时日志
+// ============================================================================
+
+/// 实时日志处理器
+///
+/// 以 SSE 推送警告及以上级别的日志和安全审计日志（如推流密钥、管理令牌验证失败），
+/// 连接后先发送内存缓冲中最近的日志（最多 `TAIL_CAPACITY` 条）供补看，再持续推送新日志。
+/// 浏览器 `EventSource` 无法设置请求头，可使用 `admin_token` 查询参数鉴权；
+/// 断线重连时携带 `Last-Event-ID` 只补发之后的日志
+///
+/// ### 路由
+/// `GET /admin/events`
+///
+/// ### 响应格式
+/// 每条日志的 `event` 字段为 `log`，`id` 字段为日志序号，`data` 字段为：
+/// ```json
+/// {"id": 42, "time": "2024-01-01T00:00:00Z", "level": "WARN", "target": "audit", "message": "..."}
+/// ```
+pub async fn admin_events_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<AdminParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+
+    let (backlog, receiver) = logging::tail().subscribe();
+    // 序号超过缓冲中最新日志时说明服务已重启，重新发送全部缓冲
+    let last_id = if backlog.last().is_some_and(|record| record.id < last_id) { 0 } else { last_id };
+    let backlog = backlog.into_iter().filter(move |record| record.id > last_id);
+    // 落后过多导致丢失的日志直接跳过
+    let live = BroadcastStream::new(receiver).filter_map(|record| record.ok());
+    let stream = tokio_stream::iter(backlog).chain(live).filter_map(|record| {
+        let data = serde_json::to_string(&record).ok()?;
+        Some(Ok::<_, Infallible>(
+            Event::default().event("log").id(record.id.to_string()).data(data),
+        ))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
@@ -4,19 +4,38 @@
 //! - 连接鉴权（答题验证）
 //! - 答案提交
 //! - 状态查询
+//! - 答题速度排行榜
 //! - 结束直播（主播权限）
 
 use super::super::{
-    error::{forbidden_json_response},
-    state::ClientStatus,
+    config::{Config, Features, LatencyMode, OfflineConnect, PublisherLoginPolicy, QuizFallback},
+    error::{forbidden_json_response, ApiError},
+    domain::{ClientIp, SessionId},
+    ids, redact,
+    state::{
+        banner::{answer_matches, AnswerKind, BannerQuestion, QuestionMeta},
+        chat::{QuizRecord, LEADERBOARD_SIZE},
+        disk_guard,
+        events::StreamEvent,
+        script::{HookPoint, ScriptDecision},
+        srs::{AnswerOutcome, EntryMode, RoomNotice, StreamSnapshot, StreamerEvent, StreamerTransition},
+        viewing_policy::{self, ViewingDenial},
+        stream_policy,
+        ClientCapabilities, ClientStatus, StreamOverlay,
+    },
 };
 use axum::{
     extract::{Query, State},
     response::{IntoResponse, Json, Response},
 };
+use rand::Rng;
 use serde::Serialize;
-use serde_json::json;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use tracing::Instrument;
+
+/// 记录的请求头值的最大长度（字符）
+const MAX_HEADER_VALUE_CHARS: usize = 256;
 
 // ============================================================================
 // 数据结构定义
@@ -33,6 +52,16 @@ pub enum StreamStatus {
     Banned,
     /// 等待答题 - 客户端已连接但尚未通过验证
     Pending,
+    /// 排队中 - 开播前已答题通过，等待开播
+    Waiting,
+    /// 人数已满 - 已授权但同时观看人数或上行带宽已达上限，暂时无法开始观看
+    Full,
+    /// 入场已关闭 - 主播暂停接纳新观众
+    Closed,
+    /// 时段外 - 不在允许新观众进入的观看时段内
+    OutsideWindow,
+    /// 地区受限 - 所在国家/地区不允许进入
+    RegionBlocked,
     /// 直播中 - 主播正在推流
     Live,
     /// 暂停 - 主播暂时中断推流（如网络问题）
@@ -48,11 +77,30 @@ impl StreamStatus {
             Self::Unregistered => "unregistered",
             Self::Banned => "banned",
             Self::Pending => "pending",
+            Self::Waiting => "waiting",
+            Self::Full => "full",
+            Self::Closed => "closed",
+            Self::OutsideWindow => "outside_window",
+            Self::RegionBlocked => "region_blocked",
             Self::Live => "live",
             Self::Paused => "paused",
             Self::Ended => "ended",
         }
     }
+
+    /// 直播本身的状态（不考虑观众个人的答题和排队状态）
+    ///
+    /// ### 返回值
+    /// `Live` / `Paused` / `Ended` 之一
+    pub fn of_stream(snapshot: &StreamSnapshot) -> Self {
+        if !snapshot.is_streaming() {
+            Self::Ended
+        } else if snapshot.is_paused() {
+            Self::Paused
+        } else {
+            Self::Live
+        }
+    }
 }
 
 /// API 请求参数（规范化后的英文字段名）
@@ -61,9 +109,11 @@ impl StreamStatus {
 #[derive(Debug, serde::Deserialize)]
 pub struct ApiParams {
     /// 会话/请求 ID - 客户端的唯一标识符
+    /// 须为不超过 128 字节、不含 `&`、`=`、`?`、`#` 的可见 ASCII 字符，否则返回 400
     session_id: String,
     /// 要执行的操作类型
     /// - "connect": 连接并获取题目
+    /// - "pair_start": 未授权设备发起跨设备配对
     action: Option<String>,
     /// 答题提交 - 用户输入的答案
     answer: Option<String>,
@@ -72,6 +122,138 @@ pub struct ApiParams {
     /// 结束直播 - 必须为 "true"
     /// 仅主播（publisher）可执行
     end: Option<String>,
+    /// 主播一次性验证码（`one_time_code` 登录策略下使用）
+    otp: Option<String>,
+    /// 确认接管主播会话 - 必须为 "true"（`single_session` 登录策略下使用）
+    takeover: Option<String>,
+    /// 设置状态提示 - `starting_soon` / `brb` / `ending` / `clear`
+    /// 仅主播（publisher）可执行
+    overlay: Option<String>,
+    /// 手动放行 - 目标观众的 session_id 或配对码
+    /// 仅主播（publisher）可执行
+    grant: Option<String>,
+    /// 确认跨设备配对 - 新设备显示的 6 位配对码
+    /// 仅已授权的会话可执行
+    pair_confirm: Option<String>,
+    /// 续连令牌 - 状态查询时携带最近一次 `video_uri` 中的 `resume` 参数，
+    /// 观众记录刚过期时凭令牌恢复授权（聊天返回 `reauth=heartbeat` 时使用）
+    resume: Option<String>,
+    /// 回访观众令牌 - 连接时携带有效令牌可跳过答题
+    alumni: Option<String>,
+    /// 访客通行证短码 - 连接时携带主播签发的有效通行证可跳过答题
+    guest: Option<String>,
+    /// 人机验证令牌 - 启用人机验证时，新用户连接需携带
+    captcha: Option<String>,
+    /// 查询排行榜 - 目前仅支持 "quiz"（答题速度）
+    leaderboard: Option<String>,
+    /// 前端版本号 - 连接时可选携带
+    client_version: Option<String>,
+    /// 前端能力 - 连接时可选携带，逗号分隔，如 `sse,websocket,uri_v2`
+    capabilities: Option<String>,
+}
+
+impl ApiParams {
+    /// 请求的操作类型（用于日志 span）
+    ///
+    /// 按处理器的分发顺序取第一个出现的操作参数
+    fn action_label(&self) -> &str {
+        if let Some(action) = self.action.as_deref() {
+            return action;
+        }
+        [
+            ("pair_confirm", self.pair_confirm.is_some()),
+            ("answer", self.answer.is_some()),
+            ("end", self.end.is_some()),
+            ("grant", self.grant.is_some()),
+            ("overlay", self.overlay.is_some()),
+            ("leaderboard", self.leaderboard.is_some()),
+            ("status", self.status.is_some()),
+        ]
+        .into_iter()
+        .find_map(|(label, present)| present.then_some(label))
+        .unwrap_or("none")
+    }
+}
+
+/// 结构化的播放目标（声明 `uri_v2` 能力的客户端）
+#[derive(Debug, Serialize)]
+pub struct VideoTarget {
+    /// 应用名称
+    app: String,
+    /// 流名称
+    stream: String,
+}
+
+/// 结构化的题目（声明 `question_v2` 能力的客户端）
+#[derive(Debug, Serialize)]
+pub struct StructuredQuestion {
+    /// 题目文本（与 `question` 相同）
+    text: String,
+    /// 题目类型、答案形式和选项
+    #[serde(flatten)]
+    meta: QuestionMeta,
+}
+
+/// 转码版本（可选清晰度）
+#[derive(Debug, Serialize)]
+pub struct StreamVariant {
+    /// 清晰度名称（流名称后缀去掉开头的 `_`，如 `hd`）
+    name: String,
+    /// 视频 URI，格式与 `video_uri` 相同
+    video_uri: String,
+}
+
+/// 推荐的播放地址
+#[derive(Debug, Serialize)]
+pub struct PlaybackUrl {
+    /// 播放协议：`webrtc` / `flv` / `hls`
+    protocol: &'static str,
+    /// 相对于 SRS HTTP 服务的播放地址（需由前端追加 `session_id` 参数）
+    url: String,
+}
+
+impl PlaybackUrl {
+    /// 按延迟模式列出推荐的播放地址（优先级从高到低）
+    fn for_mode(mode: LatencyMode, app: &str, stream: &str) -> Vec<Self> {
+        let webrtc = Self {
+            protocol: "webrtc",
+            url: format!("/rtc/v1/whep/?{}", stream_policy::stream_query(app, stream)),
+        };
+        let flv = Self {
+            protocol: "flv",
+            url: format!("/{}/{}.flv", stream_policy::path_segment(app), stream_policy::path_segment(stream)),
+        };
+        let hls = Self {
+            protocol: "hls",
+            url: format!("/{}/{}.m3u8", stream_policy::path_segment(app), stream_policy::path_segment(stream)),
+        };
+        match mode {
+            LatencyMode::Low => vec![webrtc, flv],
+            LatencyMode::Stable => vec![hls, flv],
+        }
+    }
+}
+
+/// 客户端可用的推送接口（仅返回客户端声明支持的接口）
+#[derive(Debug, Serialize)]
+pub struct Endpoints {
+    /// SSE 事件推送地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    events: Option<String>,
+    /// WebSocket 聊天地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    websocket: Option<String>,
+}
+
+/// 无人推流时的引导信息
+#[derive(Debug, Serialize)]
+pub struct OfflineInfo {
+    /// 直播时间表（自由文本）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<String>,
+    /// 落地页地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    landing_url: Option<String>,
 }
 
 /// API 响应结构（规范化后的英文字段名）
@@ -89,20 +271,151 @@ pub struct ApiResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     video_uri: Option<String>,
 
+    /// 正在推流的转码版本，供播放器提供清晰度选择
+    /// 返回视频 URI 且 SRS 输出了转码版本时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variants: Option<Vec<StreamVariant>>,
+
     /// 答题问题
     /// 新用户连接时返回此字段
     #[serde(skip_serializing_if = "Option::is_none")]
     question: Option<String>,
 
+    /// 入场确认令牌
+    /// 点击确认入场模式下代替题目答案，等待片刻后以 `answer=<令牌>` 提交
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirm_token: Option<String>,
+
+    /// 结构化的题目（类型、答案形式、选项）
+    /// 返回题目且客户端声明 `question_v2` 能力时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    question_info: Option<StructuredQuestion>,
+
+    /// 配对码
+    /// 未通过验证的用户连接时返回，可告知主播以手动放行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pairing_code: Option<String>,
+
+    /// 跨设备配对码
+    /// 发起配对（action=pair_start）时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pair_code: Option<String>,
+
+    /// 回访观众令牌
+    /// 答题通过后返回（服务端配置了签名密钥时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alumni_token: Option<String>,
+
     /// 是否为主播标识
     /// 使用 secret 验证成功后返回 true
     #[serde(skip_serializing_if = "Option::is_none")]
     is_publisher: Option<bool>,
 
+    /// 主播访问令牌（JWT）
+    /// 使用 secret 验证成功后返回（服务端配置了签名密钥时），用于快捷操作接口
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publisher_token: Option<String>,
+
     /// 当前直播状态
     /// 状态查询时返回此字段
     #[serde(skip_serializing_if = "Option::is_none")]
     stream_status: Option<String>,
+
+    /// 主播手动设置的状态提示
+    /// 状态查询时返回此字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_overlay: Option<String>,
+
+    /// 当前的播放延迟模式（`low` / `stable`）
+    /// 返回视频 URI 或直播进行中的状态查询时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_mode: Option<&'static str>,
+
+    /// 按延迟模式推荐的播放地址（优先级从高到低）
+    /// 返回视频 URI 时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    playback: Option<Vec<PlaybackUrl>>,
+
+    /// 当前直播是否正在录制
+    /// 直播进行中的状态查询时返回此字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recording: Option<bool>,
+
+    /// 主播设置的欢迎语
+    /// 授权后的首次状态查询时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    welcome: Option<String>,
+
+    /// 主播设置的房间规则
+    /// 授权后的首次状态查询时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rules: Option<String>,
+
+    /// 答错封禁的剩余秒数
+    /// 答错、在封禁期内重复提交同一错误答案或被封禁时查询状态时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ban_remaining_secs: Option<u64>,
+
+    /// 当前题目剩余的作答秒数
+    /// 待答题时查询状态返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    question_remaining_secs: Option<u64>,
+
+    /// 现在能否获取新题目（题目已超时可提交任意答案换题，推迟发题时重新连接；封禁期内不能）
+    /// 待答题或被封禁时查询状态返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    can_request_question: Option<bool>,
+
+    /// 题目已超过作答时限（同时返回新题目）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    question_expired: Option<bool>,
+
+    /// 需要输入主播一次性验证码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    otp_required: Option<bool>,
+
+    /// 已有其他主播会话，需确认接管
+    #[serde(skip_serializing_if = "Option::is_none")]
+    takeover_required: Option<bool>,
+
+    /// 需要先完成人机验证才能获取题目
+    #[serde(skip_serializing_if = "Option::is_none")]
+    captcha_required: Option<bool>,
+
+    /// 无人推流时的直播时间表和落地页
+    /// 无人推流时连接且配置了引导信息时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offline: Option<OfflineInfo>,
+
+    /// 下一次开放观看的时间（RFC 3339）
+    /// 观看时段外的新观众连接时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_open: Option<String>,
+
+    /// 排队位置（从 1 开始）
+    /// 开播前排队或人数已满在等候室中的客户端连接、答题或查询状态时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_position: Option<usize>,
+
+    /// 本场直播的答题速度排行榜
+    /// 查询排行榜（leaderboard=quiz）时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quiz_leaderboard: Option<Vec<QuizRecord>>,
+
+    /// 结构化的播放目标
+    /// 返回视频 URI 且客户端声明 `uri_v2` 能力时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    video: Option<VideoTarget>,
+
+    /// 可用的推送接口
+    /// 连接时按客户端声明的能力返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    endpoints: Option<Endpoints>,
+
+    /// 建议的下一次状态查询间隔（毫秒）
+    /// 状态查询时返回此字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poll_interval_ms: Option<u64>,
 }
 
 impl ApiResponse {
@@ -111,9 +424,36 @@ impl ApiResponse {
         Self {
             stream_name: None,
             video_uri: None,
+            variants: None,
             question: None,
+            confirm_token: None,
+            question_info: None,
+            pairing_code: None,
+            pair_code: None,
+            alumni_token: None,
             is_publisher: None,
+            publisher_token: None,
             stream_status: None,
+            stream_overlay: None,
+            latency_mode: None,
+            playback: None,
+            recording: None,
+            welcome: None,
+            rules: None,
+            ban_remaining_secs: None,
+            question_remaining_secs: None,
+            can_request_question: None,
+            question_expired: None,
+            otp_required: None,
+            takeover_required: None,
+            captcha_required: None,
+            offline: None,
+            next_open: None,
+            queue_position: None,
+            quiz_leaderboard: None,
+            video: None,
+            endpoints: None,
+            poll_interval_ms: None,
         }
     }
 
@@ -130,23 +470,298 @@ impl ApiResponse {
         self
     }
 
+    /// 设置惩罚用的播放地址（链式调用）
+    ///
+    /// 配置了诱饵流（`LIVE_SERVER_DECOY_STREAM`）时返回诱饵流的播放地址，否则返回无法播放的假地址
+    pub fn with_decoy(mut self, config: &Config, fake_uri: &str) -> Self {
+        match &config.decoy_stream {
+            Some(decoy) => {
+                self.playback = Some(PlaybackUrl::for_mode(config.latency_mode, &decoy.app, &decoy.stream));
+                self.with_video_uri(stream_policy::stream_query(&decoy.app, &decoy.stream))
+            }
+            None => self.with_video_uri(fake_uri.to_string()),
+        }
+    }
+
+    /// 设置转码版本列表（链式调用）
+    ///
+    /// ### 参数
+    /// - `variants`: `(后缀, 流 URI)` 列表，为空时不返回该字段
+    pub fn with_variants(mut self, variants: Vec<(String, String)>) -> Self {
+        if !variants.is_empty() {
+            self.variants = Some(
+                variants
+                    .into_iter()
+                    .map(|(suffix, video_uri)| StreamVariant {
+                        name: suffix.trim_start_matches('_').to_string(),
+                        video_uri,
+                    })
+                    .collect(),
+            );
+        }
+        self
+    }
+
     /// 设置答题问题（链式调用）
     pub fn with_question(mut self, q: String) -> Self {
         self.question = Some(q);
         self
     }
 
+    /// 设置入场确认令牌（链式调用）
+    ///
+    /// ### 参数
+    /// - `meta`: 题目元数据，不是点击确认题目时不设置
+    /// - `answer`: 题目答案（即确认令牌）
+    pub fn with_confirm_token(mut self, meta: Option<&QuestionMeta>, answer: &str) -> Self {
+        if meta.is_some_and(|m| m.answer_kind == AnswerKind::Confirm) {
+            self.confirm_token = Some(answer.to_string());
+        }
+        self
+    }
+
+    /// 按客户端能力附加结构化题目（链式调用，需先设置题目）
+    ///
+    /// ### 参数
+    /// - `meta`: 题目元数据
+    /// - `caps`: 客户端声明的能力，未声明 `question_v2` 时不附加
+    pub fn with_question_meta(mut self, meta: Option<QuestionMeta>, caps: &ClientCapabilities) -> Self {
+        if caps.has(ClientCapabilities::STRUCTURED_QUESTION) {
+            if let (Some(text), Some(meta)) = (&self.question, meta) {
+                self.question_info = Some(StructuredQuestion {
+                    text: text.clone(),
+                    meta,
+                });
+            }
+        }
+        self
+    }
+
+    /// 设置配对码（链式调用）
+    pub fn with_pairing_code(mut self, code: Option<&str>) -> Self {
+        self.pairing_code = code.map(str::to_string);
+        self
+    }
+
+    /// 设置跨设备配对码（链式调用）
+    pub fn with_pair_code(mut self, code: String) -> Self {
+        self.pair_code = Some(code);
+        self
+    }
+
+    /// 设置回访观众令牌（链式调用）
+    pub fn with_alumni_token(mut self, token: Option<String>) -> Self {
+        self.alumni_token = token;
+        self
+    }
+
     /// 标记为主播（链式调用）
     pub fn with_publisher(mut self) -> Self {
         self.is_publisher = Some(true);
         self
     }
 
+    /// 设置主播访问令牌（链式调用）
+    pub fn with_publisher_token(mut self, token: Option<String>) -> Self {
+        self.publisher_token = token;
+        self
+    }
+
+    /// 设置答错封禁的剩余秒数（链式调用）
+    pub fn with_ban_remaining(mut self, secs: Option<u64>) -> Self {
+        self.ban_remaining_secs = secs;
+        self
+    }
+
+    /// 设置待答题状态的提示：题目剩余作答秒数和能否获取新题目（链式调用）
+    pub fn with_question_hints(mut self, remaining_secs: Option<u64>) -> Self {
+        self.question_remaining_secs = remaining_secs;
+        self.can_request_question = Some(remaining_secs.is_none_or(|secs| secs == 0));
+        self
+    }
+
+    /// 设置被封禁状态的提示：封禁剩余秒数，封禁期内不能获取新题目（链式调用）
+    pub fn with_ban_hints(mut self, remaining_secs: Option<u64>) -> Self {
+        self.ban_remaining_secs = remaining_secs;
+        self.can_request_question = Some(false);
+        self
+    }
+
     /// 设置直播状态（链式调用）
     pub fn with_stream_status(mut self, status: &str) -> Self {
         self.stream_status = Some(status.to_string());
         self
     }
+
+    /// 标记直播已结束并附带配置的引导信息（链式调用）
+    ///
+    /// 未配置时间表和落地页时只设置 `stream_status`
+    pub fn with_offline(mut self, config: &Config) -> Self {
+        self.stream_status = Some(StreamStatus::Ended.as_str().to_string());
+        if config.offline_schedule.is_some() || config.offline_landing_url.is_some() {
+            self.offline = Some(OfflineInfo {
+                schedule: config.offline_schedule.clone(),
+                landing_url: config.offline_landing_url.clone(),
+            });
+        }
+        self
+    }
+
+    /// 设置排队状态和排队位置（链式调用）
+    ///
+    /// ### 参数
+    /// - `status`: 排队的原因（`Waiting` 开播前排队 / `Full` 人数已满）
+    /// - `position`: 排队位置，为 `None` 时不做修改
+    pub fn with_queue_position(mut self, status: StreamStatus, position: Option<usize>) -> Self {
+        if position.is_some() {
+            self.stream_status = Some(status.as_str().to_string());
+            self.queue_position = position;
+        }
+        self
+    }
+
+    /// 标记新观众被观看时段或地区限制拒绝（链式调用）
+    pub fn with_viewing_denial(mut self, denial: &ViewingDenial) -> Self {
+        let status = match denial {
+            ViewingDenial::OutsideWindow { next_open } => {
+                self.next_open = next_open.map(|t| t.to_rfc3339());
+                StreamStatus::OutsideWindow
+            }
+            ViewingDenial::Region { .. } => StreamStatus::RegionBlocked,
+        };
+        self.stream_status = Some(status.as_str().to_string());
+        self
+    }
+
+    /// 标记入场已关闭（链式调用）
+    pub fn with_entries_closed(mut self) -> Self {
+        self.stream_status = Some(StreamStatus::Closed.as_str().to_string());
+        self
+    }
+
+    /// 设置状态提示（链式调用）
+    pub fn with_stream_overlay(mut self, overlay: StreamOverlay) -> Self {
+        self.stream_overlay = Some(overlay.as_str().to_string());
+        self
+    }
+
+    /// 设置当前直播的播放信息（链式调用）
+    ///
+    /// 包括视频 URI、转码版本、延迟模式和推荐的播放地址，未推流时不做修改
+    ///
+    /// ### 参数
+    /// - `snapshot`: 直播状态快照
+    /// - `default_mode`: 配置的默认延迟模式
+    pub fn with_live_stream(mut self, snapshot: &StreamSnapshot, default_mode: LatencyMode) -> Self {
+        let Some(uri) = snapshot.uri.clone() else {
+            return self;
+        };
+        let mode = snapshot.latency_mode(default_mode);
+        self.playback = snapshot
+            .target
+            .as_ref()
+            .map(|(app, stream)| PlaybackUrl::for_mode(mode, app, stream));
+        self.latency_mode = Some(mode.as_str());
+        self.with_video_uri(uri)
+            .with_variants(snapshot.variants.clone())
+    }
+
+    /// 在视频 URI（含转码版本）后附加播放续连令牌（链式调用）
+    ///
+    /// 未设置视频 URI 或没有令牌时不做修改
+    pub fn with_resume_token(mut self, token: Option<String>) -> Self {
+        if let (Some(uri), Some(token)) = (&mut self.video_uri, token) {
+            let param = format!("&resume={}", token);
+            uri.push_str(&param);
+            for variant in self.variants.iter_mut().flatten() {
+                variant.video_uri.push_str(&param);
+            }
+        }
+        self
+    }
+
+    /// 设置延迟模式（链式调用）
+    pub fn with_latency_mode(mut self, mode: LatencyMode) -> Self {
+        self.latency_mode = Some(mode.as_str());
+        self
+    }
+
+    /// 设置录制状态（链式调用）
+    pub fn with_recording(mut self, recording: bool) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// 设置欢迎语和房间规则（链式调用）
+    pub fn with_notice(mut self, notice: &RoomNotice) -> Self {
+        self.welcome = notice.welcome.clone();
+        self.rules = notice.rules.clone();
+        self
+    }
+
+    /// 标记题目已超时（链式调用）
+    pub fn with_question_expired(mut self) -> Self {
+        self.question_expired = Some(true);
+        self
+    }
+
+    /// 标记需要一次性验证码（链式调用）
+    pub fn with_otp_required(mut self) -> Self {
+        self.otp_required = Some(true);
+        self
+    }
+
+    /// 标记需要确认接管（链式调用）
+    pub fn with_takeover_required(mut self) -> Self {
+        self.takeover_required = Some(true);
+        self
+    }
+
+    /// 标记需要人机验证（链式调用）
+    pub fn with_captcha_required(mut self) -> Self {
+        self.captcha_required = Some(true);
+        self
+    }
+
+    /// 设置答题速度排行榜（链式调用）
+    pub fn with_quiz_leaderboard(mut self, records: Vec<QuizRecord>) -> Self {
+        self.quiz_leaderboard = Some(records);
+        self
+    }
+
+    /// 设置建议的轮询间隔（链式调用）
+    pub fn with_poll_interval(mut self, ms: u64) -> Self {
+        self.poll_interval_ms = Some(ms);
+        self
+    }
+
+    /// 按客户端能力补充响应字段（链式调用）
+    ///
+    /// 未声明能力的旧版前端得到的响应与之前完全相同
+    ///
+    /// ### 参数
+    /// - `caps`: 客户端声明的能力
+    /// - `target`: 当前推流的 (app, stream)
+    /// - `websocket_enabled`: 服务端是否开启了 WebSocket 聊天
+    pub fn with_capabilities(
+        mut self,
+        caps: &ClientCapabilities,
+        target: Option<(String, String)>,
+        websocket_enabled: bool,
+    ) -> Self {
+        if caps.has(ClientCapabilities::STRUCTURED_URI) && self.video_uri.is_some() {
+            self.video = target.map(|(app, stream)| VideoTarget { app, stream });
+        }
+        let endpoints = Endpoints {
+            events: caps.has(ClientCapabilities::SSE).then(|| "/events".to_string()),
+            websocket: (websocket_enabled && caps.has(ClientCapabilities::WEBSOCKET))
+                .then(|| "/chat/ws".to_string()),
+        };
+        if endpoints.events.is_some() || endpoints.websocket.is_some() {
+            self.endpoints = Some(endpoints);
+        }
+        self
+    }
 }
 
 impl Default for ApiResponse {
@@ -159,33 +774,239 @@ impl Default for ApiResponse {
 // 辅助函数
 // ============================================================================
 
-/// 从请求头中提取客户端真实 IP 地址
+/// 提取需要记录的请求头
 ///
-/// ### 优先级
-/// 1. `X-Forwarded-For` 头 - 当服务位于反向代理（如 Nginx）后面时
-/// 2. `ConnectInfo` 中的远程地址 - 直接连接时的客户端地址
+/// ### 参数
+/// - `names`: 需要记录的请求头名称（小写）
 ///
-/// ### 为什么需要这个？
-/// - 生产环境通常使用 Nginx 等 HTTP 服务器作为反向代理
-/// - 这种情况下，rusty-live-server 看到的远程地址是 127.0.0.1（本地代理）
-/// - 真实客户端 IP 在 `X-Forwarded-For` 头中
-fn get_client_ip(headers: &axum::http::HeaderMap, remote_addr: &str) -> String {
-    headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        // X-Forwarded-For 可能包含多个 IP（客户端, 代理1, 代理2...）
-        // 取第一个（原始客户端 IP）
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .unwrap_or_else(|| {
-            // 没有 X-Forwarded-For 头，直接使用远程地址
-            // 远程地址格式为 "IP:PORT"，需要提取 IP 部分
-            remote_addr
-                .split(':')
-                .next()
-                .unwrap_or(remote_addr)
-                .to_string()
+/// ### 返回值
+/// 请求头名称 -> 值（过长的值会被截断）
+fn capture_headers(headers: &axum::http::HeaderMap, names: &[String]) -> BTreeMap<String, String> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(name.as_str())?.to_str().ok()?;
+            Some((name.clone(), value.chars().take(MAX_HEADER_VALUE_CHARS).collect()))
         })
+        .collect()
+}
+
+/// 为已授权观众签发播放续连令牌
+///
+/// ### 返回值
+/// 未在直播（没有直播场次）时返回 `None`
+fn resume_token(
+    state: &super::super::AppState,
+    snapshot: &StreamSnapshot,
+    ip: &ClientIp,
+    session_id: &SessionId,
+) -> Option<String> {
+    snapshot
+        .session_id
+        .as_deref()
+        .map(|stream_session| state.resume.issue(ip, session_id, stream_session))
+}
+
+/// 为答题通过的观众签发回访令牌
+///
+/// 观众出示了有效的回访令牌时沿用其中的标识，否则以会话 ID 为标识
+///
+/// ### 返回值
+/// 未启用回访令牌时返回 `None`
+fn alumni_token(state: &super::super::AppState, presented: Option<&str>, session_id: &SessionId) -> Option<String> {
+    let signer = state.alumni.as_ref()?;
+    let subject = presented
+        .and_then(|token| signer.subject(token))
+        .map_or(session_id.as_str(), |(subject, _)| subject);
+    Some(signer.issue(subject))
+}
+
+/// 创建会话级日志 span
+///
+/// span 携带会话 ID、操作类型、客户端 IP（已脱敏）、请求开始时解析到的客户端状态和直播场次 ID，
+/// 按 `session_id` 过滤日志即可串起同一观众从连接、答题、拉流到聊天的全过程
+///
+/// ### 参数
+/// - `action`: 操作类型，如 `connect`、`answer`、`on_play`、`sendchat`
+/// - `ip`: 请求来源 IP，为 `None` 时（SRS 回调中的 IP 不可靠）使用注册时的 IP
+/// - `session_id`: 会话 ID
+pub(super) fn session_span(
+    state: &super::super::AppState,
+    action: &str,
+    ip: Option<&ClientIp>,
+    session_id: &SessionId,
+) -> tracing::Span {
+    use tracing::field::{debug, display, Empty};
+
+    let registered = state.srs_db.clients.read().get_client_status_any_ip(session_id);
+    let span = tracing::info_span!(
+        "session",
+        session_id = %session_id,
+        action,
+        ip = Empty,
+        status = Empty,
+        stream = Empty,
+    );
+    if let Some(ip) = ip.or(registered.as_ref().map(|(ip, _)| ip)) {
+        span.record("ip", display(redact::ip(ip)));
+    }
+    if let Some((_, status)) = &registered {
+        span.record("status", debug(status));
+    }
+    if let Some(stream_session) = state.srs_db.snapshot().session_id.as_deref() {
+        span.record("stream", stream_session);
+    }
+    span
+}
+
+/// 结束当前直播
+///
+/// 所有观众转为已结束状态，关闭并转储本场聊天室，推送 `stream_ended` 事件，
+/// 并通知 SRS 踢出推流端。编码器被踢出后通常会自动重连，`END_REFUSAL_WINDOW` 内
+/// 以相同密钥向同一目标的重连会被拒绝；超过该时间仍在重连时会开始新的直播，需在编码器端停止推流
+///
+/// ### 返回值
+/// - `true`: 已结束
+/// - `false`: `session_id` 不是当前主播，或当前未在推流（预登录主播在开播前请求结束）
+pub(super) fn end_stream(state: &super::super::AppState, session_id: &SessionId) -> bool {
+    let mut streamer = state.srs_db.streamer.write();
+
+    // 结束前记录推流目标和推流端 client_id，用于通知 SRS 踢出推流端
+    let target = streamer
+        .get_stream_target()
+        .map(|(app, stream)| (app.to_string(), stream.to_string()));
+    let publisher = streamer.publisher_client_id().map(str::to_string);
+
+    // 所有观众随之转为已结束状态
+    let end = StreamerEvent::EndRequested { session_id: session_id.clone() };
+    let StreamerTransition::Applied { event, .. } = streamer.apply(&mut state.srs_db.clients.write(), end) else {
+        return false;
+    };
+    drop(streamer);
+
+    // 关闭本场聊天室并转储聊天记录
+    if let Some(room) = state.chat_db.inner.write().close_room() {
+        if let Err(e) = disk_guard::dump_closed_room(room, state.config().dump_min_free_bytes, &state.events) {
+            tracing::warn!("转储聊天记录失败: {}", e);
+        }
+    }
+
+    if let Some(event) = event {
+        state.events.publish(event);
+    }
+    state.relays.stop_all();
+
+    // 通知 SRS 踢出推流端，避免继续接收推流
+    if let Some((app, stream)) = target {
+        let srs_api = state.srs_api.clone();
+        tokio::spawn(async move {
+            match srs_api.kick_publisher(&app, &stream, publisher.as_deref()).await {
+                Ok(true) => tracing::debug!("已踢出推流端 app={}, stream={}", app, stream),
+                Ok(false) => tracing::debug!("未找到推流端 app={}, stream={}", app, stream),
+                Err(e) => tracing::warn!("踢出推流端失败: {}", e),
+            }
+        });
+    }
+    true
+}
+
+/// 点击确认入场模式下代替题目显示的提示
+const CONFIRM_PROMPT: &str = "请点击确认进入直播间";
+
+/// 入场确认令牌长度
+const CONFIRM_TOKEN_LEN: usize = 16;
+
+/// 口算题的题目类型
+const ARITHMETIC_KIND: &str = "arithmetic";
+
+/// 生成点击确认入场的确认令牌
+fn confirm_question() -> BannerQuestion {
+    BannerQuestion {
+        question: CONFIRM_PROMPT.to_string(),
+        answer: ids::short_code(CONFIRM_TOKEN_LEN),
+        source: None,
+        meta: Some(QuestionMeta {
+            kind: EntryMode::Confirm.as_str().to_string(),
+            answer_kind: AnswerKind::Confirm,
+            choices: None,
+        }),
+    }
+}
+
+/// 生成一道两位数加减法口算题（结果不为负）
+fn arithmetic_question() -> BannerQuestion {
+    let mut rng = rand::thread_rng();
+    let (a, b) = (rng.gen_range(10..100u32), rng.gen_range(10..100u32));
+    let (question, answer) = if rng.gen_bool(0.5) {
+        (format!("{} + {} = ?", a, b), a + b)
+    } else {
+        (format!("{} - {} = ?", a.max(b), a.min(b)), a.max(b) - a.min(b))
+    };
+    BannerQuestion {
+        question,
+        answer: answer.to_string(),
+        source: None,
+        meta: Some(QuestionMeta {
+            kind: ARITHMETIC_KIND.to_string(),
+            answer_kind: AnswerKind::Number,
+            choices: None,
+        }),
+    }
+}
+
+/// 当前题库的可出题条目数量（配置了题目模板时为模板数据集的记录数）
+fn quiz_pool_size(state: &super::super::AppState) -> usize {
+    match &state.template_quiz {
+        Some(quiz) => quiz.pool_size(),
+        None => state.banner_db.current().pool_size(),
+    }
+}
+
+/// 从题库随机抽取一道题
+///
+/// 避开近期已向同一 IP 发放过的题目，并记录本次发放的题目。
+/// 启用网段分组时，只从该网段当前周期的卡池子集中抽题。
+/// 点击确认入场模式下不抽题，而是发放一个随机确认令牌作为答案。
+/// 题库可出题条目少于 `LIVE_SERVER_QUIZ_MIN_POOL` 时按 `LIVE_SERVER_QUIZ_FALLBACK` 改发口算题或确认令牌。
+///
+/// ### 返回值
+/// 返回抽到的题目，公开模式下题目文本会附带答案
+fn draw_question(state: &super::super::AppState, client_ip: &ClientIp, is_public: bool) -> BannerQuestion {
+    let entry_mode = state.srs_db.access.read().entry_mode();
+    if entry_mode == EntryMode::Confirm {
+        return confirm_question();
+    }
+    let config = state.config();
+    let mut is_public = is_public;
+    if quiz_pool_size(state) < config.quiz_min_pool.max(1) {
+        match config.quiz_fallback {
+            QuizFallback::Confirm => return confirm_question(),
+            QuizFallback::Public => is_public = true,
+            QuizFallback::Arithmetic => {}
+        }
+        let mut qa = arithmetic_question();
+        if is_public {
+            qa.question = format!("{}(answer=\"{}\")", qa.question, qa.answer);
+        }
+        return qa;
+    }
+    let recent = state.srs_db.clients.read().recent_questions(client_ip);
+    let mut qa = match (&state.template_quiz, &config.cohort_policy) {
+        // 模板题库：代替卡池题库出题
+        (Some(quiz), _) => quiz.random_question_excluding(&recent),
+        // 网段分组模式：只从该网段当前周期的卡池子集中抽题
+        (None, Some(policy)) => state.banner_db.current().random_question_in_subset(
+            policy.seed(client_ip.as_str(), chrono::Utc::now().timestamp()),
+            policy.subset_size,
+            &recent,
+        ),
+        (None, None) => state.banner_db.current().random_question_excluding(&recent),
+    };
+    state.srs_db.clients.write().record_served_question(client_ip, qa.question.clone());
+    if is_public {
+        qa.question = format!("{}(answer=\"{}\")", qa.question, qa.answer);
+    }
+    qa
 }
 
 // ============================================================================
@@ -203,7 +1024,58 @@ fn get_client_ip(headers: &axum::http::HeaderMap, remote_addr: &str) -> String {
 /// | 连接 | `action=connect` | 新用户连接，获取答题问题 |
 /// | 答题 | `answer=<答案>` | 提交答案验证 |
 /// | 查询状态 | `status=check` | 查询当前直播状态 |
-/// | 结束直播 | `end=true` | 主播结束直播 |
+/// | 结束直播 | `end=true` | 主播结束直播（编码器随后的自动重连在短时间内被拒绝） |
+/// | 状态提示 | `overlay=<starting_soon\|brb\|ending\|clear>` | 主播设置状态提示 |
+/// | 手动放行 | `grant=<session_id\|配对码>` | 主播手动放行观众 |
+/// | 发起配对 | `action=pair_start` | 已连接的未授权设备获取 6 位配对码（未完成的请求过多时返回 429） |
+/// | 确认配对 | `pair_confirm=<配对码>` | 已授权设备将授权复制给新设备 |
+/// | 换题 | `action=newquestion` | 待答题观众放弃当前题目并领取新题（次数和冷却见 `LIVE_SERVER_QUESTION_REFRESH_*`） |
+/// | 答题排行 | `leaderboard=quiz` | 本场直播答题最快的观众（昵称, 秒数） |
+///
+/// 连接时可附带 `client_version` 和 `capabilities`（如 `sse,websocket,uri_v2`），
+/// 声明了能力的客户端会额外得到 `video`（结构化播放目标）和 `endpoints`（推送接口）字段；
+/// 声明 `question_v2` 时返回题目的同时附带 `question_info`（`type`、`text`、`answer_kind` 为
+/// `number` / `text` / `choice`，选择题另有 `choices`），旧版前端仍只得到 `question` 字符串
+///
+/// 无人推流时连接会返回 `stream_status=ended` 和 `offline`（时间表、落地页）；
+/// `LIVE_SERVER_OFFLINE_CONNECT=defer` 时不发放题目也不直接放行，直播开始后重新连接即可答题；
+/// `LIVE_SERVER_OFFLINE_CONNECT=queue` 时答题通过的观众进入排队（`stream_status=waiting`，
+/// 附带 `queue_position`），开播时自动放行并通过 `/events` 推送 `stream_live` 事件
+///
+/// 设置了 `LIVE_SERVER_MAX_VIEWERS` 且人数已满时，拉流被拒绝的观众进入等候室，状态查询返回
+/// `stream_status=full` 和 `queue_position`；轮到空位时状态变回 `live`，重新拉流即可。
+/// 设置了 `LIVE_SERVER_BANDWIDTH_CEILING_KBPS` 且上行带宽已满时，尚未开始观看的观众同样得到
+/// `stream_status=full`（不带 `queue_position`）。`LIVE_SERVER_ADMISSION_PRIORITY` 列出的观众类别
+/// （回访观众、设置了昵称的观众）在等候室中优先，且不受带宽上限限制
+///
+/// 已授权观众的 `video_uri` 末尾附带 15 分钟内有效的续连令牌（`resume=...`），观众记录过期后
+/// 播放器凭令牌重连仍可拉流；令牌随每次返回的 `video_uri` 更新，重连时应使用最新的地址。
+/// 状态查询同样可以携带 `resume=<令牌>`，观众记录刚过期时恢复授权
+///
+/// 重复提交（双击、重试）上次已判定的答案是幂等的：已通过的答案再次返回播放地址（或排队位置），
+/// 封禁期内重复提交同一错误答案再次返回封禁响应；答错时响应附带封禁剩余秒数 `ban_remaining_secs`
+///
+/// 推流参数带 `entry=confirm` 的直播使用点击确认入场：连接时不发放题库题目，而是返回提示文本和
+/// `confirm_token`，观众在 `LIVE_SERVER_CONFIRM_DELAY` 秒后以 `answer=<confirm_token>` 提交即可入场
+/// （过早提交返回 429 且不计为答错）
+///
+/// 待答题时查询状态附带 `question_remaining_secs`（题目剩余作答秒数）和 `can_request_question`，
+/// 被封禁时附带 `ban_remaining_secs` 和 `can_request_question=false`，供前端显示倒计时
+///
+/// 配置了观看时段（`LIVE_SERVER_VIEWING_WINDOWS`）时，时段外的新观众连接返回 `stream_status=outside_window`
+/// 和下一次开放时间 `next_open`；配置了地区限制时，不允许进入的新观众连接返回 `stream_status=region_blocked`
+///
+/// 题库为空或可出题条目少于 `LIVE_SERVER_QUIZ_MIN_POOL` 时不发放题库题目，按 `LIVE_SERVER_QUIZ_FALLBACK`
+/// 改发口算题（`question_info.type=arithmetic`）、附带答案的口算题或确认令牌
+///
+/// 连接时携带主播签发的访客通行证（`guest=<短码>`）可在通行证有效期内跳过答题直接放行
+///
+/// 主播通过快捷操作关闭入场（`gate`）后，新连接不再发放题目，只返回 `stream_status=closed`；
+/// 已连接的观众（含已领到题目的待答题观众）不受影响
+///
+/// 推流地址携带 `session_id` 时，以该会话 ID 连接直接获得主播权限（见 `PublishOptions`）
+///
+/// 主播设置了欢迎语或房间规则时，观众授权后的首次状态查询附带 `welcome` / `rules`
 ///
 /// ### 响应格式
 /// ```json
@@ -212,7 +1084,9 @@ fn get_client_ip(headers: &axum::http::HeaderMap, remote_addr: &str) -> String {
 ///   "video_uri": "app=live&stream=test",
 ///   "question": "问题内容",
 ///   "is_publisher": true,
-///   "stream_status": "live"
+///   "stream_status": "live",
+///   "stream_overlay": "brb",
+///   "poll_interval_ms": 1000
 /// }
 /// ```
 pub async fn api_handler(
@@ -222,103 +1096,348 @@ pub async fn api_handler(
     connect_info: axum::extract::ConnectInfo<std::net::SocketAddr>,
 ) -> Response {
     // 提取客户端 IP 和会话 ID
-    let client_ip = get_client_ip(&headers, &connect_info.to_string());
-    let client_session_id = params.session_id.clone();
+    let client_ip = ClientIp::from_request(&headers, connect_info.0, &state.config().trusted_proxies);
+    let client_session_id = match SessionId::parse(&params.session_id) {
+        Ok(session_id) => session_id,
+        Err(reason) => return ApiError::BadRequest(reason.to_string()).into_response(),
+    };
+
+    let span = session_span(&state, params.action_label(), Some(&client_ip), &client_session_id);
+    handle_api_request(state, params, headers, connect_info.0.ip(), client_ip, client_session_id)
+        .instrument(span)
+        .await
+}
+
+/// 处理已解析出客户端身份的 API 请求（在会话 span 内执行）
+async fn handle_api_request(
+    state: Arc<super::super::AppState>,
+    params: ApiParams,
+    headers: axum::http::HeaderMap,
+    peer: std::net::IpAddr,
+    client_ip: ClientIp,
+    client_session_id: SessionId,
+) -> Response {
+    let client_headers = capture_headers(&headers, &state.config().client_headers);
+
+    tracing::debug!("API 请求");
 
-    tracing::debug!("API 请求: ip={}, session_id={}", client_ip, client_session_id);
+    // 连接时识别外部身份（需在获取数据库锁之前完成异步校验）
+    let external_identity = match (params.action.as_deref(), &state.external_auth) {
+        (Some("connect"), Some(auth)) => auth.identify(&headers, peer).await,
+        _ => None,
+    };
+    // 连接时校验人机验证令牌（未启用人机验证时视为通过）
+    let captcha_passed = match (params.action.as_deref(), &state.captcha) {
+        (Some("connect"), Some(verifier)) => match params.captcha.as_deref() {
+            Some(token) => verifier.verify(token, client_ip.as_str()).await,
+            None => false,
+        },
+        _ => true,
+    };
 
     // 初始化响应对象
     let mut response = ApiResponse::new();
 
-    // 获取数据库读锁（后续根据需要升级为写锁）
-    let srs_db_read = state.srs_db.inner.read();
+    // 状态查询携带续连令牌时，先恢复刚过期的观众记录
+    if let (Some(_), Some(token)) = (&params.status, &params.resume) {
+        state.resume_client(&client_session_id, token);
+    }
+
+    // 新连接先交给准入策略脚本决定（脚本不得在持有锁时执行，已登记的客户端不经过脚本）
+    let connect_decision = if params.action.as_deref() == Some("connect")
+        && state.script.is_some()
+        && !state.srs_db.clients.read().is_returning_client(&client_ip, &client_session_id)
+    {
+        state.script_decide(
+            HookPoint::Connect,
+            &[("ip", client_ip.as_str()), ("session_id", client_session_id.as_str())],
+        )
+    } else {
+        ScriptDecision::Default
+    };
+
+    // 直播状态从无锁快照读取，之后只持有客户端注册表的锁
+    let snapshot = state.srs_db.snapshot();
+    let is_public = snapshot.public;
+    // 获取客户端注册表读锁（后续根据需要升级为写锁）
+    let clients_read = state.srs_db.clients.read();
 
     // ========================================
     // 设置直播间名称（所有响应都包含）
     // ========================================
     // 如果主播设置了直播间名称，所有客户端都能看到
-    if let Some(name) = srs_db_read.get_stream_name() {
-        response = response.with_stream_name(name.to_string());
+    if let Some(name) = &snapshot.name {
+        response = response.with_stream_name(name.clone());
     }
 
     // ========================================
     // 处理连接请求 (action=connect)
     // ========================================
     if params.action.as_deref() == Some("connect") {
-        // 情况1: 已存在的客户端
-        if srs_db_read.has_client(&client_ip, &client_session_id) {
-            let status = srs_db_read.get_client_status(&client_ip, &client_session_id);
+        let capabilities =
+            ClientCapabilities::parse(params.client_version.as_deref(), params.capabilities.as_deref());
+        let stream_target = snapshot.target.clone();
+        let config = state.config();
+        let offline = !snapshot.is_streaming();
+        if offline {
+            response = response.with_offline(&config);
+        }
+        // 推流参数绑定的主播会话：直接获得主播权限，无需再输入推流密钥
+        if snapshot.push_session.as_ref() == Some(&client_session_id)
+            && !clients_read.client_is_publisher(&client_ip, &client_session_id)
+        {
+            drop(clients_read);
+            let mut clients_write = state.srs_db.clients.write();
+            if !clients_write.has_client(&client_ip, &client_session_id) {
+                clients_write.add_client(client_ip.clone(), client_session_id.clone());
+                clients_write.set_client_headers(&client_ip, &client_session_id, client_headers);
+                clients_write.set_client_capabilities(&client_ip, &client_session_id, capabilities);
+            }
+            clients_write.transition(&client_ip, &client_session_id, ClientStatus::Legal);
+            clients_write.set_client_publisher(&client_ip, &client_session_id);
+            drop(clients_write);
+            tracing::debug!("({}, {}): 推流参数绑定的主播会话已连接", redact::ip(&client_ip), client_session_id);
+            response = response
+                .with_publisher()
+                .with_publisher_token(state.publisher_tokens.as_ref().map(|t| t.issue(&client_session_id)))
+                .with_live_stream(&snapshot, config.latency_mode)
+                .with_resume_token(resume_token(&state, &snapshot, &client_ip, &client_session_id));
+            return Json(response).into_response();
+        }
+        // 情况1: 已存在的客户端（上一场直播已结束的客户端、被推迟发题的客户端视为新用户）
+        let existing = clients_read.is_returning_client(&client_ip, &client_session_id);
+        // 是否携带有效的回访观众令牌
+        let has_alumni_token = match (params.alumni.as_deref(), state.alumni.as_ref()) {
+            (Some(token), Some(signer)) => signer.verify(token),
+            _ => false,
+        };
+        // 准入策略脚本的决定只对新连接生效
+        let decision = if existing { ScriptDecision::Default } else { connect_decision };
+        if decision == ScriptDecision::Deny {
+            tracing::debug!("({}, {}): 准入脚本拒绝连接", redact::ip(&client_ip), client_session_id);
+            return forbidden_json_response();
+        }
+        if existing {
+            let status = clients_read.get_client_status(&client_ip, &client_session_id);
 
             match status {
                 // 已通过验证的用户（Legal/Playing/Resting）
                 // 直接返回播放地址
                 Some(ClientStatus::Legal) | Some(ClientStatus::Playing) | Some(ClientStatus::Resting) => {
-                    if let Some(uri) = srs_db_read.get_stream_uri() {
-                        response = response.with_video_uri(uri.to_string());
-                    }
+                    response = response
+                        .with_live_stream(&snapshot, config.latency_mode)
+                        .with_resume_token(resume_token(&state, &snapshot, &client_ip, &client_session_id));
                     // 如果是主播，标记 is_publisher=true
-                    if srs_db_read.client_is_publisher(&client_ip, &client_session_id) {
+                    if clients_read.client_is_publisher(&client_ip, &client_session_id) {
                         response = response.with_publisher();
-                        tracing::debug!("({}, {}): 主播已连接", client_ip, client_session_id);
+                        tracing::debug!("({}, {}): 主播已连接", redact::ip(&client_ip), client_session_id);
                     }
                 }
+                // 开播前已答题通过、正在排队的用户
+                Some(ClientStatus::Waiting) => {
+                    response = response
+                        .with_queue_position(
+                            StreamStatus::Waiting,
+                            clients_read.waiting_position(&client_ip, &client_session_id),
+                        );
+                }
                 // 答错题被封禁的用户（Nil）
-                // 返回假的视频地址作为惩罚
+                // 返回诱饵流（或假的视频地址）作为惩罚
                 Some(ClientStatus::Nil) => {
-                    response = response.with_video_uri("app=genshin&straem=impact".to_string());
-                    tracing::debug!("({}, {}): 被封禁的客户端（答错题）", client_ip, client_session_id);
+                    response = response
+                        .with_decoy(&state.config(), "app=genshin&straem=impact")
+                        .with_pairing_code(clients_read.get_client_pairing_code(&client_ip, &client_session_id));
+                    tracing::debug!("({}, {}): 被封禁的客户端（答错题）", redact::ip(&client_ip), client_session_id);
                 }
                 // 其他状态（主要是 Pending）- 再次返回题目
                 _ => {
-                    if let Some((q, _)) = srs_db_read.get_client_qa(&client_ip, &client_session_id) {
-                        response = response.with_question(q.to_string());
+                    if let Some((q, a)) = clients_read.get_client_qa(&client_ip, &client_session_id) {
+                        let meta = clients_read.get_client_question_meta(&client_ip, &client_session_id);
+                        response = response
+                            .with_question(q.to_string())
+                            .with_confirm_token(meta, a)
+                            .with_question_meta(meta.cloned(), &capabilities);
                     }
+                    response = response
+                        .with_pairing_code(clients_read.get_client_pairing_code(&client_ip, &client_session_id));
                 }
             }
+        } else if let Some(denial) = viewing_policy::check(&config, &headers, chrono::Local::now()) {
+            // 情况2: 不在观看时段内或地区受限 - 只登记客户端（主播仍可用推流密钥登录），不发放题目
+            drop(clients_read);
+            let mut clients_write = state.srs_db.clients.write();
+            clients_write.add_client(client_ip.clone(), client_session_id.clone());
+            clients_write.set_client_headers(&client_ip, &client_session_id, client_headers);
+            clients_write.set_client_capabilities(&client_ip, &client_session_id, capabilities.clone());
+            tracing::debug!("({}, {}): 观看限制拒绝新观众: {:?}", redact::ip(&client_ip), client_session_id, denial);
+            response = response.with_viewing_denial(&denial);
+        } else if snapshot.entries_closed && !offline {
+            // 情况3: 主播暂停接纳新观众 - 不发放题目，已授权的观众不受影响
+            tracing::debug!("({}, {}): 入场已关闭，不接纳新观众", redact::ip(&client_ip), client_session_id);
+            response = response.with_entries_closed();
+        } else if offline && config.offline_connect == OfflineConnect::Defer {
+            // 情况4: 无人推流且配置为推迟发题 - 只登记客户端（主播仍可预登录），直播开始后重新连接
+            drop(clients_read);
+            let mut clients_write = state.srs_db.clients.write();
+            clients_write.add_client(client_ip.clone(), client_session_id.clone());
+            clients_write.set_client_headers(&client_ip, &client_session_id, client_headers);
+            clients_write.set_client_capabilities(&client_ip, &client_session_id, capabilities.clone());
+            tracing::debug!("({}, {}): 无人推流，推迟发放题目", redact::ip(&client_ip), client_session_id);
+        } else if has_alumni_token
+            || decision == ScriptDecision::Allow
+            || external_identity.is_some()
+            || params.guest.as_deref().is_some_and(|code| state.invites.redeem_guest_pass(code))
+        {
+            // 情况5: 持有有效回访令牌或访客通行证、被准入脚本放行或通过外部身份认证的用户 - 跳过答题直接放行
+            drop(clients_read);
+            let mut clients_write = state.srs_db.clients.write();
+            clients_write.add_client(client_ip.clone(), client_session_id.clone());
+            clients_write.set_client_headers(&client_ip, &client_session_id, client_headers);
+            clients_write.set_client_capabilities(&client_ip, &client_session_id, capabilities.clone());
+            if has_alumni_token {
+                clients_write.set_client_alumni(&client_ip, &client_session_id);
+            }
+            if offline && config.offline_connect == OfflineConnect::Queue {
+                let position = clients_write.enqueue_waiting(&client_ip, &client_session_id);
+                response = response.with_queue_position(StreamStatus::Waiting, position);
+                tracing::debug!("({}, {}): 跳过答题，进入开播前排队", redact::ip(&client_ip), client_session_id);
+            } else {
+                clients_write.transition(&client_ip, &client_session_id, ClientStatus::Legal);
+                response = response
+                    .with_live_stream(&snapshot, config.latency_mode)
+                    .with_resume_token(resume_token(&state, &snapshot, &client_ip, &client_session_id));
+                tracing::debug!("({}, {}): 跳过答题直接放行", redact::ip(&client_ip), client_session_id);
+            }
+        } else if !captcha_passed {
+            // 情况6: 新用户未通过人机验证 - 不发放题目
+            tracing::debug!("({}, {}): 人机验证未通过", redact::ip(&client_ip), client_session_id);
+            return Json(response.with_captcha_required()).into_response();
         } else {
-            // 情况2: 新用户 - 发放答题问题            
+            // 情况7: 新用户 - 发放答题问题
             // 检查是否为公开模式（无需答题）
-            let is_public = {
-                srs_db_read.is_public()
-            };
-            drop(srs_db_read);
-            
-            // 从题库随机抽取一道题
-            let (q, a) = state.banner_db.random_question();
+            drop(clients_read);
 
-            // 公开模式下，题目会附带答案
-            let q_with_answer = if is_public {
-                format!("{}(answer=\"{}\")", q, a)
-            } else {
-                q
-            };
+            // 从题库随机抽取一道题
+            let BannerQuestion {
+                question: q_with_answer,
+                answer: a,
+                meta,
+                ..
+            } = draw_question(&state, &client_ip, is_public);
 
             tracing::debug!(
                 "({}, {}): 新客户端: 问题=\"{}\", 答案=\"{}\"",
-                client_ip,
+                redact::ip(&client_ip),
                 client_session_id,
                 q_with_answer,
-                a
+                redact::text(&a)
             );
 
             // 在数据库中注册新客户端并存储题目
             {
-                let mut srs_db_write = state.srs_db.inner.write();
-                srs_db_write.add_client(client_ip.clone(), client_session_id.clone());
-                srs_db_write.set_client_qa(&client_ip, &client_session_id, q_with_answer.clone(), a);
+                let mut clients_write = state.srs_db.clients.write();
+                clients_write.add_client(client_ip.clone(), client_session_id.clone());
+                clients_write.set_client_headers(&client_ip, &client_session_id, client_headers);
+                clients_write.set_client_capabilities(&client_ip, &client_session_id, capabilities.clone());
+                response = response.with_confirm_token(meta.as_ref(), &a);
+                clients_write.set_client_qa(&client_ip, &client_session_id, q_with_answer.clone(), a, meta.clone());
+                response = response
+                    .with_pairing_code(clients_write.get_client_pairing_code(&client_ip, &client_session_id));
             }
 
-            response = response.with_question(q_with_answer);
+            response = response.with_question(q_with_answer).with_question_meta(meta, &capabilities);
         }
+        let websocket_enabled = state.feature(Features::WEBSOCKET_CHAT);
+        let response = response.with_capabilities(&capabilities, stream_target, websocket_enabled);
         return Json(response).into_response();
     }
 
+    // ========================================
+    // 处理跨设备配对发起请求 (action=pair_start)
+    // ========================================
+    if params.action.as_deref() == Some("pair_start") {
+        // 只有已连接（领过题）且尚未授权的设备可以发起配对
+        if !clients_read.has_client(&client_ip, &client_session_id)
+            || clients_read.has_authorized_client(&client_ip, &client_session_id)
+        {
+            return forbidden_json_response();
+        }
+        drop(clients_read);
+
+        let mut db = state.srs_db.clients.write();
+        let code = match db.start_pairing(&client_ip, &client_session_id) {
+            Ok(code) => code,
+            Err(retry_after) => return ApiError::RateLimited { retry_after }.into_response(),
+        };
+        tracing::debug!("({}, {}): 发起跨设备配对", redact::ip(&client_ip), client_session_id);
+        return Json(response.with_pair_code(code)).into_response();
+    }
+
+    // ========================================
+    // 处理换题请求 (action=newquestion)
+    // ========================================
+    if params.action.as_deref() == Some("newquestion") {
+        // 只有已领取题目、待答题的客户端可以换题
+        if clients_read.get_client_status(&client_ip, &client_session_id) != Some(ClientStatus::Pending)
+            || clients_read.is_awaiting_question(&client_ip, &client_session_id)
+        {
+            return ApiError::NotPending.into_response();
+        }
+        let config = state.config();
+        match clients_read.check_question_refresh(
+            &client_ip,
+            &client_session_id,
+            config.question_refresh_limit,
+            config.question_refresh_cooldown_secs,
+        ) {
+            Ok(()) => {}
+            Err(Some(secs)) => return ApiError::RateLimited { retry_after: secs }.into_response(),
+            Err(None) => return ApiError::Forbidden("question refresh limit reached".to_string()).into_response(),
+        }
+        drop(clients_read);
+
+        let qa = draw_question(&state, &client_ip, is_public);
+        response = response.with_confirm_token(qa.meta.as_ref(), &qa.answer);
+        let mut db = state.srs_db.clients.write();
+        db.set_client_qa(&client_ip, &client_session_id, qa.question.clone(), qa.answer, qa.meta.clone());
+        db.record_question_refresh(&client_ip, &client_session_id);
+        tracing::debug!("({}, {}): 主动换题", redact::ip(&client_ip), client_session_id);
+        let caps = db.get_client(&client_ip, &client_session_id).map(|c| c.capabilities.clone()).unwrap_or_default();
+        return Json(response.with_question(qa.question).with_question_meta(qa.meta, &caps)).into_response();
+    }
+
+    // ========================================
+    // 处理跨设备配对确认请求 (pair_confirm=<配对码>)
+    // ========================================
+    if let Some(code) = params.pair_confirm {
+        // 只有已授权的会话可以确认配对
+        if !clients_read.has_authorized_client(&client_ip, &client_session_id) {
+            return forbidden_json_response();
+        }
+        drop(clients_read);
+
+        let mut db = state.srs_db.clients.write();
+        return match db.confirm_pairing(&code) {
+            Some((ip, session_id)) => {
+                tracing::debug!(
+                    "({}, {}): 跨设备配对成功，授权新设备 ({}, {})",
+                    redact::ip(&client_ip),
+                    client_session_id,
+                    redact::ip(&ip),
+                    session_id
+                );
+                (axum::http::StatusCode::OK, "\"ok\"").into_response()
+            }
+            None => ApiError::NotFound("pairing code not found or expired".to_string()).into_response(),
+        };
+    }
+
     // ========================================
     // 处理答题提交 (answer=<答案>)
     // ========================================
     if let Some(answer) = params.answer {
         // 检查客户端是否存在
-        if !srs_db_read.has_client(&client_ip, &client_session_id) {
+        if !clients_read.has_client(&client_ip, &client_session_id) {
             return forbidden_json_response();
         }
 
@@ -326,56 +1445,209 @@ pub async fn api_handler(
         // 这是主播用于验证身份的方式
         // 主播可以跳过答题，直接输入推流密钥验证身份
         if answer.starts_with("secret_") {
-            drop(srs_db_read);
-            let mut db = state.srs_db.inner.write();
+            drop(clients_read);
+            // 锁顺序：streamer → clients → access
+            let mut streamer = state.srs_db.streamer.write();
+            let mut db = state.srs_db.clients.write();
+            let mut access = state.srs_db.access.write();
+
+            // 失败次数过多的 IP 处于锁定期内，直接拒绝
+            if let Some(secs) = access.secret_guard.locked_for(&client_ip) {
+                return ApiError::RateLimited { retry_after: secs }.into_response();
+            }
+
+            // 密钥正确时，按登录策略做额外校验
+            if streamer.check_streamer_secret(&access.verifier, &answer) {
+                match state.config().publisher_login_policy {
+                    PublisherLoginPolicy::Open => {}
+                    PublisherLoginPolicy::PushIp => {
+                        if streamer.streamer_ip() != Some(&client_ip) {
+                            tracing::warn!("({}, {}): 主播登录被拒绝，非推流 IP", redact::ip(&client_ip), client_session_id);
+                            return forbidden_json_response();
+                        }
+                    }
+                    PublisherLoginPolicy::OneTimeCode => match params.otp.as_deref() {
+                        Some(code) if streamer.verify_publisher_otp(&client_session_id, code) => {}
+                        Some(_) => {
+                            access.secret_guard.record_failure(&client_ip, "otp");
+                            tracing::warn!("({}, {}): 主播一次性验证码错误", redact::ip(&client_ip), client_session_id);
+                            return forbidden_json_response();
+                        }
+                        None => {
+                            let code = streamer.issue_publisher_otp(&client_session_id);
+                            tracing::info!("({}, {}): 主播登录验证码: {}", redact::ip(&client_ip), client_session_id, code);
+                            return Json(response.with_otp_required()).into_response();
+                        }
+                    },
+                    PublisherLoginPolicy::SingleSession => {
+                        if let Some(current) = streamer.publisher_session().cloned() {
+                            if current != client_session_id {
+                                if params.takeover.as_deref() != Some("true") {
+                                    return Json(response.with_takeover_required()).into_response();
+                                }
+                                db.revoke_client_publisher(&current);
+                                tracing::info!("({}, {}): 接管主播会话 {}", redact::ip(&client_ip), client_session_id, current);
+                            }
+                        }
+                    }
+                }
+            }
 
             // 验证 secret 是否正确
-            if db.connect_streamer(client_session_id.clone(), &answer) {
+            if streamer.connect_streamer(&mut db, &access.verifier, client_session_id.clone(), &answer) {
                 // 验证成功 - 标记为主播
-                db.update_client_activity(&client_ip, &client_session_id, ClientStatus::Legal);
+                access.secret_guard.record_success(&client_ip);
+                db.transition(&client_ip, &client_session_id, ClientStatus::Legal);
                 db.set_client_publisher(&client_ip, &client_session_id);
-                response = response.with_publisher();
-                if let Some(uri) = db.get_stream_uri() {
-                    response = response.with_video_uri(uri.to_string());
+                response = response.with_publisher().with_publisher_token(
+                    state.publisher_tokens.as_ref().map(|t| t.issue(&client_session_id)),
+                );
+                response = response
+                    .with_live_stream(&snapshot, state.config().latency_mode)
+                    .with_resume_token(resume_token(&state, &snapshot, &client_ip, &client_session_id));
+                if streamer.is_publisher_elect() {
+                    tracing::debug!("({}, {}): 主播预登录成功，等待推流", redact::ip(&client_ip), client_session_id);
+                } else {
+                    tracing::debug!("({}, {}): 主播身份验证成功", redact::ip(&client_ip), client_session_id);
                 }
-                tracing::debug!("({}, {}): 主播身份验证成功", client_ip, client_session_id);
             } else {
-                // 验证失败 - 返回假的视频地址
-                db.update_client_activity(&client_ip, &client_session_id, ClientStatus::Nil);
-                response = response.with_video_uri("app=ehviewer&straem=lolicon".to_string());
-                tracing::debug!("({}, {}): 无效的主播密钥", client_ip, client_session_id);
+                // 验证失败 - 记录失败次数，返回诱饵流（或假的视频地址）
+                access.secret_guard.record_failure(&client_ip, "api");
+                db.transition(&client_ip, &client_session_id, ClientStatus::Nil);
+                response = response.with_decoy(&state.config(), "app=ehviewer&straem=lolicon");
+                tracing::debug!("({}, {}): 无效的主播密钥", redact::ip(&client_ip), client_session_id);
             }
             return Json(response).into_response();
         }
 
-        // 普通用户答题
-        // 只允许 Pending 状态的用户提交答案
-        let status = srs_db_read.get_client_status(&client_ip, &client_session_id);
-        if status != Some(ClientStatus::Pending) {
-            return Json(json!({"error": "Not in pending state"})).into_response();
-        }
+        // 普通用户答题：检查、判定和状态转换在同一把写锁内完成（准入脚本可直接决定结果）
+        drop(clients_read);
+        // 准入脚本在获取写锁之前执行，锁内只做答案比对
+        let verdict = state.script_decide(
+            HookPoint::Answer,
+            &[("ip", client_ip.as_str()), ("session_id", client_session_id.as_str()), ("answer", &answer)],
+        );
+        let config = state.config();
+        let mut clients_write = state.srs_db.clients.write();
+        // 持有写锁后重新读取快照：请求开始后恰好开播时，按旧快照会让答对的观众错过开播放行而滞留在排队中
+        let snapshot = state.srs_db.snapshot();
+        let queue = !snapshot.is_streaming() && config.offline_connect == OfflineConnect::Queue;
+        let outcome = clients_write.validate_and_apply_answer(
+            &client_ip,
+            &client_session_id,
+            &answer,
+            config.confirm_delay_secs as f64,
+            queue,
+            |correct_answer| match verdict {
+                ScriptDecision::Allow => true,
+                ScriptDecision::Deny => false,
+                ScriptDecision::Default => answer_matches(correct_answer, &answer),
+            },
+        );
 
-        drop(srs_db_read);
-        let mut srs_db_write = state.srs_db.inner.write();
-
-        // 获取存储的正确答案并验证
-        let correct = srs_db_write
-            .get_client_qa(&client_ip, &client_session_id)
-            .map(|(_, correct_answer)| correct_answer == answer)
-            .unwrap_or(false);
+        let elapsed = match outcome {
+            AnswerOutcome::UnknownClient => return forbidden_json_response(),
+            AnswerOutcome::RepeatWaiting { position } => {
+                tracing::debug!("({}, {}): 重复提交已通过的答案", redact::ip(&client_ip), client_session_id);
+                return Json(
+                    response
+                        .with_queue_position(StreamStatus::Waiting, position)
+                        .with_alumni_token(alumni_token(&state, params.alumni.as_deref(), &client_session_id)),
+                )
+                .into_response();
+            }
+            AnswerOutcome::RepeatAdmitted => {
+                tracing::debug!("({}, {}): 重复提交已通过的答案", redact::ip(&client_ip), client_session_id);
+                return Json(
+                    response
+                        .with_live_stream(&snapshot, config.latency_mode)
+                        .with_resume_token(resume_token(&state, &snapshot, &client_ip, &client_session_id))
+                        .with_alumni_token(alumni_token(&state, params.alumni.as_deref(), &client_session_id)),
+                )
+                .into_response();
+            }
+            AnswerOutcome::RepeatRejected { ban_remaining } => {
+                tracing::debug!("({}, {}): 封禁期内重复提交错误答案", redact::ip(&client_ip), client_session_id);
+                return Json(
+                    response
+                        .with_decoy(&state.config(), "app=ehviewer&straem=lolicon")
+                        .with_ban_remaining(ban_remaining),
+                )
+                .into_response();
+            }
+            AnswerOutcome::AlreadyAnswered => return ApiError::AlreadyAnswered.into_response(),
+            AnswerOutcome::NotPending => return ApiError::NotPending.into_response(),
+            // 题目被推迟发放：直播未开始时无题可答，开始后需重新连接获取题目
+            AnswerOutcome::AwaitingQuestion => {
+                return if snapshot.is_streaming() {
+                    ApiError::NotPending.into_response()
+                } else {
+                    ApiError::StreamOffline.into_response()
+                };
+            }
+            // 超过作答时限：拒绝作答并自动发放新题目
+            AnswerOutcome::QuestionExpired => {
+                drop(clients_write);
 
-        if correct {
-            // 答对了 - 状态改为 Legal，返回播放地址
-            srs_db_write.update_client_activity(&client_ip, &client_session_id, ClientStatus::Legal);
-            if let Some(uri) = srs_db_write.get_stream_uri() {
-                response = response.with_video_uri(uri.to_string());
+                let qa = draw_question(&state, &client_ip, is_public);
+                response = response.with_confirm_token(qa.meta.as_ref(), &qa.answer);
+                let mut clients_write = state.srs_db.clients.write();
+                clients_write.set_client_qa(&client_ip, &client_session_id, qa.question.clone(), qa.answer, qa.meta.clone());
+                tracing::debug!("({}, {}): 作答超时，发放新题目", redact::ip(&client_ip), client_session_id);
+                let caps = clients_write
+                    .get_client(&client_ip, &client_session_id)
+                    .map(|c| c.capabilities.clone())
+                    .unwrap_or_default();
+                return Json(
+                    response
+                        .with_question_expired()
+                        .with_question(qa.question)
+                        .with_question_meta(qa.meta, &caps),
+                )
+                .into_response();
             }
-        } else {
-            // 答错了 - 状态改为 Nil（被封禁），返回假地址
-            srs_db_write.update_client_activity(&client_ip, &client_session_id, ClientStatus::Nil);
-            response = response.with_video_uri("app=ehviewer&straem=lolicon".to_string());
-            tracing::debug!("({}, {}): 答案错误", client_ip, client_session_id);
-        }
+            // 点击确认入场：领取令牌后需等待片刻才能提交，过早提交不计为答错
+            AnswerOutcome::TooEarly { retry_after } => {
+                tracing::debug!("({}, {}): 过早提交入场确认", redact::ip(&client_ip), client_session_id);
+                return ApiError::RateLimited { retry_after }.into_response();
+            }
+            AnswerOutcome::Rejected { ban_remaining } => {
+                // 答错了 - 已被封禁，返回诱饵流（或假地址）
+                drop(clients_write);
+                state.quiz_health.record_answer(false);
+                tracing::debug!("({}, {}): 答案错误", redact::ip(&client_ip), client_session_id);
+                return Json(
+                    response
+                        .with_decoy(&state.config(), "app=ehviewer&straem=lolicon")
+                        .with_ban_remaining(ban_remaining),
+                )
+                .into_response();
+            }
+            AnswerOutcome::Queued { position, elapsed } => {
+                // 答对了但尚未开播 - 已进入排队，开播时自动放行
+                response = response.with_queue_position(StreamStatus::Waiting, position);
+                tracing::debug!("({}, {}): 答题通过，进入开播前排队", redact::ip(&client_ip), client_session_id);
+                elapsed
+            }
+            AnswerOutcome::Admitted { elapsed } => {
+                // 答对了 - 已获得观看许可，返回播放地址
+                response = response
+                    .with_live_stream(&snapshot, config.latency_mode)
+                    .with_resume_token(resume_token(&state, &snapshot, &client_ip, &client_session_id));
+                elapsed
+            }
+        };
+        drop(clients_write);
+        state.quiz_health.record_answer(true);
+        response = response.with_alumni_token(alumni_token(&state, params.alumni.as_deref(), &client_session_id));
+
+        // 记录答题用时，用于排行榜
+        state
+            .chat_db
+            .inner
+            .write()
+            .active_mut()
+            .record_answer_time(&client_ip, &client_session_id, elapsed);
         return Json(response).into_response();
     }
 
@@ -383,44 +1655,189 @@ pub async fn api_handler(
     // 处理结束直播请求 (end=true)
     // ========================================
     if params.end.as_deref() == Some("true") {
-        drop(srs_db_read);
-        let mut db = state.srs_db.inner.write();
+        drop(clients_read);
 
         // 只有当前主播可以结束直播
-        if db.end_streaming(Some(&client_session_id)) {
-            // 清空聊天记录
-            state.chat_db.inner.write().reset();
-            tracing::debug!("({}, {}): 主播结束了直播", client_ip, client_session_id);
+        if end_stream(&state, &client_session_id) {
+            tracing::debug!("({}, {}): 主播结束了直播", redact::ip(&client_ip), client_session_id);
             return (axum::http::StatusCode::OK, "\"ok\"").into_response();
+        }
+        let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+        if is_publisher && !state.srs_db.is_streaming() {
+            // 主播在未推流时请求结束直播
+            return ApiError::StreamOffline.into_response();
         } else {
             return forbidden_json_response();
         }
     }
 
+    // ========================================
+    // 处理手动放行请求 (grant=<session_id|配对码>)
+    // ========================================
+    if let Some(target) = params.grant {
+        drop(clients_read);
+        let mut db = state.srs_db.clients.write();
+
+        // 只有主播可以手动放行
+        if !db.client_is_publisher(&client_ip, &client_session_id) {
+            return forbidden_json_response();
+        }
+        return match db.grant_legal(&target) {
+            Some((ip, session_id)) => {
+                tracing::info!("({}, {}): 主播手动放行 ({}, {})", redact::ip(&client_ip), client_session_id, redact::ip(&ip), session_id);
+                (axum::http::StatusCode::OK, "\"ok\"").into_response()
+            }
+            None => ApiError::NotFound(format!("client {} not found", target)).into_response(),
+        };
+    }
+
+    // ========================================
+    // 处理状态提示设置请求 (overlay=<状态>)
+    // ========================================
+    if let Some(overlay) = params.overlay {
+        let overlay = match overlay.as_str() {
+            "clear" => None,
+            other => match StreamOverlay::parse(other) {
+                Some(o) => Some(o),
+                None => return forbidden_json_response(),
+            },
+        };
+
+        // 只有主播可以设置状态提示
+        if !clients_read.client_is_publisher(&client_ip, &client_session_id) {
+            return forbidden_json_response();
+        }
+        drop(clients_read);
+        state.srs_db.streamer.write().set_overlay(overlay);
+
+        let notice = match overlay {
+            Some(o) => format!("主播：{}", o.label()),
+            None => "主播回来了".to_string(),
+        };
+        state.chat_db.inner.write().active_mut().add_system(notice, false);
+
+        state.events.publish(StreamEvent::OverlayChanged {
+            overlay: overlay.map(|o| o.as_str().to_string()),
+        });
+        tracing::debug!("({}, {}): 主播设置状态提示 {:?}", redact::ip(&client_ip), client_session_id, overlay);
+        return (axum::http::StatusCode::OK, "\"ok\"").into_response();
+    }
+
+    // ========================================
+    // 处理排行榜查询请求 (leaderboard=quiz)
+    // ========================================
+    if let Some(board) = params.leaderboard.as_deref() {
+        if board != "quiz" {
+            return ApiError::BadRequest(format!("unknown leaderboard {}", board)).into_response();
+        }
+        drop(clients_read);
+        let records = state
+            .chat_db
+            .inner
+            .read()
+            .active()
+            .quiz_leaderboard(LEADERBOARD_SIZE);
+        return Json(response.with_quiz_leaderboard(records)).into_response();
+    }
+
     // ========================================
     // 处理状态查询请求 (status=check)
     // ========================================
     if params.status.is_some() {
+        // 人数已满时在等候室中的位置
+        let config = state.config();
+        let viewer_queue = clients_read.viewer_queue_wait(
+            &client_ip,
+            &client_session_id,
+            config.max_viewers,
+            &config.admission_priority,
+        );
+        // 上行带宽已满时不再接纳新观众（优先放行的观众除外）
+        let bandwidth_full = state
+            .streaming_info
+            .inner
+            .read()
+            .bandwidth_exceeded(config.bandwidth_ceiling_kbps)
+            && !clients_read.client_has_priority(&client_ip, &client_session_id, &config.admission_priority);
         // 根据当前状态确定返回的状态值
-        let stream_status = if !srs_db_read.has_client(&client_ip, &client_session_id) {
+        let stream_status = if !clients_read.has_client(&client_ip, &client_session_id) {
             // 客户端不存在
             StreamStatus::Unregistered
         } else {
-            match srs_db_read.get_client_status(&client_ip, &client_session_id) {
+            match clients_read.get_client_status(&client_ip, &client_session_id) {
                 // 答错题被禁
                 Some(ClientStatus::Nil) => StreamStatus::Banned,
+                // 主播已结束直播
+                Some(ClientStatus::Ended) => StreamStatus::Ended,
+                // 题目被推迟发放且直播尚未开始
+                Some(ClientStatus::Pending)
+                    if !snapshot.is_streaming()
+                        && clients_read.is_awaiting_question(&client_ip, &client_session_id) =>
+                {
+                    StreamStatus::Ended
+                }
                 // 待答题
                 Some(ClientStatus::Pending) => StreamStatus::Pending,
+                // 开播前排队中
+                Some(ClientStatus::Waiting) => StreamStatus::Waiting,
                 // 主播没有在推流
-                _ if !srs_db_read.is_streaming() => StreamStatus::Ended,
+                _ if !snapshot.is_streaming() => StreamStatus::Ended,
                 // 主播推流中但处于暂停状态
-                _ if !srs_db_read.is_actively_streaming() => StreamStatus::Paused,
+                _ if !snapshot.is_actively_streaming() => StreamStatus::Paused,
+                // 直播中但人数已满，仍在等候室中
+                _ if viewer_queue.is_some() => StreamStatus::Full,
+                // 直播中但带宽已满，尚未开始观看的观众暂时无法加入
+                Some(ClientStatus::Legal) if bandwidth_full => StreamStatus::Full,
                 // 正常直播中
                 _ => StreamStatus::Live,
             }
         };
 
         response = response.with_stream_status(stream_status.as_str());
+        match stream_status {
+            StreamStatus::Waiting => {
+                response = response.with_queue_position(
+                    StreamStatus::Waiting,
+                    clients_read.waiting_position(&client_ip, &client_session_id),
+                );
+            }
+            StreamStatus::Full => response = response.with_queue_position(StreamStatus::Full, viewer_queue),
+            StreamStatus::Banned => {
+                response = response.with_ban_hints(clients_read.ban_remaining_secs(&client_ip, &client_session_id));
+            }
+            StreamStatus::Pending => {
+                response = response
+                    .with_question_hints(clients_read.question_remaining_secs(&client_ip, &client_session_id));
+            }
+            _ => {}
+        }
+        if let Some(overlay) = snapshot.overlay {
+            response = response.with_stream_overlay(overlay);
+        }
+        if snapshot.is_streaming() {
+            response = response
+                .with_recording(snapshot.recording)
+                .with_latency_mode(snapshot.latency_mode(config.latency_mode));
+        }
+        // 授权后的首次状态查询附带欢迎语和房间规则
+        let welcome_due = !snapshot.notice.is_empty()
+            && clients_read.needs_welcome(&client_ip, &client_session_id);
+        if welcome_due {
+            response = response.with_notice(&snapshot.notice);
+        }
+        let paused = snapshot.is_paused();
+        drop(clients_read);
+        // 等候室中的观众靠状态查询保留位置
+        if viewer_queue.is_some() || welcome_due {
+            let mut clients = state.srs_db.clients.write();
+            if viewer_queue.is_some() {
+                clients.touch_queued_viewer(&client_ip, &client_session_id);
+            }
+            if welcome_due {
+                clients.mark_welcomed(&client_ip, &client_session_id);
+            }
+        }
+        response = response.with_poll_interval(state.suggest_poll_interval(paused));
         return Json(response).into_response();
     }
 
@@ -4,19 +4,53 @@
 //! - 客户端连接（hello）
 //! - 设置昵称（setname）
 //! - 设置直播间名称（setlivename）
+//! - 设置欢迎语和房间规则（setwelcome）
 //! - 获取聊天消息（getchat）
 //! - 发送聊天消息（sendchat）
 //! - 获取观众人数（getaudiences）
 //! - 保存聊天快照（savesnapshot）
+//! - 导出用户消息（exportuser）
+//! - 设置进出提示偏好（setannounce）
+//! - 排行榜（setleaderboard / getleaderboard）
+//! - 开关图片嵌入（setembeds）
+//! - 屏蔽用户（blockuser）
+//! - 偏好设置（getprefs / setpref）
+//! - 慢速模式（setslowmode，主播或联合主持）
+//! - 举报与审核（report / getreports / reviewreport）
+//!
+//! 另提供 `/chat/redirect` 跳转警告页，用于打开聊天中被改写的外部链接。
 
-use super::super::error::chat_forbidden_response;
+use super::super::{
+    config::{AudienceVisibility, Features},
+    domain::{ClientIp, SessionId, Uid},
+    error::chat_forbidden_response,
+    redact,
+    state::{
+        chat::{
+            html_escape, ChatChannel, ChatCursor, ChatEntry, ChatReport, ChatRoom, LeaderboardEntry, LEADERBOARD_SIZE,
+            MAX_SLOW_MODE_SECS,
+        },
+        disk_guard,
+        embed::EmbedMeta,
+        events::StreamEvent,
+        metrics::ChurnSummary,
+        link_policy,
+        srs::{ClientRegistry, ClientStatus, RoomNotice},
+        srs_api::ViewerBreakdown,
+        streaming_info::AudienceCount,
+    },
+};
 use axum::{
     extract::{Query, State},
-    response::{IntoResponse, Json, Response},
+    http::StatusCode,
+    response::{Html, IntoResponse, Json, Response},
 };
 use serde::Serialize;
 use serde_json::json;
+use super::api::{session_span, StreamStatus};
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use tracing::Instrument;
 
 // ============================================================================
 // 数据结构定义
@@ -27,8 +61,12 @@ use std::sync::Arc;
 /// 客户端通过 URL 参数传递会话标识
 #[derive(Debug, serde::Deserialize)]
 pub struct ChatParams {
-    /// 会话 ID
+    /// 会话 ID（不合法时返回 `Nope`，规则见 `SessionId::parse`）
     session_id: String,
+    /// 昵称令牌（离线大厅鉴权使用）
+    token: Option<String>,
+    /// 回访观众令牌（偏好设置跨直播保留使用）
+    alumni: Option<String>,
 }
 
 /// 聊天室请求体
@@ -43,26 +81,116 @@ pub enum ChatRequest {
     /// 设置用户昵称
     #[serde(rename = "setname")]
     SetName { name: String },
-    /// 设置直播间名称（仅主播）
+    /// 设置直播间名称（仅主播，开播时也可通过推流参数 `name` 设置）
     #[serde(rename = "setlivename")]
     SetLiveName { name: String },
+    /// 设置欢迎语和房间规则（仅主播，跨场次保留）
+    #[serde(rename = "setwelcome")]
+    SetWelcome {
+        /// 欢迎语（省略时不修改，空字符串清除）
+        welcome: Option<String>,
+        /// 房间规则（省略时不修改，空字符串清除）
+        rules: Option<String>,
+    },
     /// 获取聊天消息
     #[serde(rename = "getchat")]
     GetChat {
-        /// 获取之前消息的时间戳（与 next 二选一）
+        /// 获取该消息 ID 之前的消息（优先于 prev/next）
+        before: Option<String>,
+        /// 获取该消息 ID 之后的消息（优先于 prev/next）
+        after: Option<String>,
+        /// 获取之前消息的时间戳（与 next 二选一，旧版客户端）
         prev: Option<f64>,
-        /// 获取之后消息的时间戳（与 prev 二选一）
+        /// 获取之后消息的时间戳（与 prev 二选一，旧版客户端）
         next: Option<f64>,
+        /// 是否接收系统消息（默认接收）
+        system: Option<bool>,
+        /// 频道：`everyone`（默认）或 `verified`
+        channel: Option<String>,
+        /// 是否只获取抽样消息流（供直播叠加层在刷屏时使用，默认否）
+        sampled: Option<bool>,
     },
     /// 发送聊天消息
     #[serde(rename = "sendchat")]
-    SendChat { chat: String },
+    SendChat {
+        chat: String,
+        /// 频道：`everyone`（默认）或 `verified`
+        channel: Option<String>,
+    },
     /// 获取观众人数
     #[serde(rename = "getaudiences")]
     GetAudiences,
     /// 保存聊天快照（仅主播）
     #[serde(rename = "savesnapshot")]
     SaveSnapshot,
+    /// 设置是否公开自己进出直播间的提示
+    #[serde(rename = "setannounce")]
+    SetAnnounce { enabled: bool },
+    /// 设置是否出现在排行榜中
+    #[serde(rename = "setleaderboard")]
+    SetLeaderboard { enabled: bool },
+    /// 屏蔽或取消屏蔽一个用户（只影响自己看到的消息）
+    #[serde(rename = "blockuser")]
+    BlockUser {
+        /// 被屏蔽者 UID
+        uid: Uid,
+        /// 是否屏蔽（默认屏蔽，`false` 为取消屏蔽）
+        blocked: Option<bool>,
+    },
+    /// 获取自己的偏好设置和屏蔽列表
+    #[serde(rename = "getprefs")]
+    GetPrefs,
+    /// 设置或删除一项偏好设置
+    #[serde(rename = "setpref")]
+    SetPref {
+        /// 偏好设置键（小写字母、数字、`_`、`-`）
+        key: String,
+        /// 新值（省略或为 `null` 时删除该项）
+        value: Option<String>,
+    },
+    /// 获取参与度排行榜
+    #[serde(rename = "getleaderboard")]
+    GetLeaderboard,
+    /// 开关聊天图片嵌入（仅主播）
+    #[serde(rename = "setembeds")]
+    SetEmbeds { enabled: bool },
+    /// 设置慢速模式间隔（主播或联合主持，0 表示关闭）
+    #[serde(rename = "setslowmode")]
+    SetSlowMode { secs: u64 },
+    /// 举报一条消息
+    #[serde(rename = "report")]
+    Report {
+        /// 被举报的消息 ID
+        id: String,
+        /// 举报理由
+        #[serde(default)]
+        reason: String,
+    },
+    /// 获取待处理的举报（仅主播）
+    #[serde(rename = "getreports")]
+    GetReports,
+    /// 审核针对某用户的举报（仅主播）
+    #[serde(rename = "reviewreport")]
+    ReviewReport {
+        /// 被举报者 UID
+        uid: Uid,
+        /// 是否限制该用户（`false` 为驳回举报并解除限制）
+        restrict: bool,
+    },
+    /// 删除一条消息（仅主播），内容被遮蔽，元数据保留
+    #[serde(rename = "deletechat")]
+    DeleteChat {
+        /// 消息 ID
+        id: String,
+    },
+    /// 导出指定用户的消息用于举报（仅主播）
+    #[serde(rename = "exportuser")]
+    ExportUser {
+        /// 目标用户 ID
+        uid: Uid,
+        /// 导出格式：`json`（默认）或 `html`
+        format: Option<String>,
+    },
 }
 
 /// 聊天室响应结构
@@ -73,6 +201,9 @@ pub struct ChatResponse {
     /// 状态标识（"Okay" 表示成功，"Nope" 表示失败）
     #[serde(skip_serializing_if = "Option::is_none")]
     status: Option<String>,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
     /// 用户昵称
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
@@ -82,15 +213,79 @@ pub struct ChatResponse {
     /// 观众人数信息
     #[serde(skip_serializing_if = "Option::is_none")]
     audiences: Option<AudienceInfo>,
+    /// 是否处于离线大厅
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lobby: Option<bool>,
+    /// 昵称令牌（设置昵称后返回，可用于离线大厅鉴权）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    /// 导出的举报报告文件名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<String>,
+    /// 保存的聊天快照文件名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<String>,
+    /// 待处理的举报列表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reports: Option<Vec<ChatReport>>,
+    /// 被暂时限制的 UID 列表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restricted: Option<Vec<Uid>>,
+    /// 参与度排行榜
+    #[serde(skip_serializing_if = "Option::is_none")]
+    leaderboard: Option<Vec<LeaderboardEntry>>,
+    /// 建议的下一次轮询间隔（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poll_interval_ms: Option<u64>,
+    /// 直播叠加层已显示到的消息 ID（此后的消息尚未上屏）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shown_until: Option<String>,
+    /// 可获取的最早一条消息的 ID（更早的消息已被清理，向前翻页到此为止）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oldest_available_id: Option<String>,
+    /// 可获取的最早一条消息的时间戳
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oldest_available_stamp: Option<f64>,
+    /// 观众偏好设置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prefs: Option<BTreeMap<String, String>>,
+    /// 观众屏蔽的 UID 列表
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocked: Option<Vec<Uid>>,
+    /// 主播设置的欢迎语
+    #[serde(skip_serializing_if = "Option::is_none")]
+    welcome: Option<String>,
+    /// 主播设置的房间规则
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rules: Option<String>,
+    /// 直播状态（`live` / `paused` / `ended`），无需另外查询 `/api` 即可得知直播是否暂停
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_status: Option<&'static str>,
+    /// 观看授权已失效时的恢复方式（`heartbeat` / `quiz`，见 `chat_handler`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reauth: Option<&'static str>,
 }
 
 /// 观众人数信息
+///
+/// 非主播按 `audience_visibility` 配置展示，隐藏时字段省略
 #[derive(Debug, Serialize)]
 pub struct AudienceInfo {
     /// 当前在线人数（从 SRS 获取，-1 表示未知）
-    current: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<AudienceCount>,
     /// 累计唯一用户数
-    total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<AudienceCount>,
+    /// 按播放协议分类的在线人数（仅主播可见）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocols: Option<ViewerBreakdown>,
+    /// 近 5 分钟的重连和停止拉流次数（仅主播可见）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    churn: Option<ChurnSummary>,
+    /// 推流码率已持续为零的秒数，判定主播可能已离开时才返回（仅主播可见）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    away_secs: Option<u64>,
 }
 
 impl ChatResponse {
@@ -98,9 +293,27 @@ impl ChatResponse {
     pub fn new() -> Self {
         Self {
             status: None,
+            reason: None,
             name: None,
             chatmsgs: None,
             audiences: None,
+            lobby: None,
+            token: None,
+            report: None,
+            snapshot: None,
+            reports: None,
+            restricted: None,
+            leaderboard: None,
+            poll_interval_ms: None,
+            shown_until: None,
+            oldest_available_id: None,
+            oldest_available_stamp: None,
+            prefs: None,
+            blocked: None,
+            welcome: None,
+            rules: None,
+            stream_status: None,
+            reauth: None,
         }
     }
 
@@ -110,6 +323,12 @@ impl ChatResponse {
         self
     }
 
+    /// 设置失败原因（链式调用）
+    pub fn with_reason(mut self, reason: String) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
     /// 设置昵称（链式调用）
     pub fn with_name(mut self, name: Option<String>) -> Self {
         self.name = name;
@@ -123,8 +342,122 @@ impl ChatResponse {
     }
 
     /// 设置观众人数（链式调用）
-    pub fn with_audiences(mut self, current: i32, total: usize) -> Self {
-        self.audiences = Some(AudienceInfo { current, total });
+    pub fn with_audiences(mut self, current: i32, total: usize, visibility: AudienceVisibility) -> Self {
+        self.audiences = Some(AudienceInfo {
+            current: AudienceCount::present(current as i64, visibility),
+            total: AudienceCount::present(total as i64, visibility),
+            protocols: None,
+            churn: None,
+            away_secs: None,
+        });
+        self
+    }
+
+    /// 附加按播放协议分类的在线人数（链式调用，需在 `with_audiences` 之后调用）
+    pub fn with_protocols(mut self, protocols: ViewerBreakdown) -> Self {
+        if let Some(audiences) = self.audiences.as_mut() {
+            audiences.protocols = Some(protocols);
+        }
+        self
+    }
+
+    /// 附加近 5 分钟的观众流失概况（链式调用，需在 `with_audiences` 之后调用）
+    pub fn with_churn(mut self, churn: ChurnSummary) -> Self {
+        if let Some(audiences) = self.audiences.as_mut() {
+            audiences.churn = Some(churn);
+        }
+        self
+    }
+
+    /// 附加主播可能已离开的提示（链式调用，需在 `with_audiences` 之后调用）
+    pub fn with_away(mut self, away_secs: Option<u64>) -> Self {
+        if let Some(audiences) = self.audiences.as_mut() {
+            audiences.away_secs = away_secs;
+        }
+        self
+    }
+
+    /// 设置叠加层已显示到的消息 ID（链式调用）
+    pub fn with_shown_until(mut self, id: Option<String>) -> Self {
+        self.shown_until = id;
+        self
+    }
+
+    /// 设置可获取的最早一条消息（链式调用）
+    pub fn with_oldest_available(mut self, entry: Option<&ChatEntry>) -> Self {
+        self.oldest_available_id = entry.map(|e| e.id.clone());
+        self.oldest_available_stamp = entry.map(|e| e.stamp);
+        self
+    }
+
+    /// 设置授权失效时的恢复方式（链式调用）
+    pub fn with_reauth(mut self, hint: &'static str) -> Self {
+        self.status = Some("Nope".to_string());
+        self.reason = Some("reauth_required".to_string());
+        self.reauth = Some(hint);
+        self
+    }
+
+    /// 设置直播状态（链式调用）
+    pub fn with_stream_status(mut self, status: StreamStatus) -> Self {
+        self.stream_status = Some(status.as_str());
+        self
+    }
+
+    /// 设置建议的轮询间隔（链式调用）
+    pub fn with_poll_interval(mut self, ms: u64) -> Self {
+        self.poll_interval_ms = Some(ms);
+        self
+    }
+
+    /// 设置偏好设置和屏蔽列表（链式调用）
+    pub fn with_prefs(mut self, prefs: BTreeMap<String, String>, blocked: Vec<Uid>) -> Self {
+        self.prefs = Some(prefs);
+        self.blocked = Some(blocked);
+        self
+    }
+
+    /// 标记为离线大厅（链式调用）
+    pub fn with_lobby(mut self) -> Self {
+        self.lobby = Some(true);
+        self
+    }
+
+    /// 设置昵称令牌（链式调用）
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// 设置举报报告文件名（链式调用）
+    pub fn with_report(mut self, report: String) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    /// 设置聊天快照文件名（链式调用）
+    pub fn with_snapshot(mut self, snapshot: String) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// 设置待处理举报和被限制用户列表（链式调用）
+    pub fn with_reports(mut self, reports: Vec<ChatReport>, restricted: Vec<Uid>) -> Self {
+        self.reports = Some(reports);
+        self.restricted = Some(restricted);
+        self
+    }
+
+    /// 设置排行榜（链式调用）
+    pub fn with_leaderboard(mut self, leaderboard: Vec<LeaderboardEntry>) -> Self {
+        self.leaderboard = Some(leaderboard);
+        self
+    }
+
+    /// 设置欢迎语和房间规则（链式调用）
+    pub fn with_notice(mut self, notice: &RoomNotice) -> Self {
+        self.welcome = notice.welcome.clone();
+        self.rules = notice.rules.clone();
         self
     }
 }
@@ -139,33 +472,40 @@ impl Default for ChatResponse {
 // 辅助函数
 // ============================================================================
 
-/// 从请求头中提取客户端真实 IP 地址
+/// 将聊天昵称同步到观众记录（用于人数受限时的优先放行）
 ///
-/// ### 优先级
-/// 1. `X-Forwarded-For` 头 - 当服务位于反向代理（如 Nginx）后面时
-/// 2. `ConnectInfo` 中的远程地址 - 直接连接时的客户端地址
+/// 须在释放聊天室锁之后调用（锁顺序：先 SRS 数据库，后聊天室）
+fn sync_display_name(state: &super::super::AppState, client_ip: &ClientIp, client_session_id: &SessionId, name: &str) {
+    let mut clients = state.srs_db.clients.write();
+    if clients.get_client_display_name(client_ip, client_session_id) != Some(name) {
+        clients.set_client_display_name(client_ip, client_session_id, name.to_string());
+    }
+}
+
+/// 解析并校验请求的聊天频道
 ///
-/// ### 为什么需要这个？
-/// - 生产环境通常使用 Nginx 等 HTTP 服务器作为反向代理
-/// - 这种情况下，rusty-live-server 看到的远程地址是 127.0.0.1（本地代理）
-/// - 真实客户端 IP 在 `X-Forwarded-For` 头中
-fn get_client_ip(headers: &axum::http::HeaderMap, remote_addr: &str) -> String {
-    headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        // X-Forwarded-For 可能包含多个 IP（客户端, 代理1, 代理2...）
-        // 取第一个（原始客户端 IP）
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .unwrap_or_else(|| {
-            // 没有 X-Forwarded-For 头，直接使用远程地址
-            // 远程地址格式为 "IP:PORT"，需要提取 IP 部分
-            remote_addr
-                .split(':')
-                .next()
-                .unwrap_or(remote_addr)
-                .to_string()
-        })
+/// ### 返回值
+/// - `Ok(channel)`: 客户端可以使用该频道
+/// - `Err(reason)`: 频道未知、未开放，或客户端不满足频道要求
+fn resolve_channel(
+    state: &super::super::AppState,
+    room: &ChatRoom,
+    client_ip: &ClientIp,
+    client_session_id: &SessionId,
+    is_publisher: bool,
+    channel: Option<&str>,
+) -> Result<ChatChannel, String> {
+    let channel = match channel {
+        Some(c) => ChatChannel::parse(c).ok_or_else(|| format!("unknown channel: {}", c))?,
+        None => ChatChannel::Everyone,
+    };
+    if channel == ChatChannel::Verified && !state.config().chat_verified_channel {
+        return Err("verified channel is disabled".to_string());
+    }
+    if !room.can_use_channel(client_ip, client_session_id, is_publisher, channel) {
+        return Err("verified channel requires a nickname".to_string());
+    }
+    Ok(channel)
 }
 
 // ============================================================================
@@ -175,12 +515,27 @@ fn get_client_ip(headers: &axum::http::HeaderMap, remote_addr: &str) -> String {
 /// 聊天室请求主处理器
 ///
 /// ### 路由
-/// `POST /chat?session_id=<会话ID>`
+/// `POST /chat?session_id=<会话ID>[&token=<昵称令牌>][&alumni=<回访观众令牌>]`
+///
+/// ### 偏好设置
+/// 观众可通过 `setpref` / `getprefs` 保存主题、是否隐藏系统消息、轮询间隔等偏好设置，
+/// 偏好设置随聊天身份保存，刷新页面后依然有效；携带有效的回访观众令牌时，
+/// 偏好设置还会在之后的直播中沿用。
+///
+/// ### 授权失效
+/// 直播中观众记录过期（如长时间只聊天不观看）后，聊天请求返回
+/// `{"status": "Nope", "reason": "reauth_required", "reauth": "heartbeat|quiz"}`：
+/// `heartbeat` 表示携带最近的续连令牌查询一次状态（`/api?session_id=<会话ID>&status=1&resume=<令牌>`）即可恢复，
+/// `quiz` 表示需要重新连接答题。从未通过验证的会话仍只返回 `{"status": "Nope"}`。
+///
+/// ### 离线大厅
+/// 启用 `LIVE_SERVER_CHAT_LOBBY` 后，未直播时曾通过验证的会话或持有昵称令牌者
+/// 可进入离线大厅，响应中带有 `"lobby": true`，大厅消息不参与直播转储。
 ///
 /// ### 请求格式
 /// ```json
 /// {
-///   "action": "hello|setname|setlivename|getchat|sendchat|getaudiences|savesnapshot",
+///   "action": "hello|setname|setlivename|setwelcome|getchat|sendchat|getaudiences|savesnapshot|setannounce|setleaderboard|getleaderboard|setembeds|blockuser|getprefs|setpref|exportuser|report|getreports|reviewreport",
 ///   ... // 其他 action 相关参数
 /// }
 /// ```
@@ -189,9 +544,14 @@ fn get_client_ip(headers: &axum::http::HeaderMap, remote_addr: &str) -> String {
 /// ```json
 /// {
 ///   "status": "Okay|Nope",
+///   "reason": "失败原因（可选）",
+///   "stream_status": "live|paused|ended",
 ///   "name": "用户昵称",
 ///   "chatmsgs": [...],
-///   "audiences": {"current": -1, "total": 10}
+///   "shown_until": "直播叠加层已显示到的消息 ID（getchat，可选）",
+///   "oldest_available_id": "可获取的最早一条消息 ID（getchat，没有消息时省略）",
+///   "oldest_available_stamp": 1700000000.0,
+///   "audiences": {"current": -1, "total": 10, "churn": {"reconnects_5m": 0, "stops_5m": 0}, "away_secs": 95}
 /// }
 /// ```
 pub async fn chat_handler(
@@ -202,24 +562,103 @@ pub async fn chat_handler(
     body: String,
 ) -> Response {
     // 提取客户端 IP 和会话 ID
-    let client_ip = get_client_ip(&headers, &connect_info.to_string());
-    let client_session_id = params.session_id;
+    let client_ip = ClientIp::from_request(&headers, connect_info.0, &state.config().trusted_proxies);
+    let client_session_id = match SessionId::parse(&params.session_id) {
+        Ok(session_id) => session_id,
+        Err(reason) => return Json(json!({"status": "Nope", "reason": reason})).into_response(),
+    };
+
+    let span = session_span(&state, &request_action(&body), Some(&client_ip), &client_session_id);
+    let started = std::time::Instant::now();
+    let response = handle_chat_request(
+        state.clone(),
+        params,
+        headers,
+        connect_info.0.ip(),
+        client_ip,
+        client_session_id,
+        body,
+    )
+        .instrument(span)
+        .await;
+    state.metrics.observe_chat(started.elapsed());
+    response
+}
+
+/// 判断未授权会话的观看授权是否在聊天过程中失效，以及如何恢复
+///
+/// ### 返回值
+/// - `Some("heartbeat")`: 观众记录刚过期，携带最近的续连令牌查询一次状态（`/api?status=1&resume=<令牌>`）即可恢复
+/// - `Some("quiz")`: 曾经通过验证，但记录已过期较久或直播已重新开始，需要重新连接答题
+/// - `None`: 从未通过验证（待答题、被封禁或未知会话）
+fn reauth_hint(clients: &ClientRegistry, ip: &ClientIp, session_id: &SessionId) -> Option<&'static str> {
+    match clients.get_client_status(ip, session_id) {
+        None if clients.lapsed_recently(session_id) => Some("heartbeat"),
+        None | Some(ClientStatus::Ended) if clients.has_legal_history(session_id) => Some("quiz"),
+        _ => None,
+    }
+}
+
+/// 请求体中的操作类型（用于日志 span，请求体不合法时为 `invalid`）
+fn request_action(body: &str) -> String {
+    #[derive(serde::Deserialize)]
+    struct ActionTag {
+        action: String,
+    }
+    serde_json::from_str::<ActionTag>(body)
+        .map(|tag| tag.action)
+        .unwrap_or_else(|_| "invalid".to_string())
+}
+
+/// 处理已解析出客户端身份的聊天室请求（在会话 span 内执行）
+async fn handle_chat_request(
+    state: Arc<super::super::AppState>,
+    params: ChatParams,
+    headers: axum::http::HeaderMap,
+    peer: std::net::IpAddr,
+    client_ip: ClientIp,
+    client_session_id: SessionId,
+    body: String,
+) -> Response {
+    tracing::debug!("聊天室请求");
 
     // ========================================
     // 权限验证
     // ========================================
-    {
-        let srs_db = state.srs_db.inner.read();
-        // 检查直播是否已开始
-        if !srs_db.is_streaming() {
-            return chat_forbidden_response();
-        }
+    let (in_lobby, stream_status) = {
+        // 直播状态须在获取客户端注册表的锁之后读取：
+        // 否则开播放行排队观众的瞬间，可能读到旧的"未开播"快照和新的授权状态而被误拒
+        let (clients, snapshot) = state.srs_db.clients_with_snapshot();
+        let (streaming, stream_status) = (snapshot.is_streaming(), StreamStatus::of_stream(&snapshot));
+        let in_lobby = if streaming {
+            // 检查客户端是否已通过答题验证，授权在聊天过程中失效时告知恢复方式
+            if !clients.has_authorized_client(&client_ip, &client_session_id) {
+                return match reauth_hint(&clients, &client_ip, &client_session_id) {
+                    Some(hint) => Json(ChatResponse::new().with_reauth(hint).with_stream_status(stream_status))
+                        .into_response(),
+                    None => Json(json!({"status": "Nope"})).into_response(),
+                };
+            }
+            false
+        } else {
+            // 直播未开始：仅在启用离线大厅时开放
+            let chat_rooms = state.chat_db.inner.read();
+            if !chat_rooms.lobby_enabled() && !state.feature(Features::OFFLINE_LOBBY) {
+                return chat_forbidden_response();
+            }
 
-        // 检查客户端是否已通过答题验证
-        if !srs_db.has_authorized_client(&client_ip, &client_session_id) {
-            return Json(json!({"status": "Nope"})).into_response();
-        }
-    }
+            // 离线大厅仅对曾通过验证的会话或持有昵称令牌者开放
+            let has_token = params
+                .token
+                .as_deref()
+                .is_some_and(|t| chat_rooms.nickname_for_token(t).is_some());
+            if !clients.has_legal_history(&client_session_id) && !has_token {
+                return Json(json!({"status": "Nope", "lobby": true})).into_response();
+            }
+            true
+        };
+        (in_lobby, stream_status)
+    };
 
     // 解析请求体
     let request: ChatRequest = match serde_json::from_str(&body) {
@@ -227,7 +666,22 @@ pub async fn chat_handler(
         Err(_) => return chat_forbidden_response(),
     };
 
+    // 外部身份作为固定昵称（需在获取数据库锁之前完成异步校验）
+    let external_identity = match (&request, &state.external_auth) {
+        (ChatRequest::Hello, Some(auth)) => auth.identify(&headers, peer).await,
+        _ => None,
+    };
+
+    // 有效的回访观众令牌用于跨直播保存偏好设置
+    let alumni_subject = match (params.alumni.as_deref(), state.alumni.as_ref()) {
+        (Some(token), Some(signer)) => signer.subject(token).map(|(subject, expires_at)| (subject.to_string(), expires_at)),
+        _ => None,
+    };
+
     let mut response = ChatResponse::new();
+    if in_lobby {
+        response = response.with_lobby();
+    }
 
     // ========================================
     // 根据操作类型分发处理
@@ -235,110 +689,549 @@ pub async fn chat_handler(
     match request {
         // --- 客户端连接 ---
         ChatRequest::Hello => {
-            let chat_db = state.chat_db.inner.read();
+            let mut chat_rooms = state.chat_db.inner.write();
+
+            // 离线大厅中凭令牌恢复昵称（昵称可用时）
+            let token_name = params
+                .token
+                .as_deref()
+                .and_then(|t| chat_rooms.nickname_for_token(t))
+                .map(str::to_string);
+            if let (true, Some(token_name)) = (in_lobby, token_name) {
+                let chat_db = chat_rooms.active_mut();
+                if chat_db.get_client_name(&client_ip, &client_session_id).is_none() {
+                    chat_db.set_client_name(&client_ip, &client_session_id, token_name);
+                }
+            }
+
+            // 通过外部身份认证的观众使用身份作为固定昵称
+            if let Some(identity) = external_identity {
+                let chat_db = chat_rooms.active_mut();
+                if chat_db.get_client_name(&client_ip, &client_session_id).is_none() {
+                    chat_db.set_client_name(&client_ip, &client_session_id, identity);
+                }
+            }
+
+            let chat_db = chat_rooms.active();
             let name = chat_db.get_client_name(&client_ip, &client_session_id);
-            let msgs = chat_db.get_chat_from(-1.0, false);
+            let viewer = chat_db.get_client_uid(&client_ip, &client_session_id);
+            let msgs = chat_db.get_chat_from(&ChatCursor::Latest, false, viewer, true, ChatChannel::Everyone, false);
+            let token = name
+                .as_deref()
+                .and_then(|n| chat_rooms.issue_nickname_token(n, &client_session_id, params.token.as_deref()));
+            drop(chat_rooms);
+            if let Some(name) = &name {
+                sync_display_name(&state, &client_ip, &client_session_id, name);
+            }
             response = response
                 .with_status("Okay")
                 .with_name(name)
-                .with_chatmsgs(msgs);
+                .with_chatmsgs(msgs)
+                .with_token(token)
+                .with_notice(&state.srs_db.snapshot().notice);
         }
 
         // --- 设置用户昵称 ---
         ChatRequest::SetName { name } => {
-            let mut chat_db = state.chat_db.inner.write();
+            let mut chat_rooms = state.chat_db.inner.write();
+            let chat_db = chat_rooms.active_mut();
             let success = chat_db.set_client_name(&client_ip, &client_session_id, name.clone());
+            let current = chat_db.get_client_name(&client_ip, &client_session_id);
+            let token = current
+                .as_deref()
+                .and_then(|n| chat_rooms.issue_nickname_token(n, &client_session_id, params.token.as_deref()));
+            drop(chat_rooms);
+            if let (true, Some(name)) = (success, &current) {
+                sync_display_name(&state, &client_ip, &client_session_id, name);
+            }
             response = response
                 .with_status(if success { "Okay" } else { "Nope" })
-                .with_name(chat_db.get_client_name(&client_ip, &client_session_id));
+                .with_name(current)
+                .with_token(token);
         }
 
         // --- 设置直播间名称（仅主播） ---
         ChatRequest::SetLiveName { name } => {
             // 检查是否为主播
-            let is_publisher = {
-                let srs_db = state.srs_db.inner.read();
-                srs_db.client_is_publisher(&client_ip, &client_session_id)
-            };
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
 
             if is_publisher {
-                let mut srs_db = state.srs_db.inner.write();
-                srs_db.set_stream_name(name.clone());
+                state.srs_db.streamer.write().set_stream_name(name.clone());
                 response = response.with_status("Okay");
             } else {
                 response = response.with_status("Nope");
             }
             // 无论如何都返回当前直播间名称
-            response = response.with_name({
-                let srs_db = state.srs_db.inner.read();
-                srs_db.get_stream_name().map(|s| s.to_string())
-            });
+            response = response.with_name(state.srs_db.streamer.read().get_stream_name().map(|s| s.to_string()));
+        }
+
+        // --- 设置欢迎语和房间规则 ---
+        ChatRequest::SetWelcome { welcome, rules } => {
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+            let too_long = [&welcome, &rules]
+                .into_iter()
+                .flatten()
+                .any(|text| text.chars().count() > RoomNotice::MAX_LEN);
+
+            let mut streamer = state.srs_db.streamer.write();
+            if !is_publisher {
+                response = response.with_status("Nope");
+            } else if too_long {
+                response = response
+                    .with_status("Nope")
+                    .with_reason(format!("welcome and rules are limited to {} characters", RoomNotice::MAX_LEN));
+            } else {
+                // 省略的字段保持不变，空字符串清除
+                let update = |current: &Option<String>, new: Option<String>| match new {
+                    Some(text) if text.trim().is_empty() => None,
+                    Some(text) => Some(text),
+                    None => current.clone(),
+                };
+                let current = streamer.get_notice();
+                let notice = RoomNotice {
+                    welcome: update(&current.welcome, welcome),
+                    rules: update(&current.rules, rules),
+                };
+                streamer.set_notice(notice);
+                tracing::info!("主播更新了欢迎语和房间规则");
+                response = response.with_status("Okay");
+            }
+            // 无论如何都返回当前的欢迎语和房间规则
+            response = response.with_notice(streamer.get_notice());
         }
 
         // --- 获取聊天消息 ---
-        ChatRequest::GetChat { prev, next } => {
-            // 必须提供 prev 或 next 之一
-            let (stamp, is_prev) = if let Some(p) = prev {
-                (p, true)
+        ChatRequest::GetChat { before, after, prev, next, system, channel, sampled } => {
+            // 必须提供 before / after / prev / next 之一，负数时间戳表示获取最近消息
+            let stamp_cursor = |stamp: f64| {
+                if stamp < 0.0 {
+                    ChatCursor::Latest
+                } else {
+                    ChatCursor::Stamp(stamp)
+                }
+            };
+            let (cursor, is_prev) = if let Some(id) = before {
+                (ChatCursor::Id(id), true)
+            } else if let Some(id) = after {
+                (ChatCursor::Id(id), false)
+            } else if let Some(p) = prev {
+                (stamp_cursor(p), true)
             } else if let Some(n) = next {
-                (n, false)
+                (stamp_cursor(n), false)
             } else {
                 return chat_forbidden_response();
             };
 
-            let chat_db = state.chat_db.inner.read();
-            let msgs = chat_db.get_chat_from(stamp, is_prev);
-            response = response
-                .with_status("Okay")
-                .with_chatmsgs(msgs);
+            let is_publisher = state
+                .srs_db
+                .clients
+                .read()
+                .client_is_publisher(&client_ip, &client_session_id);
+            let chat_rooms = state.chat_db.inner.read();
+            let chat_db = chat_rooms.active();
+            match resolve_channel(&state, chat_db, &client_ip, &client_session_id, is_publisher, channel.as_deref()) {
+                Ok(channel) => {
+                    let viewer = chat_db.get_client_uid(&client_ip, &client_session_id);
+                    let (include_system, sampled) = (system.unwrap_or(true), sampled.unwrap_or(false));
+                    let msgs = chat_db.get_chat_from(&cursor, is_prev, viewer, include_system, channel, sampled);
+                    response = response
+                        .with_status("Okay")
+                        .with_chatmsgs(msgs)
+                        .with_shown_until(chat_db.shown_until.clone())
+                        .with_oldest_available(chat_db.oldest_available(viewer, include_system, channel, sampled));
+                }
+                Err(reason) => response = response.with_status("Nope").with_reason(reason),
+            }
         }
 
         // --- 发送聊天消息 ---
-        ChatRequest::SendChat { chat } => {
-            // 检查是否为主播
-            let is_publisher = {
-                let srs_db = state.srs_db.inner.read();
-                srs_db.client_is_publisher(&client_ip, &client_session_id)
+        ChatRequest::SendChat { chat, channel } => {
+            // 检查是否为主播或联合主持
+            let (is_publisher, is_co_host) = {
+                let clients = state.srs_db.clients.read();
+                (
+                    clients.client_is_publisher(&client_ip, &client_session_id),
+                    clients.client_is_co_host(&client_ip, &client_session_id),
+                )
+            };
+
+            // 在链接策略改写之前提取图片嵌入
+            let embed = EmbedMeta::extract(&chat, &state.config().chat_embed_hosts);
+            let limits = (
+                state.config().chat_embed_user_per_min,
+                state.config().chat_embed_global_per_min,
+            );
+
+            // 观众消息按链接策略处理，主播消息原样保留
+            let chat = if is_publisher {
+                chat
+            } else {
+                link_policy::apply(
+                    &chat,
+                    state.config().chat_url_policy,
+                    &state.config().chat_url_whitelist,
+                )
             };
 
-            // 添加消息到数据库
-            let mut chat_db = state.chat_db.inner.write();
-            chat_db.add_entry(client_ip, client_session_id, chat, is_publisher);
-            response = response.with_status("Okay");
+            // 添加消息到数据库（观众受发言关闭和慢速模式限制，主播与联合主持除外）
+            let mut chat_rooms = state.chat_db.inner.write();
+            let chat_db = chat_rooms.active_mut();
+            let channel =
+                resolve_channel(&state, chat_db, &client_ip, &client_session_id, is_publisher, channel.as_deref());
+            match (channel, chat_db.slow_mode_wait(&client_ip, &client_session_id)) {
+                (Err(reason), _) => {
+                    response = response.with_status("Nope").with_reason(reason);
+                }
+                (Ok(_), _) if chat_db.chat_closed && !is_publisher && !is_co_host => {
+                    response = response.with_status("Nope").with_reason("chat is closed".to_string());
+                }
+                (Ok(_), Some(secs)) if !is_publisher && !is_co_host => {
+                    response = response
+                        .with_status("Nope")
+                        .with_reason(format!("slow mode: wait {}s", secs));
+                }
+                (Ok(channel), _) => {
+                    // 聊天过快时观众消息按抽样规则进入叠加层消息流，主播消息始终保留
+                    let sampled_in = is_publisher
+                        || chat_db
+                            .overlay_sampler
+                            .admit(state.config().chat_sample_threshold, state.config().chat_sample_every);
+                    let entry = chat_db.add_entry(client_ip, client_session_id, chat, is_publisher, embed, limits);
+                    entry.channel = channel;
+                    entry.co_host = is_co_host;
+                    entry.sampled_out = !sampled_in;
+                    response = response.with_status("Okay");
+                }
+            }
         }
 
         // --- 获取观众人数 ---
         ChatRequest::GetAudiences => {
             // 获取累计用户数
             let total = {
-                let chat_db = state.chat_db.inner.read();
+                let chat_rooms = state.chat_db.inner.read();
+                let chat_db = chat_rooms.active();
                 chat_db.size()
             };
 
-            // 返回观众人数（current 从 SRS 获取，暂不实现，返回 -1）
+            // 当前在线人数由后台任务从 SRS 获取
+            let (current, protocols, away_secs) = {
+                let info = state.streaming_info.inner.read();
+                (info.get_audiences_num(), info.protocols, info.away_secs())
+            };
+
+            // 主播始终可见精确人数、协议分布和重连情况，其他人按配置展示
+            let is_publisher = state
+                .srs_db
+                .clients
+                .read()
+                .client_is_publisher(&client_ip, &client_session_id);
+            let visibility = if is_publisher {
+                AudienceVisibility::Exact
+            } else {
+                state.config().audience_visibility
+            };
+
             response = response
                 .with_status("Okay")
-                .with_audiences(-1, total);
+                .with_audiences(current, total, visibility);
+            if is_publisher {
+                response = response
+                    .with_protocols(protocols)
+                    .with_churn(state.metrics.churn())
+                    .with_away(away_secs);
+            }
         }
 
         // --- 保存聊天快照（仅主播） ---
         ChatRequest::SaveSnapshot => {
             // 检查是否为主播
-            let is_publisher = {
-                let srs_db = state.srs_db.inner.read();
-                srs_db.client_is_publisher(&client_ip, &client_session_id)
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+
+            // 离线大厅不参与直播转储
+            if is_publisher && !in_lobby {
+                let chat_rooms = state.chat_db.inner.read();
+                let chat_db = chat_rooms.active();
+                // 磁盘空间不足时拒绝保存，留给直播结束时的精简转储
+                if let Some(low) = disk_guard::check(&chat_db.dump_path, state.config().dump_min_free_bytes) {
+                    low.report(&state.events);
+                    response = response.with_status("Nope").with_reason(low.describe());
+                } else {
+                    match chat_db.dump_full() {
+                        Ok(path) => {
+                            tracing::debug!(
+                                "({}, {}): 主播保存了聊天记录: {}",
+                                redact::ip(&client_ip), client_session_id, path.display()
+                            );
+                            response = response.with_status("Okay");
+                            if let Some(name) = path.file_name() {
+                                response = response.with_snapshot(name.to_string_lossy().into_owned());
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!("({}, {}): 保存聊天记录失败: {}", redact::ip(&client_ip), client_session_id, e);
+                            response = response.with_status("Nope").with_reason(e);
+                        }
+                    }
+                }
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 设置进出提示偏好 ---
+        ChatRequest::SetAnnounce { enabled } => {
+            let mut chat_rooms = state.chat_db.inner.write();
+            let chat_db = chat_rooms.active_mut();
+            if chat_db.set_announce(&client_ip, &client_session_id, enabled) {
+                response = response.with_status("Okay");
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 设置排行榜偏好 ---
+        ChatRequest::SetLeaderboard { enabled } => {
+            let mut chat_rooms = state.chat_db.inner.write();
+            let chat_db = chat_rooms.active_mut();
+            if chat_db.set_ranked(&client_ip, &client_session_id, enabled) {
+                response = response.with_status("Okay");
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 获取排行榜 ---
+        ChatRequest::GetLeaderboard => {
+            let chat_rooms = state.chat_db.inner.read();
+            let leaderboard = chat_rooms.active().leaderboard(LEADERBOARD_SIZE);
+            response = response.with_status("Okay").with_leaderboard(leaderboard);
+        }
+
+        // --- 屏蔽用户 ---
+        ChatRequest::BlockUser { uid, blocked } => {
+            let success = state
+                .chat_db
+                .inner
+                .write()
+                .active_mut()
+                .set_blocked(&client_ip, &client_session_id, uid, blocked.unwrap_or(true));
+            response = response.with_status(if success { "Okay" } else { "Nope" });
+        }
+
+        // --- 获取偏好设置 ---
+        ChatRequest::GetPrefs => {
+            let mut chat_rooms = state.chat_db.inner.write();
+            if let Some(saved) = alumni_subject.as_ref().and_then(|(s, _)| chat_rooms.alumni_prefs(s)).cloned() {
+                chat_rooms.active_mut().restore_prefs(&client_ip, &client_session_id, &saved);
+            }
+            let (prefs, blocked) = chat_rooms.active().preferences(&client_ip, &client_session_id);
+            response = response.with_status("Okay").with_prefs(prefs, blocked);
+        }
+
+        // --- 设置偏好设置 ---
+        ChatRequest::SetPref { key, value } => {
+            let mut chat_rooms = state.chat_db.inner.write();
+            if let Some(saved) = alumni_subject.as_ref().and_then(|(s, _)| chat_rooms.alumni_prefs(s)).cloned() {
+                chat_rooms.active_mut().restore_prefs(&client_ip, &client_session_id, &saved);
+            }
+            match chat_rooms.active_mut().set_pref(&client_ip, &client_session_id, key, value) {
+                Ok(()) => {
+                    let (prefs, blocked) = chat_rooms.active().preferences(&client_ip, &client_session_id);
+                    if let Some((subject, expires_at)) = &alumni_subject {
+                        chat_rooms.save_alumni_prefs(subject, *expires_at, prefs.clone());
+                    }
+                    response = response.with_status("Okay").with_prefs(prefs, blocked);
+                }
+                Err(reason) => {
+                    response = response.with_status("Nope").with_reason(reason.to_string());
+                }
+            }
+        }
+
+        // --- 开关图片嵌入（仅主播） ---
+        ChatRequest::SetEmbeds { enabled } => {
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+
+            if is_publisher {
+                let mut chat_rooms = state.chat_db.inner.write();
+                chat_rooms.active_mut().embeds.enabled = enabled;
+                tracing::info!("主播{}了聊天图片嵌入", if enabled { "开启" } else { "关闭" });
+                response = response.with_status("Okay");
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 设置慢速模式（主播或联合主持） ---
+        ChatRequest::SetSlowMode { secs } => {
+            let allowed = {
+                let clients = state.srs_db.clients.read();
+                clients.client_is_publisher(&client_ip, &client_session_id)
+                    || clients.client_is_co_host(&client_ip, &client_session_id)
             };
 
+            if allowed {
+                let secs = secs.min(MAX_SLOW_MODE_SECS);
+                state.chat_db.inner.write().active_mut().slow_mode_secs = secs;
+                tracing::info!(
+                    "({}, {}): 慢速模式设为 {} 秒",
+                    redact::ip(&client_ip),
+                    client_session_id,
+                    secs
+                );
+                response = response.with_status("Okay");
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 举报消息 ---
+        ChatRequest::Report { id, reason } => {
+            let threshold = state.config().report_threshold;
+            let (result, pending) = {
+                let mut chat_rooms = state.chat_db.inner.write();
+                let chat_db = chat_rooms.active_mut();
+                let reporter = chat_db.ensure_uid(&client_ip, &client_session_id);
+                let result = chat_db.add_report(reporter, &id, reason, threshold);
+                (result, chat_db.reports.len())
+            };
+            match result {
+                Some(restricted) => {
+                    if restricted {
+                        tracing::info!("消息 {} 的发送者被举报次数达到阈值，已暂时限制", id);
+                    }
+                    state.events.publish(StreamEvent::ReportFiled { pending });
+                    response = response.with_status("Okay");
+                }
+                None => response = response.with_status("Nope"),
+            }
+        }
+
+        // --- 获取待处理的举报（仅主播） ---
+        ChatRequest::GetReports => {
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+
             if is_publisher {
-                let chat_db = state.chat_db.inner.read();
-                chat_db.dump_full();
-                tracing::debug!("({}, {}): 主播保存了聊天记录", client_ip, client_session_id);
+                let chat_rooms = state.chat_db.inner.read();
+                let chat_db = chat_rooms.active();
+                let mut restricted: Vec<Uid> = chat_db.shadow_restricted.iter().copied().collect();
+                restricted.sort_unstable();
+                response = response
+                    .with_status("Okay")
+                    .with_reports(chat_db.reports.clone(), restricted);
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 审核举报（仅主播） ---
+        ChatRequest::ReviewReport { uid, restrict } => {
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+
+            if is_publisher {
+                let mut chat_rooms = state.chat_db.inner.write();
+                let handled = chat_rooms.active_mut().review_reports(uid, restrict);
+                tracing::info!(
+                    "({}, {}): 主播审核了用户 {} 的 {} 条举报，限制={}",
+                    redact::ip(&client_ip),
+                    client_session_id,
+                    uid,
+                    handled,
+                    restrict
+                );
                 response = response.with_status("Okay");
             } else {
                 response = response.with_status("Nope");
             }
         }
+
+        // --- 删除消息（仅主播） ---
+        ChatRequest::DeleteChat { id } => {
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+
+            let deleted = is_publisher && {
+                let mut chat_rooms = state.chat_db.inner.write();
+                let chat_db = chat_rooms.active_mut();
+                let by = chat_db.ensure_uid(&client_ip, &client_session_id);
+                chat_db.delete_message(&id, by)
+            };
+            if deleted {
+                tracing::info!("({}, {}): 主播删除了消息 {}", redact::ip(&client_ip), client_session_id, id);
+                response = response.with_status("Okay");
+            } else {
+                response = response.with_status("Nope");
+            }
+        }
+
+        // --- 导出用户消息用于举报（仅主播） ---
+        ChatRequest::ExportUser { uid, format } => {
+            let is_publisher = state.srs_db.clients.read().client_is_publisher(&client_ip, &client_session_id);
+
+            let exported = if is_publisher {
+                let chat_rooms = state.chat_db.inner.read();
+                chat_rooms.active().export_user(uid, format.as_deref() == Some("html"))
+            } else {
+                None
+            };
+            match exported {
+                Some(path) => {
+                    tracing::info!("({}, {}): 主播导出了用户 {} 的消息", redact::ip(&client_ip), client_session_id, uid);
+                    let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+                    response = response.with_status("Okay");
+                    if let Some(name) = name {
+                        response = response.with_report(name);
+                    }
+                }
+                None => response = response.with_status("Nope"),
+            }
+        }
+    }
+
+    let paused = stream_status == StreamStatus::Paused;
+    Json(
+        response
+            .with_poll_interval(state.suggest_poll_interval(paused))
+            .with_stream_status(stream_status),
+    )
+    .into_response()
+}
+
+// ============================================================================
+// 链接跳转警告页
+// ============================================================================
+
+/// 跳转警告页查询参数
+#[derive(Debug, serde::Deserialize)]
+pub struct RedirectParams {
+    /// 目标链接
+    u: String,
+}
+
+/// 链接跳转警告页处理器
+///
+/// 聊天消息中的外部链接在 `redirect` 策略下会被改写为 `/chat/redirect?u=...`，
+/// 此处理器展示目标地址并提醒观众注意风险，由观众自行决定是否继续访问。
+///
+/// ### 返回值
+/// - 目标为合法的 http(s) 链接时返回警告页
+/// - 否则返回 400
+pub async fn chat_redirect_handler(Query(params): Query<RedirectParams>) -> Response {
+    let valid = url::Url::parse(&params.u)
+        .is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some());
+    if !valid {
+        return (StatusCode::BAD_REQUEST, "Invalid link").into_response();
     }
 
-    Json(response).into_response()
+    let target = html_escape(&params.u);
+    Html(format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta name=\"referrer\" content=\"no-referrer\"><title>即将离开直播间</title></head>\n\
+         <body>\n<h2>即将离开直播间</h2>\n\
+         <p>你即将访问聊天中他人分享的外部链接，请注意辨别，谨防诈骗和恶意网站：</p>\n\
+         <p><code>{target}</code></p>\n\
+         <p><a href=\"{target}\" rel=\"noopener noreferrer nofollow\">继续访问</a></p>\n\
+         </body></html>\n"
+    ))
+    .into_response()
 }
@@ -0,0 +1,298 @@
+//! # 调试与故障演练接口模块
+//!
+//! 仅在启用编译特性 `debug-endpoints` 时编译，用于在正式直播前演练故障场景
+//! （主播掉线、观众批量过期、聊天高负载等）。所有接口均需携带管理令牌。
+//!
+//! - `POST /debug/fast_forward` - 将所有记录的最后活动时刻提前，模拟时间流逝
+//! - `POST /debug/viewers` - 注入合成观众
+//! - `POST /debug/chat` - 向当前活跃房间注入合成聊天消息
+//! - `POST /debug/srs` - 模拟一次 SRS 回调
+//!
+//! 不要在生产部署中启用此特性。
+
+use super::{
+    admin::check_admin,
+    srs::{dispatch_callback, SrsCallbackRequest},
+};
+use super::super::{
+    domain::{ClientIp, SessionId},
+    error::ApiError,
+    ids,
+    state::{AppState, ClientStatus},
+};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 单次注入的合成观众/消息数量上限
+const MAX_INJECT_COUNT: usize = 10_000;
+
+/// 单次快进的时长上限（秒）
+const MAX_FAST_FORWARD_SECS: u64 = 86_400;
+
+/// 合成观众的 IP 前缀（RFC 2544 基准测试网段 198.18.0.0/15）
+const SYNTHETIC_IP_PREFIX: &str = "198.18";
+
+/// 合成观众的 session_id 前缀
+const SYNTHETIC_SESSION_PREFIX: &str = "debug-";
+
+// ============================================================================
+// 时间快进
+// ============================================================================
+
+/// 时间快进请求参数
+#[derive(Debug, Deserialize)]
+pub struct FastForwardParams {
+    /// 快进时长（秒）
+    secs: u64,
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 时间快进处理器
+///
+/// 将所有客户端和主播的最后活动时刻提前 `secs` 秒，
+/// 下一次后台清理时即按新的时刻判断过期（并照常发送离开提示、关闭聊天室）
+///
+/// ### 路由
+/// `POST /debug/fast_forward?secs=3600`
+///
+/// ### 响应格式
+/// ```json
+/// {"secs": 3600, "expired_clients": 12, "streamer_expired": false}
+/// ```
+pub async fn fast_forward_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<FastForwardParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let secs = params.secs.min(MAX_FAST_FORWARD_SECS);
+    let by = Duration::from_secs(secs);
+    let mut streamer_state = state.srs_db.streamer.write();
+    let mut registry = state.srs_db.clients.write();
+    let mut expired_clients = 0;
+    for client in registry.clients.values_mut().flat_map(|m| m.values_mut()) {
+        client.last_seen = client.last_seen.checked_sub(by).unwrap_or(client.last_seen);
+        if client.is_expired() {
+            expired_clients += 1;
+        }
+    }
+    let streamer = &mut streamer_state.streamer;
+    streamer.last_seen = streamer.last_seen.checked_sub(by).unwrap_or(streamer.last_seen);
+    let streamer_expired = streamer.is_expired();
+
+    tracing::info!(
+        "调试: 时间快进 {} 秒，{} 个客户端待过期，主播待过期: {}",
+        secs,
+        expired_clients,
+        streamer_expired
+    );
+    Json(json!({
+        "secs": secs,
+        "expired_clients": expired_clients,
+        "streamer_expired": streamer_expired,
+    }))
+    .into_response()
+}
+
+// ============================================================================
+// 合成观众
+// ============================================================================
+
+/// 合成观众请求参数
+#[derive(Debug, Deserialize)]
+pub struct ViewersParams {
+    /// 观众数量
+    count: usize,
+    /// 观众状态（默认 `legal`，`playing` 状态永不过期）
+    status: Option<String>,
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 合成观众处理器
+///
+/// 注入指定数量的合成观众，IP 取自 `198.18.0.0/15`，session_id 以 `debug-` 开头
+///
+/// ### 路由
+/// `POST /debug/viewers?count=500&status=playing`
+///
+/// ### 响应格式
+/// ```json
+/// {"added": 500, "status": "playing"}
+/// ```
+pub async fn viewers_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ViewersParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let status = match params.status.as_deref() {
+        None => ClientStatus::Legal,
+        Some(s) => match ClientStatus::parse(s) {
+            Some(status) => status,
+            None => return ApiError::BadRequest(format!("unknown status: {}", s)).into_response(),
+        },
+    };
+    let count = params.count.min(MAX_INJECT_COUNT);
+
+    let mut registry = state.srs_db.clients.write();
+    for _ in 0..count {
+        let (ip, session_id) = synthetic_viewer();
+        registry.add_client(ip.clone(), session_id.clone());
+        // 观看中和暂离只能由已授权状态转换而来
+        if matches!(status, ClientStatus::Playing | ClientStatus::Resting) {
+            registry.transition(&ip, &session_id, ClientStatus::Legal);
+        }
+        registry.transition(&ip, &session_id, status);
+    }
+
+    tracing::info!("调试: 注入 {} 个合成观众（{}）", count, status.as_str());
+    Json(json!({"added": count, "status": status.as_str()})).into_response()
+}
+
+/// 生成一个合成观众的 (IP, session_id)
+fn synthetic_viewer() -> (ClientIp, SessionId) {
+    let n: u16 = rand::random();
+    let ip = ClientIp::parse(&format!("{}.{}.{}", SYNTHETIC_IP_PREFIX, n >> 8, n & 0xff)).expect("合成 IP 合法");
+    let session_id = SessionId::parse(&format!("{}{}", SYNTHETIC_SESSION_PREFIX, ids::ulid())).expect("合成会话 ID 合法");
+    (ip, session_id)
+}
+
+// ============================================================================
+// 合成聊天负载
+// ============================================================================
+
+/// 合成聊天请求参数
+#[derive(Debug, Deserialize)]
+pub struct ChatLoadParams {
+    /// 消息数量
+    count: usize,
+    /// 发送者数量（默认 10）
+    senders: Option<usize>,
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 合成聊天负载处理器
+///
+/// 由若干合成发送者向当前活跃房间轮流发送消息（不经过链接策略和准入脚本）
+///
+/// ### 路由
+/// `POST /debug/chat?count=1000&senders=50`
+///
+/// ### 响应格式
+/// ```json
+/// {"room": "live/stream", "added": 1000, "size": 1234}
+/// ```
+pub async fn chat_load_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ChatLoadParams>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let count = params.count.min(MAX_INJECT_COUNT);
+    let senders: Vec<(ClientIp, SessionId)> = (0..params.senders.unwrap_or(10).clamp(1, MAX_INJECT_COUNT))
+        .map(|_| synthetic_viewer())
+        .collect();
+    let config = state.config();
+    let limits = (config.chat_embed_user_per_min, config.chat_embed_global_per_min);
+
+    let mut chat_rooms = state.chat_db.inner.write();
+    let room = chat_rooms.active_mut();
+    for i in 0..count {
+        let (ip, session_id) = &senders[i % senders.len()];
+        room.add_entry(
+            ip.clone(),
+            session_id.clone(),
+            format!("合成消息 #{}", i + 1),
+            false,
+            None,
+            limits,
+        );
+    }
+
+    tracing::info!("调试: 向房间 {} 注入 {} 条合成消息", room.id, count);
+    Json(json!({"room": room.id, "added": count, "size": room.size()})).into_response()
+}
+
+// ============================================================================
+// 模拟 SRS 回调
+// ============================================================================
+
+/// 管理令牌查询参数
+#[derive(Debug, Deserialize)]
+pub struct TokenParams {
+    /// 管理令牌（也可通过请求头传递）
+    admin_token: Option<String>,
+}
+
+/// 模拟 SRS 回调请求体（除 `action` 外均可省略）
+#[derive(Debug, Deserialize)]
+pub struct SimulatedCallback {
+    /// 回调类型：on_publish, on_play, on_unpublish, on_stop
+    action: String,
+    /// 客户端 IP（默认 `127.0.0.1`）
+    ip: Option<String>,
+    /// 应用名称（默认取配置中的 SRS 应用名）
+    app: Option<String>,
+    /// 流名称（默认 `debug`）
+    stream: Option<String>,
+    /// 查询参数字符串，如 `?secret=xxx&session_id=yyy`
+    param: Option<String>,
+}
+
+/// 模拟 SRS 回调处理器
+///
+/// 按 SRS 回调的格式构造请求，交给 SRS 回调分发处理（跳过来源校验），
+/// 可用于演练主播掉线（`on_unpublish`）、恢复推流等场景
+///
+/// ### 路由
+/// `POST /debug/srs`
+///
+/// ### 请求格式
+/// ```json
+/// {"action": "on_unpublish", "param": "?secret=xxx"}
+/// ```
+///
+/// ### 返回值
+/// 与 SRS 回调相同：成功为 `0`，拒绝为 403
+pub async fn simulate_srs_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<TokenParams>,
+    Json(req): Json<SimulatedCallback>,
+) -> Response {
+    if let Err(e) = check_admin(&state, &headers, params.admin_token.as_deref()) {
+        return e.into_response();
+    }
+
+    let payload = SrsCallbackRequest {
+        action: req.action,
+        ip: req.ip.unwrap_or_else(|| "127.0.0.1".to_string()),
+        app: req.app.unwrap_or_else(|| state.config().srs_app.clone()),
+        stream: req.stream.unwrap_or_else(|| "debug".to_string()),
+        param: req.param.unwrap_or_default(),
+        _tc_url: String::new(),
+        file: None,
+        client_id: None,
+    };
+    tracing::info!("调试: 模拟 SRS 回调 {:?}", payload);
+    dispatch_callback(state, payload).await
+}
@@ -0,0 +1,32 @@
+//! # 事件推送处理器模块
+//!
+//! 通过 Server-Sent Events（SSE）向客户端推送直播状态变化。
+
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+/// SSE 事件流处理器
+///
+/// ### 路由
+/// `GET /events`
+///
+/// ### 响应格式
+/// 每个事件的 `event` 字段为事件名称，`data` 字段为事件的 JSON 表示
+pub async fn events_handler(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(|event| {
+        // 落后过多导致丢失的事件直接跳过
+        let event = event.ok()?;
+        let data = serde_json::to_string(&event).ok()?;
+        Some(Ok(Event::default().event(event.name()).data(data)))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
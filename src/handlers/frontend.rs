@@ -0,0 +1,172 @@
+//! # 外部前端处理器模块
+//!
+//! 支持把前端静态页面托管在其他域名上（如 GitHub Pages、CDN）：
+//! - `cors` - 只对 `LIVE_SERVER_FRONTEND_TOKENS` 中登记的来源返回 CORS 响应头
+//! - `frontend_gate` - 登记来源的请求须携带绑定的 API 令牌，并按令牌和客户端 IP 限制频率
+//! - `bootstrap_handler` - 前端启动时首先调用，获取各接口的绝对地址
+//!
+//! API 令牌通过请求头 `X-Api-Token` 携带；`EventSource` 无法设置请求头，可改用查询参数 `api_token`。
+
+use super::super::{
+    domain::ClientIp,
+    error::ApiError,
+    state::{frontend, AppState},
+};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, Method},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use url::form_urlencoded;
+
+/// 携带 API 令牌的请求头
+const API_TOKEN_HEADER: &str = "x-api-token";
+
+/// 携带 API 令牌的查询参数
+const API_TOKEN_PARAM: &str = "api_token";
+
+/// 浏览器缓存预检结果的时长（秒）
+const PREFLIGHT_MAX_AGE_SECS: u64 = 600;
+
+/// 构建观众接口的 CORS 层
+///
+/// 允许的来源在每次请求时从当前配置读取，热重载 `LIVE_SERVER_FRONTEND_TOKENS` 后立即生效
+pub fn cors(state: Arc<AppState>) -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origin
+                .to_str()
+                .is_ok_and(|o| frontend::lookup(&state.config(), o).is_some())
+        }))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([
+            header::ACCEPT,
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static(API_TOKEN_HEADER),
+        ])
+        .expose_headers([header::RETRY_AFTER])
+        .max_age(Duration::from_secs(PREFLIGHT_MAX_AGE_SECS))
+}
+
+/// 外部前端令牌校验中间件
+///
+/// 来源未登记（含同源请求和没有 `Origin` 头的请求）时直接放行
+///
+/// ### 返回值
+/// - 令牌缺失或与来源不匹配时返回 403
+/// - 超出频率限制时返回 429
+pub async fn frontend_gate(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(origin) = request.headers().get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return next.run(request).await;
+    };
+    let config = state.config();
+    let Some(frontend) = frontend::lookup(&config, origin) else {
+        return next.run(request).await;
+    };
+
+    let provided = request
+        .headers()
+        .get(API_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| {
+            form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+                .find(|(k, _)| k == API_TOKEN_PARAM)
+                .map(|(_, v)| v.into_owned())
+        });
+    if provided.as_deref() != Some(frontend.token.as_str()) {
+        tracing::debug!("来源 {} 的请求未携带有效的 API 令牌", origin);
+        return ApiError::Forbidden("invalid api token".to_string()).into_response();
+    }
+
+    let ip = ClientIp::from_request(request.headers(), peer, &config.trusted_proxies);
+    if let Err(retry_after) = state.frontend.hit(&frontend.token, &ip, config.frontend_rate_per_min) {
+        return ApiError::RateLimited { retry_after }.into_response();
+    }
+    next.run(request).await
+}
+
+/// 前端启动信息
+#[derive(Debug, Serialize)]
+pub struct Bootstrap {
+    /// 服务的公网地址
+    pub base: String,
+    /// 认证答题接口
+    pub api: String,
+    /// 聊天室接口
+    pub chat: String,
+    /// SSE 事件推送
+    pub events: String,
+    /// 观众人数
+    pub streaming_info: String,
+    /// 外部前端每个客户端每分钟的请求数上限（0 表示不限制）
+    pub rate_limit_per_min: u32,
+}
+
+/// 前端启动信息处理器
+///
+/// 托管在其他域名上的前端无法使用相对路径，启动时先调用此接口获取各接口的绝对地址
+///
+/// ### 路由
+/// `GET /api/bootstrap`
+///
+/// ### 响应格式
+/// ```json
+/// {
+///   "base": "https://live.example.com",
+///   "api": "https://live.example.com/api",
+///   "chat": "https://live.example.com/chat",
+///   "events": "https://live.example.com/events",
+///   "streaming_info": "https://live.example.com/streaming_info",
+///   "rate_limit_per_min": 300
+/// }
+/// ```
+pub async fn bootstrap_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let config = state.config();
+    let base = match &config.public_url {
+        Some(url) => url.clone(),
+        None => request_base(&headers).ok_or_else(|| ApiError::BadRequest("missing host header".to_string()))?,
+    };
+    Ok(Json(Bootstrap {
+        api: format!("{}/api", base),
+        chat: format!("{}/chat", base),
+        events: format!("{}/events", base),
+        streaming_info: format!("{}/streaming_info", base),
+        rate_limit_per_min: config.frontend_rate_per_min,
+        base,
+    })
+    .into_response())
+}
+
+/// 由请求头推断服务的公网地址
+///
+/// 协议取 `X-Forwarded-Proto`（反向代理终止 TLS 时设置），缺失时为 `http`
+fn request_base(headers: &HeaderMap) -> Option<String> {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .filter(|h| !h.is_empty())?;
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(str::trim)
+        .filter(|p| matches!(*p, "http" | "https"))
+        .unwrap_or("http");
+    Some(format!("{}://{}", scheme, host))
+}
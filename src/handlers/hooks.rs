@@ -0,0 +1,116 @@
+//! # 外部回调处理器模块
+//!
+//! 供第三方服务主动调用的回调接口（webhook），均使用共享密钥鉴权：
+//! - 请求头 `Authorization: Bearer <密钥>`，或
+//! - 请求头 `X-Hook-Secret: <密钥>`
+//!
+//! 对应的密钥未配置时接口返回 403。
+
+use super::super::{
+    error::ApiError,
+    redact,
+    state::{events::StreamEvent, secret_guard::secret_eq, AppState},
+};
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// 打赏者名称的最大长度（字符）
+const MAX_TIP_NAME_CHARS: usize = 32;
+
+/// 打赏留言的最大长度（字符）
+const MAX_TIP_MESSAGE_CHARS: usize = 200;
+
+// ============================================================================
+// 鉴权
+// ============================================================================
+
+/// 校验回调共享密钥
+///
+/// ### 返回值
+/// - `Ok(())`: 密钥有效
+/// - `Err(ApiError::Forbidden)`: 未配置密钥或密钥无效
+fn check_hook_secret(expected: Option<&SecretString>, headers: &HeaderMap) -> Result<(), ApiError> {
+    let expected = expected.ok_or_else(|| ApiError::Forbidden("hook disabled".to_string()))?;
+
+    let provided = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-hook-secret").and_then(|v| v.to_str().ok()));
+
+    if provided.is_some_and(|p| secret_eq(p, expected.expose_secret())) {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden("invalid hook secret".to_string()))
+    }
+}
+
+// ============================================================================
+// 打赏回调
+// ============================================================================
+
+/// 打赏回调请求体
+#[derive(Debug, Deserialize)]
+pub struct TipRequest {
+    /// 打赏者名称
+    name: String,
+    /// 打赏金额
+    amount: f64,
+    /// 货币单位（如 `CNY`）
+    #[serde(default)]
+    currency: String,
+    /// 打赏留言
+    message: Option<String>,
+}
+
+/// 打赏回调处理器
+///
+/// ### 路由
+/// `POST /api/hooks/tip`
+///
+/// ### 请求体
+/// ```json
+/// {"name": "某观众", "amount": 30, "currency": "CNY", "message": "加油"}
+/// ```
+///
+/// ### 行为
+/// 在当前聊天室插入高亮的 "X 打赏了 Y" 消息，记入本场聊天记录转储，
+/// 并向事件订阅者推送 `tip_received` 事件
+pub async fn tip_hook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<TipRequest>,
+) -> Result<Response, ApiError> {
+    check_hook_secret(state.config().tip_hook_secret.as_ref(), &headers)?;
+
+    let name: String = req.name.trim().chars().take(MAX_TIP_NAME_CHARS).collect();
+    if name.is_empty() || !req.amount.is_finite() || req.amount <= 0.0 {
+        return Err(ApiError::BadRequest("invalid tip".to_string()));
+    }
+    let currency: String = req.currency.trim().chars().take(8).collect();
+    let message = req
+        .message
+        .map(|m| m.trim().chars().take(MAX_TIP_MESSAGE_CHARS).collect::<String>());
+
+    {
+        let mut chat_rooms = state.chat_db.inner.write();
+        chat_rooms
+            .active_mut()
+            .add_tip(name.clone(), req.amount, currency.clone(), message);
+    }
+    tracing::info!("收到打赏: {} {} {}", redact::text(&name), req.amount, currency);
+
+    state.events.publish(StreamEvent::TipReceived {
+        name,
+        amount: req.amount,
+        currency,
+    });
+    Ok(Json(json!({ "status": "Okay" })).into_response())
+}
@@ -5,15 +5,32 @@
 //! - `api` - 观众端 API 处理器（答题验证、状态查询等）
 //! - `chat` - 聊天室处理器（发送消息、设置昵称等）
 //! - `srs` - SRS 回调处理器（推流/拉流事件回调）
+//! - `streaming_info` - 直播状态与观众人数查询处理器
+//! - `hooks` - 外部服务回调处理器（打赏通知等）
+//! - `publisher` - 主播手机端快捷操作与历史归档浏览处理器
+//! - `frontend` - 托管在其他域名上的前端（CORS、API 令牌、启动信息）
+//! - `debug` - 调试与故障演练接口（仅 `debug-endpoints` 特性）
 
 // 子模块声明
 pub mod api;   // API 处理器模块
 pub mod chat;  // 聊天室处理器模块
 pub mod srs;   // SRS 回调处理器模块
-pub mod streaming_info;
+pub mod streaming_info; // 流信息处理器模块
+pub mod events; // SSE 事件推送模块
+pub mod admin;  // 管理接口模块
+pub mod hooks;  // 外部回调模块
+pub mod publisher; // 主播快捷操作模块
+pub mod frontend;  // 外部前端模块
+#[cfg(feature = "debug-endpoints")]
+pub mod debug;  // 调试与故障演练模块
 
 // 导出公共处理器函数，供 main.rs 中使用
 pub use api::{api_handler};           // API 请求主处理器
-pub use chat::{chat_handler};         // 聊天室请求处理器
-pub use srs::{srs_callback_handler};  // SRS 回调处理器
-pub use streaming_info::{streaming_info_handler};  // SRS 回调处理器
+pub use chat::{chat_handler, chat_redirect_handler};  // 聊天室请求处理器
+pub use srs::{srs_callback_handler, srs_hook_handler};  // SRS 回调处理器
+pub use streaming_info::{streaming_info_handler};  // 流信息处理器
+pub use events::events_handler;       // SSE 事件推送处理器
+pub use admin::{bannerdb_report_handler, bannerdb_sample_handler, admin_events_handler, chat_export_handler, clients_bulk_handler, config_preview_handler, clients_handler, metrics_handler, push_qr_handler, recordings_handler, relay_seal_handler, reload_handler, status_handler};  // 题库管理处理器
+pub use hooks::tip_hook_handler;       // 打赏回调处理器
+pub use publisher::{archive_handler, archive_report_handler, archive_transcript_handler, preview_handler, preview_revoke_handler, publisher_quick_handler, push_url_handler, relay_control_handler, relay_status_handler};  // 主播快捷操作处理器
+pub use frontend::bootstrap_handler;  // 前端启动信息处理器
@@ -0,0 +1,688 @@
+//! # 主播快捷操作处理器模块
+//!
+//! 为主播离开推流电脑时在手机上使用而设计的一键操作接口，以及推流地址生成、
+//! 预览地址生成与撤销、转推控制和历史归档浏览接口，均使用主播访问令牌（JWT）鉴权：
+//! - 请求头 `Authorization: Bearer <令牌>`
+//!
+//! 令牌在主播通过推流密钥登录后随 `/api` 响应的 `publisher_token` 字段返回，
+//! 仅在签发它的会话仍是当前主播时有效（归档浏览接口只要求令牌未过期，直播结束后仍可使用）。
+//! 未配置 `LIVE_SERVER_PUBLISHER_JWT_KEY` 时接口返回 403。
+
+use super::super::{
+    config::LatencyMode,
+    domain::{ClientIp, SessionId, Uid},
+    error::ApiError,
+    state::{
+        archive::{self, ArchiveKind},
+        chat::MAX_SLOW_MODE_SECS,
+        disk_guard,
+        events::StreamEvent,
+        invites::MAX_GUEST_PASSES,
+        push_url::{self, PushTarget},
+        stream_policy,
+        scheduled::{ScheduleTime, MAX_SCHEDULED},
+        srs::EntryMode,
+        AppState,
+    },
+};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
+};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// 切换慢速模式且未指定间隔时使用的默认间隔（秒）
+const DEFAULT_SLOW_MODE_SECS: u64 = 30;
+
+/// 快捷发送消息的最大长度（字符）
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// 快捷操作请求体
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum QuickAction {
+    /// 暂停或恢复自动提示（进出直播间提示）
+    Announcements { paused: bool },
+    /// 设置慢速模式间隔（秒，0 表示关闭），省略时在关闭与默认间隔之间切换
+    SlowMode { secs: Option<u64> },
+    /// 保存聊天快照
+    Snapshot,
+    /// 结束直播
+    End,
+    /// 以主播身份发送一条消息
+    Post { message: String },
+    /// 开启或关闭当前直播的录制（DVR）
+    Recording { enabled: bool },
+    /// 切换播放延迟模式（`low` / `stable`），省略时恢复为配置的默认模式
+    LatencyMode { mode: Option<String> },
+    /// 直播叠加层回报已显示到的消息 ID
+    Shown { id: String },
+    /// 预约定时消息：`at`（Unix 时间戳，秒）与 `offset_secs`（开播后秒数）二选一
+    Schedule {
+        message: String,
+        at: Option<i64>,
+        offset_secs: Option<u64>,
+    },
+    /// 取消定时消息
+    CancelScheduled { id: String },
+    /// 列出待发送的定时消息
+    ListScheduled,
+    /// 按聊天 UID 指定联合主持，省略时取消
+    CoHost { uid: Option<Uid> },
+    /// 关闭或重新开放入场：关闭时新观众连接不再发放题目，已授权的观众继续观看和聊天
+    Gate { closed: bool },
+    /// 签发限时访客通行证（有效时长，分钟）
+    GuestPass { minutes: u64 },
+    /// 撤销访客通行证
+    RevokeGuestPass { code: String },
+    /// 列出有效的访客通行证
+    ListGuestPasses,
+}
+
+/// 校验主播访问令牌的签名和有效期
+///
+/// ### 返回值
+/// - `Ok(session_id)`: 令牌有效（不检查签发它的会话是否仍是当前主播）
+/// - `Err(ApiError::Forbidden)`: 未配置签名密钥或令牌无效
+fn verify_publisher_token(state: &AppState, headers: &HeaderMap) -> Result<SessionId, ApiError> {
+    let signer = state
+        .publisher_tokens
+        .as_ref()
+        .ok_or_else(|| ApiError::Forbidden("publisher token disabled".to_string()))?;
+
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .and_then(|token| signer.verify(token))
+        .ok_or_else(|| ApiError::Forbidden("invalid publisher token".to_string()))
+}
+
+/// 校验主播访问令牌
+///
+/// ### 返回值
+/// - `Ok((ip, session_id))`: 令牌有效且对应会话仍是当前主播
+/// - `Err(ApiError::Forbidden)`: 未配置签名密钥、令牌无效或会话已不是主播
+fn check_publisher_token(state: &AppState, headers: &HeaderMap) -> Result<(ClientIp, SessionId), ApiError> {
+    let session_id = verify_publisher_token(state, headers)?;
+
+    if state.srs_db.streamer.read().publisher_session() != Some(&session_id) {
+        return Err(ApiError::Forbidden("session is no longer the publisher".to_string()));
+    }
+    let ip = state
+        .srs_db
+        .clients
+        .read()
+        .find_client_ip(&session_id)
+        .ok_or_else(|| ApiError::Forbidden("publisher session expired".to_string()))?
+        .clone();
+    Ok((ip, session_id))
+}
+
+/// 主播快捷操作处理器
+///
+/// ### 路由
+/// `POST /api/publisher/quick`
+///
+/// ### 请求格式
+/// ```json
+/// {"action": "announcements", "paused": true}
+/// {"action": "slow_mode", "secs": 30}
+/// {"action": "snapshot"}
+/// {"action": "end"}
+/// {"action": "post", "message": "马上回来"}
+/// {"action": "recording", "enabled": true}
+/// {"action": "latency_mode", "mode": "stable"}
+/// {"action": "shown", "id": "01J..."}
+/// {"action": "schedule", "message": "抽奖开始！", "offset_secs": 3600}
+/// {"action": "cancel_scheduled", "id": "01J..."}
+/// {"action": "list_scheduled"}
+/// {"action": "co_host", "uid": 42}
+/// {"action": "gate", "closed": true}
+/// {"action": "guest_pass", "minutes": 30}
+/// {"action": "revoke_guest_pass", "code": "7KQ2MXPA"}
+/// {"action": "list_guest_passes"}
+/// ```
+///
+/// ### 响应格式
+/// ```json
+/// {"status": "ok", "slow_mode_secs": 30}
+/// ```
+pub async fn publisher_quick_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(action): Json<QuickAction>,
+) -> Result<Response, ApiError> {
+    let (ip, session_id) = check_publisher_token(&state, &headers)?;
+
+    let body = match action {
+        QuickAction::Announcements { paused } => {
+            state.chat_db.inner.write().active_mut().announcements_paused = paused;
+            tracing::info!("主播快捷操作: {}自动提示", if paused { "暂停" } else { "恢复" });
+            json!({"status": "ok", "announcements_paused": paused})
+        }
+        QuickAction::SlowMode { secs } => {
+            let mut chat_rooms = state.chat_db.inner.write();
+            let room = chat_rooms.active_mut();
+            room.slow_mode_secs = match secs {
+                Some(secs) => secs.min(MAX_SLOW_MODE_SECS),
+                None if room.slow_mode_secs > 0 => 0,
+                None => DEFAULT_SLOW_MODE_SECS,
+            };
+            tracing::info!("主播快捷操作: 慢速模式 {} 秒", room.slow_mode_secs);
+            json!({"status": "ok", "slow_mode_secs": room.slow_mode_secs})
+        }
+        QuickAction::Snapshot => {
+            let chat_rooms = state.chat_db.inner.read();
+            if chat_rooms.is_lobby_active() {
+                // 离线大厅不参与直播转储
+                return Err(ApiError::StreamOffline);
+            }
+            if let Some(low) = disk_guard::check(&chat_rooms.active().dump_path, state.config().dump_min_free_bytes) {
+                low.report(&state.events);
+                return Err(ApiError::Internal(low.describe()));
+            }
+            let path = chat_rooms.active().dump_full().map_err(|e| {
+                tracing::warn!("主播快捷操作: 保存聊天记录失败: {}", e);
+                ApiError::Internal(e)
+            })?;
+            tracing::info!("主播快捷操作: 保存了聊天记录: {}", path.display());
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+            json!({"status": "ok", "snapshot": name})
+        }
+        QuickAction::End => {
+            if !super::api::end_stream(&state, &session_id) {
+                return Err(ApiError::StreamOffline);
+            }
+            tracing::info!("主播快捷操作: 结束了直播");
+            json!({"status": "ok"})
+        }
+        QuickAction::Post { message } => {
+            let message: String = message.trim().chars().take(MAX_MESSAGE_CHARS).collect();
+            if message.is_empty() {
+                return Err(ApiError::BadRequest("empty message".to_string()));
+            }
+            let config = state.config();
+            let limits = (config.chat_embed_user_per_min, config.chat_embed_global_per_min);
+            state
+                .chat_db
+                .inner
+                .write()
+                .active_mut()
+                .add_entry(ip, session_id, message, true, None, limits);
+            json!({"status": "ok"})
+        }
+        QuickAction::Recording { enabled } => {
+            let target = state
+                .srs_db
+                .streamer
+                .read()
+                .get_stream_target()
+                .map(|(app, stream)| (app.to_string(), stream.to_string()));
+            let (app, stream) = target.ok_or(ApiError::StreamOffline)?;
+            let config = state.config();
+            state
+                .srs_api
+                .set_dvr(&config.srs_vhost, &app, &stream, enabled)
+                .await
+                .map_err(|e| {
+                    tracing::warn!("主播快捷操作: 切换录制失败: {}", e);
+                    ApiError::Internal(e)
+                })?;
+            state.srs_db.streamer.write().set_recording(enabled);
+            tracing::info!("主播快捷操作: {}录制", if enabled { "开始" } else { "停止" });
+            json!({"status": "ok", "recording": enabled})
+        }
+        QuickAction::LatencyMode { mode } => {
+            let mode = match mode.as_deref() {
+                Some(m) => Some(
+                    LatencyMode::parse(m)
+                        .ok_or_else(|| ApiError::BadRequest("mode must be low or stable".to_string()))?,
+                ),
+                None => None,
+            };
+            let mut streamer = state.srs_db.streamer.write();
+            if !streamer.is_streaming() {
+                return Err(ApiError::StreamOffline);
+            }
+            streamer.set_latency_mode(mode);
+            let effective = streamer.latency_mode(state.config().latency_mode);
+            tracing::info!("主播快捷操作: 切换为 {} 延迟模式", effective.as_str());
+            json!({"status": "ok", "latency_mode": effective.as_str()})
+        }
+        QuickAction::Shown { id } => {
+            let shown_until = {
+                let mut chat_rooms = state.chat_db.inner.write();
+                let room = chat_rooms.active_mut();
+                if !room.mark_shown(&id) {
+                    return Err(ApiError::NotFound("message not found".to_string()));
+                }
+                room.shown_until.clone()
+            };
+            if let Some(id) = &shown_until {
+                state.events.publish(StreamEvent::MessagesShown { id: id.clone() });
+            }
+            json!({"status": "ok", "shown_until": shown_until})
+        }
+        QuickAction::Schedule { message, at, offset_secs } => {
+            let message: String = message.trim().chars().take(MAX_MESSAGE_CHARS).collect();
+            if message.is_empty() {
+                return Err(ApiError::BadRequest("empty message".to_string()));
+            }
+            let when = match (at, offset_secs) {
+                (Some(at), None) => ScheduleTime::At {
+                    at: chrono::DateTime::from_timestamp(at, 0)
+                        .filter(|t| *t > chrono::Utc::now())
+                        .ok_or_else(|| ApiError::BadRequest("at must be in the future".to_string()))?,
+                },
+                (None, Some(secs)) => ScheduleTime::StreamOffset { secs },
+                _ => {
+                    return Err(ApiError::BadRequest(
+                        "exactly one of at and offset_secs is required".to_string(),
+                    ))
+                }
+            };
+            let scheduled = state
+                .scheduled
+                .schedule(ip, session_id, message, when)
+                .ok_or_else(|| ApiError::BadRequest(format!("at most {} scheduled messages", MAX_SCHEDULED)))?;
+            tracing::info!("主播快捷操作: 预约定时消息 {}", scheduled.id);
+            json!({"status": "ok", "scheduled": scheduled})
+        }
+        QuickAction::CancelScheduled { id } => {
+            if !state.scheduled.cancel(&id) {
+                return Err(ApiError::NotFound("scheduled message not found".to_string()));
+            }
+            tracing::info!("主播快捷操作: 取消定时消息 {}", id);
+            json!({"status": "ok"})
+        }
+        QuickAction::ListScheduled => {
+            json!({"status": "ok", "scheduled": state.scheduled.list()})
+        }
+        QuickAction::CoHost { uid } => {
+            let session_id = match uid {
+                Some(uid) => Some(
+                    state
+                        .chat_db
+                        .inner
+                        .read()
+                        .active()
+                        .find_client(uid)
+                        .map(|(_, session_id)| session_id.clone())
+                        .ok_or_else(|| ApiError::NotFound("user not found".to_string()))?,
+                ),
+                None => None,
+            };
+            if !state.srs_db.clients.write().set_co_host(session_id) {
+                return Err(ApiError::BadRequest("user cannot be co-host".to_string()));
+            }
+            tracing::info!("主播快捷操作: 联合主持设为 {:?}", uid);
+            json!({"status": "ok", "co_host": uid})
+        }
+        QuickAction::Gate { closed } => {
+            if !state.srs_db.is_streaming() {
+                return Err(ApiError::StreamOffline);
+            }
+            state.srs_db.access.write().set_entries_closed(closed);
+            tracing::info!("主播快捷操作: {}入场", if closed { "关闭" } else { "开放" });
+            json!({"status": "ok", "entries_closed": closed})
+        }
+        QuickAction::GuestPass { minutes } => {
+            if minutes == 0 {
+                return Err(ApiError::BadRequest("minutes must be positive".to_string()));
+            }
+            let pass = state
+                .invites
+                .issue_guest_pass(minutes)
+                .ok_or_else(|| ApiError::BadRequest(format!("at most {} guest passes", MAX_GUEST_PASSES)))?;
+            tracing::info!("主播快捷操作: 签发访客通行证，有效至 {}", pass.expires_at);
+            json!({"status": "ok", "guest_pass": pass})
+        }
+        QuickAction::RevokeGuestPass { code } => {
+            if !state.invites.revoke_guest_pass(&code) {
+                return Err(ApiError::NotFound("guest pass not found".to_string()));
+            }
+            tracing::info!("主播快捷操作: 撤销访客通行证");
+            json!({"status": "ok"})
+        }
+        QuickAction::ListGuestPasses => {
+            json!({"status": "ok", "guest_passes": state.invites.guest_passes()})
+        }
+    };
+    Ok(Json(body).into_response())
+}
+
+/// 推流地址请求参数
+#[derive(Debug, Deserialize)]
+pub struct PushUrlParams {
+    /// 流名称（默认沿用当前推流的流名称）
+    stream: Option<String>,
+    /// 是否公开模式（省略时不携带该参数）
+    public: Option<bool>,
+    /// 观众入场方式：`quiz` / `confirm`（省略时不携带该参数）
+    entry: Option<EntryMode>,
+    /// 直播间名称（省略时不携带该参数）
+    name: Option<String>,
+}
+
+/// 推流地址处理器
+///
+/// 用主播登录时验证过的推流密钥拼装完整的 RTMP 和 SRT 推流地址，
+/// 服务器地址、端口和应用名取自配置
+///
+/// ### 路由
+/// `GET /api/publisher/push_url?stream=test&public=false`
+///
+/// ### 响应格式
+/// ```json
+/// {
+///   "rtmp": "rtmp://live.example.com:1935/live/test?secret=secret_xxx&public=false",
+///   "srt": "srt://live.example.com:10080?streamid=%23%21%3A%3Ar%3Dlive%2Ftest..."
+/// }
+/// ```
+pub async fn push_url_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PushUrlParams>,
+) -> Result<Response, ApiError> {
+    check_publisher_token(&state, &headers)?;
+
+    let (secret, current_stream) = {
+        let streamer = state.srs_db.streamer.read();
+        let secret = streamer
+            .streamer
+            .secret
+            .as_ref()
+            .map(|s| s.expose_secret().to_string())
+            .ok_or_else(|| ApiError::Forbidden("no stream secret for this session".to_string()))?;
+        (secret, streamer.streamer.stream.clone())
+    };
+    let stream = params
+        .stream
+        .filter(|s| !s.is_empty())
+        .or(current_stream)
+        .ok_or_else(|| ApiError::BadRequest("stream is required".to_string()))?;
+
+    let config = state.config();
+    let host_header = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+    let host = push_url::resolve_host(config.push_host.as_deref(), host_header)
+        .ok_or_else(|| ApiError::BadRequest("push host unknown".to_string()))?;
+    let target = PushTarget {
+        host,
+        app: config.srs_app.clone(),
+        stream,
+        secret,
+        public: params.public,
+        entry: params.entry,
+        name: params.name.filter(|n| !n.trim().is_empty()),
+        session_id: None,
+    };
+
+    Ok(Json(json!({
+        "rtmp": target.rtmp_url(config.rtmp_port),
+        "srt": target.srt_url(config.srt_port),
+    }))
+    .into_response())
+}
+
+/// 预览地址处理器
+///
+/// 返回附带主播预览令牌的播放地址，主播用它监看自己的直播时不占用观看名额、不计入观众人数。
+/// 令牌只对本场直播有效，同时预览的播放端不超过 `MAX_PREVIEW_CLIENTS` 个
+///
+/// ### 路由
+/// `GET /api/publisher/preview`
+///
+/// ### 响应格式
+/// ```json
+/// {
+///   "preview": "3f2a...",
+///   "video_uri": "app=live&stream=test&preview=3f2a...",
+///   "flv": "/live/test.flv?preview=3f2a..."
+/// }
+/// ```
+pub async fn preview_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    check_publisher_token(&state, &headers)?;
+
+    let snapshot = state.srs_db.snapshot();
+    let (Some((app, stream)), Some(stream_session)) = (&snapshot.target, &snapshot.session_id) else {
+        return Err(ApiError::StreamOffline);
+    };
+    let token = state.preview.issue(stream_session);
+    Ok(Json(json!({
+        "video_uri": format!("{}&preview={}", stream_policy::stream_query(app, stream), token),
+        "flv": format!(
+            "/{}/{}.flv?preview={}",
+            stream_policy::path_segment(app),
+            stream_policy::path_segment(stream),
+            token
+        ),
+        "preview": token,
+    }))
+    .into_response())
+}
+
+/// 预览令牌撤销处理器
+///
+/// 预览地址泄露时使用：此前签发的预览令牌全部失效，正在预览的播放端被踢出，
+/// 之后可重新请求 `GET /api/publisher/preview` 获取新地址
+///
+/// ### 路由
+/// `DELETE /api/publisher/preview`
+///
+/// ### 响应格式
+/// ```json
+/// {"kicked": 2}
+/// ```
+pub async fn preview_revoke_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    check_publisher_token(&state, &headers)?;
+
+    let kick = state.preview.revoke();
+    let kicked = kick.len();
+    tracing::info!("主播撤销了预览令牌，踢出 {} 个预览播放端", kicked);
+    if !kick.is_empty() {
+        let srs_api = state.srs_api.clone();
+        tokio::spawn(async move {
+            for client_id in kick {
+                if let Err(e) = srs_api.kick_client(&client_id).await {
+                    tracing::warn!("踢出预览播放端失败 client_id={}: {}", client_id, e);
+                }
+            }
+        });
+    }
+    Ok(Json(json!({"kicked": kicked})).into_response())
+}
+
+/// 转推控制请求体
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RelayAction {
+    /// 开始转推到指定目标
+    Start { target: String },
+    /// 停止转推到指定目标
+    Stop { target: String },
+}
+
+/// 转推状态响应
+fn relay_status_body(state: &AppState) -> serde_json::Value {
+    let targets: Vec<String> = state.config().relay_targets.keys().cloned().collect();
+    json!({"targets": targets, "relays": state.relays.status()})
+}
+
+/// 转推状态处理器
+///
+/// ### 路由
+/// `GET /api/publisher/relay`
+///
+/// ### 响应格式
+/// ```json
+/// {
+///   "targets": ["twitch", "youtube"],
+///   "relays": [{"target": "youtube", "running": true, "started_at": "2024-01-01T12:00:00Z"}]
+/// }
+/// ```
+pub async fn relay_status_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    check_publisher_token(&state, &headers)?;
+    Ok(Json(relay_status_body(&state)).into_response())
+}
+
+/// 转推控制处理器
+///
+/// 通过 FFmpeg 将当前直播转推到配置中的外部平台，直播结束时自动停止全部转推
+///
+/// ### 路由
+/// `POST /api/publisher/relay`
+///
+/// ### 请求格式
+/// ```json
+/// {"action": "start", "target": "youtube"}
+/// {"action": "stop", "target": "youtube"}
+/// ```
+///
+/// ### 响应格式
+/// 与 `GET /api/publisher/relay` 相同
+pub async fn relay_control_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(action): Json<RelayAction>,
+) -> Result<Response, ApiError> {
+    check_publisher_token(&state, &headers)?;
+
+    match action {
+        RelayAction::Start { target } => {
+            let cipher = state
+                .relays
+                .cipher()
+                .ok_or_else(|| ApiError::Forbidden("relay disabled".to_string()))?;
+            let config = state.config();
+            let url = config
+                .relay_targets
+                .get(&target)
+                .ok_or_else(|| ApiError::BadRequest("unknown relay target".to_string()))?;
+            let url = cipher.open(url).ok_or_else(|| {
+                tracing::warn!("转推目标 {} 解密失败，请检查 LIVE_SERVER_RELAY_KEY", target);
+                ApiError::Internal("cannot decrypt relay target".to_string())
+            })?;
+            let source = {
+                let streamer = state.srs_db.streamer.read();
+                let (app, stream) = streamer.get_stream_target().ok_or(ApiError::StreamOffline)?;
+                state
+                    .relays
+                    .source_url(&config.srs_api_host, config.rtmp_port, app, stream)
+            };
+            state
+                .relays
+                .start(&config.ffmpeg_path, &target, &source, &url)
+                .map_err(ApiError::BadRequest)?;
+            tracing::info!("开始转推到 {}", target);
+        }
+        RelayAction::Stop { target } => {
+            if state.relays.stop(&target) {
+                tracing::info!("停止转推到 {}", target);
+            }
+        }
+    }
+    Ok(Json(relay_status_body(&state)).into_response())
+}
+
+/// 归档文件请求参数
+#[derive(Debug, Deserialize)]
+pub struct ArchiveFileParams {
+    /// 文件名（取自 `GET /api/archive` 的列表）
+    name: String,
+}
+
+/// 归档列表处理器
+///
+/// 列出转储目录中过往直播场次的聊天记录转储和用户消息报告（最近的场次在前）
+///
+/// ### 路由
+/// `GET /api/archive`
+///
+/// ### 响应格式
+/// ```json
+/// [{
+///   "session": "01J...",
+///   "transcripts": [{"name": "live-01J...-20240101T120000Z-0.json", "size": 10240, "modified": "..."}],
+///   "reports": [{"name": "report-01J...-42-20240101-120000.html", "size": 2048, "modified": "...", "uid": 42}],
+///   "last_modified": "2024-01-01T12:00:00Z",
+///   "total_bytes": 12288
+/// }]
+/// ```
+pub async fn archive_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    verify_publisher_token(&state, &headers)?;
+    let dump_path = state.config().dump_path.clone();
+    let sessions = tokio::task::spawn_blocking(move || archive::list(&dump_path))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(ApiError::Internal)?;
+    Ok(Json(sessions).into_response())
+}
+
+/// 聊天记录转储处理器
+///
+/// ### 路由
+/// `GET /api/archive/transcript?name=live-01J...-20240101T120000Z-0.json`
+///
+/// ### 返回值
+/// 转储文件原文（JSON），文件不存在时返回 404
+pub async fn archive_transcript_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ArchiveFileParams>,
+) -> Result<Response, ApiError> {
+    archive_file(&state, &headers, ArchiveKind::Transcript, params.name).await
+}
+
+/// 用户消息报告处理器
+///
+/// ### 路由
+/// `GET /api/archive/report?name=report-01J...-42-20240101-120000.html`
+///
+/// ### 返回值
+/// 报告原文（按扩展名返回 JSON 或 HTML），文件不存在时返回 404
+pub async fn archive_report_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ArchiveFileParams>,
+) -> Result<Response, ApiError> {
+    archive_file(&state, &headers, ArchiveKind::Report, params.name).await
+}
+
+/// 读取并返回单个归档文件
+async fn archive_file(
+    state: &AppState,
+    headers: &HeaderMap,
+    kind: ArchiveKind,
+    name: String,
+) -> Result<Response, ApiError> {
+    verify_publisher_token(state, headers)?;
+    let content_type = if name.ends_with(".html") {
+        "text/html; charset=utf-8"
+    } else {
+        "application/json"
+    };
+    let dump_path = state.config().dump_path.clone();
+    let content = tokio::task::spawn_blocking(move || archive::read(&dump_path, kind, &name))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(ApiError::Internal)?
+        .ok_or_else(|| ApiError::NotFound("archive file not found".to_string()))?;
+    Ok(([(header::CONTENT_TYPE, content_type)], content).into_response())
+}
@@ -7,26 +7,57 @@
 //! - `on_play` - 观众开始拉流
 //! - `on_unpublish` - 主播停止推流
 //! - `on_stop` - 观众停止拉流
+//! - `on_dvr` - 录制文件完成
 //!
 //! ## 回调验证流程
+//! 0. 校验回调来源（`LIVE_SERVER_SRS_CALLBACK_TOKEN` / `LIVE_SERVER_SRS_CALLBACK_ALLOW`），防止伪造回调
 //! 1. 解析 SRS 发送的 JSON 数据
 //! 2. 从 param 字段中提取查询参数
 //! 3. 验证密钥/权限
 //! 4. 更新内部状态
 //! 5. 返回响应给 SRS（允许/拒绝）
+//!
+//! ## 重复回调
+//! SRS 超时重试或被重放的回调在去重窗口内直接返回上次的结果（见 `callback_dedup`），
+//! 各处理函数本身也是幂等的：重复的推流恢复当前直播，重复的停止推流不会再次暂停
+//!
+//! ## 转码版本
+//! 配置了 `LIVE_SERVER_STREAM_VARIANTS` 时，SRS 转码输出的 `<stream><后缀>` 流视为当前直播的清晰度版本：
+//! 推流时不注册新主播，停止时不暂停直播，观众拉流按原流的授权处理
 
+use super::api::session_span;
 use super::super::{
+    config::{Config, PublisherLoginPolicy},
+    domain::{ClientIp, SessionId},
     error::{srs_forbidden_response, srs_success_response},
-    state::ClientStatus,
+    redact,
+    state::{
+        callback_dedup::CallbackDedup,
+        callback_echo::EchoRecord,
+        events::StreamEvent,
+        secret_guard::secret_eq,
+        metrics::{Metrics, RejectReason},
+        notify::Alert,
+        preview::MAX_PREVIEW_CLIENTS,
+        recordings::Recording,
+        script::{HookPoint, ScriptDecision},
+        srs::{EntryMode, StreamerEvent, StreamerTransition, END_REFUSAL_WINDOW},
+        stream_policy::{self, PublishOptions, MIN_PUSH_SESSION_LEN},
+        ClientStatus,
+    },
 };
 use axum::{
-    extract::State,
-    response::Response,
+    extract::{ConnectInfo, MatchedPath, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
+use secrecy::ExposeSecret;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 
 // ============================================================================
 // 数据结构定义
@@ -35,9 +66,11 @@ use std::sync::Arc;
 /// SRS 回调请求结构
 ///
 /// SRS 在发生事件时会向此服务发送 POST 请求
-#[derive(Debug, Deserialize)]
+///
+/// `Debug` 输出中 IP 被遮蔽，`param`（含推流密钥）被隐藏
+#[derive(Deserialize)]
 pub struct SrsCallbackRequest {
-    /// 回调类型：on_publish, on_play, on_unpublish, on_stop
+    /// 回调类型：on_publish, on_play, on_unpublish, on_stop, on_dvr
     pub action: String,
     /// 客户端 IP 地址
     pub ip: String,
@@ -50,12 +83,49 @@ pub struct SrsCallbackRequest {
     /// TC URL（未使用，保留以兼容 SRS 协议）
     #[serde(rename = "tcUrl", default)]
     pub _tc_url: String,
+    /// 录制文件路径（仅 on_dvr 回调）
+    #[serde(default)]
+    pub file: Option<String>,
+    /// SRS 客户端连接 ID（用于回调去重）
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// SRS 回调地址上的查询参数
+#[derive(Deserialize)]
+pub struct SrsCallbackAuth {
+    /// 回调令牌（SRS 配置的回调地址中携带）
+    token: Option<String>,
+}
+
+impl std::fmt::Debug for SrsCallbackRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SrsCallbackRequest")
+            .field("action", &self.action)
+            .field("ip", &redact::ip(&self.ip))
+            .field("app", &self.app)
+            .field("stream", &self.stream)
+            .field("param", &redact::text(&self.param))
+            .field("file", &self.file)
+            .field("client_id", &self.client_id)
+            .finish()
+    }
 }
 
 // ============================================================================
 // 辅助函数
 // ============================================================================
 
+/// 记录一次回调拒绝并返回拒绝响应
+///
+/// 拒绝原因附加在响应扩展中，供回调回显读取
+fn reject(metrics: &Metrics, action: &'static str, reason: RejectReason) -> Response {
+    metrics.reject_callback(action, reason);
+    let mut response = srs_forbidden_response();
+    response.extensions_mut().insert(reason);
+    response
+}
+
 /// 解析 param 字段中的查询参数
 ///
 /// ### 参数格式
@@ -88,31 +158,187 @@ fn parse_param(param: &str) -> HashMap<String, String> {
 /// SRS 回调主处理器
 ///
 /// ### 路由
-/// `POST /`（端口 8848）
+/// `POST /?token=<回调令牌>`（端口 8848），接受所有类型的回调
 ///
 /// ### 请求格式
 /// SRS 发送 JSON 格式的回调数据
 ///
 /// ### 响应格式
 /// - 成功：HTTP 200 + "0"
-/// - 失败：HTTP 403 + "rua"
+/// - 失败（含来源校验失败）：HTTP 403 + "rua"
+///
+/// ### 调试
+/// - 配置了回显文件时，每个回调的原始 JSON 和处理结果追加写入该文件
+/// - 实验室模式下通过来源校验的回调一律放行，本应拒绝时输出警告
 pub async fn srs_callback_handler(
     State(state): State<Arc<crate::state::AppState>>,
-    Json(payload): Json<SrsCallbackRequest>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(auth): Query<SrsCallbackAuth>,
+    Json(raw): Json<serde_json::Value>,
+) -> Response {
+    process_callback(state, peer, auth, raw, None).await
+}
+
+/// 按回调类型区分路径的 SRS 回调处理器
+///
+/// SRS 可以为每种回调配置不同的地址，按路径区分后可以为不同回调单独挂载中间件（认证、限流等）
+///
+/// ### 路由
+/// `POST /hooks/on_publish`、`/hooks/on_unpublish`、`/hooks/on_play`、`/hooks/on_stop`、`/hooks/on_dvr`
+/// （均可附带 `?token=<回调令牌>`）
+///
+/// ### 响应格式
+/// 同 `srs_callback_handler`；回调内容中的 `action` 与路径不一致时返回 HTTP 422
+pub async fn srs_hook_handler(
+    State(state): State<Arc<crate::state::AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    path: MatchedPath,
+    Query(auth): Query<SrsCallbackAuth>,
+    Json(raw): Json<serde_json::Value>,
+) -> Response {
+    let hook = Metrics::callback_label(path.as_str().trim_start_matches("/hooks/"));
+    process_callback(state, peer, auth, raw, Some(hook)).await
+}
+
+/// 校验并处理一个 SRS 回调
+///
+/// ### 参数
+/// - `hook`: 按路径区分的回调类型（`None` 表示来自接受所有回调的 `/`）
+async fn process_callback(
+    state: Arc<crate::state::AppState>,
+    peer: SocketAddr,
+    auth: SrsCallbackAuth,
+    raw: serde_json::Value,
+    hook: Option<&'static str>,
+) -> Response {
+    let started = Instant::now();
+    let echo = state.callback_echo.clone().map(|echo| (echo, raw.clone()));
+
+    let mut lab_override = false;
+    let response = match serde_json::from_value::<SrsCallbackRequest>(raw) {
+        Err(e) => {
+            tracing::warn!("无法解析的 SRS 回调: {}", e);
+            (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response()
+        }
+        Ok(payload) => {
+            if let Err(reason) = check_callback_source(&state, peer, auth.token.as_deref()) {
+                tracing::warn!(
+                    "拒绝来自 {} 的 SRS 回调 {}: {}",
+                    redact::ip(peer.ip().to_string()),
+                    payload.action,
+                    reason
+                );
+                let action = Metrics::callback_label(&payload.action);
+                reject(&state.metrics, action, RejectReason::Unauthenticated)
+            } else if hook.is_some_and(|hook| hook != payload.action) {
+                tracing::warn!("SRS 回调 {} 发送到了 /hooks/{} 路径", payload.action, hook.unwrap_or_default());
+                (StatusCode::UNPROCESSABLE_ENTITY, "callback action does not match hook path").into_response()
+            } else {
+                let action = payload.action.clone();
+                let response = dispatch_callback(state.clone(), payload).await;
+                if state.config().srs_callback_lab && !response.status().is_success() {
+                    let reason = response
+                        .extensions()
+                        .get::<RejectReason>()
+                        .map_or("unknown", RejectReason::as_str);
+                    tracing::warn!("实验室模式: 放行本应拒绝的 SRS 回调 {}（{}）", action, reason);
+                    lab_override = true;
+                }
+                response
+            }
+        }
+    };
+
+    if let Some((echo, raw)) = echo {
+        echo.record(&EchoRecord {
+            peer: peer.to_string(),
+            payload: &raw,
+            decision: if response.status().is_success() { "allow" } else { "deny" },
+            reason: match response.extensions().get::<RejectReason>() {
+                Some(reason) => Some(reason.as_str()),
+                None if response.status() == StatusCode::UNPROCESSABLE_ENTITY => Some("invalid_payload"),
+                None => None,
+            },
+            lab_override,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        });
+    }
+    if lab_override {
+        srs_success_response()
+    } else {
+        response
+    }
+}
+
+/// 校验回调来源
+///
+/// ### 返回值
+/// - `Ok(())`: 来源地址在允许范围内且令牌正确（未配置的校验项视为通过）
+/// - `Err(reason)`: 拒绝原因（用于日志）
+fn check_callback_source(
+    state: &crate::state::AppState,
+    peer: SocketAddr,
+    token: Option<&str>,
+) -> Result<(), &'static str> {
+    let config = state.config();
+    if !config.srs_callback_allow.is_empty()
+        && !config.srs_callback_allow.iter().any(|range| range.contains(peer.ip()))
+    {
+        return Err("来源地址不在允许范围内");
+    }
+    match &config.srs_callback_token {
+        Some(expected) if !token.is_some_and(|t| secret_eq(t, expected.expose_secret())) => Err("回调令牌错误"),
+        _ => Ok(()),
+    }
+}
+
+/// 按回调类型分发（来源已校验）
+///
+/// 调试接口模拟回调时直接调用
+pub async fn dispatch_callback(
+    state: Arc<crate::state::AppState>,
+    payload: SrsCallbackRequest,
 ) -> Response {
-    tracing::debug!("SRS 回调: action={}, ip={}", payload.action, payload.ip);
+    tracing::debug!("SRS 回调: action={}, ip={}", payload.action, redact::ip(&payload.ip));
+
+    let started = Instant::now();
+    let action = Metrics::callback_label(&payload.action);
+    let metrics = state.metrics.clone();
+
+    // 去重窗口内的重复回调直接返回上次的结果
+    let dedup_key = CallbackDedup::key(action, payload.client_id.as_deref(), payload.file.as_deref());
+    if let Some(allowed) = dedup_key.as_deref().and_then(|key| state.callbacks.seen(key)) {
+        tracing::info!(
+            "忽略重复的 SRS 回调: action={}, client_id={}",
+            payload.action,
+            payload.client_id.as_deref().unwrap_or_default()
+        );
+        return if allowed {
+            srs_success_response()
+        } else {
+            srs_forbidden_response()
+        };
+    }
+    let callbacks = state.callbacks.clone();
 
     // 根据回调类型分发到相应的处理函数
-    match payload.action.as_str() {
+    let response = match payload.action.as_str() {
         "on_publish" => handle_on_publish(state, payload).await,
         "on_play" => handle_on_play(state, payload).await,
         "on_unpublish" => handle_on_unpublish(state, payload).await,
         "on_stop" => handle_on_stop(state, payload).await,
+        "on_dvr" => handle_on_dvr(state, payload).await,
         _ => {
             tracing::warn!("未知的 SRS 回调类型: {}", payload.action);
-            srs_forbidden_response()
+            reject(&metrics, action, RejectReason::UnknownAction)
         }
+    };
+
+    metrics.observe_callback(action, started.elapsed(), !response.status().is_success());
+    if let Some(key) = dedup_key {
+        callbacks.record(key, response.status().is_success());
     }
+    response
 }
 
 // ============================================================================
@@ -126,10 +352,16 @@ pub async fn srs_callback_handler(
 /// ### 验证流程
 /// 1. 从 param 中提取 secret 参数
 /// 2. 如果没有 secret，拒绝
-/// 3. 如果已在推流，尝试恢复（验证 secret）
-/// 4. 如果未推流，验证 secret 并注册新主播
-/// 5. 检查是否为公开模式
-/// 6. 重置聊天室数据库
+/// 3. 如果已在推流，尝试恢复（验证 secret），由暂停恢复时发送恢复提示和 `stream_resumed` 事件
+/// 4. 如果未推流，经准入脚本检查后验证 secret 并注册新主播；同一推流目标因暂停超时结束不久时延续原场次
+/// 5. 按推流参数初始化本场直播（见 `PublishOptions`）：公开模式、观众入场方式（`entry=confirm` 时
+///    观众点击确认即可入场，无需答题）、直播间名称（`name`）
+/// 6. 为新场次重置聊天室（延续原场次或关闭 `LIVE_SERVER_CHAT_AUTO_RESET` 时沿用原聊天室），
+///    并按推流参数关闭观众发言（`chat=off`）或开启慢速模式（`slowmode`）
+/// 7. 推流参数带 `record=true` 时在后台开启录制
+///
+/// 当前直播的转码版本（来自本机 FFmpeg 或携带当前推流密钥）直接放行并记录为可选清晰度；
+/// 诱饵流（`LIVE_SERVER_DECOY_STREAM`）来自本机或携带任一有效推流密钥时直接放行，不影响主播状态
 async fn handle_on_publish(
     state: Arc<crate::state::AppState>,
     payload: SrsCallbackRequest,
@@ -137,110 +369,424 @@ async fn handle_on_publish(
     // 解析查询参数
     let queries = parse_param(&payload.param);
 
+    // 诱饵流由运维推送（本机或携带任一有效推流密钥），不作为主播登记
+    let config = state.config();
+    if config.decoy_stream.as_ref().is_some_and(|d| d.matches(&payload.app, &payload.stream)) {
+        let from_local = payload.ip.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback());
+        let trusted = from_local
+            || queries.get("secret").is_some_and(|s| state.srs_db.access.read().verify_streamer(s));
+        if !trusted {
+            tracing::debug!("SRS 回调拒绝: 诱饵流 {}/{} 来源不可信", payload.app, payload.stream);
+            return reject(&state.metrics, "on_publish", RejectReason::BadSecret);
+        }
+        tracing::info!("诱饵流 {}/{} 开始推流", payload.app, payload.stream);
+        return srs_success_response();
+    }
+
+    // app 须在允许列表内（转码版本同样适用）
+    if let Err(rejection) = stream_policy::check_app(&config, &payload.app) {
+        tracing::warn!("SRS 回调拒绝: 推流 app {:?} {}", payload.app, rejection.describe());
+        return reject(&state.metrics, "on_publish", RejectReason::BadStreamTarget);
+    }
+
+    // 转码版本归属当前直播，不作为新主播处理
+    {
+        let mut streamer = state.srs_db.streamer.write();
+        if let Some(suffix) = streamer.variant_of_current(&payload.app, &payload.stream, &config.stream_variants) {
+            let from_transcoder = payload.ip.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback());
+            let trusted = from_transcoder
+                || queries.get("secret").is_some_and(|s| {
+                    streamer.check_streamer_secret(&state.srs_db.access.read().verifier, s)
+                });
+            if !trusted {
+                tracing::debug!("SRS 回调拒绝: 转码版本 {} 来源不可信", payload.stream);
+                return reject(&state.metrics, "on_publish", RejectReason::BadSecret);
+            }
+            streamer.set_variant_live(suffix, true);
+            tracing::debug!("转码版本 {} 开始推流", payload.stream);
+            return srs_success_response();
+        }
+    }
+
+    // stream 名称须合法并匹配允许的模式
+    if let Err(rejection) = stream_policy::check_stream(&config, &payload.stream) {
+        tracing::warn!("SRS 回调拒绝: 推流 stream {:?} {}", payload.stream, rejection.describe());
+        return reject(&state.metrics, "on_publish", RejectReason::BadStreamTarget);
+    }
+
     // 获取推流密钥
     let secret = match queries.get("secret") {
         Some(s) => s.clone(),
         None => {
             tracing::debug!("SRS 回调拒绝: 未提供密钥");
-            return srs_forbidden_response();
+            return reject(&state.metrics, "on_publish", RejectReason::MissingSecret);
         }
     };
 
+    // 推流端 IP 用于防爆破记录和主播登录校验，须为合法地址
+    let Some(publisher_ip) = ClientIp::parse(&payload.ip) else {
+        tracing::warn!("SRS 回调拒绝: 推流端 IP {:?} 不合法", payload.ip);
+        return reject(&state.metrics, "on_publish", RejectReason::Unauthenticated);
+    };
+
+    // 失败次数过多的 IP 处于锁定期内，直接拒绝
+    if let Some(secs) = state.srs_db.access.read().secret_guard.locked_for(&publisher_ip) {
+        tracing::debug!("SRS 回调拒绝: {} 处于密钥猜测锁定期（剩余 {} 秒）", redact::ip(&publisher_ip), secs);
+        return reject(&state.metrics, "on_publish", RejectReason::SecretLocked);
+    }
+
     // 检查是否已在推流
-    let is_streaming = state.srs_db.inner.read().is_streaming();
+    let is_streaming = state.srs_db.is_streaming();
 
     if is_streaming {
         // 已在推流，尝试恢复（可能是网络问题导致的重新推流）
-        let mut srs_db = state.srs_db.inner.write();
-
-        if srs_db.resume_streaming(payload.ip.clone(), &secret, payload.app.clone(), payload.stream.clone()) {
-            tracing::debug!("推流者 ({}) 恢复推流", payload.ip);
-            srs_success_response()
-        } else {
-            tracing::debug!("SRS 回调拒绝: 已有其他推流者在推流");
-            srs_forbidden_response()
+        let mut streamer = state.srs_db.streamer.write();
+        let mut clients = state.srs_db.clients.write();
+
+        let resume = StreamerEvent::Resume {
+            ip: publisher_ip.clone(),
+            secret,
+            app: payload.app.clone(),
+            stream: payload.stream.clone(),
+        };
+        match streamer.apply(&mut clients, resume) {
+            StreamerTransition::Applied { event, .. } => {
+                drop(clients);
+                streamer.set_publisher_client_id(payload.client_id.clone());
+                tracing::debug!("推流者 ({}) 恢复推流", redact::ip(&publisher_ip));
+                if let Some(event) = event {
+                    state.chat_db.inner.write().active_mut().add_system("直播已恢复", false);
+                    state.events.publish(event);
+                }
+                srs_success_response()
+            }
+            StreamerTransition::Ignored | StreamerTransition::Rejected => {
+                drop(clients);
+                state.srs_db.access.write().secret_guard.record_failure(&publisher_ip, "on_publish");
+                tracing::debug!("SRS 回调拒绝: 已有其他推流者在推流");
+                reject(&state.metrics, "on_publish", RejectReason::BadSecret)
+            }
         }
     } else {
-        // 新推流
-        let mut srs_db = state.srs_db.inner.write();
+        // 新推流：准入脚本可以拒绝推流
+        let decision = state.script_decide(
+            HookPoint::Publish,
+            &[("ip", publisher_ip.as_str()), ("app", &payload.app), ("stream", &payload.stream)],
+        );
+        if decision == ScriptDecision::Deny {
+            tracing::debug!("SRS 回调拒绝: 准入脚本拒绝推流");
+            return reject(&state.metrics, "on_publish", RejectReason::ScriptDenied);
+        }
+
+        let mut streamer = state.srs_db.streamer.write();
 
-        // 验证密钥
-        if srs_db.verify_streamer(&secret) {
-            // 注册新主播
-            srs_db.register_streamer(payload.ip.clone(), secret, payload.app.clone(), payload.stream.clone());
+        // 主播刚手动结束直播：编码器被踢出后的自动重连不开始新场次
+        if streamer.refuses_republish(&secret, &payload.app, &payload.stream) {
+            tracing::info!("SRS 回调拒绝: 直播刚被主播结束，拒绝推流端重连（{} 秒内）", END_REFUSAL_WINDOW.as_secs());
+            return reject(&state.metrics, "on_publish", RejectReason::RecentlyEnded);
+        }
+
+        let mut clients = state.srs_db.clients.write();
+
+        // 并发到达的重复回调已注册了主播：按恢复处理，不再重复打开聊天室
+        if streamer.is_streaming() {
+            let resume = StreamerEvent::Resume {
+                ip: publisher_ip.clone(),
+                secret,
+                app: payload.app.clone(),
+                stream: payload.stream.clone(),
+            };
+            return if let StreamerTransition::Applied { .. } = streamer.apply(&mut clients, resume) {
+                streamer.set_publisher_client_id(payload.client_id.clone());
+                tracing::debug!("推流者 ({}) 的重复推流回调，已按恢复处理", redact::ip(&publisher_ip));
+                srs_success_response()
+            } else {
+                reject(&state.metrics, "on_publish", RejectReason::BadSecret)
+            };
+        }
+
+        // 推流参数中的本场直播设置
+        let options = PublishOptions::parse(&queries);
+
+        // 验证密钥（锁顺序：streamer → clients → access）
+        let mut access = state.srs_db.access.write();
+        if access.verify_streamer(&secret) {
+            access.secret_guard.record_success(&publisher_ip);
+            // 注册新主播，开播前排队的观众随之转为已授权
+            let publish = StreamerEvent::Publish {
+                ip: publisher_ip.clone(),
+                secret,
+                app: payload.app.clone(),
+                stream: payload.stream.clone(),
+                push_session: push_session(&config, &options),
+            };
+            let StreamerTransition::Applied { event: live_event, continued } = streamer.apply(&mut clients, publish) else {
+                unreachable!("未推流时 Publish 总是生效");
+            };
+            drop(clients);
+            streamer.set_publisher_client_id(payload.client_id.clone());
 
             // 检查是否为公开模式
-            if let Some(public_val) = queries.get("public") {
-                if public_val.to_lowercase() == "true" {
-                    srs_db.set_public(true);
-                    tracing::debug!("推流者 ({}) 开始公开模式推流", payload.ip);
-                } else {
-                    srs_db.set_public(false);
-                    tracing::debug!("推流者 ({}) 开始推流", payload.ip);
-                }
+            if let Some(public) = options.public {
+                access.set_public(public);
+            }
+            if options.public == Some(true) {
+                tracing::debug!("推流者 ({}) 开始公开模式推流", redact::ip(&publisher_ip));
+            } else {
+                tracing::debug!("推流者 ({}) 开始推流", redact::ip(&publisher_ip));
+            }
+
+            // 观众入场方式（默认答题），新直播总是开放入场
+            access.set_entry_mode(options.entry);
+            access.set_entries_closed(false);
+            drop(access);
+            if options.entry != EntryMode::Quiz {
+                tracing::debug!("本场直播的观众入场方式: {}", options.entry.as_str());
+            }
+
+            if let Some(name) = options.name.clone() {
+                streamer.set_stream_name(name);
+            }
+
+            // 为本场直播打开独立的聊天室（延续上一场或关闭了自动清空时沿用原聊天室）
+            let stream_id = format!("{}/{}", payload.app, payload.stream);
+            let session_id = streamer.get_stream_session_id().map(str::to_string);
+            if continued {
+                tracing::info!("直播场次 {} 继续（{}）", session_id.as_deref().unwrap_or("-"), stream_id);
             } else {
-                tracing::debug!("推流者 ({}) 开始推流", payload.ip);
+                tracing::info!("直播场次 {} 开始（{}）", session_id.as_deref().unwrap_or("-"), stream_id);
+            }
+            state.metrics.set_stream_session(session_id.clone());
+            {
+                let mut chat_rooms = state.chat_db.inner.write();
+                chat_rooms.open_room(&stream_id, session_id, continued || !config.chat_auto_reset);
+                let room = chat_rooms.active_mut();
+                room.chat_closed = options.chat_closed;
+                if let Some(secs) = options.slow_mode_secs {
+                    room.slow_mode_secs = secs;
+                }
+            }
+            if options.chat_closed || options.slow_mode_secs.is_some() {
+                tracing::debug!(
+                    "本场直播的聊天设置: 观众发言{}，慢速模式 {} 秒",
+                    if options.chat_closed { "关闭" } else { "开启" },
+                    options.slow_mode_secs.unwrap_or(0)
+                );
+            }
+
+            // 通知订阅者开播
+            if let Some(StreamEvent::StreamLive { activated }) = live_event {
+                if activated > 0 {
+                    tracing::info!("开播前排队的 {} 名观众已放行", activated);
+                }
             }
+            if let Some(event) = live_event {
+                state.events.publish(event);
+            }
+            state.notify(Alert::StreamLive, format!("直播 {} 已开始推流。", stream_id));
 
-            // 重置聊天室数据库
-            state.chat_db.inner.write().reset();
+            // 开播即录制：回调返回后再通过 SRS API 开启，避免阻塞推流
+            if options.record {
+                start_recording(state.clone(), payload.app.clone(), payload.stream.clone());
+            }
 
             srs_success_response()
         } else {
+            access.secret_guard.record_failure(&publisher_ip, "on_publish");
             tracing::debug!("SRS 回调拒绝: 无效的推流密钥");
-            srs_forbidden_response()
+            reject(&state.metrics, "on_publish", RejectReason::BadSecret)
         }
     }
 }
 
+/// 推流参数绑定的主播会话
+///
+/// 仅在不要求额外验证的登录策略（`open` / `single_session`）下生效，
+/// 且会话 ID 须不少于 `MIN_PUSH_SESSION_LEN` 个字符，否则忽略并记录警告
+fn push_session(config: &Config, options: &PublishOptions) -> Option<SessionId> {
+    let session_id = options.session_id.as_ref()?;
+    let policy_allows = matches!(
+        config.publisher_login_policy,
+        PublisherLoginPolicy::Open | PublisherLoginPolicy::SingleSession
+    );
+    if !policy_allows {
+        tracing::warn!("推流参数中的 session_id 被忽略: 当前登录策略要求在网页端验证主播身份");
+        None
+    } else if session_id.as_str().len() < MIN_PUSH_SESSION_LEN {
+        tracing::warn!("推流参数中的 session_id 被忽略: 须不少于 {} 个字符", MIN_PUSH_SESSION_LEN);
+        None
+    } else {
+        tracing::debug!("推流参数绑定主播会话 session_id={}", session_id);
+        Some(session_id.clone())
+    }
+}
+
+/// 在后台开启录制（推流参数 `record=true`）
+///
+/// SRS 确认后记录录制状态；开启失败只记录警告，不影响推流
+fn start_recording(state: Arc<crate::state::AppState>, app: String, stream: String) {
+    tokio::spawn(async move {
+        let vhost = state.config().srs_vhost.clone();
+        match state.srs_api.set_dvr(&vhost, &app, &stream, true).await {
+            Ok(()) => {
+                let mut streamer = state.srs_db.streamer.write();
+                // 期间直播已结束或换了推流目标时不再记录
+                if streamer.get_stream_target() == Some((app.as_str(), stream.as_str())) {
+                    streamer.set_recording(true);
+                    tracing::info!("已按推流参数开启录制 {}/{}", app, stream);
+                }
+            }
+            Err(e) => tracing::warn!("按推流参数开启录制失败: {}", e),
+        }
+    });
+}
+
 /// 处理 on_play 回调
 ///
 /// 当观众开始拉流时触发。
 ///
 /// ### 验证流程
-/// 1. 从 param 中提取 session_id 参数（向后兼容 rid）
-/// 2. 检查客户端是否已注册
+/// 1. 从 param 中提取 session_id 参数（向后兼容 rid），携带转推拉流令牌时直接放行；
+///    携带当前直播场次的主播预览令牌（`preview`）时直接放行（同时预览的播放端有上限），并记录该播放端不计入观众人数；
+///    拉取诱饵流时只检查会话是否处于封禁状态（答错题或推流密钥错误时领到诱饵流地址），不改变观众状态
+/// 2. 检查客户端是否已注册，未注册但携带有效续连令牌（`resume`）时重建已授权的观众记录
 /// 3. 检查客户端状态是否允许拉流
-/// 4. 更新客户端状态为 Playing
+/// 4. 上行带宽已达上限时，拒绝尚未观看过本场直播、且不属于优先放行类别的新观众
+/// 5. 同时观看人数已满时，观众进入等候室并拒绝本次拉流
+/// 6. 更新客户端状态为 Playing，记录该连接的 SRS client_id 和拉流次数（同一会话再次拉流计为重连）
+/// 7. 启用进出提示时，为首次开始观看的观众发送进入提示
 async fn handle_on_play(
     state: Arc<crate::state::AppState>,
     payload: SrsCallbackRequest,
 ) -> Response {
     // 解析查询参数（优先使用 session_id，向后兼容 rid）
     let queries = parse_param(&payload.param);
-    let session_id = queries
+
+    // 转推使用的 FFmpeg 拉流
+    if queries.get("relay").is_some_and(|token| state.relays.is_pull_token(token)) {
+        tracing::debug!("转推拉流 {}/{}", payload.app, payload.stream);
+        return srs_success_response();
+    }
+
+    // 主播监看自己的直播：不占用观看名额，播放端不计入观众人数
+    if let Some(token) = queries.get("preview") {
+        let snapshot = state.srs_db.snapshot();
+        let on_target = snapshot
+            .target
+            .as_ref()
+            .is_some_and(|(app, stream)| *app == payload.app && *stream == payload.stream);
+        let stream_session = snapshot
+            .session_id
+            .as_deref()
+            .filter(|s| on_target && state.preview.verify(token, s));
+        let (Some(stream_session), Some(client_id)) = (stream_session, payload.client_id.filter(|id| !id.is_empty()))
+        else {
+            tracing::debug!("SRS 回调拒绝: 预览令牌无效 {}/{}", payload.app, payload.stream);
+            return reject(&state.metrics, "on_play", RejectReason::NotAuthorized);
+        };
+        if !state.preview.add_client(stream_session, client_id) {
+            tracing::warn!("SRS 回调拒绝: 同时预览的播放端已达上限 {}", MAX_PREVIEW_CLIENTS);
+            return reject(&state.metrics, "on_play", RejectReason::ViewerCap);
+        }
+        tracing::debug!("主播开始预览 {}/{}", payload.app, payload.stream);
+        return srs_success_response();
+    }
+
+    let Some(session_id) = queries
         .get("session_id")
         .or_else(|| queries.get("rid"))
-        .cloned()
-        .unwrap_or_default();
+        .and_then(|s| SessionId::parse(s).ok())
+    else {
+        tracing::debug!("SRS 回调拒绝: 未携带合法的 session_id");
+        return reject(&state.metrics, "on_play", RejectReason::UnknownSession);
+    };
+    let _span = session_span(&state, "on_play", None, &session_id).entered();
 
-    let srs_db = state.srs_db.inner.read();
+    // 诱饵流：只放行被封禁的观众（领到诱饵流地址的会话），不计入观看状态
+    if state.config().decoy_stream.as_ref().is_some_and(|d| d.matches(&payload.app, &payload.stream)) {
+        let status = state.srs_db.clients.read().get_client_status_any_ip(&session_id);
+        return if matches!(status, Some((_, ClientStatus::Nil))) {
+            tracing::debug!("被封禁的观众开始观看诱饵流 session_id={}", session_id);
+            srs_success_response()
+        } else {
+            tracing::debug!("SRS 回调拒绝: 诱饵流只对被封禁的观众开放 session_id={}", session_id);
+            reject(&state.metrics, "on_play", RejectReason::NotAuthorized)
+        };
+    }
 
     // 检查客户端是否已注册（只检查 session_id，因为 SRS 回调的 IP 是 Docker 内部 IP）
-    let client_status = srs_db.get_client_status_any_ip(&session_id);
+    let client_status = state.srs_db.clients.read().get_client_status_any_ip(&session_id);
+
+    // 未注册时尝试凭续连令牌重建刚过期的观众记录（播放器断线重连）
+    let client_status = client_status.or_else(|| {
+        state.resume_client(&session_id, queries.get("resume")?)?;
+        state.srs_db.clients.read().get_client_status_any_ip(&session_id)
+    });
 
     let (client_ip, client_status) = match client_status {
         Some((ip, status)) => (ip, status),
         None => {
             tracing::debug!("SRS 回调拒绝: 客户端未注册 session_id={}", session_id);
-            return srs_forbidden_response();
+            return reject(&state.metrics, "on_play", RejectReason::UnknownSession);
         }
     };
 
-    drop(srs_db);
-
     match client_status {
-        ClientStatus::Pending | ClientStatus::Nil => {
-            // 待答题或被封禁，不允许拉流
+        ClientStatus::Pending | ClientStatus::Nil | ClientStatus::Ended | ClientStatus::Waiting => {
+            // 待答题、被封禁、直播已结束或尚在开播前排队，不允许拉流
             tracing::debug!("SRS 回调拒绝: 客户端未获得许可 session_id={}", session_id);
-            return srs_forbidden_response();
+            return reject(&state.metrics, "on_play", RejectReason::NotAuthorized);
         }
         _ => {}
     }
 
+    // 带宽已满：只保留已在观看（含暂离后重连）的观众和优先放行的观众
+    let config = state.config();
+    let bandwidth_full = !matches!(client_status, ClientStatus::Playing | ClientStatus::Resting)
+        && state.streaming_info.inner.read().bandwidth_exceeded(config.bandwidth_ceiling_kbps);
+    let is_variant = state
+        .srs_db
+        .streamer
+        .read()
+        .variant_of_current(&payload.app, &payload.stream, &config.stream_variants)
+        .is_some();
+    let mut clients = state.srs_db.clients.write();
+    if bandwidth_full && !clients.client_has_priority(&client_ip, &session_id, &config.admission_priority) {
+        tracing::debug!("SRS 回调拒绝: 上行带宽已达上限 session_id={}", session_id);
+        return reject(&state.metrics, "on_play", RejectReason::BandwidthCeiling);
+    }
+
+    // 人数已满：进入等候室，轮到时再放行
+    if let Err(position) =
+        clients.admit_viewer(&client_ip, &session_id, config.max_viewers, &config.admission_priority)
+    {
+        tracing::debug!("SRS 回调拒绝: 观看人数已满 session_id={}，等候位置 {}", session_id, position);
+        return reject(&state.metrics, "on_play", RejectReason::ViewerCap);
+    }
+
     // 更新客户端状态为 Playing
-    let mut srs_db = state.srs_db.inner.write();
-    srs_db.update_client_activity(&client_ip, &session_id, ClientStatus::Playing);
+    if is_variant {
+        tracing::debug!("session_id={} 观看转码版本 {}", session_id, payload.stream);
+    }
+    if !clients.transition(&client_ip, &session_id, ClientStatus::Playing) {
+        return reject(&state.metrics, "on_play", RejectReason::NotAuthorized);
+    }
+    if let Some(client_id) = payload.client_id.filter(|id| !id.is_empty()) {
+        clients.add_srs_client(&client_ip, &session_id, client_id);
+    }
+    let reconnect = clients.record_play(&client_ip, &session_id);
+    drop(clients);
+    state.metrics.record_play(reconnect);
+
+    // 首次开始观看时发送进入提示
+    if config.chat_presence_notices && client_status != ClientStatus::Playing {
+        state
+            .chat_db
+            .inner
+            .write()
+            .active_mut()
+            .announce_presence(&client_ip, &session_id, true);
+    }
 
     srs_success_response()
 }
@@ -250,14 +796,35 @@ async fn handle_on_play(
 /// 当主播停止推流时触发。
 ///
 /// ### 处理流程
-/// 将主播状态设置为 Pausing（暂停），允许一段时间内恢复
+/// 将主播状态设置为 Pausing（暂停），允许一段时间内恢复，
+/// 并在聊天室中发送暂停提示、推送 `stream_paused` 事件。
+/// 转码版本停止时只将其从可选清晰度中移除，诱饵流停止时不做处理
 async fn handle_on_unpublish(
     state: Arc<crate::state::AppState>,
     payload: SrsCallbackRequest,
 ) -> Response {
-    let mut srs_db = state.srs_db.inner.write();
-    srs_db.pause_streaming();
-    tracing::debug!("推流者 ({}) 停止推流", payload.ip);
+    let config = state.config();
+    if config.decoy_stream.as_ref().is_some_and(|d| d.matches(&payload.app, &payload.stream)) {
+        tracing::info!("诱饵流 {}/{} 停止推流", payload.app, payload.stream);
+        return srs_success_response();
+    }
+    let mut streamer = state.srs_db.streamer.write();
+    if let Some(suffix) = streamer.variant_of_current(&payload.app, &payload.stream, &config.stream_variants) {
+        streamer.set_variant_live(suffix, false);
+        tracing::debug!("转码版本 {} 停止推流", payload.stream);
+        return srs_success_response();
+    }
+    let transition = streamer.apply(&mut state.srs_db.clients.write(), StreamerEvent::Unpublish);
+    if let StreamerTransition::Applied { event: Some(event), .. } = transition {
+        state
+            .chat_db
+            .inner
+            .write()
+            .active_mut()
+            .add_system("直播暂停 — 正在等待推流端重新连接", false);
+        state.events.publish(event);
+    }
+    tracing::debug!("推流者 ({}) 停止推流", redact::ip(&payload.ip));
     srs_success_response()
 }
 
@@ -266,20 +833,85 @@ async fn handle_on_unpublish(
 /// 当观众停止拉流时触发。
 ///
 /// ### 处理流程
-/// 将客户端状态更新为 Resting（暂离）
+/// 记录停止次数，移除该连接的 SRS client_id，观众没有其他拉流连接时将状态更新为 Resting（暂离）；
+/// 主播的预览播放端只移除登记
 async fn handle_on_stop(
     state: Arc<crate::state::AppState>,
     payload: SrsCallbackRequest,
 ) -> Response {
     // 解析查询参数（优先使用 session_id，向后兼容 rid）
     let queries = parse_param(&payload.param);
-    let session_id = queries.get("session_id").or_else(|| queries.get("rid")).cloned();
+    let session_id = queries
+        .get("session_id")
+        .or_else(|| queries.get("rid"))
+        .and_then(|s| SessionId::parse(s).ok());
+    let _span = session_id
+        .as_ref()
+        .map(|session_id| session_span(&state, "on_stop", None, session_id).entered());
+
+    // 诱饵流的观看不计入观众状态
+    if state.config().decoy_stream.as_ref().is_some_and(|d| d.matches(&payload.app, &payload.stream)) {
+        return srs_success_response();
+    }
+
+    // 主播的预览播放端不对应观众记录
+    if payload.client_id.as_deref().is_some_and(|id| state.preview.remove_client(id)) {
+        return srs_success_response();
+    }
+
+    let mut clients = state.srs_db.clients.write();
+
+    // 如果客户端存在，更新状态为 Resting（通过 session 索引查找，回调中的 IP 不可靠）
+    if let Some(session_id) = session_id {
+        if let Some(client_ip) = clients.find_client_ip(&session_id).cloned() {
+            clients.record_stop(&client_ip, &session_id);
+            state.metrics.record_stop();
+            let remaining = match payload.client_id.as_deref() {
+                Some(client_id) => clients.remove_srs_client(&client_ip, &session_id, client_id),
+                None => 0,
+            };
+            if remaining == 0 {
+                clients.transition(&client_ip, &session_id, ClientStatus::Resting);
+            }
+        }
+    }
+
+    srs_success_response()
+}
 
-    let mut srs_db = state.srs_db.inner.write();
+/// 处理 on_dvr 回调
+///
+/// 当 SRS 完成一个录制文件时触发。
+///
+/// ### 处理流程
+/// 将文件登记到录制文件登记表，录制的是当前直播时关联到当前直播场次
+async fn handle_on_dvr(
+    state: Arc<crate::state::AppState>,
+    payload: SrsCallbackRequest,
+) -> Response {
+    let Some(file) = payload.file else {
+        tracing::warn!("on_dvr 回调缺少 file 字段");
+        return srs_success_response();
+    };
 
-    // 如果客户端存在，更新状态为 Resting
-    if session_id.is_some() && srs_db.has_client(&payload.ip, session_id.as_deref().unwrap_or("")) {
-        srs_db.update_client_activity(&payload.ip, session_id.as_deref().unwrap_or(""), ClientStatus::Resting);
+    let stream_session_id = {
+        let streamer = state.srs_db.streamer.read();
+        let is_current = streamer.get_stream_target() == Some((payload.app.as_str(), payload.stream.as_str()));
+        is_current
+            .then(|| streamer.get_stream_session_id().map(str::to_string))
+            .flatten()
+    };
+    let recorded = state.recordings.record(Recording {
+        file: file.clone(),
+        app: payload.app,
+        stream: payload.stream,
+        stream_session_id: stream_session_id.clone(),
+        created_at: chrono::Utc::now(),
+    });
+    if recorded {
+        tracing::info!("录制文件完成: {}（场次 {}）", file, stream_session_id.as_deref().unwrap_or("-"));
+    } else {
+        tracing::debug!("录制文件 {} 已登记，忽略重复回调", file);
     }
 
     srs_success_response()
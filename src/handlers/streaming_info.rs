@@ -1,40 +1,135 @@
-use axum::{Json, response::Response};
-use crate::state::AppState;
+//! # 流信息处理器模块
+//!
+//! 向观众端提供当前直播的状态、开播时长、推流码率和观众人数，供轻量的状态组件单独轮询，
+//! 人数和码率来自 `state::streaming_info` 后台轮询 SRS API 的结果。
+
+use super::api::StreamStatus;
+use crate::config::AudienceVisibility;
+use crate::state::{srs_api::ViewerBreakdown, streaming_info::AudienceCount, AppState};
 use axum::{
     extract::State,
-    response::{IntoResponse},
+    response::{IntoResponse, Json, Response},
 };
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use std::sync::Arc;
 
+/// 流信息响应
 #[derive(Serialize)]
-struct StreamingInfoReasponse {
-    /// 观众数
-    audiences_num: i32,
+struct StreamingInfoResponse {
+    /// 直播状态（live / paused / ended）
+    status: &'static str,
+    /// 本场直播已推流的秒数（未推流时不返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uptime_secs: Option<i64>,
+    /// 推流端近 30 秒的上传码率（kbps，未推流或数据过期时不返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitrate_kbps: Option<u64>,
+    /// 观众数（按可见性配置展示，隐藏时不返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audiences_num: Option<AudienceCount>,
+    /// 按播放协议分类的观众数（仅精确展示人数时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocols: Option<ViewerBreakdown>,
+    /// 人数和码率是否过期（SRS API 暂时不可用，返回的是上次获取的值）
+    stale: bool,
+    /// 人数最近一次成功更新的时间（Unix 时间戳，秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at: Option<i64>,
+    /// 建议的下一次轮询间隔（毫秒）
+    poll_interval_ms: u64,
 }
 
-impl StreamingInfoReasponse {
-    pub fn new() -> Self {
+impl StreamingInfoResponse {
+    /// 创建空响应
+    fn new() -> Self {
         Self {
-            audiences_num: 0,
+            status: StreamStatus::Ended.as_str(),
+            uptime_secs: None,
+            bitrate_kbps: None,
+            audiences_num: None,
+            protocols: None,
+            stale: false,
+            updated_at: None,
+            poll_interval_ms: 0,
         }
     }
 
-    pub fn with_stream_name(mut self, num: i32) -> Self {
-        self.audiences_num = num;
+    /// 设置直播状态和开播时间（链式调用）
+    fn with_status(mut self, status: StreamStatus, live_since: Option<DateTime<Utc>>) -> Self {
+        self.status = status.as_str();
+        self.uptime_secs = live_since.map(|t| (Utc::now() - t).num_seconds().max(0));
+        self
+    }
+
+    /// 设置推流码率（链式调用）
+    fn with_bitrate(mut self, kbps: Option<u64>) -> Self {
+        self.bitrate_kbps = kbps;
+        self
+    }
+
+    /// 按可见性设置观众数（链式调用）
+    fn with_audiences_num(mut self, num: i32, visibility: AudienceVisibility) -> Self {
+        self.audiences_num = AudienceCount::present(num as i64, visibility);
         self
     }
-}
 
-pub async fn streaming_info_handler(
-    State(state): State<Arc<AppState>>
-) -> Response {
-    let response = StreamingInfoReasponse::new();
+    /// 设置按协议分类的观众数，仅精确展示人数时生效（链式调用）
+    fn with_protocols(mut self, protocols: ViewerBreakdown, visibility: AudienceVisibility) -> Self {
+        self.protocols = (visibility == AudienceVisibility::Exact).then_some(protocols);
+        self
+    }
 
-    let streaming_info = state.streaming_info.clone();
-    let streaming_info_guard = streaming_info.inner.read();
-    let response = response.with_stream_name(streaming_info_guard.get_audiences_num());
+    /// 设置人数的新鲜度信息（链式调用）
+    fn with_freshness(mut self, stale: bool, updated_at: Option<DateTime<Utc>>) -> Self {
+        self.stale = stale;
+        self.updated_at = updated_at.map(|t| t.timestamp());
+        self
+    }
 
-    return Json(response).into_response();
+    /// 设置建议的轮询间隔（链式调用）
+    fn with_poll_interval(mut self, ms: u64) -> Self {
+        self.poll_interval_ms = ms;
+        self
+    }
 }
 
+/// 流信息处理器
+///
+/// ### 路由
+/// `GET /streaming_info`、`GET /api/streaminfo`
+///
+/// ### 响应格式
+/// ```json
+/// {
+///   "status": "live",
+///   "uptime_secs": 3600,
+///   "bitrate_kbps": 2500,
+///   "audiences_num": 12,
+///   "protocols": {"http_flv": 8, "hls": 3, "webrtc": 1, "rtmp": 0, "other": 0},
+///   "stale": false,
+///   "updated_at": 1700000000,
+///   "poll_interval_ms": 1000
+/// }
+/// ```
+pub async fn streaming_info_handler(State(state): State<Arc<AppState>>) -> Response {
+    let live_since = {
+        let streamer = state.srs_db.streamer.read();
+        streamer.live_since().filter(|_| streamer.is_streaming())
+    };
+    let snapshot = state.srs_db.snapshot();
+    let status = StreamStatus::of_stream(&snapshot);
+    let visibility = state.config().audience_visibility;
+    let info = state.streaming_info.inner.read();
+    let bitrate = info.ingest_kbps.filter(|_| status != StreamStatus::Ended && !info.stale);
+    // 该接口无会话信息，始终按非主播可见性展示
+    let response = StreamingInfoResponse::new()
+        .with_poll_interval(state.suggest_poll_interval(snapshot.is_paused()))
+        .with_status(status, live_since)
+        .with_bitrate(bitrate)
+        .with_audiences_num(info.get_audiences_num(), visibility)
+        .with_protocols(info.protocols, visibility)
+        .with_freshness(info.stale, info.updated_at);
+
+    Json(response).into_response()
+}
@@ -0,0 +1,108 @@
+//! # 标识符生成模块
+//!
+//! 统一生成服务内使用的各类标识符：
+//! - `ulid` - 直播场次 ID、消息 ID 等需要全局唯一且按时间排序的标识
+//! - `short_code` - 配对码、邀请码等需要人工输入的短码
+//! - `UidAllocator` - 聊天室内的用户 UID 分配
+//!
+//! 所有文本标识均使用 Crockford Base32 字符集，不含易混淆的 I/L/O/U。
+
+use crate::domain::Uid;
+use parking_lot::Mutex;
+use rand::Rng;
+
+/// Crockford Base32 字符集
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// 人工输入短码的字符集（在 Crockford 基础上去除易与字母混淆的 0/1）
+const SHORT_CODE_CHARSET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// ULID 长度（字符数）
+pub const ULID_LEN: usize = 26;
+
+/// ULID 随机部分的掩码（80 位）
+const ULID_RANDOM_MASK: u128 = (1 << 80) - 1;
+
+/// 彩蛋 UID 起始值
+pub const EASTER_EGG_UID_OFFSET: u32 = 114514;
+
+/// 上一次生成的 ULID（毫秒时间戳, 随机部分），用于保证进程内单调递增
+static LAST_ULID: Mutex<(u64, u128)> = Mutex::new((0, 0));
+
+/// 生成 ULID（48 位毫秒时间戳 + 80 位随机数，26 个字符）
+///
+/// 同一毫秒内生成的 ULID 在上一个的随机部分上递增，
+/// 因此进程内生成的 ULID 按字典序严格递增
+pub fn ulid() -> String {
+    let now = chrono::Utc::now().timestamp_millis().max(0) as u64 & ((1 << 48) - 1);
+    let (millis, random) = {
+        let mut last = LAST_ULID.lock();
+        if now <= last.0 {
+            // 同一毫秒内（或系统时钟回拨）：沿用上次时间戳，随机部分加一
+            last.1 = (last.1 + 1) & ULID_RANDOM_MASK;
+        } else {
+            *last = (now, rand::thread_rng().gen::<u128>() & ULID_RANDOM_MASK);
+        }
+        *last
+    };
+    encode_ulid(((millis as u128) << 80) | random)
+}
+
+/// 将 128 位数值编码为 ULID 文本
+fn encode_ulid(value: u128) -> String {
+    (0..ULID_LEN)
+        .rev()
+        .map(|i| CROCKFORD[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// 生成指定长度的随机短码
+///
+/// ### 参数
+/// - `len`: 短码长度
+pub fn short_code(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| SHORT_CODE_CHARSET[rng.gen_range(0..SHORT_CODE_CHARSET.len())] as char)
+        .collect()
+}
+
+/// 聊天室 UID 分配器
+///
+/// UID 在房间内从起始值开始顺序分配。起始值默认为 1，
+/// 启用彩蛋时为 `EASTER_EGG_UID_OFFSET`
+#[derive(Debug, Clone)]
+pub struct UidAllocator {
+    /// 起始值
+    offset: u32,
+    /// 下一个可用的 UID
+    next: u32,
+}
+
+impl UidAllocator {
+    /// 创建新的分配器
+    ///
+    /// ### 参数
+    /// - `easter_egg`: 是否从彩蛋值开始分配
+    pub fn new(easter_egg: bool) -> Self {
+        let offset = if easter_egg { EASTER_EGG_UID_OFFSET } else { 1 };
+        Self { offset, next: offset }
+    }
+
+    /// 分配下一个 UID
+    pub fn allocate(&mut self) -> Uid {
+        let uid = self.next;
+        self.next += 1;
+        Uid(uid)
+    }
+
+    /// 保留一个已被占用的 UID，之后只分配更大的值
+    pub fn reserve(&mut self, uid: Uid) {
+        self.next = self.next.max(uid.0.saturating_add(1));
+    }
+
+    /// 重置为起始值
+    pub fn reset(&mut self) {
+        self.next = self.offset;
+    }
+}
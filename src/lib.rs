@@ -0,0 +1,36 @@
+//! # Rusty Live Server
+//!
+//! 高性能直播互动服务器库，供主程序及辅助工具共享。
+//!
+//! ## 模块
+//! - `config` - 配置加载
+//! - `domain` - 客户端 IP、会话 ID、UID 等领域类型
+//! - `dump_merge` - 聊天转储与崩溃恢复日志合并
+//! - `dump_schema` - 聊天转储格式版本、读取结构体与格式升级
+//! - `error` - 错误类型与响应辅助函数
+//! - `handlers` - HTTP 请求处理器
+//! - `ids` - 标识符生成
+//! - `logging` - 日志初始化与运行时调整日志级别
+//! - `redact` - 日志脱敏
+//! - `respond` - 响应格式协商
+//! - `router` - HTTP 路由
+//! - `selfcheck` - 端到端自检
+//! - `state` - 应用状态
+//! - `upgrade` - 零停机升级（监听套接字与运行状态交接）
+
+pub mod config;
+pub mod domain;
+pub mod dump_merge;
+pub mod dump_schema;
+pub mod error;
+pub mod handlers;
+pub mod ids;
+pub mod logging;
+pub mod redact;
+pub mod respond;
+pub mod router;
+pub mod selfcheck;
+pub mod state;
+pub mod upgrade;
+
+pub use state::AppState;
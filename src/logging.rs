@@ -0,0 +1,165 @@
+//! # 日志初始化模块
+//!
+//! 初始化全局日志订阅器，并允许在运行时调整日志级别（热重载配置时使用）。
+//! 日志级别使用 `EnvFilter` 语法，如 `info`、`debug`、`info,audit=warn`。
+//!
+//! 警告及以上级别的日志和 `audit` 目标的审计日志同时写入内存中的实时日志缓冲，
+//! 供 `GET /admin/events` 以 SSE 推送（受日志级别过滤，被过滤掉的日志不会进入缓冲）。
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+use tracing::{field::Field, Event, Level, Subscriber};
+use tracing_subscriber::{
+    fmt, layer::Context, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer, Registry,
+};
+
+/// 默认日志级别
+pub const DEFAULT_LEVEL: &str = "debug";
+
+/// 安全审计日志的目标名称
+pub const AUDIT_TARGET: &str = "audit";
+
+/// 实时日志缓冲容量（条），新订阅者先收到缓冲中的日志
+pub const TAIL_CAPACITY: usize = 500;
+
+/// 日志级别的热更新句柄
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// 初始化全局日志订阅器（启动时调用一次）
+pub fn init() {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(DEFAULT_LEVEL));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_target(false))
+        .with(TailLayer)
+        .init();
+    let _ = FILTER_HANDLE.set(handle);
+}
+
+/// 调整日志级别
+///
+/// ### 参数
+/// - `level`: `EnvFilter` 语法的日志级别
+///
+/// ### 返回值
+/// - `Ok(())`: 已生效
+/// - `Err(msg)`: 级别格式错误或日志尚未初始化
+pub fn set_level(level: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(level).map_err(|e| format!("无效的日志级别 {}: {}", level, e))?;
+    FILTER_HANDLE
+        .get()
+        .ok_or_else(|| "日志尚未初始化".to_string())?
+        .reload(filter)
+        .map_err(|e| format!("调整日志级别失败: {}", e))
+}
+
+// ============================================================================
+// 实时日志
+// ============================================================================
+
+/// 一条实时日志
+#[derive(Debug, Clone, Serialize)]
+pub struct TailRecord {
+    /// 序号（单调递增，用作 SSE 事件 ID）
+    pub id: u64,
+    /// 记录时间
+    pub time: DateTime<Utc>,
+    /// 日志级别，如 `WARN`
+    pub level: &'static str,
+    /// 日志目标，审计日志为 `audit`
+    pub target: &'static str,
+    /// 日志内容（含结构化字段）
+    pub message: String,
+}
+
+/// 实时日志缓冲
+///
+/// 保留最近 `TAIL_CAPACITY` 条日志，并向订阅者广播新日志
+pub struct LogTail {
+    /// 最近的日志与下一条日志的序号
+    inner: Mutex<(VecDeque<TailRecord>, u64)>,
+    /// 新日志广播
+    sender: broadcast::Sender<TailRecord>,
+}
+
+impl LogTail {
+    /// 创建空缓冲
+    fn new() -> Self {
+        Self {
+            inner: Mutex::new((VecDeque::with_capacity(TAIL_CAPACITY), 1)),
+            sender: broadcast::channel(TAIL_CAPACITY).0,
+        }
+    }
+
+    /// 追加一条日志
+    fn push(&self, level: &Level, target: &'static str, message: String) {
+        let mut inner = self.inner.lock();
+        let (buffer, next_id) = &mut *inner;
+        let record = TailRecord {
+            id: *next_id,
+            time: Utc::now(),
+            level: level.as_str(),
+            target,
+            message,
+        };
+        *next_id += 1;
+        if buffer.len() == TAIL_CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(record.clone());
+        // 在锁内广播，保证订阅时取到的缓冲与之后收到的日志既不重复也不遗漏
+        let _ = self.sender.send(record);
+    }
+
+    /// 订阅实时日志
+    ///
+    /// ### 返回值
+    /// 缓冲中的日志（按时间顺序）和之后新日志的接收端
+    pub fn subscribe(&self) -> (Vec<TailRecord>, broadcast::Receiver<TailRecord>) {
+        let inner = self.inner.lock();
+        (inner.0.iter().cloned().collect(), self.sender.subscribe())
+    }
+}
+
+/// 全局实时日志缓冲
+pub fn tail() -> &'static LogTail {
+    static TAIL: OnceLock<LogTail> = OnceLock::new();
+    TAIL.get_or_init(LogTail::new)
+}
+
+/// 将警告及以上级别日志和审计日志写入实时日志缓冲的日志层
+struct TailLayer;
+
+impl<S: Subscriber> Layer<S> for TailLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        // 级别越详细越“大”：跳过 INFO 及更详细的非审计日志
+        if *meta.level() > Level::WARN && meta.target() != AUDIT_TARGET {
+            return;
+        }
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+        tail().push(meta.level(), meta.target(), visitor.0);
+    }
+}
+
+/// 将日志消息和结构化字段拼接为一行文本
+struct MessageVisitor(String);
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
+}
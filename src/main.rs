@@ -9,27 +9,37 @@
 //! - 主播身份验证和权限管理
 //!
 //! ## 服务设计
-//! - **端口 8848**: 统一服务
+//! - **端口 8848**: 统一服务（可通过 `LIVE_SERVER_LISTEN` 按服务拆分到多个地址）
 //!   - `/` → SRS 回调
 //!   - `/api` → 认证答题
 //!   - `/chat` → 聊天室
+//!
+//! ## 子命令
+//! - `selfcheck` - 使用当前配置在临时端口上模拟一场完整直播，输出自检报告后退出
+//! - `dump-merge` - 将崩溃恢复日志与不完整的聊天转储合并为完整归档后退出
+//! - `dump-migrate` - 将旧版聊天转储升级到当前格式版本后退出
+//!
+//! ## 零停机升级
+//! 启用 `hot-upgrade` 特性编译时，收到 SIGUSR2 后把监听套接字和运行状态交给新版本的进程再退出（见 `upgrade` 模块）
 
-mod config;
-mod error;
-mod handlers;
-mod state;
-
-use axum::{
-    routing::{get, post},
-    Router,
+use rusty_live_server::config::Config;
+use rusty_live_server::{dump_merge, dump_schema};
+use rusty_live_server::logging;
+use rusty_live_server::redact;
+use rusty_live_server::router;
+use rusty_live_server::selfcheck;
+use rusty_live_server::upgrade;
+use rusty_live_server::state::{
+    disk_guard, events::StreamEvent, health, mqtt::MqttPublisher, notify::Alert, replication::Replicator, srs_check,
+    srs_watchdog, AppState,
 };
-use config::Config;
-use state::AppState;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::signal;
-use tower_http::trace::TraceLayer;
-use tracing::{info, Level};
+use tracing::info;
+
+/// 后台清理任务名称（用于心跳上报）
+const CLEANUP_TASK: &str = "cleanup";
 
 /// 程序入口点
 ///
@@ -39,7 +49,7 @@ use tracing::{info, Level};
 /// 3. 确保必要目录存在
 /// 4. 检查/创建密钥文件
 /// 5. 初始化应用状态
-/// 6. 构建统一路由
+/// 6. 绑定监听地址
 /// 7. 启动后台清理任务
 /// 8. 启动 HTTP 服务
 #[tokio::main]
@@ -47,17 +57,64 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ========================================
     // 1. 初始化日志系统
     // ========================================
-    tracing_subscriber::fmt()
-        .with_max_level(Level::DEBUG)
-        .with_target(false)
-        .init();
+    logging::init();
 
     info!("正在启动 live-server-rs...");
 
     // ========================================
     // 2. 加载配置
     // ========================================
+    if let Err(e) = Config::load_file() {
+        eprintln!("{}", e);
+        return Err(e.into());
+    }
     let config = Config::from_env();
+    if let Err(e) = logging::set_level(&config.log_level) {
+        tracing::warn!("{}，使用默认日志级别", e);
+    }
+    redact::init(config.log_sensitive);
+    for name in config.features.unknown_names() {
+        tracing::warn!("未知的实验性功能: {}", name);
+    }
+    let enabled = config.features.enabled_names();
+    if !enabled.is_empty() {
+        info!("已开启实验性功能: {}", enabled.join(", "));
+    }
+
+    // `selfcheck` 子命令：自检完成后以退出码报告结果
+    if std::env::args().nth(1).as_deref() == Some("selfcheck") {
+        if let Err(e) = logging::set_level("warn") {
+            tracing::warn!("{}", e);
+        }
+        let passed = selfcheck::run(config).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // `dump-merge` 子命令：离线合并聊天转储，不启动服务
+    if std::env::args().nth(1).as_deref() == Some("dump-merge") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        match dump_merge::run(&args) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // `dump-migrate` 子命令：离线升级旧版聊天转储，不启动服务
+    if std::env::args().nth(1).as_deref() == Some("dump-migrate") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        match dump_schema::run(&args) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
 
     // ========================================
     // 3. 确保必要目录存在
@@ -88,62 +145,355 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             return Err(e);
         }
     };
+    // 配置了远程题库时，启动前先拉取一次
+    if let Some(source) = &state.banner_source {
+        if let Err(e) = source.refresh(&state.banner_db).await {
+            tracing::warn!("拉取远程题库失败，使用本地缓存: {}", e);
+        }
+    }
+    let report = state.banner_db.current().report();
+    info!(
+        "题库已加载: 共 {} 条，排除 {} 条，无公告 {} 条，可出题 {} 条",
+        report.total, report.excluded, report.empty, report.eligible
+    );
+    for warning in &report.warnings {
+        tracing::warn!("题库: {}", warning);
+    }
+    if state.template_quiz.is_none() && report.eligible < config.quiz_min_pool.max(1) {
+        tracing::warn!(
+            "题库可出题条目不足 {} 条，观众将改用 {:?} 方式入场",
+            config.quiz_min_pool.max(1),
+            config.quiz_fallback
+        );
+    }
+
+    // 可选：检查 SRS 侧配置是否与本服务一致
+    if config.srs_self_check {
+        let problems = srs_check::run(&state.srs_api, &config).await;
+        if problems.is_empty() {
+            info!("SRS 配置自检通过");
+        } else {
+            for problem in &problems {
+                tracing::warn!("SRS 配置自检: {}", problem);
+            }
+            tracing::warn!("SRS 配置自检发现 {} 个问题，推流/拉流可能无法正常工作", problems.len());
+        }
+    }
 
     // ========================================
-    // 6. 构建统一路由（端口 8848）
+    // 6. 绑定监听地址（每个地址只挂载其配置的服务）
     // ========================================
-    let app = Router::new()
-        .route("/", post(handlers::srs_callback_handler))  // SRS 回调
-        .route("/api", get(handlers::api_handler))          // 认证答题
-        .route("/chat", post(handlers::chat_handler))       // 聊天室
-        .route("/streaming_info", get(handlers::streaming_info_handler))
-        .layer(TraceLayer::new_for_http())
-        .with_state(state.clone());
+    // 由零停机升级启动时沿用旧进程的监听套接字
+    let mut inherited = upgrade::Inherited::from_env();
+    let mut servers = Vec::new();
+    for listen in config.listeners() {
+        let tcp_listener = match inherited.take(listen.addr)? {
+            Some(tcp_listener) => tcp_listener,
+            None => tokio::net::TcpListener::bind(listen.addr).await?,
+        };
+        state.health.add_listener(tcp_listener.local_addr()?);
+        servers.push((tcp_listener, listen));
+    }
+    drop(inherited);
+    upgrade::import(&state);
 
     // ========================================
     // 7. 启动后台任务
     // ========================================
-    // 每 10 秒清理过期的客户端和主播记录
+    // 定期清理过期的客户端和主播记录（默认每 10 秒）
     let srs_db_for_tick = state.srs_db.clone();
+    let chat_db_for_tick = state.chat_db.clone();
+    let state_for_tick = state.clone();
+    let cleanup_secs = config.intervals.cleanup_secs;
+    let health_for_tick = state.health.clone();
+    health_for_tick.register(CLEANUP_TASK, cleanup_secs);
     let tick_task = tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(10));
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(cleanup_secs));
         loop {
             interval.tick().await;
-            srs_db_for_tick.tick();
+            health_for_tick.beat(CLEANUP_TASK);
+            let expired = srs_db_for_tick.tick(&state_for_tick.events);
+
+            // 观众记录过期视为离开直播间
+            if state_for_tick.config().chat_presence_notices && !expired.is_empty() {
+                let mut chat_rooms = chat_db_for_tick.inner.write();
+                let room = chat_rooms.active_mut();
+                for (ip, session_id) in &expired {
+                    room.announce_presence(ip, session_id, false);
+                }
+            }
+
+            // 回收观众记录已过期、长时间无聊天活动的身份
+            let retention_secs = state_for_tick.config().chat_identity_retention_secs;
+            if retention_secs > 0 {
+                let clients = srs_db_for_tick.clients.read();
+                let swept = chat_db_for_tick.inner.write().active_mut().sweep_identities(
+                    std::time::Duration::from_secs(retention_secs),
+                    |ip, session_id| clients.has_client(ip, session_id),
+                );
+                if swept > 0 {
+                    tracing::debug!("回收了 {} 个不活跃的聊天身份", swept);
+                }
+            }
+
+            // 转储目录剩余空间不足时发送邮件通知
+            if state_for_tick.notifier.is_some() {
+                let dump_path = state_for_tick.config().dump_path.clone();
+                if let Some(available) = health::disk_available(&dump_path).filter(|b| *b < health::MIN_DISK_BYTES) {
+                    state_for_tick.notify(
+                        Alert::LowDiskSpace,
+                        format!("转储目录 {} 剩余空间仅 {} MiB", dump_path.display(), available / 1024 / 1024),
+                    );
+                }
+            }
+
+            // 大量观众卡在答题阶段或答错率过高时提醒主播
+            let stuck = srs_db_for_tick
+                .is_streaming()
+                .then(|| srs_db_for_tick.clients.read().stuck_pending_count(chrono::Duration::seconds(60)));
+            if let Some(stuck) = stuck {
+                let config = state_for_tick.config();
+                if let Some(event) = state_for_tick.quiz_health.evaluate(
+                    stuck,
+                    config.stuck_pending_alert,
+                    config.quiz_failure_alert,
+                ) {
+                    state_for_tick.events.publish(event);
+                }
+            }
+
+            // 推流码率持续为零时提示主播可能已离开
+            let config = state_for_tick.config();
+            let away = state_for_tick
+                .streaming_info
+                .inner
+                .write()
+                .evaluate_away(std::time::Duration::from_secs(config.away_after_secs));
+            if let Some(away) = away {
+                if away {
+                    tracing::info!("推流码率持续为零，主播可能已离开");
+                } else {
+                    tracing::info!("推流码率已恢复");
+                }
+                if config.away_notice {
+                    let notice = if away { "主播似乎暂时离开了，请稍候" } else { "主播回来了" };
+                    chat_db_for_tick.inner.write().active_mut().add_system(notice, false);
+                }
+                state_for_tick.events.publish(StreamEvent::StreamerAway { away });
+            }
+
+            // 发送到期的定时消息
+            let live_since = {
+                let streamer = srs_db_for_tick.streamer.read();
+                streamer.live_since().filter(|_| streamer.is_streaming())
+            };
+            let due = state_for_tick.scheduled.take_due(live_since);
+            if !due.is_empty() {
+                let config = state_for_tick.config();
+                let limits = (config.chat_embed_user_per_min, config.chat_embed_global_per_min);
+                let mut chat_rooms = chat_db_for_tick.inner.write();
+                let room = chat_rooms.active_mut();
+                for message in due {
+                    tracing::info!("发送定时消息 {}", message.id);
+                    room.add_entry(message.ip, message.session_id, message.message, true, None, limits);
+                }
+            }
+
+            // 主播过期后关闭本场聊天室
+            if !srs_db_for_tick.is_streaming() {
+                if let Some(room) = chat_db_for_tick.inner.write().close_room() {
+                    if let Err(e) = disk_guard::dump_closed_room(room, state_for_tick.config().dump_min_free_bytes, &state_for_tick.events) {
+                        tracing::warn!("转储聊天记录失败: {}", e);
+                    }
+                }
+            }
         }
     });
 
+    // 定时刷新远程题库
+    let banner_refresh_task = state
+        .banner_source
+        .clone()
+        .map(|source| {
+            source.tick(
+                state.banner_db.clone(),
+                config.intervals.banner_db_refresh_secs,
+                state.health.clone(),
+                state.notifier.clone(),
+            )
+        });
+
+    // 收到 SIGHUP 时重新加载配置
+    let reload_task = spawn_reload_listener(state.clone());
+
     // 从srs获取观众人数
-    let srs_api_url = config.srs_api_addr();
     let streaming_info = state.streaming_info.clone();
-    let streaming_info_task_handle = streaming_info.tick(srs_api_url);
+    let streaming_info_task_handle = streaming_info.tick(
+        state.srs_api.clone(),
+        state.srs_db.clone(),
+        state.metrics.clone(),
+        config.intervals,
+        state.health.clone(),
+        state.notifier.clone(),
+    );
+
+    // 检测 SRS 回调中断
+    let watchdog_task = srs_watchdog::spawn(state.clone(), config.intervals.srs_watchdog_secs);
+
+    // 定期计算派生指标
+    let metrics_task = state
+        .metrics
+        .clone()
+        .tick(state.quiz_health.clone(), state.health.clone());
+
+    // 向 MQTT broker 发布直播状态
+    let mqtt_task = config.mqtt.clone().and_then(|target| {
+        MqttPublisher::new(target, config.mqtt_password.clone()).spawn(
+            state.srs_db.clone(),
+            state.chat_db.clone(),
+            state.streaming_info.clone(),
+            state.events.clone(),
+        )
+    });
+
+    // 主备实例间的热备复制
+    let replication_task = match (config.replication.clone(), config.replication_key.as_ref()) {
+        (Some(role), Some(key)) => Some(Replicator::new(role, key).spawn(
+            state.srs_db.clone(),
+            state.chat_db.clone(),
+            state.health.clone(),
+        )),
+        (Some(_), None) => {
+            tracing::warn!("已配置热备复制但未设置 LIVE_SERVER_REPLICATION_KEY，复制未启用");
+            None
+        }
+        (None, _) => None,
+    };
 
     // ========================================
     // 8. 启动 HTTP 服务
     // ========================================
-    let addr: SocketAddr = config.addr().parse()?;
-
-    info!("服务启动成功，监听于 {}", addr);
-    info!("  /      → SRS 回调");
-    info!("  /api   → 认证答题");
-    info!("  /chat  → 聊天室");
-    info!("  /streaming_info  → 流信息");
+    // 零停机升级时交给新进程的监听套接字
+    let handoff_listeners = upgrade::Listeners::keep(&servers)?;
+    loop {
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(());
+        let server_tasks: Vec<_> = servers
+            .into_iter()
+            .map(|(tcp_listener, listen)| {
+                info!("服务启动成功，监听于 {}（{}）", listen.addr, listen.services.names().join("+"));
+                if listen.services.callback {
+                    info!("  /      → SRS 回调");
+                }
+                if listen.services.api {
+                    info!("  /api   → 认证答题");
+                    info!("  /chat  → 聊天室");
+                    info!("  /streaming_info  → 流信息");
+                    info!("  /events  → 事件推送");
+                }
+                if listen.services.admin {
+                    info!("  /admin   → 管理接口");
+                }
 
-    let tcp_listener = tokio::net::TcpListener::bind(addr).await?;
+                let app = router::build_for(state.clone(), listen.services);
+                let mut shutdown = shutdown_rx.clone();
+                tokio::spawn(async move {
+                    axum::serve(tcp_listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                        .with_graceful_shutdown(async move {
+                            let _ = shutdown.changed().await;
+                        })
+                        .await
+                })
+            })
+            .collect();
 
-    axum::serve(tcp_listener, app.into_make_service_with_connect_info::<SocketAddr>())
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
+        let upgrading = tokio::select! {
+            _ = shutdown_signal() => false,
+            _ = upgrade::requested() => true,
+        };
+        let _ = shutdown_tx.send(());
+        let drain = async {
+            for task in server_tasks {
+                if let Ok(Err(e)) = task.await {
+                    tracing::warn!("HTTP 服务异常退出: {}", e);
+                }
+            }
+        };
+        if !upgrading {
+            drain.await;
+            break;
+        }
+        if tokio::time::timeout(upgrade::DRAIN_TIMEOUT, drain).await.is_err() {
+            info!("仍有未结束的连接（如 SSE），不再等待");
+        }
+        match upgrade::hand_over(&state, &handoff_listeners).await {
+            Ok(pid) => {
+                info!("新进程 {} 已接管服务", pid);
+                break;
+            }
+            Err(e) => {
+                tracing::error!("零停机升级失败，由本进程继续提供服务: {}", e);
+                servers = handoff_listeners.reopen()?;
+            }
+        }
+    }
 
     // 中止后台清理任务
     tick_task.abort();
     streaming_info_task_handle.abort();
+    metrics_task.abort();
+    if let Some(task) = banner_refresh_task {
+        task.abort();
+    }
+    if let Some(task) = reload_task {
+        task.abort();
+    }
+    if let Some(task) = mqtt_task {
+        task.abort();
+    }
+    if let Some(task) = replication_task {
+        task.abort();
+    }
+    if let Some(task) = watchdog_task {
+        task.abort();
+    }
 
     info!("live-server-rs 已停止");
     Ok(())
 }
 
+/// 监听 SIGHUP 并重新加载配置（仅 Unix 系统）
+///
+/// ### 返回值
+/// 后台任务句柄，非 Unix 系统或无法安装信号处理器时返回 `None`
+#[cfg(unix)]
+fn spawn_reload_listener(state: Arc<AppState>) -> Option<tokio::task::JoinHandle<()>> {
+    let mut hangup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!("无法安装 SIGHUP 处理器，配置热重载仅可通过管理接口触发: {}", e);
+            return None;
+        }
+    };
+    Some(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("收到 SIGHUP，重新加载配置");
+            match state.reload_config() {
+                Ok(report) => info!(
+                    "配置已重新加载: 生效 {:?}，需重启 {:?}",
+                    report.applied, report.restart_required
+                ),
+                Err(e) => tracing::warn!("重新加载配置失败: {}", e),
+            }
+        }
+    }))
+}
+
+/// 非 Unix 系统不支持 SIGHUP
+#[cfg(not(unix))]
+fn spawn_reload_listener(_state: Arc<AppState>) -> Option<tokio::task::JoinHandle<()>> {
+    None
+}
+
 /// 优雅关闭信号处理
 ///
 /// 监听以下信号并触发关闭流程：
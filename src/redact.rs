@@ -0,0 +1,60 @@
+//! # 日志脱敏模块
+//!
+//! 默认情况下，日志和 `Debug` 输出中的敏感信息会被遮蔽：
+//! - IP 地址只保留网段部分（IPv4 保留前 3 段，IPv6 保留前 3 组）
+//! - 题目答案、推流密钥、回调参数等显示为 `[REDACTED]`
+//!
+//! 排查问题时可设置 `LIVE_SERVER_LOG_SENSITIVE=true` 输出完整信息。
+//! `audit` 目标的安全审计日志始终记录完整 IP。
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 脱敏占位文本
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 是否输出完整敏感信息
+static LOG_SENSITIVE: AtomicBool = AtomicBool::new(false);
+
+/// 设置是否输出完整敏感信息（启动时调用一次）
+pub fn init(log_sensitive: bool) {
+    LOG_SENSITIVE.store(log_sensitive, Ordering::Relaxed);
+}
+
+/// 是否输出完整敏感信息
+pub fn log_sensitive() -> bool {
+    LOG_SENSITIVE.load(Ordering::Relaxed)
+}
+
+/// 遮蔽 IP 地址的主机部分
+///
+/// ### 示例
+/// - `203.0.113.42` -> `203.0.113.*`
+/// - `2001:db8:1:2::5` -> `2001:db8:1:*`
+/// - 无法解析的地址 -> `[REDACTED]`
+pub fn ip(ip: impl AsRef<str>) -> String {
+    let ip = ip.as_ref();
+    if log_sensitive() {
+        return ip.to_string();
+    }
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{}.{}.{}.*", a, b, c)
+        }
+        Ok(IpAddr::V6(v6)) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}:{:x}:*", s[0], s[1], s[2])
+        }
+        Err(_) => REDACTED.to_string(),
+    }
+}
+
+/// 遮蔽任意敏感文本（答案、回调参数等）
+pub fn text(s: &str) -> &str {
+    if log_sensitive() {
+        s
+    } else {
+        REDACTED
+    }
+}
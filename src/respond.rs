@@ -0,0 +1,126 @@
+//! # 响应协商模块
+//!
+//! 根据请求头 `Accept` 选择响应格式：
+//! - 旧版客户端：保持各接口原有的响应结构
+//! - 请求 `application/vnd.live-server.v2+json` 的客户端：统一包装为
+//!   `{"data": ..., "error": ..., "meta": {...}}` 结构
+//!
+//! 处理器无需关心版本，由 `negotiate` 中间件统一转换。
+
+use crate::error::ApiErrorCode;
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{json, Value};
+
+/// v2 响应格式的媒体类型
+pub const V2_MEDIA_TYPE: &str = "application/vnd.live-server.v2+json";
+
+/// 转换时允许缓冲的最大响应体大小
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// 响应格式版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// 原有响应结构
+    V1,
+    /// 统一信封结构
+    V2,
+}
+
+impl ApiVersion {
+    /// 从请求头中协商响应格式版本
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let wants_v2 = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|t| t.split(';').next().map(str::trim) == Some(V2_MEDIA_TYPE));
+        if wants_v2 {
+            Self::V2
+        } else {
+            Self::V1
+        }
+    }
+
+    /// 将版本转换为数字
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+}
+
+/// 构造 v2 信封
+///
+/// ### 参数
+/// - `status`: 原响应状态码
+/// - `body`: 原响应体
+fn envelope(status: StatusCode, body: &[u8]) -> Value {
+    let parsed: Option<Value> = serde_json::from_slice(body).ok();
+    let meta = json!({ "version": ApiVersion::V2.as_u8(), "status": status.as_u16() });
+
+    if status.is_success() {
+        let data = parsed.unwrap_or_else(|| Value::String(String::from_utf8_lossy(body).into_owned()));
+        return json!({ "data": data, "error": null, "meta": meta });
+    }
+
+    // 错误响应：优先使用 ApiError 输出的结构化字段，其余按状态码推断错误码
+    let field = |key: &str| parsed.as_ref().and_then(|v| v.get(key)).cloned();
+    let code = field("code").unwrap_or_else(|| {
+        json!(match status {
+            StatusCode::FORBIDDEN => ApiErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ApiErrorCode::NotFound,
+            StatusCode::BAD_REQUEST => ApiErrorCode::BadRequest,
+            StatusCode::TOO_MANY_REQUESTS => ApiErrorCode::RateLimited,
+            _ => ApiErrorCode::Internal,
+        })
+    });
+    let message = field("error")
+        .unwrap_or_else(|| Value::String(String::from_utf8_lossy(body).into_owned()));
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(retry_after) = field("retry_after") {
+        error["retry_after"] = retry_after;
+    }
+    json!({ "data": null, "error": error, "meta": meta })
+}
+
+/// 响应格式协商中间件
+///
+/// 仅转换请求了 v2 格式的 JSON / 文本响应，SSE 等流式响应原样返回
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let version = ApiVersion::from_headers(request.headers());
+    let response = next.run(request).await;
+    if version == ApiVersion::V1 {
+        return response;
+    }
+
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_event_stream {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(b) => b,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let body = envelope(parts.status, &bytes).to_string();
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(V2_MEDIA_TYPE));
+    parts.headers.append(header::VARY, HeaderValue::from_static("accept"));
+    Response::from_parts(parts, Body::from(body))
+}
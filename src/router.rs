@@ -0,0 +1,123 @@
+//! # 路由模块
+//!
+//! 构建 HTTP 路由，供主程序和 `selfcheck` 自检共用。
+//! 路由按服务划分，每个监听地址只挂载其配置的服务（见 `LIVE_SERVER_LISTEN`）：
+//! - `callback`: `/` → SRS 回调（全部类型），`/hooks/<回调类型>` → 单一类型的 SRS 回调
+//! - `api`: `/api` → 认证答题，`/chat` → 聊天室（允许 `LIVE_SERVER_FRONTEND_TOKENS` 登记的前端跨域调用）
+//! - `admin`: `/admin` → 管理接口
+
+use crate::{config::Services, handlers, respond, state::AppState};
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+use tower_http::trace::TraceLayer;
+
+/// 构建统一路由（提供全部服务）
+pub fn build(state: Arc<AppState>) -> Router {
+    build_for(state, Services::ALL)
+}
+
+/// 构建只提供指定服务的路由
+///
+/// ### 参数
+/// - `services`: 监听地址上提供的服务，未提供的服务路径返回 404
+pub fn build_for(state: Arc<AppState>, services: Services) -> Router {
+    let mut router = Router::new();
+    if services.callback {
+        router = router.merge(callback_routes());
+    }
+    if services.api {
+        // 外部前端：登记来源的跨域请求须携带 API 令牌（CORS 层在外，先处理预检请求）
+        router = router.merge(
+            api_routes()
+                .layer(axum::middleware::from_fn_with_state(state.clone(), handlers::frontend::frontend_gate))
+                .layer(handlers::frontend::cors(state.clone())),
+        );
+    }
+    if services.admin {
+        router = router.merge(admin_routes()).merge(debug_routes());
+    }
+    router
+        // 按 Accept 头协商响应格式（v2 统一信封）
+        .layer(axum::middleware::from_fn(respond::negotiate))
+        // 请求日志只记录路径，查询参数中可能包含答案或推流密钥
+        .layer(TraceLayer::new_for_http().make_span_with(|req: &axum::http::Request<_>| {
+            tracing::debug_span!("request", method = %req.method(), path = %req.uri().path())
+        }))
+        .with_state(state)
+}
+
+/// SRS 回调路由
+fn callback_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(handlers::srs_callback_handler))  // SRS 回调（兼容，接受全部类型）
+        .route("/hooks/on_publish", post(handlers::srs_hook_handler))
+        .route("/hooks/on_unpublish", post(handlers::srs_hook_handler))
+        .route("/hooks/on_play", post(handlers::srs_hook_handler))
+        .route("/hooks/on_stop", post(handlers::srs_hook_handler))
+        .route("/hooks/on_dvr", post(handlers::srs_hook_handler))
+}
+
+/// 观众接口路由（含主播操作和聊天室）
+fn api_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api", get(handlers::api_handler))          // 认证答题
+        .route("/api/bootstrap", get(handlers::bootstrap_handler))  // 前端启动信息
+        .route("/api/streaminfo", get(handlers::streaming_info_handler))  // 直播状态组件
+        .route("/api/hooks/tip", post(handlers::tip_hook_handler))  // 打赏回调
+        .route("/api/publisher/quick", post(handlers::publisher_quick_handler))  // 主播快捷操作
+        .route("/api/publisher/push_url", get(handlers::push_url_handler))  // 推流地址
+        .route(
+            "/api/publisher/preview",
+            get(handlers::preview_handler).delete(handlers::preview_revoke_handler),
+        )  // 主播预览地址
+        .route(
+            "/api/publisher/relay",
+            get(handlers::relay_status_handler).post(handlers::relay_control_handler),
+        )  // 转推
+        .route("/api/archive", get(handlers::archive_handler))  // 历史归档
+        .route("/api/archive/transcript", get(handlers::archive_transcript_handler))  // 聊天记录转储
+        .route("/api/archive/report", get(handlers::archive_report_handler))  // 用户消息报告
+        .route("/chat", post(handlers::chat_handler))       // 聊天室
+        .route("/chat/redirect", get(handlers::chat_redirect_handler))  // 外链跳转警告页
+        .route("/streaming_info", get(handlers::streaming_info_handler))
+        .route("/events", get(handlers::events_handler))    // SSE 事件推送
+}
+
+/// 管理接口路由
+fn admin_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/admin/bannerdb/sample", get(handlers::bannerdb_sample_handler))
+        .route("/admin/bannerdb/report", get(handlers::bannerdb_report_handler))
+        .route("/admin/metrics", get(handlers::metrics_handler))  // 运行指标
+        .route("/admin/status", get(handlers::status_handler))    // 健康状态汇总
+        .route("/admin/clients", get(handlers::clients_handler))  // 客户端列表
+        .route("/admin/clients/bulk", post(handlers::clients_bulk_handler))  // 客户端批量操作
+        .route("/admin/push_qr", get(handlers::push_qr_handler))  // 推流地址二维码
+        .route("/admin/reload", post(handlers::reload_handler))   // 配置热重载
+        .route("/admin/config/preview", post(handlers::config_preview_handler))  // 候选配置预览
+        .route("/admin/relay/seal", post(handlers::relay_seal_handler))  // 转推目标加密
+        .route("/admin/recordings", get(handlers::recordings_handler))  // 录制文件
+        .route("/admin/chat/export", get(handlers::chat_export_handler))  // 聊天记录导出
+        .route("/admin/events", get(handlers::admin_events_handler))  // 实时日志
+}
+
+/// 调试与故障演练路由（仅 `debug-endpoints` 特性）
+#[cfg(feature = "debug-endpoints")]
+fn debug_routes() -> Router<Arc<AppState>> {
+    use handlers::debug;
+    tracing::warn!("已启用调试接口 /debug，请勿在生产环境中使用");
+    Router::new()
+        .route("/debug/fast_forward", post(debug::fast_forward_handler))
+        .route("/debug/viewers", post(debug::viewers_handler))
+        .route("/debug/chat", post(debug::chat_load_handler))
+        .route("/debug/srs", post(debug::simulate_srs_handler))
+}
+
+/// 未启用 `debug-endpoints` 特性时没有调试路由
+#[cfg(not(feature = "debug-endpoints"))]
+fn debug_routes() -> Router<Arc<AppState>> {
+    Router::new()
+}
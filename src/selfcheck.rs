@@ -0,0 +1,322 @@
+//! # 端到端自检模块
+//!
+//! `rusty-live-server selfcheck` 子命令：使用当前配置在本机临时端口上启动路由，
+//! 按真实顺序模拟一场完整的直播流程并输出通过/失败报告，适合在修改配置后、开播前运行。
+//!
+//! ## 模拟流程
+//! 1. SRS `on_publish` 回调（使用临时生成的推流密钥）
+//! 2. 观众连接（`action=connect`）
+//! 3. 观众答题
+//! 4. 答题通过后立即进入聊天室（授权状态须对聊天接口立即可见）
+//! 5. SRS `on_play` 回调
+//! 6. 观众发送并拉取聊天消息
+//! 7. SRS `on_unpublish` 回调（直播应进入暂停状态）
+//!
+//! 推流密钥文件和聊天转储使用临时目录，不会影响正式数据。
+
+use crate::{
+    config::Config,
+    domain::{ClientIp, SessionId},
+    ids, router,
+    state::{stream_policy, AppState},
+};
+use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 模拟流程使用的流名称
+const STREAM: &str = "selfcheck";
+
+/// 模拟观众的 IP（请求来自本机）
+const VIEWER_IP: &str = "127.0.0.1";
+
+/// 自检报告
+#[derive(Debug, Default)]
+struct Report {
+    /// 已执行的步骤：(名称, 结果)
+    steps: Vec<(&'static str, Result<String, String>)>,
+}
+
+impl Report {
+    /// 记录一个步骤的结果
+    ///
+    /// ### 返回值
+    /// 步骤是否通过（失败时后续步骤不再执行）
+    fn step(&mut self, name: &'static str, result: Result<String, String>) -> bool {
+        let passed = result.is_ok();
+        self.steps.push((name, result));
+        passed
+    }
+
+    /// 输出报告
+    fn print(&self, total: usize) {
+        for (name, result) in &self.steps {
+            match result {
+                Ok(detail) => println!("[通过] {} — {}", name, detail),
+                Err(reason) => println!("[失败] {} — {}", name, reason),
+            }
+        }
+        let skipped = total.saturating_sub(self.steps.len());
+        if skipped > 0 {
+            println!("[跳过] 其余 {} 个步骤", skipped);
+        }
+    }
+}
+
+/// 运行端到端自检
+///
+/// ### 参数
+/// - `config`: 当前配置（推流密钥文件、转储目录和监听地址会被替换为临时值）
+///
+/// ### 返回值
+/// 全部步骤通过时返回 `true`
+pub async fn run(mut config: Config) -> bool {
+    let dir = std::env::temp_dir().join(format!("rusty-live-selfcheck-{}", ids::ulid()));
+    let passed = match prepare(&mut config, &dir) {
+        Ok(secret) => run_in(config, &secret).await,
+        Err(e) => {
+            println!("[失败] 准备临时目录 — {}", e);
+            false
+        }
+    };
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        tracing::debug!("清理自检临时目录失败: {}", e);
+    }
+
+    println!("{}", if passed { "自检通过" } else { "自检未通过" });
+    passed
+}
+
+/// 创建临时目录和推流密钥文件
+///
+/// ### 返回值
+/// 临时生成的推流密钥
+fn prepare(config: &mut Config, dir: &Path) -> Result<String, String> {
+    let secret = format!("secret_{}", ids::short_code(16));
+    std::fs::create_dir_all(dir.join("dump")).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join("secret.txt"), format!("{}\n", secret)).map_err(|e| e.to_string())?;
+    config.dump_path = dir.join("dump");
+    config.secret_path = dir.join("secret.txt");
+    Ok(secret)
+}
+
+/// 在临时端口上启动路由并执行模拟流程
+async fn run_in(config: Config, secret: &str) -> bool {
+    /// 模拟流程的步骤数
+    const TOTAL_STEPS: usize = 8;
+
+    let app = config.srs_app.clone();
+    let mut report = Report::default();
+
+    let state = match AppState::new(config) {
+        Ok(s) => Arc::new(s),
+        Err(e) => {
+            report.step("初始化应用状态", Err(e.to_string()));
+            report.print(TOTAL_STEPS);
+            return false;
+        }
+    };
+    let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+        Ok(l) => l,
+        Err(e) => {
+            report.step("初始化应用状态", Err(format!("无法监听临时端口: {}", e)));
+            report.print(TOTAL_STEPS);
+            return false;
+        }
+    };
+    let base = match listener.local_addr() {
+        Ok(addr) => format!("http://{}", addr),
+        Err(e) => {
+            report.step("初始化应用状态", Err(e.to_string()));
+            report.print(TOTAL_STEPS);
+            return false;
+        }
+    };
+    report.step("初始化应用状态", Ok(format!("监听于 {}", base)));
+
+    let router = router::build(state.clone());
+    let server = tokio::spawn(async move {
+        axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>()).await
+    });
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap_or_default();
+    let check = Check {
+        client,
+        base,
+        app,
+        session_id: SessionId::parse(&format!("selfcheck-{}", ids::short_code(8))).expect("自检会话 ID 合法"),
+        state,
+    };
+
+    let _ = report.step(
+        "on_publish 回调",
+        check.callback("on_publish", &format!("?secret={}", secret)).await,
+    ) && report.step("观众连接", check.connect().await)
+        && report.step("观众答题", check.answer().await)
+        && report.step("答题后立即聊天", check.chat_after_answer().await)
+        && report.step(
+            "on_play 回调",
+            check.callback("on_play", &format!("?session_id={}", check.session_id)).await,
+        )
+        && report.step("聊天", check.chat().await)
+        && report.step("on_unpublish 回调", check.unpublish().await);
+
+    server.abort();
+    report.print(TOTAL_STEPS);
+    report.steps.len() == TOTAL_STEPS && report.steps.iter().all(|(_, r)| r.is_ok())
+}
+
+/// 模拟流程的上下文
+struct Check {
+    /// HTTP 客户端
+    client: reqwest::Client,
+    /// 服务基础地址
+    base: String,
+    /// 推流应用名称
+    app: String,
+    /// 模拟观众的会话 ID
+    session_id: SessionId,
+    /// 应用状态（用于读取题目答案和校验内部状态）
+    state: Arc<AppState>,
+}
+
+impl Check {
+    /// 模拟一次 SRS 回调，期望返回 200
+    async fn callback(&self, action: &str, param: &str) -> Result<String, String> {
+        let body = json!({
+            "action": action,
+            "ip": VIEWER_IP,
+            "app": self.app,
+            "stream": STREAM,
+            "param": param,
+        });
+        // 配置了回调令牌时与 SRS 一样携带
+        let token = self
+            .state
+            .config()
+            .srs_callback_token
+            .as_ref()
+            .map(|t| t.expose_secret().to_string());
+        let resp = self
+            .client
+            .post(format!("{}/", self.base))
+            .query(&[("token", token)])
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if resp.status().is_success() {
+            Ok(format!("{}/{} 已放行", self.app, STREAM))
+        } else {
+            Err(format!("回调被拒绝（{}）", resp.status()))
+        }
+    }
+
+    /// 请求 `/api` 并解析 JSON 响应
+    async fn api(&self, query: &[(&str, &str)]) -> Result<Value, String> {
+        let resp = self
+            .client
+            .get(format!("{}/api", self.base))
+            .query(&[("session_id", self.session_id.as_str())])
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("/api 返回状态码 {}", resp.status()));
+        }
+        resp.json().await.map_err(|e| e.to_string())
+    }
+
+    /// 观众连接，期望获得题目（或被直接放行）
+    async fn connect(&self) -> Result<String, String> {
+        let json = self.api(&[("action", "connect")]).await?;
+        if json.get("captcha_required").is_some() {
+            return Err("需要人机验证，自检无法继续".to_string());
+        }
+        match (json.get("question"), json.get("video_uri")) {
+            (Some(q), _) => Ok(format!("获得题目: {}", q.as_str().unwrap_or_default())),
+            (None, Some(_)) => Ok("已直接放行".to_string()),
+            _ => Err(format!("响应中没有题目: {}", json)),
+        }
+    }
+
+    /// 提交正确答案，期望获得当前直播的播放地址
+    async fn answer(&self) -> Result<String, String> {
+        let expected = stream_policy::stream_query(&self.app, STREAM);
+        let answer = {
+            let viewer_ip = ClientIp::parse(VIEWER_IP).expect("自检观众 IP 合法");
+            let clients = self.state.srs_db.clients.read();
+            clients
+                .get_client_qa(&viewer_ip, &self.session_id)
+                .map(|(_, a)| a.to_string())
+        };
+        let Some(answer) = answer else {
+            // 连接时已被直接放行
+            return Ok("无需答题".to_string());
+        };
+        let json = self.api(&[("answer", &answer)]).await?;
+        match json.get("video_uri").and_then(Value::as_str) {
+            // 播放地址末尾附带续连令牌
+            Some(uri) if uri.split("&resume=").next() == Some(expected.as_str()) => {
+                Ok(format!("获得播放地址 {}", uri))
+            }
+            Some(uri) => Err(format!("播放地址不正确: {}", uri)),
+            None => Err(format!("响应中没有播放地址: {}", json)),
+        }
+    }
+
+    /// 请求 `/chat`
+    async fn chat_request(&self, body: Value) -> Result<Value, String> {
+        let resp = self
+            .client
+            .post(format!("{}/chat", self.base))
+            .query(&[("session_id", self.session_id.as_str())])
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("/chat 返回状态码 {}", resp.status()));
+        }
+        resp.json().await.map_err(|e| e.to_string())
+    }
+
+    /// 答题通过后不等拉流立即进入聊天室，期望不被拒绝
+    async fn chat_after_answer(&self) -> Result<String, String> {
+        let hello = self.chat_request(json!({"action": "hello"})).await?;
+        match hello.get("status").and_then(Value::as_str) {
+            Some("Okay") => Ok("已进入聊天室".to_string()),
+            _ => Err(format!("答题通过后聊天被拒绝: {}", hello)),
+        }
+    }
+
+    /// 发送一条聊天消息并确认能拉取到
+    async fn chat(&self) -> Result<String, String> {
+        let marker = format!("selfcheck {}", ids::short_code(6));
+        let sent = self
+            .chat_request(json!({"action": "sendchat", "chat": marker}))
+            .await?;
+        if sent.get("status").and_then(Value::as_str) != Some("Okay") {
+            return Err(format!("发送消息失败: {}", sent));
+        }
+        let fetched = self.chat_request(json!({"action": "hello"})).await?;
+        if fetched.get("chatmsgs").is_some_and(|m| m.to_string().contains(&marker)) {
+            Ok("消息已发送并拉取".to_string())
+        } else {
+            Err(format!("拉取的消息中没有刚发送的消息: {}", fetched))
+        }
+    }
+
+    /// 停止推流，期望直播进入暂停状态
+    async fn unpublish(&self) -> Result<String, String> {
+        self.callback("on_unpublish", "").await?;
+        if self.state.srs_db.is_paused() {
+            Ok("直播已进入暂停状态".to_string())
+        } else {
+            Err("直播未进入暂停状态".to_string())
+        }
+    }
+}
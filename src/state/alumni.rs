@@ -0,0 +1,81 @@
+//! # 回访观众令牌模块
+//!
+//! 观众答题通过后签发带有效期的 HMAC 令牌，之后的直播中凭令牌可跳过答题。
+//! 令牌完全由服务端密钥校验，无需保存任何观众数据。
+//!
+//! 令牌标识是观众首次获得令牌时的会话 ID，之后重新签发时沿用，
+//! 按标识保存的数据（如偏好设置）不会因换发令牌而分散。
+//!
+//! ## 令牌格式
+//! `<标识>.<过期时间戳>.<HMAC-SHA256 十六进制>`
+
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 回访观众令牌签发器
+#[derive(Clone)]
+pub struct AlumniSigner {
+    /// HMAC 密钥
+    key: Vec<u8>,
+    /// 令牌有效期
+    validity: Duration,
+}
+
+impl AlumniSigner {
+    /// 创建新的令牌签发器
+    ///
+    /// ### 参数
+    /// - `key`: HMAC 密钥
+    /// - `validity`: 令牌有效期
+    pub fn new(key: Vec<u8>, validity: Duration) -> Self {
+        Self { key, validity }
+    }
+
+    /// 计算载荷的签名
+    fn sign(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC 可接受任意长度密钥");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// 签发新令牌
+    ///
+    /// ### 参数
+    /// - `subject`: 令牌标识（观众的会话 ID，或观众出示的有效令牌中的标识）
+    pub fn issue(&self, subject: &str) -> String {
+        let expires_at = (Utc::now() + self.validity).timestamp();
+        let payload = format!("{}.{}", subject, expires_at);
+        let signature = self.sign(&payload).finalize().into_bytes();
+        let hex: String = signature.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}.{}", payload, hex)
+    }
+
+    /// 校验令牌签名及有效期
+    pub fn verify(&self, token: &str) -> bool {
+        self.subject(token).is_some()
+    }
+
+    /// 校验令牌并取出令牌标识
+    ///
+    /// ### 返回值
+    /// 令牌有效时返回 (标识, 过期时间)，标识可用于在服务端关联该回访观众的数据
+    pub fn subject<'a>(&self, token: &'a str) -> Option<(&'a str, DateTime<Utc>)> {
+        let (payload, hex) = token.rsplit_once('.')?;
+        // 会话 ID 可能含有 `.`，从右侧拆分
+        let (subject, expires_at) = payload.rsplit_once('.')?;
+        let expires_at = DateTime::from_timestamp(expires_at.parse::<i64>().ok()?, 0)?;
+        if Utc::now() > expires_at || subject.is_empty() || hex.len() % 2 != 0 {
+            return None;
+        }
+        let signature = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        // verify_slice 使用常量时间比较
+        self.sign(payload).verify_slice(&signature).ok()?;
+        Some((subject, expires_at))
+    }
+}
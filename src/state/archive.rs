@@ -0,0 +1,199 @@
+//! # 归档浏览模块
+//!
+//! 列出转储目录中过往直播场次的聊天记录转储（`live-<场次>-<时间>-<序号>.json`，
+//! 含 `dump-merge` 输出的 `.merged.json` 和 `dump-migrate` 输出的 `.migrated.json`）和用户消息报告（`reports/report-<场次>-<UID>-<时间>.json|html`），
+//! 并按文件名读取单个文件，主播无需服务器文件系统权限即可查阅历史数据。
+//!
+//! 只读取文件名符合上述格式的文件，文件名中不允许出现路径分隔符。
+
+use crate::domain::Uid;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 报告文件所在的子目录
+const REPORTS_DIR: &str = "reports";
+
+/// 可读取的单个归档文件大小上限（字节）
+pub const MAX_ARCHIVE_FILE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 归档文件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    /// 聊天记录转储
+    Transcript,
+    /// 用户消息报告
+    Report,
+}
+
+impl ArchiveKind {
+    /// 该类型文件所在的目录
+    fn dir(self, dump_path: &Path) -> PathBuf {
+        match self {
+            Self::Transcript => dump_path.to_path_buf(),
+            Self::Report => dump_path.join(REPORTS_DIR),
+        }
+    }
+
+    /// 解析文件名
+    ///
+    /// ### 返回值
+    /// 文件名符合该类型的格式时返回 (场次标识, 报告对象 UID)
+    fn parse(self, name: &str) -> Option<(String, Option<Uid>)> {
+        if !is_archive_name(name) {
+            return None;
+        }
+        match self {
+            Self::Transcript => {
+                let rest = name.strip_prefix("live-")?.strip_suffix(".json")?;
+                let rest = rest
+                    .strip_suffix(".merged")
+                    .or_else(|| rest.strip_suffix(".migrated"))
+                    .unwrap_or(rest);
+                // <场次>-<时间>-<序号>
+                let mut parts = rest.rsplitn(3, '-');
+                let seq = parts.next()?;
+                let _stamp = parts.next()?;
+                let stem = parts.next()?;
+                seq.parse::<u64>().ok()?;
+                Some((stem.to_string(), None))
+            }
+            Self::Report => {
+                let rest = name.strip_prefix("report-")?;
+                let rest = rest.strip_suffix(".json").or_else(|| rest.strip_suffix(".html"))?;
+                // <场次>-<UID>-<日期>-<时间>
+                let mut parts = rest.rsplitn(4, '-');
+                let _time = parts.next()?;
+                let _date = parts.next()?;
+                let uid = parts.next()?.parse::<Uid>().ok()?;
+                let stem = parts.next()?;
+                Some((stem.to_string(), Some(uid)))
+            }
+        }
+    }
+}
+
+/// 归档中的单个文件
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveFile {
+    /// 文件名（读取时使用）
+    pub name: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 最后修改时间
+    pub modified: Option<DateTime<Utc>>,
+    /// 报告针对的用户 UID（仅报告）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<Uid>,
+}
+
+/// 一个直播场次的归档
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveSession {
+    /// 场次标识（直播场次 ID，离线大厅为房间 ID）
+    pub session: String,
+    /// 聊天记录转储（按时间先后）
+    pub transcripts: Vec<ArchiveFile>,
+    /// 用户消息报告（按时间先后）
+    pub reports: Vec<ArchiveFile>,
+    /// 最近一个文件的修改时间
+    pub last_modified: Option<DateTime<Utc>>,
+    /// 全部文件的总大小（字节）
+    pub total_bytes: u64,
+}
+
+/// 列出转储目录中的归档，按场次分组（最近的场次在前）
+///
+/// ### 返回值
+/// - `Ok(sessions)`: 转储目录不存在时为空列表
+/// - `Err(msg)`: 读取目录失败
+pub fn list(dump_path: &Path) -> Result<Vec<ArchiveSession>, String> {
+    let mut sessions: BTreeMap<String, ArchiveSession> = BTreeMap::new();
+    for kind in [ArchiveKind::Transcript, ArchiveKind::Report] {
+        let dir = kind.dir(dump_path);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("读取目录 {} 失败: {}", dir.display(), e)),
+        };
+        for entry in entries.flatten() {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let Some((stem, uid)) = kind.parse(&name) else {
+                continue;
+            };
+            let Some(meta) = entry.metadata().ok().filter(|m| m.is_file()) else {
+                continue;
+            };
+            let file = ArchiveFile {
+                name,
+                size: meta.len(),
+                modified: meta.modified().ok().map(DateTime::<Utc>::from),
+                uid,
+            };
+            let session = sessions.entry(stem.clone()).or_insert_with(|| ArchiveSession {
+                session: stem,
+                transcripts: Vec::new(),
+                reports: Vec::new(),
+                last_modified: None,
+                total_bytes: 0,
+            });
+            session.total_bytes += file.size;
+            session.last_modified = session.last_modified.max(file.modified);
+            match kind {
+                ArchiveKind::Transcript => session.transcripts.push(file),
+                ArchiveKind::Report => session.reports.push(file),
+            }
+        }
+    }
+
+    let mut sessions: Vec<ArchiveSession> = sessions.into_values().collect();
+    for session in &mut sessions {
+        session.transcripts.sort_by(|a, b| (a.modified, &a.name).cmp(&(b.modified, &b.name)));
+        session.reports.sort_by(|a, b| (a.modified, &a.name).cmp(&(b.modified, &b.name)));
+    }
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.last_modified));
+    Ok(sessions)
+}
+
+/// 读取单个归档文件
+///
+/// ### 参数
+/// - `dump_path`: 转储目录
+/// - `kind`: 文件类型
+/// - `name`: 文件名（由 `list` 返回）
+///
+/// ### 返回值
+/// - `Ok(Some(content))`: 文件内容
+/// - `Ok(None)`: 文件名格式不符或文件不存在
+/// - `Err(msg)`: 文件过大或读取失败
+pub fn read(dump_path: &Path, kind: ArchiveKind, name: &str) -> Result<Option<String>, String> {
+    if kind.parse(name).is_none() {
+        return Ok(None);
+    }
+    let path = kind.dir(dump_path).join(name);
+    let meta = match fs::metadata(&path) {
+        Ok(meta) if meta.is_file() => meta,
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("读取 {} 失败: {}", path.display(), e)),
+    };
+    if meta.len() > MAX_ARCHIVE_FILE_BYTES {
+        return Err(format!("{} 超过 {} MiB，请直接从服务器获取", name, MAX_ARCHIVE_FILE_BYTES / 1024 / 1024));
+    }
+    fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| format!("读取 {} 失败: {}", path.display(), e))
+}
+
+/// 文件名是否只包含安全字符（字母、数字、`-_.`，且不以 `.` 开头）
+fn is_archive_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
@@ -9,7 +9,7 @@
 //! - 角色/游戏问题（58%）：最常见，询问角色或游戏名称
 //! - 内容问题（10%）：询问公告内容
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -86,7 +86,7 @@ where
 }
 
 /// 单个卡池/公告条目
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Banner {
     /// 卡池序号（支持字符串格式的数字如 "外·337"）
     #[serde(deserialize_with = "deserialize_index")]
@@ -99,6 +99,15 @@ pub struct Banner {
     pub character: Option<String>,
     /// 公告列表
     pub announces: Vec<BannerAnnounce>,
+    /// 是否从出题中排除（用于停用有误或剧透的条目而不删除数据）
+    #[serde(default)]
+    pub exclude: bool,
+    /// 维护用标签（可选）
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 维护备注（可选）
+    #[serde(default)]
+    pub notes: Option<String>,
 }
 
 /// 反序列化索引字段
@@ -140,12 +149,143 @@ where
     }
 }
 
+/// 题目类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionKind {
+    /// 日期问题
+    Date,
+    /// 持续时间问题
+    Life,
+    /// 发布者问题
+    Publisher,
+    /// 角色/游戏问题
+    CharacterGame,
+    /// 内容问题
+    Content,
+}
+
+impl QuestionKind {
+    /// 将题目类型转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Life => "life",
+            Self::Publisher => "publisher",
+            Self::CharacterGame => "character",
+            Self::Content => "content",
+        }
+    }
+
+    /// 从字符串解析题目类型
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "date" => Some(Self::Date),
+            "life" => Some(Self::Life),
+            "publisher" => Some(Self::Publisher),
+            "character" => Some(Self::CharacterGame),
+            "content" => Some(Self::Content),
+            _ => None,
+        }
+    }
+
+    /// 该类型题目的答案形式
+    pub fn answer_kind(&self) -> AnswerKind {
+        match self {
+            // 持续时间作答时可以不带单位
+            Self::Date | Self::Life => AnswerKind::Number,
+            Self::Publisher | Self::CharacterGame | Self::Content => AnswerKind::Text,
+        }
+    }
+}
+
+/// 答案形式（供前端选择输入控件）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerKind {
+    /// 数字（年份、月份、时长等）
+    Number,
+    /// 自由文本
+    Text,
+    /// 从给定选项中选择
+    Choice,
+    /// 点击确认（提交响应中的 `confirm_token`）
+    Confirm,
+}
+
+impl AnswerKind {
+    /// 根据答案文本推断答案形式
+    pub fn infer(answer: &str) -> Self {
+        if answer.trim().parse::<f64>().is_ok() {
+            Self::Number
+        } else {
+            Self::Text
+        }
+    }
+}
+
+/// 题目元数据
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuestionMeta {
+    /// 题目类型（卡池题库为 `QuestionKind::as_str`，模板题库由模板指定）
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// 答案形式
+    pub answer_kind: AnswerKind,
+    /// 可选项（仅 `choice` 形式）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub choices: Option<Vec<String>>,
+}
+
+impl QuestionMeta {
+    /// 卡池题库题目的元数据
+    pub fn of_kind(kind: QuestionKind) -> Self {
+        Self {
+            kind: kind.as_str().to_string(),
+            answer_kind: kind.answer_kind(),
+            choices: None,
+        }
+    }
+}
+
+/// 题库校验报告
+#[derive(Debug, Clone, Serialize)]
+pub struct BannerReport {
+    /// 条目总数（不含索引 0 的占位条目）
+    pub total: usize,
+    /// 标记为排除的条目数
+    pub excluded: usize,
+    /// 没有任何公告的条目数
+    pub empty: usize,
+    /// 可用于出题的条目数
+    pub eligible: usize,
+    /// 宽松解析时跳过的格式错误条目数
+    pub skipped: usize,
+    /// 宽松解析时记录的警告（每个跳过的条目一条）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// 题库解析策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BannerParsePolicy {
+    /// 是否跳过格式错误的条目（否则任一条目错误都会导致整个题库加载失败）
+    pub lenient: bool,
+    /// 宽松解析时至少需要的可出题条目数，不足时加载失败
+    pub min_questions: usize,
+}
+
 /// 题库数据库
 ///
 /// 从 JSON 文件加载卡池数据，生成随机问题
 pub struct BannerDatabase {
     /// 卡池列表
     banners: Vec<Banner>,
+    /// 可用于出题的卡池索引（跳过索引 0、被排除的和没有公告的条目）
+    eligible: Vec<usize>,
+    /// 内容问题"第 N 个字"中 N 的上限
+    content_char_limit: usize,
+    /// 宽松解析时跳过的条目的警告
+    warnings: Vec<String>,
 }
 
 /// 问题-答案对
@@ -155,6 +295,35 @@ pub struct BannerQuestion {
     pub question: String,
     /// 答案文本
     pub answer: String,
+    /// 生成答案所依据的原始内容片段（供题库维护者核对）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 题目元数据（题库为空时的占位题目没有类型）
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<QuestionMeta>,
+}
+
+impl BannerQuestion {
+    /// 为指定类型的问题-答案对创建题目
+    fn of_kind(kind: QuestionKind, (question, answer): (String, String)) -> Self {
+        Self {
+            question,
+            answer,
+            source: None,
+            meta: Some(QuestionMeta::of_kind(kind)),
+        }
+    }
+}
+
+impl From<(String, String)> for BannerQuestion {
+    fn from((question, answer): (String, String)) -> Self {
+        Self {
+            question,
+            answer,
+            source: None,
+            meta: None,
+        }
+    }
 }
 
 impl BannerDatabase {
@@ -170,20 +339,122 @@ impl BannerDatabase {
     ///     "index": 337,
     ///     "game": "原神",
     ///     "character": "胡桃",
-    ///     "announces": [...]
+    ///     "announces": [...],
+    ///     "exclude": false,
+    ///     "tags": ["剧透"],
+    ///     "notes": "可选备注"
     ///   }
     /// ]
     /// ```
+    ///
+    /// `exclude`、`tags`、`notes` 均为可选字段，`exclude: true` 的条目不参与出题
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load(path, BannerParsePolicy::default())
+    }
+
+    /// 按解析策略从 JSON 文件创建题库
+    ///
+    /// ### 参数
+    /// - `path`: JSON 文件路径，格式同 `new`
+    /// - `policy`: 解析策略
+    pub fn load<P: AsRef<Path>>(path: P, policy: BannerParsePolicy) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
-        let banners: Vec<Banner> = serde_json::from_str(&content)?;
-        Ok(Self { banners })
+        Ok(Self::parse(&content, policy)?)
     }
 
-    /// 获取随机问题-答案对
+    /// 从 JSON 文本创建题库
+    ///
+    /// ### 参数
+    /// - `content`: JSON 文本，格式同 `new`
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        let banners: Vec<Banner> = serde_json::from_str(content)?;
+        Ok(Self::from_banners(banners, Vec::new()))
+    }
+
+    /// 按解析策略从 JSON 文本创建题库
+    ///
+    /// 宽松解析时格式错误的条目被跳过并记录警告（第一个条目是占位条目，格式错误时以空条目代替），
+    /// 可出题条目少于 `min_questions` 时返回错误
+    ///
+    /// ### 参数
+    /// - `content`: JSON 文本，格式同 `new`
+    /// - `policy`: 解析策略
     ///
     /// ### 返回值
-    /// 返回 (问题, 答案) 元组
+    /// - `Ok(db)`: 题库
+    /// - `Err(msg)`: JSON 格式错误、严格模式下条目格式错误或可出题条目不足
+    pub fn parse(content: &str, policy: BannerParsePolicy) -> Result<Self, String> {
+        if !policy.lenient {
+            return Self::from_json(content).map_err(|e| e.to_string());
+        }
+        let entries: Vec<serde_json::Value> = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let mut banners = Vec::with_capacity(entries.len());
+        let mut warnings = Vec::new();
+        for (position, entry) in entries.into_iter().enumerate() {
+            let index = entry.get("index").cloned();
+            match serde_json::from_value::<Banner>(entry) {
+                Ok(banner) => banners.push(banner),
+                Err(e) => {
+                    warnings.push(match index {
+                        Some(index) => format!("第 {} 个条目（index={}）格式错误，已跳过: {}", position, index, e),
+                        None => format!("第 {} 个条目格式错误，已跳过: {}", position, e),
+                    });
+                    if position == 0 {
+                        banners.push(Banner::default());
+                    }
+                }
+            }
+        }
+        let db = Self::from_banners(banners, warnings);
+        if db.eligible.len() < policy.min_questions {
+            return Err(format!(
+                "可出题条目只有 {} 条，少于要求的 {} 条（跳过了 {} 个格式错误的条目）",
+                db.eligible.len(),
+                policy.min_questions,
+                db.warnings.len()
+            ));
+        }
+        Ok(db)
+    }
+
+    /// 由已解析的条目创建题库
+    fn from_banners(banners: Vec<Banner>, warnings: Vec<String>) -> Self {
+        let eligible = (1..banners.len())
+            .filter(|&i| !banners[i].exclude && !banners[i].announces.is_empty())
+            .collect();
+        Self {
+            banners,
+            eligible,
+            content_char_limit: 20,
+            warnings,
+        }
+    }
+
+    /// 设置内容问题"第 N 个字"中 N 的上限（链式调用）
+    pub fn with_content_char_limit(mut self, limit: usize) -> Self {
+        self.content_char_limit = limit.max(1);
+        self
+    }
+
+    /// 可出题条目数量
+    pub fn pool_size(&self) -> usize {
+        self.eligible.len()
+    }
+
+    /// 生成题库校验报告
+    pub fn report(&self) -> BannerReport {
+        let entries = self.banners.iter().skip(1);
+        BannerReport {
+            total: self.banners.len().saturating_sub(1),
+            excluded: entries.clone().filter(|b| b.exclude).count(),
+            empty: entries.filter(|b| b.announces.is_empty()).count(),
+            eligible: self.eligible.len(),
+            skipped: self.warnings.len(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// 获取随机题目
     ///
     /// ### 题目类型分布
     /// - 0-15: 日期问题（15%）
@@ -191,40 +462,136 @@ impl BannerDatabase {
     /// - 30-32: 发布者问题（2%）
     /// - 32-90: 角色/游戏问题（58%）
     /// - 90-100: 内容问题（10%）
-    pub fn random_question(&self) -> (String, String) {
-        if self.banners.is_empty() {
-            return (
+    pub fn random_question(&self) -> BannerQuestion {
+        self.random_question_excluding(&[])
+    }
+
+    /// 获取随机题目，尽量避开指定的题目
+    ///
+    /// ### 参数
+    /// - `exclude`: 需要避开的题目文本
+    ///
+    /// ### 注意事项
+    /// 最多重新抽取 16 次，题库过小时仍可能返回被排除的题目
+    pub fn random_question_excluding(&self, exclude: &[String]) -> BannerQuestion {
+        let mut qa = self.draw_question();
+        for _ in 0..16 {
+            if !exclude.contains(&qa.question) {
+                break;
+            }
+            qa = self.draw_question();
+        }
+        qa
+    }
+
+    /// 抽取一道随机题目
+    fn draw_question(&self) -> BannerQuestion {
+        if self.eligible.is_empty() {
+            return BannerQuestion::from((
                 "No questions available".to_string(),
                 "N/A".to_string(),
-            );
+            ));
         }
 
+        // 随机选择一个可用卡池（索引 0 可能是占位符，已在 eligible 中跳过）
+        let idx = self.eligible[rand::thread_rng().gen_range(0..self.eligible.len())];
+        self.question_for_banner(idx)
+    }
+
+    /// 从卡池子集中获取随机题目，尽量避开指定的题目
+    ///
+    /// ### 参数
+    /// - `seed`: 子集种子，相同种子得到相同的卡池子集
+    /// - `subset_size`: 子集大小
+    /// - `exclude`: 需要避开的题目文本
+    ///
+    /// ### 使用场景
+    /// 同一网段的观众在同一轮换周期内只会抽到同一子集中的题目
+    pub fn random_question_in_subset(
+        &self,
+        seed: u64,
+        subset_size: usize,
+        exclude: &[String],
+    ) -> BannerQuestion {
+        if self.eligible.is_empty() {
+            return self.random_question_excluding(exclude);
+        }
+
+        // 由种子确定卡池子集（仅从可用卡池中选取）
+        let mut seeded = rand::rngs::StdRng::seed_from_u64(seed);
+        let pool = self.eligible.len();
+        let subset: Vec<usize> = rand::seq::index::sample(&mut seeded, pool, subset_size.clamp(1, pool))
+            .into_iter()
+            .map(|i| self.eligible[i])
+            .collect();
+
         let mut rng = rand::thread_rng();
+        let mut qa = self.question_for_banner(subset[rng.gen_range(0..subset.len())]);
+        for _ in 0..16 {
+            if !exclude.contains(&qa.question) {
+                break;
+            }
+            qa = self.question_for_banner(subset[rng.gen_range(0..subset.len())]);
+        }
+        qa
+    }
 
-        // 加权随机选择题目类型（总和 100）
-        let question_type = rng.gen_range(0..100);
+    /// 生成指定类型的样例题目（不影响任何客户端状态）
+    ///
+    /// ### 参数
+    /// - `kind`: 题目类型
+    /// - `count`: 样例数量
+    ///
+    /// ### 使用场景
+    /// 供题库维护者检查题目措辞和答案提取是否正确
+    pub fn sample_questions(&self, kind: QuestionKind, count: usize) -> Vec<BannerQuestion> {
+        if self.eligible.is_empty() {
+            return Vec::new();
+        }
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|_| {
+                let idx = self.eligible[rng.gen_range(0..self.eligible.len())];
+                self.question_of_kind(kind, idx)
+            })
+            .collect()
+    }
 
-        // 随机选择一个卡池（跳过索引 0，因为可能是占位符）
-        let idx = rng.gen_range(1..self.banners.len()).max(1);
+    /// 针对指定卡池生成指定类型的题目
+    fn question_of_kind(&self, kind: QuestionKind, idx: usize) -> BannerQuestion {
+        match kind {
+            QuestionKind::Date => self.date_question(idx),
+            QuestionKind::Life => self.life_question(idx),
+            QuestionKind::Publisher => self.publisher_question(idx),
+            QuestionKind::CharacterGame => self.character_game_question(idx),
+            QuestionKind::Content => self.content_question(idx),
+        }
+    }
+
+    /// 针对指定卡池按权重生成一道题目
+    fn question_for_banner(&self, idx: usize) -> BannerQuestion {
+        // 加权随机选择题目类型（总和 100）
+        let question_type = rand::thread_rng().gen_range(0..100);
 
         // 根据权重分发到不同的问题生成函数
-        if question_type < 15 {
-            self.date_question(idx)
+        let kind = if question_type < 15 {
+            QuestionKind::Date
         } else if question_type < 30 {
-            self.life_question(idx)
+            QuestionKind::Life
         } else if question_type < 32 {
-            self.publisher_question(idx)
+            QuestionKind::Publisher
         } else if question_type < 90 {
-            self.character_game_question(idx)
+            QuestionKind::CharacterGame
         } else {
-            self.content_question(idx)
-        }
+            QuestionKind::Content
+        };
+        self.question_of_kind(kind, idx)
     }
 
     /// 生成日期问题（15%）
     ///
     /// 询问卡池的发布时间（年/月/日/时）
-    fn date_question(&self, idx: usize) -> (String, String) {
+    fn date_question(&self, idx: usize) -> BannerQuestion {
         let banner = &self.banners[idx];
         let mut rng = rand::thread_rng();
 
@@ -246,75 +613,60 @@ impl BannerDatabase {
                 ("tm_mday", dt.day() as i32, "是该月几号发布的?"),
                 ("tm_hour", dt.hour() as i32, "是当天几点发布的(精确到小时)?"),
             ];
-            let (key, val, suffix) = keys[rng.gen_range(0..keys.len())];
+            let (_key, val, _suffix) = keys[rng.gen_range(0..keys.len())];
             (format!("的第{}篇公告", announce.revision.unwrap_or(1)), val.to_string())
         } else {
             // 解析失败，使用默认值
             ("发布".to_string(), "2024".to_string())
         };
 
-        if banner.announces.len() == 1 {
-            (format!("{}期公告娘{}", banner.index, suffix), answer)
-        } else {
-            (
-                format!("{}期公告娘{}", banner.index, suffix),
-                answer,
-            )
-        }
+        let question = format!("{}期公告娘{}", banner.index, suffix);
+        BannerQuestion::of_kind(QuestionKind::Date, (question, answer))
     }
 
     /// 生成持续时间问题（15%）
     ///
-    /// 询问卡池或公告的持续时间
-    fn life_question(&self, idx: usize) -> (String, String) {
+    /// 询问卡池或公告的持续时间，按数据中记录的单位提问。
+    /// 答案保存为"数值+单位"（如 "30分钟"），作答时带不带单位均可。
+    /// 持续时间缺失或无法解析时，回退到角色/游戏问题。
+    fn life_question(&self, idx: usize) -> BannerQuestion {
         let banner = &self.banners[idx];
         let mut rng = rand::thread_rng();
 
         // 随机选择：卡池持续时间 或 公告持续时间
-        let (answer, suffix) = if banner.announces.len() == 1 || rng.gen_range(0..2) == 0 {
+        let (life, suffix) = if banner.announces.len() == 1 || rng.gen_range(0..2) == 0 {
             // 卡池持续时间
-            if let Some(ref life) = banner.announces[0].banner_life {
-                (life.clone(), String::new())
-            } else {
-                ("7天".to_string(), String::new())
-            }
+            (banner.announces[0].banner_life.as_deref(), String::new())
         } else {
             // 公告持续时间
             let announce_idx = rng.gen_range(0..banner.announces.len());
             let announce = &banner.announces[announce_idx];
-            if let Some(ref life) = announce.announce_life {
-                (
-                    life.clone(),
-                    format!("的第{}篇公告", announce.revision.unwrap_or(1)),
-                )
-            } else {
-                (
-                    "7天".to_string(),
-                    format!("的第{}篇公告", announce.revision.unwrap_or(1)),
-                )
-            }
+            (
+                announce.announce_life.as_deref(),
+                format!("的第{}篇公告", announce.revision.unwrap_or(1)),
+            )
         };
 
-        // 提取数值部分（去掉 "天"、"小时" 等单位）
-        let unit = if answer.contains("天") { "天" } else { "小时" };
-        let numeric_answer = answer
-            .chars()
-            .take_while(|c| c.is_ascii_digit() || *c == '.')
-            .collect::<String>();
+        let Some((value, unit)) = life.and_then(parse_duration) else {
+            return self.character_game_question(idx);
+        };
 
-        (
-            format!("{}期公告娘{}持续了几{}?", banner.index, suffix, unit),
-            numeric_answer,
+        BannerQuestion::of_kind(
+            QuestionKind::Life,
+            (
+                format!("{}期公告娘{}持续了几{}?", banner.index, suffix, unit),
+                format!("{}{}", value, unit),
+            ),
         )
     }
 
     /// 生成发布者问题（2%）
     ///
     /// 询问谁上传了该卡池
-    fn publisher_question(&self, idx: usize) -> (String, String) {
+    fn publisher_question(&self, idx: usize) -> BannerQuestion {
         let banner = &self.banners[idx];
 
-        if banner.announces.len() == 1 {
+        let qa = if banner.announces.len() == 1 {
             (
                 format!("{}期公告娘是谁上传的?", banner.index),
                 banner.announces[0].publisher.clone(),
@@ -331,13 +683,14 @@ impl BannerDatabase {
                 ),
                 announce.publisher.clone(),
             )
-        }
+        };
+        BannerQuestion::of_kind(QuestionKind::Publisher, qa)
     }
 
     /// 生成角色/游戏问题（58%）
     ///
     /// 最常见的问题类型，询问角色对应的游戏或游戏对应的角色
-    fn character_game_question(&self, idx: usize) -> (String, String) {
+    fn character_game_question(&self, idx: usize) -> BannerQuestion {
         let banner = &self.banners[idx];
         
         // 如果游戏或角色信息缺失，回退到发布者问题
@@ -351,7 +704,7 @@ impl BannerDatabase {
         let mut rng = rand::thread_rng();
         // 随机选择：问角色 还是 问游戏
         let mode = rng.gen_range(0..2);
-        if mode == 0 {
+        let qa = if mode == 0 {
             // 问：某游戏里的哪个角色？
             (
                 format!(
@@ -369,13 +722,20 @@ impl BannerDatabase {
                 ),
                 game.to_string(),
             )
-        }
+        };
+        BannerQuestion::of_kind(QuestionKind::CharacterGame, qa)
     }
 
     /// 生成内容问题（10%）
     ///
-    /// 询问公告内容（首行或第 N 个中文字符）
-    fn content_question(&self, idx: usize) -> (String, String) {
+    /// 询问公告内容（首个非标点片段或第 N 个汉字）
+    ///
+    /// ### 答案提取规则
+    /// - 半角/全角空白均视为分隔符
+    /// - 忽略只由标点组成的片段，并去除片段首尾的标点
+    /// - 第 N 个字只统计汉字（不含标点），N 不超过 `content_char_limit`
+    /// - 内容中没有可用文本时，回退到发布者问题
+    fn content_question(&self, idx: usize) -> BannerQuestion {
         let banner = &self.banners[idx];
         let mut rng = rand::thread_rng();
 
@@ -389,47 +749,130 @@ impl BannerDatabase {
             String::new()
         };
 
-        // 随机选择：首行问题 或 第 N 个字问题
+        // 随机选择：首个片段问题 或 第 N 个字问题
         let mode = rng.gen_range(0..10);
 
         if mode > 1 {
-            // 首行问题（约 90% 概率）
-            let answer = announce
+            // 首个片段问题（约 80% 概率）
+            let Some((raw, answer)) = announce
                 .content
-                .split_whitespace()
-                .next()
-                .unwrap_or("")
-                .to_string();
-            (
-                format!("{}期公告娘{}的内容的第一个换行或空格之前的内容是什么？", banner.index, suffix),
-                answer,
-            )
+                .split(|c: char| c.is_whitespace())
+                .map(|raw| (raw, raw.trim_matches(is_punctuation)))
+                .find(|(_, token)| !token.is_empty())
+            else {
+                return self.publisher_question(idx);
+            };
+            BannerQuestion {
+                question: format!(
+                    "{}期公告娘{}的内容的第一个换行或空格之前的内容是什么(忽略首尾标点符号)？",
+                    banner.index, suffix
+                ),
+                answer: answer.to_string(),
+                source: Some(raw.to_string()),
+                meta: Some(QuestionMeta::of_kind(QuestionKind::Content)),
+            }
         } else {
-            // 第 N 个中文字符问题（约 10% 概率）
-            let chinese_chars: Vec<char> = announce
+            // 第 N 个汉字问题（约 20% 概率）
+            let hanzi: Vec<(usize, char)> = announce
                 .content
-                .chars()
-                .filter(|c| is_chinese(*c))
+                .char_indices()
+                .filter(|(_, c)| is_chinese(*c) && !is_punctuation(*c))
+                .take(self.content_char_limit)
                 .collect();
 
-            if chinese_chars.is_empty() {
-                (
-                    format!("{}期公告娘{}的内容是什么？", banner.index, suffix),
-                    announce.content.clone(),
-                )
-            } else {
-                let ch_idx = rng.gen_range(0..chinese_chars.len());
-                (
-                    format!(
-                        "{}期公告娘{}的内容的第{}个字是什么(不包含英文字符和半角标点符号)？",
-                        banner.index,
-                        suffix,
-                        ch_idx + 1
-                    ),
-                    chinese_chars[ch_idx].to_string(),
-                )
+            if hanzi.is_empty() {
+                return self.publisher_question(idx);
             }
+
+            let ch_idx = rng.gen_range(0..hanzi.len());
+            let (byte_pos, ch) = hanzi[ch_idx];
+            BannerQuestion {
+                question: format!(
+                    "{}期公告娘{}的内容的第{}个字是什么(不包含英文字符和标点符号)？",
+                    banner.index,
+                    suffix,
+                    ch_idx + 1
+                ),
+                answer: ch.to_string(),
+                source: Some(announce.content[..byte_pos + ch.len_utf8()].to_string()),
+                meta: Some(QuestionMeta::of_kind(QuestionKind::Content)),
+            }
+        }
+    }
+}
+
+/// 持续时间单位：(规范化单位, 可识别的写法)
+const DURATION_UNITS: [(&str, &[&str]); 7] = [
+    ("个月", &["个月", "月"]),
+    ("小时", &["小时", "个小时", "钟头", "时", "h"]),
+    ("分钟", &["分钟", "分", "min"]),
+    ("秒", &["秒钟", "秒", "s"]),
+    ("天", &["天", "日", "d"]),
+    ("周", &["星期", "周", "礼拜"]),
+    ("年", &["年"]),
+];
+
+/// 将持续时间解析为规范化的 (数值, 单位)
+///
+/// ### 示例
+/// - "7天" -> ("7", "天")
+/// - "1个月" -> ("1", "个月")
+/// - "30 分钟" -> ("30", "分钟")
+/// - "1.5h" -> ("1.5", "小时")
+/// - "很久" -> None
+pub fn parse_duration(s: &str) -> Option<(String, &'static str)> {
+    let s = s.trim();
+    let value: String = s
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    if value.is_empty() || value.parse::<f64>().is_err() {
+        return None;
+    }
+    let rest = s[value.len()..].trim().to_lowercase();
+    let unit = DURATION_UNITS
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&rest.as_str()))
+        .map(|(unit, _)| *unit)?;
+    // 规范化数值写法（如 "07" -> "7"，"1.50" -> "1.5"）
+    let value = value.parse::<f64>().ok()?.to_string();
+    Some((value, unit))
+}
+
+/// 判断作答是否与正确答案一致
+///
+/// 对持续时间类答案，数值相同且单位一致（或省略单位）即视为正确
+pub fn answer_matches(expected: &str, given: &str) -> bool {
+    let given = given.trim();
+    if given == expected {
+        return true;
+    }
+    match parse_duration(expected) {
+        Some((value, unit)) => {
+            given.parse::<f64>().ok().map(|v| v.to_string()) == Some(value.clone())
+                || parse_duration(given) == Some((value, unit))
         }
+        None => false,
+    }
+}
+
+/// 判断字符是否为标点符号
+///
+/// ### 检测范围
+/// - ASCII 标点
+/// - 通用标点（U+2000 - U+206F）
+/// - CJK 符号和标点（U+3000 - U+303F）
+/// - 全角 ASCII 标点及半角片假名标点
+fn is_punctuation(c: char) -> bool {
+    match c {
+        _ if c.is_ascii_punctuation() => true,
+        '\u{2000}'..='\u{206F}' => true, // 通用标点
+        '\u{3000}'..='\u{303F}' => true, // CJK 符号和标点
+        '\u{FF01}'..='\u{FF0F}' => true, // 全角 ！＂＃...／
+        '\u{FF1A}'..='\u{FF20}' => true, // 全角 ：；＜...＠
+        '\u{FF3B}'..='\u{FF40}' => true, // 全角 ［＼］...｀
+        '\u{FF5B}'..='\u{FF65}' => true, // 全角 ｛｜｝～ 及半角标点
+        _ => false,
     }
 }
 
@@ -0,0 +1,212 @@
+//! # 远程题库模块
+//!
+//! 支持从 HTTP(S) 地址拉取题库，并定时刷新：
+//! - 使用 ETag 缓存，题库未变化时服务端返回 304，不重复解析
+//! - 拉取成功后写入本地缓存文件（即 `banner_db_path`）
+//! - 拉取或解析失败时继续使用上一份可用的题库
+
+use super::banner::{BannerDatabase, BannerParsePolicy};
+use super::health::Health;
+use super::notify::{Alert, Notifier};
+use parking_lot::{Mutex, RwLock};
+use reqwest::{header, StatusCode};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 后台任务名称（用于心跳上报）
+pub const TASK_NAME: &str = "banner_refresh";
+
+/// 建立到题库地址连接的超时
+const FETCH_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 拉取题库的总超时（启动时在监听端口之前拉取，不能无限等待）
+const FETCH_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 题库响应体大小上限（字节）
+const MAX_BANNER_BYTES: usize = 16 * 1024 * 1024;
+
+/// 可热替换的题库
+///
+/// 处理器每次出题时通过 `current()` 取得当前题库的快照，
+/// 刷新任务通过 `replace()` 原子地替换为新题库
+pub struct BannerStore {
+    /// 当前题库
+    current: RwLock<Arc<BannerDatabase>>,
+}
+
+impl BannerStore {
+    /// 创建新的题库容器
+    pub fn new(db: BannerDatabase) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(db)),
+        }
+    }
+
+    /// 获取当前题库
+    pub fn current(&self) -> Arc<BannerDatabase> {
+        self.current.read().clone()
+    }
+
+    /// 替换当前题库
+    pub fn replace(&self, db: BannerDatabase) {
+        *self.current.write() = Arc::new(db);
+    }
+}
+
+/// 远程题库来源
+pub struct BannerSource {
+    /// 题库地址
+    url: String,
+    /// 本地缓存文件路径
+    cache_path: PathBuf,
+    /// 内容问题"第 N 个字"中 N 的上限（重新加载时沿用）
+    content_char_limit: usize,
+    /// 题库解析策略
+    parse_policy: BannerParsePolicy,
+    /// 上一次成功拉取时服务端返回的 ETag
+    etag: Mutex<Option<String>>,
+    /// HTTP 客户端
+    client: reqwest::Client,
+}
+
+impl BannerSource {
+    /// 创建远程题库来源
+    ///
+    /// ### 参数
+    /// - `url`: 题库地址（HTTP 或 HTTPS）
+    /// - `cache_path`: 本地缓存文件路径
+    /// - `content_char_limit`: 内容问题"第 N 个字"中 N 的上限
+    pub fn new(url: String, cache_path: PathBuf, content_char_limit: usize) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(FETCH_CONNECT_TIMEOUT)
+            .timeout(FETCH_REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            url,
+            cache_path,
+            content_char_limit,
+            parse_policy: BannerParsePolicy::default(),
+            etag: Mutex::new(None),
+            client,
+        }
+    }
+
+    /// 设置题库解析策略（链式调用）
+    pub fn with_parse_policy(mut self, policy: BannerParsePolicy) -> Self {
+        self.parse_policy = policy;
+        self
+    }
+
+    /// 拉取一次远程题库
+    ///
+    /// ### 返回值
+    /// - `Ok(true)`: 题库已更新
+    /// - `Ok(false)`: 题库未变化（304）
+    /// - `Err(msg)`: 拉取或解析失败，当前题库保持不变
+    pub async fn refresh(&self, store: &BannerStore) -> Result<bool, String> {
+        let mut request = self.client.get(&self.url);
+        if let Some(etag) = self.etag.lock().clone() {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        let mut resp = request
+            .send()
+            .await
+            .map_err(|e| format!("GET {} 失败: {}", self.url, e))?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(false);
+        }
+        if !resp.status().is_success() {
+            return Err(format!("GET {} 返回 {}", self.url, resp.status()));
+        }
+
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        if resp.content_length().is_some_and(|len| len > MAX_BANNER_BYTES as u64) {
+            return Err(format!("{} 的题库超过 {} 字节", self.url, MAX_BANNER_BYTES));
+        }
+        // 逐块读取，服务端未声明长度时也不会超出上限
+        let mut bytes = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| format!("读取 {} 响应失败: {}", self.url, e))?
+        {
+            if bytes.len() + chunk.len() > MAX_BANNER_BYTES {
+                return Err(format!("{} 的题库超过 {} 字节", self.url, MAX_BANNER_BYTES));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let body = String::from_utf8(bytes).map_err(|e| format!("{} 的题库不是合法的 UTF-8: {}", self.url, e))?;
+
+        let db = BannerDatabase::parse(&body, self.parse_policy)
+            .map_err(|e| format!("解析 {} 题库失败: {}", self.url, e))?
+            .with_content_char_limit(self.content_char_limit);
+
+        // 缓存写入失败不影响使用新题库，仅记录日志
+        if let Err(e) = tokio::fs::write(&self.cache_path, &body).await {
+            tracing::warn!("写入题库缓存 {} 失败: {}", self.cache_path.display(), e);
+        }
+
+        store.replace(db);
+        *self.etag.lock() = etag;
+        Ok(true)
+    }
+
+    /// 启动定时刷新任务
+    ///
+    /// ### 参数
+    /// - `store`: 需要刷新的题库容器
+    /// - `interval_secs`: 刷新间隔（秒）
+    /// - `health`: 健康状态（每轮上报心跳）
+    /// - `notifier`: 邮件通知器（刷新失败时通知）
+    pub fn tick(
+        self: Arc<Self>,
+        store: Arc<BannerStore>,
+        interval_secs: u64,
+        health: Arc<Health>,
+        notifier: Option<Arc<Notifier>>,
+    ) -> JoinHandle<()> {
+        health.register(TASK_NAME, interval_secs);
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(interval_secs.max(1)));
+            // 第一次 tick 立即返回，启动时已拉取过一次，跳过
+            interval.tick().await;
+            health.beat(TASK_NAME);
+
+            loop {
+                interval.tick().await;
+                health.beat(TASK_NAME);
+                match self.refresh(&store).await {
+                    Ok(true) => {
+                        let report = store.current().report();
+                        tracing::info!(
+                            "远程题库已更新: 共 {} 条，可出题 {} 条，跳过格式错误 {} 条",
+                            report.total, report.eligible, report.skipped
+                        );
+                        for warning in &report.warnings {
+                            tracing::warn!("远程题库: {}", warning);
+                        }
+                    }
+                    Ok(false) => tracing::debug!("远程题库未变化"),
+                    Err(e) => {
+                        tracing::warn!("刷新远程题库失败，继续使用当前题库: {}", e);
+                        if let Some(notifier) = &notifier {
+                            notifier.notify(
+                                Alert::BannerRefreshFailed,
+                                format!("刷新远程题库失败，继续使用当前题库：{}", e),
+                            );
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
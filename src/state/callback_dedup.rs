@@ -0,0 +1,54 @@
+//! # SRS 回调去重模块
+//!
+//! SRS 在回调超时后会重试，网络设备也可能重放请求。按 (回调类型, `client_id`, 录制文件) 记录
+//! 短时间内处理过的回调及其结果，重复的回调直接返回上次的结果，不再改变状态。
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 去重窗口：窗口内相同的回调视为重复
+const DEDUP_WINDOW: Duration = Duration::from_secs(30);
+
+/// 近期处理过的回调
+#[derive(Debug, Default)]
+pub struct CallbackDedup {
+    /// 去重键 -> (处理时间, 是否放行)
+    recent: Mutex<HashMap<String, (Instant, bool)>>,
+}
+
+impl CallbackDedup {
+    /// 创建空的去重记录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 构造去重键
+    ///
+    /// ### 返回值
+    /// 回调未携带 `client_id` 时返回 `None`（无法去重）
+    pub fn key(action: &str, client_id: Option<&str>, file: Option<&str>) -> Option<String> {
+        let client_id = client_id.filter(|id| !id.is_empty())?;
+        Some(format!("{}:{}:{}", action, client_id, file.unwrap_or_default()))
+    }
+
+    /// 查询窗口内是否已处理过相同的回调
+    ///
+    /// ### 返回值
+    /// - `Some(allowed)`: 重复回调，上次是否放行
+    /// - `None`: 首次出现
+    pub fn seen(&self, key: &str) -> Option<bool> {
+        self.recent
+            .lock()
+            .get(key)
+            .filter(|(at, _)| at.elapsed() < DEDUP_WINDOW)
+            .map(|(_, allowed)| *allowed)
+    }
+
+    /// 记录回调的处理结果，同时清理窗口外的记录
+    pub fn record(&self, key: String, allowed: bool) {
+        let mut recent = self.recent.lock();
+        recent.retain(|_, (at, _)| at.elapsed() < DEDUP_WINDOW);
+        recent.insert(key, (Instant::now(), allowed));
+    }
+}
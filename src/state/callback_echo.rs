@@ -0,0 +1,98 @@
+//! # SRS 回调回显模块
+//!
+//! 调试新版本 SRS 或编码器的兼容问题时，把每个 SRS 回调的原始内容和处理结果
+//! 逐行写入专用文件（JSON Lines），无需改动正式的准入策略代码。
+//! 配合实验室模式（`LIVE_SERVER_SRS_CALLBACK_LAB`）可在一律放行的同时记录本应作出的决定。
+//!
+//! 回显文件包含完整的回调参数（含推流密钥），只应在调试环境中启用。
+
+use chrono::Local;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 一条回显记录
+#[derive(Debug, Serialize)]
+pub struct EchoRecord<'a> {
+    /// 回调来源地址
+    pub peer: String,
+    /// SRS 发送的原始 JSON
+    pub payload: &'a serde_json::Value,
+    /// 处理结果：`allow` / `deny`
+    pub decision: &'static str,
+    /// 拒绝原因（与 `/admin/metrics` 中的标签一致）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    /// 是否因实验室模式改为放行
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub lab_override: bool,
+    /// 处理耗时（毫秒）
+    pub elapsed_ms: f64,
+}
+
+/// SRS 回调回显器
+pub struct CallbackEcho {
+    /// 回显文件路径
+    path: PathBuf,
+    /// 以追加方式打开的回显文件
+    file: Mutex<File>,
+}
+
+impl CallbackEcho {
+    /// 打开回显文件（不存在时创建，已存在时追加；含推流密钥，权限 0600）
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建目录 {} 失败: {}", parent.display(), e))?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            options.mode(0o600);
+            // 已存在的文件不受 mode 影响，单独收紧权限
+            if path.exists() {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                    .map_err(|e| format!("设置回显文件 {} 权限失败: {}", path.display(), e))?;
+            }
+        }
+        let file = options
+            .open(path)
+            .map_err(|e| format!("打开回显文件 {} 失败: {}", path.display(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// 回显文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 追加一条记录（写入失败只输出警告，不影响回调处理）
+    pub fn record(&self, record: &EchoRecord<'_>) {
+        /// 带时间戳的一行
+        #[derive(Serialize)]
+        struct Line<'a, 'b> {
+            time: String,
+            #[serde(flatten)]
+            record: &'a EchoRecord<'b>,
+        }
+
+        let line = Line {
+            time: Local::now().to_rfc3339(),
+            record,
+        };
+        let Ok(line) = serde_json::to_string(&line) else {
+            return;
+        };
+        let mut file = self.file.lock();
+        if let Err(e) = writeln!(file, "{}", line) {
+            tracing::warn!("写入 SRS 回调回显文件 {} 失败: {}", self.path.display(), e);
+        }
+    }
+}
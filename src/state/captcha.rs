@@ -0,0 +1,75 @@
+//! # 人机验证模块
+//!
+//! 在发放题目之前校验 Cloudflare Turnstile / hCaptcha 令牌，
+//! 防止脚本批量获取题目或暴力尝试答案。
+
+use crate::config::CaptchaProvider;
+use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
+
+/// 建立到校验接口连接的超时
+const VERIFY_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 校验请求的总超时（观众的取题请求会等待该请求）
+const VERIFY_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 人机验证校验器
+pub struct CaptchaVerifier {
+    /// 服务提供方
+    provider: CaptchaProvider,
+    /// 服务端密钥
+    secret: SecretString,
+    /// HTTP 客户端
+    client: reqwest::Client,
+}
+
+impl CaptchaVerifier {
+    /// 创建人机验证校验器
+    pub fn new(provider: CaptchaProvider, secret: SecretString) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(VERIFY_CONNECT_TIMEOUT)
+            .timeout(VERIFY_REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            provider,
+            secret,
+            client,
+        }
+    }
+
+    /// 向服务提供方校验客户端提交的令牌
+    ///
+    /// ### 参数
+    /// - `token`: 前端组件生成的令牌
+    /// - `remote_ip`: 客户端 IP
+    ///
+    /// ### 返回值
+    /// 请求失败时视为校验不通过
+    pub async fn verify(&self, token: &str, remote_ip: &str) -> bool {
+        let form = [
+            ("secret", self.secret.expose_secret()),
+            ("response", token),
+            ("remoteip", remote_ip),
+        ];
+        let resp = self
+            .client
+            .post(self.provider.verify_url())
+            .form(&form)
+            .send()
+            .await;
+
+        match resp {
+            Ok(resp) => resp
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|json| json.get("success").and_then(|v| v.as_bool()))
+                .unwrap_or(false),
+            Err(e) => {
+                tracing::warn!("人机验证请求失败: {}", e);
+                false
+            }
+        }
+    }
+}
@@ -2,27 +2,117 @@
 //!
 //! 管理聊天室的消息记录、用户身份映射、昵称设置等功能。
 //! 支持消息的时间戳排序、用户去重、聊天记录转储等。
+//!
+//! ## 房间划分
+//! 每场直播使用独立的 `ChatRoom`，由 `ChatRooms` 按流 ID 统一管理；
+//! 未直播时可使用跨直播保留的大厅房间（lobby）。
 
+use super::embed::{EmbedLimiter, EmbedMeta};
 use chrono::{DateTime, Utc};
+use crate::domain::{ClientIp, SessionId, Uid};
+use crate::dump_schema;
+use crate::ids::{self, UidAllocator};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 use parking_lot::RwLock;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // ============================================================================
 // 数据结构定义
 // ============================================================================
 
+/// 聊天消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatKind {
+    /// 用户（观众或主播）发送的普通消息
+    #[default]
+    Chat,
+    /// 服务器生成的系统消息（开播/下播、状态提示、打赏、进出房间等）
+    System,
+}
+
+impl ChatKind {
+    /// 将消息类型转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::System => "system",
+        }
+    }
+}
+
+/// 聊天频道
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatChannel {
+    /// 所有可进入聊天室的人（默认）
+    #[default]
+    Everyone,
+    /// 仅限设置了昵称的观众和主播（见 `LIVE_SERVER_CHAT_VERIFIED_CHANNEL`）
+    Verified,
+}
+
+impl ChatChannel {
+    /// 从字符串解析频道
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "everyone" => Some(Self::Everyone),
+            "verified" => Some(Self::Verified),
+            _ => None,
+        }
+    }
+
+    /// 是否为默认频道（默认频道在转储中省略）
+    pub fn is_everyone(&self) -> bool {
+        *self == Self::Everyone
+    }
+}
+
+/// 聊天消息分页游标
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatCursor {
+    /// 最近的消息
+    Latest,
+    /// 按消息 ID 定位（不受系统时钟调整影响）
+    Id(String),
+    /// 按时间戳定位（兼容旧版客户端）
+    Stamp(f64),
+}
+
+/// 消息删除标记（墓碑）
+///
+/// 被删除的消息保留元数据和原始内容，但不再对观众展示内容，普通转储中内容也被遮蔽
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    /// 执行删除的管理者 UID
+    pub by: Uid,
+    /// 删除时间戳（Unix 时间戳，秒级精度）
+    pub stamp: f64,
+}
+
 /// 单条聊天消息记录
 ///
 /// 存储一条聊天消息的完整信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatEntry {
+    /// 消息 ID（ULID，全局唯一，用于举报等引用）
+    #[serde(default)]
+    pub id: String,
+    /// 消息类型
+    #[serde(default)]
+    pub kind: ChatKind,
+    /// 所属频道（系统消息在所有频道中可见）
+    #[serde(default, skip_serializing_if = "ChatChannel::is_everyone")]
+    pub channel: ChatChannel,
     /// 发送者用户 ID
-    pub uid: u32,
+    pub uid: Uid,
     /// 消息内容
     pub content: String,
     /// 消息时间戳（Unix 时间戳，秒级精度）
@@ -30,6 +120,24 @@ pub struct ChatEntry {
     /// 是否为主播发送的消息（序列化时重命名为 "pub"）
     #[serde(rename = "pub")]
     pub is_publisher: bool,
+    /// 是否为联合主持发送的消息（序列化时重命名为 "cohost"）
+    #[serde(default, rename = "cohost", skip_serializing_if = "std::ops::Not::not")]
+    pub co_host: bool,
+    /// 可内联显示的图片链接信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed: Option<EmbedMeta>,
+    /// 是否高亮显示（如打赏致谢）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub highlight: bool,
+    /// 所属直播场次 ID（离线大厅消息为 `None`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// 删除标记（未删除时为 `None`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<Tombstone>,
+    /// 是否在抽样叠加层消息流中省略（聊天过快时按抽样规则标记，不随转储保存）
+    #[serde(skip)]
+    pub sampled_out: bool,
 }
 
 impl ChatEntry {
@@ -40,14 +148,90 @@ impl ChatEntry {
     /// - `content`: 消息内容
     /// - `stamp`: 消息时间戳
     /// - `is_publisher`: 是否为主播消息
-    pub fn new(uid: u32, content: String, stamp: f64, is_publisher: bool) -> Self {
+    pub fn new(id: String, uid: Uid, content: String, stamp: f64, is_publisher: bool) -> Self {
         Self {
+            id,
+            kind: ChatKind::Chat,
+            channel: ChatChannel::Everyone,
             uid,
             content,
             stamp,
             is_publisher,
+            co_host: false,
+            embed: None,
+            highlight: false,
+            session: None,
+            deleted: None,
+            sampled_out: false,
+        }
+    }
+
+    /// 创建系统消息条目
+    ///
+    /// ### 参数
+    /// - `id`: 消息 ID
+    /// - `content`: 消息内容
+    /// - `stamp`: 消息时间戳
+    pub fn system(id: String, content: String, stamp: f64) -> Self {
+        Self {
+            kind: ChatKind::System,
+            ..Self::new(id, SYSTEM_UID, content, stamp, false)
         }
     }
+
+    /// 附加图片嵌入信息（链式调用）
+    pub fn with_embed(mut self, embed: Option<EmbedMeta>) -> Self {
+        self.embed = embed;
+        self
+    }
+
+    /// 标记所属直播场次（链式调用）
+    pub fn with_session(mut self, session: Option<String>) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// 转储中使用的消息内容
+    ///
+    /// ### 参数
+    /// - `include_redacted`: 是否输出已删除消息的原始内容
+    ///
+    /// ### 返回值
+    /// 已删除且不包含原始内容时返回 `None`
+    fn dump_content(&self, include_redacted: bool) -> Option<&str> {
+        (self.deleted.is_none() || include_redacted).then_some(self.content.as_str())
+    }
+
+    /// 转储中的单条消息记录
+    ///
+    /// ### 参数
+    /// - `name`: 发送者昵称
+    /// - `ip`: 发送者 IP
+    /// - `include_redacted`: 是否输出已删除消息的原始内容
+    pub fn dump_record(&self, name: Option<&String>, ip: Option<&ClientIp>, include_redacted: bool) -> serde_json::Value {
+        let mut obj = serde_json::json!({
+            "id": self.id,
+            "uid": self.uid,
+            "session": self.session,
+            "kind": self.kind.as_str(),
+            "name": name,
+            "ip": ip,
+            "content": self.dump_content(include_redacted),
+            "date": dump_date(self.stamp),
+        });
+        if let Some(tombstone) = &self.deleted {
+            obj["deleted"] = serde_json::json!({
+                "by": tombstone.by,
+                "date": dump_date(tombstone.stamp),
+            });
+        }
+        obj
+    }
+}
+
+/// 转储中的时间格式（秒级精度）
+fn dump_date(stamp: f64) -> String {
+    format!("{:?}", DateTime::<Utc>::from_timestamp(stamp as i64, 0).unwrap_or_default())
 }
 
 /// 客户端身份信息
@@ -56,73 +240,266 @@ impl ChatEntry {
 #[derive(Debug, Clone, Serialize)]
 pub struct ClientIdentity {
     /// 用户 ID
-    pub uid: u32,
+    pub uid: Uid,
     /// 用户昵称（如果已设置）
     pub name: Option<String>,
+    /// 是否愿意公开进出直播间的提示
+    pub announce: bool,
+    /// 是否愿意出现在排行榜中
+    pub ranked: bool,
+    /// 该用户屏蔽的 UID（被屏蔽者的消息不再返回给该用户）
+    #[serde(skip_serializing_if = "HashSet::is_empty")]
+    pub blocked: HashSet<Uid>,
+    /// 观众偏好设置（主题、隐藏系统消息、轮询间隔等，由客户端自行解释）
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub prefs: BTreeMap<String, String>,
+}
+
+/// 单个用户的参与统计
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatterStats {
+    /// 发送的消息数
+    pub messages: u32,
+    /// 首次出现的时间戳
+    pub first_seen: f64,
+    /// 答题用时（秒，未通过答题或免答题进入时为空）
+    pub answer_secs: Option<f64>,
+}
+
+/// 排行榜条目
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    /// 用户昵称
+    pub name: String,
+    /// 发送的消息数
+    pub messages: u32,
+    /// 首次出现的时间戳
+    pub first_seen: f64,
+    /// 答题用时（秒）
+    pub answer_secs: Option<f64>,
+}
+
+/// 用户举报记录
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatReport {
+    /// 被举报的消息 ID
+    pub message_id: String,
+    /// 举报者 UID
+    pub reporter: Uid,
+    /// 被举报者 UID
+    pub target: Uid,
+    /// 举报理由
+    pub reason: String,
+    /// 被举报消息的内容快照
+    pub content: String,
+    /// 举报时间戳
+    pub stamp: f64,
 }
 
-/// 聊天室数据库
+/// 打赏记录
 ///
-/// 管理聊天室的所有状态，包括消息记录、用户映射等。
+/// 由外部支付平台通过打赏回调写入，随聊天记录一起转储
+#[derive(Debug, Clone, Serialize)]
+pub struct TipRecord {
+    /// 打赏者名称
+    pub name: String,
+    /// 打赏金额
+    pub amount: f64,
+    /// 货币单位
+    pub currency: String,
+    /// 打赏留言
+    pub message: Option<String>,
+    /// 打赏时间戳
+    pub stamp: f64,
+}
+
+/// 答题速度排行榜条目
+#[derive(Debug, Clone, Serialize)]
+pub struct QuizRecord {
+    /// 用户昵称
+    pub name: String,
+    /// 答题用时（秒，保留两位小数）
+    pub seconds: f64,
+}
+
+/// 叠加层抽样统计聊天速率的窗口
+const SAMPLE_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 叠加层消息抽样器
+///
+/// 观众消息速率超过阈值时，只让每 N 条中的一条进入抽样消息流，
+/// 使直播画面上的聊天在刷屏时仍然可读
+#[derive(Debug, Default)]
+pub struct OverlaySampler {
+    /// 窗口内观众消息的发送时刻
+    recent: VecDeque<Instant>,
+    /// 超过阈值期间已经过的消息数
+    counter: u64,
+}
+
+impl OverlaySampler {
+    /// 记录一条观众消息并判断是否进入抽样消息流
+    ///
+    /// ### 参数
+    /// - `threshold`: 每分钟消息数阈值（0 表示不抽样）
+    /// - `every`: 超过阈值时每多少条保留一条
+    ///
+    /// ### 返回值
+    /// 该消息是否保留在抽样消息流中
+    pub fn admit(&mut self, threshold: usize, every: usize) -> bool {
+        let now = Instant::now();
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) > SAMPLE_RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+        if threshold == 0 || self.recent.len() <= threshold {
+            self.counter = 0;
+            return true;
+        }
+        let keep = self.counter.is_multiple_of(every as u64);
+        self.counter += 1;
+        keep
+    }
+}
+
+/// 排行榜条目数
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// 系统消息使用的发送者 UID（不对应任何真实用户）
+pub const SYSTEM_UID: Uid = Uid(0);
+
+/// 慢速模式间隔上限（秒）
+pub const MAX_SLOW_MODE_SECS: u64 = 3600;
+
+/// 每位观众的偏好设置条数上限
+pub const MAX_PREFS: usize = 16;
+
+/// 偏好设置键的最大长度（字节，仅允许小写字母、数字、`_` 和 `-`）
+pub const MAX_PREF_KEY_LEN: usize = 32;
+
+/// 偏好设置值的最大长度（字符）
+pub const MAX_PREF_VALUE_LEN: usize = 256;
+
+/// 聊天室
+///
+/// 管理单个聊天室的所有状态，包括消息记录、用户映射等。
 ///
 /// ### 数据结构说明
-/// - `messages`: 按时间戳排序的消息列表
+/// - `id`: 房间 ID（流 ID 或大厅 ID）
+/// - `messages`: 按消息 ID 排序的消息列表
 /// - `name_map`: 已被占用的昵称集合（用于防止昵称重复）
 /// - `uid_map`: UID -> 昵称 的映射
 /// - `client_map`: 二层 HashMap，IP -> session_id -> ClientIdentity
 /// - `ip_map`: UID -> IP 的映射（用于消息归属）
-/// - `next_uid`: 下一个可用的 UID 起始值
+/// - `uids`: UID 分配器
 /// - `dump_path`: 聊天记录转储目录路径
 #[derive(Debug)]
-pub struct ChatDatabaseInner {
-    /// 消息列表（按时间戳排序）
+pub struct ChatRoom {
+    /// 房间 ID
+    pub id: String,
+    /// 直播场次 ID（离线大厅为 `None`）
+    pub session_id: Option<String>,
+    /// 消息列表（按消息 ID 排序）
     pub messages: Vec<ChatEntry>,
     /// 已被占用的昵称集合
     pub name_map: HashSet<String>,
     /// UID -> 昵称 映射
-    pub uid_map: HashMap<u32, String>,
+    pub uid_map: HashMap<Uid, String>,
     /// 客户端映射：IP -> session_id -> 身份信息
-    pub client_map: HashMap<String, HashMap<String, ClientIdentity>>,
+    pub client_map: HashMap<ClientIp, HashMap<SessionId, ClientIdentity>>,
     /// UID -> IP 映射（用于显示消息来源）
-    pub ip_map: HashMap<u32, String>,
-    /// 下一个可用的 UID
-    pub next_uid: u32,
+    pub ip_map: HashMap<Uid, ClientIp>,
+    /// UID 分配器
+    pub uids: UidAllocator,
+    /// 待处理的举报
+    pub reports: Vec<ChatReport>,
+    /// 被暂时限制的 UID：其消息只对自己可见，等待主播审核
+    pub shadow_restricted: HashSet<Uid>,
+    /// 图片嵌入的频率限制与主播开关
+    pub embeds: EmbedLimiter,
+    /// 本场直播收到的打赏
+    pub tips: Vec<TipRecord>,
+    /// UID -> 参与统计
+    pub stats: HashMap<Uid, ChatterStats>,
+    /// 慢速模式：观众两条消息之间的最短间隔（秒），0 表示关闭
+    pub slow_mode_secs: u64,
+    /// 是否关闭观众发言（推流参数 `chat=off`，主播和联合主持不受影响）
+    pub chat_closed: bool,
+    /// UID -> 最近一次发言时刻（单调时钟，用于慢速模式）
+    pub last_sent: HashMap<Uid, Instant>,
+    /// UID -> 最近一次聊天活动时刻（身份创建、设置昵称、发言等，用于身份回收）
+    pub last_active: HashMap<Uid, Instant>,
+    /// 是否暂停自动提示（进出直播间提示）
+    pub announcements_paused: bool,
+    /// 直播叠加层已显示到的消息 ID（此后的消息尚未上屏）
+    pub shown_until: Option<String>,
+    /// 叠加层消息抽样器
+    pub overlay_sampler: OverlaySampler,
+    /// 本房间已生成的转储序号（保证同一秒内多次转储不重名）
+    pub dump_seq: AtomicU32,
     /// 聊天记录转储目录
     pub dump_path: PathBuf,
 }
 
-impl ChatDatabaseInner {
-    /// 创建新的聊天室数据库
+impl ChatRoom {
+    /// 创建新的聊天室
     ///
     /// ### 参数
+    /// - `id`: 房间 ID
     /// - `dump_path`: 聊天记录转储目录路径
-    ///
-    /// ### 注意事项
-    /// UID 从 114514~1919810 范围内随机开始，这是一个彩蛋值
-    pub fn new(dump_path: PathBuf) -> Self {
-        let mut rng = rand::thread_rng();
+    /// - `uid_easter_egg`: UID 是否从彩蛋值开始分配
+    pub fn new(id: String, dump_path: PathBuf, uid_easter_egg: bool) -> Self {
         Self {
+            id,
+            session_id: None,
             messages: Vec::new(),
             name_map: HashSet::new(),
             uid_map: HashMap::new(),
             client_map: HashMap::new(),
             ip_map: HashMap::new(),
-            next_uid: rng.gen_range(114514..1919810),
+            uids: UidAllocator::new(uid_easter_egg),
+            reports: Vec::new(),
+            shadow_restricted: HashSet::new(),
+            embeds: EmbedLimiter::new(),
+            tips: Vec::new(),
+            stats: HashMap::new(),
+            slow_mode_secs: 0,
+            chat_closed: false,
+            last_sent: HashMap::new(),
+            last_active: HashMap::new(),
+            announcements_paused: false,
+            shown_until: None,
+            overlay_sampler: OverlaySampler::default(),
+            dump_seq: AtomicU32::new(0),
             dump_path,
         }
     }
 
-    /// 重置聊天室数据库
+    /// 设置直播场次 ID（链式调用）
+    pub fn with_session(mut self, session_id: Option<String>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    /// 重置聊天室
     ///
-    /// 在新直播开始时调用，清空所有消息和用户信息
+    /// 清空所有消息和用户信息
     pub fn reset(&mut self) {
-        let mut rng = rand::thread_rng();
         self.messages.clear();
         self.name_map.clear();
         self.uid_map.clear();
         self.client_map.clear();
         self.ip_map.clear();
-        self.next_uid = rng.gen_range(114514..1919810);
+        self.uids.reset();
+        self.reports.clear();
+        self.shadow_restricted.clear();
+        self.embeds.reset();
+        self.tips.clear();
+        self.stats.clear();
+        self.last_sent.clear();
+        self.last_active.clear();
+        self.shown_until = None;
+        self.overlay_sampler = OverlaySampler::default();
     }
 
     /// 添加聊天消息
@@ -132,40 +509,543 @@ impl ChatDatabaseInner {
     /// - `session_id`: 发送者会话 ID
     /// - `content`: 消息内容
     /// - `is_publisher`: 是否为主播发送
+    /// - `embed`: 候选的图片嵌入信息（仍需通过频率限制）
+    /// - `limits`: 嵌入频率限制（单用户每分钟, 全房间每分钟）
     ///
     /// ### 行为说明
     /// 1. 如果客户端不存在，自动创建匿名用户
-    /// 2. 消息按时间戳插入到正确位置（保持有序）
-    pub fn add_entry(&mut self, ip: String, session_id: String, content: String, is_publisher: bool) {
+    /// 2. 嵌入被关闭或超出频率限制时，消息照常发送但不标记嵌入
+    /// 3. 消息按 ID（单调递增）追加，不受系统时钟调整影响
+    ///
+    /// ### 返回值
+    /// 新追加的消息（调用方可继续设置所属频道等属性）
+    pub fn add_entry(
+        &mut self,
+        ip: ClientIp,
+        session_id: SessionId,
+        content: String,
+        is_publisher: bool,
+        embed: Option<EmbedMeta>,
+        limits: (usize, usize),
+    ) -> &mut ChatEntry {
         // 获取当前时间戳（秒级精度）
         let stamp = Utc::now().timestamp_millis() as f64 / 1000.0;
 
         // 获取或创建客户端 UID
-        let uid = if let Some(client) = self.client_map.get(&ip).and_then(|m| m.get(&session_id)) {
-            // 客户端已存在，使用其 UID
-            client.uid
-        } else {
-            // 创建新的匿名用户
-            let uid = self.next_uid;
-            self.next_uid += 1;
-            self.client_map
-                .entry(ip.clone())
-                .or_insert_with(HashMap::new)
-                .insert(session_id.clone(), ClientIdentity { uid, name: None });
-            self.ip_map.insert(uid, ip);
-            uid
-        };
+        let uid = self.ensure_uid(&ip, &session_id);
 
         // 创建消息条目
-        let entry = ChatEntry::new(uid, content, stamp, is_publisher);
+        let id = ids::ulid();
+        let embed = embed.filter(|_| {
+            self.embeds
+                .try_acquire(uid, Utc::now().timestamp(), limits.0, limits.1)
+        });
+        let entry = ChatEntry::new(id, uid, content, stamp, is_publisher)
+            .with_embed(embed)
+            .with_session(self.session_id.clone());
+        self.stats_mut(uid).messages += 1;
+        self.last_sent.insert(uid, Instant::now());
+        self.messages.push(entry);
+        self.messages.last_mut().expect("刚追加的消息")
+    }
 
-        // 使用 partition_point 找到插入位置（保持时间戳有序）
-        let pos = self
-            .messages
-            .partition_point(|e| e.stamp <= stamp);
+    /// 写入主实例复制来的消息（热备复制，备用实例调用）
+    ///
+    /// 按消息 ID 插入到对应位置，已存在的消息忽略；发送者的昵称和 IP 仅在本房间没有记录时写入
+    pub fn insert_replicated(&mut self, entry: ChatEntry, name: Option<String>, ip: Option<ClientIp>) {
+        let Err(pos) = self.messages.binary_search_by(|e| e.id.cmp(&entry.id)) else {
+            return;
+        };
+        if entry.kind == ChatKind::Chat {
+            self.uids.reserve(entry.uid);
+            if let Some(name) = name.filter(|_| !self.uid_map.contains_key(&entry.uid)) {
+                self.name_map.insert(name.clone());
+                self.uid_map.insert(entry.uid, name);
+            }
+            if let Some(ip) = ip {
+                self.ip_map.entry(entry.uid).or_insert(ip);
+            }
+        }
         self.messages.insert(pos, entry);
     }
 
+    /// 慢速模式下距离可以再次发言还需等待的秒数
+    ///
+    /// ### 返回值
+    /// - `Some(secs)`: 仍需等待
+    /// - `None`: 可以发言（或未开启慢速模式）
+    pub fn slow_mode_wait(&self, ip: &ClientIp, session_id: &SessionId) -> Option<u64> {
+        if self.slow_mode_secs == 0 {
+            return None;
+        }
+        let uid = self.get_client_uid(ip, session_id)?;
+        let elapsed = self.last_sent.get(&uid)?.elapsed().as_secs();
+        (elapsed < self.slow_mode_secs).then(|| self.slow_mode_secs - elapsed)
+    }
+
+    /// 添加系统消息
+    ///
+    /// ### 参数
+    /// - `content`: 消息内容
+    /// - `highlight`: 是否高亮显示
+    pub fn add_system(&mut self, content: impl Into<String>, highlight: bool) {
+        let stamp = Utc::now().timestamp_millis() as f64 / 1000.0;
+        let id = ids::ulid();
+        let mut entry = ChatEntry::system(id, content.into(), stamp).with_session(self.session_id.clone());
+        entry.highlight = highlight;
+        self.messages.push(entry);
+    }
+
+    /// 记录一笔打赏，并在聊天中插入高亮的致谢消息
+    ///
+    /// ### 参数
+    /// - `name`: 打赏者名称
+    /// - `amount`: 打赏金额
+    /// - `currency`: 货币单位
+    /// - `message`: 打赏留言
+    pub fn add_tip(&mut self, name: String, amount: f64, currency: String, message: Option<String>) {
+        let stamp = Utc::now().timestamp_millis() as f64 / 1000.0;
+        let mut content = format!("{} 打赏了 {} {}", name, amount, currency)
+            .trim_end()
+            .to_string();
+        if let Some(msg) = message.as_deref().filter(|m| !m.is_empty()) {
+            content.push('：');
+            content.push_str(msg);
+        }
+
+        self.add_system(content, true);
+        self.tips.push(TipRecord {
+            name,
+            amount,
+            currency,
+            message,
+            stamp,
+        });
+    }
+
+    /// 获取客户端 UID，不存在时创建匿名用户
+    pub fn ensure_uid(&mut self, ip: &ClientIp, session_id: &SessionId) -> Uid {
+        if let Some(uid) = self.get_client_uid(ip, session_id) {
+            self.last_active.insert(uid, Instant::now());
+            return uid;
+        }
+        let uid = self.uids.allocate();
+        self.client_map
+            .entry(ip.clone())
+            .or_default()
+            .insert(session_id.clone(), ClientIdentity {
+                uid,
+                name: None,
+                announce: false,
+                ranked: false,
+                blocked: HashSet::new(),
+                prefs: BTreeMap::new(),
+            });
+        self.ip_map.insert(uid, ip.clone());
+        self.stats_mut(uid);
+        self.last_active.insert(uid, Instant::now());
+        uid
+    }
+
+    /// 设置是否公开进出直播间的提示
+    ///
+    /// ### 返回值
+    /// 客户端不存在时返回 false
+    pub fn set_announce(&mut self, ip: &ClientIp, session_id: &SessionId, enabled: bool) -> bool {
+        match self.client_map.get_mut(ip).and_then(|m| m.get_mut(session_id)) {
+            Some(client) => {
+                client.announce = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 设置是否出现在排行榜中
+    ///
+    /// ### 返回值
+    /// 客户端不存在时返回 false
+    pub fn set_ranked(&mut self, ip: &ClientIp, session_id: &SessionId, enabled: bool) -> bool {
+        match self.client_map.get_mut(ip).and_then(|m| m.get_mut(session_id)) {
+            Some(client) => {
+                client.ranked = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 屏蔽或取消屏蔽一个用户
+    ///
+    /// ### 参数
+    /// - `ip`: 观众 IP
+    /// - `session_id`: 观众会话 ID
+    /// - `target`: 被屏蔽者 UID
+    /// - `blocked`: true 为屏蔽，false 为取消屏蔽
+    ///
+    /// ### 返回值
+    /// 目标用户不存在或为观众本人时返回 false
+    pub fn set_blocked(&mut self, ip: &ClientIp, session_id: &SessionId, target: Uid, blocked: bool) -> bool {
+        if !self.ip_map.contains_key(&target) {
+            return false;
+        }
+        let uid = self.ensure_uid(ip, session_id);
+        if uid == target {
+            return false;
+        }
+        match self.client_map.get_mut(ip).and_then(|m| m.get_mut(session_id)) {
+            Some(client) => {
+                if blocked {
+                    client.blocked.insert(target);
+                } else {
+                    client.blocked.remove(&target);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 设置或删除一项偏好设置
+    ///
+    /// ### 参数
+    /// - `ip`: 观众 IP
+    /// - `session_id`: 观众会话 ID
+    /// - `key`: 偏好设置键
+    /// - `value`: 新值，`None` 表示删除该项
+    ///
+    /// ### 返回值
+    /// 键或值不合法、条数已达上限时返回失败原因
+    pub fn set_pref(&mut self, ip: &ClientIp, session_id: &SessionId, key: String, value: Option<String>) -> Result<(), &'static str> {
+        if key.is_empty()
+            || key.len() > MAX_PREF_KEY_LEN
+            || !key.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-')
+        {
+            return Err("invalid key");
+        }
+        if value.as_ref().is_some_and(|v| v.chars().count() > MAX_PREF_VALUE_LEN) {
+            return Err("value too long");
+        }
+        self.ensure_uid(ip, session_id);
+        let Some(client) = self.client_map.get_mut(ip).and_then(|m| m.get_mut(session_id)) else {
+            return Err("unknown client");
+        };
+        match value {
+            Some(value) => {
+                if client.prefs.len() >= MAX_PREFS && !client.prefs.contains_key(&key) {
+                    return Err("too many prefs");
+                }
+                client.prefs.insert(key, value);
+            }
+            None => {
+                client.prefs.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    /// 本场聊天室中尚未设置任何偏好时，沿用之前保存的偏好设置
+    ///
+    /// ### 参数
+    /// - `ip`: 观众 IP
+    /// - `session_id`: 观众会话 ID
+    /// - `saved`: 之前保存的偏好设置
+    pub fn restore_prefs(&mut self, ip: &ClientIp, session_id: &SessionId, saved: &BTreeMap<String, String>) {
+        self.ensure_uid(ip, session_id);
+        if let Some(client) = self.client_map.get_mut(ip).and_then(|m| m.get_mut(session_id)) {
+            if client.prefs.is_empty() {
+                client.prefs = saved.clone();
+            }
+        }
+    }
+
+    /// 获取观众的偏好设置和屏蔽列表
+    ///
+    /// ### 返回值
+    /// `(偏好设置, 按 UID 排序的屏蔽列表)`，观众尚无身份时均为空
+    pub fn preferences(&self, ip: &ClientIp, session_id: &SessionId) -> (BTreeMap<String, String>, Vec<Uid>) {
+        match self.client_map.get(ip).and_then(|m| m.get(session_id)) {
+            Some(client) => {
+                let mut blocked: Vec<Uid> = client.blocked.iter().copied().collect();
+                blocked.sort_unstable();
+                (client.prefs.clone(), blocked)
+            }
+            None => (BTreeMap::new(), Vec::new()),
+        }
+    }
+
+    /// 查看者屏蔽的 UID 集合
+    fn blocked_by(&self, viewer: Uid) -> Option<&HashSet<Uid>> {
+        let ip = self.ip_map.get(&viewer)?;
+        self.client_map
+            .get(ip)?
+            .values()
+            .find(|client| client.uid == viewer)
+            .map(|client| &client.blocked)
+    }
+
+    /// 获取用户的参与统计，不存在时以当前时间为首次出现时间创建
+    fn stats_mut(&mut self, uid: Uid) -> &mut ChatterStats {
+        self.stats.entry(uid).or_insert_with(|| ChatterStats {
+            messages: 0,
+            first_seen: Utc::now().timestamp_millis() as f64 / 1000.0,
+            answer_secs: None,
+        })
+    }
+
+    /// 记录观众的答题用时
+    ///
+    /// ### 参数
+    /// - `ip`: 观众 IP
+    /// - `session_id`: 观众会话 ID
+    /// - `secs`: 从领取题目到答对的秒数
+    pub fn record_answer_time(&mut self, ip: &ClientIp, session_id: &SessionId, secs: f64) {
+        let uid = self.ensure_uid(ip, session_id);
+        self.stats_mut(uid).answer_secs = Some(secs);
+    }
+
+    /// 获取本场直播的答题速度排行榜
+    ///
+    /// 只包含设置了昵称并主动加入排行榜的用户，按答题用时升序排列
+    ///
+    /// ### 参数
+    /// - `limit`: 最多返回的条目数
+    pub fn quiz_leaderboard(&self, limit: usize) -> Vec<QuizRecord> {
+        let mut records: Vec<QuizRecord> = self
+            .client_map
+            .values()
+            .flat_map(|sessions| sessions.values())
+            .filter(|c| c.ranked)
+            .filter_map(|c| {
+                let name = c.name.clone()?;
+                let secs = self.stats.get(&c.uid)?.answer_secs?;
+                Some(QuizRecord {
+                    name,
+                    seconds: (secs * 100.0).round() / 100.0,
+                })
+            })
+            .collect();
+        records.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+        records.truncate(limit);
+        records
+    }
+
+    /// 获取参与度排行榜
+    ///
+    /// 只包含设置了昵称并主动加入排行榜的用户，按消息数降序、首次出现时间升序排列
+    ///
+    /// ### 参数
+    /// - `limit`: 最多返回的条目数
+    pub fn leaderboard(&self, limit: usize) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = self
+            .client_map
+            .values()
+            .flat_map(|sessions| sessions.values())
+            .filter(|c| c.ranked)
+            .filter_map(|c| {
+                let name = c.name.clone()?;
+                let stats = self.stats.get(&c.uid)?;
+                Some(LeaderboardEntry {
+                    name,
+                    messages: stats.messages,
+                    first_seen: stats.first_seen,
+                    answer_secs: stats.answer_secs,
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.messages
+                .cmp(&a.messages)
+                .then(a.first_seen.total_cmp(&b.first_seen))
+        });
+        entries.truncate(limit);
+        entries
+    }
+
+    /// 发送进出直播间提示
+    ///
+    /// 只对设置了昵称且主动开启提示的观众生效，匿名观众不会被暴露；
+    /// 主播暂停自动提示时不发送
+    ///
+    /// ### 参数
+    /// - `ip`: 观众 IP
+    /// - `session_id`: 观众会话 ID
+    /// - `joined`: true 为进入，false 为离开
+    pub fn announce_presence(&mut self, ip: &ClientIp, session_id: &SessionId, joined: bool) {
+        if self.announcements_paused {
+            return;
+        }
+        let name = match self.client_map.get(ip).and_then(|m| m.get(session_id)) {
+            Some(ClientIdentity { name: Some(name), announce: true, .. }) => name.clone(),
+            _ => return,
+        };
+        let action = if joined { "进入" } else { "离开" };
+        self.add_system(format!("{} {}了直播间", name, action), false);
+    }
+
+    /// 删除一条消息（保留墓碑）
+    ///
+    /// ### 参数
+    /// - `message_id`: 消息 ID
+    /// - `by`: 执行删除的管理者 UID
+    ///
+    /// ### 返回值
+    /// - `true`: 已删除
+    /// - `false`: 消息不存在、是系统消息或已被删除
+    pub fn delete_message(&mut self, message_id: &str, by: Uid) -> bool {
+        match self
+            .messages
+            .iter_mut()
+            .find(|m| m.id == message_id && m.kind == ChatKind::Chat)
+        {
+            Some(message) if message.deleted.is_none() => {
+                message.deleted = Some(Tombstone {
+                    by,
+                    stamp: Utc::now().timestamp_millis() as f64 / 1000.0,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 回收不再活跃的身份
+    ///
+    /// 长时间直播中只连接过一次、从未发言的观众会一直占用 `client_map`、`ip_map`、`uid_map`，
+    /// 由定时清理任务调用本方法回收，同时释放其昵称。
+    ///
+    /// ### 参数
+    /// - `retention`: 最近一次聊天活动距今超过此时长才回收
+    /// - `is_present`: 观众记录是否仍存在（尚未被过期清理），存在时不回收
+    ///
+    /// ### 返回值
+    /// 回收的身份数
+    ///
+    /// ### 保留条件
+    /// 发过消息、有关联举报或被限制发言的身份始终保留，转储和审核仍需要它们
+    pub fn sweep_identities(&mut self, retention: Duration, is_present: impl Fn(&ClientIp, &SessionId) -> bool) -> usize {
+        let reported: HashSet<Uid> = self
+            .reports
+            .iter()
+            .flat_map(|r| [r.reporter, r.target])
+            .chain(self.shadow_restricted.iter().copied())
+            .collect();
+        let stale: Vec<(ClientIp, SessionId, Uid)> = self
+            .client_map
+            .iter()
+            .flat_map(|(ip, clients)| {
+                clients
+                    .iter()
+                    .map(move |(session_id, client)| (ip, session_id, client.uid))
+            })
+            .filter(|(_, _, uid)| self.stats.get(uid).is_none_or(|s| s.messages == 0))
+            .filter(|(_, _, uid)| !reported.contains(uid))
+            .filter(|(_, _, uid)| self.last_active.get(uid).is_none_or(|t| t.elapsed() >= retention))
+            .filter(|(ip, session_id, _)| !is_present(ip, session_id))
+            .map(|(ip, session_id, uid)| (ip.clone(), session_id.clone(), uid))
+            .collect();
+
+        for (ip, session_id, uid) in &stale {
+            if let Some(clients) = self.client_map.get_mut(ip) {
+                clients.remove(session_id);
+                if clients.is_empty() {
+                    self.client_map.remove(ip);
+                }
+            }
+            if let Some(name) = self.uid_map.remove(uid) {
+                self.name_map.remove(&name);
+            }
+            self.ip_map.remove(uid);
+            self.stats.remove(uid);
+            self.last_sent.remove(uid);
+            self.last_active.remove(uid);
+        }
+        stale.len()
+    }
+
+    /// 获取客户端 UID（不创建）
+    pub fn get_client_uid(&self, ip: &ClientIp, session_id: &SessionId) -> Option<Uid> {
+        self.client_map.get(ip)?.get(session_id).map(|c| c.uid)
+    }
+
+    /// 根据 UID 查找客户端
+    ///
+    /// ### 返回值
+    /// `(IP, session_id)`，UID 不存在时返回 `None`
+    pub fn find_client(&self, uid: Uid) -> Option<(&ClientIp, &SessionId)> {
+        let ip = self.ip_map.get(&uid)?;
+        self.client_map
+            .get(ip)?
+            .iter()
+            .find(|(_, client)| client.uid == uid)
+            .map(|(session_id, _)| (ip, session_id))
+    }
+
+    /// 举报一条消息
+    ///
+    /// ### 参数
+    /// - `reporter`: 举报者 UID
+    /// - `message_id`: 被举报的消息 ID
+    /// - `reason`: 举报理由
+    /// - `threshold`: 自动限制阈值（不同举报者数量，0 表示不自动限制）
+    ///
+    /// ### 返回值
+    /// - `Some(true)`: 举报成功且被举报者因此被自动限制
+    /// - `Some(false)`: 举报成功
+    /// - `None`: 消息不存在、举报自己、举报主播或重复举报
+    pub fn add_report(&mut self, reporter: Uid, message_id: &str, reason: String, threshold: usize) -> Option<bool> {
+        let message = self.messages.iter().find(|m| m.id == message_id)?;
+        if message.uid == reporter || message.is_publisher {
+            return None;
+        }
+        let target = message.uid;
+        if self
+            .reports
+            .iter()
+            .any(|r| r.reporter == reporter && r.message_id == message_id)
+        {
+            return None;
+        }
+
+        self.reports.push(ChatReport {
+            message_id: message_id.to_string(),
+            reporter,
+            target,
+            reason,
+            content: message.content.clone(),
+            stamp: Utc::now().timestamp_millis() as f64 / 1000.0,
+        });
+
+        // 不同举报者数量达到阈值时自动限制
+        let reporters: HashSet<Uid> = self
+            .reports
+            .iter()
+            .filter(|r| r.target == target)
+            .map(|r| r.reporter)
+            .collect();
+        let restrict = threshold > 0 && reporters.len() >= threshold;
+        Some(restrict && self.shadow_restricted.insert(target))
+    }
+
+    /// 审核针对某用户的全部举报
+    ///
+    /// ### 参数
+    /// - `target`: 被举报者 UID
+    /// - `restrict`: `true` 保持/施加限制，`false` 解除限制
+    ///
+    /// ### 返回值
+    /// 被处理的举报数量
+    pub fn review_reports(&mut self, target: Uid, restrict: bool) -> usize {
+        let before = self.reports.len();
+        self.reports.retain(|r| r.target != target);
+        if restrict {
+            self.shadow_restricted.insert(target);
+        } else {
+            self.shadow_restricted.remove(&target);
+        }
+        before - self.reports.len()
+    }
+
     /// 设置或更改客户端昵称
     ///
     /// ### 参数
@@ -176,7 +1056,7 @@ impl ChatDatabaseInner {
     /// ### 返回值
     /// - `true`: 昵称设置成功
     /// - `false`: 昵称已被占用或客户端已有昵称
-    pub fn set_client_name(&mut self, ip: &str, session_id: &str, name: String) -> bool {
+    pub fn set_client_name(&mut self, ip: &ClientIp, session_id: &SessionId, name: String) -> bool {
         // 检查昵称是否已被占用
         if self.name_map.contains(&name) {
             return false;
@@ -191,22 +1071,27 @@ impl ChatDatabaseInner {
             client.uid
         } else {
             // 新客户端，带昵称注册
-            let uid = self.next_uid;
-            self.next_uid += 1;
+            let uid = self.uids.allocate();
             self.client_map
-                .entry(ip.to_string())
-                .or_insert_with(HashMap::new)
-                .insert(session_id.to_string(), ClientIdentity {
+                .entry(ip.clone())
+                .or_default()
+                .insert(session_id.clone(), ClientIdentity {
                     uid,
                     name: Some(name.clone()),
+                    announce: false,
+                    ranked: false,
+                    blocked: HashSet::new(),
+                    prefs: BTreeMap::new(),
                 });
-            self.ip_map.insert(uid, ip.to_string());
+            self.ip_map.insert(uid, ip.clone());
+            self.stats_mut(uid);
             uid
         };
 
         // 更新所有映射
         self.name_map.insert(name.clone());
         self.uid_map.insert(uid, name.clone());
+        self.last_active.insert(uid, Instant::now());
 
         if let Some(client) = self.client_map.get_mut(ip).and_then(|m| m.get_mut(session_id)) {
             client.name = Some(name);
@@ -223,38 +1108,79 @@ impl ChatDatabaseInner {
     ///
     /// ### 返回值
     /// 返回客户端昵称（如果已设置）
-    pub fn get_client_name(&self, ip: &str, session_id: &str) -> Option<String> {
+    pub fn get_client_name(&self, ip: &ClientIp, session_id: &SessionId) -> Option<String> {
         self.client_map
             .get(ip)?
             .get(session_id)
             .and_then(|c| c.name.clone())
     }
 
-    /// 获取指定时间戳之后的聊天消息
+    /// 客户端能否在指定频道收发消息
+    ///
+    /// `verified` 频道只对主播和设置了昵称的观众开放（不含仅以 IP 显示的匿名用户）
+    pub fn can_use_channel(&self, ip: &ClientIp, session_id: &SessionId, is_publisher: bool, channel: ChatChannel) -> bool {
+        match channel {
+            ChatChannel::Everyone => true,
+            ChatChannel::Verified => is_publisher || self.get_client_name(ip, session_id).is_some(),
+        }
+    }
+
+    /// 获取游标前后的聊天消息
     ///
     /// ### 参数
-    /// - `stamp`: 起始时间戳
+    /// - `cursor`: 分页游标
     /// - `prev`: 是否获取之前的消息（true）还是之后的消息（false）
+    /// - `viewer`: 查看者 UID（被限制用户的消息只对其本人可见，查看者屏蔽的用户的消息不返回）
+    /// - `include_system`: 是否包含系统消息
+    /// - `channel`: 频道（系统消息在所有频道中返回）
+    /// - `sampled`: 是否只返回抽样消息流（主播消息和系统消息始终保留）
     ///
     /// ### 返回值
-    /// 返回符合条件消息的 JSON 数组
-    pub fn get_chat_from(&self, stamp: f64, prev: bool) -> Vec<serde_json::Value> {
-        let entries = self.get_entries_from(stamp, prev);
+    /// 返回符合条件消息的 JSON 数组，系统消息带有 `"kind": "system"` 且不含发送者信息，
+    /// 已删除的消息内容为空并带有 `"deleted": true`，聊天消息带有发送者 `uid`（用于屏蔽）
+    pub fn get_chat_from(
+        &self,
+        cursor: &ChatCursor,
+        prev: bool,
+        viewer: Option<Uid>,
+        include_system: bool,
+        channel: ChatChannel,
+        sampled: bool,
+    ) -> Vec<serde_json::Value> {
+        let entries = self.get_entries_from(cursor, prev, viewer, include_system, channel, sampled);
 
         entries
             .into_iter()
             .map(|entry| {
                 let mut obj = serde_json::json!({
+                    "id": entry.id,
                     "content": entry.content,
                     "stamp": entry.stamp,
                     "pub": entry.is_publisher,
+                    "kind": entry.kind.as_str(),
                 });
+                if entry.co_host {
+                    obj["cohost"] = serde_json::json!(true);
+                }
+                if entry.highlight {
+                    obj["highlight"] = serde_json::json!(true);
+                }
+                if entry.deleted.is_some() {
+                    obj["content"] = serde_json::json!("");
+                    obj["deleted"] = serde_json::json!(true);
+                } else if let Some(embed) = &entry.embed {
+                    obj["embed"] = serde_json::json!(true);
+                    obj["media"] = serde_json::json!(embed);
+                }
 
-                // 优先显示昵称，其次显示 IP
-                if let Some(name) = self.uid_map.get(&entry.uid) {
-                    obj["name"] = serde_json::json!(name);
-                } else if let Some(ip) = self.ip_map.get(&entry.uid) {
-                    obj["ip"] = serde_json::json!(ip);
+                // 优先显示昵称，其次显示 IP（系统消息没有发送者）
+                if entry.kind == ChatKind::Chat {
+                    obj["uid"] = serde_json::json!(entry.uid);
+                    if let Some(name) = self.uid_map.get(&entry.uid) {
+                        obj["name"] = serde_json::json!(name);
+                    } else if let Some(ip) = self.ip_map.get(&entry.uid) {
+                        obj["ip"] = serde_json::json!(ip);
+                    }
                 }
 
                 obj
@@ -262,41 +1188,92 @@ impl ChatDatabaseInner {
             .collect()
     }
 
-    /// 获取指定时间戳的原始消息条目
+    /// 查看者可见的最早一条消息
+    ///
+    /// 参数含义同 `get_chat_from`，更早的消息已被清理，客户端向前翻页到这条消息即可停止
+    ///
+    /// ### 返回值
+    /// 没有可见消息时返回 `None`
+    pub fn oldest_available(
+        &self,
+        viewer: Option<Uid>,
+        include_system: bool,
+        channel: ChatChannel,
+        sampled: bool,
+    ) -> Option<&ChatEntry> {
+        self.visible_entries(viewer, include_system, channel, sampled).next()
+    }
+
+    /// 按查看者过滤后的消息（按 ID 有序）
+    ///
+    /// 过滤掉其他频道的消息、被限制用户的消息（对其本人除外）、查看者屏蔽的用户的消息
+    /// 及客户端不需要的系统消息
+    fn visible_entries(
+        &self,
+        viewer: Option<Uid>,
+        include_system: bool,
+        channel: ChatChannel,
+        sampled: bool,
+    ) -> impl Iterator<Item = &ChatEntry> {
+        let blocked = viewer.and_then(|uid| self.blocked_by(uid));
+        self.messages
+            .iter()
+            .filter(move |e| e.kind == ChatKind::System || e.channel == channel)
+            .filter(move |e| include_system || e.kind != ChatKind::System)
+            .filter(move |e| !self.shadow_restricted.contains(&e.uid) || Some(e.uid) == viewer)
+            .filter(move |e| e.kind == ChatKind::System || !blocked.is_some_and(|b| b.contains(&e.uid)))
+            .filter(move |e| !sampled || !e.sampled_out)
+    }
+
+    /// 获取游标前后的原始消息条目
     ///
     /// ### 参数
-    /// - `stamp`: 起始时间戳
+    /// - `cursor`: 分页游标
     /// - `prev`: 是否获取之前的消息
+    /// - `viewer`: 查看者 UID
+    /// - `include_system`: 是否包含系统消息
+    /// - `channel`: 频道
+    /// - `sampled`: 是否只返回抽样消息流
     ///
     /// ### 返回值
     /// 返回符合条件的消息条目列表
-    fn get_entries_from(&self, stamp: f64, prev: bool) -> Vec<ChatEntry> {
-        if stamp < 0.0 {
-            // 客户端没有消息记录，返回最近 10 条
-            if self.messages.len() < 10 {
-                return self.messages.clone();
-            } else {
-                return self.messages[self.messages.len() - 10..].to_vec();
-            }
-        }
+    fn get_entries_from(
+        &self,
+        cursor: &ChatCursor,
+        prev: bool,
+        viewer: Option<Uid>,
+        include_system: bool,
+        channel: ChatChannel,
+        sampled: bool,
+    ) -> Vec<ChatEntry> {
+        let visible: Vec<&ChatEntry> = self.visible_entries(viewer, include_system, channel, sampled).collect();
 
-        // 找到时间戳位置
-        
-        if prev {
-            let idx = self.messages.partition_point(|e| e.stamp < stamp);
+        // 消息按 ID 有序；按时间戳定位仅为兼容旧版客户端，系统时钟回拨后可能不准确
+        let (before, after) = match cursor {
+            ChatCursor::Latest => {
+                // 客户端没有消息记录，返回最近 10 条
+                return visible[visible.len().saturating_sub(10)..]
+                    .iter()
+                    .map(|e| (*e).clone())
+                    .collect();
+            }
+            ChatCursor::Id(id) => (
+                visible.partition_point(|e| e.id < *id),
+                visible.partition_point(|e| e.id <= *id),
+            ),
+            ChatCursor::Stamp(stamp) => (
+                visible.partition_point(|e| e.stamp < *stamp),
+                visible.partition_point(|e| e.stamp <= *stamp),
+            ),
+        };
+        let slice: &[&ChatEntry] = if prev {
             // 获取之前的 10 条消息
-            let start = if idx >= 10 { idx - 10 } else { 0 };
-            self.messages[start..idx].to_vec()
+            &visible[before.saturating_sub(10)..before]
         } else {
-            let idx = self.messages.partition_point(|e| e.stamp <= stamp);
-            // 获取之后的所有消息（严格大于 stamp，避免重复）
-            // 注意：这里使用 idx + 1，跳过等于 stamp 的消息
-            if idx < self.messages.len() {
-                self.messages[idx..].to_vec()
-            } else {
-                Vec::new()
-            }
-        }
+            // 获取之后的所有消息（不含游标本身，避免重复）
+            &visible[after..]
+        };
+        slice.iter().map(|e| (*e).clone()).collect()
     }
 
     /// 获取唯一用户数量
@@ -307,54 +1284,222 @@ impl ChatDatabaseInner {
         self.ip_map.len()
     }
 
+    /// 标记直播叠加层已显示到指定消息
+    ///
+    /// 标记只会前进，早于当前标记的消息 ID 被忽略（叠加层可能乱序回报）
+    ///
+    /// ### 返回值
+    /// 消息不存在时返回 `false`
+    pub fn mark_shown(&mut self, message_id: &str) -> bool {
+        if !self.messages.iter().any(|e| e.id == message_id) {
+            return false;
+        }
+        // 消息 ID 单调递增，可直接按字符串比较先后
+        if self.shown_until.as_deref().is_none_or(|current| current < message_id) {
+            self.shown_until = Some(message_id.to_string());
+        }
+        true
+    }
+
+    /// 最近一段时间内用户发送的消息条数（不含系统消息）
+    ///
+    /// ### 参数
+    /// - `window_secs`: 统计窗口（秒）
+    pub fn recent_message_count(&self, window_secs: f64) -> usize {
+        let since = Utc::now().timestamp_millis() as f64 / 1000.0 - window_secs;
+        self.messages
+            .iter()
+            .rev()
+            .take_while(|e| e.stamp >= since)
+            .filter(|e| e.kind == ChatKind::Chat)
+            .count()
+    }
+
     /// 转储完整聊天记录到文件
     ///
-    /// 包含完整的用户映射、客户端映射和消息记录
-    pub fn dump_full(&self) {
+    /// 包含完整的用户映射、客户端映射和消息记录，已删除消息的内容被遮蔽
+    ///
+    /// ### 返回值
+    /// - `Ok(path)`: 转储文件路径
+    /// - `Err(msg)`: 写入失败的原因（包含目标路径）
+    pub fn dump_full(&self) -> Result<PathBuf, String> {
+        self.dump_full_with(false)
+    }
+
+    /// 转储完整聊天记录到文件
+    ///
+    /// ### 参数
+    /// - `include_redacted`: 是否输出已删除消息的原始内容（仅管理导出使用）
+    ///
+    /// ### 返回值
+    /// 同 `dump_full`
+    pub fn dump_full_with(&self, include_redacted: bool) -> Result<PathBuf, String> {
         // 构建消息记录（包含用户信息）
         let records: Vec<serde_json::Value> = self
             .messages
             .iter()
-            .map(|m| {
-                let obj = serde_json::json!({
-                    "uid": m.uid,
-                    "name": self.uid_map.get(&m.uid),
-                    "ip": self.ip_map.get(&m.uid),
-                    "content": m.content,
-                    "date": format!("{:?}", DateTime::<Utc>::from_timestamp(m.stamp as i64, 0).unwrap_or_default()),
-                });
-                obj
-            })
+            .map(|m| m.dump_record(self.uid_map.get(&m.uid), self.ip_map.get(&m.uid), include_redacted))
             .collect();
 
         // 构建完整转储数据
         let dump_data = serde_json::json!({
+            "schema_version": dump_schema::SCHEMA_VERSION,
+            "stream_id": self.id,
+            "stream_session_id": self.session_id,
             "umap": self.uid_map,
             "cmap": self.client_map,
             "records": records,
+            "tips": self.tips,
+            "stats": self.stats,
+            "leaderboard": self.leaderboard(LEADERBOARD_SIZE),
         });
 
-        // 生成文件名：live-YYYY-MM-DD HH:MM:SS.dump
-        let filename = self.dump_path.join(format!(
-            "live-{}.dump",
-            Utc::now().format("%Y-%m-%d %H:%M:%S")
-        ));
+        self.write_dump(&dump_data)
+    }
 
-        // 确保目录存在
-        if let Some(parent) = filename.parent() {
-            fs::create_dir_all(parent).ok();
+    /// 将转储数据写入文件
+    ///
+    /// ### 返回值
+    /// - `Ok(path)`: 写入成功，返回转储文件路径
+    /// - `Err(msg)`: 创建目录、序列化或写入失败（消息中包含目标路径）
+    fn write_dump(&self, data: &serde_json::Value) -> Result<PathBuf, String> {
+        fs::create_dir_all(&self.dump_path)
+            .map_err(|e| format!("创建转储目录 {} 失败: {}", self.dump_path.display(), e))?;
+        let content = serde_json::to_string_pretty(data)
+            .map_err(|e| format!("序列化转储失败: {}", e))?;
+
+        // 文件名：live-<stream_id>-<UTC 紧凑时间>-<序号>.json
+        // 以 create_new 打开，极端情况下仍重名时递增序号重试
+        let stream_id = self.file_stem();
+        let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+        loop {
+            let seq = self.dump_seq.fetch_add(1, Ordering::Relaxed);
+            let filename = self
+                .dump_path
+                .join(format!("live-{}-{}-{}.json", stream_id, stamp, seq));
+            match fs::OpenOptions::new().write(true).create_new(true).open(&filename) {
+                Ok(mut file) => {
+                    file.write_all(content.as_bytes())
+                        .map_err(|e| format!("写入转储 {} 失败: {}", filename.display(), e))?;
+                    return Ok(filename);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(format!("创建转储 {} 失败: {}", filename.display(), e)),
+            }
         }
+    }
 
-        // 写入文件
-        if let Ok(content) = serde_json::to_string_pretty(&dump_data) {
-            fs::write(filename, content).ok();
+    /// 转储文件名中的场次标识
+    ///
+    /// 优先使用直播场次 ID；离线大厅使用房间 ID（非字母数字字符替换为 `_`）
+    fn file_stem(&self) -> String {
+        if let Some(session_id) = &self.session_id {
+            return session_id.clone();
         }
+        self.id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect()
+    }
+
+    /// 导出指定用户的全部消息，用于举报骚扰等滥用行为
+    ///
+    /// ### 参数
+    /// - `uid`: 目标用户 ID
+    /// - `html`: 是否导出为 HTML（否则为 JSON）
+    ///
+    /// ### 返回值
+    /// 成功返回报告文件路径，用户没有发言或写入失败时返回 `None`
+    ///
+    /// ### 报告内容
+    /// 目标用户的每条消息及其前后各 `REPORT_CONTEXT` 条上下文消息。
+    /// 上下文中其他用户以"用户 N"代替昵称，且不包含任何 IP
+    pub fn export_user(&self, uid: Uid, html: bool) -> Option<PathBuf> {
+        let hits: Vec<usize> = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.uid == uid)
+            .map(|(i, _)| i)
+            .collect();
+        if hits.is_empty() {
+            return None;
+        }
+
+        // 其他用户按首次出现顺序匿名编号
+        let mut aliases: HashMap<Uid, String> = HashMap::new();
+        let mut label = |m: &ChatEntry| -> String {
+            if m.uid == uid {
+                self.uid_map.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+            } else if m.is_publisher {
+                "主播".to_string()
+            } else if m.kind == ChatKind::System {
+                "系统".to_string()
+            } else {
+                let next = aliases.len() + 1;
+                aliases.entry(m.uid).or_insert_with(|| format!("用户 {}", next)).clone()
+            }
+        };
+
+        let excerpts: Vec<serde_json::Value> = hits
+            .iter()
+            .map(|&i| {
+                let start = i.saturating_sub(REPORT_CONTEXT);
+                let end = (i + REPORT_CONTEXT + 1).min(self.messages.len());
+                let lines: Vec<serde_json::Value> = self.messages[start..end]
+                    .iter()
+                    .map(|m| {
+                        serde_json::json!({
+                            "from": label(m),
+                            "target": m.uid == uid,
+                            "content": m.content,
+                            "date": DateTime::<Utc>::from_timestamp(m.stamp as i64, 0)
+                                .unwrap_or_default()
+                                .to_rfc3339(),
+                        })
+                    })
+                    .collect();
+                serde_json::json!(lines)
+            })
+            .collect();
+
+        let report = serde_json::json!({
+            "room": self.id,
+            "stream_session_id": self.session_id,
+            "uid": uid,
+            "name": self.uid_map.get(&uid),
+            "message_count": hits.len(),
+            "generated_at": Utc::now().to_rfc3339(),
+            "excerpts": excerpts,
+        });
+
+        let filename = self.dump_path.join("reports").join(format!(
+            "report-{}-{}-{}.{}",
+            self.file_stem(),
+            uid,
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            if html { "html" } else { "json" }
+        ));
+        if let Some(parent) = filename.parent() {
+            fs::create_dir_all(parent).ok()?;
+        }
+
+        let content = if html {
+            render_report_html(&report)
+        } else {
+            serde_json::to_string_pretty(&report).ok()?
+        };
+        fs::write(&filename, content).ok()?;
+        Some(filename)
     }
 
     /// 转储精简聊天记录到文件
     ///
     /// 仅包含消息记录，不包含用户映射
-    pub fn _dump_brief(&self) {
+    ///
+    /// ### 返回值
+    /// 同 `dump_full`
+    pub fn dump_brief(&self) -> Result<PathBuf, String> {
         let records: Vec<serde_json::Value> = self
             .messages
             .iter()
@@ -363,25 +1508,327 @@ impl ChatDatabaseInner {
                     "uid": m.uid,
                     "name": self.uid_map.get(&m.uid),
                     "ip": self.ip_map.get(&m.uid),
-                    "content": m.content,
-                    "date": format!("{:?}", DateTime::<Utc>::from_timestamp(m.stamp as i64, 0).unwrap_or_default()),
+                    "content": m.dump_content(false),
+                    "date": dump_date(m.stamp),
                 })
             })
             .collect();
 
-        let filename = self.dump_path.join(format!(
-            "live-{}.dump",
-            Utc::now().format("%Y-%m-%d %H:%M:%S")
-        ));
+        self.write_dump(&serde_json::json!(records))
+    }
+}
 
-        if let Some(parent) = filename.parent() {
-            fs::create_dir_all(parent).ok();
+/// 举报导出时每条消息前后附带的上下文消息数
+const REPORT_CONTEXT: usize = 3;
+
+/// 将举报报告渲染为独立的 HTML 页面
+fn render_report_html(report: &serde_json::Value) -> String {
+    let text = |v: &serde_json::Value| html_escape(v.as_str().unwrap_or_default());
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Report {uid}</title></head><body>\n\
+         <h1>用户 {uid}（{name}）消息报告</h1>\n<p>房间: {room}，消息数: {count}，生成时间: {at}</p>\n",
+        uid = report["uid"],
+        name = text(&report["name"]),
+        room = text(&report["room"]),
+        count = report["message_count"],
+        at = text(&report["generated_at"]),
+    );
+    for excerpt in report["excerpts"].as_array().into_iter().flatten() {
+        out.push_str("<table border=\"1\" cellpadding=\"4\">\n");
+        for line in excerpt.as_array().into_iter().flatten() {
+            let style = if line["target"].as_bool() == Some(true) {
+                " style=\"font-weight:bold;background:#fee\""
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "<tr{}><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                style,
+                text(&line["date"]),
+                text(&line["from"]),
+                text(&line["content"]),
+            ));
         }
+        out.push_str("</table><br>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
 
-        if let Ok(content) = serde_json::to_string_pretty(&records) {
-            fs::write(filename, content).ok();
+/// 转义 HTML 特殊字符
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// ============================================================================
+// 聊天室注册表
+// ============================================================================
+
+/// 大厅房间 ID
+pub const LOBBY_ROOM_ID: &str = "lobby";
+
+/// 昵称令牌有效期（每次取用时顺延）
+const NICKNAME_TOKEN_VALIDITY: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 昵称令牌记录
+#[derive(Debug)]
+struct NicknameToken {
+    /// 令牌
+    token: String,
+    /// 最近取用令牌的会话
+    owner: SessionId,
+    /// 过期时刻
+    expires_at: Instant,
+}
+
+/// 聊天室注册表
+///
+/// 按流 ID 管理各场直播的聊天室，保证不同直播之间的聊天互相隔离。
+/// 大厅房间始终存在，不随直播开始/结束而重置。
+#[derive(Debug)]
+pub struct ChatRooms {
+    /// 房间映射：房间 ID -> 聊天室
+    rooms: HashMap<String, ChatRoom>,
+    /// 当前活跃房间 ID（未直播时为大厅）
+    active: String,
+    /// 是否启用离线大厅
+    lobby_enabled: bool,
+    /// 昵称令牌：昵称 -> 令牌记录，持有令牌者可在离线大厅以该昵称发言
+    nickname_tokens: HashMap<String, NicknameToken>,
+    /// 昵称令牌索引：令牌 -> 昵称
+    token_names: HashMap<String, String>,
+    /// 回访观众的偏好设置：回访令牌标识 -> (偏好设置, 令牌过期时间)，跨直播保留
+    alumni_prefs: HashMap<String, (BTreeMap<String, String>, DateTime<Utc>)>,
+    /// 聊天记录转储目录
+    dump_path: PathBuf,
+    /// 新房间的 UID 是否从彩蛋值开始分配
+    uid_easter_egg: bool,
+}
+
+impl ChatRooms {
+    /// 创建新的聊天室注册表
+    ///
+    /// ### 参数
+    /// - `dump_path`: 聊天记录转储目录路径
+    /// - `lobby_enabled`: 是否启用离线大厅
+    /// - `uid_easter_egg`: UID 是否从彩蛋值开始分配
+    pub fn new(dump_path: PathBuf, lobby_enabled: bool, uid_easter_egg: bool) -> Self {
+        let mut rooms = HashMap::new();
+        rooms.insert(
+            LOBBY_ROOM_ID.to_string(),
+            ChatRoom::new(LOBBY_ROOM_ID.to_string(), dump_path.clone(), uid_easter_egg),
+        );
+        Self {
+            rooms,
+            active: LOBBY_ROOM_ID.to_string(),
+            lobby_enabled,
+            nickname_tokens: HashMap::new(),
+            token_names: HashMap::new(),
+            alumni_prefs: HashMap::new(),
+            dump_path,
+            uid_easter_egg,
         }
     }
+
+    /// 是否启用离线大厅
+    pub fn lobby_enabled(&self) -> bool {
+        self.lobby_enabled
+    }
+
+    /// 当前活跃房间是否为大厅
+    pub fn is_lobby_active(&self) -> bool {
+        self.active == LOBBY_ROOM_ID
+    }
+
+    /// 为新直播打开聊天室并设为活跃房间
+    ///
+    /// 默认以全新房间替换同一流 ID 的旧房间；`keep_history` 为真且上一场直播关闭的
+    /// 仍是该流 ID 的房间时，沿用旧房间的聊天记录和用户身份
+    ///
+    /// ### 参数
+    /// - `stream_id`: 流 ID（app/stream）
+    /// - `session_id`: 本场直播的场次 ID
+    /// - `keep_history`: 是否沿用上一场的聊天室
+    pub fn open_room(&mut self, stream_id: &str, session_id: Option<String>, keep_history: bool) {
+        // 其他流的房间（包括已关闭但仍保留的）不再需要
+        self.rooms.retain(|id, _| id == LOBBY_ROOM_ID || id == stream_id);
+        self.prune_nickname_tokens();
+        self.prune_alumni_prefs();
+
+        if keep_history {
+            if let Some(room) = self.rooms.get_mut(stream_id) {
+                room.session_id = session_id;
+                room.add_system("直播已恢复", false);
+                self.active = stream_id.to_string();
+                return;
+            }
+        }
+
+        let mut room =
+            ChatRoom::new(stream_id.to_string(), self.dump_path.clone(), self.uid_easter_egg)
+                .with_session(session_id);
+        room.add_system("直播开始了", false);
+        self.rooms.insert(stream_id.to_string(), room);
+        self.active = stream_id.to_string();
+    }
+
+    /// 关闭当前直播的聊天室，活跃房间切回大厅
+    ///
+    /// 关闭的房间暂时保留在注册表中（不再可见），以便下一场直播选择沿用
+    ///
+    /// ### 返回值
+    /// 被关闭的聊天室（大厅活跃时返回 `None`）
+    pub fn close_room(&mut self) -> Option<&ChatRoom> {
+        if self.is_lobby_active() {
+            return None;
+        }
+        let closed = std::mem::replace(&mut self.active, LOBBY_ROOM_ID.to_string());
+        let room = self.rooms.get_mut(&closed)?;
+        room.add_system("直播已结束", false);
+        Some(room)
+    }
+
+    /// 获取直播聊天室（热备复制，备用实例调用）
+    ///
+    /// 房间不存在时创建但不设为活跃房间，推流端切换到本实例后延续同一场直播时沿用
+    ///
+    /// ### 返回值
+    /// 大厅不参与复制，`room_id` 为大厅时返回 `None`
+    pub fn replica_room(&mut self, room_id: &str, session_id: Option<String>) -> Option<&mut ChatRoom> {
+        if room_id == LOBBY_ROOM_ID {
+            return None;
+        }
+        Some(self.rooms.entry(room_id.to_string()).or_insert_with(|| {
+            ChatRoom::new(room_id.to_string(), self.dump_path.clone(), self.uid_easter_egg).with_session(session_id)
+        }))
+    }
+
+    /// 将已有的直播聊天室设为活跃房间（零停机升级，新进程接管直播时调用）
+    ///
+    /// ### 返回值
+    /// 房间不存在时返回 `false`，活跃房间不变
+    pub fn activate(&mut self, room_id: &str) -> bool {
+        if !self.rooms.contains_key(room_id) {
+            return false;
+        }
+        self.active = room_id.to_string();
+        true
+    }
+
+    /// 获取当前活跃房间
+    pub fn active(&self) -> &ChatRoom {
+        self.rooms
+            .get(&self.active)
+            .expect("活跃房间必须存在于注册表中")
+    }
+
+    /// 获取当前活跃房间（可变）
+    pub fn active_mut(&mut self) -> &mut ChatRoom {
+        self.rooms
+            .get_mut(&self.active)
+            .expect("活跃房间必须存在于注册表中")
+    }
+
+    /// 获取指定房间
+    pub fn get(&self, room_id: &str) -> Option<&ChatRoom> {
+        self.rooms.get(room_id)
+    }
+
+    /// 获取或签发昵称令牌
+    ///
+    /// 昵称首次被认领时为认领者签发随机令牌，之后只交给同一会话或出示该令牌的请求，
+    /// 其他人在令牌有效期内取用同一昵称拿不到令牌。令牌跨直播保留，每次取用时顺延有效期
+    ///
+    /// ### 参数
+    /// - `name`: 昵称
+    /// - `session_id`: 请求的会话 ID
+    /// - `presented`: 请求携带的昵称令牌
+    ///
+    /// ### 返回值
+    /// 昵称已被他人认领时返回 `None`
+    pub fn issue_nickname_token(&mut self, name: &str, session_id: &SessionId, presented: Option<&str>) -> Option<String> {
+        let now = Instant::now();
+        if let Some(entry) = self.nickname_tokens.get_mut(name).filter(|e| e.expires_at > now) {
+            if entry.owner != *session_id && presented != Some(entry.token.as_str()) {
+                return None;
+            }
+            entry.owner = session_id.clone();
+            entry.expires_at = now + NICKNAME_TOKEN_VALIDITY;
+            return Some(entry.token.clone());
+        }
+
+        if let Some(expired) = self.nickname_tokens.remove(name) {
+            self.token_names.remove(&expired.token);
+        }
+        let mut rng = rand::thread_rng();
+        let token: String = (0..32)
+            .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap_or('0'))
+            .collect();
+        self.token_names.insert(token.clone(), name.to_string());
+        self.nickname_tokens.insert(
+            name.to_string(),
+            NicknameToken {
+                token: token.clone(),
+                owner: session_id.clone(),
+                expires_at: now + NICKNAME_TOKEN_VALIDITY,
+            },
+        );
+        Some(token)
+    }
+
+    /// 通过令牌查找昵称（令牌已过期时返回 `None`）
+    pub fn nickname_for_token(&self, token: &str) -> Option<&str> {
+        let name = self.token_names.get(token)?;
+        self.nickname_tokens
+            .get(name)
+            .filter(|e| e.token == token && e.expires_at > Instant::now())
+            .map(|_| name.as_str())
+    }
+
+    /// 清理已过期的昵称令牌
+    fn prune_nickname_tokens(&mut self) {
+        let now = Instant::now();
+        self.nickname_tokens.retain(|_, e| e.expires_at > now);
+        let tokens = &self.nickname_tokens;
+        self.token_names
+            .retain(|token, name| tokens.get(name).is_some_and(|e| e.token == *token));
+    }
+
+    /// 获取回访观众保存的偏好设置
+    ///
+    /// ### 参数
+    /// - `subject`: 回访令牌标识（见 `AlumniSigner::subject`）
+    pub fn alumni_prefs(&self, subject: &str) -> Option<&BTreeMap<String, String>> {
+        self.alumni_prefs
+            .get(subject)
+            .filter(|(_, expires_at)| *expires_at > Utc::now())
+            .map(|(prefs, _)| prefs)
+    }
+
+    /// 保存回访观众的偏好设置，供之后的直播沿用
+    ///
+    /// 屏蔽列表按房间内 UID 记录，换场后没有意义，因此不随偏好设置保存
+    ///
+    /// ### 参数
+    /// - `subject`: 回访令牌标识
+    /// - `expires_at`: 观众出示的回访令牌的过期时间（过期后记录被清理）
+    /// - `prefs`: 偏好设置（为空时删除记录）
+    pub fn save_alumni_prefs(&mut self, subject: &str, expires_at: DateTime<Utc>, prefs: BTreeMap<String, String>) {
+        if prefs.is_empty() {
+            self.alumni_prefs.remove(subject);
+        } else {
+            self.alumni_prefs.insert(subject.to_string(), (prefs, expires_at));
+        }
+    }
+
+    /// 清理令牌已过期的回访观众偏好设置
+    fn prune_alumni_prefs(&mut self) {
+        let now = Utc::now();
+        self.alumni_prefs.retain(|_, (_, expires_at)| *expires_at > now);
+    }
 }
 
 // ============================================================================
@@ -391,15 +1838,15 @@ impl ChatDatabaseInner {
 /// 聊天数据库包装器
 #[derive(Clone)]
 pub struct ChatDatabase {
-    /// 内部数据库
-    pub inner: Arc<RwLock<ChatDatabaseInner>>,
+    /// 内部聊天室注册表
+    pub inner: Arc<RwLock<ChatRooms>>,
 }
 
 impl ChatDatabase {
     /// 创建新的聊天数据库
-    pub fn new(dump_path: PathBuf) -> Self {
+    pub fn new(dump_path: PathBuf, lobby_enabled: bool, uid_easter_egg: bool) -> Self {
         Self {
-            inner: Arc::new(RwLock::new(ChatDatabaseInner::new(dump_path))),
+            inner: Arc::new(RwLock::new(ChatRooms::new(dump_path, lobby_enabled, uid_easter_egg))),
         }
     }
 }
@@ -0,0 +1,83 @@
+//! # 磁盘空间保护模块
+//!
+//! 转储目录所在磁盘的剩余空间低于 `LIVE_SERVER_DUMP_MIN_FREE_MB` 时：
+//! - 直播结束时的自动转储改为精简转储（只含消息记录，不含用户映射、统计和排行榜）
+//! - 拒绝主播手动保存聊天快照（`savesnapshot`），并返回原因
+//! - 输出警告并推送 `disk_low` 事件
+//!
+//! 无法查询剩余空间（如非 Unix 系统）时视为空间充足。
+
+use crate::state::chat::ChatRoom;
+use crate::state::events::{EventBus, StreamEvent};
+use crate::state::health;
+use std::path::{Path, PathBuf};
+
+/// 磁盘剩余空间不足
+#[derive(Debug, Clone, Copy)]
+pub struct DiskLow {
+    /// 剩余可用空间（字节）
+    pub available_bytes: u64,
+    /// 配置的最低剩余空间（字节）
+    pub min_free_bytes: u64,
+}
+
+impl DiskLow {
+    /// 面向主播的说明
+    pub fn describe(&self) -> String {
+        format!(
+            "转储目录所在磁盘剩余 {} MB，低于 {} MB",
+            self.available_bytes / (1024 * 1024),
+            self.min_free_bytes / (1024 * 1024)
+        )
+    }
+
+    /// 输出警告并推送 `disk_low` 事件
+    pub fn report(&self, events: &EventBus) {
+        tracing::warn!("{}，请尽快清理磁盘", self.describe());
+        events.publish(StreamEvent::DiskLow {
+            available_mb: self.available_bytes / (1024 * 1024),
+        });
+    }
+}
+
+/// 检查转储目录所在磁盘的剩余空间
+///
+/// ### 参数
+/// - `dump_path`: 转储目录
+/// - `min_free_bytes`: 最低剩余空间（字节，0 表示不检查）
+///
+/// ### 返回值
+/// 空间不足时返回 `Some`
+pub fn check(dump_path: &Path, min_free_bytes: u64) -> Option<DiskLow> {
+    if min_free_bytes == 0 {
+        return None;
+    }
+    // 转储目录尚未创建时查询其上级目录
+    let available_bytes = dump_path
+        .ancestors()
+        .find(|p| p.exists())
+        .and_then(health::disk_available)?;
+    (available_bytes < min_free_bytes).then_some(DiskLow { available_bytes, min_free_bytes })
+}
+
+/// 转储直播结束时关闭的聊天室
+///
+/// 空间不足时改为精简转储，并输出警告、推送事件
+///
+/// ### 参数
+/// - `room`: 已关闭的聊天室
+/// - `min_free_bytes`: 最低剩余空间（字节，0 表示不检查）
+/// - `events`: 事件总线
+///
+/// ### 返回值
+/// 同 `ChatRoom::dump_full`
+pub fn dump_closed_room(room: &ChatRoom, min_free_bytes: u64, events: &EventBus) -> Result<PathBuf, String> {
+    match check(&room.dump_path, min_free_bytes) {
+        Some(low) => {
+            low.report(events);
+            tracing::warn!("磁盘空间不足，本场聊天记录改为精简转储");
+            room.dump_brief()
+        }
+        None => room.dump_full(),
+    }
+}
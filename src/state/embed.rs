@@ -0,0 +1,134 @@
+//! # 图片嵌入模块
+//!
+//! 消息中来自白名单图床的图片链接会被标记为可嵌入（`embed: true`），
+//! 并附带提取出的链接信息，供前端直接内联显示，无需服务器保存上传文件。
+//!
+//! 为防止刷屏，嵌入受单用户与全局的每分钟次数限制，主播也可随时关闭嵌入。
+//! 超出限制或被关闭时消息照常发送，只是不再标记为嵌入。
+
+use super::link_policy;
+use crate::domain::Uid;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use url::Url;
+
+/// 可嵌入的图片扩展名
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "avif"];
+
+/// 频率限制的统计窗口（秒）
+const WINDOW_SECS: i64 = 60;
+
+/// 嵌入链接信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedMeta {
+    /// 图片链接
+    pub url: String,
+    /// 图片所在域名
+    pub host: String,
+    /// 图片格式（扩展名，小写）
+    pub format: String,
+}
+
+impl EmbedMeta {
+    /// 从消息中提取第一个可嵌入的图片链接
+    ///
+    /// ### 参数
+    /// - `content`: 原始消息
+    /// - `hosts`: 允许嵌入的图床域名（包含其子域名）
+    ///
+    /// ### 返回值
+    /// 白名单域名下以图片扩展名结尾的第一个 http(s) 链接
+    pub fn extract(content: &str, hosts: &[String]) -> Option<Self> {
+        if hosts.is_empty() {
+            return None;
+        }
+        link_policy::links(content).into_iter().find_map(|link| {
+            let url = Url::parse(link).ok()?;
+            let host = url.host_str()?.to_lowercase();
+            if !link_policy::is_whitelisted(&host, hosts) {
+                return None;
+            }
+            let format = url
+                .path()
+                .rsplit_once('.')
+                .map(|(_, ext)| ext.to_lowercase())
+                .filter(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))?;
+            Some(Self {
+                url: url.to_string(),
+                host,
+                format,
+            })
+        })
+    }
+}
+
+/// 嵌入频率限制与开关
+#[derive(Debug)]
+pub struct EmbedLimiter {
+    /// 主播是否允许嵌入
+    pub enabled: bool,
+    /// 每个用户最近一个窗口内的嵌入时间
+    per_user: HashMap<Uid, VecDeque<i64>>,
+    /// 全房间最近一个窗口内的嵌入时间
+    global: VecDeque<i64>,
+}
+
+impl EmbedLimiter {
+    /// 创建嵌入限制器（默认允许嵌入）
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            per_user: HashMap::new(),
+            global: VecDeque::new(),
+        }
+    }
+
+    /// 尝试占用一次嵌入额度
+    ///
+    /// ### 参数
+    /// - `uid`: 发送者 UID
+    /// - `now`: 当前时间戳（秒）
+    /// - `user_limit`: 单用户每分钟上限（0 表示不限制）
+    /// - `global_limit`: 全房间每分钟上限（0 表示不限制）
+    ///
+    /// ### 返回值
+    /// 已关闭嵌入或超出任一限制时返回 false
+    pub fn try_acquire(&mut self, uid: Uid, now: i64, user_limit: usize, global_limit: usize) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let cutoff = now - WINDOW_SECS;
+        while self.global.front().is_some_and(|&t| t <= cutoff) {
+            self.global.pop_front();
+        }
+        self.per_user.retain(|_, q| {
+            while q.front().is_some_and(|&t| t <= cutoff) {
+                q.pop_front();
+            }
+            !q.is_empty()
+        });
+
+        let user_count = self.per_user.get(&uid).map_or(0, |q| q.len());
+        if (global_limit > 0 && self.global.len() >= global_limit)
+            || (user_limit > 0 && user_count >= user_limit)
+        {
+            return false;
+        }
+        self.global.push_back(now);
+        self.per_user.entry(uid).or_default().push_back(now);
+        true
+    }
+
+    /// 清空计数并恢复默认开关
+    pub fn reset(&mut self) {
+        self.enabled = true;
+        self.per_user.clear();
+        self.global.clear();
+    }
+}
+
+impl Default for EmbedLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
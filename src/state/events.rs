@@ -0,0 +1,87 @@
+//! # 事件总线模块
+//!
+//! 基于 `tokio::sync::broadcast` 的进程内事件总线，
+//! 用于将直播状态变化推送给 SSE 订阅者等消费方。
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// 事件通道容量（落后过多的订阅者会丢失旧事件）
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// 直播事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// 开始推流（新的一场直播），`activated` 为开播前排队、此时转为已授权的观众数
+    StreamLive { activated: usize },
+    /// 直播已结束
+    StreamEnded,
+    /// 推流中断，等待编码器重连
+    StreamPaused,
+    /// 推流已恢复
+    StreamResumed,
+    /// 主播设置或清除了状态提示（`overlay` 为 `None` 表示清除）
+    OverlayChanged { overlay: Option<String> },
+    /// 有新的聊天举报（只包含待处理数量，详情需主播通过 `getreports` 获取）
+    ReportFiled { pending: usize },
+    /// 转储目录所在磁盘的剩余空间低于 `LIVE_SERVER_DUMP_MIN_FREE_MB`（聊天记录改为精简转储）
+    DiskLow { available_mb: u64 },
+    /// 收到打赏（供直播叠加层显示）
+    TipReceived { name: String, amount: f64, currency: String },
+    /// 直播叠加层已显示到指定消息（供协管界面标出尚未上屏的消息）
+    MessagesShown { id: String },
+    /// 大量观众长时间卡在答题阶段或近期答错率过高（`failure_percent` 在作答次数不足时为 `None`）
+    QuizTrouble { stuck_pending: usize, failure_percent: Option<u32> },
+    /// 推流码率持续为零，主播可能已离开（`away` 为 `false` 表示码率已恢复）
+    StreamerAway { away: bool },
+}
+
+impl StreamEvent {
+    /// 事件名称（用作 SSE 的 event 字段）
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StreamLive { .. } => "stream_live",
+            Self::StreamEnded => "stream_ended",
+            Self::StreamPaused => "stream_paused",
+            Self::StreamResumed => "stream_resumed",
+            Self::OverlayChanged { .. } => "overlay_changed",
+            Self::ReportFiled { .. } => "report_filed",
+            Self::DiskLow { .. } => "disk_low",
+            Self::TipReceived { .. } => "tip_received",
+            Self::MessagesShown { .. } => "messages_shown",
+            Self::QuizTrouble { .. } => "quiz_trouble",
+            Self::StreamerAway { .. } => "streamer_away",
+        }
+    }
+}
+
+/// 事件总线
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<StreamEvent>,
+}
+
+impl EventBus {
+    /// 创建新的事件总线
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// 发布事件（没有订阅者时静默丢弃）
+    pub fn publish(&self, event: StreamEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// 订阅事件
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
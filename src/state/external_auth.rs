@@ -0,0 +1,134 @@
+//! # 外部身份认证模块
+//!
+//! 将观众鉴权委托给上游身份系统，通过认证的观众无需答题：
+//! - 受信任请求头：由反向代理（如 oauth2-proxy）注入，例如 `X-Auth-User`。
+//!   只接受来自受信任代理地址段的连接携带的该请求头，其他来源一律忽略
+//! - OIDC：客户端携带 `Authorization: Bearer <access_token>`，
+//!   服务端请求身份提供方的 userinfo 接口校验令牌
+//!
+//! 认证得到的身份同时作为观众在聊天室中的固定昵称。
+
+use crate::config::IpRange;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// OIDC 校验结果缓存时长（秒）
+const OIDC_CACHE_SECS: i64 = 300;
+
+/// 建立到 userinfo 接口连接的超时
+const OIDC_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// userinfo 请求的总超时（观众的 connect 请求会等待该请求）
+const OIDC_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 外部身份认证
+pub struct ExternalAuth {
+    /// 受信任的身份请求头名称
+    trusted_header: Option<String>,
+    /// 允许注入身份请求头的代理地址段
+    trusted_proxies: Vec<IpRange>,
+    /// OIDC userinfo 接口地址
+    userinfo_url: Option<String>,
+    /// OIDC 校验结果缓存：access_token -> (身份, 过期时间)
+    cache: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+    /// HTTP 客户端
+    client: reqwest::Client,
+}
+
+impl ExternalAuth {
+    /// 创建外部身份认证
+    ///
+    /// ### 参数
+    /// - `trusted_header`: 受信任的身份请求头名称
+    /// - `trusted_proxies`: 允许注入身份请求头的代理地址段
+    /// - `userinfo_url`: OIDC userinfo 接口地址
+    ///
+    /// ### 返回值
+    /// 身份请求头和 userinfo 接口均未配置时返回 `None`
+    pub fn new(
+        trusted_header: Option<String>,
+        trusted_proxies: Vec<IpRange>,
+        userinfo_url: Option<String>,
+    ) -> Option<Self> {
+        if trusted_header.is_none() && userinfo_url.is_none() {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .connect_timeout(OIDC_CONNECT_TIMEOUT)
+            .timeout(OIDC_REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Some(Self {
+            trusted_header,
+            trusted_proxies,
+            userinfo_url,
+            cache: Mutex::new(HashMap::new()),
+            client,
+        })
+    }
+
+    /// 从请求中识别外部身份
+    ///
+    /// ### 参数
+    /// - `headers`: 请求头
+    /// - `peer`: 连接的对端地址（不是 `X-Forwarded-For` 中的地址）
+    ///
+    /// ### 返回值
+    /// - `Some(身份)`: 认证通过
+    /// - `None`: 请求未携带身份或校验失败
+    pub async fn identify(&self, headers: &HeaderMap, peer: IpAddr) -> Option<String> {
+        let from_proxy = self.trusted_proxies.iter().any(|range| range.contains(peer));
+        if let Some(name) = self.trusted_header.as_ref().filter(|_| from_proxy) {
+            let identity = headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty());
+            if let Some(identity) = identity {
+                return Some(identity.to_string());
+            }
+        }
+
+        let url = self.userinfo_url.as_ref()?;
+        let token = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))?;
+
+        let now = Utc::now();
+        {
+            let mut cache = self.cache.lock();
+            cache.retain(|_, (_, expiry)| *expiry > now);
+            if let Some((identity, _)) = cache.get(token) {
+                return Some(identity.clone());
+            }
+        }
+
+        let resp = self.client.get(url).bearer_auth(token).send().await;
+        let userinfo: serde_json::Value = match resp {
+            Ok(resp) if resp.status().is_success() => resp.json().await.ok()?,
+            Ok(resp) => {
+                tracing::debug!("OIDC 令牌校验失败: {}", resp.status());
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("请求 OIDC userinfo 失败: {}", e);
+                return None;
+            }
+        };
+
+        let identity = ["preferred_username", "name", "sub"]
+            .iter()
+            .find_map(|key| userinfo.get(key).and_then(|v| v.as_str()))
+            .filter(|v| !v.is_empty())?
+            .to_string();
+        self.cache.lock().insert(
+            token.to_string(),
+            (identity.clone(), now + Duration::seconds(OIDC_CACHE_SECS)),
+        );
+        Some(identity)
+    }
+}
@@ -0,0 +1,84 @@
+//! # 外部前端模块
+//!
+//! 前端静态页面可以托管在其他域名上（如 GitHub Pages、CDN），跨域调用观众接口。
+//! 每个允许的前端来源绑定一个公开的 API 令牌（`LIVE_SERVER_FRONTEND_TOKENS`，随前端页面发布，不是密钥）：
+//! - 来源已登记的请求必须携带与之绑定的令牌
+//! - 每个令牌按客户端 IP 限制每分钟请求数（`LIVE_SERVER_FRONTEND_RATE_LIMIT`），
+//!   客户端 IP 只在连接来自受信任代理时取自 `X-Forwarded-For`，伪造该头无法绕过限制
+//! - 未登记的来源得不到 CORS 响应头，浏览器拒绝跨域读取；同源请求不受影响
+//!
+//! `Origin` 头可由非浏览器客户端伪造，来源绑定只防止其他网站直接借用令牌，滥用防护依靠频率限制。
+
+use crate::config::{Config, FrontendToken};
+use crate::domain::ClientIp;
+use chrono::Utc;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// 频率限制的统计窗口（秒）
+const WINDOW_SECS: i64 = 60;
+
+/// 超过该数量的计数记录时清理已过期的窗口
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// 计数记录数量的硬上限（清理后仍达到上限时，本窗口内不再接受新的客户端）
+const MAX_TRACKED: usize = 65_536;
+
+/// 查找来源登记的外部前端
+///
+/// ### 返回值
+/// 来源未登记（含同源请求）时返回 `None`
+pub fn lookup<'a>(config: &'a Config, origin: &str) -> Option<&'a FrontendToken> {
+    config.frontend_tokens.iter().find(|t| t.origin == origin)
+}
+
+/// 外部前端请求频率限制
+#[derive(Debug, Default)]
+pub struct FrontendLimiter {
+    /// (令牌, 客户端 IP) -> (窗口编号, 窗口内请求数)
+    windows: Mutex<HashMap<(String, ClientIp), (i64, u32)>>,
+}
+
+impl FrontendLimiter {
+    /// 创建空的频率限制器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次请求
+    ///
+    /// ### 参数
+    /// - `token`: 请求携带的 API 令牌
+    /// - `ip`: 客户端 IP
+    /// - `limit`: 每分钟请求数上限（0 表示不限制）
+    ///
+    /// ### 返回值
+    /// - `Ok(())`: 未超出限制
+    /// - `Err(秒数)`: 已超出限制（或计数记录已满），距当前窗口结束的秒数
+    pub fn hit(&self, token: &str, ip: &ClientIp, limit: u32) -> Result<(), u64> {
+        if limit == 0 {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp();
+        let window = now.div_euclid(WINDOW_SECS);
+        let retry_after = (WINDOW_SECS - now.rem_euclid(WINDOW_SECS)) as u64;
+        let mut windows = self.windows.lock();
+        if windows.len() >= PRUNE_THRESHOLD {
+            windows.retain(|_, (w, _)| *w == window);
+        }
+        let key = (token.to_string(), ip.clone());
+        if windows.len() >= MAX_TRACKED && !windows.contains_key(&key) {
+            tracing::debug!("外部前端频率限制的计数记录已达上限 {}，暂时拒绝新的客户端", MAX_TRACKED);
+            return Err(retry_after);
+        }
+        let entry = windows.entry(key).or_insert((window, 0));
+        if entry.0 != window {
+            *entry = (window, 0);
+        }
+        if entry.1 >= limit {
+            return Err(retry_after);
+        }
+        entry.1 += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! # 健康状态模块
+//!
+//! 记录监听端口绑定情况和各后台任务的心跳，供 `/admin/status` 汇总服务健康状态。
+//!
+//! 后台任务每轮循环调用 `beat` 上报心跳；超过三个周期未上报即视为任务已停止。
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// 心跳超时倍数：超过 `周期 × 倍数` 未上报视为任务已停止
+const STALE_FACTOR: u32 = 3;
+
+/// 单个后台任务的心跳记录
+#[derive(Debug, Clone, Copy)]
+struct Beat {
+    /// 最近一次心跳时间（尚未上报时为 `None`）
+    last: Option<Instant>,
+    /// 注册时间
+    since: Instant,
+    /// 预期的心跳周期
+    period: Duration,
+}
+
+/// 后台任务状态
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    /// 任务名称
+    pub name: &'static str,
+    /// 任务是否存活
+    pub alive: bool,
+    /// 距离最近一次心跳的秒数（尚未上报时为 `None`）
+    pub last_beat_secs: Option<u64>,
+    /// 预期的心跳周期（秒）
+    pub period_secs: u64,
+}
+
+/// 服务健康状态
+#[derive(Debug, Default)]
+pub struct Health {
+    /// 已绑定的监听地址
+    listeners: RwLock<Vec<SocketAddr>>,
+    /// 任务名称 -> 心跳记录
+    tasks: RwLock<BTreeMap<&'static str, Beat>>,
+}
+
+impl Health {
+    /// 创建空的健康状态
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录监听地址已绑定
+    pub fn add_listener(&self, addr: SocketAddr) {
+        self.listeners.write().push(addr);
+    }
+
+    /// 获取已绑定的监听地址
+    pub fn listeners(&self) -> Vec<SocketAddr> {
+        self.listeners.read().clone()
+    }
+
+    /// 注册后台任务
+    ///
+    /// ### 参数
+    /// - `name`: 任务名称
+    /// - `period_secs`: 预期的心跳周期（秒），带退避的任务应传入最大退避间隔
+    pub fn register(&self, name: &'static str, period_secs: u64) {
+        self.tasks.write().insert(
+            name,
+            Beat {
+                last: None,
+                since: Instant::now(),
+                period: Duration::from_secs(period_secs.max(1)),
+            },
+        );
+    }
+
+    /// 上报任务心跳
+    pub fn beat(&self, name: &'static str) {
+        if let Some(beat) = self.tasks.write().get_mut(name) {
+            beat.last = Some(Instant::now());
+        }
+    }
+
+    /// 获取所有已注册任务的状态
+    pub fn tasks(&self) -> Vec<TaskStatus> {
+        let now = Instant::now();
+        self.tasks
+            .read()
+            .iter()
+            .map(|(name, beat)| {
+                let reference = beat.last.unwrap_or(beat.since);
+                TaskStatus {
+                    name,
+                    alive: now.duration_since(reference) <= beat.period * STALE_FACTOR,
+                    last_beat_secs: beat.last.map(|t| now.duration_since(t).as_secs()),
+                    period_secs: beat.period.as_secs(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// 转储目录剩余空间低于此值时视为不足（100 MiB）
+pub const MIN_DISK_BYTES: u64 = 100 * 1024 * 1024;
+
+/// 查询路径所在文件系统的剩余可用空间
+///
+/// ### 返回值
+/// - `Some(bytes)`: 非特权用户可用的字节数
+/// - `None`: 查询失败或当前平台不支持
+#[cfg(unix)]
+pub fn disk_available(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: c_path 是以 NUL 结尾的有效路径，stat 仅在调用成功后读取
+    let ret = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+    if ret != 0 {
+        return None;
+    }
+    // SAFETY: statvfs 返回 0 时已完整写入 stat
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// 查询路径所在文件系统的剩余可用空间（当前平台不支持）
+#[cfg(not(unix))]
+pub fn disk_available(_path: &Path) -> Option<u64> {
+    None
+}
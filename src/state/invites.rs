@@ -0,0 +1,121 @@
+//! # 邀请模块
+//!
+//! 主播签发的入场凭证，持有者连接时可跳过答题直接放行。
+//!
+//! 目前支持限时访客通行证：主播指定有效时长（如 30 分钟），得到一个短码，
+//! 有效期内任何人携带该短码连接都可入场，次数不限，适合"接下来半小时直接放人进来"的场景。
+//! 通行证可随时撤销，过期后自动清除。
+
+use crate::ids;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// 同时有效的访客通行证数量上限
+pub const MAX_GUEST_PASSES: usize = 20;
+
+/// 访客通行证的最长有效时长（分钟）
+pub const MAX_GUEST_PASS_MINUTES: u64 = 24 * 60;
+
+/// 访客通行证短码长度
+const GUEST_PASS_CODE_LEN: usize = 8;
+
+/// 限时访客通行证
+#[derive(Debug, Clone, Serialize)]
+pub struct GuestPass {
+    /// 短码（连接时以 `guest=<短码>` 携带）
+    pub code: String,
+    /// 签发时间
+    pub created_at: DateTime<Utc>,
+    /// 失效时间
+    pub expires_at: DateTime<Utc>,
+    /// 已入场次数
+    pub uses: u32,
+}
+
+impl GuestPass {
+    /// 是否仍在有效期内
+    fn is_valid(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// 邀请簿
+#[derive(Debug, Default)]
+pub struct InviteBook {
+    /// 有效期内的访客通行证（按签发先后）
+    guest_passes: Mutex<Vec<GuestPass>>,
+}
+
+impl InviteBook {
+    /// 创建空的邀请簿
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 签发一张访客通行证
+    ///
+    /// ### 参数
+    /// - `minutes`: 有效时长（分钟，不超过 `MAX_GUEST_PASS_MINUTES`）
+    ///
+    /// ### 返回值
+    /// - `Some(pass)`: 签发成功
+    /// - `None`: 有效的通行证已达上限
+    pub fn issue_guest_pass(&self, minutes: u64) -> Option<GuestPass> {
+        let now = Utc::now();
+        let mut passes = self.guest_passes.lock();
+        passes.retain(|p| p.is_valid(now));
+        if passes.len() >= MAX_GUEST_PASSES {
+            return None;
+        }
+        let pass = GuestPass {
+            code: ids::short_code(GUEST_PASS_CODE_LEN),
+            created_at: now,
+            expires_at: now + Duration::minutes(minutes.min(MAX_GUEST_PASS_MINUTES) as i64),
+            uses: 0,
+        };
+        passes.push(pass.clone());
+        Some(pass)
+    }
+
+    /// 使用访客通行证入场
+    ///
+    /// ### 参数
+    /// - `code`: 观众携带的短码（不区分大小写）
+    ///
+    /// ### 返回值
+    /// 短码有效时记录一次入场并返回 `true`
+    pub fn redeem_guest_pass(&self, code: &str) -> bool {
+        let now = Utc::now();
+        let mut passes = self.guest_passes.lock();
+        match passes
+            .iter_mut()
+            .find(|p| p.is_valid(now) && p.code.eq_ignore_ascii_case(code.trim()))
+        {
+            Some(pass) => {
+                pass.uses = pass.uses.saturating_add(1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 撤销一张访客通行证
+    ///
+    /// ### 返回值
+    /// 通行证不存在（已过期或已撤销）时返回 `false`
+    pub fn revoke_guest_pass(&self, code: &str) -> bool {
+        let mut passes = self.guest_passes.lock();
+        let before = passes.len();
+        passes.retain(|p| !p.code.eq_ignore_ascii_case(code.trim()));
+        passes.len() != before
+    }
+
+    /// 列出有效期内的访客通行证
+    pub fn guest_passes(&self) -> Vec<GuestPass> {
+        let now = Utc::now();
+        let mut passes = self.guest_passes.lock();
+        passes.retain(|p| p.is_valid(now));
+        passes.clone()
+    }
+}
@@ -0,0 +1,88 @@
+//! # 聊天链接策略模块
+//!
+//! 在消息写入聊天室之前，按 `ChatUrlPolicy` 处理其中的 http(s) 链接，
+//! 防止观众被垃圾链接引导到恶意页面。
+
+use crate::config::ChatUrlPolicy;
+use url::Url;
+
+/// 链接被移除后的占位文本
+pub const STRIPPED_LINK: &str = "[链接已移除]";
+
+/// 跳转警告页路径
+pub const REDIRECT_PATH: &str = "/chat/redirect";
+
+/// 按策略处理消息中的链接
+///
+/// ### 参数
+/// - `content`: 原始消息
+/// - `policy`: 链接策略
+/// - `whitelist`: 白名单域名（小写，包含其子域名）
+///
+/// ### 返回值
+/// 处理后的消息
+pub fn apply(content: &str, policy: ChatUrlPolicy, whitelist: &[String]) -> String {
+    if policy == ChatUrlPolicy::Allow {
+        return content.to_string();
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = find_url_start(rest) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let end = url_end(tail);
+        let link = &tail[..end];
+
+        let allowed = Url::parse(link)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_lowercase))
+            .is_some_and(|host| is_whitelisted(&host, whitelist));
+        match policy {
+            _ if allowed => out.push_str(link),
+            ChatUrlPolicy::Redirect => {
+                let encoded: String = url::form_urlencoded::byte_serialize(link.as_bytes()).collect();
+                out.push_str(&format!("{}?u={}", REDIRECT_PATH, encoded));
+            }
+            _ => out.push_str(STRIPPED_LINK),
+        }
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 提取消息中的所有 http(s) 链接
+pub fn links(content: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = content;
+    while let Some(start) = find_url_start(rest) {
+        let tail = &rest[start..];
+        let end = url_end(tail);
+        found.push(&tail[..end]);
+        rest = &tail[end..];
+    }
+    found
+}
+
+/// 查找链接的结束位置（遇到空白、非 ASCII 字符或 HTML 特殊字符为止）
+fn url_end(tail: &str) -> usize {
+    tail.find(|c: char| c.is_whitespace() || !c.is_ascii() || matches!(c, '<' | '>' | '"'))
+        .unwrap_or(tail.len())
+}
+
+/// 查找下一个 http(s) 链接的起始位置（大小写不敏感）
+fn find_url_start(s: &str) -> Option<usize> {
+    let lower = s.to_ascii_lowercase();
+    [lower.find("http://"), lower.find("https://")]
+        .into_iter()
+        .flatten()
+        .min()
+}
+
+/// 判断域名是否在白名单中（包含子域名）
+pub fn is_whitelisted(host: &str, whitelist: &[String]) -> bool {
+    whitelist
+        .iter()
+        .any(|d| host == d || host.ends_with(&format!(".{}", d)))
+}
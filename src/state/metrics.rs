@@ -0,0 +1,450 @@
+//! # 运行指标模块
+//!
+//! 进程内统计 SRS 回调的处理耗时与拒绝原因、各播放协议的观众人数和当前直播场次，
+//! 以 Prometheus 文本格式通过 `/admin/metrics` 导出。
+//!
+//! 运维人员可据此区分推流失败是配置问题（如密钥错误）还是客户端问题（如未答题即拉流）。
+//!
+//! 观众拉流的开始 / 停止次数和重连次数（同一会话再次 on_play）也在此统计，
+//! 近 5 分钟重连数陡增说明推流端上行不稳、观众播放器在集体重连，而不是观众在离开。
+//!
+//! 后台聚合任务定期根据近 5 分钟的原始数据计算派生指标（答错率、SRS 回调拒绝率、
+//! 聊天请求 p95 耗时），运维人员可直接对其设置阈值告警，无需编写复杂的 PromQL。
+
+use super::health::Health;
+use super::quiz_health::QuizHealth;
+use super::srs_api::{ViewerBreakdown, ViewerProtocol};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 耗时直方图的桶上界（秒）
+const LATENCY_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// 近期统计（观众流失、派生指标）的滑动窗口
+const RECENT_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// 派生指标的聚合间隔
+const AGGREGATE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 窗口内最多保留的聊天请求耗时样本数（超出时丢弃最早的样本）
+const MAX_LATENCY_SAMPLES: usize = 10_000;
+
+/// 后台任务名称（用于健康检查）
+const TASK_NAME: &str = "metrics_aggregate";
+
+/// SRS 回调拒绝原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RejectReason {
+    /// 推流未携带密钥
+    MissingSecret,
+    /// 推流密钥错误
+    BadSecret,
+    /// 来源 IP 处于密钥猜测锁定期
+    SecretLocked,
+    /// 准入脚本拒绝
+    ScriptDenied,
+    /// 拉流会话未注册
+    UnknownSession,
+    /// 拉流会话未获得许可（未答题、被封禁或直播已结束）
+    NotAuthorized,
+    /// 未知的回调类型
+    UnknownAction,
+    /// 回调未携带正确的令牌或来源地址不在允许范围内
+    Unauthenticated,
+    /// 推流的 app / stream 不合法或不在允许列表内
+    BadStreamTarget,
+    /// 同时观看人数已满，观众进入等候室
+    ViewerCap,
+    /// 上行带宽已达上限，不再接纳新观众
+    BandwidthCeiling,
+    /// 主播刚手动结束直播，拒绝编码器自动重连
+    RecentlyEnded,
+}
+
+impl RejectReason {
+    /// 将拒绝原因转换为字符串（用作指标标签）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MissingSecret => "missing_secret",
+            Self::BadSecret => "bad_secret",
+            Self::SecretLocked => "secret_locked",
+            Self::ScriptDenied => "script_denied",
+            Self::UnknownSession => "unknown_session",
+            Self::NotAuthorized => "not_authorized",
+            Self::UnknownAction => "unknown_action",
+            Self::Unauthenticated => "unauthenticated",
+            Self::BadStreamTarget => "bad_stream_target",
+            Self::ViewerCap => "viewer_cap",
+            Self::BandwidthCeiling => "bandwidth_ceiling",
+            Self::RecentlyEnded => "recently_ended",
+        }
+    }
+}
+
+/// 耗时直方图
+#[derive(Debug, Default)]
+struct Histogram {
+    /// 各桶的累计计数（与 `LATENCY_BUCKETS` 一一对应）
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    /// 观测值总和（秒）
+    sum: f64,
+    /// 观测次数
+    count: u64,
+}
+
+impl Histogram {
+    /// 记录一次观测
+    fn observe(&mut self, secs: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// 观众拉流开始 / 停止统计
+#[derive(Debug, Default)]
+struct ViewerChurn {
+    /// 累计开始拉流次数
+    plays: u64,
+    /// 累计停止拉流次数
+    stops: u64,
+    /// 累计重连次数
+    reconnects: u64,
+    /// 窗口内的重连时刻
+    recent_reconnects: VecDeque<Instant>,
+    /// 窗口内的停止时刻
+    recent_stops: VecDeque<Instant>,
+}
+
+impl ViewerChurn {
+    /// 丢弃滑出窗口的记录
+    fn prune(&mut self, now: Instant) {
+        for recent in [&mut self.recent_reconnects, &mut self.recent_stops] {
+            while recent.front().is_some_and(|t| now.duration_since(*t) > RECENT_WINDOW) {
+                recent.pop_front();
+            }
+        }
+    }
+}
+
+/// 近 5 分钟的观众流失概况
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ChurnSummary {
+    /// 近 5 分钟的重连次数（同一会话再次开始拉流）
+    pub reconnects_5m: usize,
+    /// 近 5 分钟的停止拉流次数
+    pub stops_5m: usize,
+}
+
+/// 由聚合任务定期计算的派生指标（样本不足时为 `None`）
+#[derive(Debug, Default, Clone, Copy)]
+struct DerivedGauges {
+    /// 近 5 分钟答错率（0 ~ 1）
+    quiz_failure_ratio: Option<f64>,
+    /// 近 5 分钟通过来源校验的 SRS 回调中被拒绝的比例（0 ~ 1）
+    callback_error_ratio: Option<f64>,
+    /// 近 5 分钟聊天请求处理耗时的 p95（秒）
+    chat_latency_p95: Option<f64>,
+}
+
+/// 移除窗口外的样本
+fn prune_window<T>(recent: &mut VecDeque<(Instant, T)>, now: Instant) {
+    while recent.front().is_some_and(|(t, _)| now.duration_since(*t) > RECENT_WINDOW) {
+        recent.pop_front();
+    }
+}
+
+/// 运行指标
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// 回调类型 -> 处理耗时
+    callback_latency: Mutex<BTreeMap<&'static str, Histogram>>,
+    /// (回调类型, 拒绝原因) -> 次数
+    callback_rejections: Mutex<BTreeMap<(&'static str, RejectReason), u64>>,
+    /// 当前各播放协议的观众数
+    viewers: Mutex<ViewerBreakdown>,
+    /// 当前直播场次 ID（未推流时为 `None`）
+    stream_session: Mutex<Option<String>>,
+    /// SRS 上行带宽占用（kbps，未推流时为 `None`）
+    send_kbps: Mutex<Option<u64>>,
+    /// 观众拉流开始 / 停止统计
+    churn: Mutex<ViewerChurn>,
+    /// 窗口内通过来源校验的 SRS 回调：(处理完成时刻, 是否被拒绝)
+    recent_callbacks: Mutex<VecDeque<(Instant, bool)>>,
+    /// 窗口内的聊天请求：(处理完成时刻, 耗时秒数)
+    recent_chat_latency: Mutex<VecDeque<(Instant, f64)>>,
+    /// 派生指标
+    derived: Mutex<DerivedGauges>,
+}
+
+impl Metrics {
+    /// 创建空的指标集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 将回调类型归一化为固定标签，避免任意字符串导致标签数量膨胀
+    pub fn callback_label(action: &str) -> &'static str {
+        match action {
+            "on_publish" => "on_publish",
+            "on_play" => "on_play",
+            "on_unpublish" => "on_unpublish",
+            "on_stop" => "on_stop",
+            "on_dvr" => "on_dvr",
+            _ => "unknown",
+        }
+    }
+
+    /// 记录一次回调的处理耗时和结果
+    ///
+    /// ### 参数
+    /// - `action`: 回调类型标签
+    /// - `elapsed`: 处理耗时
+    /// - `rejected`: 是否被拒绝（计入近 5 分钟回调拒绝率）
+    pub fn observe_callback(&self, action: &'static str, elapsed: Duration, rejected: bool) {
+        self.callback_latency
+            .lock()
+            .entry(action)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+        self.recent_callbacks.lock().push_back((Instant::now(), rejected));
+    }
+
+    /// 记录一次回调拒绝
+    pub fn reject_callback(&self, action: &'static str, reason: RejectReason) {
+        *self
+            .callback_rejections
+            .lock()
+            .entry((action, reason))
+            .or_default() += 1;
+    }
+
+    /// 记录一次聊天请求的处理耗时
+    pub fn observe_chat(&self, elapsed: Duration) {
+        let mut recent = self.recent_chat_latency.lock();
+        if recent.len() >= MAX_LATENCY_SAMPLES {
+            recent.pop_front();
+        }
+        recent.push_back((Instant::now(), elapsed.as_secs_f64()));
+    }
+
+    /// 根据近 5 分钟的原始数据重新计算派生指标
+    ///
+    /// ### 参数
+    /// - `quiz_failure_ratio`: 近 5 分钟答错率（作答次数不足时为 `None`）
+    pub fn aggregate(&self, quiz_failure_ratio: Option<f64>) {
+        let now = Instant::now();
+
+        let callback_error_ratio = {
+            let mut recent = self.recent_callbacks.lock();
+            prune_window(&mut recent, now);
+            let rejected = recent.iter().filter(|(_, rejected)| *rejected).count();
+            (!recent.is_empty()).then(|| rejected as f64 / recent.len() as f64)
+        };
+
+        let chat_latency_p95 = {
+            let mut recent = self.recent_chat_latency.lock();
+            prune_window(&mut recent, now);
+            let mut samples: Vec<f64> = recent.iter().map(|(_, secs)| *secs).collect();
+            samples.sort_by(f64::total_cmp);
+            let rank = (samples.len() as f64 * 0.95).ceil() as usize;
+            rank.checked_sub(1).and_then(|i| samples.get(i).copied())
+        };
+
+        *self.derived.lock() = DerivedGauges {
+            quiz_failure_ratio,
+            callback_error_ratio,
+            chat_latency_p95,
+        };
+    }
+
+    /// 启动派生指标聚合任务
+    ///
+    /// ### 参数
+    /// - `quiz_health`: 答题健康度统计（提供答错率）
+    /// - `health`: 后台任务健康检查
+    pub fn tick(self: Arc<Self>, quiz_health: Arc<QuizHealth>, health: Arc<Health>) -> JoinHandle<()> {
+        health.register(TASK_NAME, AGGREGATE_INTERVAL.as_secs());
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(AGGREGATE_INTERVAL);
+            loop {
+                interval.tick().await;
+                health.beat(TASK_NAME);
+                self.aggregate(quiz_health.failure_ratio());
+            }
+        })
+    }
+
+    /// 更新各播放协议的观众数
+    pub fn set_viewers(&self, viewers: ViewerBreakdown) {
+        *self.viewers.lock() = viewers;
+    }
+
+    /// 更新 SRS 上行带宽占用
+    pub fn set_send_kbps(&self, kbps: Option<u64>) {
+        *self.send_kbps.lock() = kbps;
+    }
+
+    /// 更新当前直播场次 ID
+    pub fn set_stream_session(&self, session_id: Option<String>) {
+        *self.stream_session.lock() = session_id;
+    }
+
+    /// 记录一次观众开始拉流
+    ///
+    /// ### 参数
+    /// - `reconnect`: 该会话此前是否已经拉过流
+    pub fn record_play(&self, reconnect: bool) {
+        let mut churn = self.churn.lock();
+        churn.plays += 1;
+        if reconnect {
+            let now = Instant::now();
+            churn.reconnects += 1;
+            churn.recent_reconnects.push_back(now);
+            churn.prune(now);
+        }
+    }
+
+    /// 记录一次观众停止拉流
+    pub fn record_stop(&self) {
+        let now = Instant::now();
+        let mut churn = self.churn.lock();
+        churn.stops += 1;
+        churn.recent_stops.push_back(now);
+        churn.prune(now);
+    }
+
+    /// 获取近 5 分钟的观众流失概况
+    pub fn churn(&self) -> ChurnSummary {
+        let mut churn = self.churn.lock();
+        churn.prune(Instant::now());
+        ChurnSummary {
+            reconnects_5m: churn.recent_reconnects.len(),
+            stops_5m: churn.recent_stops.len(),
+        }
+    }
+
+    /// 以 Prometheus 文本格式导出全部指标
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP live_server_srs_callback_duration_seconds SRS 回调处理耗时\n");
+        out.push_str("# TYPE live_server_srs_callback_duration_seconds histogram\n");
+        for (action, h) in self.callback_latency.lock().iter() {
+            for (count, bound) in h.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "live_server_srs_callback_duration_seconds_bucket{{action=\"{}\",le=\"{}\"}} {}",
+                    action, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "live_server_srs_callback_duration_seconds_bucket{{action=\"{}\",le=\"+Inf\"}} {}",
+                action, h.count
+            );
+            let _ = writeln!(
+                out,
+                "live_server_srs_callback_duration_seconds_sum{{action=\"{}\"}} {}",
+                action, h.sum
+            );
+            let _ = writeln!(
+                out,
+                "live_server_srs_callback_duration_seconds_count{{action=\"{}\"}} {}",
+                action, h.count
+            );
+        }
+
+        out.push_str("# HELP live_server_srs_callback_rejections_total SRS 回调拒绝次数（按原因）\n");
+        out.push_str("# TYPE live_server_srs_callback_rejections_total counter\n");
+        for ((action, reason), count) in self.callback_rejections.lock().iter() {
+            let _ = writeln!(
+                out,
+                "live_server_srs_callback_rejections_total{{action=\"{}\",reason=\"{}\"}} {}",
+                action,
+                reason.as_str(),
+                count
+            );
+        }
+
+        out.push_str("# HELP live_server_viewers 当前直播各播放协议的观众数\n");
+        out.push_str("# TYPE live_server_viewers gauge\n");
+        let viewers = *self.viewers.lock();
+        for protocol in ViewerProtocol::ALL {
+            let _ = writeln!(
+                out,
+                "live_server_viewers{{protocol=\"{}\"}} {}",
+                protocol.as_str(),
+                viewers.get(protocol)
+            );
+        }
+
+        {
+            let mut churn = self.churn.lock();
+            churn.prune(Instant::now());
+            for (name, help, value) in [
+                ("live_server_viewer_plays_total", "观众开始拉流次数", churn.plays),
+                ("live_server_viewer_stops_total", "观众停止拉流次数", churn.stops),
+                ("live_server_viewer_reconnects_total", "观众重连次数（同一会话再次开始拉流）", churn.reconnects),
+            ] {
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+                let _ = writeln!(out, "# TYPE {} counter", name);
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+            for (name, help, value) in [
+                ("live_server_viewer_reconnects_5m", "近 5 分钟的观众重连次数", churn.recent_reconnects.len()),
+                ("live_server_viewer_stops_5m", "近 5 分钟的观众停止拉流次数", churn.recent_stops.len()),
+            ] {
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+                let _ = writeln!(out, "# TYPE {} gauge", name);
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+        }
+
+        {
+            let derived = *self.derived.lock();
+            for (name, help, value) in [
+                ("live_server_quiz_failure_ratio_5m", "近 5 分钟答错率（作答不足 10 次时无样本）", derived.quiz_failure_ratio),
+                (
+                    "live_server_srs_callback_error_ratio_5m",
+                    "近 5 分钟通过来源校验的 SRS 回调中被拒绝的比例（无回调时无样本）",
+                    derived.callback_error_ratio,
+                ),
+                (
+                    "live_server_chat_latency_p95_seconds_5m",
+                    "近 5 分钟聊天请求处理耗时的 p95（无请求时无样本）",
+                    derived.chat_latency_p95,
+                ),
+            ] {
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+                let _ = writeln!(out, "# TYPE {} gauge", name);
+                if let Some(value) = value {
+                    let _ = writeln!(out, "{} {}", name, value);
+                }
+            }
+        }
+
+        out.push_str("# HELP live_server_send_kbps SRS 全部流近 30 秒的发送码率之和（未推流时无样本）\n");
+        out.push_str("# TYPE live_server_send_kbps gauge\n");
+        if let Some(kbps) = *self.send_kbps.lock() {
+            let _ = writeln!(out, "live_server_send_kbps {}", kbps);
+        }
+
+        out.push_str("# HELP live_server_stream_info 当前直播场次（未推流时无样本）\n");
+        out.push_str("# TYPE live_server_stream_info gauge\n");
+        if let Some(session_id) = self.stream_session.lock().as_deref() {
+            let _ = writeln!(out, "live_server_stream_info{{stream_session_id=\"{}\"}} 1", session_id);
+        }
+
+        out
+    }
+}
@@ -5,43 +5,183 @@
 //! - `srs` - SRS 客户端和主播状态管理
 //! - `chat` - 聊天室消息和用户管理
 //! - `banner` - 答题题库管理
+//! - `banner_source` - 远程题库拉取与定时刷新
+//! - `template` - 基于模板的自定义题库
+//! - `script` - 自定义准入策略脚本钩子
+//! - `external_auth` - 受信任请求头 / OIDC 外部身份认证
+//! - `captcha` - 答题前的人机验证
+//! - `secret_guard` - 推流密钥猜测的频率限制与审计
+//! - `link_policy` - 聊天消息中的链接过滤与跳转改写
+//! - `embed` - 白名单图床的图片嵌入与频率限制
+//! - `metrics` - SRS 回调耗时与拒绝原因统计
+//! - `disk_guard` - 转储目录磁盘空间不足时的降级转储
+//! - `streaming_info` - 观众人数与上行带宽（轮询 SRS API）
+//! - `srs_check` - 启动时的 SRS 配置自检
+//! - `health` - 监听状态与后台任务心跳
+//! - `poll` - 客户端轮询节奏建议
+//! - `publisher_token` - 主播访问令牌（JWT）
+//! - `push_url` - 推流地址拼装与二维码
+//! - `relay` - 转推到外部平台
+//! - `recordings` - 录制文件登记
+//! - `resources` - 进程资源占用
+//! - `callback_dedup` - SRS 回调去重
+//! - `stream_policy` - 推流目标校验与播放地址编码
+//! - `quiz_health` - 答题卡住与答错率异常提醒
+//! - `mqtt` - 向 MQTT broker 发布直播状态（家庭自动化）
+//! - `notify` - 开播与严重错误的邮件通知
+//! - `scheduled` - 主播预约的定时聊天消息
+//! - `invites` - 主播签发的限时访客通行证
+//! - `viewing_policy` - 新观众的观看时段与地区限制
+//! - `resume` - 播放器断线重连使用的续连令牌
+//! - `callback_echo` - SRS 回调回显（调试用）
+//! - `replication` - 主备实例间的热备复制
+//! - `archive` - 过往场次的聊天记录转储与报告浏览
+//! - `frontend` - 托管在其他域名上的前端的 API 令牌与频率限制
 
 // 子模块声明
 pub mod srs;    // SRS 相关状态管理
 pub mod chat;   // 聊天室状态管理
 pub mod banner; // 题库状态管理
-pub mod streaming_info;
+pub mod banner_source; // 远程题库
+pub mod template;  // 题目模板
+pub mod script;    // 脚本钩子
+pub mod external_auth; // 外部身份认证
+pub mod captcha;   // 人机验证
+pub mod secret_guard; // 推流密钥防爆破
+pub mod link_policy;  // 聊天链接策略
+pub mod embed;        // 图片嵌入
+pub mod metrics;      // 运行指标
+pub mod streaming_info; // 观众人数统计
+pub mod srs_api;   // SRS HTTP API 客户端
+pub mod srs_check; // SRS 配置自检
+pub mod health;    // 健康状态
+pub mod events;    // 事件总线
+pub mod disk_guard; // 磁盘空间保护
+pub mod alumni;    // 回访观众令牌
+pub mod poll;      // 轮询节奏建议
+pub mod publisher_token; // 主播访问令牌
+pub mod push_url;  // 推流地址
+pub mod relay;     // 转推
+pub mod recordings; // 录制文件
+pub mod resources;  // 资源占用
+pub mod callback_dedup; // SRS 回调去重
+pub mod stream_policy;  // 推流目标策略
+pub mod quiz_health;    // 答题健康度
+pub mod mqtt;           // MQTT 状态发布
+pub mod notify;         // 邮件通知
+pub mod scheduled;      // 定时消息
+pub mod invites;        // 访客通行证
+pub mod viewing_policy; // 观看时段与地区限制
+pub mod resume;         // 播放续连令牌
+pub mod callback_echo;  // SRS 回调回显
+pub mod replication;    // 热备复制
+pub mod archive;        // 归档浏览
+pub mod frontend;       // 外部前端
+pub mod preview;        // 主播预览令牌
+pub mod srs_watchdog;   // SRS 回调看门狗
 
 // 导出公共类型，供其他模块使用
-pub use srs::ClientStatus;  // SRS 状态枚举
-pub use banner::BannerDatabase;  // 题库数据库
+pub use srs::{ClientCapabilities, ClientStatus, StreamOverlay};  // SRS 状态枚举
+pub use banner::{BannerDatabase, BannerParsePolicy};  // 题库数据库
 
 // 导入依赖
 use std::sync::Arc;
-use crate::config::Config;
+use secrecy::ExposeSecret;
+use crate::config::{Config, ReloadReport};
+use crate::domain::{ClientIp, SessionId};
+use parking_lot::RwLock;
+use crate::state::alumni::AlumniSigner;
+use crate::state::banner_source::{BannerSource, BannerStore};
+use crate::state::callback_dedup::CallbackDedup;
+use crate::state::callback_echo::CallbackEcho;
+use crate::state::captcha::CaptchaVerifier;
+use crate::state::events::EventBus;
+use crate::state::external_auth::ExternalAuth;
+use crate::state::health::Health;
+use crate::state::metrics::Metrics;
+use crate::state::notify::{Alert, Notifier};
+use crate::state::srs_api::SrsApi;
 use crate::state::streaming_info::StreamingInfo;
+use crate::state::script::{HookPoint, ScriptDecision, ScriptHook};
+use crate::state::poll::PollAdvisor;
+use crate::state::quiz_health::QuizHealth;
+use crate::state::publisher_token::PublisherTokenSigner;
+use crate::state::recordings::RecordingRegistry;
+use crate::state::resume::ResumeSigner;
+use crate::state::scheduled::MessageScheduler;
+use crate::state::invites::InviteBook;
+use crate::state::frontend::FrontendLimiter;
+use crate::state::preview::PreviewTokens;
+use crate::state::relay::RelayManager;
+use crate::state::template::TemplateQuiz;
 
 /// 全局应用状态
 ///
 /// 此结构体在所有处理器之间共享，包含了应用程序运行所需的所有状态数据。
 ///
 /// ### 字段说明
-/// - `srs_db`: SRS 客户端和主播状态数据库
+/// - `srs_db`: SRS 客户端和主播状态数据库（主播状态、客户端注册表、准入策略各用一把锁）
 /// - `chat_db`: 聊天室消息和用户映射数据库
-/// - `banner_db`: 题库数据库（只读，使用 Arc 共享）
-/// - `config`: 应用配置信息
+/// - `banner_db`: 题库数据库（可热替换，使用 Arc 共享）
+/// - `config`: 应用配置信息（可热重载，通过 `config()` 获取快照）
 #[derive(Clone)]
 pub struct AppState {
     /// SRS 数据库 - 管理客户端连接、主播状态、答题验证等
     pub srs_db: srs::SrsDatabase,
     /// 聊天室数据库 - 管理聊天消息、用户昵称、UID 映射等
     pub chat_db: chat::ChatDatabase,
-    /// 题库数据库 - 管理答题问题，只读访问
-    pub banner_db: Arc<BannerDatabase>,
-    /// 应用配置 - 包含端口、路径等配置信息
-    pub config: Config,
+    /// 题库数据库 - 管理答题问题，远程题库刷新时整体替换
+    pub banner_db: Arc<BannerStore>,
+    /// 远程题库来源（未配置题库地址时为 `None`）
+    pub banner_source: Option<Arc<BannerSource>>,
+    /// 模板题库（配置后代替卡池题库出题）
+    pub template_quiz: Option<Arc<TemplateQuiz>>,
+    /// 准入策略脚本钩子（未配置脚本时为 `None`）
+    pub script: Option<Arc<ScriptHook>>,
+    /// 外部身份认证（未配置时为 `None`）
+    pub external_auth: Option<Arc<ExternalAuth>>,
+    /// 人机验证校验器（未配置时为 `None`）
+    pub captcha: Option<Arc<CaptchaVerifier>>,
+    /// 应用配置 - 包含端口、路径等配置信息，热重载时整体替换
+    config: Arc<RwLock<Arc<Config>>>,
     /// 后台流信息统计
     pub streaming_info: StreamingInfo,
+    /// SRS HTTP API 客户端
+    pub srs_api: SrsApi,
+    /// 直播事件总线
+    pub events: EventBus,
+    /// 回访观众令牌签发器（未配置密钥时为 `None`）
+    pub alumni: Option<AlumniSigner>,
+    /// 主播访问令牌签发器（未配置签名密钥时为 `None`）
+    pub publisher_tokens: Option<PublisherTokenSigner>,
+    /// 运行指标
+    pub metrics: Arc<Metrics>,
+    /// 监听状态与后台任务心跳
+    pub health: Arc<Health>,
+    /// 客户端轮询节奏建议
+    pub poll: Arc<PollAdvisor>,
+    /// 转推任务
+    pub relays: Arc<RelayManager>,
+    /// 录制文件登记表
+    pub recordings: Arc<RecordingRegistry>,
+    /// 近期处理过的 SRS 回调（用于去重）
+    pub callbacks: Arc<CallbackDedup>,
+    /// 答题健康度统计
+    pub quiz_health: Arc<QuizHealth>,
+    /// 邮件通知器（未配置 SMTP 时为 `None`）
+    pub notifier: Option<Arc<Notifier>>,
+    /// 定时消息队列
+    pub scheduled: Arc<MessageScheduler>,
+    /// 访客通行证
+    pub invites: Arc<InviteBook>,
+    /// 播放续连令牌签发器
+    pub resume: ResumeSigner,
+    /// SRS 回调回显器（未配置回显文件时为 `None`）
+    pub callback_echo: Option<Arc<CallbackEcho>>,
+    /// 外部前端请求频率限制
+    pub frontend: Arc<FrontendLimiter>,
+    /// 主播预览令牌与预览播放端
+    pub preview: Arc<PreviewTokens>,
 }
 
 impl AppState {
@@ -59,16 +199,198 @@ impl AppState {
     /// 3. 初始化聊天室数据库（需要转储路径）
     pub fn new(config: Config) -> Result<Self, Box<dyn std::error::Error>> {
         // 初始化题库数据库
-        let banner_db = Arc::new(BannerDatabase::new(&config.banner_db_path)?);
+        // 配置了远程题库时，本地文件仅作为缓存，不存在时先以空题库启动
+        let banner_policy = BannerParsePolicy {
+            lenient: config.banner_lenient,
+            min_questions: config.banner_min_questions,
+        };
+        let banner_source = config.banner_db_url.as_ref().map(|url| {
+            Arc::new(
+                BannerSource::new(url.clone(), config.banner_db_path.clone(), config.content_char_limit)
+                    .with_parse_policy(banner_policy),
+            )
+        });
+        let banner_db = match BannerDatabase::load(&config.banner_db_path, banner_policy) {
+            Ok(db) => db,
+            Err(e) if banner_source.is_some() => {
+                tracing::warn!("读取题库缓存失败，等待远程题库: {}", e);
+                BannerDatabase::from_json("[]")?
+            }
+            Err(e) => return Err(e),
+        };
+        let banner_db = Arc::new(BannerStore::new(
+            banner_db.with_content_char_limit(config.content_char_limit),
+        ));
+        let template_quiz = match &config.question_templates_path {
+            Some(path) => Some(Arc::new(TemplateQuiz::load(path)?)),
+            None => None,
+        };
+        let script = match &config.script_path {
+            Some(path) => Some(Arc::new(ScriptHook::load(path)?)),
+            None => None,
+        };
+        let external_auth = ExternalAuth::new(
+            config.trusted_user_header.clone(),
+            config.trusted_proxies.clone(),
+            config.oidc_userinfo_url.clone(),
+        )
+        .map(Arc::new);
+        let captcha = config
+            .captcha
+            .clone()
+            .map(|(provider, secret)| Arc::new(CaptchaVerifier::new(provider, secret)));
         let secret_path = config.secret_path.clone();
         let dump_path = config.dump_path.clone();
+        let srs_api = SrsApi::new(&config.srs_api_addr());
+        let publisher_tokens = config.publisher_jwt_key.as_ref().map(|key| {
+            PublisherTokenSigner::new(
+                key.expose_secret().as_bytes().to_vec(),
+                chrono::Duration::hours(config.publisher_jwt_hours),
+            )
+        });
+        let relays = RelayManager::new(config.relay_key.as_ref().map(|key| key.expose_secret().as_bytes()));
+        let notifier = config.smtp.clone().and_then(|target| {
+            Notifier::new(target, config.smtp_password.clone())
+                .map_err(|e| tracing::warn!("邮件通知不可用: {}", e))
+                .ok()
+                .map(Arc::new)
+        });
+        let callback_echo = config.srs_callback_echo_path.as_deref().and_then(|path| {
+            CallbackEcho::open(path)
+                .map_err(|e| tracing::warn!("SRS 回调回显不可用: {}", e))
+                .ok()
+                .map(|echo| {
+                    tracing::warn!("SRS 回调回显已开启，完整回调内容（含推流密钥）将写入 {}", echo.path().display());
+                    Arc::new(echo)
+                })
+        });
+        let alumni = config.alumni_key.as_ref().map(|key| {
+            AlumniSigner::new(
+                key.expose_secret().as_bytes().to_vec(),
+                chrono::Duration::days(config.alumni_validity_days),
+            )
+        });
 
+        let preview = Arc::new(PreviewTokens::new());
         Ok(Self {
-            srs_db: srs::SrsDatabase::new(secret_path)?,
-            chat_db: chat::ChatDatabase::new(dump_path),
+            srs_db: srs::SrsDatabase::new(
+                secret_path,
+                chrono::Duration::seconds(config.question_time_limit_secs),
+                chrono::Duration::seconds(config.question_memory_secs),
+            )?,
+            chat_db: chat::ChatDatabase::new(
+                dump_path,
+                config.chat_lobby_enabled,
+                config.chat_uid_easter_egg,
+            ),
             banner_db,
-            config,
-            streaming_info: StreamingInfo::new(),
+            banner_source,
+            template_quiz,
+            script,
+            external_auth,
+            captcha,
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            streaming_info: StreamingInfo::new(preview.clone()),
+            srs_api,
+            events: EventBus::new(),
+            alumni,
+            publisher_tokens,
+            metrics: Arc::new(Metrics::new()),
+            health: Arc::new(Health::new()),
+            poll: Arc::new(PollAdvisor::new()),
+            relays: Arc::new(relays),
+            recordings: Arc::new(RecordingRegistry::new()),
+            callbacks: Arc::new(CallbackDedup::new()),
+            quiz_health: Arc::new(QuizHealth::new()),
+            notifier,
+            scheduled: Arc::new(MessageScheduler::new()),
+            invites: Arc::new(InviteBook::new()),
+            resume: ResumeSigner::new(),
+            callback_echo,
+            frontend: Arc::new(FrontendLimiter::new()),
+            preview,
         })
     }
+
+    /// 获取当前配置的快照
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().clone()
+    }
+
+    /// 实验性功能是否开启（名称见 `Features` 中的常量）
+    pub fn feature(&self, name: &str) -> bool {
+        self.config.read().features.enabled(name)
+    }
+
+    /// 记录一次轮询请求，返回建议的轮询间隔（毫秒）
+    ///
+    /// ### 参数
+    /// - `paused`: 直播是否处于暂停状态（由调用方在已持有的锁内判断）
+    pub fn suggest_poll_interval(&self, paused: bool) -> u64 {
+        self.poll.record(&self.config().poll, paused)
+    }
+
+    /// 重新读取配置文件和环境变量，应用可热更新的配置项
+    ///
+    /// ### 返回值
+    /// - `Ok(report)`: 已生效和需重启才能生效的配置项
+    /// - `Err(msg)`: 读取配置文件失败，当前配置保持不变
+    pub fn reload_config(&self) -> Result<ReloadReport, String> {
+        Config::load_file()?;
+        let (merged, report) = self.config().merge_reload(Config::from_env());
+
+        if report.applied.contains(&"log_level") {
+            crate::logging::set_level(&merged.log_level)?;
+        }
+        crate::redact::init(merged.log_sensitive);
+        {
+            let mut clients = self.srs_db.clients.write();
+            clients.question_time_limit = chrono::Duration::seconds(merged.question_time_limit_secs);
+            clients.question_memory = chrono::Duration::seconds(merged.question_memory_secs);
+        }
+
+        *self.config.write() = Arc::new(merged);
+        Ok(report)
+    }
+
+    /// 发送邮件通知（未配置 SMTP 时忽略）
+    pub fn notify(&self, alert: Alert, body: String) {
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(alert, body);
+        }
+    }
+
+    /// 在指定调用点执行准入策略脚本
+    ///
+    /// ### 返回值
+    /// 未配置脚本时返回 `ScriptDecision::Default`
+    pub fn script_decide(&self, point: HookPoint, ctx: &[(&str, &str)]) -> ScriptDecision {
+        self.script
+            .as_ref()
+            .map_or(ScriptDecision::Default, |script| script.decide(point, ctx))
+    }
+
+    /// 凭续连令牌重建刚过期的已授权观众记录
+    ///
+    /// ### 参数
+    /// - `session_id`: 观众会话 ID
+    /// - `token`: 最近一次 `video_uri` 中附带的续连令牌
+    ///
+    /// ### 返回值
+    /// 令牌对当前直播场次有效且会话未被管理员撤销时返回观众 IP（会话已有记录时不做改动）
+    pub fn resume_client(&self, session_id: &SessionId, token: &str) -> Option<ClientIp> {
+        let stream_session = self.srs_db.streamer.read().get_stream_session_id()?.to_string();
+        let ip = self.resume.verify(token, session_id, &stream_session)?;
+        let mut clients = self.srs_db.clients.write();
+        if clients.resume_revoked(session_id) {
+            tracing::debug!("会话已被撤销，拒绝凭续连令牌恢复 session_id={}", session_id);
+            return None;
+        }
+        if clients.find_client_ip(session_id).is_none() {
+            clients.add_client(ip.clone(), session_id.clone());
+            clients.transition(&ip, session_id, ClientStatus::Legal);
+            tracing::debug!("凭续连令牌恢复观众记录 session_id={}", session_id);
+        }
+        Some(ip)
+    }
 }
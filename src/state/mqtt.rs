@@ -0,0 +1,192 @@
+//! # MQTT 状态发布模块
+//!
+//! 把直播状态、观众人数和聊天速率发布到 MQTT broker，供家庭自动化系统订阅
+//! （如"直播中"指示灯），无需另写轮询桥接程序。
+//!
+//! 发布的主题（`<前缀>` 见 `LIVE_SERVER_MQTT_TOPIC`）：
+//! - `<前缀>/state` - `live` / `paused` / `offline`（保留消息，服务断线时由遗嘱消息置为 `offline`）
+//! - `<前缀>/audience` - 当前观众人数（保留消息）
+//! - `<前缀>/chat_rate` - 近一分钟的聊天消息条数
+//!
+//! 需启用 `mqtt` 特性编译。
+
+use crate::config::MqttTarget;
+use crate::state::chat::ChatDatabase;
+use crate::state::events::EventBus;
+use crate::state::srs::SrsDatabase;
+use crate::state::streaming_info::StreamingInfo;
+use secrecy::SecretString;
+use tokio::task::JoinHandle;
+
+/// 聊天速率的统计窗口（秒）
+#[cfg(feature = "mqtt")]
+const CHAT_RATE_WINDOW_SECS: f64 = 60.0;
+
+/// 连接失败后的重试间隔
+#[cfg(feature = "mqtt")]
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 发布请求队列容量（broker 不可用时超出的消息直接丢弃）
+#[cfg(feature = "mqtt")]
+const REQUEST_CAPACITY: usize = 32;
+
+/// 当前的直播状态
+#[cfg(feature = "mqtt")]
+fn stream_state(srs_db: &SrsDatabase) -> &'static str {
+    let db = srs_db.streamer.read();
+    if db.is_actively_streaming() {
+        "live"
+    } else if db.is_streaming() {
+        "paused"
+    } else {
+        "offline"
+    }
+}
+
+/// MQTT 状态发布器
+pub struct MqttPublisher {
+    /// 发布目标
+    target: MqttTarget,
+    /// 认证密码
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    password: Option<SecretString>,
+}
+
+impl MqttPublisher {
+    /// 创建发布器
+    ///
+    /// ### 参数
+    /// - `target`: broker 地址与主题配置
+    /// - `password`: 认证密码（仅在配置了用户名时使用）
+    pub fn new(target: MqttTarget, password: Option<SecretString>) -> Self {
+        Self { target, password }
+    }
+
+    /// 主题前缀下的完整主题名
+    #[cfg(feature = "mqtt")]
+    fn topic(&self, name: &str) -> String {
+        format!("{}/{}", self.target.topic, name)
+    }
+
+    /// 启动后台发布任务
+    ///
+    /// ### 行为说明
+    /// 1. 直播开始、暂停、恢复和结束时立即发布状态和观众人数
+    /// 2. 按配置的间隔定期发布全部主题
+    /// 3. broker 不可用时每 5 秒重连，只在首次失败和恢复时输出日志
+    ///
+    /// ### 返回值
+    /// 未启用 `mqtt` 特性时输出警告并返回 `None`
+    pub fn spawn(
+        self,
+        srs_db: SrsDatabase,
+        chat_db: ChatDatabase,
+        streaming_info: StreamingInfo,
+        events: EventBus,
+    ) -> Option<JoinHandle<()>> {
+        #[cfg(feature = "mqtt")]
+        {
+            Some(tokio::spawn(self.run(srs_db, chat_db, streaming_info, events)))
+        }
+        #[cfg(not(feature = "mqtt"))]
+        {
+            let _ = (srs_db, chat_db, streaming_info, events);
+            tracing::warn!(
+                "已配置 MQTT broker {}:{}，但编译时未启用 mqtt 特性，不会发布状态",
+                self.target.host,
+                self.target.port
+            );
+            None
+        }
+    }
+
+    /// 后台发布任务主体：在同一任务中驱动 MQTT 连接和发布循环
+    #[cfg(feature = "mqtt")]
+    async fn run(self, srs_db: SrsDatabase, chat_db: ChatDatabase, streaming_info: StreamingInfo, events: EventBus) {
+        use crate::state::events::StreamEvent;
+        use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+        use secrecy::ExposeSecret;
+        use tokio::sync::broadcast::error::RecvError;
+
+        let mut options = MqttOptions::new(&self.target.client_id, &self.target.host, self.target.port);
+        options.set_keep_alive(std::time::Duration::from_secs(30));
+        options.set_last_will(LastWill::new(self.topic("state"), "offline", QoS::AtLeastOnce, true));
+        if let Some(username) = &self.target.username {
+            let password = self.password.as_ref().map(|p| p.expose_secret().to_string());
+            options.set_credentials(username, password.unwrap_or_default());
+        }
+        let (client, mut eventloop) = AsyncClient::new(options, REQUEST_CAPACITY);
+
+        let broker = format!("{}:{}", self.target.host, self.target.port);
+        let connection = async {
+            let mut failures = 0u32;
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        if failures > 0 {
+                            tracing::info!("MQTT broker {} 已恢复（此前连续失败 {} 次）", broker, failures);
+                        } else {
+                            tracing::info!("已连接 MQTT broker {}", broker);
+                        }
+                        failures = 0;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        failures += 1;
+                        if failures == 1 {
+                            tracing::warn!("MQTT broker {} 连接失败，将重试: {}", broker, e);
+                        } else {
+                            tracing::debug!("MQTT broker {} 连接失败（连续 {} 次）: {}", broker, failures, e);
+                        }
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        };
+
+        let publish = |name: &str, payload: String, retain: bool| {
+            if let Err(e) = client.try_publish(self.topic(name), QoS::AtLeastOnce, retain, payload) {
+                tracing::debug!("MQTT 消息 {} 未能发布: {}", name, e);
+            }
+        };
+        let publish_state = || {
+            let state = stream_state(&srs_db);
+            let audience = if state == "offline" {
+                0
+            } else {
+                streaming_info.inner.read().get_audiences_num().max(0)
+            };
+            publish("state", state.to_string(), true);
+            publish("audience", audience.to_string(), true);
+        };
+
+        let mut subscriber = events.subscribe();
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(self.target.interval_secs));
+        let publisher = async {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        publish_state();
+                        let rate = chat_db.inner.read().active().recent_message_count(CHAT_RATE_WINDOW_SECS);
+                        publish("chat_rate", rate.to_string(), false);
+                    }
+                    event = subscriber.recv() => match event {
+                        Ok(
+                            StreamEvent::StreamLive { .. }
+                            | StreamEvent::StreamEnded
+                            | StreamEvent::StreamPaused
+                            | StreamEvent::StreamResumed,
+                        ) => publish_state(),
+                        Ok(_) | Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    },
+                }
+            }
+        };
+
+        tokio::select! {
+            _ = connection => {}
+            _ = publisher => {}
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! # 邮件通知模块
+//!
+//! 直播开始和服务出现严重问题（远程题库刷新失败、SRS API 长时间不可用、磁盘空间不足）时，
+//! 通过 SMTP 向配置的收件人列表发送邮件。同类通知在冷却时间内只发送一次，避免故障期间刷屏。
+//!
+//! 需启用 `email` 特性编译。
+
+use crate::config::SmtpTarget;
+use parking_lot::Mutex;
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 通知类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alert {
+    /// 直播开始
+    StreamLive,
+    /// 远程题库刷新失败
+    BannerRefreshFailed,
+    /// SRS API 长时间不可用
+    SrsUnreachable,
+    /// 转储目录剩余空间不足
+    LowDiskSpace,
+    /// SRS 回调疑似中断（主播记录与 SRS 中的推流不一致）
+    CallbacksMissing,
+}
+
+impl Alert {
+    /// 邮件标题
+    pub fn subject(&self) -> &'static str {
+        match self {
+            Self::StreamLive => "直播已开始",
+            Self::BannerRefreshFailed => "远程题库刷新失败",
+            Self::SrsUnreachable => "SRS API 长时间不可用",
+            Self::LowDiskSpace => "磁盘空间不足",
+            Self::CallbacksMissing => "SRS 回调疑似中断",
+        }
+    }
+
+    /// 同类通知的最短发送间隔
+    fn cooldown(&self) -> Duration {
+        match self {
+            // 推流端短时间内反复重连时只通知一次
+            Self::StreamLive => Duration::from_secs(600),
+            _ => Duration::from_secs(3600),
+        }
+    }
+}
+
+/// 邮件通知器
+pub struct Notifier {
+    /// SMTP 连接
+    #[cfg(feature = "email")]
+    mailer: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+    /// 发件人与收件人配置
+    #[cfg_attr(not(feature = "email"), allow(dead_code))]
+    target: SmtpTarget,
+    /// 各类通知上次发送的时刻
+    last_sent: Mutex<HashMap<Alert, Instant>>,
+}
+
+impl Notifier {
+    /// 创建通知器
+    ///
+    /// ### 参数
+    /// - `target`: SMTP 服务器与收件人配置
+    /// - `password`: 登录密码（仅在配置了用户名时使用）
+    ///
+    /// ### 返回值
+    /// 未启用 `email` 特性或 SMTP 参数无效时返回错误
+    pub fn new(target: SmtpTarget, password: Option<SecretString>) -> Result<Self, String> {
+        #[cfg(feature = "email")]
+        {
+            use crate::config::SmtpTls;
+            use lettre::transport::smtp::authentication::Credentials;
+            use lettre::{AsyncSmtpTransport, Tokio1Executor};
+            use secrecy::ExposeSecret;
+
+            let builder = match target.tls {
+                SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&target.host)
+                    .map_err(|e| e.to_string())?,
+                SmtpTls::Tls => {
+                    AsyncSmtpTransport::<Tokio1Executor>::relay(&target.host).map_err(|e| e.to_string())?
+                }
+                SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&target.host),
+            };
+            let mut builder = builder.port(target.port);
+            if let Some(username) = &target.username {
+                let password = password.as_ref().map(|p| p.expose_secret().to_string());
+                builder = builder.credentials(Credentials::new(username.clone(), password.unwrap_or_default()));
+            }
+            Ok(Self {
+                mailer: builder.build(),
+                target,
+                last_sent: Mutex::new(HashMap::new()),
+            })
+        }
+        #[cfg(not(feature = "email"))]
+        {
+            let _ = password;
+            Err(format!(
+                "已配置 SMTP 服务器 {}:{}，但编译时未启用 email 特性，不会发送邮件",
+                target.host, target.port
+            ))
+        }
+    }
+
+    /// 发送一封通知邮件（后台发送，不阻塞调用方）
+    ///
+    /// ### 参数
+    /// - `alert`: 通知类别，冷却时间内的同类通知被忽略
+    /// - `body`: 邮件正文
+    pub fn notify(&self, alert: Alert, body: String) {
+        {
+            let mut last_sent = self.last_sent.lock();
+            if last_sent.get(&alert).is_some_and(|at| at.elapsed() < alert.cooldown()) {
+                tracing::debug!("{} 通知仍在冷却中，已忽略", alert.subject());
+                return;
+            }
+            last_sent.insert(alert, Instant::now());
+        }
+        self.send(alert, body);
+    }
+
+    /// 构造邮件并在后台任务中发送
+    #[cfg(feature = "email")]
+    fn send(&self, alert: Alert, body: String) {
+        use lettre::message::header::ContentType;
+        use lettre::{AsyncTransport, Message};
+
+        let mut builder = Message::builder()
+            .subject(format!("[live-server] {}", alert.subject()))
+            .header(ContentType::TEXT_PLAIN);
+        match self.target.from.parse() {
+            Ok(from) => builder = builder.from(from),
+            Err(e) => {
+                tracing::warn!("发件人地址 {} 无效，无法发送通知: {}", self.target.from, e);
+                return;
+            }
+        }
+        for recipient in &self.target.recipients {
+            match recipient.parse() {
+                Ok(to) => builder = builder.to(to),
+                Err(e) => tracing::warn!("收件人地址 {} 无效，已跳过: {}", recipient, e),
+            }
+        }
+        let message = match builder.body(format!("{}\n\n{}", body, chrono::Local::now().to_rfc3339())) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!("构造通知邮件失败: {}", e);
+                return;
+            }
+        };
+
+        let mailer = self.mailer.clone();
+        tokio::spawn(async move {
+            match mailer.send(message).await {
+                Ok(_) => tracing::info!("已发送通知邮件: {}", alert.subject()),
+                Err(e) => tracing::warn!("发送通知邮件失败（{}）: {}", alert.subject(), e),
+            }
+        });
+    }
+
+    /// 未启用 `email` 特性时不会创建通知器
+    #[cfg(not(feature = "email"))]
+    fn send(&self, _alert: Alert, _body: String) {}
+}
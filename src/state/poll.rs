@@ -0,0 +1,87 @@
+//! # 轮询节奏建议模块
+//!
+//! 统计聊天和状态查询的请求速率，据此在响应中建议客户端下一次轮询的间隔（`poll_interval_ms`）。
+//! 负载升高或直播暂停时建议更长的间隔，服务端无需重新部署前端即可削减自身的轮询负载。
+
+use crate::config::PollPolicy;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// 请求速率的统计窗口
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// 直播暂停时轮询间隔相对基础间隔的倍数
+const PAUSED_FACTOR: u64 = 5;
+
+/// 固定窗口计数器
+#[derive(Debug)]
+struct RateWindow {
+    /// 当前窗口的开始时刻
+    started: Instant,
+    /// 当前窗口内的请求数
+    count: u64,
+    /// 上一个完整窗口内的请求数
+    last: u64,
+}
+
+/// 轮询节奏建议器
+#[derive(Debug)]
+pub struct PollAdvisor {
+    /// 请求计数窗口
+    window: Mutex<RateWindow>,
+}
+
+impl PollAdvisor {
+    /// 创建新的建议器
+    pub fn new() -> Self {
+        Self {
+            window: Mutex::new(RateWindow {
+                started: Instant::now(),
+                count: 0,
+                last: 0,
+            }),
+        }
+    }
+
+    /// 记录一次轮询请求，并返回建议的轮询间隔（毫秒）
+    ///
+    /// ### 参数
+    /// - `policy`: 轮询节奏配置
+    /// - `paused`: 直播是否处于暂停状态
+    pub fn record(&self, policy: &PollPolicy, paused: bool) -> u64 {
+        let rate = {
+            let mut window = self.window.lock();
+            let elapsed = window.started.elapsed();
+            if elapsed >= WINDOW {
+                // 超过两个窗口没有请求时，上一窗口的计数已无意义
+                window.last = if elapsed >= WINDOW * 2 { 0 } else { window.count };
+                window.count = 0;
+                window.started = Instant::now();
+            }
+            window.count += 1;
+            window.last.max(window.count)
+        };
+        suggest(policy, rate, paused)
+    }
+}
+
+impl Default for PollAdvisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 根据请求速率计算建议的轮询间隔（毫秒）
+///
+/// 速率超过阈值时按超出的倍数线性放大基础间隔，暂停时至少为基础间隔的 5 倍，
+/// 结果不超过配置的上限
+fn suggest(policy: &PollPolicy, rate: u64, paused: bool) -> u64 {
+    let mut interval = policy.base_ms;
+    if policy.load_threshold > 0 && rate > policy.load_threshold {
+        interval = interval.saturating_mul(rate) / policy.load_threshold;
+    }
+    if paused {
+        interval = interval.max(policy.base_ms.saturating_mul(PAUSED_FACTOR));
+    }
+    interval.clamp(policy.base_ms, policy.max_ms.max(policy.base_ms))
+}
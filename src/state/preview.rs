@@ -0,0 +1,132 @@
+//! # 主播预览令牌模块
+//!
+//! 主播在手机或另一台电脑上监看自己的直播画面时，不应占用观看名额，也不应计入观众人数。
+//! 主播通过 `/api/publisher/preview` 获取带预览令牌（`preview`）的播放地址，
+//! `on_play` 凭令牌直接放行，并记录该播放端的 SRS client_id，统计观众人数时予以排除。
+//! 同时预览的播放端不超过 `MAX_PREVIEW_CLIENTS` 个，预览地址泄露时主播可以撤销令牌，
+//! 撤销后旧令牌失效，正在使用旧令牌预览的播放端被踢出。
+//!
+//! 令牌绑定直播场次，签名密钥在启动时随机生成，服务重启或直播结束后全部失效。
+//!
+//! ## 令牌格式
+//! `HMAC-SHA256(密钥, "preview.<直播场次>.<撤销次数>")` 的十六进制
+
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use rand::Rng;
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 同时预览的播放端数量上限（超出时拒绝新的预览播放）
+pub const MAX_PREVIEW_CLIENTS: usize = 3;
+
+/// 主播预览令牌签发器与预览播放端登记
+pub struct PreviewTokens {
+    /// HMAC 密钥（启动时随机生成）
+    key: [u8; 32],
+    /// 令牌撤销次数（参与签名，每次撤销使此前签发的令牌全部失效）
+    generation: AtomicU64,
+    /// 正在预览的播放端：(直播场次, SRS client_id 集合)
+    clients: Mutex<(String, HashSet<String>)>,
+}
+
+impl PreviewTokens {
+    /// 创建新的令牌签发器
+    pub fn new() -> Self {
+        Self {
+            key: rand::thread_rng().gen(),
+            generation: AtomicU64::new(0),
+            clients: Mutex::new((String::new(), HashSet::new())),
+        }
+    }
+
+    /// 计算指定直播场次的签名
+    fn sign(&self, stream_session: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC 可接受任意长度密钥");
+        let generation = self.generation.load(Ordering::Acquire);
+        mac.update(format!("preview.{}.{}", stream_session, generation).as_bytes());
+        mac
+    }
+
+    /// 为当前直播场次签发预览令牌
+    ///
+    /// ### 参数
+    /// - `stream_session`: 当前直播场次 ID
+    pub fn issue(&self, stream_session: &str) -> String {
+        self.sign(stream_session)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 校验预览令牌
+    ///
+    /// ### 参数
+    /// - `token`: 令牌
+    /// - `stream_session`: 当前直播场次 ID
+    pub fn verify(&self, token: &str, stream_session: &str) -> bool {
+        if !token.len().is_multiple_of(2) {
+            return false;
+        }
+        let signature = (0..token.len())
+            .step_by(2)
+            .map(|i| token.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+            .collect::<Option<Vec<u8>>>();
+        signature.is_some_and(|s| self.sign(stream_session).verify_slice(&s).is_ok())
+    }
+
+    /// 登记开始预览的播放端（直播场次变化时先清空上一场的记录）
+    ///
+    /// ### 参数
+    /// - `stream_session`: 当前直播场次 ID
+    /// - `client_id`: 播放端的 SRS client_id
+    ///
+    /// ### 返回值
+    /// 同时预览的播放端已达上限时返回 `false`，不登记
+    pub fn add_client(&self, stream_session: &str, client_id: String) -> bool {
+        let mut guard = self.clients.lock();
+        let (session, clients) = &mut *guard;
+        if session != stream_session {
+            *session = stream_session.to_string();
+            clients.clear();
+        }
+        if clients.len() >= MAX_PREVIEW_CLIENTS && !clients.contains(&client_id) {
+            return false;
+        }
+        clients.insert(client_id);
+        true
+    }
+
+    /// 移除停止播放的播放端
+    ///
+    /// ### 返回值
+    /// 该播放端是否为预览播放端
+    pub fn remove_client(&self, client_id: &str) -> bool {
+        self.clients.lock().1.remove(client_id)
+    }
+
+    /// 正在预览的 SRS client_id（统计观众人数时排除）
+    pub fn clients(&self) -> HashSet<String> {
+        self.clients.lock().1.clone()
+    }
+
+    /// 撤销此前签发的全部预览令牌
+    ///
+    /// ### 返回值
+    /// 正在预览的 SRS client_id（调用方负责通知 SRS 踢出）
+    pub fn revoke(&self) -> Vec<String> {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.clients.lock().1.drain().collect()
+    }
+}
+
+impl Default for PreviewTokens {
+    fn default() -> Self {
+        Self::new()
+    }
+}
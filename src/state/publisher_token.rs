@@ -0,0 +1,101 @@
+//! # 主播访问令牌模块
+//!
+//! 主播通过推流密钥登录后签发 JWT（HS256），供手机端快捷操作等接口鉴权，
+//! 无需在手机上再次输入推流密钥。
+//!
+//! ## 令牌载荷
+//! - `sub`: 主播的会话 ID（令牌只在该会话仍是当前主播时有效）
+//! - `iat` / `exp`: 签发与过期时间（Unix 时间戳，秒）
+
+use crate::domain::SessionId;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// JWT 头部（固定为 HS256）
+const JWT_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// 令牌载荷
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    /// 主播会话 ID
+    sub: String,
+    /// 签发时间
+    iat: i64,
+    /// 过期时间
+    exp: i64,
+}
+
+/// 主播访问令牌签发器
+#[derive(Clone)]
+pub struct PublisherTokenSigner {
+    /// HMAC 密钥
+    key: Vec<u8>,
+    /// 令牌有效期
+    validity: Duration,
+}
+
+impl PublisherTokenSigner {
+    /// 创建新的令牌签发器
+    ///
+    /// ### 参数
+    /// - `key`: HMAC 密钥
+    /// - `validity`: 令牌有效期
+    pub fn new(key: Vec<u8>, validity: Duration) -> Self {
+        Self { key, validity }
+    }
+
+    /// 计算签名输入的 MAC
+    fn mac(&self, signing_input: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC 可接受任意长度密钥");
+        mac.update(signing_input.as_bytes());
+        mac
+    }
+
+    /// 为主播会话签发令牌
+    pub fn issue(&self, session_id: &SessionId) -> String {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: session_id.to_string(),
+            iat: now.timestamp(),
+            exp: (now + self.validity).timestamp(),
+        };
+        let payload = serde_json::to_vec(&claims).expect("载荷可序列化");
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(JWT_HEADER),
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+        let signature = self.mac(&signing_input).finalize().into_bytes();
+        format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// 校验令牌签名及有效期
+    ///
+    /// ### 返回值
+    /// 令牌有效时返回其中的主播会话 ID
+    pub fn verify(&self, token: &str) -> Option<SessionId> {
+        let (signing_input, signature) = token.rsplit_once('.')?;
+        let (header, payload) = signing_input.split_once('.')?;
+
+        // 只接受 HS256，拒绝 `alg: none` 等降级攻击
+        let header: serde_json::Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+        if header.get("alg").and_then(|a| a.as_str()) != Some("HS256") {
+            return None;
+        }
+
+        // verify_slice 使用常量时间比较
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(signing_input).verify_slice(&signature).ok()?;
+
+        let claims: Claims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        if Utc::now().timestamp() > claims.exp {
+            return None;
+        }
+        SessionId::parse(&claims.sub).ok()
+    }
+}
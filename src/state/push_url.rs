@@ -0,0 +1,195 @@
+//! # 推流地址模块
+//!
+//! 按配置拼装主播推流地址（RTMP / SRT），并渲染为二维码，
+//! 方便在推流软件或手机推流应用中配置，避免手动输入长地址出错
+//! （地址拼错目前只会表现为推流时的 403）。
+
+use super::srs::EntryMode;
+use qrcode::{render::svg, Color, EcLevel, QrCode};
+use url::form_urlencoded;
+
+/// 二维码每个模块的像素大小（PNG）
+const QR_MODULE_PX: usize = 8;
+
+/// 二维码四周的留白（模块数）
+const QR_QUIET_ZONE: usize = 4;
+
+/// 推流目标
+#[derive(Debug, Clone)]
+pub struct PushTarget {
+    /// 推流服务器主机名（不含端口）
+    pub host: String,
+    /// 应用名称
+    pub app: String,
+    /// 流名称
+    pub stream: String,
+    /// 推流密钥
+    pub secret: String,
+    /// 是否公开模式（`None` 表示不携带该参数）
+    pub public: Option<bool>,
+    /// 观众入场方式（`None` 表示不携带该参数，即答题）
+    pub entry: Option<EntryMode>,
+    /// 直播间名称（`None` 表示不携带该参数）
+    pub name: Option<String>,
+    /// 会话 ID（可选）
+    pub session_id: Option<String>,
+}
+
+impl PushTarget {
+    /// 推流查询参数（`secret=...&public=...&entry=...&name=...&session_id=...`）
+    fn query(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.append_pair("secret", &self.secret);
+        if let Some(public) = self.public {
+            query.append_pair("public", if public { "true" } else { "false" });
+        }
+        if let Some(entry) = self.entry {
+            query.append_pair("entry", entry.as_str());
+        }
+        if let Some(name) = &self.name {
+            query.append_pair("name", name);
+        }
+        if let Some(session_id) = &self.session_id {
+            query.append_pair("session_id", session_id);
+        }
+        query.finish()
+    }
+
+    /// RTMP 推流地址
+    ///
+    /// 格式：`rtmp://host:port/app/stream?secret=...`
+    pub fn rtmp_url(&self, port: u16) -> String {
+        format!(
+            "rtmp://{}:{}/{}/{}?{}",
+            self.host,
+            port,
+            self.app,
+            self.stream,
+            self.query()
+        )
+    }
+
+    /// SRT 推流地址
+    ///
+    /// 使用 SRS 的 streamid 格式：`#!::r=app/stream,secret=...,m=publish`，
+    /// 其中的额外键值对会作为回调的 param 传给本服务
+    pub fn srt_url(&self, port: u16) -> String {
+        let mut stream_id = format!("#!::r={}/{},secret={}", self.app, self.stream, self.secret);
+        if let Some(public) = self.public {
+            stream_id.push_str(if public { ",public=true" } else { ",public=false" });
+        }
+        if let Some(entry) = self.entry {
+            stream_id.push_str(",entry=");
+            stream_id.push_str(entry.as_str());
+        }
+        if let Some(name) = &self.name {
+            // 名称中的 `,` `=` 等会破坏 streamid 的键值对，先编码一层（回调中解码）
+            stream_id.push_str(",name=");
+            stream_id.extend(form_urlencoded::byte_serialize(name.as_bytes()));
+        }
+        if let Some(session_id) = &self.session_id {
+            stream_id.push_str(",session_id=");
+            stream_id.push_str(session_id);
+        }
+        stream_id.push_str(",m=publish");
+        let encoded: String = form_urlencoded::byte_serialize(stream_id.as_bytes()).collect();
+        format!("srt://{}:{}?streamid={}", self.host, port, encoded)
+    }
+}
+
+/// 确定推流地址中的主机名
+///
+/// ### 参数
+/// - `configured`: 配置的公网主机名
+/// - `host_header`: 请求的 Host 头（配置缺失时使用，去掉端口）
+pub fn resolve_host(configured: Option<&str>, host_header: Option<&str>) -> Option<String> {
+    configured
+        .or_else(|| {
+            let host = host_header?;
+            // IPv6 字面量形如 `[::1]:8848`，保留方括号
+            Some(match host.rfind(':') {
+                Some(i) if !host[i..].contains(']') => &host[..i],
+                _ => host,
+            })
+        })
+        .filter(|h| !h.is_empty())
+        .map(str::to_string)
+}
+
+/// 二维码图片格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrFormat {
+    /// PNG 位图
+    Png,
+    /// SVG 矢量图
+    Svg,
+}
+
+impl QrFormat {
+    /// 从字符串解析格式
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "png" => Some(Self::Png),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+
+    /// 响应的 Content-Type
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// 将文本渲染为二维码图片
+///
+/// ### 返回值
+/// - `Ok(bytes)`: 图片内容
+/// - `Err(msg)`: 文本过长无法编码或 PNG 编码失败
+pub fn render_qr(text: &str, format: QrFormat) -> Result<Vec<u8>, String> {
+    let code = QrCode::with_error_correction_level(text, EcLevel::M)
+        .map_err(|e| format!("生成二维码失败: {}", e))?;
+    match format {
+        QrFormat::Svg => Ok(code
+            .render::<svg::Color>()
+            .quiet_zone(true)
+            .min_dimensions(256, 256)
+            .build()
+            .into_bytes()),
+        QrFormat::Png => encode_png(&code),
+    }
+}
+
+/// 将二维码编码为灰度 PNG
+fn encode_png(code: &QrCode) -> Result<Vec<u8>, String> {
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + QR_QUIET_ZONE * 2) * QR_MODULE_PX;
+
+    let mut pixels = vec![0xffu8; side * side];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let (mx, my) = (i % modules + QR_QUIET_ZONE, i / modules + QR_QUIET_ZONE);
+        for y in my * QR_MODULE_PX..(my + 1) * QR_MODULE_PX {
+            pixels[y * side + mx * QR_MODULE_PX..y * side + (mx + 1) * QR_MODULE_PX].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("PNG 编码失败: {}", e))?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| format!("PNG 编码失败: {}", e))?;
+    writer.finish().map_err(|e| format!("PNG 编码失败: {}", e))?;
+    Ok(out)
+}
@@ -0,0 +1,116 @@
+//! # 答题健康度模块
+//!
+//! 统计近期的答题结果，在大量观众长时间卡在答题阶段或答错率骤升时提醒主播，
+//! 便于及时发现题库过难或损坏导致"谁都进不来"的情况。
+
+use crate::state::events::StreamEvent;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 答错率的统计窗口
+const WINDOW: Duration = Duration::from_secs(300);
+
+/// 计算答错率所需的最少作答次数
+const MIN_SAMPLES: usize = 10;
+
+/// 异常持续时重复提醒的最短间隔
+const REPEAT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 答题健康度统计
+#[derive(Debug, Default)]
+pub struct QuizHealth {
+    /// 窗口内的作答结果：(作答时刻, 是否答对)
+    outcomes: Mutex<VecDeque<(Instant, bool)>>,
+    /// 上一次提醒的时刻（异常已解除时为 `None`）
+    alerted: Mutex<Option<Instant>>,
+}
+
+impl QuizHealth {
+    /// 创建空的统计
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次作答结果
+    pub fn record_answer(&self, correct: bool) {
+        let mut outcomes = self.outcomes.lock();
+        prune(&mut outcomes);
+        outcomes.push_back((Instant::now(), correct));
+    }
+
+    /// 窗口内的答错率（百分比）
+    ///
+    /// ### 返回值
+    /// 作答次数不足 `MIN_SAMPLES` 时返回 `None`
+    pub fn failure_percent(&self) -> Option<u32> {
+        self.failure_counts().map(|(failed, total)| (failed * 100 / total) as u32)
+    }
+
+    /// 窗口内的答错率（0 ~ 1）
+    ///
+    /// ### 返回值
+    /// 作答次数不足 `MIN_SAMPLES` 时返回 `None`
+    pub fn failure_ratio(&self) -> Option<f64> {
+        self.failure_counts().map(|(failed, total)| failed as f64 / total as f64)
+    }
+
+    /// 窗口内的 (答错次数, 作答次数)，作答次数不足 `MIN_SAMPLES` 时返回 `None`
+    fn failure_counts(&self) -> Option<(usize, usize)> {
+        let mut outcomes = self.outcomes.lock();
+        prune(&mut outcomes);
+        if outcomes.len() < MIN_SAMPLES {
+            return None;
+        }
+        let failed = outcomes.iter().filter(|(_, correct)| !correct).count();
+        Some((failed, outcomes.len()))
+    }
+
+    /// 根据当前情况判断是否需要提醒主播（定期调用）
+    ///
+    /// ### 参数
+    /// - `stuck_pending`: 长时间未通过答题的观众数
+    /// - `stuck_threshold`: 卡住人数超过此值时提醒，0 表示不检查
+    /// - `failure_threshold`: 答错率（百分比）达到此值时提醒，0 表示不检查
+    ///
+    /// ### 返回值
+    /// 需要提醒时返回待发布的事件；异常持续期间每 `REPEAT_INTERVAL` 最多提醒一次
+    pub fn evaluate(
+        &self,
+        stuck_pending: usize,
+        stuck_threshold: usize,
+        failure_threshold: u32,
+    ) -> Option<StreamEvent> {
+        let failure_percent = self.failure_percent();
+        let stuck = stuck_threshold > 0 && stuck_pending > stuck_threshold;
+        let failing = failure_threshold > 0 && failure_percent.is_some_and(|p| p >= failure_threshold);
+
+        let mut alerted = self.alerted.lock();
+        if !stuck && !failing {
+            if alerted.take().is_some() {
+                tracing::info!("答题情况已恢复正常");
+            }
+            return None;
+        }
+        if alerted.is_some_and(|at| at.elapsed() < REPEAT_INTERVAL) {
+            return None;
+        }
+        *alerted = Some(Instant::now());
+        tracing::warn!(
+            "答题异常：{} 名观众超过一分钟仍未通过，近期答错率 {}，题库可能过难或已损坏",
+            stuck_pending,
+            failure_percent.map_or_else(|| "未知".to_string(), |p| format!("{}%", p))
+        );
+        Some(StreamEvent::QuizTrouble {
+            stuck_pending,
+            failure_percent,
+        })
+    }
+}
+
+/// 移除窗口外的作答结果
+fn prune(outcomes: &mut VecDeque<(Instant, bool)>) {
+    while outcomes.front().is_some_and(|(at, _)| at.elapsed() > WINDOW) {
+        outcomes.pop_front();
+    }
+}
@@ -0,0 +1,71 @@
+//! # 录制文件登记模块
+//!
+//! 记录 SRS 通过 `on_dvr` 回调报告的录制文件，并关联到对应的直播场次，
+//! 便于直播结束后查找本场的录像。
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// 最多保留的录制文件记录数（超出后丢弃最早的记录）
+const MAX_RECORDINGS: usize = 1000;
+
+/// 录制文件记录
+#[derive(Debug, Clone, Serialize)]
+pub struct Recording {
+    /// SRS 报告的文件路径（SRS 所在主机上的路径）
+    pub file: String,
+    /// 应用名称
+    pub app: String,
+    /// 流名称
+    pub stream: String,
+    /// 所属直播场次 ID（录制完成时没有直播时为 `None`）
+    pub stream_session_id: Option<String>,
+    /// 登记时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 录制文件登记表
+#[derive(Debug, Default)]
+pub struct RecordingRegistry {
+    /// 录制文件记录（按登记时间排序）
+    entries: RwLock<VecDeque<Recording>>,
+}
+
+impl RecordingRegistry {
+    /// 创建空的登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个录制文件
+    ///
+    /// ### 返回值
+    /// 该文件已登记过（重复的回调）时返回 `false`
+    pub fn record(&self, recording: Recording) -> bool {
+        let mut entries = self.entries.write();
+        if entries.iter().any(|r| r.file == recording.file) {
+            return false;
+        }
+        if entries.len() >= MAX_RECORDINGS {
+            entries.pop_front();
+        }
+        entries.push_back(recording);
+        true
+    }
+
+    /// 列出录制文件（最新的在前）
+    ///
+    /// ### 参数
+    /// - `stream_session_id`: 只列出指定直播场次的录制文件
+    pub fn list(&self, stream_session_id: Option<&str>) -> Vec<Recording> {
+        self.entries
+            .read()
+            .iter()
+            .rev()
+            .filter(|r| stream_session_id.is_none() || r.stream_session_id.as_deref() == stream_session_id)
+            .cloned()
+            .collect()
+    }
+}
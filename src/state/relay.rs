@@ -0,0 +1,230 @@
+//! # 转推模块
+//!
+//! 通过 FFmpeg 从 SRS 拉取当前直播，转推到外部 RTMP 平台（YouTube、Twitch 等）。
+//!
+//! 平台推流地址中含有推流码，配置中只保存 ChaCha20-Poly1305 加密后的密文，
+//! 密钥由 `LIVE_SERVER_RELAY_KEY` 派生，仅在启动转推时解密。
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use tokio::process::{Child, Command};
+
+use super::secret_guard::secret_eq;
+
+/// ChaCha20-Poly1305 随机数长度（字节）
+const NONCE_LEN: usize = 12;
+
+/// 转推目标地址的加解密
+#[derive(Clone)]
+pub struct RelayCipher {
+    /// 加密器
+    cipher: ChaCha20Poly1305,
+}
+
+impl RelayCipher {
+    /// 由配置的密钥派生加密器（SHA-256 得到 256 位密钥）
+    pub fn new(key: &[u8]) -> Self {
+        let key = Sha256::digest(key);
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        }
+    }
+
+    /// 加密推流地址
+    ///
+    /// ### 返回值
+    /// `base64url(随机数 || 密文)`，可直接写入 `LIVE_SERVER_RELAY_TARGETS`
+    pub fn seal(&self, url: &str) -> String {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), url.as_bytes())
+            .expect("内存中加密不会失败");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        URL_SAFE_NO_PAD.encode(sealed)
+    }
+
+    /// 解密推流地址
+    ///
+    /// ### 返回值
+    /// 密文格式错误、被篡改或密钥不匹配时返回 `None`
+    pub fn open(&self, sealed: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+}
+
+/// 转推任务
+struct RelayJob {
+    /// FFmpeg 子进程
+    child: Child,
+    /// 开始时间
+    started_at: DateTime<Utc>,
+    /// 进程退出状态（仍在运行时为 `None`）
+    exit: Option<String>,
+}
+
+/// 转推任务状态
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayStatus {
+    /// 转推目标名称
+    pub target: String,
+    /// 是否正在转推
+    pub running: bool,
+    /// 开始时间
+    pub started_at: DateTime<Utc>,
+    /// FFmpeg 退出状态（仍在运行时不返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit: Option<String>,
+}
+
+/// 转推任务管理器
+pub struct RelayManager {
+    /// 目标地址加解密（未配置密钥时为 `None`，转推不可用）
+    cipher: Option<RelayCipher>,
+    /// 拉流令牌：FFmpeg 从 SRS 拉流时携带，使 on_play 回调放行
+    pull_token: String,
+    /// 转推任务（按目标名称）
+    jobs: Mutex<BTreeMap<String, RelayJob>>,
+}
+
+impl RelayManager {
+    /// 创建新的管理器
+    ///
+    /// ### 参数
+    /// - `key`: 转推目标地址的加密密钥
+    pub fn new(key: Option<&[u8]>) -> Self {
+        let token: [u8; 16] = rand::thread_rng().gen();
+        Self {
+            cipher: key.map(RelayCipher::new),
+            pull_token: token.iter().map(|b| format!("{:02x}", b)).collect(),
+            jobs: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 目标地址加解密（未配置密钥时为 `None`）
+    pub fn cipher(&self) -> Option<&RelayCipher> {
+        self.cipher.as_ref()
+    }
+
+    /// 拉流令牌是否有效（on_play 回调中使用）
+    pub fn is_pull_token(&self, token: &str) -> bool {
+        secret_eq(token, &self.pull_token)
+    }
+
+    /// FFmpeg 的拉流地址
+    ///
+    /// ### 参数
+    /// - `srs_host`: SRS 主机
+    /// - `rtmp_port`: SRS RTMP 端口
+    pub fn source_url(&self, srs_host: &str, rtmp_port: u16, app: &str, stream: &str) -> String {
+        format!("rtmp://{}:{}/{}/{}?relay={}", srs_host, rtmp_port, app, stream, self.pull_token)
+    }
+
+    /// 开始转推
+    ///
+    /// ### 参数
+    /// - `ffmpeg`: FFmpeg 可执行文件路径
+    /// - `name`: 转推目标名称
+    /// - `source`: 拉流地址（见 `source_url`）
+    /// - `target`: 解密后的平台推流地址
+    ///
+    /// ### 返回值
+    /// - `Ok(())`: 已启动 FFmpeg
+    /// - `Err(msg)`: 该目标已在转推或 FFmpeg 启动失败
+    pub fn start(&self, ffmpeg: &str, name: &str, source: &str, target: &str) -> Result<(), String> {
+        let mut jobs = self.jobs.lock();
+        if let Some(job) = jobs.get_mut(name) {
+            if refresh(job) {
+                return Err(format!("{} 已在转推", name));
+            }
+        }
+
+        let child = Command::new(ffmpeg)
+            .args(["-nostdin", "-loglevel", "error", "-i", source])
+            .args(["-c", "copy", "-f", "flv", target])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("启动 FFmpeg 失败: {}", e))?;
+        jobs.insert(
+            name.to_string(),
+            RelayJob {
+                child,
+                started_at: Utc::now(),
+                exit: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// 停止转推
+    ///
+    /// ### 返回值
+    /// - `true`: 已停止
+    /// - `false`: 该目标没有转推任务
+    pub fn stop(&self, name: &str) -> bool {
+        match self.jobs.lock().remove(name) {
+            Some(mut job) => {
+                let _ = job.child.start_kill();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 停止全部转推（直播结束时调用）
+    pub fn stop_all(&self) {
+        for (_, mut job) in std::mem::take(&mut *self.jobs.lock()) {
+            let _ = job.child.start_kill();
+        }
+    }
+
+    /// 获取全部转推任务的状态
+    pub fn status(&self) -> Vec<RelayStatus> {
+        self.jobs
+            .lock()
+            .iter_mut()
+            .map(|(name, job)| RelayStatus {
+                target: name.clone(),
+                running: refresh(job),
+                started_at: job.started_at,
+                exit: job.exit.clone(),
+            })
+            .collect()
+    }
+}
+
+/// 检查 FFmpeg 进程是否仍在运行，已退出时记录退出状态
+fn refresh(job: &mut RelayJob) -> bool {
+    if job.exit.is_some() {
+        return false;
+    }
+    match job.child.try_wait() {
+        Ok(None) => true,
+        Ok(Some(status)) => {
+            job.exit = Some(status.to_string());
+            false
+        }
+        Err(e) => {
+            job.exit = Some(e.to_string());
+            false
+        }
+    }
+}
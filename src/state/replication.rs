@@ -0,0 +1,537 @@
+//! # 热备复制模块
+//!
+//! 主实例通过一条 TCP 链路把状态变化推送给备用实例，故障切换时备用实例已掌握观众的授权状态，
+//! 观众不必全部重新答题。复制的内容：
+//! - 已授权（及开播前排队）的观众会话
+//! - 直播聊天室的新消息（含发送者昵称）
+//! - 正在进行的直播场次：备用实例将其视为刚暂停的直播，推流端切换过来后以相同密钥推流即延续同一场直播
+//!
+//! ## 链路协议
+//! 每行一帧，内容为以 `LIVE_SERVER_REPLICATION_KEY` 派生的密钥加密（ChaCha20-Poly1305，同转推地址）的 JSON，
+//! 链路本身无需 TLS，密钥不一致的一端无法解密任何帧。
+//!
+//! 主实例接受连接后先发送一帧随机挑战，备用实例回复的握手须带回该挑战并附上自己的随机数，
+//! 两者拼接为本次连接的会话标识。之后每一帧都连同会话标识和从 0 递增的序号一起加密，
+//! 备用实例拒绝会话标识不符或序号不连续的帧，录下的旧帧无法被重放或调换顺序。
+//! 握手通过后主实例推送完整状态，之后每秒推送变化，并每 30 秒重发一次完整状态以刷新备用实例中的记录。
+//!
+//! ## 限制
+//! - 复制是单向的，主实例在线期间备用实例上的观众记录以主实例为准
+//! - 聊天只复制新消息，之后的删除和举报不同步；故障切换后观众重新发言会得到新的 UID
+//! - 主播需在备用实例上重新以推流密钥登录
+
+use crate::config::ReplicationRole;
+use crate::domain::{ClientIp, SessionId};
+use crate::logging;
+use crate::state::chat::{ChatDatabase, ChatEntry};
+use crate::state::health::Health;
+use crate::state::relay::RelayCipher;
+use crate::state::srs::{ClientStatus, SrsDatabase, StreamerRecord, StreamerState, StreamerStatus};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// 备用实例接收复制数据的后台任务名称（见 `/admin/status`）
+pub const REPLICATION_TASK: &str = "replication";
+
+/// 主实例推送变化的间隔
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 主实例重发完整状态的间隔
+const FULL_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 备用实例断线后的重连间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// 等待握手帧的超时时间
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 握手帧的最大长度（字节）
+const MAX_HELLO_BYTES: u64 = 1024;
+
+/// 握手随机数的字节数
+const NONCE_BYTES: usize = 16;
+
+/// 备用实例连接时补发的聊天消息条数上限
+const MAX_CHAT_BACKLOG: usize = 500;
+
+/// 复制的观众会话
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ReplicatedClient {
+    /// 客户端 IP
+    ip: ClientIp,
+    /// 会话 ID
+    session_id: SessionId,
+    /// 是否在开播前排队中（否则为已授权）
+    #[serde(default)]
+    waiting: bool,
+    /// 显示昵称
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+/// 复制的直播场次（含推流密钥，只在加密链路中传输，不实现 `Debug`）
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct ReplicatedStreamer {
+    /// 应用名称
+    app: String,
+    /// 流名称
+    stream: String,
+    /// 推流密钥
+    secret: String,
+    /// 直播场次 ID
+    stream_session_id: Option<String>,
+    /// 本场直播开始推流的时间
+    live_since: Option<DateTime<Utc>>,
+    /// 直播间名称
+    stream_name: Option<String>,
+}
+
+impl ReplicatedStreamer {
+    /// 读取正在进行的直播场次（未推流时返回 `None`）
+    fn capture(state: &StreamerState) -> Option<Self> {
+        if !state.is_streaming() {
+            return None;
+        }
+        let record = &state.streamer;
+        Some(Self {
+            app: record.app.clone()?,
+            stream: record.stream.clone()?,
+            secret: record.secret.as_ref()?.expose_secret().to_string(),
+            stream_session_id: record.stream_session_id.clone(),
+            live_since: record.live_since,
+            stream_name: record.stream_name.clone(),
+        })
+    }
+
+    /// 转为暂停状态的主播记录
+    fn into_record(self) -> StreamerRecord {
+        let mut record = StreamerRecord::new();
+        record.secret = Some(SecretString::from(self.secret));
+        record.app = Some(self.app);
+        record.stream = Some(self.stream);
+        record.stream_session_id = self.stream_session_id;
+        record.live_since = self.live_since;
+        record.stream_name = self.stream_name;
+        record.status = StreamerStatus::Pausing;
+        record
+    }
+}
+
+/// 复制的聊天消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReplicatedChat {
+    /// 消息
+    entry: ChatEntry,
+    /// 发送者昵称
+    #[serde(default)]
+    name: Option<String>,
+    /// 发送者 IP
+    #[serde(default)]
+    ip: Option<ClientIp>,
+}
+
+/// 链路上传输的帧
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Frame {
+    /// 主实例接受连接后发送的挑战
+    Challenge {
+        /// 主实例的随机数
+        nonce: String,
+    },
+    /// 备用实例回复的握手
+    Hello {
+        /// 带回的主实例随机数
+        challenge: String,
+        /// 备用实例的随机数
+        nonce: String,
+    },
+    /// 观众会话变化（`full` 为真时 `upsert` 是完整列表，备用实例移除列表之外的会话）
+    Clients {
+        full: bool,
+        upsert: Vec<ReplicatedClient>,
+        remove: Vec<(ClientIp, SessionId)>,
+    },
+    /// 直播场次（`None` 表示主实例未在推流）
+    Streamer { streamer: Option<ReplicatedStreamer> },
+    /// 直播聊天室的新消息
+    Chat {
+        /// 聊天室 ID
+        room: String,
+        /// 直播场次 ID
+        session_id: Option<String>,
+        entries: Vec<ReplicatedChat>,
+    },
+}
+
+/// 握手之后链路上的加密单元：帧连同所属连接和序号一起加密
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    /// 会话标识（主实例随机数 + 备用实例随机数）
+    session: String,
+    /// 帧序号（每个连接从 0 开始递增）
+    seq: u64,
+    /// 帧
+    frame: Frame,
+}
+
+/// 生成握手随机数（十六进制）
+fn random_nonce() -> String {
+    let bytes: [u8; NONCE_BYTES] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 主实例为单个备用实例记录的已推送状态
+#[derive(Default)]
+struct Follower {
+    /// 已推送的观众会话
+    clients: HashMap<(ClientIp, SessionId), ReplicatedClient>,
+    /// 已推送的直播场次
+    streamer: Option<ReplicatedStreamer>,
+    /// 已推送消息所在的聊天室
+    room: Option<String>,
+    /// 已推送的最后一条消息 ID
+    last_chat: Option<String>,
+    /// 上次推送完整状态的时刻
+    last_full: Option<Instant>,
+}
+
+impl Follower {
+    /// 收集自上次推送以来的变化
+    fn collect(&mut self, srs_db: &SrsDatabase, chat_db: &ChatDatabase) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        let full = self.last_full.is_none_or(|at| at.elapsed() >= FULL_SYNC_INTERVAL);
+
+        let streamer = ReplicatedStreamer::capture(&srs_db.streamer.read());
+        let clients: HashMap<(ClientIp, SessionId), ReplicatedClient> = srs_db
+            .clients
+            .read()
+            .clients
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|c| c.status.is_authorized() || c.status == ClientStatus::Waiting)
+            .map(|c| {
+                let client = ReplicatedClient {
+                    ip: c.ip.clone(),
+                    session_id: c.session_id.clone(),
+                    waiting: c.status == ClientStatus::Waiting,
+                    display_name: c.display_name.clone(),
+                };
+                ((c.ip.clone(), c.session_id.clone()), client)
+            })
+            .collect();
+
+        if full {
+            self.last_full = Some(Instant::now());
+            frames.push(Frame::Clients { full: true, upsert: clients.values().cloned().collect(), remove: Vec::new() });
+            frames.push(Frame::Streamer { streamer: streamer.clone() });
+        } else {
+            let upsert: Vec<ReplicatedClient> = clients
+                .iter()
+                .filter(|(key, client)| self.clients.get(*key) != Some(*client))
+                .map(|(_, client)| client.clone())
+                .collect();
+            let remove: Vec<(ClientIp, SessionId)> =
+                self.clients.keys().filter(|key| !clients.contains_key(*key)).cloned().collect();
+            if !upsert.is_empty() || !remove.is_empty() {
+                frames.push(Frame::Clients { full: false, upsert, remove });
+            }
+            if streamer != self.streamer {
+                frames.push(Frame::Streamer { streamer: streamer.clone() });
+            }
+        }
+        self.clients = clients;
+        self.streamer = streamer;
+
+        let chat_rooms = chat_db.inner.read();
+        if !chat_rooms.is_lobby_active() {
+            let room = chat_rooms.active();
+            if self.room.as_deref() != Some(room.id.as_str()) {
+                self.room = Some(room.id.clone());
+                self.last_chat = None;
+            }
+            let start = match &self.last_chat {
+                Some(id) => room.messages.partition_point(|e| e.id <= *id),
+                None => room.messages.len().saturating_sub(MAX_CHAT_BACKLOG),
+            };
+            let entries: Vec<ReplicatedChat> = room.messages[start..]
+                .iter()
+                .map(|entry| ReplicatedChat {
+                    entry: entry.clone(),
+                    name: room.uid_map.get(&entry.uid).cloned(),
+                    ip: room.ip_map.get(&entry.uid).cloned(),
+                })
+                .collect();
+            if let Some(last) = entries.last() {
+                self.last_chat = Some(last.entry.id.clone());
+                frames.push(Frame::Chat { room: room.id.clone(), session_id: room.session_id.clone(), entries });
+            }
+        }
+        frames
+    }
+}
+
+/// 观众会话与直播聊天室的完整快照（零停机升级时交给新进程，见 `upgrade` 模块）
+///
+/// 内容与主实例推送给新连接的备用实例的完整状态相同，但不含直播场次：
+/// 升级时推流端的连接并未中断，主播记录由 `upgrade` 模块按原状态交接
+#[derive(Serialize, Deserialize)]
+pub struct StateExport {
+    frames: Vec<Frame>,
+}
+
+impl StateExport {
+    /// 读取当前的完整状态
+    pub fn capture(srs_db: &SrsDatabase, chat_db: &ChatDatabase) -> Self {
+        let frames = Follower::default()
+            .collect(srs_db, chat_db)
+            .into_iter()
+            .filter(|frame| !matches!(frame, Frame::Streamer { .. }))
+            .collect();
+        Self { frames }
+    }
+
+    /// 写入快照中的状态
+    ///
+    /// ### 返回值
+    /// 快照中直播聊天室的 ID（没有直播聊天室时为 `None`）
+    pub fn apply(self, srs_db: &SrsDatabase, chat_db: &ChatDatabase) -> Option<String> {
+        let mut room_id = None;
+        for frame in self.frames {
+            if let Frame::Chat { room, .. } = &frame {
+                room_id = Some(room.clone());
+            }
+            apply(frame, srs_db, chat_db);
+        }
+        room_id
+    }
+}
+
+/// 热备复制
+pub struct Replicator {
+    /// 本实例的角色
+    role: ReplicationRole,
+    /// 帧加解密
+    cipher: RelayCipher,
+}
+
+impl Replicator {
+    /// 创建复制器
+    ///
+    /// ### 参数
+    /// - `role`: 本实例的角色
+    /// - `key`: 链路共享密钥
+    pub fn new(role: ReplicationRole, key: &SecretString) -> Self {
+        Self {
+            role,
+            cipher: RelayCipher::new(key.expose_secret().as_bytes()),
+        }
+    }
+
+    /// 启动后台复制任务
+    ///
+    /// ### 行为说明
+    /// - 主实例：监听配置的地址，为每个通过握手的备用实例推送状态
+    /// - 备用实例：连接主实例并应用收到的状态，断线后每 5 秒重连，只在首次失败和恢复时输出日志；
+    ///   收到数据时上报 `replication` 任务心跳，链路中断超过 90 秒时 `/admin/status` 显示异常
+    pub fn spawn(self, srs_db: SrsDatabase, chat_db: ChatDatabase, health: Arc<Health>) -> JoinHandle<()> {
+        let this = Arc::new(self);
+        match this.role.clone() {
+            ReplicationRole::Primary(addr) => tokio::spawn(this.serve(addr, srs_db, chat_db)),
+            ReplicationRole::Standby(primary) => {
+                health.register(REPLICATION_TASK, FULL_SYNC_INTERVAL.as_secs());
+                tokio::spawn(this.follow(primary, srs_db, chat_db, health))
+            }
+        }
+    }
+
+    /// 加密一帧或一个加密单元（含行尾换行符）
+    fn seal<T: Serialize>(&self, value: &T) -> String {
+        let json = serde_json::to_string(value).expect("复制帧可以序列化");
+        format!("{}\n", self.cipher.seal(&json))
+    }
+
+    /// 解密一帧或一个加密单元
+    ///
+    /// ### 返回值
+    /// 密钥不一致、数据被篡改或格式错误时返回 `None`
+    fn open<T: DeserializeOwned>(&self, line: &str) -> Option<T> {
+        serde_json::from_str(&self.cipher.open(line.trim())?).ok()
+    }
+
+    /// 主实例：等待备用实例连接
+    async fn serve(self: Arc<Self>, addr: SocketAddr, srs_db: SrsDatabase, chat_db: ChatDatabase) {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::warn!("热备复制: 监听 {} 失败，不会向备用实例复制: {}", addr, e);
+                return;
+            }
+        };
+        tracing::info!("热备复制: 在 {} 等待备用实例连接", addr);
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    tokio::spawn(self.clone().push(stream, peer, srs_db.clone(), chat_db.clone()));
+                }
+                Err(e) => tracing::warn!("热备复制: 接受连接失败: {}", e),
+            }
+        }
+    }
+
+    /// 主实例：验证握手后持续向备用实例推送状态
+    async fn push(self: Arc<Self>, stream: TcpStream, peer: SocketAddr, srs_db: SrsDatabase, chat_db: ChatDatabase) {
+        let (read, mut write) = stream.into_split();
+        let challenge = random_nonce();
+        if write.write_all(self.seal(&Frame::Challenge { nonce: challenge.clone() }).as_bytes()).await.is_err() {
+            return;
+        }
+        let mut hello = String::new();
+        let mut reader = BufReader::new(read.take(MAX_HELLO_BYTES));
+        let received = tokio::time::timeout(HANDSHAKE_TIMEOUT, reader.read_line(&mut hello)).await;
+        let standby_nonce = match (received, self.open(&hello)) {
+            (Ok(Ok(n)), Some(Frame::Hello { challenge: echoed, nonce }))
+                if n > 0 && echoed == challenge && nonce.len() == NONCE_BYTES * 2 =>
+            {
+                nonce
+            }
+            _ => {
+                tracing::warn!(target: logging::AUDIT_TARGET, "热备复制: 拒绝了来自 {} 的连接（握手失败）", peer);
+                return;
+            }
+        };
+        tracing::info!("热备复制: 备用实例 {} 已连接", peer);
+
+        let session = format!("{}{}", challenge, standby_nonce);
+        let mut seq = 0u64;
+        let mut follower = Follower::default();
+        let mut interval = tokio::time::interval(SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            for frame in follower.collect(&srs_db, &chat_db) {
+                let envelope = Envelope { session: session.clone(), seq, frame };
+                seq += 1;
+                if let Err(e) = write.write_all(self.seal(&envelope).as_bytes()).await {
+                    tracing::warn!("热备复制: 备用实例 {} 已断开: {}", peer, e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 备用实例：连接主实例，断线后重连
+    async fn follow(self: Arc<Self>, primary: String, srs_db: SrsDatabase, chat_db: ChatDatabase, health: Arc<Health>) {
+        let mut failing = false;
+        loop {
+            let result = self.receive(&primary, &srs_db, &chat_db, &health, &mut failing).await;
+            if !failing {
+                match result {
+                    Ok(()) => tracing::warn!("热备复制: 主实例 {} 关闭了连接", primary),
+                    Err(e) => tracing::warn!("热备复制: 与主实例 {} 的连接失败: {}", primary, e),
+                }
+                failing = true;
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// 备用实例：接收并应用一次连接中的全部帧
+    ///
+    /// ### 返回值
+    /// - `Ok(())`: 主实例关闭了连接
+    /// - `Err(msg)`: 连接失败、读取失败、无法解密，或收到重放、乱序的帧
+    async fn receive(
+        &self,
+        primary: &str,
+        srs_db: &SrsDatabase,
+        chat_db: &ChatDatabase,
+        health: &Health,
+        failing: &mut bool,
+    ) -> Result<(), String> {
+        const KEY_MISMATCH: &str = "无法解密收到的数据，请检查两端的 LIVE_SERVER_REPLICATION_KEY";
+
+        let stream = TcpStream::connect(primary).await.map_err(|e| e.to_string())?;
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        let challenge = tokio::time::timeout(HANDSHAKE_TIMEOUT, lines.next_line())
+            .await
+            .map_err(|_| "等待主实例的握手挑战超时".to_string())?
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "主实例在握手前关闭了连接".to_string())?;
+        let Some(Frame::Challenge { nonce: challenge }) = self.open(&challenge) else {
+            return Err(KEY_MISMATCH.to_string());
+        };
+        let nonce = random_nonce();
+        let session = format!("{}{}", challenge, nonce);
+        let hello = self.seal(&Frame::Hello { challenge, nonce });
+        write.write_all(hello.as_bytes()).await.map_err(|e| e.to_string())?;
+
+        let mut next_seq = 0u64;
+        while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+            let Some(envelope) = self.open::<Envelope>(&line) else {
+                return Err(KEY_MISMATCH.to_string());
+            };
+            if envelope.session != session || envelope.seq != next_seq {
+                tracing::warn!(target: logging::AUDIT_TARGET, "热备复制: 收到不属于本次连接或乱序的帧，已断开");
+                return Err("收到重放或乱序的复制帧".to_string());
+            }
+            if next_seq == 0 {
+                *failing = false;
+                tracing::info!("热备复制: 已连接主实例 {}", primary);
+            }
+            next_seq += 1;
+            health.beat(REPLICATION_TASK);
+            apply(envelope.frame, srs_db, chat_db);
+        }
+        if next_seq == 0 {
+            // 主实例在握手失败时直接断开，不返回原因
+            return Err("主实例未接受握手，请检查两端的 LIVE_SERVER_REPLICATION_KEY".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 备用实例：应用一帧
+fn apply(frame: Frame, srs_db: &SrsDatabase, chat_db: &ChatDatabase) {
+    match frame {
+        Frame::Challenge { .. } | Frame::Hello { .. } => {}
+        Frame::Clients { full, upsert, remove } => {
+            let mut clients = srs_db.clients.write();
+            if full {
+                let keep: HashSet<(&ClientIp, &SessionId)> = upsert.iter().map(|c| (&c.ip, &c.session_id)).collect();
+                clients.remove_clients_where(|c| {
+                    (c.status.is_authorized() || c.status == ClientStatus::Waiting)
+                        && !keep.contains(&(&c.ip, &c.session_id))
+                });
+            }
+            for (ip, session_id) in &remove {
+                clients.remove_client(ip, session_id);
+            }
+            for client in upsert {
+                clients.apply_replicated(client.ip, client.session_id, client.waiting, client.display_name);
+            }
+        }
+        Frame::Streamer { streamer } => {
+            srs_db.streamer.write().adopt_replicated(streamer.map(ReplicatedStreamer::into_record));
+        }
+        Frame::Chat { room, session_id, entries } => {
+            let mut chat_rooms = chat_db.inner.write();
+            if let Some(room) = chat_rooms.replica_room(&room, session_id) {
+                for chat in entries {
+                    room.insert_replicated(chat.entry, chat.name, chat.ip);
+                }
+            }
+        }
+    }
+}
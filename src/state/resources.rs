@@ -0,0 +1,170 @@
+//! # 资源占用模块
+//!
+//! 采集进程级资源占用（打开的文件描述符、常驻内存、Tokio 任务数）和主要内存表的条目数，
+//! 由 `/admin/metrics` 和 `/admin/status` 导出。
+//!
+//! 长时间直播中某张表只增不减（如永不过期的观看中记录）时，可从条目数的走势上发现。
+
+use super::AppState;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// 主要内存表的条目数
+#[derive(Debug, Clone, Serialize)]
+pub struct MapEntries {
+    /// 观众记录（全部状态）
+    pub clients: usize,
+    /// 曾通过验证的会话 ID（跨直播保留）
+    pub legal_history: usize,
+    /// 近期发放题目记录的 IP 数（跨直播保留）
+    pub served_questions: usize,
+    /// 当前聊天室的消息数
+    pub chat_messages: usize,
+    /// 当前聊天室的身份数
+    pub chat_identities: usize,
+    /// 当前聊天室已占用的昵称数
+    pub chat_names: usize,
+}
+
+impl MapEntries {
+    /// 按 (表名, 条目数) 列出，顺序固定
+    fn iter(&self) -> [(&'static str, usize); 6] {
+        [
+            ("clients", self.clients),
+            ("legal_history", self.legal_history),
+            ("served_questions", self.served_questions),
+            ("chat_messages", self.chat_messages),
+            ("chat_identities", self.chat_identities),
+            ("chat_names", self.chat_names),
+        ]
+    }
+}
+
+/// 进程资源占用
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUsage {
+    /// 打开的文件描述符数（当前平台不支持时为 `None`）
+    pub open_fds: Option<u64>,
+    /// 常驻内存字节数（当前平台不支持时为 `None`）
+    pub resident_bytes: Option<u64>,
+    /// 存活的 Tokio 任务数
+    pub tokio_tasks: usize,
+    /// 主要内存表的条目数
+    pub entries: MapEntries,
+}
+
+impl ResourceUsage {
+    /// 采集当前资源占用
+    pub fn collect(state: &AppState) -> Self {
+        let (clients, legal_history, served_questions) = {
+            let clients = state.srs_db.clients.read();
+            (
+                clients.session_index.len(),
+                clients.legal_history.len(),
+                clients.served_questions.len(),
+            )
+        };
+        let (chat_messages, chat_identities, chat_names) = {
+            let chat_rooms = state.chat_db.inner.read();
+            let room = chat_rooms.active();
+            (room.messages.len(), room.ip_map.len(), room.name_map.len())
+        };
+
+        Self {
+            open_fds: open_fds(),
+            resident_bytes: resident_bytes(),
+            tokio_tasks: tokio::runtime::Handle::try_current()
+                .map(|h| h.metrics().num_alive_tasks())
+                .unwrap_or(0),
+            entries: MapEntries {
+                clients,
+                legal_history,
+                served_questions,
+                chat_messages,
+                chat_identities,
+                chat_names,
+            },
+        }
+    }
+
+    /// 以 Prometheus 文本格式导出
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        if let Some(fds) = self.open_fds {
+            out.push_str("# HELP live_server_open_fds 打开的文件描述符数\n");
+            out.push_str("# TYPE live_server_open_fds gauge\n");
+            let _ = writeln!(out, "live_server_open_fds {}", fds);
+        }
+
+        if let Some(bytes) = self.resident_bytes {
+            out.push_str("# HELP live_server_resident_memory_bytes 常驻内存字节数\n");
+            out.push_str("# TYPE live_server_resident_memory_bytes gauge\n");
+            let _ = writeln!(out, "live_server_resident_memory_bytes {}", bytes);
+        }
+
+        out.push_str("# HELP live_server_tokio_tasks 存活的 Tokio 任务数\n");
+        out.push_str("# TYPE live_server_tokio_tasks gauge\n");
+        let _ = writeln!(out, "live_server_tokio_tasks {}", self.tokio_tasks);
+
+        out.push_str("# HELP live_server_map_entries 主要内存表的条目数\n");
+        out.push_str("# TYPE live_server_map_entries gauge\n");
+        for (map, count) in self.entries.iter() {
+            let _ = writeln!(out, "live_server_map_entries{{map=\"{}\"}} {}", map, count);
+        }
+
+        out
+    }
+
+    /// 渲染为纯文本表格中的一行说明
+    pub fn summary(&self) -> String {
+        let mut out = format!("{} tasks", self.tokio_tasks);
+        if let Some(fds) = self.open_fds {
+            let _ = write!(out, ", {} fds", fds);
+        }
+        if let Some(bytes) = self.resident_bytes {
+            let _ = write!(out, ", {} MiB rss", bytes / 1024 / 1024);
+        }
+        for (map, count) in self.entries.iter() {
+            let _ = write!(out, ", {} {}", count, map);
+        }
+        out
+    }
+}
+
+/// 打开的文件描述符数
+///
+/// ### 返回值
+/// 读取 `/proc/self/fd` 失败时返回 `None`
+#[cfg(target_os = "linux")]
+fn open_fds() -> Option<u64> {
+    // 计数时 read_dir 自身也会打开一个描述符
+    std::fs::read_dir("/proc/self/fd")
+        .ok()
+        .map(|dir| dir.count().saturating_sub(1) as u64)
+}
+
+/// 打开的文件描述符数（当前平台不支持）
+#[cfg(not(target_os = "linux"))]
+fn open_fds() -> Option<u64> {
+    None
+}
+
+/// 常驻内存字节数
+///
+/// ### 返回值
+/// 读取 `/proc/self/statm` 失败时返回 `None`
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf 只读取系统常量
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    u64::try_from(page_size).ok().map(|size| pages * size)
+}
+
+/// 常驻内存字节数（当前平台不支持）
+#[cfg(not(target_os = "linux"))]
+fn resident_bytes() -> Option<u64> {
+    None
+}
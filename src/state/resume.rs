@@ -0,0 +1,96 @@
+//! # 播放续连令牌模块
+//!
+//! FLV 播放器断线重连会重新触发 `on_play` 回调，若观众记录恰好在断线期间过期，
+//! 播放会静默失败。已授权观众拿到的 `video_uri` 中附带一个短期有效的续连令牌（`resume`），
+//! `on_play` 遇到未知会话时凭令牌重建观众记录，观众无需重新答题。
+//!
+//! 令牌绑定会话 ID 和直播场次，签名密钥在启动时随机生成，服务重启或直播结束后全部失效。
+//!
+//! ## 令牌格式
+//! `<过期时间戳>.<Base64URL 编码的 IP>.<HMAC-SHA256 十六进制>`
+
+use crate::domain::{ClientIp, SessionId};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 令牌有效期（每次返回 `video_uri` 时重新签发）
+pub const RESUME_VALIDITY_MINUTES: i64 = 15;
+
+/// 播放续连令牌签发器
+#[derive(Clone)]
+pub struct ResumeSigner {
+    /// HMAC 密钥（启动时随机生成）
+    key: [u8; 32],
+}
+
+impl ResumeSigner {
+    /// 创建新的令牌签发器
+    pub fn new() -> Self {
+        Self {
+            key: rand::thread_rng().gen(),
+        }
+    }
+
+    /// 计算令牌的签名
+    ///
+    /// ### 参数
+    /// - `session_id`: 观众会话 ID
+    /// - `stream_session`: 直播场次 ID
+    /// - `payload`: 令牌中签名之前的部分
+    fn sign(&self, session_id: &SessionId, stream_session: &str, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC 可接受任意长度密钥");
+        mac.update(format!("{}.{}.{}", session_id, stream_session, payload).as_bytes());
+        mac
+    }
+
+    /// 为已授权观众签发续连令牌
+    ///
+    /// ### 参数
+    /// - `ip`: 观众 IP
+    /// - `session_id`: 观众会话 ID
+    /// - `stream_session`: 当前直播场次 ID
+    pub fn issue(&self, ip: &ClientIp, session_id: &SessionId, stream_session: &str) -> String {
+        let expires_at = (Utc::now() + Duration::minutes(RESUME_VALIDITY_MINUTES)).timestamp();
+        let payload = format!("{}.{}", expires_at, URL_SAFE_NO_PAD.encode(ip.as_str()));
+        let signature = self.sign(session_id, stream_session, &payload).finalize().into_bytes();
+        let hex: String = signature.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}.{}", payload, hex)
+    }
+
+    /// 校验续连令牌
+    ///
+    /// ### 参数
+    /// - `token`: 令牌
+    /// - `session_id`: 发起拉流的会话 ID
+    /// - `stream_session`: 当前直播场次 ID
+    ///
+    /// ### 返回值
+    /// 令牌有效时返回签发时的观众 IP
+    pub fn verify(&self, token: &str, session_id: &SessionId, stream_session: &str) -> Option<ClientIp> {
+        let (payload, hex) = token.rsplit_once('.')?;
+        let (expires_at, ip) = payload.split_once('.')?;
+        if Utc::now().timestamp() > expires_at.parse::<i64>().ok()? || hex.len() % 2 != 0 {
+            return None;
+        }
+        let signature = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        // verify_slice 使用常量时间比较
+        self.sign(session_id, stream_session, payload)
+            .verify_slice(&signature)
+            .ok()?;
+        ClientIp::parse(std::str::from_utf8(&URL_SAFE_NO_PAD.decode(ip).ok()?).ok()?)
+    }
+}
+
+impl Default for ResumeSigner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,132 @@
+//! # 定时消息模块
+//!
+//! 主播可以预约在指定时间或开播后指定时长自动发送的聊天消息（如"开播 60 分钟后抽奖"），
+//! 消息在到期前保存在待发送队列中，可随时取消，由后台清理任务定期检查并发送。
+
+use crate::domain::{ClientIp, SessionId};
+use crate::ids;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// 待发送消息的数量上限
+pub const MAX_SCHEDULED: usize = 50;
+
+/// 发送时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduleTime {
+    /// 指定时刻（只在直播进行中发送，到期时未在直播则丢弃）
+    At { at: DateTime<Utc> },
+    /// 开播后经过指定秒数（未在直播时等待下一场直播）
+    StreamOffset { secs: u64 },
+}
+
+impl ScheduleTime {
+    /// 计算实际发送时刻
+    ///
+    /// ### 参数
+    /// - `live_since`: 本场直播开始推流的时间（未推流时为 `None`）
+    fn due_at(&self, live_since: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        match *self {
+            Self::At { at } => Some(at),
+            Self::StreamOffset { secs } => {
+                live_since.map(|since| since + Duration::seconds(secs.min(i64::MAX as u64) as i64))
+            }
+        }
+    }
+}
+
+/// 一条待发送的定时消息
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledMessage {
+    /// 定时消息 ID（用于取消）
+    pub id: String,
+    /// 消息内容
+    pub message: String,
+    /// 发送时间
+    pub when: ScheduleTime,
+    /// 预约时间
+    pub created_at: DateTime<Utc>,
+    /// 预约者 IP（发送时作为主播消息的来源）
+    #[serde(skip)]
+    pub ip: ClientIp,
+    /// 预约者会话 ID
+    #[serde(skip)]
+    pub session_id: SessionId,
+}
+
+/// 定时消息队列
+#[derive(Debug, Default)]
+pub struct MessageScheduler {
+    /// 待发送的消息（按预约先后）
+    pending: Mutex<Vec<ScheduledMessage>>,
+}
+
+impl MessageScheduler {
+    /// 创建空队列
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 预约一条消息
+    ///
+    /// ### 返回值
+    /// - `Some(message)`: 预约成功
+    /// - `None`: 待发送消息已达上限
+    pub fn schedule(&self, ip: ClientIp, session_id: SessionId, message: String, when: ScheduleTime) -> Option<ScheduledMessage> {
+        let mut pending = self.pending.lock();
+        if pending.len() >= MAX_SCHEDULED {
+            return None;
+        }
+        let entry = ScheduledMessage {
+            id: ids::ulid(),
+            message,
+            when,
+            created_at: Utc::now(),
+            ip,
+            session_id,
+        };
+        pending.push(entry.clone());
+        Some(entry)
+    }
+
+    /// 取消一条定时消息
+    ///
+    /// ### 返回值
+    /// 消息不存在（已发送或已取消）时返回 `false`
+    pub fn cancel(&self, id: &str) -> bool {
+        let mut pending = self.pending.lock();
+        let before = pending.len();
+        pending.retain(|m| m.id != id);
+        pending.len() != before
+    }
+
+    /// 列出全部待发送消息
+    pub fn list(&self) -> Vec<ScheduledMessage> {
+        self.pending.lock().clone()
+    }
+
+    /// 取出已到期的消息（定期调用）
+    ///
+    /// ### 参数
+    /// - `live_since`: 本场直播开始推流的时间（未推流时为 `None`）
+    ///
+    /// ### 返回值
+    /// 需要立即发送的消息；未在直播时到期的指定时刻消息被丢弃
+    pub fn take_due(&self, live_since: Option<DateTime<Utc>>) -> Vec<ScheduledMessage> {
+        let now = Utc::now();
+        let mut pending = self.pending.lock();
+        let (due, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut *pending)
+            .into_iter()
+            .partition(|m| m.when.due_at(live_since).is_some_and(|at| at <= now));
+        *pending = rest;
+        if live_since.is_some() {
+            return due;
+        }
+        for message in &due {
+            tracing::info!("定时消息 {} 到期时未在直播，已丢弃", message.id);
+        }
+        Vec::new()
+    }
+}
@@ -0,0 +1,193 @@
+//! # 脚本钩子模块
+//!
+//! 允许运维人员用 Rhai 脚本实现自定义的准入策略（VIP 名单、分时段规则等），无需修改源码。
+//! 需要启用 `scripting` 特性编译。
+//!
+//! ## 钩子函数
+//! 脚本中可以定义以下任意函数，未定义的函数按默认逻辑处理：
+//! - `on_connect(ctx)`: 新观众连接时调用
+//! - `on_answer(ctx)`: 观众提交答案时调用（主播密钥登录不经过此钩子）
+//! - `on_publish(ctx)`: 主播开始推流时调用
+//!
+//! `ctx` 为对象映射，包含 `ip`、`session_id`、`hour`（服务器本地时间的小时）等字段，
+//! 不同钩子还会附带 `answer`、`app`、`stream` 等字段。
+//!
+//! ## 返回值
+//! - `"allow"` / `true`: 直接放行（连接时无需答题，答题时视为答对）
+//! - `"deny"` / `false`: 直接拒绝
+//! - 其他值（包括 `()`）: 按默认逻辑处理
+//!
+//! `on_publish` 只能拒绝推流，放行时仍需校验推流密钥
+//!
+//! ## 资源限制
+//! 脚本在请求路径上同步执行（调用方保证此时未持有任何状态锁），因此限制了运算次数、调用深度、
+//! 字符串/数组/映射大小和单次执行时长（`SCRIPT_TIME_LIMIT`）；超限时记录警告并按默认逻辑处理。
+//!
+//! 脚本无法发起网络请求，不支持调用外部接口做校验；需要外部身份校验时请使用
+//! `LIVE_SERVER_TRUSTED_USER_HEADER` / `LIVE_SERVER_OIDC_USERINFO_URL`（见 `external_auth` 模块）。
+//!
+//! ## 示例
+//! ```rhai
+//! fn on_connect(ctx) {
+//!     if ctx.ip in ["10.0.0.2", "10.0.0.3"] { return "allow"; }
+//!     if ctx.hour < 6 { return "deny"; }
+//! }
+//! ```
+
+use std::path::Path;
+use std::time::Duration;
+
+/// 单次脚本执行的最长时间
+pub const SCRIPT_TIME_LIMIT: Duration = Duration::from_millis(50);
+
+/// 单次脚本执行的最大运算次数
+#[cfg(feature = "scripting")]
+const MAX_OPERATIONS: u64 = 100_000;
+
+#[cfg(feature = "scripting")]
+thread_local! {
+    /// 当前线程上正在执行的脚本的截止时刻
+    static DEADLINE: std::cell::Cell<Option<std::time::Instant>> = const { std::cell::Cell::new(None) };
+}
+
+/// 脚本钩子调用点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPoint {
+    /// 新观众连接
+    Connect,
+    /// 观众提交答案
+    Answer,
+    /// 主播开始推流
+    Publish,
+}
+
+impl HookPoint {
+    /// 对应的脚本函数名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connect => "on_connect",
+            Self::Answer => "on_answer",
+            Self::Publish => "on_publish",
+        }
+    }
+}
+
+/// 脚本决策结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptDecision {
+    /// 直接放行
+    Allow,
+    /// 直接拒绝
+    Deny,
+    /// 按默认逻辑处理
+    Default,
+}
+
+/// 脚本钩子
+pub struct ScriptHook {
+    /// 脚本引擎
+    #[cfg(feature = "scripting")]
+    engine: rhai::Engine,
+    /// 编译后的脚本
+    #[cfg(feature = "scripting")]
+    ast: rhai::AST,
+}
+
+impl ScriptHook {
+    /// 加载并编译脚本文件
+    ///
+    /// ### 返回值
+    /// 未启用 `scripting` 特性时始终返回错误
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        #[cfg(feature = "scripting")]
+        {
+            let mut engine = rhai::Engine::new();
+            engine
+                .set_max_operations(MAX_OPERATIONS)
+                .set_max_call_levels(32)
+                .set_max_expr_depths(64, 32)
+                .set_max_string_size(4096)
+                .set_max_array_size(1024)
+                .set_max_map_size(1024);
+            // 运算次数之外再限制执行时长（如大量字符串操作）
+            engine.on_progress(|ops| {
+                if ops % 1024 != 0 {
+                    return None;
+                }
+                DEADLINE
+                    .with(|d| d.get())
+                    .filter(|deadline| std::time::Instant::now() > *deadline)
+                    .map(|_| "脚本执行超时".into())
+            });
+            let ast = engine.compile_file(path.as_ref().to_path_buf())?;
+            Ok(Self { engine, ast })
+        }
+        #[cfg(not(feature = "scripting"))]
+        {
+            Err(format!(
+                "脚本 {} 无法加载: 编译时未启用 scripting 特性",
+                path.as_ref().display()
+            )
+            .into())
+        }
+    }
+
+    /// 在指定调用点执行脚本
+    ///
+    /// ### 参数
+    /// - `point`: 调用点
+    /// - `ctx`: 上下文字段（键值对）
+    ///
+    /// ### 返回值
+    /// 脚本未定义对应函数、执行出错或超出资源限制时返回 `ScriptDecision::Default`
+    ///
+    /// ### 注意事项
+    /// 调用方不得持有任何状态锁
+    pub fn decide(&self, point: HookPoint, ctx: &[(&str, &str)]) -> ScriptDecision {
+        #[cfg(feature = "scripting")]
+        {
+            use chrono::Timelike;
+
+            let mut map = rhai::Map::new();
+            for (key, value) in ctx {
+                map.insert((*key).into(), rhai::Dynamic::from(value.to_string()));
+            }
+            map.insert("hour".into(), rhai::Dynamic::from(chrono::Local::now().hour() as i64));
+
+            let has_fn = self
+                .ast
+                .iter_functions()
+                .any(|f| f.name == point.as_str() && f.params.len() == 1);
+            if !has_fn {
+                return ScriptDecision::Default;
+            }
+
+            DEADLINE.with(|d| d.set(Some(std::time::Instant::now() + SCRIPT_TIME_LIMIT)));
+            let result = self.engine.call_fn::<rhai::Dynamic>(
+                &mut rhai::Scope::new(),
+                &self.ast,
+                point.as_str(),
+                (map,),
+            );
+            DEADLINE.with(|d| d.set(None));
+            match result {
+                Ok(value) => match (value.clone().into_string(), value.as_bool()) {
+                    (Ok(s), _) if s == "allow" => ScriptDecision::Allow,
+                    (Ok(s), _) if s == "deny" => ScriptDecision::Deny,
+                    (_, Ok(true)) => ScriptDecision::Allow,
+                    (_, Ok(false)) => ScriptDecision::Deny,
+                    _ => ScriptDecision::Default,
+                },
+                Err(e) => {
+                    tracing::warn!("脚本 {} 执行出错: {}", point.as_str(), e);
+                    ScriptDecision::Default
+                }
+            }
+        }
+        #[cfg(not(feature = "scripting"))]
+        {
+            let _ = (point, ctx);
+            ScriptDecision::Default
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! # 推流密钥防爆破模块
+//!
+//! 记录每个 IP 猜测推流密钥的失败次数，超过免费次数后按指数退避锁定：
+//! 第 4 次失败锁定 5 秒，之后每次失败锁定时长翻倍，最长 1 小时。
+//! 所有失败尝试都会写入 `audit` 目标的日志。
+
+use crate::domain::ClientIp;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+
+/// 不触发锁定的失败次数
+const FREE_ATTEMPTS: u32 = 3;
+/// 首次锁定时长（秒）
+const BASE_LOCKOUT_SECS: u64 = 5;
+/// 最长锁定时长（秒）
+const MAX_LOCKOUT_SECS: u64 = 3600;
+/// 无新失败记录多久后遗忘该 IP（小时）
+const FORGET_AFTER_HOURS: u64 = 24;
+
+/// 常数时间比较两个密钥
+///
+/// 比较耗时只与长度有关，不泄露第一个不同字符的位置
+pub fn secret_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+/// 单个 IP 的失败记录
+#[derive(Debug, Clone)]
+struct GuardEntry {
+    /// 连续失败次数
+    failures: u32,
+    /// 最近一次失败时刻（单调时钟）
+    last_failure: Instant,
+    /// 锁定截止时刻（单调时钟）
+    locked_until: Option<Instant>,
+}
+
+/// 推流密钥防爆破记录
+#[derive(Debug, Default)]
+pub struct SecretGuard {
+    /// IP -> 失败记录
+    entries: HashMap<ClientIp, GuardEntry>,
+}
+
+impl SecretGuard {
+    /// 创建空的防爆破记录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 查询 IP 是否处于锁定期
+    ///
+    /// ### 返回值
+    /// - `Some(秒数)`: 剩余锁定时长
+    /// - `None`: 未锁定
+    pub fn locked_for(&self, ip: &ClientIp) -> Option<u64> {
+        let until = self.entries.get(ip)?.locked_until?;
+        let remaining = until.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then(|| remaining.as_secs().max(1))
+    }
+
+    /// 记录一次失败尝试并写入审计日志
+    ///
+    /// ### 参数
+    /// - `ip`: 来源 IP
+    /// - `source`: 尝试入口（如 `api`、`on_publish`）
+    pub fn record_failure(&mut self, ip: &ClientIp, source: &str) {
+        let now = Instant::now();
+        let entry = self.entries.entry(ip.clone()).or_insert(GuardEntry {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        entry.failures += 1;
+        entry.last_failure = now;
+
+        let lockout = entry.failures.checked_sub(FREE_ATTEMPTS + 1).map(|exp| {
+            BASE_LOCKOUT_SECS
+                .saturating_mul(1u64.checked_shl(exp.min(32)).unwrap_or(u64::MAX))
+                .min(MAX_LOCKOUT_SECS)
+        });
+        entry.locked_until = lockout.map(|secs| now + Duration::from_secs(secs));
+
+        tracing::warn!(
+            target: crate::logging::AUDIT_TARGET,
+            "推流密钥验证失败: ip={}, source={}, failures={}, lockout_secs={}",
+            ip,
+            source,
+            entry.failures,
+            lockout.unwrap_or(0)
+        );
+    }
+
+    /// 验证成功后清除该 IP 的失败记录
+    pub fn record_success(&mut self, ip: &ClientIp) {
+        self.entries.remove(ip);
+    }
+
+    /// 清理长时间没有新失败的记录
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let forget_after = Duration::from_secs(FORGET_AFTER_HOURS * 3600);
+        self.entries.retain(|_, e| {
+            now.duration_since(e.last_failure) < forget_after
+                || e.locked_until.is_some_and(|t| t > now)
+        });
+    }
+}
@@ -6,12 +6,27 @@
 //! - 基于答题的观众鉴权
 //! - 密钥验证
 
+use arc_swap::ArcSwap;
 use chrono::{DateTime, Utc, Duration};
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use parking_lot::{RwLock, RwLockReadGuard};
+use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
+
+use super::banner::{AnswerKind, QuestionMeta};
+use super::events::{EventBus, StreamEvent};
+use super::secret_guard::{secret_eq, SecretGuard};
+use super::resume::RESUME_VALIDITY_MINUTES;
+use super::srs_api;
+use super::stream_policy;
+use crate::config::{LatencyMode, PriorityClass};
+use crate::domain::{ClientIp, SessionId};
+use crate::ids;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
 
 // ============================================================================
 // 枚举定义
@@ -37,6 +52,12 @@ pub enum ClientStatus {
     /// 暂离 - 暂时离开（可能回来）
     /// 过期时间：7200 秒（2 小时）
     Resting = 4,
+    /// 已结束 - 主播结束了直播，需重新连接
+    /// 过期时间：300 秒（5 分钟）
+    Ended = 5,
+    /// 排队中 - 开播前已答题通过，开播时自动转为已授权
+    /// 过期时间：7200 秒（2 小时）
+    Waiting = 6,
 }
 
 impl ClientStatus {
@@ -48,17 +69,21 @@ impl ClientStatus {
             Self::Nil => "nil",
             Self::Playing => "playing",
             Self::Resting => "resting",
+            Self::Ended => "ended",
+            Self::Waiting => "waiting",
         }
     }
 
     /// 从字符串解析状态
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn parse(s: &str) -> Option<Self> {
         match s {
             "pending" => Some(Self::Pending),
             "legal" => Some(Self::Legal),
             "nil" => Some(Self::Nil),
             "playing" => Some(Self::Playing),
             "resting" => Some(Self::Resting),
+            "ended" => Some(Self::Ended),
+            "waiting" => Some(Self::Waiting),
             _ => None,
         }
     }
@@ -68,6 +93,27 @@ impl ClientStatus {
         matches!(self, Self::Legal | Self::Playing | Self::Resting)
     }
 
+    /// 判断能否从当前状态转换到目标状态
+    ///
+    /// ### 允许的转换
+    /// | 目标状态 | 允许的来源状态 |
+    /// |---------|--------------|
+    /// | Pending | Pending（新记录总是从 Pending 开始） |
+    /// | Legal | 任意（答对、手动放行、主播登录、配对、续连等授权途径） |
+    /// | Nil | 除 Ended 外的任意状态（答错、主播密钥错误） |
+    /// | Playing / Resting | Legal、Playing、Resting（只有已授权的观众才能拉流） |
+    /// | Ended | 任意（直播结束） |
+    /// | Waiting | Pending、Waiting（开播前答题通过） |
+    pub fn can_transition_to(&self, next: Self) -> bool {
+        match next {
+            Self::Pending => *self == Self::Pending,
+            Self::Legal | Self::Ended => true,
+            Self::Nil => *self != Self::Ended,
+            Self::Playing | Self::Resting => self.is_authorized(),
+            Self::Waiting => matches!(self, Self::Pending | Self::Waiting),
+        }
+    }
+
     /// 获取状态的过期时间
     ///
     /// ### 返回值
@@ -80,6 +126,8 @@ impl ClientStatus {
             Self::Nil => Some(Duration::seconds(60)),
             Self::Playing => None, // 观看时永不过期
             Self::Resting => Some(Duration::seconds(7200)),
+            Self::Ended => Some(Duration::seconds(300)),
+            Self::Waiting => Some(Duration::seconds(7200)),
         }
     }
 }
@@ -120,33 +168,283 @@ impl StreamerStatus {
     }
 }
 
+/// 主播状态机事件
+///
+/// 主播状态只能通过 `StreamerState::apply` 处理这些事件来改变
+#[derive(Debug)]
+pub enum StreamerEvent {
+    /// 新推流开始（on_publish，当前未在推流）
+    Publish {
+        ip: ClientIp,
+        secret: String,
+        app: String,
+        stream: String,
+        /// 推流参数绑定的主播会话（调用方已按登录策略过滤）
+        push_session: Option<SessionId>,
+    },
+    /// 推流中断（on_unpublish）
+    Unpublish,
+    /// 推流端重连或重复的推流回调（on_publish，当前已在推流或暂停中）
+    Resume {
+        ip: ClientIp,
+        secret: String,
+        app: String,
+        stream: String,
+    },
+    /// 主播请求结束直播
+    EndRequested { session_id: SessionId },
+    /// 主播记录过期（定时清理任务）
+    Expired,
+}
+
+/// 主播状态机处理事件的结果
+#[derive(Debug)]
+pub enum StreamerTransition {
+    /// 事件已生效
+    Applied {
+        /// 需要发布到事件总线的领域事件
+        event: Option<StreamEvent>,
+        /// 新推流是否延续了上一场直播（仅 `Publish`）
+        continued: bool,
+    },
+    /// 事件在当前状态下不适用（如未推流时的 `Unpublish`），状态不变
+    Ignored,
+    /// 密钥或会话不匹配，状态不变
+    Rejected,
+}
+
+impl StreamerTransition {
+    /// 已生效、无附加信息的结果
+    fn applied(event: Option<StreamEvent>) -> Self {
+        Self::Applied { event, continued: false }
+    }
+}
+
+/// 主播手动设置的直播状态提示
+///
+/// 与 SRS 推流状态无关，用于向观众传达主播意图
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamOverlay {
+    /// 即将开始
+    StartingSoon,
+    /// 马上回来
+    Brb,
+    /// 即将结束
+    Ending,
+}
+
+impl StreamOverlay {
+    /// 将状态提示转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StartingSoon => "starting_soon",
+            Self::Brb => "brb",
+            Self::Ending => "ending",
+        }
+    }
+
+    /// 状态提示的显示文本
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::StartingSoon => "即将开始",
+            Self::Brb => "马上回来",
+            Self::Ending => "即将结束",
+        }
+    }
+
+    /// 从字符串解析状态提示
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "starting_soon" => Some(Self::StartingSoon),
+            "brb" => Some(Self::Brb),
+            "ending" => Some(Self::Ending),
+            _ => None,
+        }
+    }
+}
+
+/// 观众入场方式
+///
+/// 由推流参数 `entry` 按场设置，直播结束后恢复为答题
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryMode {
+    /// 回答题库中的题目
+    #[default]
+    Quiz,
+    /// 点击确认：领取确认令牌，等待片刻后提交即可入场（只作轻量的机器人过滤）
+    Confirm,
+}
+
+impl EntryMode {
+    /// 将入场方式转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Quiz => "quiz",
+            Self::Confirm => "confirm",
+        }
+    }
+
+    /// 从字符串解析入场方式
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "quiz" => Some(Self::Quiz),
+            "confirm" => Some(Self::Confirm),
+            _ => None,
+        }
+    }
+}
+
 // ============================================================================
 // 数据结构定义
 // ============================================================================
 
+/// 客户端声明的版本和能力
+///
+/// 前端在 `action=connect` 时通过 `client_version` 和 `capabilities` 参数声明，
+/// 服务端据此调整响应内容，未声明任何能力的客户端按旧版前端处理
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientCapabilities {
+    /// 前端版本号
+    pub version: Option<String>,
+    /// 能力标识集合
+    pub flags: BTreeSet<String>,
+}
+
+impl ClientCapabilities {
+    /// 支持 SSE 事件推送（`/events`）
+    pub const SSE: &'static str = "sse";
+    /// 支持 WebSocket 聊天
+    pub const WEBSOCKET: &'static str = "websocket";
+    /// 支持结构化的播放地址（`video` 字段）
+    pub const STRUCTURED_URI: &'static str = "uri_v2";
+    /// 支持结构化的题目（`question_info` 字段）
+    pub const STRUCTURED_QUESTION: &'static str = "question_v2";
+
+    /// 单个能力标识的最大长度
+    const MAX_FLAG_LEN: usize = 32;
+
+    /// 从请求参数解析
+    ///
+    /// ### 参数
+    /// - `version`: 前端版本号
+    /// - `capabilities`: 逗号分隔的能力标识，如 `sse,uri_v2`
+    pub fn parse(version: Option<&str>, capabilities: Option<&str>) -> Self {
+        Self {
+            version: version
+                .map(|v| v.trim().chars().take(Self::MAX_FLAG_LEN).collect::<String>())
+                .filter(|v| !v.is_empty()),
+            flags: capabilities
+                .unwrap_or_default()
+                .split(',')
+                .map(|c| c.trim().to_lowercase())
+                .filter(|c| !c.is_empty() && c.len() <= Self::MAX_FLAG_LEN)
+                .collect(),
+        }
+    }
+
+    /// 是否声明了指定能力
+    pub fn has(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+}
+
 /// 客户端记录
 ///
 /// 存储单个观众客户端的所有信息
-#[derive(Debug, Clone)]
+///
+/// `Debug` 输出中 IP 被遮蔽、答案被隐藏，见 `crate::redact`
+#[derive(Clone)]
 pub struct ClientRecord {
     /// 客户端 IP 地址
-    pub ip: String,
+    pub ip: ClientIp,
     /// 会话 ID
-    pub session_id: String,
+    pub session_id: SessionId,
     /// 分配的问题
     pub question: String,
     /// 正确答案
     pub answer: String,
-    /// 显示昵称（可选）
+    /// 题目元数据（类型、答案形式），供声明 `question_v2` 能力的前端选择输入控件
+    pub question_meta: Option<QuestionMeta>,
+    /// 最近一次被判定的答案（用于识别重复提交）
+    pub judged_answer: Option<String>,
+    /// 作答截止时间（仅用于展示）
+    pub question_deadline: Option<DateTime<Utc>>,
+    /// 题目发放时间（仅用于展示）
+    pub question_issued_at: Option<DateTime<Utc>>,
+    /// 题目发放时刻（单调时钟，用于计算作答时限和用时）
+    pub question_issued: Option<Instant>,
+    /// 已主动换题的次数（`action=newquestion`）
+    pub question_refreshes: u32,
+    /// 配对码 - 展示给观众，主播可凭此码手动放行
+    pub pairing_code: String,
+    /// 显示昵称（可选，与聊天室昵称同步）
     pub display_name: Option<String>,
+    /// 连接时是否出示了有效的回访观众令牌
+    pub alumni: bool,
     /// 是否为主播
     pub is_publisher: bool,
     /// 创建时间
     pub created_at: DateTime<Utc>,
     /// 当前状态
     pub status: ClientStatus,
-    /// 最后活动时间
+    /// 最后活动时间（仅用于展示）
     pub last_activity: DateTime<Utc>,
+    /// 最后活动时刻（单调时钟，用于判断过期）
+    pub last_seen: Instant,
+    /// 首次连接时记录的请求头（如 User-Agent），见 `LIVE_SERVER_CLIENT_HEADERS`
+    pub first_seen_headers: BTreeMap<String, String>,
+    /// 首次连接时声明的版本和能力
+    pub capabilities: ClientCapabilities,
+    /// 正在拉流的 SRS 连接：client_id -> on_play 到达时刻（同一观众可能有多个连接）
+    pub srs_clients: HashMap<String, Instant>,
+    /// 进入开播前排队的时刻（用于计算排队位置）
+    pub waiting_since: Option<Instant>,
+    /// 因观看人数已满进入等候室的时刻（用于计算等候位置）
+    pub queued_since: Option<Instant>,
+    /// 授权后是否已在状态查询中收到过欢迎语和房间规则
+    pub welcomed: bool,
+    /// 开始拉流次数（on_play 放行次数）
+    pub plays: u32,
+    /// 停止拉流次数（on_stop 次数）
+    pub stops: u32,
+    /// 当前拉流连接近 30 秒的发送码率之和（kbps，来自 SRS 统计）
+    pub send_kbps: u64,
+    /// 估算的累计下行流量（字节，按每轮轮询的码率与间隔累加）
+    pub bytes_sent: u64,
+}
+
+impl std::fmt::Debug for ClientRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientRecord")
+            .field("ip", &crate::redact::ip(&self.ip))
+            .field("session_id", &self.session_id)
+            .field("question", &self.question)
+            .field("answer", &crate::redact::text(&self.answer))
+            .field("question_meta", &self.question_meta)
+            .field("judged_answer", &self.judged_answer.as_deref().map(crate::redact::text))
+            .field("question_deadline", &self.question_deadline)
+            .field("question_issued_at", &self.question_issued_at)
+            .field("question_refreshes", &self.question_refreshes)
+            .field("pairing_code", &crate::redact::text(&self.pairing_code))
+            .field("display_name", &self.display_name)
+            .field("alumni", &self.alumni)
+            .field("is_publisher", &self.is_publisher)
+            .field("created_at", &self.created_at)
+            .field("status", &self.status)
+            .field("last_activity", &self.last_activity)
+            .field("first_seen_headers", &self.first_seen_headers)
+            .field("capabilities", &self.capabilities)
+            .field("srs_clients", &self.srs_clients.keys().collect::<Vec<_>>())
+            .field("waiting_since", &self.waiting_since)
+            .field("queued_since", &self.queued_since)
+            .field("welcomed", &self.welcomed)
+            .field("plays", &self.plays)
+            .field("stops", &self.stops)
+            .field("send_kbps", &self.send_kbps)
+            .field("bytes_sent", &self.bytes_sent)
+            .finish()
+    }
 }
 
 impl ClientRecord {
@@ -155,52 +453,185 @@ impl ClientRecord {
     /// ### 参数
     /// - `ip`: 客户端 IP 地址
     /// - `session_id`: 会话 ID
-    pub fn new(ip: String, session_id: String) -> Self {
+    pub fn new(ip: ClientIp, session_id: SessionId) -> Self {
         let now = Utc::now();
         Self {
             ip,
             session_id,
             question: String::new(),
             answer: String::new(),
+            question_meta: None,
+            judged_answer: None,
+            question_deadline: None,
+            question_issued_at: None,
+            question_issued: None,
+            question_refreshes: 0,
+            pairing_code: ids::short_code(PAIRING_CODE_LEN),
             display_name: None,
+            alumni: false,
             is_publisher: false,
             created_at: now,
             status: ClientStatus::Pending,
             last_activity: now,
+            last_seen: Instant::now(),
+            first_seen_headers: BTreeMap::new(),
+            capabilities: ClientCapabilities::default(),
+            srs_clients: HashMap::new(),
+            waiting_since: None,
+            queued_since: None,
+            welcomed: false,
+            plays: 0,
+            stops: 0,
+            send_kbps: 0,
+            bytes_sent: 0,
+        }
+    }
+
+    /// 按优先放行策略计算的排序等级（越小越优先，不属于任何类别时为类别数）
+    pub fn priority_rank(&self, order: &[PriorityClass]) -> usize {
+        order
+            .iter()
+            .position(|class| match class {
+                PriorityClass::Alumni => self.alumni,
+                PriorityClass::Nickname => self.display_name.is_some(),
+            })
+            .unwrap_or(order.len())
+    }
+
+    /// 刷新最后活动时间
+    pub fn touch(&mut self) {
+        self.last_activity = Utc::now();
+        self.last_seen = Instant::now();
+    }
+
+    /// 转换到目标状态并刷新最后活动时间
+    ///
+    /// ### 返回值
+    /// 转换不被允许时记录警告、保持原状态并返回 `false`（见 `ClientStatus::can_transition_to`）
+    pub fn transition(&mut self, next: ClientStatus) -> bool {
+        if !self.status.can_transition_to(next) {
+            tracing::warn!(
+                "拒绝非法的客户端状态转换 session_id={}: {} -> {}",
+                self.session_id,
+                self.status.as_str(),
+                next.as_str()
+            );
+            return false;
         }
+        self.status = next;
+        self.touch();
+        true
     }
 
     /// 判断客户端是否已过期
     ///
-    /// 根据当前状态和最后活动时间判断
+    /// 根据当前状态和最后活动时刻（单调时钟）判断，不受系统时钟调整影响
     pub fn is_expired(&self) -> bool {
-        if let Some(duration) = self.status.expiration_duration() {
-            Utc::now().signed_duration_since(self.last_activity) > duration
-        } else {
-            false
-        }
+        self.status
+            .expiration_duration()
+            .is_some_and(|duration| elapsed_beyond(self.last_seen, duration))
     }
 }
 
+/// 判断自某一时刻（单调时钟）起经过的时间是否超过给定时长
+fn elapsed_beyond(since: Instant, duration: Duration) -> bool {
+    since.elapsed() > duration.to_std().unwrap_or_default()
+}
+
+/// 跨设备配对码和主播一次性验证码的有效期
+const PAIRING_VALIDITY: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// 配对码长度
+const PAIRING_CODE_LEN: usize = 6;
+
+/// 单个 IP 同时未完成的跨设备配对请求上限
+const MAX_PAIR_REQUESTS_PER_IP: usize = 4;
+
+/// 全局同时未完成的跨设备配对请求上限
+const MAX_PAIR_REQUESTS: usize = 1024;
+
+/// 生成不重复配对码的最大尝试次数
+const PAIR_CODE_ATTEMPTS: usize = 16;
+
+/// 每个 IP 记住的近期发放题目数量上限（超出时丢弃最早的记录）
+const MAX_SERVED_QUESTIONS_PER_IP: usize = 32;
+
+/// 主播手动结束直播后，在此时间内拒绝以相同密钥向同一推流目标重新推流（编码器自动重连）
+pub const END_REFUSAL_WINDOW: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 暂停超时的直播在此时间内以相同密钥重新推流时，视为同一场直播继续
+const SESSION_CONTINUE_WINDOW: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// 等候室中超过此时长无活动（未查询状态）的观众不再占据等候位置
+const VIEWER_QUEUE_STALE: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// 主播记录
 ///
 /// 存储当前主播的状态信息
-#[derive(Debug, Clone)]
+///
+/// `Debug` 输出中 IP 被遮蔽，推流密钥始终显示为 `[REDACTED]`
+#[derive(Clone)]
 pub struct StreamerRecord {
     /// 主播 IP 地址
-    pub ip: Option<String>,
-    /// 推流密钥
-    pub secret: Option<String>,
+    pub ip: Option<ClientIp>,
+    /// 推流密钥（`Debug` 输出中显示为 `[REDACTED]`，释放时清零）
+    pub secret: Option<SecretString>,
     /// 主播的会话 ID
-    pub session_id: Option<String>,
+    pub session_id: Option<SessionId>,
+    /// 推流参数 `session_id` 绑定的主播会话（用该会话 ID 打开网页即获得主播权限）
+    pub push_session: Option<SessionId>,
+    /// 应用名称（如 "live"）
+    pub app: Option<String>,
+    /// 流名称
+    pub stream: Option<String>,
     /// 流 URI（格式：app=xxx&stream=xxx）
     pub stream_uri: Option<String>,
     /// 直播间名称
     pub stream_name: Option<String>,
+    /// 直播场次 ID（每次新推流时生成，用于区分同一天内的多场直播）
+    pub stream_session_id: Option<String>,
+    /// 本场直播开始推流的时间
+    pub live_since: Option<DateTime<Utc>>,
+    /// 主播手动设置的状态提示
+    pub overlay: Option<StreamOverlay>,
+    /// 正在推流的转码版本（流名称后缀）
+    pub variants: BTreeSet<String>,
+    /// 是否正在录制（DVR）
+    pub recording: bool,
+    /// 主播临时切换的播放延迟模式（`None` 表示使用配置的默认模式）
+    pub latency_mode: Option<LatencyMode>,
+    /// 推流端的 SRS client_id（来自 on_publish 回调，用于踢出推流端和排除观众统计）
+    pub client_id: Option<String>,
     /// 当前状态
     pub status: StreamerStatus,
-    /// 最后活动时间
+    /// 最后活动时间（仅用于展示）
     pub last_activity: DateTime<Utc>,
+    /// 最后活动时刻（单调时钟，用于判断过期）
+    pub last_seen: Instant,
+}
+
+impl std::fmt::Debug for StreamerRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamerRecord")
+            .field("ip", &self.ip.as_ref().map(crate::redact::ip))
+            .field("secret", &self.secret)
+            .field("session_id", &self.session_id)
+            .field("push_session", &self.push_session)
+            .field("app", &self.app)
+            .field("stream", &self.stream)
+            .field("stream_uri", &self.stream_uri)
+            .field("stream_name", &self.stream_name)
+            .field("stream_session_id", &self.stream_session_id)
+            .field("live_since", &self.live_since)
+            .field("overlay", &self.overlay)
+            .field("variants", &self.variants)
+            .field("recording", &self.recording)
+            .field("latency_mode", &self.latency_mode)
+            .field("client_id", &self.client_id)
+            .field("status", &self.status)
+            .field("last_activity", &self.last_activity)
+            .finish()
+    }
 }
 
 impl StreamerRecord {
@@ -211,20 +642,48 @@ impl StreamerRecord {
             ip: None,
             secret: None,
             session_id: None,
+            push_session: None,
+            app: None,
+            stream: None,
             stream_uri: None,
             stream_name: None,
+            stream_session_id: None,
+            live_since: None,
+            overlay: None,
+            variants: BTreeSet::new(),
+            recording: false,
+            latency_mode: None,
+            client_id: None,
             status: StreamerStatus::Standby,
             last_activity: now,
+            last_seen: Instant::now(),
         }
     }
 
-    /// 判断主播是否已过期
+    /// 刷新最后活动时间
+    pub fn touch(&mut self) {
+        self.last_activity = Utc::now();
+        self.last_seen = Instant::now();
+    }
+
+    /// 判断主播是否已过期（单调时钟）
     pub fn is_expired(&self) -> bool {
-        if let Some(duration) = self.status.expiration_duration() {
-            Utc::now().signed_duration_since(self.last_activity) > duration
-        } else {
-            false
-        }
+        self.status
+            .expiration_duration()
+            .is_some_and(|duration| elapsed_beyond(self.last_seen, duration))
+    }
+
+    /// 以常数时间比较推流密钥
+    pub fn secret_matches(&self, secret: &str) -> bool {
+        self.secret
+            .as_ref()
+            .is_some_and(|s| secret_eq(s.expose_secret(), secret))
+    }
+}
+
+impl Default for StreamerRecord {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -260,176 +719,287 @@ impl StreamerVerifier {
         match fs::read_to_string(&self.secret_path) {
             Ok(content) => {
                 // 按空白字符分割，获取所有密钥
-                let known_secrets: Vec<&str> = content
+                // 逐个常数时间比较，且不提前退出
+                content
                     .split_whitespace()
-                    .collect();
-                known_secrets.iter().any(|s| *s == secret)
+                    .fold(false, |found, known| secret_eq(known, secret) | found)
             }
             Err(_) => false,
         }
     }
+
+    /// 统计密钥文件中的密钥数量
+    ///
+    /// ### 返回值
+    /// - `Ok(n)`: 密钥文件可读，共 n 个密钥
+    /// - `Err(e)`: 读取密钥文件失败
+    pub fn key_count(&self) -> std::io::Result<usize> {
+        Ok(fs::read_to_string(&self.secret_path)?.split_whitespace().count())
+    }
 }
 
 // ============================================================================
 // SRS 数据库
 // ============================================================================
 
-/// SRS 数据库内部结构
+/// 直播状态快照
 ///
-/// 管理所有客户端和主播的状态
-pub struct SrsDatabaseInner {
-    /// 客户端映射：IP -> session_id -> ClientRecord
-    pub clients: HashMap<String, HashMap<String, ClientRecord>>,
-    /// 主播记录
-    pub streamer: StreamerRecord,
-    /// 密钥验证器
-    pub verifier: StreamerVerifier,
+/// 主播状态或公开模式变化时整体替换，状态查询、聊天鉴权等高频路径直接读取快照，无需获取主播锁
+#[derive(Debug, Clone)]
+pub struct StreamSnapshot {
+    /// 推流状态
+    pub status: StreamerStatus,
+    /// 直播间名称
+    pub name: Option<String>,
+    /// 流 URI
+    pub uri: Option<String>,
+    /// 当前推流的 (app, stream)
+    pub target: Option<(String, String)>,
+    /// 直播场次 ID
+    pub session_id: Option<String>,
+    /// 正在推流的转码版本：(后缀, 流 URI)
+    pub variants: Vec<(String, String)>,
+    /// 主播切换的播放延迟模式（`None` 为配置的默认模式）
+    pub latency_mode: Option<LatencyMode>,
+    /// 主播手动设置的状态提示
+    pub overlay: Option<StreamOverlay>,
+    /// 是否正在录制
+    pub recording: bool,
     /// 是否为公开模式（无需答题）
-    pub public_stream: bool,
+    pub public: bool,
+    /// 主播是否暂停接纳新观众
+    pub entries_closed: bool,
+    /// 主播设置的欢迎语和房间规则
+    pub notice: RoomNotice,
+    /// 推流参数绑定的主播会话
+    pub push_session: Option<SessionId>,
 }
 
-impl SrsDatabaseInner {
-    /// 创建新的 SRS 数据库
-    ///
-    /// ### 参数
-    /// - `secret_path`: 密钥文件路径
-    pub fn new(secret_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            clients: HashMap::new(),
-            streamer: StreamerRecord::new(),
-            verifier: StreamerVerifier::new(secret_path),
-            public_stream: false,
-        })
-    }
-
-    /// 重置数据库
-    ///
-    /// 清除所有客户端和主播数据
-    pub fn reset(&mut self) {
-        self.clients.clear();
-        self.streamer = StreamerRecord::new();
-        self.public_stream = false;
-    }
-
-    // ========================================================================
-    // 客户端操作
-    // ========================================================================
-
-    /// 检查客户端是否存在
-    pub fn has_client(&self, ip: &str, session_id: &str) -> bool {
-        self.clients
-            .get(ip)
-            .and_then(|m| m.get(session_id))
-            .is_some()
+impl StreamSnapshot {
+    /// 是否正在推流（含暂停中）
+    pub fn is_streaming(&self) -> bool {
+        self.status != StreamerStatus::Standby
     }
 
-    /// 检查客户端是否已授权（可以拉流）
-    pub fn has_authorized_client(&self, ip: &str, session_id: &str) -> bool {
-        self.clients
-            .get(ip)
-            .and_then(|m| m.get(session_id))
-            .map(|r| r.status.is_authorized())
-            .unwrap_or(false)
+    /// 是否正在活跃推流（非暂停状态）
+    pub fn is_actively_streaming(&self) -> bool {
+        self.status == StreamerStatus::Streaming
     }
 
-    /// 添加新客户端
-    pub fn add_client(&mut self, ip: String, session_id: String) {
-        self.clients
-            .entry(ip.clone())
-            .or_insert_with(HashMap::new)
-            .insert(session_id.clone(), ClientRecord::new(ip, session_id));
+    /// 是否处于推流暂停中
+    pub fn is_paused(&self) -> bool {
+        self.status == StreamerStatus::Pausing
     }
 
-    /// 获取客户端记录（只读）
-    pub fn get_client(&self, ip: &str, session_id: &str) -> Option<&ClientRecord> {
-        self.clients.get(ip)?.get(session_id)
+    /// 获取当前生效的播放延迟模式
+    ///
+    /// ### 参数
+    /// - `default`: 配置的默认模式（主播未切换时使用）
+    pub fn latency_mode(&self, default: LatencyMode) -> LatencyMode {
+        self.latency_mode.unwrap_or(default)
     }
+}
 
-    /// 获取客户端记录（可变）
-    pub fn get_client_mut(&mut self, ip: &str, session_id: &str) -> Option<&mut ClientRecord> {
-        self.clients.get_mut(ip)?.get_mut(session_id)
+impl Default for StreamSnapshot {
+    fn default() -> Self {
+        Self {
+            status: StreamerStatus::Standby,
+            name: None,
+            uri: None,
+            target: None,
+            session_id: None,
+            variants: Vec::new(),
+            latency_mode: None,
+            overlay: None,
+            recording: false,
+            public: false,
+            entries_closed: false,
+            notice: RoomNotice::default(),
+            push_session: None,
+        }
     }
+}
 
-    /// 移除客户端
-    pub fn remove_client(&mut self, ip: &str, session_id: &str) -> Option<ClientRecord> {
-        self.clients.get_mut(ip)?.remove(session_id)
-    }
+/// 直播间公告：欢迎语和房间规则
+///
+/// 由主播设置，跨场次保留（不随主播记录重置），新观众在聊天室连接和授权后的首次状态查询中收到
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomNotice {
+    /// 欢迎语
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub welcome: Option<String>,
+    /// 房间规则
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules: Option<String>,
+}
 
-    /// 获取客户端的问题和答案
-    pub fn get_client_qa(&self, ip: &str, session_id: &str) -> Option<(&str, &str)> {
-        self.get_client(ip, session_id)
-            .map(|r| (r.question.as_str(), r.answer.as_str()))
-    }
+impl RoomNotice {
+    /// 欢迎语和房间规则的长度上限（字符）
+    pub const MAX_LEN: usize = 2000;
 
-    /// 设置客户端的问题和答案
-    pub fn set_client_qa(&mut self, ip: &str, session_id: &str, q: String, a: String) {
-        if let Some(client) = self.get_client_mut(ip, session_id) {
-            client.question = q;
-            client.answer = a;
-        }
+    /// 是否未设置任何内容
+    pub fn is_empty(&self) -> bool {
+        self.welcome.is_none() && self.rules.is_none()
     }
+}
 
-    /// 获取客户端显示名称
-    pub fn get_client_display_name(&self, ip: &str, session_id: &str) -> Option<&str> {
-        self.get_client(ip, session_id)?.display_name.as_deref()
-    }
+/// 主播状态
+///
+/// 当前推流及主播会话的状态，主播操作（改名、切换状态提示等）只需获取这一把锁
+pub struct StreamerState {
+    /// 主播记录
+    pub streamer: StreamerRecord,
+    /// 待验证的主播一次性验证码：(session_id, 验证码, 过期时间)
+    pub publisher_otp: Option<(SessionId, String, Instant)>,
+    /// 最近一场因暂停超时而结束的直播及其结束时刻，用于推流端断线过久后重新推流时延续场次
+    pub recent_pause: Option<(StreamerRecord, Instant)>,
+    /// 最近一场由主播手动结束的直播及其结束时刻，用于拒绝编码器随后的自动重连
+    pub recent_end: Option<(StreamerRecord, Instant)>,
+    /// 欢迎语和房间规则（跨场次保留，`reset` 不清除）
+    notice: RoomNotice,
+    /// 直播状态快照（与 `SrsDatabase` 共享）
+    snapshot: Arc<ArcSwap<StreamSnapshot>>,
+}
 
-    /// 设置客户端显示名称
-    pub fn set_client_display_name(&mut self, ip: &str, session_id: &str, name: String) {
-        if let Some(client) = self.get_client_mut(ip, session_id) {
-            client.display_name = Some(name);
+impl StreamerState {
+    /// 创建空的主播状态
+    ///
+    /// ### 参数
+    /// - `snapshot`: 状态变化时发布到的直播状态快照
+    pub fn new(snapshot: Arc<ArcSwap<StreamSnapshot>>) -> Self {
+        Self {
+            streamer: StreamerRecord::new(),
+            publisher_otp: None,
+            recent_pause: None,
+            recent_end: None,
+            notice: RoomNotice::default(),
+            snapshot,
         }
     }
 
-    /// 获取客户端状态
-    pub fn get_client_status(&self, ip: &str, session_id: &str) -> Option<ClientStatus> {
-        self.get_client(ip, session_id).map(|r| r.status)
+    /// 按当前主播状态重新发布快照（公开模式标记沿用快照中的值）
+    fn publish_snapshot(&self) {
+        self.snapshot.rcu(|current| StreamSnapshot {
+            status: self.streamer.status,
+            name: self.streamer.stream_name.clone(),
+            uri: self.streamer.stream_uri.clone(),
+            target: self
+                .get_stream_target()
+                .map(|(app, stream)| (app.to_string(), stream.to_string())),
+            session_id: self.streamer.stream_session_id.clone(),
+            variants: self.get_stream_variants(),
+            latency_mode: self.streamer.latency_mode,
+            overlay: self.streamer.overlay,
+            recording: self.streamer.recording,
+            public: current.public,
+            entries_closed: current.entries_closed,
+            notice: self.notice.clone(),
+            push_session: self.streamer.push_session.clone(),
+        });
     }
 
-    /// 获取客户端状态（通过 session_id，忽略 IP）
-    /// 用于 SRS 回调，因为回调中的 IP 是 Docker 内部 IP
-    pub fn get_client_status_any_ip(&self, session_id: &str) -> Option<(String, ClientStatus)> {
-        for (ip, clients) in self.clients.iter() {
-            if let Some(client) = clients.get(session_id) {
-                return Some((ip.clone(), client.status));
+    /// 处理主播状态机事件
+    ///
+    /// 主播状态的所有变化都经由此方法：
+    /// - `Publish`: Standby → Streaming，开播前排队的观众转为已授权，发布 `StreamLive`
+    /// - `Unpublish`: Streaming → Pausing，发布 `StreamPaused`
+    /// - `Resume`: Streaming / Pausing → Streaming，由暂停恢复时发布 `StreamResumed`
+    /// - `EndRequested`: Streaming / Pausing → Standby，所有观众转为已结束，发布 `StreamEnded`；
+    ///   未推流时（包括预登录主播请求结束）不生效
+    /// - `Expired`: 记录过期时 → Standby，清空客户端，直播中过期时发布 `StreamEnded`
+    ///
+    /// ### 参数
+    /// - `clients`: 客户端注册表（锁顺序：streamer → clients）
+    /// - `event`: 状态机事件
+    ///
+    /// ### 返回值
+    /// 处理结果，需要发布的领域事件由调用方在完成其余处理后发布到事件总线
+    pub fn apply(&mut self, clients: &mut ClientRegistry, event: StreamerEvent) -> StreamerTransition {
+        let from = self.streamer.status;
+        let transition = match event {
+            StreamerEvent::Publish { ip, secret, app, stream, push_session } => {
+                if from != StreamerStatus::Standby {
+                    return StreamerTransition::Ignored;
+                }
+                let continued = self.register_streamer(clients, ip, secret, app, stream, push_session);
+                let activated = clients.activate_waiting_clients();
+                StreamerTransition::Applied {
+                    event: Some(StreamEvent::StreamLive { activated }),
+                    continued,
+                }
+            }
+            StreamerEvent::Unpublish => {
+                if !self.pause_streaming() {
+                    return StreamerTransition::Ignored;
+                }
+                StreamerTransition::applied(Some(StreamEvent::StreamPaused))
+            }
+            StreamerEvent::Resume { ip, secret, app, stream } => {
+                if from == StreamerStatus::Standby {
+                    return StreamerTransition::Ignored;
+                }
+                if !self.resume_streaming(ip, &secret, app, stream) {
+                    return StreamerTransition::Rejected;
+                }
+                StreamerTransition::applied((from == StreamerStatus::Pausing).then_some(StreamEvent::StreamResumed))
+            }
+            StreamerEvent::EndRequested { session_id } => {
+                // 预登录主播在开播前请求结束：没有可结束的直播，排队的观众和聊天室都不受影响
+                if from == StreamerStatus::Standby {
+                    return StreamerTransition::Ignored;
+                }
+                let ended = self.streamer.clone();
+                if !self.end_streaming(&session_id) {
+                    return StreamerTransition::Rejected;
+                }
+                self.recent_end = Some((ended, Instant::now()));
+                clients.end_all_clients();
+                StreamerTransition::applied(Some(StreamEvent::StreamEnded))
+            }
+            StreamerEvent::Expired => {
+                if !self.streamer.is_expired() {
+                    return StreamerTransition::Ignored;
+                }
+                // 暂停超时结束的直播短时间内可能以相同密钥重新推流，保留记录以便延续场次
+                let paused = (from == StreamerStatus::Pausing).then(|| (self.streamer.clone(), Instant::now()));
+                self.reset();
+                self.recent_pause = paused;
+                clients.reset();
+                StreamerTransition::applied((from != StreamerStatus::Standby).then_some(StreamEvent::StreamEnded))
             }
+        };
+        if self.streamer.status != from {
+            tracing::debug!("主播状态: {} -> {}", from.as_str(), self.streamer.status.as_str());
         }
-        None
+        transition
     }
 
-    /// 更新客户端活动和状态
+    /// 接收主实例复制来的直播场次（热备复制，备用实例调用）
     ///
-    /// ### 返回值
-    /// - `true`: 更新成功
-    /// - `false`: 客户端不存在
-    pub fn update_client_activity(&mut self, ip: &str, session_id: &str, status: ClientStatus) -> bool {
-        if let Some(client) = self.get_client_mut(ip, session_id) {
-            client.status = status;
-            client.last_activity = Utc::now();
-            true
-        } else {
-            false
+    /// 作为刚暂停的直播保存，推流端切换到本实例后以相同密钥推流即延续同一场直播；
+    /// 本实例正在推流时忽略
+    pub fn adopt_replicated(&mut self, record: Option<StreamerRecord>) {
+        if !self.is_streaming() {
+            self.recent_pause = record.map(|record| (record, Instant::now()));
         }
     }
 
-    /// 设置客户端为主播
-    pub fn set_client_publisher(&mut self, ip: &str, session_id: &str) {
-        if let Some(client) = self.get_client_mut(ip, session_id) {
-            client.is_publisher = true;
-        }
+    /// 接管升级前的进程交来的主播记录（零停机升级，新进程启动时调用）
+    ///
+    /// 与热备复制不同，升级期间推流端的连接并未中断，记录保持交接时的状态（推流中或暂停）
+    pub fn adopt_handoff(&mut self, record: StreamerRecord) {
+        self.streamer = record;
+        self.recent_pause = None;
+        self.publish_snapshot();
     }
 
-    /// 检查客户端是否为主播
-    pub fn client_is_publisher(&self, ip: &str, session_id: &str) -> bool {
-        self.get_client(ip, session_id)
-            .map(|r| r.is_publisher)
-            .unwrap_or(false)
+    /// 重置主播状态（`recent_pause` 由调用方处理）
+    fn reset(&mut self) {
+        self.streamer = StreamerRecord::new();
+        self.publisher_otp = None;
+        self.publish_snapshot();
     }
 
-    // ========================================================================
-    // 主播操作
-    // ========================================================================
-
     /// 检查是否正在推流
     pub fn is_streaming(&self) -> bool {
         self.streamer.status != StreamerStatus::Standby
@@ -445,55 +1015,306 @@ impl SrsDatabaseInner {
         self.streamer.stream_uri.as_deref()
     }
 
-    /// 获取直播间名称
-    pub fn get_stream_name(&self) -> Option<&str> {
-        self.streamer.stream_name.as_deref()
+    /// 获取当前推流的 (app, stream)
+    pub fn get_stream_target(&self) -> Option<(&str, &str)> {
+        Some((self.streamer.app.as_deref()?, self.streamer.stream.as_deref()?))
     }
 
-    /// 设置直播间名称
-    pub fn set_stream_name(&mut self, name: String) {
-        self.streamer.stream_name = Some(name);
+    /// 判断流是否为当前直播的转码版本
+    ///
+    /// ### 参数
+    /// - `suffixes`: 配置的转码流名称后缀
+    ///
+    /// ### 返回值
+    /// 是当前推流（同一 app）加上某个后缀时返回该后缀
+    pub fn variant_of_current<'a>(&self, app: &str, stream: &str, suffixes: &'a [String]) -> Option<&'a str> {
+        let (current_app, current_stream) = self.get_stream_target()?;
+        if app != current_app {
+            return None;
+        }
+        suffixes
+            .iter()
+            .find(|suffix| stream.strip_suffix(suffix.as_str()) == Some(current_stream))
+            .map(String::as_str)
     }
 
-    /// 验证主播密钥
-    pub fn verify_streamer(&self, secret: &str) -> bool {
-        self.verifier.authorize(secret)
+    /// 标记转码版本开始或停止推流
+    pub fn set_variant_live(&mut self, suffix: &str, live: bool) {
+        if live {
+            self.streamer.variants.insert(suffix.to_string());
+        } else {
+            self.streamer.variants.remove(suffix);
+        }
+        self.publish_snapshot();
+    }
+
+    /// 获取正在推流的转码版本
+    ///
+    /// ### 返回值
+    /// `(后缀, 流 URI)` 列表，流 URI 格式与 `get_stream_uri` 相同
+    pub fn get_stream_variants(&self) -> Vec<(String, String)> {
+        let Some((app, stream)) = self.get_stream_target() else {
+            return Vec::new();
+        };
+        self.streamer
+            .variants
+            .iter()
+            .map(|suffix| (suffix.clone(), stream_policy::stream_query(app, &format!("{}{}", stream, suffix))))
+            .collect()
+    }
+
+    /// 当前直播是否正在录制
+    pub fn is_recording(&self) -> bool {
+        self.streamer.recording
+    }
+
+    /// 记录当前直播的录制状态（SRS 已确认开关后调用）
+    pub fn set_recording(&mut self, recording: bool) {
+        self.streamer.recording = recording;
+        self.streamer.touch();
+        self.publish_snapshot();
+    }
+
+    /// 获取当前生效的播放延迟模式
+    ///
+    /// ### 参数
+    /// - `default`: 配置的默认模式（主播未切换时使用）
+    pub fn latency_mode(&self, default: LatencyMode) -> LatencyMode {
+        self.streamer.latency_mode.unwrap_or(default)
+    }
+
+    /// 切换本场直播的播放延迟模式（`None` 恢复为配置的默认模式）
+    pub fn set_latency_mode(&mut self, mode: Option<LatencyMode>) {
+        self.streamer.latency_mode = mode;
+        self.streamer.touch();
+        self.publish_snapshot();
+    }
+
+    /// 本场直播开始推流的时间（未推流时为 `None`）
+    pub fn live_since(&self) -> Option<DateTime<Utc>> {
+        self.streamer.live_since
+    }
+
+    /// 获取当前直播场次 ID（未推流时为 `None`）
+    pub fn get_stream_session_id(&self) -> Option<&str> {
+        self.streamer.stream_session_id.as_deref()
+    }
+
+    /// 获取直播间名称
+    pub fn get_stream_name(&self) -> Option<&str> {
+        self.streamer.stream_name.as_deref()
+    }
+
+    /// 设置直播间名称
+    pub fn set_stream_name(&mut self, name: String) {
+        self.streamer.stream_name = Some(name);
+        self.publish_snapshot();
+    }
+
+    /// 获取欢迎语和房间规则
+    pub fn get_notice(&self) -> &RoomNotice {
+        &self.notice
+    }
+
+    /// 设置欢迎语和房间规则
+    pub fn set_notice(&mut self, notice: RoomNotice) {
+        self.notice = notice;
+        self.publish_snapshot();
+    }
+
+    /// 获取主播手动设置的状态提示
+    pub fn get_overlay(&self) -> Option<StreamOverlay> {
+        self.streamer.overlay
+    }
+
+    /// 设置或清除状态提示
+    pub fn set_overlay(&mut self, overlay: Option<StreamOverlay>) {
+        self.streamer.overlay = overlay;
+        self.streamer.touch();
+        self.publish_snapshot();
     }
 
     /// 注册主播（新推流开始）
-    pub fn register_streamer(
+    ///
+    /// 如果已有预登录的主播会话（publisher-elect）且其密钥与本次推流密钥一致，
+    /// 则该会话自动绑定为当前主播；否则撤销预登录会话的主播权限。
+    /// 推流参数指定了主播会话时，该会话取代预登录会话成为主播，已连接时立即获得主播权限，
+    /// 之后以该会话 ID 连接也直接获得主播权限
+    ///
+    /// 通常每次注册都会生成新的直播场次 ID；若同一推流目标的上一场直播因暂停超时结束不久
+    /// （`SESSION_CONTINUE_WINDOW` 内）且密钥一致，则视为同一场直播继续，沿用原场次 ID、
+    /// 开播时间和直播名称
+    ///
+    /// ### 参数
+    /// - `clients`: 客户端注册表（用于撤销预登录会话的主播标记）
+    /// - `push_session`: 推流参数绑定的主播会话（调用方已按登录策略过滤）
+    ///
+    /// ### 返回值
+    /// 是否延续了上一场直播
+    fn register_streamer(
         &mut self,
-        ip: String,
+        clients: &mut ClientRegistry,
+        ip: ClientIp,
         secret: String,
         app: String,
         stream: String,
-    ) {
+        push_session: Option<SessionId>,
+    ) -> bool {
+        let previous = self.recent_pause.take().filter(|(record, ended_at)| {
+            ended_at.elapsed() <= SESSION_CONTINUE_WINDOW
+                && record.app.as_deref() == Some(app.as_str())
+                && record.stream.as_deref() == Some(stream.as_str())
+                && record.secret_matches(&secret)
+        });
+        if !self.streamer.secret_matches(&secret) {
+            if let Some(elect) = self.streamer.session_id.take() {
+                tracing::debug!("推流密钥与预登录主播不一致，撤销预登录会话 session_id={}", elect);
+                clients.revoke_client_publisher(&elect);
+            }
+        }
+        if let Some(session) = push_session {
+            if let Some(elect) = self.streamer.session_id.replace(session.clone()).filter(|e| *e != session) {
+                tracing::debug!("推流参数指定了主播会话，撤销预登录会话 session_id={}", elect);
+                clients.revoke_client_publisher(&elect);
+            }
+            clients.bind_publisher(&session);
+            self.streamer.push_session = Some(session);
+        }
         self.streamer.ip = Some(ip);
-        self.streamer.secret = Some(secret.clone());
-        self.streamer.stream_uri = Some(format!("app={}&stream={}", app, stream));
+        self.streamer.secret = Some(SecretString::from(secret));
+        self.streamer.stream_uri = Some(stream_policy::stream_query(&app, &stream));
+        self.streamer.app = Some(app);
+        self.streamer.stream = Some(stream);
+        self.streamer.variants.clear();
+        self.streamer.recording = false;
+        self.streamer.latency_mode = None;
         self.streamer.status = StreamerStatus::Streaming;
-        self.streamer.last_activity = Utc::now();
+        self.streamer.touch();
+        let continued = match previous {
+            Some((record, _)) => {
+                self.streamer.stream_session_id = record.stream_session_id;
+                self.streamer.live_since = record.live_since;
+                if self.streamer.stream_name.is_none() {
+                    self.streamer.stream_name = record.stream_name;
+                }
+                true
+            }
+            None => {
+                self.streamer.stream_session_id = Some(ids::ulid());
+                self.streamer.live_since = Some(Utc::now());
+                false
+            }
+        };
+        self.publish_snapshot();
+        continued
     }
 
     /// 连接主播（通过 API 回答问题）
     ///
+    /// ### 行为说明
+    /// - 正在推流时：密钥必须与当前推流密钥一致
+    /// - 未推流（Standby）时：密钥通过密钥文件验证即可，会话成为预登录主播（publisher-elect），
+    ///   待 on_publish 以相同密钥到达时自动绑定
+    ///
+    /// ### 参数
+    /// - `clients`: 客户端注册表（Standby 时由其他会话重新登录，撤销原预登录会话的主播标记）
+    /// - `verifier`: 密钥验证器（未推流时使用）
+    ///
     /// ### 返回值
     /// - `true`: 密钥匹配，连接成功
     /// - `false`: 密钥不匹配
-    pub fn connect_streamer(&mut self, session_id: String, secret: &str) -> bool {
-        if self.streamer.secret.as_deref() == Some(secret) {
-            self.streamer.session_id = Some(session_id);
+    pub fn connect_streamer(
+        &mut self,
+        clients: &mut ClientRegistry,
+        verifier: &StreamerVerifier,
+        session_id: SessionId,
+        secret: &str,
+    ) -> bool {
+        if !self.check_streamer_secret(verifier, secret) {
+            return false;
+        }
+        let previous = self.streamer.session_id.replace(session_id.clone());
+        if !self.is_streaming() {
+            if let Some(previous) = previous.filter(|p| *p != session_id) {
+                tracing::debug!("其他会话重新预登录，撤销原预登录会话 session_id={}", previous);
+                clients.revoke_client_publisher(&previous);
+            }
+            self.streamer.secret = Some(SecretString::from(secret));
+            self.streamer.touch();
+        }
+        true
+    }
+
+    /// 检查推流是否为刚被主播手动结束的直播的自动重连
+    ///
+    /// 踢出推流端后编码器通常会自动重连，在 `END_REFUSAL_WINDOW` 内以相同密钥向同一推流目标
+    /// 重新推流时应拒绝，避免刚结束的直播又以新场次开始
+    pub fn refuses_republish(&self, secret: &str, app: &str, stream: &str) -> bool {
+        self.recent_end.as_ref().is_some_and(|(record, ended_at)| {
+            ended_at.elapsed() <= END_REFUSAL_WINDOW
+                && record.app.as_deref() == Some(app)
+                && record.stream.as_deref() == Some(stream)
+                && record.secret_matches(secret)
+        })
+    }
+
+    /// 检查密钥能否用于主播登录（不修改状态）
+    ///
+    /// 正在推流时需与当前推流密钥一致，未推流时通过密钥文件验证即可
+    pub fn check_streamer_secret(&self, verifier: &StreamerVerifier, secret: &str) -> bool {
+        if self.streamer.secret_matches(secret) {
             true
         } else {
-            false
+            !self.is_streaming() && verifier.authorize(secret)
         }
     }
 
+    /// 获取当前主播会话 ID
+    pub fn publisher_session(&self) -> Option<&SessionId> {
+        self.streamer.session_id.as_ref()
+    }
+
+    /// 获取当前推流端 IP
+    pub fn streamer_ip(&self) -> Option<&ClientIp> {
+        self.streamer.ip.as_ref()
+    }
+
+    /// 为指定会话签发主播一次性验证码（5 分钟内有效）
+    pub fn issue_publisher_otp(&mut self, session_id: &SessionId) -> String {
+        let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+        let expires_at = Instant::now() + PAIRING_VALIDITY;
+        self.publisher_otp = Some((session_id.clone(), code.clone(), expires_at));
+        code
+    }
+
+    /// 校验主播一次性验证码
+    ///
+    /// 无论成功与否验证码都会作废，输错后需重新申请，避免在有效期内穷举
+    pub fn verify_publisher_otp(&mut self, session_id: &SessionId, code: &str) -> bool {
+        matches!(
+            self.publisher_otp.take(),
+            Some((sid, c, expires_at)) if sid == *session_id && secret_eq(&c, code) && Instant::now() <= expires_at
+        )
+    }
+
+    /// 检查当前主播会话是否为预登录状态（已验证密钥但尚未推流）
+    pub fn is_publisher_elect(&self) -> bool {
+        !self.is_streaming() && self.streamer.session_id.is_some()
+    }
+
     /// 暂停推流（on_unpublish 回调）
-    pub fn pause_streaming(&mut self) {
+    ///
+    /// ### 返回值
+    /// - `true`: 由推流中转为暂停
+    /// - `false`: 当前并未在推流，状态不变
+    fn pause_streaming(&mut self) -> bool {
         if self.streamer.status == StreamerStatus::Streaming {
             self.streamer.status = StreamerStatus::Pausing;
-            self.streamer.last_activity = Utc::now();
+            self.streamer.touch();
+            self.publish_snapshot();
+            true
+        } else {
+            false
         }
     }
 
@@ -502,18 +1323,21 @@ impl SrsDatabaseInner {
     /// ### 返回值
     /// - `true`: 密钥匹配，恢复成功
     /// - `false`: 密钥不匹配
-    pub fn resume_streaming(
+    fn resume_streaming(
         &mut self,
-        ip: String,
+        ip: ClientIp,
         secret: &str,
         app: String,
         stream: String,
     ) -> bool {
-        if self.streamer.secret.as_deref() == Some(secret) {
+        if self.streamer.secret_matches(secret) {
             self.streamer.ip = Some(ip);
-            self.streamer.stream_uri = Some(format!("app={}&stream={}", app, stream));
+            self.streamer.stream_uri = Some(stream_policy::stream_query(&app, &stream));
+            self.streamer.app = Some(app);
+            self.streamer.stream = Some(stream);
             self.streamer.status = StreamerStatus::Streaming;
-            self.streamer.last_activity = Utc::now();
+            self.streamer.touch();
+            self.publish_snapshot();
             true
         } else {
             false
@@ -523,91 +1347,1268 @@ impl SrsDatabaseInner {
     /// 结束推流
     ///
     /// ### 参数
-    /// - `session_id`: 主播的会话 ID（可选，用于验证）
+    /// - `session_id`: 主播的会话 ID（用于验证）
     ///
     /// ### 返回值
     /// - `true`: 结束成功
     /// - `false`: session_id 不匹配
-    pub fn end_streaming(&mut self, session_id: Option<&str>) -> bool {
-        if session_id.is_some() && self.streamer.session_id.as_deref() == session_id {
+    fn end_streaming(&mut self, session_id: &SessionId) -> bool {
+        if self.streamer.session_id.as_ref() == Some(session_id) {
             self.streamer = StreamerRecord::new();
+            self.publish_snapshot();
             true
         } else {
             false
         }
     }
 
-    /// 设置公开模式
-    pub fn set_public(&mut self, public: bool) {
-        self.public_stream = public;
+    /// 记录推流端的 SRS client_id
+    pub fn set_publisher_client_id(&mut self, client_id: Option<String>) {
+        self.streamer.client_id = client_id;
     }
 
-    /// 检查是否为公开模式
-    pub fn is_public(&self) -> bool {
-        self.public_stream
+    /// 推流端的 SRS client_id
+    pub fn publisher_client_id(&self) -> Option<&str> {
+        self.streamer.client_id.as_deref()
     }
 }
 
-// ============================================================================
-// SRS 数据库包装器
-// ============================================================================
+/// 答题提交的判定结果
+///
+/// 由 `ClientRegistry::validate_and_apply_answer` 在同一把写锁内完成检查和状态转换后返回
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnswerOutcome {
+    /// 客户端不存在
+    UnknownClient,
+    /// 重复提交已通过的答案，仍在开播前排队
+    RepeatWaiting { position: Option<usize> },
+    /// 重复提交已通过的答案，已获得观看许可
+    RepeatAdmitted,
+    /// 封禁期内重复提交上次答错的答案
+    RepeatRejected { ban_remaining: Option<u64> },
+    /// 已经通过答题，不再接受新的答案
+    AlreadyAnswered,
+    /// 当前状态不能作答（未发放题目或已被封禁）
+    NotPending,
+    /// 题目被推迟发放，尚无题可答
+    AwaitingQuestion,
+    /// 超过作答时限，需要发放新题目
+    QuestionExpired,
+    /// 点击确认入场时过早提交（不计为答错）
+    TooEarly { retry_after: u64 },
+    /// 答对，已获得观看许可（`elapsed` 为答题用时，秒）
+    Admitted { elapsed: f64 },
+    /// 答对但尚未开播，进入开播前排队
+    Queued { position: Option<usize>, elapsed: f64 },
+    /// 答错，进入封禁
+    Rejected { ban_remaining: Option<u64> },
+}
 
-/// SRS 数据库包装器
+/// 客户端注册表
 ///
-/// 提供后台任务支持
-#[derive(Clone)]
-pub struct SrsDatabase {
-    /// 内部数据库
-    pub inner: Arc<RwLock<SrsDatabaseInner>>,
+/// 所有观众的鉴权状态，观众的连接、答题和拉流校验只需获取这一把锁
+pub struct ClientRegistry {
+    /// 客户端映射：IP -> session_id -> ClientRecord
+    pub clients: HashMap<ClientIp, HashMap<SessionId, ClientRecord>>,
+    /// 会话索引：session_id -> IP
+    ///
+    /// 与 `clients` 同步维护，用于 SRS 回调等只携带 session_id 的场景下 O(1) 查找客户端
+    pub session_index: HashMap<SessionId, ClientIp>,
+    /// 曾经通过验证（Legal）的会话 ID 集合，跨直播保留，用于离线大厅鉴权
+    pub legal_history: HashSet<SessionId>,
+    /// 单道题目的作答时限
+    pub question_time_limit: Duration,
+    /// 近期发放题目记录：IP -> [(题目, 发放时间)]，跨直播保留
+    pub served_questions: HashMap<ClientIp, Vec<(String, Instant)>>,
+    /// 近期发放题目的记忆时长
+    pub question_memory: Duration,
+    /// 跨设备配对请求：配对码 -> (IP, session_id, 过期时间)
+    pub pair_requests: HashMap<String, (ClientIp, SessionId, Instant)>,
+    /// 联合主持的会话 ID（由主播指定，可使用部分管理功能，不能结束直播或修改密钥）
+    pub co_host: Option<SessionId>,
+    /// 近期因过期被移除的已授权会话：session_id -> 移除时刻（保留时长与续连令牌有效期一致）
+    lapsed: HashMap<SessionId, Instant>,
+    /// 被管理员批量移除的会话：session_id -> 移除时刻（续连令牌有效期内不得凭令牌恢复）
+    revoked: HashMap<SessionId, Instant>,
 }
 
-impl SrsDatabase {
-    /// 创建新的 SRS 数据库
-    pub fn new(secret_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            inner: Arc::new(RwLock::new(SrsDatabaseInner::new(secret_path)?)),
-        })
+impl ClientRegistry {
+    /// 创建空的客户端注册表
+    ///
+    /// ### 参数
+    /// - `question_time_limit`: 单道题目的作答时限
+    /// - `question_memory`: 近期发放题目的记忆时长
+    pub fn new(question_time_limit: Duration, question_memory: Duration) -> Self {
+        Self {
+            clients: HashMap::new(),
+            session_index: HashMap::new(),
+            legal_history: HashSet::new(),
+            question_time_limit,
+            served_questions: HashMap::new(),
+            question_memory,
+            pair_requests: HashMap::new(),
+            co_host: None,
+            lapsed: HashMap::new(),
+            revoked: HashMap::new(),
+        }
     }
 
-    /// 清理过期记录（定期调用）
-    pub fn tick(&self) {
-        let mut db = self.inner.write();
+    /// 清除本场直播的客户端数据（`legal_history` 和 `served_questions` 跨直播保留）
+    pub fn reset(&mut self) {
+        self.clients.clear();
+        self.session_index.clear();
+        self.pair_requests.clear();
+        self.co_host = None;
+        self.lapsed.clear();
+        self.revoked.clear();
+    }
 
-        // 先检查主播是否过期
-        if db.streamer.is_expired() {
-            tracing::debug!("srs_db.tick(): 主播已过期，清除所有数据");
-            db.reset();
-            return;
+    /// 检查客户端是否存在
+    pub fn has_client(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.clients
+            .get(ip)
+            .and_then(|m| m.get(session_id))
+            .is_some()
+    }
+
+    /// 检查客户端是否已授权（可以拉流）
+    pub fn has_authorized_client(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.clients
+            .get(ip)
+            .and_then(|m| m.get(session_id))
+            .map(|r| r.status.is_authorized())
+            .unwrap_or(false)
+    }
+
+    /// 添加新客户端
+    ///
+    /// 同一 session_id 已在其他 IP 下登记时，旧 IP 下的记录会被移除，
+    /// 保证每个 session_id 只对应一条客户端记录
+    pub fn add_client(&mut self, ip: ClientIp, session_id: SessionId) {
+        if let Some(previous_ip) = self.session_index.get(&session_id).filter(|p| **p != ip).cloned() {
+            self.remove_client(&previous_ip, &session_id);
         }
+        self.session_index.insert(session_id.clone(), ip.clone());
+        self.clients
+            .entry(ip.clone())
+            .or_default()
+            .insert(session_id.clone(), ClientRecord::new(ip, session_id));
+    }
 
-        // 清理过期的客户端
-        let mut clients_to_remove = Vec::new();
-        for (ip, clients) in db.clients.iter_mut() {
-            let mut session_ids_to_remove = Vec::new();
-            for (session_id, client) in clients.iter() {
-                if client.is_expired() {
-                    tracing::debug!(
-                        "srs_db.tick(): 移除过期客户端: (ip={}, session_id={})",
-                        ip,
-                        session_id
-                    );
-                    session_ids_to_remove.push(session_id.clone());
-                }
+    /// 记录客户端首次连接时的请求头（已记录过的客户端不覆盖）
+    pub fn set_client_headers(&mut self, ip: &ClientIp, session_id: &SessionId, headers: BTreeMap<String, String>) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            if client.first_seen_headers.is_empty() {
+                client.first_seen_headers = headers;
+            }
+        }
+    }
+
+    /// 记录客户端声明的版本和能力
+    pub fn set_client_capabilities(&mut self, ip: &ClientIp, session_id: &SessionId, capabilities: ClientCapabilities) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.capabilities = capabilities;
+        }
+    }
+
+    /// 获取客户端记录（只读）
+    pub fn get_client(&self, ip: &ClientIp, session_id: &SessionId) -> Option<&ClientRecord> {
+        self.clients.get(ip)?.get(session_id)
+    }
+
+    /// 获取客户端记录（可变）
+    pub fn get_client_mut(&mut self, ip: &ClientIp, session_id: &SessionId) -> Option<&mut ClientRecord> {
+        self.clients.get_mut(ip)?.get_mut(session_id)
+    }
+
+    /// 移除客户端
+    pub fn remove_client(&mut self, ip: &ClientIp, session_id: &SessionId) -> Option<ClientRecord> {
+        let clients = self.clients.get_mut(ip)?;
+        let record = clients.remove(session_id)?;
+        if clients.is_empty() {
+            self.clients.remove(ip);
+        }
+        // 仅当索引仍指向该 IP 时才移除（同一 session_id 可能已在新 IP 下重新注册）
+        if self.session_index.get(session_id) == Some(ip) {
+            self.session_index.remove(session_id);
+        }
+        Some(record)
+    }
+
+    /// 通过 session_id 查找客户端所在的 IP
+    pub fn find_client_ip(&self, session_id: &SessionId) -> Option<&ClientIp> {
+        self.session_index.get(session_id)
+    }
+
+    /// 获取客户端的问题和答案
+    pub fn get_client_qa(&self, ip: &ClientIp, session_id: &SessionId) -> Option<(&str, &str)> {
+        self.get_client(ip, session_id)
+            .map(|r| (r.question.as_str(), r.answer.as_str()))
+    }
+
+    /// 获取客户端当前题目的元数据
+    pub fn get_client_question_meta(&self, ip: &ClientIp, session_id: &SessionId) -> Option<&QuestionMeta> {
+        self.get_client(ip, session_id)?.question_meta.as_ref()
+    }
+
+    /// 设置客户端的问题和答案
+    ///
+    /// 同时设置作答截止时间，并刷新最后活动时间
+    pub fn set_client_qa(&mut self, ip: &ClientIp, session_id: &SessionId, q: String, a: String, meta: Option<QuestionMeta>) {
+        let now = Utc::now();
+        let deadline = now + self.question_time_limit;
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.question = q;
+            client.answer = a;
+            client.question_meta = meta;
+            client.question_deadline = Some(deadline);
+            client.question_issued_at = Some(now);
+            client.question_issued = Some(Instant::now());
+            client.touch();
+        }
+    }
+
+    /// 记录客户端本次被判定的答案
+    pub fn set_judged_answer(&mut self, ip: &ClientIp, session_id: &SessionId, answer: &str) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.judged_answer = Some(answer.to_string());
+        }
+    }
+
+    /// 获取答错封禁（Nil 状态）的剩余秒数
+    ///
+    /// ### 返回值
+    /// 客户端不处于封禁状态时返回 `None`
+    pub fn ban_remaining_secs(&self, ip: &ClientIp, session_id: &SessionId) -> Option<u64> {
+        let client = self.get_client(ip, session_id).filter(|r| r.status == ClientStatus::Nil)?;
+        let total = client.status.expiration_duration()?.to_std().ok()?;
+        Some(total.saturating_sub(client.last_seen.elapsed()).as_secs())
+    }
+
+    /// 校验观众提交的答案并完成状态转换
+    ///
+    /// 检查、判定和状态转换在同一次调用中完成，调用方持有写锁期间记录不会过期或被修改。
+    /// 只有 Pending 状态的观众可以作答；重复提交上次被判定的答案时返回与上次相同的结果
+    ///
+    /// ### 参数
+    /// - `answer`: 提交的答案
+    /// - `confirm_delay_secs`: 点击确认入场时领取令牌后需等待的秒数
+    /// - `queue`: 答对时是否进入开播前排队（尚未开播且配置为排队）
+    /// - `judge`: 根据存储的正确答案判定是否答对（在写锁内调用，只应做廉价的比对；准入脚本须在加锁前执行）
+    ///
+    /// ### 返回值
+    /// 判定结果，答对或答错时已更新客户端状态并记录本次被判定的答案
+    pub fn validate_and_apply_answer(
+        &mut self,
+        ip: &ClientIp,
+        session_id: &SessionId,
+        answer: &str,
+        confirm_delay_secs: f64,
+        queue: bool,
+        judge: impl FnOnce(&str) -> bool,
+    ) -> AnswerOutcome {
+        let Some(client) = self.get_client(ip, session_id) else {
+            return AnswerOutcome::UnknownClient;
+        };
+        // 与上次被判定的答案相同视为重复提交（双击、重试等）
+        let repeat = client.judged_answer.as_deref() == Some(answer);
+        match client.status {
+            ClientStatus::Pending => {}
+            ClientStatus::Waiting if repeat => {
+                return AnswerOutcome::RepeatWaiting {
+                    position: self.waiting_position(ip, session_id),
+                };
+            }
+            ClientStatus::Legal | ClientStatus::Playing | ClientStatus::Resting if repeat => {
+                return AnswerOutcome::RepeatAdmitted;
             }
-            for session_id in session_ids_to_remove {
-                clients.remove(&session_id);
+            ClientStatus::Nil if repeat => {
+                return AnswerOutcome::RepeatRejected {
+                    ban_remaining: self.ban_remaining_secs(ip, session_id),
+                };
             }
-            if clients.is_empty() {
-                clients_to_remove.push(ip.clone());
+            ClientStatus::Legal | ClientStatus::Playing | ClientStatus::Resting | ClientStatus::Waiting => {
+                return AnswerOutcome::AlreadyAnswered;
             }
+            _ => return AnswerOutcome::NotPending,
         }
-        for ip in clients_to_remove {
-            db.clients.remove(&ip);
+
+        let Some(issued) = client.question_issued else {
+            return AnswerOutcome::AwaitingQuestion;
+        };
+        if elapsed_beyond(issued, self.question_time_limit) {
+            return AnswerOutcome::QuestionExpired;
+        }
+        let elapsed = issued.elapsed().as_secs_f64();
+        let is_confirm = client
+            .question_meta
+            .as_ref()
+            .is_some_and(|m| m.answer_kind == AnswerKind::Confirm);
+        if is_confirm && elapsed < confirm_delay_secs {
+            let retry_after = (confirm_delay_secs - elapsed).ceil().max(1.0) as u64;
+            return AnswerOutcome::TooEarly { retry_after };
+        }
+
+        let correct = judge(&client.answer);
+        self.set_judged_answer(ip, session_id, answer);
+        if !correct {
+            self.transition(ip, session_id, ClientStatus::Nil);
+            return AnswerOutcome::Rejected {
+                ban_remaining: self.ban_remaining_secs(ip, session_id),
+            };
+        }
+        if queue {
+            AnswerOutcome::Queued {
+                position: self.enqueue_waiting(ip, session_id),
+                elapsed,
+            }
+        } else {
+            self.transition(ip, session_id, ClientStatus::Legal);
+            AnswerOutcome::Admitted { elapsed }
         }
     }
 
-    /// 启动后台 tick 任务
-    pub async fn spin(&self) {
-        // 暂无实现，tick 由 main.rs 中的定时任务处理
+    /// 获取指定 IP 近期已发放的题目
+    pub fn recent_questions(&self, ip: &ClientIp) -> Vec<String> {
+        self.served_questions
+            .get(ip)
+            .map(|list| {
+                list.iter()
+                    .filter(|(_, at)| !elapsed_beyond(*at, self.question_memory))
+                    .map(|(q, _)| q.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 记录向指定 IP 发放的题目（每个 IP 只保留最近 `MAX_SERVED_QUESTIONS_PER_IP` 道）
+    pub fn record_served_question(&mut self, ip: &ClientIp, question: String) {
+        let list = self.served_questions.entry(ip.clone()).or_default();
+        list.push((question, Instant::now()));
+        if list.len() > MAX_SERVED_QUESTIONS_PER_IP {
+            let excess = list.len() - MAX_SERVED_QUESTIONS_PER_IP;
+            list.drain(..excess);
+        }
+    }
+
+    /// 清理超过记忆时长的发放记录
+    pub fn prune_served_questions(&mut self) {
+        let memory = self.question_memory;
+        self.served_questions.retain(|_, list| {
+            list.retain(|(_, at)| !elapsed_beyond(*at, memory));
+            !list.is_empty()
+        });
+    }
+
+    /// 记录因过期被移除的已授权会话
+    fn record_lapse(&mut self, session_id: &SessionId) {
+        self.lapsed.insert(session_id.clone(), Instant::now());
+    }
+
+    /// 清理超过续连令牌有效期的过期会话和撤销会话记录
+    pub fn prune_lapsed(&mut self) {
+        let retention = Duration::minutes(RESUME_VALIDITY_MINUTES);
+        self.lapsed.retain(|_, at| !elapsed_beyond(*at, retention));
+        self.revoked.retain(|_, at| !elapsed_beyond(*at, retention));
+    }
+
+    /// 撤销会话的续连资格（管理员批量移除客户端后调用，防止播放器重连时凭令牌恢复）
+    pub fn revoke_resume(&mut self, session_id: &SessionId) {
+        self.lapsed.remove(session_id);
+        self.revoked.insert(session_id.clone(), Instant::now());
+    }
+
+    /// 会话的续连资格是否已被撤销
+    pub fn resume_revoked(&self, session_id: &SessionId) -> bool {
+        self.revoked.contains_key(session_id)
+    }
+
+    /// 会话的已授权记录是否刚因过期被移除（凭续连令牌仍可恢复）
+    pub fn lapsed_recently(&self, session_id: &SessionId) -> bool {
+        !self.session_index.contains_key(session_id) && self.lapsed.contains_key(session_id)
+    }
+
+    /// 客户端是否仍在等待发放题目（无人推流时连接、题目被推迟发放）
+    pub fn is_awaiting_question(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.get_client(ip, session_id)
+            .is_some_and(|c| c.status == ClientStatus::Pending && c.question_issued.is_none())
+    }
+
+    /// 客户端连接时是否视为已登记的老客户端
+    ///
+    /// 上一场直播已结束的客户端、被推迟发题的客户端视为新用户
+    pub fn is_returning_client(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.get_client_status(ip, session_id).is_some_and(|s| s != ClientStatus::Ended)
+            && !self.is_awaiting_question(ip, session_id)
+    }
+
+    /// 检查客户端能否主动换题
+    ///
+    /// ### 参数
+    /// - `limit`: 每个会话的换题次数上限
+    /// - `cooldown_secs`: 领取题目后需等待的秒数
+    ///
+    /// ### 返回值
+    /// - `Ok(())`: 可以换题
+    /// - `Err(None)`: 已达换题次数上限
+    /// - `Err(Some(secs))`: 冷却中，需再等待的秒数
+    pub fn check_question_refresh(
+        &self,
+        ip: &ClientIp,
+        session_id: &SessionId,
+        limit: u32,
+        cooldown_secs: u64,
+    ) -> Result<(), Option<u64>> {
+        let client = self.get_client(ip, session_id).ok_or(None)?;
+        if client.question_refreshes >= limit {
+            return Err(None);
+        }
+        let cooldown = std::time::Duration::from_secs(cooldown_secs);
+        match client.question_issued.map(|issued| cooldown.saturating_sub(issued.elapsed())) {
+            Some(wait) if !wait.is_zero() => Err(Some(wait.as_secs().max(1))),
+            _ => Ok(()),
+        }
+    }
+
+    /// 记录客户端主动换题一次
+    pub fn record_question_refresh(&mut self, ip: &ClientIp, session_id: &SessionId) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.question_refreshes += 1;
+        }
+    }
+
+    /// 获取当前题目剩余的作答秒数
+    ///
+    /// ### 返回值
+    /// 尚未发放题目时返回 `None`，已超时返回 `Some(0)`
+    pub fn question_remaining_secs(&self, ip: &ClientIp, session_id: &SessionId) -> Option<u64> {
+        let issued = self.get_client(ip, session_id)?.question_issued?;
+        let limit = self.question_time_limit.to_std().unwrap_or_default();
+        Some(limit.saturating_sub(issued.elapsed()).as_secs())
+    }
+
+    /// 获取客户端显示名称
+    pub fn get_client_display_name(&self, ip: &ClientIp, session_id: &SessionId) -> Option<&str> {
+        self.get_client(ip, session_id)?.display_name.as_deref()
+    }
+
+    /// 设置客户端显示名称
+    pub fn set_client_display_name(&mut self, ip: &ClientIp, session_id: &SessionId, name: String) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.display_name = Some(name);
+        }
+    }
+
+    /// 获取客户端状态
+    pub fn get_client_status(&self, ip: &ClientIp, session_id: &SessionId) -> Option<ClientStatus> {
+        self.get_client(ip, session_id).map(|r| r.status)
+    }
+
+    /// 获取客户端状态（通过 session_id，忽略 IP）
+    /// 用于 SRS 回调，因为回调中的 IP 是 Docker 内部 IP
+    pub fn get_client_status_any_ip(&self, session_id: &SessionId) -> Option<(ClientIp, ClientStatus)> {
+        let ip = self.find_client_ip(session_id)?;
+        let status = self.get_client_status(ip, session_id)?;
+        Some((ip.clone(), status))
+    }
+
+    /// 转换客户端状态并刷新活动时间
+    ///
+    /// 所有状态变更都经由此方法（或 `ClientRecord::transition`），不允许的转换被拒绝并记录警告
+    ///
+    /// ### 返回值
+    /// - `true`: 转换成功
+    /// - `false`: 客户端不存在或转换不被允许
+    pub fn transition(&mut self, ip: &ClientIp, session_id: &SessionId, status: ClientStatus) -> bool {
+        let Some(client) = self.get_client_mut(ip, session_id) else {
+            return false;
+        };
+        if !client.transition(status) {
+            return false;
+        }
+        if status == ClientStatus::Legal {
+            self.legal_history.insert(session_id.clone());
+        }
+        true
+    }
+
+    /// 记录观众开始拉流的 SRS 连接
+    pub fn add_srs_client(&mut self, ip: &ClientIp, session_id: &SessionId, client_id: String) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.srs_clients.insert(client_id, Instant::now());
+        }
+    }
+
+    /// 记录观众一次开始拉流
+    ///
+    /// ### 返回值
+    /// 该会话此前是否已经拉过流（即本次为重连）
+    pub fn record_play(&mut self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        match self.get_client_mut(ip, session_id) {
+            Some(client) => {
+                client.plays = client.plays.saturating_add(1);
+                client.plays > 1
+            }
+            None => false,
+        }
+    }
+
+    /// 记录观众一次停止拉流
+    pub fn record_stop(&mut self, ip: &ClientIp, session_id: &SessionId) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.stops = client.stops.saturating_add(1);
+        }
+    }
+
+    /// 移除观众停止拉流的 SRS 连接
+    ///
+    /// ### 返回值
+    /// 该观众剩余的拉流连接数（客户端不存在时为 0）
+    pub fn remove_srs_client(&mut self, ip: &ClientIp, session_id: &SessionId, client_id: &str) -> usize {
+        match self.get_client_mut(ip, session_id) {
+            Some(client) => {
+                client.srs_clients.remove(client_id);
+                client.srs_clients.len()
+            }
+            None => 0,
+        }
+    }
+
+    /// 与 SRS 的客户端列表对账
+    ///
+    /// 网络中断等情况下 SRS 可能不发送 on_stop，观众记录会一直停留在观看中（永不过期）。
+    /// 在 SRS 列表中已不存在的连接被移除，观看中的观众没有剩余连接时转为暂离。
+    ///
+    /// ### 参数
+    /// - `present`: SRS 当前连接到本场直播的 client_id
+    /// - `fetched_at`: 获取列表的时刻，之后才到达的 on_play 不参与对账
+    ///
+    /// ### 返回值
+    /// 转为暂离的观众数
+    pub fn reconcile_srs_clients(&mut self, present: &HashSet<String>, fetched_at: Instant) -> usize {
+        let mut rested = 0;
+        for client in self.clients.values_mut().flat_map(|m| m.values_mut()) {
+            if client.srs_clients.is_empty() {
+                continue;
+            }
+            client
+                .srs_clients
+                .retain(|id, since| *since >= fetched_at || present.contains(id));
+            if client.srs_clients.is_empty() && client.status == ClientStatus::Playing {
+                client.transition(ClientStatus::Resting);
+                rested += 1;
+            }
+        }
+        rested
+    }
+
+    /// 按 SRS 统计的各连接码率记录观众的带宽占用
+    ///
+    /// ### 参数
+    /// - `client_kbps`: SRS client_id -> 近 30 秒的发送码率（kbps）
+    /// - `elapsed`: 距上一轮统计的时长，按此估算本轮的下行流量
+    pub fn record_bandwidth(&mut self, client_kbps: &HashMap<String, u64>, elapsed: std::time::Duration) {
+        for client in self.clients.values_mut().flat_map(|m| m.values_mut()) {
+            let kbps: u64 = client.srs_clients.keys().filter_map(|id| client_kbps.get(id)).sum();
+            client.send_kbps = kbps;
+            client.bytes_sent = client.bytes_sent.saturating_add(srs_api::kbps_to_bytes(kbps, elapsed));
+        }
+    }
+
+    /// 将客户端加入开播前的排队（答题已通过，等待开播）
+    ///
+    /// ### 返回值
+    /// 排队位置（从 1 开始），客户端不存在时返回 `None`
+    pub fn enqueue_waiting(&mut self, ip: &ClientIp, session_id: &SessionId) -> Option<usize> {
+        let client = self.get_client_mut(ip, session_id)?;
+        if client.transition(ClientStatus::Waiting) {
+            client.waiting_since.get_or_insert_with(Instant::now);
+        }
+        self.waiting_position(ip, session_id)
+    }
+
+    /// 客户端在开播前排队中的位置（从 1 开始）
+    ///
+    /// ### 返回值
+    /// 客户端不在排队中时返回 `None`
+    pub fn waiting_position(&self, ip: &ClientIp, session_id: &SessionId) -> Option<usize> {
+        let client = self.get_client(ip, session_id)?;
+        let since = client.waiting_since.filter(|_| client.status == ClientStatus::Waiting)?;
+        let ahead = self
+            .clients
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|c| c.status == ClientStatus::Waiting)
+            .filter(|c| c.waiting_since.is_some_and(|s| s < since))
+            .count();
+        Some(ahead + 1)
+    }
+
+    /// 开播时将所有排队中的客户端转为已授权
+    ///
+    /// ### 返回值
+    /// 转为已授权的客户端数
+    pub fn activate_waiting_clients(&mut self) -> usize {
+        let mut activated = Vec::new();
+        for client in self.clients.values_mut().flat_map(|m| m.values_mut()) {
+            if client.status == ClientStatus::Waiting && client.transition(ClientStatus::Legal) {
+                client.waiting_since = None;
+                activated.push(client.session_id.clone());
+            }
+        }
+        let count = activated.len();
+        self.legal_history.extend(activated);
+        count
+    }
+
+    /// 正在观看的观众数（不含主播）
+    pub fn playing_viewers(&self) -> usize {
+        self.clients
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|c| c.status == ClientStatus::Playing && !c.is_publisher)
+            .count()
+    }
+
+    /// 已领到题目、但连接超过指定时长仍未通过答题的观众数
+    ///
+    /// ### 参数
+    /// - `min_age`: 自首次连接起经过的最短时长
+    pub fn stuck_pending_count(&self, min_age: Duration) -> usize {
+        let since = Utc::now() - min_age;
+        self.clients
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|c| {
+                c.status == ClientStatus::Pending
+                    && !c.is_publisher
+                    && c.question_issued.is_some()
+                    && c.created_at < since
+            })
+            .count()
+    }
+
+    /// 客户端在等候室中的位置（从 1 开始）
+    ///
+    /// 只统计已授权、尚未开始观看且近期仍有活动的观众，先按优先放行类别、再按进入等候室的先后排序
+    ///
+    /// ### 参数
+    /// - `priority`: 优先放行的类别（按优先级从高到低）
+    ///
+    /// ### 返回值
+    /// 客户端不在等候室中时返回 `None`
+    pub fn viewer_queue_position(&self, ip: &ClientIp, session_id: &SessionId, priority: &[PriorityClass]) -> Option<usize> {
+        let client = self.get_client(ip, session_id)?;
+        let key = (client.priority_rank(priority), client.queued_since?);
+        let ahead = self
+            .clients
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|c| matches!(c.status, ClientStatus::Legal | ClientStatus::Resting))
+            .filter(|c| c.last_seen.elapsed() < VIEWER_QUEUE_STALE)
+            .filter(|c| c.queued_since.is_some_and(|s| (c.priority_rank(priority), s) < key))
+            .count();
+        Some(ahead + 1)
+    }
+
+    /// 客户端是否属于优先放行的类别
+    pub fn client_has_priority(&self, ip: &ClientIp, session_id: &SessionId, priority: &[PriorityClass]) -> bool {
+        self.get_client(ip, session_id)
+            .is_some_and(|c| c.priority_rank(priority) < priority.len())
+    }
+
+    /// 标记客户端出示了有效的回访观众令牌
+    pub fn set_client_alumni(&mut self, ip: &ClientIp, session_id: &SessionId) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.alumni = true;
+        }
+    }
+
+    /// 观看人数已满时，客户端在等候室中仍需等待的位置
+    ///
+    /// ### 参数
+    /// - `max_viewers`: 同时观看人数上限（0 表示不限制）
+    /// - `priority`: 优先放行的类别（按优先级从高到低）
+    ///
+    /// ### 返回值
+    /// 客户端在等候室中且尚未轮到时返回等候位置，否则返回 `None`
+    pub fn viewer_queue_wait(
+        &self,
+        ip: &ClientIp,
+        session_id: &SessionId,
+        max_viewers: usize,
+        priority: &[PriorityClass],
+    ) -> Option<usize> {
+        if max_viewers == 0 {
+            return None;
+        }
+        let position = self.viewer_queue_position(ip, session_id, priority)?;
+        let free = max_viewers.saturating_sub(self.playing_viewers());
+        (position > free).then_some(position)
+    }
+
+    /// 按同时观看人数上限决定是否允许客户端开始观看
+    ///
+    /// 已在观看的客户端和主播不受限制；其余客户端先进入等候室（已在其中的保留原位置），
+    /// 轮到空出的名额时放行并离开等候室
+    ///
+    /// ### 参数
+    /// - `max_viewers`: 同时观看人数上限（0 表示不限制）
+    /// - `priority`: 优先放行的类别（按优先级从高到低）
+    ///
+    /// ### 返回值
+    /// - `Ok(())`: 允许观看
+    /// - `Err(position)`: 人数已满，返回等候位置
+    pub fn admit_viewer(
+        &mut self,
+        ip: &ClientIp,
+        session_id: &SessionId,
+        max_viewers: usize,
+        priority: &[PriorityClass],
+    ) -> Result<(), usize> {
+        let Some(client) = self.get_client_mut(ip, session_id) else {
+            return Ok(());
+        };
+        if max_viewers == 0 || client.status == ClientStatus::Playing || client.is_publisher {
+            client.queued_since = None;
+            return Ok(());
+        }
+        client.queued_since.get_or_insert_with(Instant::now);
+        client.touch();
+        match self.viewer_queue_wait(ip, session_id, max_viewers, priority) {
+            Some(position) => Err(position),
+            None => {
+                if let Some(client) = self.get_client_mut(ip, session_id) {
+                    client.queued_since = None;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// 客户端是否已授权但尚未收到欢迎语和房间规则
+    pub fn needs_welcome(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.get_client(ip, session_id)
+            .is_some_and(|c| c.status.is_authorized() && !c.welcomed)
+    }
+
+    /// 标记客户端已收到欢迎语和房间规则
+    pub fn mark_welcomed(&mut self, ip: &ClientIp, session_id: &SessionId) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.welcomed = true;
+        }
+    }
+
+    /// 刷新等候室中客户端的活动时间，使其保留等候位置
+    pub fn touch_queued_viewer(&mut self, ip: &ClientIp, session_id: &SessionId) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            if client.queued_since.is_some() {
+                client.touch();
+            }
+        }
+    }
+
+    /// 将所有客户端转为已结束状态
+    ///
+    /// 直播结束时调用，所有观众需重新连接答题
+    pub fn end_all_clients(&mut self) {
+        for client in self.clients.values_mut().flat_map(|m| m.values_mut()) {
+            client.transition(ClientStatus::Ended);
+            client.is_publisher = false;
+            client.queued_since = None;
+        }
+    }
+
+    /// 批量移除满足条件的客户端（主播和联合主持除外）
+    ///
+    /// 供管理员在遭受攻击流量或配置失误后快速清理
+    ///
+    /// ### 返回值
+    /// 被移除的客户端记录
+    pub fn remove_clients_where(&mut self, pred: impl Fn(&ClientRecord) -> bool) -> Vec<ClientRecord> {
+        let targets: Vec<(ClientIp, SessionId)> = self
+            .clients
+            .values()
+            .flat_map(|m| m.values())
+            .filter(|c| !c.is_publisher && self.co_host.as_ref() != Some(&c.session_id) && pred(c))
+            .map(|c| (c.ip.clone(), c.session_id.clone()))
+            .collect();
+        targets
+            .iter()
+            .filter_map(|(ip, session_id)| self.remove_client(ip, session_id))
+            .collect()
+    }
+
+    /// 应用主实例复制来的已授权客户端（热备复制，备用实例调用）
+    ///
+    /// 客户端不存在时创建；尚未授权时转为已授权（`waiting` 为真时转为排队中），并刷新活动时间
+    pub fn apply_replicated(&mut self, ip: ClientIp, session_id: SessionId, waiting: bool, display_name: Option<String>) {
+        if !self.has_client(&ip, &session_id) {
+            self.add_client(ip.clone(), session_id.clone());
+        }
+        let status = self.get_client_status(&ip, &session_id);
+        if waiting && status.is_some_and(|s| s.can_transition_to(ClientStatus::Waiting)) {
+            self.enqueue_waiting(&ip, &session_id);
+        } else if !status.is_some_and(|s| s.is_authorized()) {
+            self.transition(&ip, &session_id, ClientStatus::Legal);
+        }
+        if let Some(client) = self.get_client_mut(&ip, &session_id) {
+            if display_name.is_some() {
+                client.display_name = display_name;
+            }
+            client.touch();
+        }
+    }
+
+    /// 将所有观看中的观众转为暂离状态（主播除外）
+    ///
+    /// 暂离状态的观众仍可拉流，但会在长时间无活动后过期
+    ///
+    /// ### 返回值
+    /// 转换的客户端数量
+    pub fn rest_all_playing(&mut self) -> usize {
+        self.clients
+            .values_mut()
+            .flat_map(|m| m.values_mut())
+            .filter(|c| !c.is_publisher && c.status == ClientStatus::Playing)
+            .map(|c| c.transition(ClientStatus::Resting))
+            .filter(|&moved| moved)
+            .count()
+    }
+
+    /// 获取客户端配对码
+    pub fn get_client_pairing_code(&self, ip: &ClientIp, session_id: &SessionId) -> Option<&str> {
+        self.get_client(ip, session_id).map(|r| r.pairing_code.as_str())
+    }
+
+    /// 通过 session_id 或配对码查找客户端
+    ///
+    /// ### 返回值
+    /// 找到时返回 (IP, session_id)
+    pub fn find_client(&self, session_id_or_code: &str) -> Option<(ClientIp, SessionId)> {
+        if let Some((session_id, ip)) = self.session_index.get_key_value(session_id_or_code) {
+            return Some((ip.clone(), session_id.clone()));
+        }
+        let code = session_id_or_code.to_uppercase();
+        self.clients
+            .values()
+            .flat_map(|m| m.values())
+            .find(|c| c.pairing_code == code)
+            .map(|c| (c.ip.clone(), c.session_id.clone()))
+    }
+
+    /// 手动将客户端放行为 Legal
+    ///
+    /// ### 参数
+    /// - `session_id_or_code`: 客户端的 session_id 或配对码
+    ///
+    /// ### 返回值
+    /// 放行成功时返回 (IP, session_id)
+    pub fn grant_legal(&mut self, session_id_or_code: &str) -> Option<(ClientIp, SessionId)> {
+        let (ip, session_id) = self.find_client(session_id_or_code)?;
+        self.transition(&ip, &session_id, ClientStatus::Legal);
+        Some((ip, session_id))
+    }
+
+    /// 为新设备发起跨设备配对，返回 6 位数字配对码（5 分钟内有效）
+    ///
+    /// 同一会话重复发起时，旧配对码作废
+    ///
+    /// ### 返回值
+    /// - `Ok(配对码)`: 发起成功
+    /// - `Err(秒数)`: 该 IP 或全局未完成的配对请求已达上限（或暂时无法生成不重复的配对码），需等待的秒数
+    pub fn start_pairing(&mut self, ip: &ClientIp, session_id: &SessionId) -> Result<String, u64> {
+        let now = Instant::now();
+        self.pair_requests
+            .retain(|_, (_, sid, expires_at)| sid != session_id && *expires_at > now);
+
+        // 达到上限时，需等到最早过期的请求释放名额
+        let retry_after = |expiries: Vec<Instant>| {
+            expiries
+                .into_iter()
+                .min()
+                .map_or(1, |expires_at| expires_at.saturating_duration_since(now).as_secs().max(1))
+        };
+        let same_ip: Vec<Instant> = self
+            .pair_requests
+            .values()
+            .filter(|(rip, _, _)| rip == ip)
+            .map(|(_, _, expires_at)| *expires_at)
+            .collect();
+        if same_ip.len() >= MAX_PAIR_REQUESTS_PER_IP {
+            return Err(retry_after(same_ip));
+        }
+        if self.pair_requests.len() >= MAX_PAIR_REQUESTS {
+            return Err(retry_after(self.pair_requests.values().map(|(_, _, expires_at)| *expires_at).collect()));
+        }
+
+        let mut rng = rand::thread_rng();
+        let code = (0..PAIR_CODE_ATTEMPTS)
+            .map(|_| format!("{:06}", rng.gen_range(0..1_000_000)))
+            .find(|code| !self.pair_requests.contains_key(code))
+            .ok_or(1u64)?;
+        self.pair_requests.insert(
+            code.clone(),
+            (ip.clone(), session_id.clone(), now + PAIRING_VALIDITY),
+        );
+        Ok(code)
+    }
+
+    /// 确认跨设备配对，将授权复制给发起配对的设备
+    ///
+    /// ### 返回值
+    /// 配对成功时返回新设备的 (IP, session_id)
+    pub fn confirm_pairing(&mut self, code: &str) -> Option<(ClientIp, SessionId)> {
+        let (ip, session_id, expires_at) = self.pair_requests.remove(code)?;
+        if Instant::now() > expires_at {
+            return None;
+        }
+        // 新设备的待答题记录可能已过期，重新注册
+        if !self.has_client(&ip, &session_id) {
+            self.add_client(ip.clone(), session_id.clone());
+        }
+        self.transition(&ip, &session_id, ClientStatus::Legal);
+        Some((ip, session_id))
+    }
+
+    /// 清理过期的配对请求
+    pub fn prune_pair_requests(&mut self) {
+        let now = Instant::now();
+        self.pair_requests.retain(|_, (_, _, expires_at)| *expires_at > now);
+    }
+
+    /// 检查会话是否曾经通过验证
+    pub fn has_legal_history(&self, session_id: &SessionId) -> bool {
+        self.legal_history.contains(session_id)
+    }
+
+    /// 设置客户端为主播
+    pub fn set_client_publisher(&mut self, ip: &ClientIp, session_id: &SessionId) {
+        if let Some(client) = self.get_client_mut(ip, session_id) {
+            client.is_publisher = true;
+        }
+    }
+
+    /// 检查客户端是否为主播
+    pub fn client_is_publisher(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.get_client(ip, session_id)
+            .map(|r| r.is_publisher)
+            .unwrap_or(false)
+    }
+
+    /// 指定或取消联合主持
+    ///
+    /// ### 参数
+    /// - `session_id`: 联合主持的会话 ID，`None` 为取消
+    ///
+    /// ### 返回值
+    /// 会话不存在或是主播本人时返回 false
+    pub fn set_co_host(&mut self, session_id: Option<SessionId>) -> bool {
+        if let Some(sid) = &session_id {
+            let valid = self
+                .find_client_ip(sid)
+                .and_then(|ip| self.get_client(ip, sid))
+                .is_some_and(|client| !client.is_publisher);
+            if !valid {
+                return false;
+            }
+        }
+        self.co_host = session_id;
+        true
+    }
+
+    /// 检查客户端是否为联合主持
+    pub fn client_is_co_host(&self, ip: &ClientIp, session_id: &SessionId) -> bool {
+        self.co_host.as_ref() == Some(session_id) && self.has_client(ip, session_id)
+    }
+
+    /// 撤销指定会话的主播标记
+    pub fn revoke_client_publisher(&mut self, session_id: &SessionId) {
+        if let Some(ip) = self.find_client_ip(session_id).cloned() {
+            if let Some(client) = self.get_client_mut(&ip, session_id) {
+                client.is_publisher = false;
+            }
+        }
+    }
+
+    /// 将会话设为已授权的主播（推流参数绑定）
+    ///
+    /// ### 返回值
+    /// 会话尚未连接时返回 false（之后连接时再授予）
+    pub fn bind_publisher(&mut self, session_id: &SessionId) -> bool {
+        let Some(ip) = self.find_client_ip(session_id).cloned() else {
+            return false;
+        };
+        self.transition(&ip, session_id, ClientStatus::Legal);
+        self.set_client_publisher(&ip, session_id);
+        true
+    }
+}
+
+/// 准入策略状态
+///
+/// 推流密钥校验、公开模式、观众入场方式和入场开关
+pub struct AccessPolicyState {
+    /// 密钥验证器
+    pub verifier: StreamerVerifier,
+    /// 是否为公开模式（无需答题）
+    pub public_stream: bool,
+    /// 本场直播的观众入场方式
+    pub entry_mode: EntryMode,
+    /// 是否暂停接纳新观众（已授权的观众不受影响）
+    pub entries_closed: bool,
+    /// 推流密钥猜测失败记录，跨直播保留
+    pub secret_guard: SecretGuard,
+    /// 直播状态快照（与 `SrsDatabase` 共享）
+    snapshot: Arc<ArcSwap<StreamSnapshot>>,
+}
+
+impl AccessPolicyState {
+    /// 创建准入策略状态
+    ///
+    /// ### 参数
+    /// - `secret_path`: 密钥文件路径
+    /// - `snapshot`: 公开模式变化时发布到的直播状态快照
+    pub fn new(secret_path: PathBuf, snapshot: Arc<ArcSwap<StreamSnapshot>>) -> Self {
+        Self {
+            verifier: StreamerVerifier::new(secret_path),
+            public_stream: false,
+            entry_mode: EntryMode::Quiz,
+            entries_closed: false,
+            secret_guard: SecretGuard::new(),
+            snapshot,
+        }
+    }
+
+    /// 恢复本场直播的准入设置（`secret_guard` 跨直播保留）
+    pub fn reset(&mut self) {
+        self.set_public(false);
+        self.set_entries_closed(false);
+        self.entry_mode = EntryMode::Quiz;
+    }
+
+    /// 验证主播密钥
+    pub fn verify_streamer(&self, secret: &str) -> bool {
+        self.verifier.authorize(secret)
+    }
+
+    /// 设置公开模式
+    pub fn set_public(&mut self, public: bool) {
+        self.public_stream = public;
+        self.snapshot.rcu(|current| StreamSnapshot {
+            public,
+            ..StreamSnapshot::clone(current)
+        });
+    }
+
+    /// 检查是否为公开模式
+    pub fn is_public(&self) -> bool {
+        self.public_stream
+    }
+
+    /// 暂停或恢复接纳新观众
+    pub fn set_entries_closed(&mut self, closed: bool) {
+        self.entries_closed = closed;
+        self.snapshot.rcu(|current| StreamSnapshot {
+            entries_closed: closed,
+            ..StreamSnapshot::clone(current)
+        });
+    }
+
+    /// 设置本场直播的观众入场方式
+    pub fn set_entry_mode(&mut self, mode: EntryMode) {
+        self.entry_mode = mode;
+    }
+
+    /// 获取本场直播的观众入场方式
+    pub fn entry_mode(&self) -> EntryMode {
+        self.entry_mode
+    }
+}
+
+// ============================================================================
+// SRS 数据库包装器
+// ============================================================================
+
+/// SRS 数据库包装器
+///
+/// 主播状态、客户端注册表和准入策略各自使用独立的锁，互不阻塞。
+/// 需要同时持有多把锁时按 `streamer` → `clients` → `access` 的顺序获取
+/// （整体仍在 `chat_db` 之前），同一作用域内不要重复获取同一把锁。
+/// 只需要推流状态、直播间名称等信息的高频路径应读取 [`SrsDatabase::snapshot`]，不必获取锁
+#[derive(Clone)]
+pub struct SrsDatabase {
+    /// 主播状态
+    pub streamer: Arc<RwLock<StreamerState>>,
+    /// 客户端注册表
+    pub clients: Arc<RwLock<ClientRegistry>>,
+    /// 准入策略状态
+    pub access: Arc<RwLock<AccessPolicyState>>,
+    /// 直播状态快照（主播状态和公开模式变化时更新，读取无需加锁）
+    snapshot: Arc<ArcSwap<StreamSnapshot>>,
+}
+
+impl SrsDatabase {
+    /// 创建新的 SRS 数据库
+    ///
+    /// ### 参数
+    /// - `secret_path`: 密钥文件路径
+    /// - `question_time_limit`: 单道题目的作答时限
+    /// - `question_memory`: 近期发放题目的记忆时长
+    pub fn new(
+        secret_path: PathBuf,
+        question_time_limit: Duration,
+        question_memory: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let snapshot = Arc::new(ArcSwap::from_pointee(StreamSnapshot::default()));
+        Ok(Self {
+            streamer: Arc::new(RwLock::new(StreamerState::new(snapshot.clone()))),
+            clients: Arc::new(RwLock::new(ClientRegistry::new(question_time_limit, question_memory))),
+            access: Arc::new(RwLock::new(AccessPolicyState::new(secret_path, snapshot.clone()))),
+            snapshot,
+        })
+    }
+
+    /// 获取当前的直播状态快照
+    ///
+    /// 主播状态机转换在持有客户端注册表写锁期间发布快照，因此先获取注册表的锁再读取快照，
+    /// 读到的直播状态不会比注册表中观众的授权状态旧（见 `clients_with_snapshot`）
+    pub fn snapshot(&self) -> Arc<StreamSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// 获取客户端注册表读锁，并在持有锁后读取直播状态快照
+    ///
+    /// 需要同时判断观众授权和直播状态时使用：开播放行排队观众的瞬间，
+    /// 先读快照再加锁可能读到旧的"未开播"快照和新的授权状态
+    pub fn clients_with_snapshot(&self) -> (RwLockReadGuard<'_, ClientRegistry>, Arc<StreamSnapshot>) {
+        let clients = self.clients.read();
+        let snapshot = self.snapshot();
+        (clients, snapshot)
+    }
+
+    /// 是否正在推流（含暂停中）
+    pub fn is_streaming(&self) -> bool {
+        self.snapshot.load().is_streaming()
+    }
+
+    /// 是否处于推流暂停中
+    pub fn is_paused(&self) -> bool {
+        self.snapshot.load().is_paused()
+    }
+
+    /// 清理过期记录（定期调用）
+    ///
+    /// ### 返回值
+    /// 本次被移除的过期客户端 (IP, session_id) 列表
+    pub fn tick(&self, events: &EventBus) -> Vec<(ClientIp, SessionId)> {
+        let mut streamer = self.streamer.write();
+        let mut clients = self.clients.write();
+        let mut access = self.access.write();
+
+        clients.prune_served_questions();
+        clients.prune_pair_requests();
+        clients.prune_lapsed();
+        access.secret_guard.prune();
+
+        // 先检查主播是否过期
+        if let StreamerTransition::Applied { event, .. } = streamer.apply(&mut clients, StreamerEvent::Expired) {
+            tracing::debug!("srs_db.tick(): 主播已过期，清除所有数据");
+            access.reset();
+            if let Some(event) = event {
+                events.publish(event);
+            }
+            return Vec::new();
+        }
+        drop(access);
+        drop(streamer);
+
+        // 清理过期的客户端
+        let expired: Vec<(ClientIp, SessionId)> = clients
+            .clients
+            .iter()
+            .flat_map(|(ip, clients)| {
+                clients
+                    .iter()
+                    .filter(|(_, client)| client.is_expired())
+                    .map(move |(session_id, _)| (ip.clone(), session_id.clone()))
+            })
+            .collect();
+        for (ip, session_id) in &expired {
+            tracing::debug!(
+                "srs_db.tick(): 移除过期客户端: (ip={}, session_id={})",
+                crate::redact::ip(ip),
+                session_id
+            );
+            if clients.has_authorized_client(ip, session_id) {
+                clients.record_lapse(session_id);
+            }
+            clients.remove_client(ip, session_id);
+        }
+        expired
+    }
+
+    /// 启动后台 tick 任务
+    pub async fn spin(&self) {
+        // 暂无实现，tick 由 main.rs 中的定时任务处理
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// 开播放行排队观众时，聊天接口的授权检查不能读到"已授权但未开播"的组合
+    ///
+    /// 与 `handle_chat_request` 的权限验证相同，通过 `clients_with_snapshot` 读取。
+    /// 读取方在 `Publish` 持有客户端注册表写锁期间发起，必须等到转换完成后读到新快照
+    #[test]
+    fn admitted_viewer_never_sees_offline_snapshot() {
+        let db = Arc::new(
+            SrsDatabase::new(
+                PathBuf::from("/nonexistent/secret.txt"),
+                Duration::seconds(60),
+                Duration::seconds(60),
+            )
+            .unwrap(),
+        );
+        let ip = ClientIp::parse("10.0.0.2").unwrap();
+        let session_id = SessionId::parse("viewer-session").unwrap();
+        {
+            let mut clients = db.clients.write();
+            clients.add_client(ip.clone(), session_id.clone());
+            clients.enqueue_waiting(&ip, &session_id);
+        }
+
+        // 与 on_publish 相同的锁顺序：streamer → clients
+        let mut streamer = db.streamer.write();
+        let mut clients = db.clients.write();
+
+        // 聊天接口的授权检查在开播转换进行中到达
+        let reader = {
+            let (db, ip, session_id) = (db.clone(), ip.clone(), session_id.clone());
+            thread::spawn(move || {
+                let (clients, snapshot) = db.clients_with_snapshot();
+                (clients.has_authorized_client(&ip, &session_id), snapshot.is_streaming())
+            })
+        };
+        // 让读取方先阻塞在注册表的锁上
+        thread::sleep(std::time::Duration::from_millis(50));
+
+        let publish = StreamerEvent::Publish {
+            ip: ClientIp::parse("10.0.0.1").unwrap(),
+            secret: "secret_test".to_string(),
+            app: "live".to_string(),
+            stream: "livestream".to_string(),
+            push_session: None,
+        };
+        assert!(matches!(streamer.apply(&mut clients, publish), StreamerTransition::Applied { .. }));
+        drop(clients);
+        drop(streamer);
+
+        let (authorized, streaming) = reader.join().unwrap();
+        assert!(authorized, "排队观众应在开播时转为已授权");
+        assert!(streaming, "观众已授权时读到了未开播的快照");
     }
 }
@@ -0,0 +1,389 @@
+//! # SRS HTTP API 客户端模块
+//!
+//! 封装对 SRS HTTP API（默认端口 1985）的主动调用，例如踢出推流端、统计观众人数、开关录制。
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// 观众播放协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewerProtocol {
+    /// HTTP-FLV
+    HttpFlv,
+    /// HLS
+    Hls,
+    /// WebRTC
+    WebRtc,
+    /// RTMP
+    Rtmp,
+    /// 无法识别的协议
+    Other,
+}
+
+impl ViewerProtocol {
+    /// 全部协议（用于指标输出）
+    pub const ALL: [Self; 5] = [Self::HttpFlv, Self::Hls, Self::WebRtc, Self::Rtmp, Self::Other];
+
+    /// 将协议转换为字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HttpFlv => "http_flv",
+            Self::Hls => "hls",
+            Self::WebRtc => "webrtc",
+            Self::Rtmp => "rtmp",
+            Self::Other => "other",
+        }
+    }
+
+    /// 根据 SRS 客户端信息判断播放协议
+    ///
+    /// 优先使用 `type` 字段（如 `flv-play`、`rtc-play`、`rtmp-play`），
+    /// 无法判断时再看 `url` 的扩展名
+    pub fn classify(client: &Value) -> Self {
+        let kind = client
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_lowercase();
+        let url = client
+            .get("url")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if kind.contains("flv") || url.ends_with(".flv") {
+            Self::HttpFlv
+        } else if kind.contains("hls") || url.ends_with(".m3u8") {
+            Self::Hls
+        } else if kind.contains("rtc") {
+            Self::WebRtc
+        } else if kind.contains("rtmp") || kind == "play" {
+            Self::Rtmp
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// 按播放协议分类的观众人数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ViewerBreakdown {
+    /// HTTP-FLV 观众数
+    pub http_flv: usize,
+    /// HLS 观众数
+    pub hls: usize,
+    /// WebRTC 观众数
+    pub webrtc: usize,
+    /// RTMP 观众数
+    pub rtmp: usize,
+    /// 无法识别协议的观众数
+    pub other: usize,
+}
+
+impl ViewerBreakdown {
+    /// 指定协议的观众数
+    pub fn get(&self, protocol: ViewerProtocol) -> usize {
+        match protocol {
+            ViewerProtocol::HttpFlv => self.http_flv,
+            ViewerProtocol::Hls => self.hls,
+            ViewerProtocol::WebRtc => self.webrtc,
+            ViewerProtocol::Rtmp => self.rtmp,
+            ViewerProtocol::Other => self.other,
+        }
+    }
+
+    /// 计入一名观众
+    fn add(&mut self, protocol: ViewerProtocol) {
+        match protocol {
+            ViewerProtocol::HttpFlv => self.http_flv += 1,
+            ViewerProtocol::Hls => self.hls += 1,
+            ViewerProtocol::WebRtc => self.webrtc += 1,
+            ViewerProtocol::Rtmp => self.rtmp += 1,
+            ViewerProtocol::Other => self.other += 1,
+        }
+    }
+
+    /// 观众总数
+    pub fn total(&self) -> usize {
+        self.http_flv + self.hls + self.webrtc + self.rtmp + self.other
+    }
+}
+
+/// SRS 中正在推流的流
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivePublish {
+    /// 应用名
+    pub app: String,
+    /// 流名称
+    pub stream: String,
+    /// 推流端的 SRS client_id
+    pub client_id: String,
+}
+
+/// 指定流的观众统计
+#[derive(Debug, Clone, Default)]
+pub struct StreamViewers {
+    /// 按播放协议分类的人数
+    pub breakdown: ViewerBreakdown,
+    /// 观众连接的 SRS client_id（用于与观众记录对账）
+    pub client_ids: HashSet<String>,
+    /// SRS 全部流近 30 秒的发送码率之和（kbps），即当前的上行带宽占用
+    pub send_kbps: u64,
+    /// 各观众连接近 30 秒的发送码率：SRS client_id -> kbps
+    pub client_kbps: HashMap<String, u64>,
+    /// 各流近 30 秒的发送码率：`app/stream` -> kbps
+    pub stream_kbps: HashMap<String, u64>,
+    /// 该流近 30 秒的接收码率（kbps，即推流端的上传码率，SRS 中没有该流时为 `None`）
+    pub ingest_kbps: Option<u64>,
+}
+
+/// SRS HTTP API 客户端
+#[derive(Clone)]
+pub struct SrsApi {
+    /// API 基础地址（如 http://127.0.0.1:1985）
+    base_url: String,
+    /// HTTP 客户端
+    client: reqwest::Client,
+}
+
+impl SrsApi {
+    /// 创建新的 SRS API 客户端
+    ///
+    /// ### 参数
+    /// - `srs_api_addr`: SRS API 地址（host:port 格式）
+    pub fn new(srs_api_addr: &str) -> Self {
+        // 禁用代理，避免本地请求被系统代理拦截
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .unwrap_or_default();
+        Self {
+            base_url: format!("http://{}", srs_api_addr),
+            client,
+        }
+    }
+
+    /// 请求 SRS API 并解析 JSON 响应
+    ///
+    /// ### 参数
+    /// - `path`: 以 `/` 开头的接口路径（如 `/api/v1/versions`）
+    pub async fn get_json(&self, path: &str) -> Result<Value, String> {
+        let url = format!("{}{}", self.base_url, path);
+        let resp = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("GET {} 失败: {}", url, e))?;
+        if !resp.status().is_success() {
+            return Err(format!("GET {} 返回状态码 {}", url, resp.status()));
+        }
+        resp.json()
+            .await
+            .map_err(|e| format!("解析 {} 响应失败: {}", url, e))
+    }
+
+    /// 获取 SRS 的流列表
+    async fn list_streams(&self) -> Result<Vec<Value>, String> {
+        let json = self.get_json("/api/v1/streams/?count=1000").await?;
+        Ok(json
+            .get("streams")
+            .and_then(|s| s.as_array())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// 列出 SRS 中正在推流的流（回调看门狗使用）
+    pub async fn active_publishes(&self) -> Result<Vec<ActivePublish>, String> {
+        Ok(self
+            .list_streams()
+            .await?
+            .iter()
+            .filter_map(|s| {
+                Some(ActivePublish {
+                    app: s.get("app")?.as_str()?.to_string(),
+                    stream: s.get("name")?.as_str()?.to_string(),
+                    client_id: publisher_cid(s)?,
+                })
+            })
+            .collect())
+    }
+
+    /// 在流列表中查找指定的流
+    async fn find_stream(&self, app: &str, stream: &str) -> Result<Option<Value>, String> {
+        Ok(find_in(&self.list_streams().await?, app, stream).cloned())
+    }
+
+    /// 查找指定流的推流端 client_id
+    ///
+    /// ### 返回值
+    /// - `Ok(Some(cid))`: 找到推流端
+    /// - `Ok(None)`: 该流当前没有推流端
+    /// - `Err(msg)`: 请求 SRS API 失败
+    pub async fn find_publisher(&self, app: &str, stream: &str) -> Result<Option<String>, String> {
+        Ok(self
+            .find_stream(app, stream)
+            .await?
+            .as_ref()
+            .and_then(publisher_cid))
+    }
+
+    /// 统计指定流的观众人数
+    ///
+    /// ### 统计方式
+    /// 1. 在 `/api/v1/streams/` 中找到该流的 SRS 流 ID 和推流端 client_id，同时记录各流的发送码率
+    /// 2. 在 `/api/v1/clients/` 中统计属于该流、且不是推流端的客户端及其发送码率
+    ///
+    /// 其他流的观众、转推/转拉客户端、主播的预览播放端以及推流端本身都不会被计入人数
+    ///
+    /// ### 参数
+    /// - `known_publisher`: on_publish 回调记录的推流端 client_id，流信息中没有活跃推流端时用于排除
+    /// - `excluded`: 不计入人数的其他 client_id（主播的预览播放端）
+    ///
+    /// ### 返回值
+    /// - `Ok(观众统计)`: 该流不存在时人数全部为 0
+    /// - `Err(msg)`: 请求 SRS API 失败
+    pub async fn count_viewers(
+        &self,
+        app: &str,
+        stream: &str,
+        known_publisher: Option<&str>,
+        excluded: &HashSet<String>,
+    ) -> Result<StreamViewers, String> {
+        let streams = self.list_streams().await?;
+        let stream_kbps: HashMap<String, u64> = streams
+            .iter()
+            .filter_map(|s| {
+                let app = s.get("app")?.as_str()?;
+                let name = s.get("name")?.as_str()?;
+                Some((format!("{}/{}", app, name), send_30s(s)?))
+            })
+            .collect();
+        let send_kbps = streams.iter().filter_map(send_30s).sum();
+        let Some(info) = find_in(&streams, app, stream) else {
+            return Ok(StreamViewers { send_kbps, stream_kbps, ..Default::default() });
+        };
+        let Some(stream_id) = info.get("id").and_then(Value::as_str) else {
+            return Err("SRS 流信息缺少 id 字段".to_string());
+        };
+        let publisher = publisher_cid(info).or_else(|| known_publisher.map(str::to_string));
+        let ingest_kbps = info.get("kbps").and_then(|k| k.get("recv_30s")).and_then(Value::as_u64);
+
+        let json = self.get_json("/api/v1/clients/?count=10000").await?;
+        let clients = json
+            .get("clients")
+            .and_then(|c| c.as_array())
+            .ok_or_else(|| "SRS 客户端列表缺少 clients 字段".to_string())?;
+
+        let mut viewers = StreamViewers { send_kbps, stream_kbps, ingest_kbps, ..Default::default() };
+        clients
+            .iter()
+            .filter(|c| c.get("stream").and_then(Value::as_str) == Some(stream_id))
+            .filter(|c| !c.get("publish").and_then(Value::as_bool).unwrap_or(false))
+            .filter(|c| {
+                publisher.is_none()
+                    || c.get("id").and_then(Value::as_str) != publisher.as_deref()
+            })
+            .filter(|c| !c.get("id").and_then(Value::as_str).is_some_and(|id| excluded.contains(id)))
+            .for_each(|c| {
+                viewers.breakdown.add(ViewerProtocol::classify(c));
+                if let Some(id) = c.get("id").and_then(Value::as_str) {
+                    viewers.client_ids.insert(id.to_string());
+                    if let Some(kbps) = send_30s(c) {
+                        viewers.client_kbps.insert(id.to_string(), kbps);
+                    }
+                }
+            });
+        Ok(viewers)
+    }
+
+    /// 踢出指定的 SRS 客户端
+    pub async fn kick_client(&self, client_id: &str) -> Result<(), String> {
+        let url = format!("{}/api/v1/clients/{}", self.base_url, client_id);
+        let resp = self
+            .client
+            .delete(&url)
+            .send()
+            .await
+            .map_err(|e| format!("DELETE {} 失败: {}", url, e))?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("DELETE {} 返回状态码 {}", url, resp.status()))
+        }
+    }
+
+    /// 开启或关闭指定流的录制（DVR）
+    ///
+    /// 使用 SRS 的 RAW API（`rpc=update&scope=dvr`），需要在 SRS 中开启 `raw_api` 和 `allow_update`，
+    /// vhost 中需配置 `dvr { enabled on; dvr_apply none; }` 以便按流开关
+    ///
+    /// ### 参数
+    /// - `vhost`: SRS vhost
+    /// - `enabled`: `true` 开始录制，`false` 停止录制
+    pub async fn set_dvr(&self, vhost: &str, app: &str, stream: &str, enabled: bool) -> Result<(), String> {
+        let param = if enabled { "enable" } else { "disable" };
+        let path = format!(
+            "/api/v1/raw?rpc=update&scope=dvr&value={}&param={}&data={}/{}",
+            vhost, param, app, stream
+        );
+        let json = self.get_json(&path).await?;
+        match json.get("code").and_then(Value::as_i64) {
+            Some(0) => Ok(()),
+            code => Err(format!("SRS 拒绝{}录制: code={:?}", if enabled { "开启" } else { "关闭" }, code)),
+        }
+    }
+
+    /// 踢出指定流的推流端
+    ///
+    /// ### 参数
+    /// - `known_publisher`: on_publish 回调记录的推流端 client_id，优先直接踢出，
+    ///   失败时（如已重连）再按流查找
+    ///
+    /// ### 返回值
+    /// - `Ok(true)`: 已踢出推流端
+    /// - `Ok(false)`: 该流当前没有推流端
+    pub async fn kick_publisher(&self, app: &str, stream: &str, known_publisher: Option<&str>) -> Result<bool, String> {
+        if let Some(cid) = known_publisher {
+            match self.kick_client(cid).await {
+                Ok(()) => return Ok(true),
+                Err(e) => tracing::debug!("按记录的 client_id 踢出推流端失败，按流查找: {}", e),
+            }
+        }
+        match self.find_publisher(app, stream).await? {
+            Some(cid) => self.kick_client(&cid).await.map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
+
+/// 从流信息中提取正在推流的推流端 client_id
+fn publisher_cid(stream: &Value) -> Option<String> {
+    stream
+        .get("publish")
+        .filter(|p| p.get("active").and_then(Value::as_bool).unwrap_or(false))
+        .and_then(|p| p.get("cid"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// 在流列表中按 app 和流名称查找
+fn find_in<'a>(streams: &'a [Value], app: &str, stream: &str) -> Option<&'a Value> {
+    streams.iter().find(|s| {
+        s.get("app").and_then(Value::as_str) == Some(app)
+            && s.get("name").and_then(Value::as_str) == Some(stream)
+    })
+}
+
+/// 按码率和时长估算传输的字节数
+///
+/// SRS 的码率单位为 kbps（1000 bit/s）
+pub fn kbps_to_bytes(kbps: u64, elapsed: std::time::Duration) -> u64 {
+    (kbps as u128 * 1000 * elapsed.as_millis() / 8 / 1000).min(u64::MAX as u128) as u64
+}
+
+/// 读取 SRS 流或客户端信息中近 30 秒的发送码率（kbps）
+fn send_30s(info: &Value) -> Option<u64> {
+    info.get("kbps")?.get("send_30s")?.as_u64()
+}
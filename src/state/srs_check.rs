@@ -0,0 +1,122 @@
+//! # SRS 配置自检模块
+//!
+//! 启动时通过 SRS HTTP API 检查 SRS 侧的配置是否与本服务一致：
+//! - SRS API 是否可达
+//! - 期望的 vhost 是否存在
+//! - 当前推流使用的应用名是否与期望一致
+//! - HTTP 回调地址是否指向本服务的端口和路径
+//!
+//! 回调地址配置错误是"什么都不工作"类部署问题最常见的原因，
+//! 因此每个不一致之处都会单独输出警告。
+
+use super::srs_api::SrsApi;
+use crate::config::Config;
+use serde_json::Value;
+
+/// 需要指向本服务的 SRS 回调类型
+const REQUIRED_HOOKS: &[&str] = &["on_publish", "on_unpublish", "on_play", "on_stop"];
+
+/// 检查 SRS 配置
+///
+/// ### 参数
+/// - `api`: SRS HTTP API 客户端
+/// - `config`: 本服务配置
+///
+/// ### 返回值
+/// 发现的问题列表（为空表示检查通过）
+pub async fn run(api: &SrsApi, config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // 1. API 可达
+    if let Err(e) = api.get_json("/api/v1/versions").await {
+        problems.push(format!("无法访问 SRS API（{}）: {}", config.srs_api_addr(), e));
+        return problems;
+    }
+
+    // 2. vhost 存在
+    match api.get_json("/api/v1/vhosts/").await {
+        Ok(json) => {
+            let found = json
+                .get("vhosts")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .any(|v| v.get("name").and_then(Value::as_str) == Some(config.srs_vhost.as_str()));
+            if !found {
+                problems.push(format!("SRS 中不存在 vhost {}", config.srs_vhost));
+            }
+        }
+        Err(e) => problems.push(format!("获取 SRS vhost 列表失败: {}", e)),
+    }
+
+    // 3. 正在推流的应用名（没有推流时无法判断）
+    if let Ok(json) = api.get_json("/api/v1/streams/?count=1000").await {
+        let apps: Vec<&str> = json
+            .get("streams")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|s| s.get("app").and_then(Value::as_str))
+            .collect();
+        if !apps.is_empty() && !apps.contains(&config.srs_app.as_str()) {
+            problems.push(format!(
+                "SRS 当前的流使用应用 {:?}，与期望的 {} 不一致",
+                apps, config.srs_app
+            ));
+        }
+    }
+
+    // 4. HTTP 回调地址（需要 SRS 开启 raw_api）
+    let path = format!("/api/v1/raw?rpc=query&scope=vhost&vhost={}", config.srs_vhost);
+    match api.get_json(&path).await {
+        Ok(json) => problems.extend(check_hooks(&json, config.port)),
+        Err(e) => problems.push(format!(
+            "无法读取 SRS 回调配置（需在 SRS 中开启 raw_api），请手动确认回调地址: {}",
+            e
+        )),
+    }
+
+    problems
+}
+
+/// 检查 vhost 配置中的 HTTP 回调地址
+///
+/// 回调地址的主机名因部署方式不同（如 Docker 网络）无法可靠判断，只检查端口和路径
+fn check_hooks(json: &Value, port: u16) -> Vec<String> {
+    let hooks = json
+        .get("vhost")
+        .and_then(|v| v.get("http_hooks"))
+        .or_else(|| json.get("http_hooks"));
+    let Some(hooks) = hooks else {
+        return vec!["SRS vhost 未配置 http_hooks，推流和拉流不会经过本服务验证".to_string()];
+    };
+    if hooks.get("enabled").and_then(Value::as_bool) == Some(false) {
+        return vec!["SRS vhost 的 http_hooks 未启用".to_string()];
+    }
+
+    let mut problems = Vec::new();
+    for hook in REQUIRED_HOOKS {
+        let urls: Vec<&str> = match hooks.get(*hook) {
+            Some(Value::String(s)) => s.split_whitespace().collect(),
+            Some(Value::Array(list)) => list.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if urls.is_empty() {
+            problems.push(format!("SRS 未配置 {} 回调", hook));
+            continue;
+        }
+        let points_here = urls.iter().any(|u| {
+            url::Url::parse(u).is_ok_and(|u| {
+                u.port_or_known_default() == Some(port)
+                    && (u.path() == "/" || u.path() == format!("/hooks/{}", hook))
+            })
+        });
+        if !points_here {
+            problems.push(format!(
+                "SRS 的 {} 回调 {:?} 没有指向本服务（期望端口 {}，路径 / 或 /hooks/{}）",
+                hook, urls, port, hook
+            ));
+        }
+    }
+    problems
+}
@@ -0,0 +1,184 @@
+//! # SRS 回调看门狗模块
+//!
+//! 本服务的直播状态完全依赖 SRS 的 HTTP 回调。SRS 重启或回调配置丢失时，推流开始和结束
+//! 不再通知本服务，旧状态会一直保留（例如推流早已断开，观众端仍显示直播中）。
+//!
+//! 看门狗定期通过 SRS API 列出正在推流的流，与主播记录对比：
+//! - 本服务认为正在推流，但 SRS 中该流已没有推流端：漏收 `on_unpublish`
+//! - 本服务认为直播暂停，但 SRS 中该流仍在推流：漏收恢复推流的 `on_publish`
+//! - 本服务没有直播记录，但 SRS 的应用下有推流：漏收 `on_publish`
+//!
+//! 同一不一致连续出现两轮才视为确认（避免与正在途中的回调竞争），确认后输出警告并发送邮件通知。
+//! 开启自动修复（`LIVE_SERVER_SRS_WATCHDOG_HEAL`）时补做缺失的状态转换：前两种情况分别按
+//! 暂停和恢复处理；第三种情况无法验证推流密钥，只做提示。
+
+use super::AppState;
+use crate::state::notify::Alert;
+use crate::state::srs::{StreamerEvent, StreamerStatus, StreamerTransition};
+use crate::state::srs_api::ActivePublish;
+use secrecy::ExposeSecret;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 后台任务名称（用于心跳上报）
+pub const TASK_NAME: &str = "srs_watchdog";
+
+/// 同一不一致连续出现多少轮后确认
+const CONFIRM_ROUNDS: u32 = 2;
+
+/// 主播记录与 SRS 之间的不一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// 本服务认为正在推流，SRS 中该流已没有推流端
+    MissingUnpublish { app: String, stream: String },
+    /// 本服务认为直播暂停，SRS 中该流仍在推流
+    MissingResume { publish: ActivePublish },
+    /// 本服务没有直播记录，SRS 的应用下有推流
+    MissingPublish { app: String, stream: String },
+}
+
+impl Divergence {
+    /// 对比主播记录与 SRS 中正在推流的流
+    ///
+    /// ### 参数
+    /// - `status`: 主播状态
+    /// - `target`: 主播记录中的 (app, stream)
+    /// - `active`: SRS 中正在推流的流
+    /// - `watched_app`: 本服务使用的应用名（只在该应用下检查未知推流）
+    /// - `ignored`: 不需要回调的流（如诱饵流）
+    pub fn detect(
+        status: StreamerStatus,
+        target: Option<(&str, &str)>,
+        active: &[ActivePublish],
+        watched_app: &str,
+        ignored: impl Fn(&str, &str) -> bool,
+    ) -> Option<Self> {
+        let find = |app: &str, stream: &str| active.iter().find(|p| p.app == app && p.stream == stream);
+        match (status, target) {
+            (StreamerStatus::Streaming, Some((app, stream))) if find(app, stream).is_none() => {
+                Some(Self::MissingUnpublish { app: app.to_string(), stream: stream.to_string() })
+            }
+            (StreamerStatus::Pausing, Some((app, stream))) => {
+                find(app, stream).map(|p| Self::MissingResume { publish: p.clone() })
+            }
+            (StreamerStatus::Standby, _) => active
+                .iter()
+                .find(|p| p.app == watched_app && !ignored(&p.app, &p.stream))
+                .map(|p| Self::MissingPublish { app: p.app.clone(), stream: p.stream.clone() }),
+            _ => None,
+        }
+    }
+
+    /// 不一致的说明（用于日志和邮件通知）
+    fn describe(&self) -> String {
+        match self {
+            Self::MissingUnpublish { app, stream } => {
+                format!("本服务记录 {}/{} 正在推流，但 SRS 中该流已没有推流端（可能漏收 on_unpublish）", app, stream)
+            }
+            Self::MissingResume { publish } => format!(
+                "本服务记录 {}/{} 已暂停，但 SRS 中该流仍在推流（可能漏收 on_publish）",
+                publish.app, publish.stream
+            ),
+            Self::MissingPublish { app, stream } => {
+                format!("SRS 中 {}/{} 正在推流，但本服务没有直播记录（可能漏收 on_publish）", app, stream)
+            }
+        }
+    }
+}
+
+/// 启动看门狗任务
+///
+/// ### 参数
+/// - `state`: 应用状态
+/// - `interval_secs`: 检查间隔（秒，0 表示不启动）
+///
+/// ### 返回值
+/// 后台任务句柄，未启用时返回 `None`
+pub fn spawn(state: Arc<AppState>, interval_secs: u64) -> Option<JoinHandle<()>> {
+    if interval_secs == 0 {
+        return None;
+    }
+    state.health.register(TASK_NAME, interval_secs);
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        // 上一轮发现的不一致及其连续出现的轮数
+        let mut pending: Option<(Divergence, u32)> = None;
+        loop {
+            interval.tick().await;
+            state.health.beat(TASK_NAME);
+            // SRS API 不可用时由观众人数轮询任务负责告警
+            let Ok(active) = state.srs_api.active_publishes().await else {
+                pending = None;
+                continue;
+            };
+            let config = state.config();
+            let divergence = {
+                let streamer = state.srs_db.streamer.read();
+                Divergence::detect(
+                    streamer.streamer.status,
+                    streamer.get_stream_target(),
+                    &active,
+                    &config.srs_app,
+                    |app, stream| config.decoy_stream.as_ref().is_some_and(|d| d.matches(app, stream)),
+                )
+            };
+            let Some(divergence) = divergence else {
+                pending = None;
+                continue;
+            };
+            let rounds = match pending.take() {
+                Some((last, rounds)) if last == divergence => rounds + 1,
+                _ => 1,
+            };
+            if rounds != CONFIRM_ROUNDS {
+                pending = Some((divergence, rounds));
+                continue;
+            }
+            // 确认后只处理一次；状态仍未恢复一致时重新计数
+            let description = divergence.describe();
+            tracing::warn!("SRS 回调看门狗: {}，请检查 SRS 是否重启或 http_hooks 配置是否丢失", description);
+            if let Some(notifier) = &state.notifier {
+                notifier.notify(Alert::CallbacksMissing, description);
+            }
+            if config.srs_watchdog_heal {
+                heal(&state, divergence);
+            }
+        }
+    }))
+}
+
+/// 补做缺失的状态转换
+fn heal(state: &AppState, divergence: Divergence) {
+    let mut streamer = state.srs_db.streamer.write();
+    let (event, publisher, notice) = match divergence {
+        Divergence::MissingUnpublish { .. } => {
+            (StreamerEvent::Unpublish, None, "直播暂停 — 正在等待推流端重新连接")
+        }
+        Divergence::MissingResume { publish } => {
+            let (Some(ip), Some(secret)) = (
+                streamer.streamer.ip.clone(),
+                streamer.streamer.secret.as_ref().map(|s| s.expose_secret().to_string()),
+            ) else {
+                return;
+            };
+            let resume = StreamerEvent::Resume { ip, secret, app: publish.app, stream: publish.stream };
+            (resume, Some(publish.client_id), "直播已恢复")
+        }
+        Divergence::MissingPublish { .. } => {
+            tracing::warn!("SRS 回调看门狗: 无法验证未知推流的推流密钥，不做自动修复");
+            return;
+        }
+    };
+    let transition = streamer.apply(&mut state.srs_db.clients.write(), event);
+    if let StreamerTransition::Applied { event, .. } = transition {
+        if publisher.is_some() {
+            streamer.set_publisher_client_id(publisher);
+        }
+        tracing::info!("SRS 回调看门狗: 已自动修复，{}", notice);
+        if let Some(event) = event {
+            state.chat_db.inner.write().active_mut().add_system(notice, false);
+            state.events.publish(event);
+        }
+    }
+}
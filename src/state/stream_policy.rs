@@ -0,0 +1,210 @@
+//! # 推流目标策略模块
+//!
+//! SRS 回调中的 app / stream 由推流端决定，不能直接信任：
+//! - 名称只允许字母、数字和 `-_.`，且长度受限
+//! - app 须在允许列表内（默认只允许 `LIVE_SERVER_SRS_APP`），stream 可按通配模式限制
+//! - 拼装播放地址时统一做 URL 编码，不再直接插值
+//!
+//! 推流地址中还可携带本场直播的初始设置，见 `PublishOptions`。
+
+use super::chat::MAX_SLOW_MODE_SECS;
+use super::srs::EntryMode;
+use crate::config::Config;
+use crate::domain::SessionId;
+use std::collections::HashMap;
+use url::form_urlencoded;
+
+/// app / stream 名称的最大长度
+const MAX_NAME_LEN: usize = 64;
+
+/// 推流参数中直播间名称的最大长度（字符）
+const MAX_STREAM_NAME_LEN: usize = 100;
+
+/// 推流参数绑定主播会话时，会话 ID 的最小长度（防止被猜中而冒充主播）
+pub const MIN_PUSH_SESSION_LEN: usize = 16;
+
+/// 推流目标被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamRejection {
+    /// 名称为空、过长或含有不允许的字符
+    InvalidName,
+    /// app 不在允许列表内
+    AppNotAllowed,
+    /// stream 不匹配任何允许的模式
+    StreamNotAllowed,
+}
+
+impl StreamRejection {
+    /// 日志中使用的说明
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::InvalidName => "名称不合法",
+            Self::AppNotAllowed => "app 不在允许列表内",
+            Self::StreamNotAllowed => "stream 不匹配允许的模式",
+        }
+    }
+}
+
+/// 名称是否只包含安全字符（字母、数字、`-`、`_`、`.`，且不以 `.` 开头）
+pub fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with('.')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// 简单通配匹配，`*` 匹配任意长度（含空）的字符
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut remain) = name.strip_prefix(head) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let tail = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remain.find(part) {
+            Some(pos) => remain = &remain[pos + part.len()..],
+            None => return false,
+        }
+    }
+    remain.len() >= tail.len() && remain.ends_with(tail)
+}
+
+/// 检查 app 名称（转码版本也须通过此检查）
+///
+/// ### 返回值
+/// 未配置 `publish_apps` 时只允许 `srs_app`
+pub fn check_app(config: &Config, app: &str) -> Result<(), StreamRejection> {
+    if !is_safe_name(app) {
+        return Err(StreamRejection::InvalidName);
+    }
+    let allowed = if config.publish_apps.is_empty() {
+        app == config.srs_app
+    } else {
+        config.publish_apps.iter().any(|p| glob_match(p, app))
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(StreamRejection::AppNotAllowed)
+    }
+}
+
+/// 检查主推流的 stream 名称
+///
+/// ### 返回值
+/// 未配置 `publish_streams` 时只检查名称字符
+pub fn check_stream(config: &Config, stream: &str) -> Result<(), StreamRejection> {
+    if !is_safe_name(stream) {
+        return Err(StreamRejection::InvalidName);
+    }
+    if config.publish_streams.is_empty() || config.publish_streams.iter().any(|p| glob_match(p, stream)) {
+        Ok(())
+    } else {
+        Err(StreamRejection::StreamNotAllowed)
+    }
+}
+
+/// 拼装流 URI（`app=...&stream=...`，已 URL 编码）
+pub fn stream_query(app: &str, stream: &str) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .append_pair("app", app)
+        .append_pair("stream", stream)
+        .finish()
+}
+
+/// 对播放地址中的路径段做 URL 编码（非保留字符一律转为 `%XX`）
+pub fn path_segment(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for b in name.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+// ============================================================================
+// 推流参数
+// ============================================================================
+
+/// 推流地址中携带的本场直播初始设置
+///
+/// 例如 `?secret=...&public=true&entry=confirm&chat=off&slowmode=10&record=true&name=...&session_id=...`，
+/// 主播只需在 OBS 的推流地址中配置即可，无需开播后再逐项设置。
+/// 无法识别的取值按未携带处理
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublishOptions {
+    /// 公开模式（`public=true|false`，未携带时沿用当前设置）
+    pub public: Option<bool>,
+    /// 观众入场方式（`entry=quiz|confirm`，默认答题）
+    pub entry: EntryMode,
+    /// 是否关闭观众发言（`chat=off`，主播和联合主持不受影响）
+    pub chat_closed: bool,
+    /// 慢速模式间隔秒数（`slowmode=10`，上限同聊天室设置）
+    pub slow_mode_secs: Option<u64>,
+    /// 开播即开启录制（`record=true`）
+    pub record: bool,
+    /// 直播间名称（`name=...`，按 UTF-8 做 URL 解码，去掉控制字符）
+    pub name: Option<String>,
+    /// 主播会话（`session_id=...`），以该会话 ID 打开网页即获得主播权限，无需再输入密钥
+    pub session_id: Option<SessionId>,
+}
+
+impl PublishOptions {
+    /// 从推流参数中解析
+    ///
+    /// ### 参数
+    /// - `queries`: on_publish 回调中解析出的查询参数（值未解码）
+    pub fn parse(queries: &HashMap<String, String>) -> Self {
+        let flag = |key: &str| queries.get(key).and_then(|v| parse_flag(v));
+        Self {
+            public: flag("public"),
+            entry: queries
+                .get("entry")
+                .and_then(|v| EntryMode::parse(&v.to_lowercase()))
+                .unwrap_or_default(),
+            chat_closed: flag("chat") == Some(false),
+            slow_mode_secs: queries
+                .get("slowmode")
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(|secs| secs.min(MAX_SLOW_MODE_SECS)),
+            record: flag("record").unwrap_or(false),
+            name: queries
+                .get("name")
+                .map(|raw| decode_value(raw))
+                .map(|name| {
+                    name.trim()
+                        .chars()
+                        .filter(|c| !c.is_control())
+                        .take(MAX_STREAM_NAME_LEN)
+                        .collect::<String>()
+                })
+                .filter(|name| !name.is_empty()),
+            session_id: queries.get("session_id").and_then(|s| SessionId::parse(s).ok()),
+        }
+    }
+}
+
+/// 解析开关取值：`true|1|on|yes` 或 `false|0|off|no`
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "on" | "yes" => Some(true),
+        "false" | "0" | "off" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// 解码 URL 编码的参数值（`%XX` 与 `+`，按 UTF-8 解码，非法字节替换为 `U+FFFD`）
+fn decode_value(raw: &str) -> String {
+    form_urlencoded::parse(format!("v={}", raw).as_bytes())
+        .next()
+        .map(|(_, value)| value.into_owned())
+        .unwrap_or_default()
+}
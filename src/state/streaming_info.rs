@@ -1,12 +1,80 @@
-// ============================================================================
-// 流信息结构体
-// ============================================================================
+//! # 流信息模块
+//!
+//! 后台定期轮询 SRS HTTP API，维护当前直播的观众人数（按播放协议分类）和上行带宽占用，
+//! 供 `/streaming_info`（观众端状态组件）、聊天室人数展示、带宽准入控制和 MQTT 状态发布共用。
+//! 同时按 SRS 报告的码率估算每名观众和每路流的累计下行流量（供关注宽带流量上限的自建用户参考）。
+//! 推流端仍在推流但上传码率持续为零时（编码器只发送空白画面），提示主播可能已离开。
+//! SRS API 不可用时指数退避重试，期间保留上次的人数并标记为过期。
 
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use parking_lot::{RwLock};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
 use tokio::task::JoinHandle;
 
+use crate::config::{AudienceVisibility, Intervals};
+use crate::state::health::Health;
+use crate::state::srs::SrsDatabase;
+use crate::state::metrics::Metrics;
+use crate::state::notify::{Alert, Notifier};
+use crate::state::preview::PreviewTokens;
+use crate::state::srs_api::{self, SrsApi, StreamViewers, ViewerBreakdown};
+
+/// 后台任务名称（用于心跳上报）
+pub const TASK_NAME: &str = "srs_poll";
+
+/// SRS API 连续不可用超过此时长时发送邮件通知
+const SRS_OUTAGE_ALERT: Duration = Duration::from_secs(300);
+
+/// 人数分档阈值（从高到低）
+const AUDIENCE_BUCKETS: [i64; 5] = [1000, 500, 100, 50, 10];
+
+/// 对外展示的人数
+///
+/// 序列化为数字（精确）或字符串（分档，如 "50+"）
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum AudienceCount {
+    /// 精确人数（-1 表示未知）
+    Exact(i64),
+    /// 分档人数
+    Bucket(String),
+}
+
+impl AudienceCount {
+    /// 按可见性设置展示人数
+    ///
+    /// ### 返回值
+    /// - `None`: 人数被隐藏
+    /// - 未知人数（负数）始终原样返回
+    pub fn present(num: i64, visibility: AudienceVisibility) -> Option<Self> {
+        match visibility {
+            _ if num < 0 => Some(Self::Exact(num)),
+            AudienceVisibility::Exact => Some(Self::Exact(num)),
+            AudienceVisibility::Hidden => None,
+            AudienceVisibility::Bucketed => Some(Self::Bucket(
+                AUDIENCE_BUCKETS
+                    .iter()
+                    .find(|b| num >= **b)
+                    .map(|b| format!("{}+", b))
+                    .unwrap_or_else(|| format!("<{}", AUDIENCE_BUCKETS[AUDIENCE_BUCKETS.len() - 1])),
+            )),
+        }
+    }
+}
+
+/// 单路流的下行流量估算
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamEgress {
+    /// 最近一轮统计的发送码率（kbps）
+    pub kbps: u64,
+    /// 估算的累计发送字节数
+    pub bytes: u64,
+}
+
 /// 流信息统计
 ///
 /// 用于从 SRS API 获取观众人数信息
@@ -14,12 +82,47 @@ use tokio::task::JoinHandle;
 pub struct StreamingInfoInner {
     /// 当前观众人数（-1 表示未知）
     pub audiences_num: i32,
+    /// 最近一次成功获取人数的时间
+    pub updated_at: Option<DateTime<Utc>>,
+    /// 最近一次获取是否失败（此时人数为上次成功获取的值）
+    pub stale: bool,
+    /// 连续失败次数
+    pub failures: u32,
+    /// 本轮连续失败开始的时刻（未失败时为 `None`）
+    pub failing_since: Option<Instant>,
+    /// 按播放协议分类的观众人数
+    pub protocols: ViewerBreakdown,
+    /// SRS 当前的上行带宽占用（kbps，未推流或从未获取成功时为 `None`）
+    pub send_kbps: Option<u64>,
+    /// 各路流（`app/stream`）自服务启动以来的下行流量估算
+    pub egress: BTreeMap<String, StreamEgress>,
+    /// 开始估算流量的时间（服务启动时间）
+    pub egress_since: DateTime<Utc>,
+    /// 推流端近 30 秒的上传码率（kbps，未推流时为 `None`）
+    pub ingest_kbps: Option<u64>,
+    /// 推流码率开始持续为零的时刻（推流正常或未推流时为 `None`）
+    pub ingest_idle_since: Option<Instant>,
+    /// 是否已判定主播离开
+    pub away: bool,
 }
 
 impl StreamingInfoInner {
     /// 创建新的流信息对象
     pub fn new() -> Self {
-        Self { audiences_num: 0 }
+        Self {
+            audiences_num: 0,
+            updated_at: None,
+            stale: false,
+            failures: 0,
+            failing_since: None,
+            protocols: ViewerBreakdown::default(),
+            send_kbps: None,
+            egress: BTreeMap::new(),
+            egress_since: Utc::now(),
+            ingest_kbps: None,
+            ingest_idle_since: None,
+            away: false,
+        }
     }
 
     /// 获取当前观众人数
@@ -27,79 +130,256 @@ impl StreamingInfoInner {
         self.audiences_num
     }
 
-    pub fn set_audiences_num(&mut self, num: i32) {
-        self.audiences_num = num;
+    /// 记录一次成功获取，清除过期标记和失败计数
+    pub fn record_success(&mut self, protocols: ViewerBreakdown, send_kbps: Option<u64>) {
+        self.audiences_num = protocols.total() as i32;
+        self.protocols = protocols;
+        self.send_kbps = send_kbps;
+        self.updated_at = Some(Utc::now());
+        self.stale = false;
+        self.failures = 0;
+        self.failing_since = None;
+    }
+
+    /// 按各路流的码率累加下行流量估算
+    ///
+    /// ### 参数
+    /// - `stream_kbps`: `app/stream` -> 近 30 秒的发送码率（kbps）
+    /// - `elapsed`: 距上一轮成功统计的时长
+    pub fn record_egress(&mut self, stream_kbps: &HashMap<String, u64>, elapsed: Duration) {
+        for egress in self.egress.values_mut() {
+            egress.kbps = 0;
+        }
+        for (stream, &kbps) in stream_kbps {
+            let egress = self.egress.entry(stream.clone()).or_default();
+            egress.kbps = kbps;
+            egress.bytes = egress.bytes.saturating_add(srs_api::kbps_to_bytes(kbps, elapsed));
+        }
+    }
+
+    /// 记录推流端的上传码率
+    ///
+    /// ### 参数
+    /// - `kbps`: 当前直播流近 30 秒的接收码率（未推流时为 `None`）
+    pub fn record_ingest(&mut self, kbps: Option<u64>) {
+        self.ingest_kbps = kbps;
+        if kbps == Some(0) {
+            self.ingest_idle_since.get_or_insert_with(Instant::now);
+        } else {
+            self.ingest_idle_since = None;
+        }
+    }
+
+    /// 判定主播是否已离开
+    ///
+    /// ### 参数
+    /// - `after`: 码率持续为零超过此时长判定为离开（为零时不检测）
+    ///
+    /// ### 返回值
+    /// 判定结果发生变化时返回新的结果
+    pub fn evaluate_away(&mut self, after: Duration) -> Option<bool> {
+        let away = !after.is_zero() && self.ingest_idle_since.is_some_and(|since| since.elapsed() >= after);
+        (away != self.away).then(|| {
+            self.away = away;
+            away
+        })
+    }
+
+    /// 推流码率已持续为零的秒数（未判定为离开时为 `None`）
+    pub fn away_secs(&self) -> Option<u64> {
+        self.ingest_idle_since
+            .filter(|_| self.away)
+            .map(|since| since.elapsed().as_secs())
+    }
+
+    /// 记录一次获取失败
+    ///
+    /// 保留上次成功获取的人数并标记为过期；从未成功过时人数为未知（-1）
+    pub fn record_failure(&mut self) {
+        if self.updated_at.is_none() {
+            self.audiences_num = -1;
+        }
+        self.stale = true;
+        self.failures = self.failures.saturating_add(1);
+        self.failing_since.get_or_insert_with(Instant::now);
+    }
+
+    /// 上行带宽是否已达到上限
+    ///
+    /// 带宽数据过期（SRS API 不可用）时不做限制，避免因统计缺失拒绝所有新观众
+    ///
+    /// ### 参数
+    /// - `ceiling_kbps`: 带宽上限（0 表示不限制）
+    pub fn bandwidth_exceeded(&self, ceiling_kbps: u64) -> bool {
+        ceiling_kbps > 0 && !self.stale && self.send_kbps.is_some_and(|kbps| kbps >= ceiling_kbps)
+    }
+
+    /// 下一次轮询前的等待时间
+    ///
+    /// 连续失败时从轮询间隔起指数退避，直到最大退避间隔
+    ///
+    /// ### 参数
+    /// - `poll_secs`: 正常轮询间隔（秒）
+    /// - `max_backoff_secs`: 最大退避间隔（秒）
+    pub fn next_delay(&self, poll_secs: u64, max_backoff_secs: u64) -> Duration {
+        let secs = match self.failures {
+            0 => poll_secs,
+            n => poll_secs
+                .saturating_mul(1u64 << (n - 1).min(16))
+                .min(max_backoff_secs),
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+impl Default for StreamingInfoInner {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// 流信息（`AppState` 中共享的句柄）
 #[derive(Clone)]
 pub struct StreamingInfo {
+    /// 流信息统计
     pub inner: Arc<RwLock<StreamingInfoInner>>,
+    /// 主播预览播放端登记（统计人数时排除）
+    preview: Arc<PreviewTokens>,
 }
 
 impl StreamingInfo {
-    pub fn new() -> Self {
+    /// 创建空的流信息
+    ///
+    /// ### 参数
+    /// - `preview`: 主播预览令牌与预览播放端（与 `AppState::preview` 共享）
+    pub fn new(preview: Arc<PreviewTokens>) -> Self {
         Self {
             inner: Arc::new(RwLock::new(StreamingInfoInner::new())),
+            preview,
         }
     }
 
     /// 从 SRS API 获取观众人数
     ///
     /// ### 参数
-    /// - `srs_api_url`: SRS API 地址（如 http://localhost:1985）
+    /// - `srs_api`: SRS HTTP API 客户端
+    /// - `srs_db`: SRS 数据库（用于获取当前推流的 app 和 stream）
+    /// - `metrics`: 运行指标（同步各协议观众数和当前直播场次）
+    /// - `intervals`: 轮询与退避间隔
+    /// - `health`: 健康状态（每轮上报心跳）
+    /// - `notifier`: 邮件通知器（SRS API 持续不可用时通知）
     ///
     /// ### 行为说明
-    /// 1. 请求 SRS 的 `/api/v1/clients/` 接口
-    /// 2. 获取当前连接的客户端数量
-    /// 3. 减去 1（排除推流端）得到观众人数
-    pub fn tick(self, srs_api_url: String) -> JoinHandle<()> {
-        let api_url = format!("http://{}/api/v1/clients/", srs_api_url);
-
+    /// 1. 未在推流时观众人数为 0，不请求 SRS
+    /// 2. 推流中时按当前流统计拉流客户端数（排除推流端和其他流的客户端），并按播放协议分类
+    /// 3. SRS API 不可用时指数退避重试，期间保留上次的人数并标记为过期，
+    ///    只在首次失败和恢复时输出日志
+    /// 4. 统计成功时按 SRS client_id 与观众记录对账，没有 on_stop 的观众转为暂离
+    /// 5. 同时记录 SRS 全部流的发送码率，供带宽准入控制使用；按码率和距上一轮成功统计的时长
+    ///    （不超过最大退避间隔）累加每名观众和每路流的下行流量估算
+    /// 6. SRS API 连续不可用超过 5 分钟时发送邮件通知
+    /// 7. 记录推流端上传码率持续为零的起始时刻，由定期清理任务判定主播是否离开
+    /// 8. 主播的预览播放端不计入观众人数
+    pub fn tick(
+        self,
+        srs_api: SrsApi,
+        srs_db: SrsDatabase,
+        metrics: Arc<Metrics>,
+        intervals: Intervals,
+        health: Arc<Health>,
+        notifier: Option<Arc<Notifier>>,
+    ) -> JoinHandle<()> {
+        // 退避期间心跳间隔会拉长，按最大退避间隔判断存活
+        health.register(TASK_NAME, intervals.srs_poll_max_backoff_secs);
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
-
-            // 禁用代理，避免本地请求被系统代理拦截
-            let client = reqwest::Client::builder()
-                .no_proxy()
-                .build()
-                .unwrap();
-
+            // 上一轮成功统计的时刻（用于估算流量），未推流时清空
+            let mut last_fetched: Option<Instant> = None;
             loop {
-                interval.tick().await;
-
-                let mut new_num = -1;
-                match client.get(&api_url).send().await {
-                    Ok(resp) => {
-                        if resp.status().is_success() {
-                            if let Ok(json) = resp.json::<serde_json::Value>().await {
-                                if let Some(clients) =
-                                    json.get("clients").and_then(|c| c.as_array())
-                                {
-                                    // 减去 1 排除推流端
-                                    new_num = clients.len().saturating_sub(1) as i32;
-                                } else {
-                                    tracing::warn!(
-                                    "GET from {}, received response and status is success, but response has no key\"clients\"",
-                                    api_url
+                health.beat(TASK_NAME);
+                let (target, session_id, publisher) = {
+                    let db = srs_db.streamer.read();
+                    (
+                        db.get_stream_target()
+                            .map(|(app, stream)| (app.to_string(), stream.to_string())),
+                        db.get_stream_session_id().map(str::to_string),
+                        db.publisher_client_id().map(str::to_string),
+                    )
+                };
+                // 直播结束后同步清除指标中的场次
+                metrics.set_stream_session(session_id);
+                let fetched_at = Instant::now();
+                let result = match &target {
+                    Some((app, stream)) => srs_api.count_viewers(app, stream, publisher.as_deref(), &self.preview.clients()).await,
+                    None => Ok(StreamViewers::default()),
+                };
+                let elapsed = last_fetched
+                    .map(|at| fetched_at.duration_since(at))
+                    .unwrap_or_default()
+                    .min(Duration::from_secs(intervals.srs_poll_max_backoff_secs));
+                if target.is_none() {
+                    last_fetched = None;
+                }
+                if let (Some(_), Ok(viewers)) = (&target, &result) {
+                    last_fetched = Some(fetched_at);
+                    let mut clients = srs_db.clients.write();
+                    let rested = clients.reconcile_srs_clients(&viewers.client_ids, fetched_at);
+                    if rested > 0 {
+                        tracing::debug!("对账: {} 名观众已不在 SRS 中，转为暂离", rested);
+                    }
+                    clients.record_bandwidth(&viewers.client_kbps, elapsed);
+                }
+
+                let delay = {
+                    let mut inner = self.inner.write();
+                    match result {
+                        Ok(StreamViewers { breakdown: protocols, send_kbps, stream_kbps, ingest_kbps, .. }) => {
+                            if inner.failures > 0 {
+                                tracing::info!(
+                                    "SRS API 已恢复（此前连续失败 {} 次）",
+                                    inner.failures
                                 );
+                            }
+                            let send_kbps = target.is_some().then_some(send_kbps);
+                            inner.record_success(protocols, send_kbps);
+                            inner.record_egress(&stream_kbps, elapsed);
+                            inner.record_ingest(ingest_kbps);
+                            metrics.set_viewers(protocols);
+                            metrics.set_send_kbps(send_kbps);
+                        }
+                        Err(e) => {
+                            inner.record_failure();
+                            if inner.failures == 1 {
+                                tracing::warn!("获取观众人数失败，将退避重试: {}", e);
+                            } else {
+                                tracing::debug!("获取观众人数失败（连续 {} 次）: {}", inner.failures, e);
+                            }
+                            let outage = inner.failing_since.map(|since| since.elapsed());
+                            if let (Some(notifier), Some(outage)) = (&notifier, outage) {
+                                if outage >= SRS_OUTAGE_ALERT {
+                                    notifier.notify(
+                                        Alert::SrsUnreachable,
+                                        format!(
+                                            "SRS API 已连续 {} 分钟不可用（失败 {} 次），最近一次错误：{}",
+                                            outage.as_secs() / 60,
+                                            inner.failures,
+                                            e
+                                        ),
+                                    );
                                 }
                             }
-                        } else {
-                            tracing::warn!(
-                                "GET from {}, received response but status is not success",
-                                api_url
-                            );
                         }
                     }
-                    Err(e) => {
-                        tracing::warn!("GET from {} error: {}", api_url, e);
-                    }
+                    inner.next_delay(intervals.srs_poll_secs, intervals.srs_poll_max_backoff_secs)
                 };
 
-                let mut inner = self.inner.write();
-                inner.set_audiences_num(new_num);
+                tokio::time::sleep(delay).await;
             }
         })
     }
 }
+
+impl Default for StreamingInfo {
+    fn default() -> Self {
+        Self::new(Arc::new(PreviewTokens::new()))
+    }
+}
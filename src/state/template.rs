@@ -0,0 +1,267 @@
+//! # 题目模板模块
+//!
+//! 通过配置中的题目模板驱动答题，使歌单、影视问答等非卡池数据也能用于出题。
+//!
+//! ## 模板文件格式
+//! ```json
+//! {
+//!   "dataset": "playlist.json",
+//!   "templates": [
+//!     {"question": "第{index}首歌的歌手是谁?", "answer": "{artist}"},
+//!     {"question": "《{title}》是哪一年发行的?", "answer": "{released|year}"},
+//!     {"question": "《{title}》是谁唱的?", "answer": "{artist}", "type": "artist",
+//!      "choices": ["{artist}", "{alt.0}", "{alt.1}"]}
+//!   ]
+//! }
+//! ```
+//!
+//! - `dataset`: 数据集文件路径（相对路径以模板文件所在目录为基准），内容为 JSON 对象数组
+//! - `{字段}`: 从记录中取值，支持 `a.b.0` 形式的嵌套路径
+//! - `{字段|提取器}`: 对取到的值做进一步处理，支持的提取器见 `apply_extractor`
+//! - `type`（可选）: 题目类型，随结构化题目返回给前端（默认 `template`）
+//! - `answer_kind`（可选）: 答案形式 `number` / `text` / `choice`，缺省时按答案文本推断
+//! - `choices`（可选）: 选项模板列表，给出时答案形式为 `choice`，渲染失败的选项被跳过，顺序随机打乱
+
+use super::banner::{AnswerKind, BannerQuestion, QuestionMeta};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// 单个题目模板
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuestionTemplate {
+    /// 问题模板
+    pub question: String,
+    /// 答案模板
+    pub answer: String,
+    /// 题目类型
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+    /// 答案形式（缺省时按答案文本推断）
+    #[serde(default)]
+    pub answer_kind: Option<AnswerKind>,
+    /// 选项模板
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+/// 模板文件内容
+#[derive(Debug, Deserialize)]
+struct TemplateFile {
+    /// 数据集文件路径
+    dataset: String,
+    /// 题目模板列表
+    templates: Vec<QuestionTemplate>,
+}
+
+/// 模板题库
+///
+/// 将数据集中的每条记录与题目模板组合生成问题
+pub struct TemplateQuiz {
+    /// 数据集记录
+    records: Vec<Value>,
+    /// 题目模板
+    templates: Vec<QuestionTemplate>,
+}
+
+impl TemplateQuiz {
+    /// 从模板文件加载模板题库
+    ///
+    /// ### 参数
+    /// - `path`: 模板文件路径
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let file: TemplateFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let dataset_path = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(&file.dataset);
+        let records: Vec<Value> = serde_json::from_str(&fs::read_to_string(dataset_path)?)?;
+
+        if file.templates.is_empty() {
+            return Err("题目模板列表为空".into());
+        }
+        Ok(Self {
+            records,
+            templates: file.templates,
+        })
+    }
+
+    /// 数据集记录数量
+    pub fn pool_size(&self) -> usize {
+        self.records.len()
+    }
+
+    /// 获取随机题目，尽量避开指定的题目
+    ///
+    /// ### 参数
+    /// - `exclude`: 需要避开的题目文本
+    ///
+    /// ### 注意事项
+    /// 记录缺少模板所需字段时会重新抽取，最多 16 次
+    pub fn random_question_excluding(&self, exclude: &[String]) -> BannerQuestion {
+        let mut rng = rand::thread_rng();
+        let mut fallback = None;
+        if !self.records.is_empty() {
+            for _ in 0..16 {
+                let idx = rng.gen_range(0..self.records.len());
+                let template = &self.templates[rng.gen_range(0..self.templates.len())];
+                let Some(qa) = self.render(template, idx) else {
+                    continue;
+                };
+                if !exclude.contains(&qa.question) {
+                    return qa;
+                }
+                fallback = Some(qa);
+            }
+        }
+        fallback.unwrap_or_else(|| ("No questions available".to_string(), "N/A".to_string()).into())
+    }
+
+    /// 用指定记录渲染模板
+    ///
+    /// ### 返回值
+    /// 任一字段缺失或答案为空时返回 `None`
+    fn render(&self, template: &QuestionTemplate, idx: usize) -> Option<BannerQuestion> {
+        let record = &self.records[idx];
+        let question = render(&template.question, record, idx)?;
+        let answer = render(&template.answer, record, idx)?;
+        if answer.trim().is_empty() {
+            return None;
+        }
+        // 选项顺序随机打乱，避免正确答案总在固定位置
+        let mut choices: Vec<String> = template
+            .choices
+            .iter()
+            .filter_map(|choice| render(choice, record, idx))
+            .collect();
+        choices.shuffle(&mut rand::thread_rng());
+        let answer_kind = if choices.is_empty() {
+            template
+                .answer_kind
+                .filter(|kind| *kind != AnswerKind::Choice)
+                .unwrap_or_else(|| AnswerKind::infer(&answer))
+        } else {
+            AnswerKind::Choice
+        };
+        Some(BannerQuestion {
+            question,
+            answer,
+            source: None,
+            meta: Some(QuestionMeta {
+                kind: template.kind.clone().unwrap_or_else(|| "template".to_string()),
+                answer_kind,
+                choices: (!choices.is_empty()).then_some(choices),
+            }),
+        })
+    }
+}
+
+// ============================================================================
+// 模板渲染
+// ============================================================================
+
+/// 渲染模板字符串
+///
+/// ### 参数
+/// - `template`: 模板字符串
+/// - `record`: 数据记录
+/// - `idx`: 记录在数据集中的序号（从 0 开始）
+///
+/// ### 特殊字段
+/// - `{index}`: 记录自身没有 `index` 字段时，取序号 + 1
+/// - `{{` / `}}`: 输出字面量花括号
+///
+/// ### 返回值
+/// 任一字段缺失或模板语法错误时返回 `None`
+pub fn render(template: &str, record: &Value, idx: usize) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut expr = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        ch => expr.push(ch),
+                    }
+                }
+                let (path, extractor) = match expr.split_once('|') {
+                    Some((p, e)) => (p.trim(), Some(e.trim())),
+                    None => (expr.trim(), None),
+                };
+                let value = match lookup(record, path) {
+                    Some(v) => value_to_string(v)?,
+                    None if path == "index" => (idx + 1).to_string(),
+                    None => return None,
+                };
+                match extractor {
+                    Some(e) => out.push_str(&apply_extractor(&value, e)?),
+                    None => out.push_str(&value),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// 按 `a.b.0` 形式的路径在记录中取值
+fn lookup<'a>(record: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(record, |v, key| match v {
+        Value::Array(arr) => arr.get(key.parse::<usize>().ok()?),
+        _ => v.get(key),
+    })
+}
+
+/// 将 JSON 值转换为文本（只支持字符串、数字和布尔值）
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// 对字段值应用提取器
+///
+/// ### 支持的提取器
+/// - `year` / `month` / `day` / `hour`: 从 `YYYY-MM-DD[ HH:MM:SS]` 格式的时间中提取对应部分
+/// - `len`: 字符数
+/// - `first` / `last`: 第一个/最后一个字符
+fn apply_extractor(value: &str, extractor: &str) -> Option<String> {
+    let datetime = || {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+            })
+    };
+    match extractor {
+        "year" => datetime().map(|t| t.year().to_string()),
+        "month" => datetime().map(|t| t.month().to_string()),
+        "day" => datetime().map(|t| t.day().to_string()),
+        "hour" => datetime().map(|t| t.hour().to_string()),
+        "len" => Some(value.chars().count().to_string()),
+        "first" => value.chars().next().map(String::from),
+        "last" => value.chars().last().map(String::from),
+        _ => None,
+    }
+}
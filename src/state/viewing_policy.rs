@@ -0,0 +1,86 @@
+//! # 观看时段与地区限制模块
+//!
+//! 新观众连接时检查：
+//! - 观看时段（`LIVE_SERVER_VIEWING_WINDOWS`，服务器本地时间）：时段外不接纳新观众，告知下一次开放时间
+//! - 所在国家/地区（由反向代理通过 `LIVE_SERVER_COUNTRY_HEADER` 指定的请求头提供）：
+//!   不在允许列表中或在拒绝列表中时不接纳
+//!
+//! 已进入的观众不受影响。本服务不内置 IP 地理位置数据库，地区只能由前置的反向代理 / CDN 识别。
+
+use crate::config::{Config, ViewingWindow};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDateTime, TimeZone};
+
+/// 反向代理无法识别地区时使用的代码（Cloudflare 为 `XX`，Tor 出口为 `T1`）
+const UNKNOWN_COUNTRIES: &[&str] = &["XX", "T1"];
+
+/// 拒绝新观众进入的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewingDenial {
+    /// 不在观看时段内
+    OutsideWindow {
+        /// 下一次开放的时间
+        next_open: Option<DateTime<Local>>,
+    },
+    /// 所在国家/地区不允许进入
+    Region {
+        /// 国家/地区代码（未知时为 `None`）
+        country: Option<String>,
+    },
+}
+
+/// 检查新观众是否可以进入
+///
+/// ### 参数
+/// - `config`: 当前配置
+/// - `headers`: 请求头（用于读取国家/地区代码）
+/// - `now`: 当前服务器本地时间
+///
+/// ### 返回值
+/// 允许进入时返回 `None`
+pub fn check(config: &Config, headers: &HeaderMap, now: DateTime<Local>) -> Option<ViewingDenial> {
+    let windows = &config.viewing_windows;
+    if !windows.is_empty() && !windows.iter().any(|w| w.contains(now.time())) {
+        return Some(ViewingDenial::OutsideWindow { next_open: next_opening(windows, now) });
+    }
+
+    let header = config.country_header.as_deref()?;
+    if config.country_allow.is_empty() && config.country_deny.is_empty() {
+        return None;
+    }
+    let country = headers
+        .get(header)
+        .and_then(|v| v.to_str().ok())
+        .map(|c| c.trim().to_ascii_uppercase())
+        .filter(|c| !c.is_empty() && !UNKNOWN_COUNTRIES.contains(&c.as_str()));
+    let allowed = match &country {
+        Some(code) => {
+            (config.country_allow.is_empty() || config.country_allow.contains(code))
+                && !config.country_deny.contains(code)
+        }
+        // 地区未知：只有设置了允许列表时才拒绝
+        None => config.country_allow.is_empty(),
+    };
+    (!allowed).then_some(ViewingDenial::Region { country })
+}
+
+/// 计算下一次进入观看时段的时间
+fn next_opening(windows: &[ViewingWindow], now: DateTime<Local>) -> Option<DateTime<Local>> {
+    windows
+        .iter()
+        .filter_map(|w| {
+            let day = if w.start > now.time() { now.date_naive() } else { now.date_naive() + Duration::days(1) };
+            local_time(day.and_time(w.start))
+        })
+        .min()
+}
+
+/// 将本地日期时间转换为带时区的时间
+///
+/// 夏令时切换导致该时刻有两个对应时间时取较早者，不存在时顺延一小时
+fn local_time(naive: NaiveDateTime) -> Option<DateTime<Local>> {
+    match Local.from_local_datetime(&naive) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => Some(t),
+        LocalResult::None => Local.from_local_datetime(&(naive + Duration::hours(1))).earliest(),
+    }
+}
@@ -0,0 +1,441 @@
+//! # 零停机升级模块
+//!
+//! 收到 SIGUSR2 时，当前进程把监听套接字和运行状态交给新版本的进程后退出。
+//! 监听套接字在整个过程中保持打开，期间到达的连接在内核队列中等待新进程接受，
+//! SRS 回调、观众拉流鉴权和推流端的连接都不会中断，直播进行中也可以升级。
+//!
+//! ## 交接流程
+//! 1. 旧进程停止接受新连接，等待进行中的请求完成（最多 3 秒）
+//! 2. 将主播记录、欢迎语、已授权的观众会话和直播聊天室的近期消息写入基础路径下的
+//!    `upgrade-state.json`（权限 0600，含推流密钥）
+//! 3. 以相同的命令行参数启动 `LIVE_SERVER_UPGRADE_BINARY`（默认为当前程序），
+//!    监听套接字以继承的文件描述符传入
+//! 4. 新进程沿用监听地址相同的套接字（监听配置变化时重新绑定），导入状态后删除状态文件
+//! 5. 旧进程看到状态文件被删除后退出；新进程 30 秒内未完成启动或提前退出时，旧进程恢复服务
+//!
+//! ## 限制
+//! - 需启用 `hot-upgrade` 特性编译，仅 Unix 系统
+//! - 进程号会变化，进程管理器须能跟随新进程（如 systemd 需配合 `PIDFile`）
+//! - 聊天只交接直播聊天室最近的消息和发送者昵称，观众的聊天身份、举报和统计不交接（同热备复制）
+//! - 主播预览令牌、转推任务和 SSE 连接不交接，客户端需重新连接
+
+use crate::config::Listen;
+use crate::state::AppState;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+#[cfg(all(unix, feature = "hot-upgrade"))]
+use crate::{
+    config::LatencyMode,
+    domain::{ClientIp, SessionId},
+    state::{
+        replication::StateExport,
+        srs::{RoomNotice, StreamOverlay, StreamerRecord, StreamerState, StreamerStatus},
+    },
+};
+#[cfg(all(unix, feature = "hot-upgrade"))]
+use chrono::{DateTime, Utc};
+#[cfg(all(unix, feature = "hot-upgrade"))]
+use serde::{Deserialize, Serialize};
+#[cfg(all(unix, feature = "hot-upgrade"))]
+use std::collections::BTreeSet;
+#[cfg(all(unix, feature = "hot-upgrade"))]
+use std::path::{Path, PathBuf};
+
+/// 旧进程传入监听套接字的环境变量（逗号分隔的文件描述符）
+pub const FDS_ENV: &str = "LIVE_SERVER_UPGRADE_FDS";
+
+/// 旧进程传入状态文件路径的环境变量
+pub const STATE_ENV: &str = "LIVE_SERVER_UPGRADE_STATE";
+
+/// 升级前等待进行中的请求完成的最长时间（SSE 等长连接不会主动结束）
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 状态文件名（位于基础路径下）
+#[cfg(all(unix, feature = "hot-upgrade"))]
+const STATE_FILE: &str = "upgrade-state.json";
+
+/// 等待新进程完成启动的最长时间
+#[cfg(all(unix, feature = "hot-upgrade"))]
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 检查新进程状态的间隔
+#[cfg(all(unix, feature = "hot-upgrade"))]
+const READY_POLL: Duration = Duration::from_millis(100);
+
+/// 交接的主播记录（含推流密钥，只写入权限为 0600 的状态文件，不实现 `Debug`）
+#[cfg(all(unix, feature = "hot-upgrade"))]
+#[derive(Serialize, Deserialize)]
+struct HandoffStreamer {
+    ip: Option<ClientIp>,
+    secret: Option<String>,
+    session_id: Option<SessionId>,
+    push_session: Option<SessionId>,
+    app: Option<String>,
+    stream: Option<String>,
+    stream_uri: Option<String>,
+    stream_name: Option<String>,
+    stream_session_id: Option<String>,
+    live_since: Option<DateTime<Utc>>,
+    overlay: Option<String>,
+    variants: BTreeSet<String>,
+    recording: bool,
+    latency_mode: Option<String>,
+    client_id: Option<String>,
+    /// 交接时直播是否处于暂停状态
+    paused: bool,
+}
+
+#[cfg(all(unix, feature = "hot-upgrade"))]
+impl HandoffStreamer {
+    /// 读取正在进行的直播（未推流时返回 `None`）
+    fn capture(state: &StreamerState) -> Option<Self> {
+        use secrecy::ExposeSecret;
+
+        if !state.is_streaming() {
+            return None;
+        }
+        let record = &state.streamer;
+        Some(Self {
+            ip: record.ip.clone(),
+            secret: record.secret.as_ref().map(|s| s.expose_secret().to_string()),
+            session_id: record.session_id.clone(),
+            push_session: record.push_session.clone(),
+            app: record.app.clone(),
+            stream: record.stream.clone(),
+            stream_uri: record.stream_uri.clone(),
+            stream_name: record.stream_name.clone(),
+            stream_session_id: record.stream_session_id.clone(),
+            live_since: record.live_since,
+            overlay: record.overlay.map(|o| o.as_str().to_string()),
+            variants: record.variants.clone(),
+            recording: record.recording,
+            latency_mode: record.latency_mode.map(|m| m.as_str().to_string()),
+            client_id: record.client_id.clone(),
+            paused: record.status == StreamerStatus::Pausing,
+        })
+    }
+
+    /// 转为主播记录
+    fn into_record(self) -> StreamerRecord {
+        let mut record = StreamerRecord::new();
+        record.ip = self.ip;
+        record.secret = self.secret.map(secrecy::SecretString::from);
+        record.session_id = self.session_id;
+        record.push_session = self.push_session;
+        record.app = self.app;
+        record.stream = self.stream;
+        record.stream_uri = self.stream_uri;
+        record.stream_name = self.stream_name;
+        record.stream_session_id = self.stream_session_id;
+        record.live_since = self.live_since;
+        record.overlay = self.overlay.as_deref().and_then(StreamOverlay::parse);
+        record.variants = self.variants;
+        record.recording = self.recording;
+        record.latency_mode = self.latency_mode.as_deref().and_then(LatencyMode::parse);
+        record.client_id = self.client_id;
+        record.status = if self.paused { StreamerStatus::Pausing } else { StreamerStatus::Streaming };
+        record
+    }
+}
+
+/// 状态文件的内容
+#[cfg(all(unix, feature = "hot-upgrade"))]
+#[derive(Serialize, Deserialize)]
+struct Handoff {
+    /// 正在进行的直播（未推流时为 `None`）
+    streamer: Option<HandoffStreamer>,
+    /// 欢迎语和房间规则
+    notice: RoomNotice,
+    /// 观众会话与直播聊天室
+    state: StateExport,
+}
+
+/// 从旧进程继承的监听套接字
+#[derive(Default)]
+pub struct Inherited {
+    /// 尚未被取用的套接字（剩余的在丢弃时关闭）
+    listeners: Vec<std::net::TcpListener>,
+}
+
+impl Inherited {
+    /// 读取旧进程传入的监听套接字
+    ///
+    /// ### 返回值
+    /// 不是由升级启动（或未启用 `hot-upgrade` 特性）时为空
+    pub fn from_env() -> Self {
+        #[cfg(all(unix, feature = "hot-upgrade"))]
+        {
+            use std::os::fd::{FromRawFd, RawFd};
+
+            let Ok(fds) = std::env::var(FDS_ENV) else {
+                return Self::default();
+            };
+            let listeners = fds
+                .split(',')
+                .filter_map(|fd| fd.trim().parse::<RawFd>().ok())
+                .filter(|fd| *fd > 2)
+                // SAFETY: 文件描述符由旧进程在启动本进程前清除 close-on-exec 标志后传入，且只在此处取得所有权
+                .map(|fd| unsafe { std::net::TcpListener::from_raw_fd(fd) })
+                .collect();
+            Self { listeners }
+        }
+        #[cfg(not(all(unix, feature = "hot-upgrade")))]
+        {
+            Self::default()
+        }
+    }
+
+    /// 取出绑定在指定地址上的套接字
+    ///
+    /// ### 返回值
+    /// 没有继承该地址的套接字时返回 `None`，由调用方重新绑定
+    pub fn take(&mut self, addr: SocketAddr) -> io::Result<Option<tokio::net::TcpListener>> {
+        let Some(index) = self.listeners.iter().position(|l| l.local_addr().is_ok_and(|a| a == addr)) else {
+            return Ok(None);
+        };
+        let listener = self.listeners.swap_remove(index);
+        listener.set_nonblocking(true)?;
+        tokio::net::TcpListener::from_std(listener).map(Some)
+    }
+}
+
+/// 升级时交给新进程的监听套接字
+///
+/// 每个监听地址保留一份副本，HTTP 服务停止接受连接后套接字仍保持打开
+#[derive(Default)]
+pub struct Listeners {
+    /// 套接字副本及其监听配置
+    #[cfg_attr(not(all(unix, feature = "hot-upgrade")), allow(dead_code))]
+    sockets: Vec<(std::net::TcpListener, Listen)>,
+}
+
+impl Listeners {
+    /// 为各监听地址保留套接字副本（未启用 `hot-upgrade` 特性时不保留）
+    pub fn keep<'a>(servers: impl IntoIterator<Item = &'a (tokio::net::TcpListener, Listen)>) -> io::Result<Self> {
+        #[cfg(all(unix, feature = "hot-upgrade"))]
+        {
+            use std::os::fd::AsFd;
+
+            let sockets = servers
+                .into_iter()
+                .map(|(listener, listen)| {
+                    let fd = listener.as_fd().try_clone_to_owned()?;
+                    Ok((std::net::TcpListener::from(fd), *listen))
+                })
+                .collect::<io::Result<_>>()?;
+            Ok(Self { sockets })
+        }
+        #[cfg(not(all(unix, feature = "hot-upgrade")))]
+        {
+            let _ = servers.into_iter();
+            Ok(Self::default())
+        }
+    }
+
+    /// 重新取得监听套接字（升级失败后恢复服务）
+    pub fn reopen(&self) -> io::Result<Vec<(tokio::net::TcpListener, Listen)>> {
+        self.sockets
+            .iter()
+            .map(|(socket, listen)| {
+                let listener = socket.try_clone()?;
+                listener.set_nonblocking(true)?;
+                Ok((tokio::net::TcpListener::from_std(listener)?, *listen))
+            })
+            .collect()
+    }
+}
+
+/// 等待升级请求（SIGUSR2）
+///
+/// 未启用 `hot-upgrade` 特性或无法安装信号处理器时永不完成
+pub async fn requested() {
+    #[cfg(all(unix, feature = "hot-upgrade"))]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::user_defined2()) {
+            Ok(mut upgrade) => {
+                if upgrade.recv().await.is_some() {
+                    tracing::info!("收到 SIGUSR2，开始零停机升级");
+                    return;
+                }
+            }
+            Err(e) => tracing::warn!("无法安装 SIGUSR2 处理器，零停机升级不可用: {}", e),
+        }
+    }
+    std::future::pending::<()>().await
+}
+
+/// 导入旧进程交来的状态（应在绑定监听地址之后、开始提供服务之前调用）
+///
+/// 导入完成后删除状态文件，旧进程据此得知新进程已就绪
+pub fn import(state: &AppState) {
+    #[cfg(all(unix, feature = "hot-upgrade"))]
+    {
+        let Ok(path) = std::env::var(STATE_ENV) else {
+            return;
+        };
+        let handoff = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str::<Handoff>(&content).map_err(|e| e.to_string()));
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::warn!("删除升级状态文件 {} 失败: {}", path, e);
+        }
+        let handoff = match handoff {
+            Ok(handoff) => handoff,
+            Err(e) => {
+                tracing::error!("读取升级状态文件 {} 失败，以空状态启动: {}", path, e);
+                return;
+            }
+        };
+
+        let live = handoff.streamer.is_some();
+        {
+            let mut streamer = state.srs_db.streamer.write();
+            streamer.set_notice(handoff.notice);
+            if let Some(record) = handoff.streamer {
+                streamer.adopt_handoff(record.into_record());
+            }
+        }
+        let room = handoff.state.apply(&state.srs_db, &state.chat_db);
+        if live {
+            if let Some(room) = room {
+                state.chat_db.inner.write().activate(&room);
+            }
+        }
+        let clients = state.srs_db.clients.read().clients.values().map(|m| m.len()).sum::<usize>();
+        tracing::info!(
+            "已接管旧进程的状态: {}，观众会话 {} 个",
+            if live { "直播进行中" } else { "未在直播" },
+            clients
+        );
+    }
+    #[cfg(not(all(unix, feature = "hot-upgrade")))]
+    {
+        let _ = state;
+    }
+}
+
+/// 将监听套接字和运行状态交给新进程
+///
+/// 调用前 HTTP 服务须已停止接受连接
+///
+/// ### 返回值
+/// - `Ok(pid)`: 新进程已完成启动
+/// - `Err(msg)`: 写入状态、启动新进程失败或新进程未能完成启动，本进程应恢复服务
+pub async fn hand_over(state: &AppState, listeners: &Listeners) -> Result<u32, String> {
+    #[cfg(all(unix, feature = "hot-upgrade"))]
+    {
+        let config = state.config();
+        let handoff = {
+            let streamer = state.srs_db.streamer.read();
+            (HandoffStreamer::capture(&streamer), streamer.get_notice().clone())
+        };
+        let handoff = Handoff {
+            streamer: handoff.0,
+            notice: handoff.1,
+            state: StateExport::capture(&state.srs_db, &state.chat_db),
+        };
+        let path = config.base_path.join(STATE_FILE);
+        write_state(&path, &handoff)?;
+
+        let binary = match &config.upgrade_binary {
+            Some(binary) => binary.clone(),
+            None => current_binary()?,
+        };
+        let mut child = match spawn(&binary, &path, listeners) {
+            Ok(child) => child,
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                return Err(format!("启动新进程 {} 失败: {}", binary.display(), e));
+            }
+        };
+        let pid = child.id();
+        tracing::info!("已启动新进程 {}（{}），等待其接管服务", pid, binary.display());
+
+        let started = std::time::Instant::now();
+        loop {
+            tokio::time::sleep(READY_POLL).await;
+            if !path.exists() {
+                return Ok(pid);
+            }
+            let failure = match child.try_wait() {
+                Ok(Some(status)) => Some(format!("新进程 {} 启动时退出（{}）", pid, status)),
+                Ok(None) if started.elapsed() >= READY_TIMEOUT => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    Some(format!("新进程 {} 未在 {} 秒内完成启动，已终止", pid, READY_TIMEOUT.as_secs()))
+                }
+                Ok(None) => None,
+                Err(e) => Some(format!("检查新进程 {} 状态失败: {}", pid, e)),
+            };
+            if let Some(failure) = failure {
+                let _ = std::fs::remove_file(&path);
+                return Err(failure);
+            }
+        }
+    }
+    #[cfg(not(all(unix, feature = "hot-upgrade")))]
+    {
+        let _ = (state, listeners);
+        Err("编译时未启用 hot-upgrade 特性".to_string())
+    }
+}
+
+/// 写入状态文件（权限 0600）
+#[cfg(all(unix, feature = "hot-upgrade"))]
+fn write_state(path: &Path, handoff: &Handoff) -> Result<(), String> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let content = serde_json::to_vec(handoff).map_err(|e| format!("序列化升级状态失败: {}", e))?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(&content))
+        .map_err(|e| format!("写入升级状态文件 {} 失败: {}", path.display(), e))
+}
+
+/// 当前程序的路径
+///
+/// 程序文件已被新版本替换时，Linux 上读到的路径带有 ` (deleted)` 后缀，去掉后缀即为新版本的路径
+#[cfg(all(unix, feature = "hot-upgrade"))]
+fn current_binary() -> Result<PathBuf, String> {
+    let path = std::env::current_exe().map_err(|e| format!("无法确定当前程序的路径: {}", e))?;
+    Ok(match path.to_str().and_then(|p| p.strip_suffix(" (deleted)")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => path,
+    })
+}
+
+/// 启动新进程，监听套接字以继承的文件描述符传入
+#[cfg(all(unix, feature = "hot-upgrade"))]
+fn spawn(binary: &Path, state_path: &Path, listeners: &Listeners) -> io::Result<std::process::Child> {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    let fds: Vec<i32> = listeners.sockets.iter().map(|(socket, _)| socket.as_raw_fd()).collect();
+    let fd_list = fds.iter().map(i32::to_string).collect::<Vec<_>>().join(",");
+    let mut command = std::process::Command::new(binary);
+    command
+        .args(std::env::args_os().skip(1))
+        .env(FDS_ENV, fd_list)
+        .env(STATE_ENV, state_path);
+    // SAFETY: 子进程 exec 之前只调用异步信号安全的 fcntl
+    unsafe {
+        command.pre_exec(move || {
+            for &fd in &fds {
+                if libc::fcntl(fd, libc::F_SETFD, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    command.spawn()
+}